@@ -5,6 +5,7 @@ use ethrex::{
 };
 use ethrex_blockchain::{BlockchainOptions, BlockchainType};
 use ethrex_config::networks::Network;
+use ethrex_storage::StoreOptions;
 
 #[inline]
 fn block_import() {
@@ -27,6 +28,7 @@ fn block_import() {
             r#type: blockchain_type,
             ..Default::default()
         },
+        StoreOptions::default(),
     ))
     .expect("Failed to import blocks on the Tokio runtime");
 }
@@ -7,14 +7,31 @@ use std::{
 
 use clap::{ArgAction, Parser as ClapParser, Subcommand as ClapSubcommand};
 use ethrex_blockchain::{BlockchainOptions, BlockchainType, error::ChainError};
-use ethrex_common::types::{Block, Genesis, fee_config::FeeConfig};
+use ethrex_common::{
+    Address, H256, U256,
+    constants::EMPTY_TRIE_HASH,
+    types::{AccountState, Block, Genesis, fee_config::FeeConfig},
+};
 use ethrex_p2p::{
     discv4::peer_table::TARGET_PEERS, sync::SyncMode, tx_broadcaster::BROADCAST_INTERVAL_MS,
     types::Node,
 };
-use ethrex_rlp::encode::RLPEncode;
-use ethrex_storage::error::StoreError;
+use ethrex_rlp::{decode::RLPDecode, encode::RLPEncode};
+use ethrex_rpc::{
+    EthClient,
+    debug::{
+        execution_witness::RpcExecutionWitness,
+        verify_witness::{decode_raw_block, verify_execution_witness},
+    },
+    http_config::HttpConfig,
+    utils::RpcNamespace,
+};
+use ethrex_storage::{CheckpointManifest, Store, StoreOptions, TrieScheme, error::StoreError};
+use ethrex_storage_rollup::ROLLUP_STORE_FILE;
+use secp256k1::PublicKey;
+use serde::Serialize;
 use tracing::{Level, info, warn};
+use url::Url;
 
 use crate::{
     initializers::{get_network, init_blockchain, init_store, init_tracing, load_store},
@@ -117,6 +134,64 @@ pub struct Options {
         help_heading = "Node options"
     )]
     pub mempool_max_size: usize,
+    #[arg(
+        long = "tx-index.by-address",
+        action = ArgAction::SetTrue,
+        help = "Index every canonical transaction by its sender and recipient addresses",
+        long_help = "Keeps an index of the transactions sent or received by each address, queryable through the `ethrex_getTransactionsByAddress` RPC method. Only blocks stored while the flag is set are indexed.",
+        help_heading = "Node options"
+    )]
+    pub index_transactions_by_address: bool,
+    #[arg(
+        long = "db.preimages",
+        action = ArgAction::SetTrue,
+        help = "Store the preimages of the hashed addresses and storage keys",
+        long_help = "Keeps the address and storage key behind each hash written to the state tries by imported blocks, so `debug_getModifiedAccountsByNumber`, `debug_getModifiedAccountsByHash`, `debug_storageRangeAt`, `debug_accountRange` and the `state-diff` command can report them. Only blocks stored while the flag is set have their preimages stored.",
+        help_heading = "Node options"
+    )]
+    pub store_preimages: bool,
+    #[arg(
+        long = "db.trie-scheme",
+        value_name = "TRIE_SCHEME",
+        value_parser = utils::parse_trie_scheme,
+        help = "How trie nodes are stored in a new database",
+        long_help = "Can be either \"hash\" or \"path\". The path scheme keys nodes by their position in the trie and only keeps the state of the last 128 blocks, using much less disk space. Only used when creating the database, existing ones keep their scheme; use the `migrations hash2path` tool to convert them. Snap sync is not supported with the path scheme, the node refuses to start unless `--syncmode full` is given.",
+        help_heading = "Node options"
+    )]
+    pub trie_scheme: Option<TrieScheme>,
+    #[arg(
+        long = "execution.parallel",
+        action = ArgAction::SetTrue,
+        help = "Execute the transactions of each imported block in parallel (experimental)",
+        long_help = "Executes the transactions of each imported block optimistically in parallel, re-executing those that conflict with previous ones. Experimental, by default they are executed one after the other.",
+        help_heading = "Node options"
+    )]
+    pub parallel_execution: bool,
+    #[arg(
+        long = "readonly",
+        action = ArgAction::SetTrue,
+        requires = "readonly_secondary_path",
+        help = "Serve RPC requests from the database of another running node",
+        long_help = "Opens the database in the datadir as a read-only secondary instance of the node writing to it, periodically catching up with its writes. Only the eth and debug namespaces are served over http and IPC, without p2p networking nor engine API. IPC is only served if `--ipc.path` is given, so the primary's socket is left untouched. The http and metrics ports must differ from the primary's, the node refuses to start if they are taken. Signing methods are not served.",
+        help_heading = "Read-only options"
+    )]
+    pub readonly: bool,
+    #[arg(
+        long = "readonly.secondary-path",
+        value_name = "SECONDARY_DIRECTORY",
+        help = "Directory where the read-only instance keeps its own database logs",
+        long_help = "Required by `--readonly`. Must not be shared with other instances.",
+        help_heading = "Read-only options"
+    )]
+    pub readonly_secondary_path: Option<PathBuf>,
+    #[arg(
+        long = "readonly.catch-up-interval",
+        default_value_t = 1000,
+        value_name = "MILLISECONDS",
+        help = "How often the read-only instance catches up with the writes of the primary",
+        help_heading = "Read-only options"
+    )]
+    pub readonly_catch_up_interval: u64,
     #[arg(
         long = "http.addr",
         default_value = "0.0.0.0",
@@ -135,6 +210,70 @@ pub struct Options {
         env = "ETHREX_HTTP_PORT"
     )]
     pub http_port: String,
+    #[arg(
+        long = "http.api",
+        value_name = "NAMESPACES",
+        value_delimiter = ',',
+        num_args = 1..,
+        value_parser = utils::parse_rpc_namespace,
+        help = "Comma separated rpc namespaces served by the http and IPC rpc servers.",
+        long_help = "Comma separated rpc namespaces (eth, net, web3, debug, admin, txpool, ethrex) served by the http and IPC rpc servers. All of them are served if not set.",
+        help_heading = "RPC options"
+    )]
+    pub http_api: Vec<RpcNamespace>,
+    #[arg(
+        long = "http.deny-methods",
+        value_name = "METHODS",
+        value_delimiter = ',',
+        num_args = 1..,
+        help = "Comma separated rpc methods rejected by the http and IPC rpc servers.",
+        help_heading = "RPC options"
+    )]
+    pub http_deny_methods: Vec<String>,
+    #[arg(
+        long = "http.rate-limit",
+        value_name = "REQUESTS_PER_SECOND",
+        help = "Max amount of requests per second the http rpc server accepts from a single IP.",
+        long_help = "Max amount of requests per second the http rpc server accepts from a single IP. Each request of a batch counts separately. Unlimited if not set.",
+        help_heading = "RPC options"
+    )]
+    pub http_rate_limit: Option<u32>,
+    #[arg(
+        long = "http.batch-limit",
+        value_name = "REQUESTS",
+        help = "Max amount of requests in a single batch sent to the http rpc server. Unlimited if not set.",
+        help_heading = "RPC options"
+    )]
+    pub http_batch_limit: Option<usize>,
+    #[arg(
+        long = "http.max-response-size",
+        value_name = "BYTES",
+        help = "Max size in bytes of the responses of the http rpc server, batches included.",
+        long_help = "Max size in bytes of the responses of the http rpc server. Requests of a batch whose responses go over it are answered with an error. Unlimited if not set.",
+        help_heading = "RPC options"
+    )]
+    pub http_max_response_size: Option<usize>,
+    #[arg(
+        long = "http.corsdomain",
+        default_value = "*",
+        value_name = "ORIGINS",
+        value_delimiter = ',',
+        num_args = 1..,
+        help = "Comma separated origins allowed to make cross-origin requests to the http rpc server, `*` allows any.",
+        help_heading = "RPC options"
+    )]
+    pub http_cors_domains: Vec<String>,
+    #[arg(
+        long = "http.vhosts",
+        default_value = "*",
+        value_name = "HOSTNAMES",
+        value_delimiter = ',',
+        num_args = 1..,
+        help = "Comma separated hostnames accepted in the Host header of http rpc requests, `*` accepts any.",
+        long_help = "Comma separated hostnames accepted in the Host header of http rpc requests, `*` accepts any. Requests addressed to an IP are always accepted.",
+        help_heading = "RPC options"
+    )]
+    pub http_vhosts: Vec<String>,
     #[arg(
         long = "authrpc.addr",
         default_value = "127.0.0.1",
@@ -159,6 +298,84 @@ pub struct Options {
         help_heading = "RPC options"
     )]
     pub authrpc_jwtsecret: String,
+    #[arg(
+        long = "ipc.path",
+        value_name = "IPC_PATH",
+        help = "Path of the IPC socket.",
+        long_help = "Path of the Unix domain socket serving the RPC API. Defaults to `ethrex.ipc` inside the data directory.",
+        help_heading = "RPC options"
+    )]
+    pub ipc_path: Option<PathBuf>,
+    #[arg(
+        long = "ipc.disable",
+        action = ArgAction::SetTrue,
+        help = "Disable the IPC RPC server.",
+        help_heading = "RPC options"
+    )]
+    pub ipc_disable: bool,
+    #[arg(
+        long = "rpc.checkpoint-dir",
+        value_name = "CHECKPOINT_DIRECTORY",
+        help = "Directory where admin_createCheckpoint creates database checkpoints.",
+        long_help = "admin_createCheckpoint only takes the name of the checkpoint, which is created inside this directory. The method is disabled if it isn't set, and it's only served over http when listening on a loopback address.",
+        help_heading = "RPC options"
+    )]
+    pub checkpoint_dir: Option<PathBuf>,
+    #[arg(
+        long = "rpc.bad-block-traces-dir",
+        value_name = "TRACES_DIRECTORY",
+        help = "Directory where debug_standardTraceBadBlockToFile writes the traces.",
+        long_help = "Defaults to a bad_block_traces directory within the system's temporary directory. debug_standardTraceBadBlockToFile is only served over http when listening on a loopback address.",
+        help_heading = "RPC options"
+    )]
+    pub bad_block_traces_dir: Option<PathBuf>,
+    #[arg(
+        long = "rpc.keystore",
+        value_name = "KEYSTORE_PATH",
+        value_delimiter = ',',
+        num_args = 1..,
+        help = "Comma separated keystore files or directories with the accounts to be managed by the node.",
+        long_help = "Accounts are Web3 Secret Storage (keystore V3) JSON files. Managed accounts can be used through the eth_accounts, eth_sendTransaction, eth_signTransaction, eth_sign and eth_signTypedData_v4 rpc methods.",
+        requires = "keystore_password_file",
+        help_heading = "RPC options"
+    )]
+    pub keystore: Vec<PathBuf>,
+    #[arg(
+        long = "rpc.keystore-password-file",
+        value_name = "PASSWORD_FILE_PATH",
+        help = "File containing the password to decrypt the keystore accounts.",
+        requires = "keystore",
+        help_heading = "RPC options"
+    )]
+    pub keystore_password_file: Option<PathBuf>,
+    #[arg(
+        long = "rpc.remote-signer-url",
+        value_name = "URL",
+        help = "URL of a Web3Signer-compatible server used to sign for the managed remote accounts.",
+        requires = "remote_signer_public_keys",
+        help_heading = "RPC options"
+    )]
+    pub remote_signer_url: Option<Url>,
+    #[arg(
+        long = "rpc.remote-signer-public-keys",
+        value_name = "PUBLIC_KEYS",
+        value_parser = utils::parse_public_key,
+        value_delimiter = ',',
+        num_args = 1..,
+        help = "Comma separated public keys of the accounts managed by the remote signer.",
+        requires = "remote_signer_url",
+        help_heading = "RPC options"
+    )]
+    pub remote_signer_public_keys: Vec<PublicKey>,
+    #[arg(
+        long = "rpc.allow-insecure-signing",
+        visible_alias = "allow-insecure-signing",
+        action = ArgAction::SetTrue,
+        help = "Allow serving the signing endpoints of the managed accounts over a non-loopback HTTP address.",
+        long_help = "By default the node refuses to start when accounts are managed through --rpc.keystore or --rpc.remote-signer-url and the HTTP server is not bound to a loopback address, as anyone able to reach it could sign with the managed accounts.",
+        help_heading = "RPC options"
+    )]
+    pub allow_insecure_signing: bool,
     #[arg(long = "p2p.enabled", default_value = "true", value_name = "P2P_ENABLED", action = ArgAction::SetTrue, help_heading = "P2P options")]
     pub p2p_enabled: bool,
     #[arg(
@@ -223,6 +440,26 @@ impl Options {
         }
     }
 
+    pub fn http_config(&self) -> HttpConfig {
+        HttpConfig {
+            namespaces: (!self.http_api.is_empty()).then(|| self.http_api.clone()),
+            denied_methods: self.http_deny_methods.iter().cloned().collect(),
+            rate_limit: self.http_rate_limit,
+            max_batch_size: self.http_batch_limit,
+            max_response_size: self.http_max_response_size,
+            cors_origins: self.http_cors_domains.clone(),
+            vhosts: self.http_vhosts.clone(),
+        }
+    }
+
+    pub fn store_options(&self) -> StoreOptions {
+        StoreOptions {
+            index_transactions_by_address: self.index_transactions_by_address,
+            store_preimages: self.store_preimages,
+            trie_scheme: self.trie_scheme,
+        }
+    }
+
     pub fn default_l2() -> Self {
         Self {
             network: Some(Network::LocalDevnetL2),
@@ -249,10 +486,26 @@ impl Default for Options {
         Self {
             http_addr: Default::default(),
             http_port: Default::default(),
+            http_api: Default::default(),
+            http_deny_methods: Default::default(),
+            http_rate_limit: Default::default(),
+            http_batch_limit: Default::default(),
+            http_max_response_size: Default::default(),
+            http_cors_domains: vec!["*".to_string()],
+            http_vhosts: vec!["*".to_string()],
             log_level: Level::INFO,
             authrpc_addr: Default::default(),
             authrpc_port: Default::default(),
             authrpc_jwtsecret: Default::default(),
+            ipc_path: Default::default(),
+            ipc_disable: Default::default(),
+            checkpoint_dir: Default::default(),
+            bad_block_traces_dir: Default::default(),
+            keystore: Default::default(),
+            keystore_password_file: Default::default(),
+            remote_signer_url: Default::default(),
+            remote_signer_public_keys: Default::default(),
+            allow_insecure_signing: false,
             p2p_enabled: Default::default(),
             p2p_port: Default::default(),
             discovery_port: Default::default(),
@@ -266,6 +519,13 @@ impl Default for Options {
             dev: Default::default(),
             force: false,
             mempool_max_size: Default::default(),
+            index_transactions_by_address: false,
+            store_preimages: false,
+            trie_scheme: None,
+            parallel_execution: false,
+            readonly: false,
+            readonly_secondary_path: None,
+            readonly_catch_up_interval: 1000,
             tx_broadcasting_time_interval: Default::default(),
             target_peers: Default::default(),
             extra_data: get_minimal_client_version(),
@@ -333,10 +593,80 @@ pub enum Subcommand {
         )]
         genesis_path: PathBuf,
     },
+    #[command(
+        name = "state-diff",
+        about = "Write the accounts and storage slots that changed between the states of two blocks as json"
+    )]
+    StateDiff {
+        #[arg(
+            long = "from",
+            value_name = "NUMBER",
+            help = "Block whose state is the base of the diff"
+        )]
+        from: u64,
+        #[arg(
+            long = "to",
+            value_name = "NUMBER",
+            help = "Block whose state is compared against the base"
+        )]
+        to: u64,
+        #[arg(
+            long = "output",
+            value_name = "FILE_PATH",
+            help = "File where the diff will be written to, printed to stdout if not set"
+        )]
+        output: Option<PathBuf>,
+        #[arg(long = "no-storage", help = "Only include account changes, skipping their storage", action = ArgAction::SetTrue)]
+        no_storage: bool,
+    },
+    #[command(
+        name = "verify-witness",
+        about = "Validate a block statelessly using an execution witness"
+    )]
+    VerifyWitness {
+        #[arg(
+            long = "block",
+            value_name = "FILE_PATH",
+            help = "Path to the RLP-encoded block, either as raw bytes or as the hex string returned by `debug_getRawBlock`"
+        )]
+        block: PathBuf,
+        #[arg(
+            long = "witness",
+            value_name = "FILE_PATH",
+            help = "Path to the json execution witness of the block, as returned by `debug_executionWitness`"
+        )]
+        witness: PathBuf,
+    },
+    #[command(name = "db", subcommand, about = "Database maintenance commands")]
+    Db(DbSubcommand),
     #[command(name = "l2")]
     L2(l2::L2Command),
 }
 
+#[derive(ClapSubcommand)]
+pub enum DbSubcommand {
+    #[command(
+        name = "checkpoint",
+        about = "Create a consistent copy of the database, including the rollup store if present"
+    )]
+    Checkpoint {
+        #[arg(
+            required = true,
+            value_name = "CHECKPOINT_DIRECTORY",
+            help = "Directory where the checkpoint is created, it must not exist",
+            long_help = "Directory where the checkpoint is created, it must not exist. Files are hard-linked when it's in the same filesystem as the datadir. The checkpoint can be restored by using it as the datadir of a node, its manifest is validated on start."
+        )]
+        path: PathBuf,
+        #[arg(
+            long = "rpc.url",
+            value_name = "URL",
+            help = "Ask a running node to create the checkpoint through its admin RPC",
+            long_help = "The checkpoint is created by the node serving the `admin` namespace at this URL, which must listen on a loopback address. The path is then the name of the checkpoint inside the node's `--rpc.checkpoint-dir`. If not set, the database in the datadir is opened directly, which requires the node to be stopped."
+        )]
+        rpc_url: Option<Url>,
+    },
+}
+
 impl Subcommand {
     pub async fn run(self, opts: &Options) -> eyre::Result<()> {
         // L2 has its own init_tracing because of the ethrex monitor
@@ -369,8 +699,10 @@ impl Subcommand {
                     BlockchainOptions {
                         max_mempool_size: opts.mempool_max_size,
                         r#type: blockchain_type,
+                        parallel_execution: opts.parallel_execution,
                         ..Default::default()
                     },
+                    opts.store_options(),
                 )
                 .await?;
             }
@@ -382,6 +714,27 @@ impl Subcommand {
                 let state_root = genesis.compute_state_root();
                 println!("{state_root:#x}");
             }
+            Subcommand::StateDiff {
+                from,
+                to,
+                output,
+                no_storage,
+            } => state_diff(&opts.datadir, from, to, output, no_storage).await?,
+            Subcommand::VerifyWitness { block, witness } => verify_witness(opts, &block, &witness)?,
+            Subcommand::Db(DbSubcommand::Checkpoint { path, rpc_url }) => {
+                let manifest = match rpc_url {
+                    Some(url) => {
+                        let name = path
+                            .to_str()
+                            .ok_or_else(|| eyre::eyre!("Checkpoint name must be valid UTF-8"))?;
+                        EthClient::new(url.as_str())?
+                            .create_checkpoint(name)
+                            .await?
+                    }
+                    None => create_checkpoint(opts, &path).await?,
+                };
+                println!("{}", serde_json::to_string_pretty(&manifest)?);
+            }
             Subcommand::L2(command) => command.run().await?,
         }
 
@@ -389,6 +742,49 @@ impl Subcommand {
     }
 }
 
+/// Executes a block using only the state in its execution witness, failing if the witness or the
+/// block's header don't match the execution
+pub fn verify_witness(opts: &Options, block_path: &Path, witness_path: &Path) -> eyre::Result<()> {
+    let chain_config = get_network(opts).get_genesis()?.config;
+
+    let raw_block = std::fs::read(block_path)?;
+    let block = match std::str::from_utf8(&raw_block) {
+        Ok(hex_block) if hex_block.trim().starts_with("0x") => decode_raw_block(hex_block)?,
+        _ => Block::decode(&raw_block)?,
+    };
+
+    // Accept both the witness and the whole `debug_executionWitness` response
+    let mut witness: serde_json::Value = serde_json::from_reader(File::open(witness_path)?)?;
+    if let Some(result) = witness.get_mut("result") {
+        witness = result.take();
+    }
+    let witness: RpcExecutionWitness = serde_json::from_value(witness)?;
+
+    let verification = verify_execution_witness(block, witness, chain_config);
+    println!("{}", serde_json::to_string_pretty(&verification)?);
+    if let Some(error) = verification.error {
+        eyre::bail!(
+            "Witness verification failed for block {}: {error}",
+            verification.block_number
+        );
+    }
+    Ok(())
+}
+
+/// Creates a checkpoint of the database in the datadir, which can't be in use by a running node
+pub async fn create_checkpoint(opts: &Options, path: &Path) -> eyre::Result<CheckpointManifest> {
+    let genesis = get_network(opts).get_genesis()?;
+    let store = init_store(&opts.datadir, genesis, opts.store_options()).await;
+    let rollup_store_path = opts.datadir.join(ROLLUP_STORE_FILE);
+    let manifest = if rollup_store_path.exists() {
+        let rollup_store = l2::init_rollup_store(&rollup_store_path).await;
+        rollup_store.create_checkpoint(&store, path).await?
+    } else {
+        store.create_checkpoint(path).await?
+    };
+    Ok(manifest)
+}
+
 pub fn remove_db(datadir: &Path, force: bool) {
     init_datadir(datadir);
 
@@ -420,10 +816,11 @@ pub async fn import_blocks(
     datadir: &Path,
     genesis: Genesis,
     blockchain_opts: BlockchainOptions,
+    store_opts: StoreOptions,
 ) -> Result<(), ChainError> {
     let start_time = Instant::now();
     init_datadir(datadir);
-    let store = init_store(datadir, genesis).await;
+    let store = init_store(datadir, genesis, store_opts).await;
     let blockchain = init_blockchain(store.clone(), blockchain_opts);
     let path_metadata = metadata(path).expect("Failed to read path");
 
@@ -576,3 +973,263 @@ pub async fn export_blocks(
     }
     info!(blocks = end.saturating_sub(start), path = %path, "Exported blocks to file");
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StateDiff {
+    from: u64,
+    to: u64,
+    from_state_root: H256,
+    to_state_root: H256,
+    accounts: Vec<AccountDiff>,
+}
+
+/// Accounts and storage slots are identified by their hashed address and key, along with the
+/// address and key themselves when their preimages are stored (see `--db.preimages`)
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountDiff {
+    hashed_address: H256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<Address>,
+    old: Option<AccountState>,
+    new: Option<AccountState>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    storage: Vec<StorageSlotDiff>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StorageSlotDiff {
+    hashed_key: H256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<H256>,
+    old: Option<U256>,
+    new: Option<U256>,
+}
+
+/// Writes the diff between the states of two blocks of the database at `datadir`, which is opened
+/// read-only so that it can't be modified by mistake
+pub async fn state_diff(
+    datadir: &Path,
+    from: u64,
+    to: u64,
+    output: Option<PathBuf>,
+    no_storage: bool,
+) -> eyre::Result<()> {
+    let store = Store::new_read_only(datadir)?;
+    let diff = compute_state_diff(&store, from, to, no_storage)?;
+    match output {
+        Some(path) => serde_json::to_writer_pretty(File::create(path)?, &diff)?,
+        None => serde_json::to_writer_pretty(io::stdout().lock(), &diff)?,
+    }
+    Ok(())
+}
+
+fn compute_state_diff(
+    store: &Store,
+    from: u64,
+    to: u64,
+    no_storage: bool,
+) -> eyre::Result<StateDiff> {
+    let state_root = |number: u64| -> eyre::Result<H256> {
+        let header = store
+            .get_block_header(number)?
+            .ok_or_else(|| eyre::eyre!("Block {number} not found"))?;
+        if !store.contains_state_node(header.state_root)? {
+            eyre::bail!("State for block {number} is not available");
+        }
+        Ok(header.state_root)
+    };
+    let from_state_root = state_root(from)?;
+    let to_state_root = state_root(to)?;
+
+    let mut accounts = Vec::new();
+    for change in store.iter_account_changes(from_state_root, to_state_root)? {
+        let (hashed_address, old, new) = change?;
+        let storage_root = |account: &Option<AccountState>| {
+            account
+                .as_ref()
+                .map_or(*EMPTY_TRIE_HASH, |a| a.storage_root)
+        };
+        let mut storage = Vec::new();
+        if !no_storage {
+            for change in store.iter_storage_changes(
+                hashed_address,
+                storage_root(&old),
+                storage_root(&new),
+            )? {
+                let (hashed_key, old, new) = change?;
+                storage.push(StorageSlotDiff {
+                    hashed_key,
+                    key: store.get_storage_key_preimage(hashed_key)?,
+                    old,
+                    new,
+                });
+            }
+        }
+        accounts.push(AccountDiff {
+            hashed_address,
+            address: store.get_address_preimage(hashed_address)?,
+            old,
+            new,
+            storage,
+        });
+    }
+    info!(from, to, accounts = accounts.len(), "Computed state diff");
+
+    Ok(StateDiff {
+        from,
+        to,
+        from_state_root,
+        to_state_root,
+        accounts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bytes::Bytes;
+    use ethrex_common::types::{
+        AccountInfo, AccountUpdate, BlockBody, BlockHeader, GenesisAccount, code_hash,
+    };
+    use ethrex_storage::{EngineType, UpdateBatch};
+
+    use super::*;
+
+    const CONTRACT: u64 = 0xc0ffee;
+    const RECIPIENT: u64 = 0xbeef;
+
+    /// Stores a genesis with a contract holding a single storage slot, and a block on top of it
+    /// which writes another slot of the contract and funds a new account
+    async fn setup_store(path: &Path) {
+        let store = Store::new_with_options(
+            path,
+            EngineType::RocksDB,
+            StoreOptions {
+                store_preimages: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut genesis: Genesis =
+            serde_json::from_str(include_str!("../../fixtures/genesis/l1.json")).unwrap();
+        genesis.alloc.insert(
+            Address::from_low_u64_be(CONTRACT),
+            GenesisAccount {
+                code: Bytes::from_static(&[0x00]),
+                storage: BTreeMap::from([(U256::from(1), U256::from(10))]),
+                balance: U256::zero(),
+                nonce: 1,
+            },
+        );
+        store.add_initial_state(genesis).await.unwrap();
+        let genesis_header = store.get_block_header(0).unwrap().unwrap();
+
+        let mut contract = AccountUpdate::new(Address::from_low_u64_be(CONTRACT));
+        contract
+            .added_storage
+            .insert(H256::from_low_u64_be(2), U256::from(20));
+        let mut recipient = AccountUpdate::new(Address::from_low_u64_be(RECIPIENT));
+        recipient.info = Some(AccountInfo {
+            code_hash: code_hash(&Bytes::new()),
+            balance: U256::from(5),
+            nonce: 0,
+        });
+        let updates = store
+            .apply_account_updates_batch(genesis_header.hash(), &[contract, recipient])
+            .await
+            .unwrap()
+            .unwrap();
+
+        let header = BlockHeader {
+            number: 1,
+            parent_hash: genesis_header.hash(),
+            state_root: updates.state_trie_hash,
+            ..Default::default()
+        };
+        let block = Block::new(header, BlockBody::default());
+        let block_hash = block.hash();
+        store
+            .store_block_updates(UpdateBatch {
+                account_updates: updates.state_updates,
+                storage_updates: updates.storage_updates,
+                blocks: vec![block],
+                receipts: Vec::new(),
+                senders: Vec::new(),
+                code_updates: updates.code_updates,
+                preimages: updates.preimages,
+            })
+            .await
+            .unwrap();
+        store
+            .forkchoice_update(None, 1, block_hash, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn state_diff_identifies_accounts_and_slots_by_their_preimages() {
+        let datadir = tempfile::tempdir().unwrap();
+        setup_store(datadir.path()).await;
+        let output = datadir.path().join("diff.json");
+        state_diff(datadir.path(), 0, 1, Some(output.clone()), false)
+            .await
+            .unwrap();
+
+        let diff: serde_json::Value =
+            serde_json::from_reader(File::open(&output).unwrap()).unwrap();
+        assert_eq!(diff["from"], 0);
+        assert_eq!(diff["to"], 1);
+        let accounts = diff["accounts"].as_array().unwrap();
+        assert_eq!(accounts.len(), 2);
+        let account = |address: u64| {
+            let address = serde_json::json!(Address::from_low_u64_be(address));
+            accounts
+                .iter()
+                .find(|account| account["address"] == address)
+                .unwrap()
+        };
+
+        let recipient = account(RECIPIENT);
+        assert!(recipient["old"].is_null());
+        assert_eq!(
+            recipient["new"]["balance"],
+            serde_json::json!(U256::from(5))
+        );
+        assert!(recipient.get("storage").is_none());
+
+        // Only the slot written by the block is part of the diff
+        let storage = account(CONTRACT)["storage"].as_array().unwrap();
+        assert_eq!(storage.len(), 1);
+        assert_eq!(
+            storage[0]["key"],
+            serde_json::json!(H256::from_low_u64_be(2))
+        );
+        assert!(storage[0]["old"].is_null());
+        assert_eq!(storage[0]["new"], serde_json::json!(U256::from(20)));
+
+        // The storage can be left out
+        state_diff(datadir.path(), 0, 1, Some(output.clone()), true)
+            .await
+            .unwrap();
+        let diff: serde_json::Value =
+            serde_json::from_reader(File::open(&output).unwrap()).unwrap();
+        assert!(
+            diff["accounts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|account| account.get("storage").is_none())
+        );
+    }
+
+    #[tokio::test]
+    async fn state_diff_fails_for_missing_blocks() {
+        let datadir = tempfile::tempdir().unwrap();
+        setup_store(datadir.path()).await;
+        assert!(state_diff(datadir.path(), 0, 2, None, false).await.is_err());
+    }
+}
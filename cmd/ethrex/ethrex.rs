@@ -1,7 +1,7 @@
 use clap::Parser;
 use ethrex::{
     cli::CLI,
-    initializers::{init_l1, init_tracing},
+    initializers::{init_l1, init_readonly, init_tracing},
     utils::{NodeConfigFile, get_client_version, store_node_config_file},
 };
 use ethrex_p2p::{discv4::peer_table::PeerTable, types::NodeRecord};
@@ -60,6 +60,18 @@ async fn main() -> eyre::Result<()> {
 
     info!("ethrex version: {}", get_client_version());
 
+    if opts.readonly {
+        let cancel_token = init_readonly(opts, Some(log_filter_handler)).await?;
+        let mut signal_terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = signal_terminate.recv() => {}
+        }
+        info!("Server shutting down!");
+        cancel_token.cancel();
+        return Ok(());
+    }
+
     let (datadir, cancel_token, peer_table, local_node_record) =
         init_l1(opts, Some(log_filter_handler)).await?;
 
@@ -21,7 +21,11 @@ use ethrex_p2p::{
     types::{Node, NodeRecord},
     utils::public_key_from_signing_key,
 };
-use ethrex_storage::{EngineType, Store};
+use ethrex_rpc::{
+    signer::{LocalSigner, RemoteSigner, Signer},
+    utils::RpcNamespace,
+};
+use ethrex_storage::{EngineType, Store, StoreOptions, TrieScheme};
 use local_ip_address::{local_ip, local_ipv6};
 use rand::rngs::OsRng;
 use secp256k1::SecretKey;
@@ -33,7 +37,7 @@ use std::{
     net::SocketAddr,
     path::{Path, PathBuf},
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::sync::Mutex;
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
@@ -92,12 +96,20 @@ pub fn init_metrics(opts: &Options, tracker: TaskTracker) {
 }
 
 /// Opens a new or pre-existing Store and loads the initial state provided by the network
-pub async fn init_store(datadir: impl AsRef<Path>, genesis: Genesis) -> Store {
-    let store = open_store(datadir.as_ref());
+pub async fn init_store(
+    datadir: impl AsRef<Path>,
+    genesis: Genesis,
+    store_opts: StoreOptions,
+) -> Store {
+    let store = open_store_with_options(datadir.as_ref(), store_opts);
     store
         .add_initial_state(genesis)
         .await
         .expect("Failed to create genesis block");
+    // Databases restored from a checkpoint must still match its manifest
+    store
+        .validate_checkpoint_manifest(datadir.as_ref())
+        .expect("Database doesn't match its checkpoint manifest");
     store
 }
 
@@ -113,14 +125,20 @@ pub async fn load_store(datadir: &Path) -> Store {
 
 /// Opens a pre-existing Store or creates a new one
 pub fn open_store(datadir: &Path) -> Store {
+    open_store_with_options(datadir, StoreOptions::default())
+}
+
+/// Opens a pre-existing Store or creates a new one with the given optional features
+pub fn open_store_with_options(datadir: &Path, store_opts: StoreOptions) -> Store {
     if datadir.ends_with("memory") {
-        Store::new(datadir, EngineType::InMemory).expect("Failed to create Store")
+        Store::new_with_options(datadir, EngineType::InMemory, store_opts)
+            .expect("Failed to create Store")
     } else {
         #[cfg(feature = "rocksdb")]
         let engine_type = EngineType::RocksDB;
         #[cfg(feature = "metrics")]
         ethrex_metrics::metrics_process::set_datadir_path(datadir.to_path_buf());
-        Store::new(datadir, engine_type).expect("Failed to create Store")
+        Store::new_with_options(datadir, engine_type, store_opts).expect("Failed to create Store")
     }
 }
 
@@ -164,7 +182,9 @@ pub async fn init_rpc_api(
 
     let rpc_api = ethrex_rpc::start_api(
         get_http_socket_addr(opts),
-        get_authrpc_socket_addr(opts),
+        Some(get_authrpc_socket_addr(opts)),
+        get_ipc_path(opts),
+        opts.http_config(),
         store,
         blockchain,
         read_jwtsecret_file(&opts.authrpc_jwtsecret),
@@ -176,6 +196,9 @@ pub async fn init_rpc_api(
         log_filter_handler,
         gas_ceil,
         extra_data,
+        get_managed_accounts(opts),
+        opts.checkpoint_dir.clone(),
+        opts.bad_block_traces_dir.clone(),
     );
 
     tracker.spawn(rpc_api);
@@ -310,6 +333,66 @@ pub fn get_signer(datadir: &Path) -> SecretKey {
     }
 }
 
+/// Loads the accounts managed by the node, both from keystore files and from a remote signer
+pub fn get_managed_accounts(opts: &Options) -> Vec<Signer> {
+    let mut accounts = Vec::new();
+
+    if let Some(password_file) = &opts.keystore_password_file {
+        let password =
+            fs::read_to_string(password_file).expect("Keystore password file could not be read.");
+        let password = password.trim_end_matches(['\r', '\n']);
+        for path in &opts.keystore {
+            let keystore_files = if path.is_dir() {
+                let mut files: Vec<PathBuf> = fs::read_dir(path)
+                    .expect("Keystore directory could not be read.")
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.is_file())
+                    .collect();
+                files.sort();
+                files
+            } else {
+                vec![path.clone()]
+            };
+            for keystore_file in keystore_files {
+                let signer =
+                    LocalSigner::from_keystore(&keystore_file, password).unwrap_or_else(|err| {
+                        panic!("Failed to load keystore {keystore_file:?}: {err}")
+                    });
+                info!("Loaded managed account {:#x} from keystore", signer.address);
+                accounts.push(signer.into());
+            }
+        }
+    }
+
+    if let Some(url) = &opts.remote_signer_url {
+        for public_key in &opts.remote_signer_public_keys {
+            let signer = RemoteSigner::new(url.clone(), *public_key);
+            info!(
+                "Loaded managed account {:#x} from remote signer",
+                signer.address
+            );
+            accounts.push(signer.into());
+        }
+    }
+
+    // The signing endpoints are served through the unauthenticated HTTP server,
+    // so they must not be reachable from other hosts unless explicitly allowed
+    if !accounts.is_empty()
+        && !opts.allow_insecure_signing
+        && !get_http_socket_addr(opts).ip().is_loopback()
+    {
+        error!(
+            "Managed accounts can only be used with the HTTP server bound to a loopback address, got {}",
+            opts.http_addr
+        );
+        panic!(
+            "Bind --http.addr to a loopback address or pass --rpc.allow-insecure-signing to serve the managed accounts."
+        );
+    }
+
+    accounts
+}
+
 pub fn get_local_p2p_node(opts: &Options, signer: &SecretKey) -> Node {
     let udp_socket_addr = parse_socket_addr("::", &opts.discovery_port)
         .expect("Failed to parse discovery address and port");
@@ -367,6 +450,18 @@ pub fn get_http_socket_addr(opts: &Options) -> SocketAddr {
         .expect("Failed to parse http address and port")
 }
 
+pub fn get_ipc_path(opts: &Options) -> Option<PathBuf> {
+    const IPC_FILENAME: &str = "ethrex.ipc";
+    if opts.ipc_disable {
+        return None;
+    }
+    Some(
+        opts.ipc_path
+            .clone()
+            .unwrap_or_else(|| opts.datadir.join(IPC_FILENAME)),
+    )
+}
+
 #[cfg(feature = "sync-test")]
 async fn set_sync_block(store: &Store) {
     if let Ok(block_number) = env::var("SYNC_BLOCK_NUM") {
@@ -404,7 +499,12 @@ pub async fn init_l1(
 
     raise_fd_limit()?;
 
-    let store = init_store(datadir, genesis).await;
+    let store = init_store(datadir, genesis, opts.store_options()).await;
+    if !opts.dev && opts.syncmode == SyncMode::Snap && store.trie_scheme() == TrieScheme::Path {
+        eyre::bail!(
+            "Snap sync is not supported with the path trie scheme, run with `--syncmode full`"
+        );
+    }
 
     #[cfg(feature = "sync-test")]
     set_sync_block(&store).await;
@@ -415,6 +515,8 @@ pub async fn init_l1(
             max_mempool_size: opts.mempool_max_size,
             perf_logs_enabled: true,
             r#type: BlockchainType::L1,
+            parallel_execution: opts.parallel_execution,
+            ..Default::default()
         },
     );
 
@@ -484,3 +586,145 @@ pub async fn init_l1(
         local_node_record,
     ))
 }
+
+/// Namespaces served by read-only nodes, none of them needs p2p networking or writing to the database
+const READONLY_NAMESPACES: [RpcNamespace; 2] = [RpcNamespace::Eth, RpcNamespace::Debug];
+
+/// Methods of the read-only namespaces that would need p2p networking to take effect or that sign
+/// with accounts managed by the node
+const READONLY_DENIED_METHODS: [&str; 5] = [
+    "eth_sendRawTransaction",
+    "eth_sendTransaction",
+    "eth_sign",
+    "eth_signTransaction",
+    "eth_signTypedData_v4",
+];
+
+/// Starts a node serving RPC requests from the database of another node running on the same datadir.
+/// The database is opened as a secondary instance which periodically catches up with the writes of the primary
+pub async fn init_readonly(
+    opts: Options,
+    log_filter_handler: Option<reload::Handle<EnvFilter, Registry>>,
+) -> eyre::Result<CancellationToken> {
+    let datadir = &opts.datadir;
+
+    let network = get_network(&opts);
+
+    let genesis = network.get_genesis()?;
+    display_chain_initialization(&genesis);
+
+    raise_fd_limit()?;
+
+    let Some(secondary_path) = opts.readonly_secondary_path.clone() else {
+        eyre::bail!(
+            "Read-only nodes need a directory of their own, given by `--readonly.secondary-path`"
+        );
+    };
+    let http_addr = get_http_socket_addr(&opts);
+    ensure_addr_available(http_addr, "--http.port")?;
+    if opts.metrics_enabled {
+        ensure_addr_available(
+            parse_socket_addr(&opts.metrics_addr, &opts.metrics_port)?,
+            "--metrics.port",
+        )?;
+    }
+
+    let store = Store::new_secondary(datadir, &secondary_path)?;
+    store.load_secondary_state(genesis).await?;
+
+    let blockchain = init_blockchain(
+        store.clone(),
+        BlockchainOptions {
+            max_mempool_size: opts.mempool_max_size,
+            perf_logs_enabled: true,
+            r#type: BlockchainType::L1,
+            parallel_execution: opts.parallel_execution,
+            ..Default::default()
+        },
+    );
+
+    // The node key belongs to the primary, read-only nodes don't take part in the p2p network
+    let signer = SecretKey::new(&mut OsRng);
+    let local_p2p_node = get_local_p2p_node(&opts, &signer);
+    let local_node_record = get_local_node_record(datadir, &local_p2p_node, &signer);
+
+    let mut http_config = opts.http_config();
+    http_config.namespaces = Some(match http_config.namespaces {
+        Some(namespaces) => namespaces
+            .into_iter()
+            .filter(|namespace| READONLY_NAMESPACES.contains(namespace))
+            .collect(),
+        None => READONLY_NAMESPACES.to_vec(),
+    });
+    http_config
+        .denied_methods
+        .extend(READONLY_DENIED_METHODS.map(String::from));
+
+    // The primary's IPC socket lives in the datadir, so it is only served at an explicit path
+    let ipc_path = opts.ipc_path.clone().filter(|_| !opts.ipc_disable);
+
+    let tracker = TaskTracker::new();
+    let cancel_token = CancellationToken::new();
+
+    let rpc_api = ethrex_rpc::start_api(
+        http_addr,
+        None,
+        ipc_path,
+        http_config,
+        store.clone(),
+        blockchain,
+        Default::default(),
+        local_p2p_node,
+        local_node_record,
+        SyncManager::dummy(),
+        PeerHandler::dummy(),
+        get_client_version(),
+        log_filter_handler,
+        None,
+        opts.extra_data.clone(),
+        // Signing and checkpoints are left to the primary
+        Vec::new(),
+        None,
+        opts.bad_block_traces_dir.clone(),
+    );
+    tracker.spawn(rpc_api);
+
+    if opts.metrics_enabled {
+        init_metrics(&opts, tracker.clone());
+    }
+
+    let catch_up_interval = Duration::from_millis(opts.readonly_catch_up_interval);
+    let catch_up_cancel_token = cancel_token.clone();
+    tracker.spawn(async move {
+        let mut interval = tokio::time::interval(catch_up_interval);
+        loop {
+            tokio::select! {
+                _ = catch_up_cancel_token.cancelled() => break,
+                _ = interval.tick() => {
+                    if let Err(error) = store.catch_up_with_primary().await {
+                        warn!("Failed to catch up with the primary database: {error}");
+                    }
+                }
+            }
+        }
+    });
+
+    info!(
+        ?secondary_path,
+        "Serving read-only RPC requests from the database at {}",
+        datadir.display()
+    );
+
+    Ok(cancel_token)
+}
+
+/// Fails if the address can't be bound, which for read-only nodes usually means the primary is
+/// serving on it
+fn ensure_addr_available(addr: SocketAddr, flag: &str) -> eyre::Result<()> {
+    std::net::TcpListener::bind(addr).map_err(|error| {
+        eyre::eyre!(
+            "Can't listen on {addr} ({error}), if the primary node serves on it pass a different `{flag}` to the read-only node"
+        )
+    })?;
+    Ok(())
+}
@@ -20,7 +20,7 @@ use ethrex_l2_sdk::call_contract;
 use ethrex_rpc::{
     EthClient, clients::beacon::BeaconClient, types::block_identifier::BlockIdentifier,
 };
-use ethrex_storage::{EngineType, Store, UpdateBatch};
+use ethrex_storage::{EngineType, Store, StoreOptions, UpdateBatch};
 use ethrex_storage_rollup::StoreRollup;
 use eyre::OptionExt;
 use itertools::Itertools;
@@ -451,7 +451,9 @@ impl Command {
                         storage_updates: accounts_updates,
                         blocks: vec![],
                         receipts: vec![],
+                        senders: vec![],
                         code_updates: vec![],
+                        preimages: vec![],
                     };
 
                     store
@@ -700,7 +702,7 @@ async fn delete_blocks_from_batch(
     let genesis = network.get_genesis()?;
 
     let mut block_to_delete = last_kept_block + 1;
-    let store = init_store(datadir, genesis).await;
+    let store = init_store(datadir, genesis, StoreOptions::default()).await;
 
     while store
         .get_canonical_block_hash(block_to_delete)
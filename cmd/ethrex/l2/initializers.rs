@@ -1,7 +1,8 @@
 use crate::cli::Options as L1Options;
 use crate::initializers::{
-    self, get_authrpc_socket_addr, get_http_socket_addr, get_local_node_record, get_local_p2p_node,
-    get_network, get_signer, init_blockchain, init_network, init_store,
+    self, get_authrpc_socket_addr, get_http_socket_addr, get_ipc_path, get_local_node_record,
+    get_local_p2p_node, get_managed_accounts, get_network, get_signer, init_blockchain,
+    init_network, init_store,
 };
 use crate::l2::L2Options;
 use crate::utils::{
@@ -11,6 +12,7 @@ use ethrex_blockchain::{Blockchain, BlockchainType};
 use ethrex_common::types::fee_config::FeeConfig;
 use ethrex_common::{Address, types::DEFAULT_BUILDER_GAS_CEIL};
 use ethrex_l2::SequencerConfig;
+use ethrex_l2_common::precompiles::l2_custom_precompiles;
 use ethrex_p2p::{
     discv4::peer_table::PeerTable,
     peer_handler::PeerHandler,
@@ -61,6 +63,8 @@ async fn init_rpc_api(
     let rpc_api = ethrex_l2_rpc::start_api(
         get_http_socket_addr(opts),
         get_authrpc_socket_addr(opts),
+        get_ipc_path(opts),
+        opts.http_config(),
         store,
         blockchain,
         read_jwtsecret_file(&opts.authrpc_jwtsecret),
@@ -74,6 +78,9 @@ async fn init_rpc_api(
         rollup_store,
         log_filter_handler,
         gas_ceil.unwrap_or(DEFAULT_BUILDER_GAS_CEIL),
+        get_managed_accounts(opts),
+        opts.checkpoint_dir.clone(),
+        opts.bad_block_traces_dir.clone(),
     );
 
     tracker.spawn(rpc_api);
@@ -153,7 +160,7 @@ pub async fn init_l2(
     let network = get_network(&opts.node_opts);
 
     let genesis = network.get_genesis()?;
-    let store = init_store(&datadir, genesis).await;
+    let store = init_store(&datadir, genesis, opts.node_opts.store_options()).await;
     let rollup_store = init_rollup_store(&rollup_store_dir).await;
 
     let fee_config = FeeConfig {
@@ -165,6 +172,9 @@ pub async fn init_l2(
         max_mempool_size: opts.node_opts.mempool_max_size,
         r#type: BlockchainType::L2(fee_config),
         perf_logs_enabled: true,
+        parallel_execution: opts.node_opts.parallel_execution,
+        precompiles: l2_custom_precompiles()?,
+        ..Default::default()
     };
 
     let blockchain = init_blockchain(store.clone(), blockchain_opts);
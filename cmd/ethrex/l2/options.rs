@@ -206,6 +206,7 @@ impl TryFrom<SequencerOptions> for SequencerConfig {
                     .proof_coordinator_tdx_private_key,
                 qpl_tool_path: opts.proof_coordinator_opts.proof_coordinator_qpl_tool_path,
                 validium: opts.validium,
+                compact_witness: opts.proof_coordinator_opts.compact_witness,
             },
             based: BasedConfig {
                 enabled: opts.based,
@@ -606,6 +607,16 @@ pub struct ProofCoordinatorOptions {
         help_heading = "Proof coordinator options"
     )]
     pub proof_send_interval_ms: u64,
+    #[arg(
+        long = "proof-coordinator.compact-witness",
+        action = clap::ArgAction::SetTrue,
+        default_value = "false",
+        env = "ETHREX_PROOF_COORDINATOR_COMPACT_WITNESS",
+        help_heading = "Proof coordinator options",
+        help = "Reduce the size of the execution witnesses sent to the provers.",
+        long_help = "Reduce the size of the execution witnesses sent to the provers by dropping unreachable trie nodes, repeated bytecodes and the headers of the blocks in the batch. Provers must run a version that accepts compact witnesses."
+    )]
+    pub compact_witness: bool,
 }
 
 impl Default for ProofCoordinatorOptions {
@@ -623,6 +634,7 @@ impl Default for ProofCoordinatorOptions {
             proof_send_interval_ms: 5000,
             proof_coordinator_tdx_private_key: None,
             proof_coordinator_qpl_tool_path: None,
+            compact_witness: false,
         }
     }
 }
@@ -8,6 +8,8 @@ use ethrex_p2p::{
     types::{Node, NodeRecord},
 };
 use ethrex_rlp::decode::RLPDecode;
+use ethrex_rpc::utils::{RpcNamespace, resolve_namespace};
+use ethrex_storage::TrieScheme;
 use hex::FromHexError;
 use secp256k1::{PublicKey, SecretKey};
 use serde::{Deserialize, Serialize};
@@ -82,6 +84,26 @@ pub fn parse_sync_mode(s: &str) -> eyre::Result<SyncMode> {
     }
 }
 
+pub fn parse_trie_scheme(s: &str) -> eyre::Result<TrieScheme> {
+    match s {
+        "hash" => Ok(TrieScheme::Hash),
+        "path" => Ok(TrieScheme::Path),
+        other => Err(eyre::eyre!(
+            "Invalid trie scheme {other:?} expected either hash or path",
+        )),
+    }
+}
+
+pub fn parse_rpc_namespace(s: &str) -> eyre::Result<RpcNamespace> {
+    match resolve_namespace(s, s.to_string()) {
+        Ok(RpcNamespace::Engine) => Err(eyre::eyre!(
+            "The engine namespace is only served by the authenticated rpc server"
+        )),
+        Ok(namespace) => Ok(namespace),
+        Err(_) => Err(eyre::eyre!("Unknown rpc namespace {s:?}")),
+    }
+}
+
 pub fn parse_socket_addr(addr: &str, port: &str) -> io::Result<SocketAddr> {
     // NOTE: this blocks until hostname can be resolved
     format!("{addr}:{port}")
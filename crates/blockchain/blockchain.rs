@@ -4,15 +4,16 @@ pub mod fork_choice;
 pub mod mempool;
 pub mod payload;
 mod smoke_test;
+pub mod stateless;
 pub mod tracing;
 pub mod vm;
 
-use ::tracing::{debug, info};
+use ::tracing::{debug, info, warn};
 use constants::{MAX_INITCODE_SIZE, MAX_TRANSACTION_DATA_SIZE, POST_OSAKA_GAS_LIMIT_CAP};
 use error::MempoolError;
 use error::{ChainError, InvalidBlockError};
 use ethrex_common::constants::{GAS_PER_BLOB, MAX_RLP_BLOCK_SIZE, MIN_BASE_FEE_PER_BLOB_GAS};
-use ethrex_common::types::block_execution_witness::ExecutionWitness;
+use ethrex_common::types::block_execution_witness::{ExecutionWitness, WitnessOptions};
 use ethrex_common::types::fee_config::FeeConfig;
 use ethrex_common::types::requests::{EncodedRequests, Requests, compute_requests_hash};
 use ethrex_common::types::{
@@ -30,7 +31,9 @@ use ethrex_storage::{
     AccountUpdatesList, Store, UpdateBatch, error::StoreError, hash_address, hash_key,
 };
 use ethrex_vm::backends::levm::db::DatabaseLogger;
-use ethrex_vm::{BlockExecutionResult, DynVmDatabase, Evm, EvmError};
+use ethrex_vm::{
+    BlockExecutionResult, CodeCache, CustomHooks, CustomPrecompiles, DynVmDatabase, Evm, EvmError,
+};
 use mempool::Mempool;
 use payload::PayloadOrTask;
 use std::collections::{BTreeMap, HashMap};
@@ -73,6 +76,8 @@ pub struct Blockchain {
     /// Mapping from a payload id to either a complete payload or a payload build task
     /// We need to keep completed payloads around in case consensus requests them twice
     pub payloads: Arc<TokioMutex<Vec<(u64, PayloadOrTask)>>>,
+    /// Analyzed bytecode shared by the executions of all blocks
+    code_cache: Arc<CodeCache>,
 }
 
 #[derive(Debug, Clone)]
@@ -81,6 +86,14 @@ pub struct BlockchainOptions {
     /// Whether performance logs should be emitted
     pub perf_logs_enabled: bool,
     pub r#type: BlockchainType,
+    /// Whether the transactions of each block are executed optimistically in parallel when importing it.
+    /// Off by default, the sequential executor is the reference one.
+    pub parallel_execution: bool,
+    /// Hooks run in every transaction executed, both when importing and building blocks and in RPC calls
+    pub hooks: CustomHooks,
+    /// Precompiles added to the standard ones, only available to L2 transactions. The guest
+    /// program of the prover must use the same ones.
+    pub precompiles: CustomPrecompiles,
 }
 
 impl Default for BlockchainOptions {
@@ -89,6 +102,9 @@ impl Default for BlockchainOptions {
             max_mempool_size: MAX_MEMPOOL_SIZE_DEFAULT,
             perf_logs_enabled: false,
             r#type: BlockchainType::default(),
+            parallel_execution: false,
+            hooks: CustomHooks::default(),
+            precompiles: CustomPrecompiles::default(),
         }
     }
 }
@@ -119,6 +135,7 @@ impl Blockchain {
             is_synced: AtomicBool::new(false),
             payloads: Arc::new(TokioMutex::new(Vec::new())),
             options: blockchain_opts,
+            code_cache: Arc::new(CodeCache::default()),
         }
     }
 
@@ -129,6 +146,7 @@ impl Blockchain {
             is_synced: AtomicBool::new(false),
             payloads: Arc::new(TokioMutex::new(Vec::new())),
             options: BlockchainOptions::default(),
+            code_cache: Arc::new(CodeCache::default()),
         }
     }
 
@@ -185,6 +203,16 @@ impl Blockchain {
     pub async fn generate_witness_for_blocks(
         &self,
         blocks: &[Block],
+    ) -> Result<ExecutionWitness, ChainError> {
+        self.generate_witness_for_blocks_with_options(blocks, WitnessOptions::default())
+            .await
+    }
+
+    /// Same as [`Blockchain::generate_witness_for_blocks`], applying the size reductions enabled in `options`
+    pub async fn generate_witness_for_blocks_with_options(
+        &self,
+        blocks: &[Block],
+        options: WitnessOptions,
     ) -> Result<ExecutionWitness, ChainError> {
         let first_block_header = blocks
             .first()
@@ -226,6 +254,7 @@ impl Blockchain {
                     Evm::new_from_db_for_l2(logger.clone(), fee_config)
                 }
             };
+            self.configure_evm(&mut vm);
 
             // Re-execute block with logger
             vm.execute_block(block)?;
@@ -353,9 +382,14 @@ impl Blockchain {
         let mut needed_block_numbers = block_hashes.keys().collect::<Vec<_>>();
         needed_block_numbers.sort();
 
-        // Last needed block header for the witness is the parent of the last block we need to execute
-        let last_needed_block_number = blocks
-            .last()
+        // Last needed block header for the witness is the parent of the last block we need to execute,
+        // or the parent of the first one if the guest program takes the rest from the blocks
+        let last_needed_block = if options.compact_headers {
+            blocks.first()
+        } else {
+            blocks.last()
+        };
+        let last_needed_block_number = last_needed_block
             .ok_or(ChainError::WitnessGeneration("Empty batch".to_string()))?
             .header
             .number
@@ -388,14 +422,24 @@ impl Blockchain {
             }
         }
 
-        Ok(ExecutionWitness {
+        let mut witness = ExecutionWitness {
             codes,
             block_headers_bytes,
             first_block_number: first_block_header.number,
             chain_config,
             nodes,
             keys,
-        })
+        };
+        if options.prune_unreachable_nodes {
+            witness
+                .prune_unreachable_nodes()
+                .map_err(|e| ChainError::WitnessGeneration(e.to_string()))?;
+        }
+        if options.dedup_codes {
+            witness.dedup_codes();
+        }
+
+        Ok(witness)
     }
 
     pub async fn store_block(
@@ -407,12 +451,25 @@ impl Blockchain {
         // Check state root matches the one in block header
         validate_state_root(&block.header, account_updates_list.state_trie_hash)?;
 
+        self.persist_block(block, account_updates_list, execution_result)
+            .await
+    }
+
+    /// Stores a block whose state root was already validated, along with its state updates
+    async fn persist_block(
+        &self,
+        block: Block,
+        account_updates_list: AccountUpdatesList,
+        execution_result: BlockExecutionResult,
+    ) -> Result<(), ChainError> {
         let update_batch = UpdateBatch {
             account_updates: account_updates_list.state_updates,
             storage_updates: account_updates_list.storage_updates,
             receipts: vec![(block.hash(), execution_result.receipts)],
+            senders: vec![(block.hash(), execution_result.senders)],
             blocks: vec![block],
             code_updates: account_updates_list.code_updates,
+            preimages: account_updates_list.preimages,
         };
 
         self.storage
@@ -424,7 +481,10 @@ impl Blockchain {
 
     pub async fn add_block(&self, block: Block) -> Result<(), ChainError> {
         let since = Instant::now();
-        let (res, updates) = self.execute_block(&block).await?;
+        let (res, updates) = match self.execute_block(&block).await {
+            Ok(result) => result,
+            Err(error) => return Err(self.reject_block(block, error).await),
+        };
         let executed = Instant::now();
 
         // Apply the account updates over the last block's state and compute the new state root
@@ -433,6 +493,10 @@ impl Blockchain {
             .apply_account_updates_batch(block.header.parent_hash, &updates)
             .await?
             .ok_or(ChainError::ParentStateNotFound)?;
+        if let Err(error) = validate_state_root(&block.header, account_updates_list.state_trie_hash)
+        {
+            return Err(self.reject_block(block, error).await);
+        }
 
         let (gas_used, gas_limit, block_number, transactions_count) = (
             block.header.gas_used,
@@ -442,9 +506,11 @@ impl Blockchain {
         );
 
         let merkleized = Instant::now();
-        let result = self.store_block(block, account_updates_list, res).await;
+        let result = self.persist_block(block, account_updates_list, res).await;
         let stored = Instant::now();
 
+        metrics!(self.update_code_cache_metrics());
+
         if self.options.perf_logs_enabled {
             Self::print_add_block_logs(
                 gas_used,
@@ -460,6 +526,17 @@ impl Blockchain {
         result
     }
 
+    /// Stores the block as a bad block if the error makes it invalid, returning the error back.
+    async fn reject_block(&self, block: Block, error: ChainError) -> ChainError {
+        if let ChainError::InvalidBlock(_) = error {
+            warn!("Rejected invalid block {}: {error}", block.hash());
+            if let Err(store_error) = self.storage.add_bad_block(block).await {
+                warn!("Failed to store bad block: {store_error}");
+            }
+        }
+        error
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn print_add_block_logs(
         gas_used: u64,
@@ -545,6 +622,7 @@ impl Blockchain {
 
         let blocks_len = blocks.len();
         let mut all_receipts: Vec<(BlockHash, Vec<Receipt>)> = Vec::with_capacity(blocks_len);
+        let mut all_senders: Vec<(BlockHash, Vec<Address>)> = Vec::with_capacity(blocks_len);
         let mut total_gas_used = 0;
         let mut transactions_count = 0;
 
@@ -570,22 +648,27 @@ impl Blockchain {
                 blocks[i - 1].header.clone()
             };
 
-            let BlockExecutionResult { receipts, .. } = self
-                .execute_block_from_state(&parent_header, block, &chain_config, &mut vm)
-                .map_err(|err| {
-                    (
+            let BlockExecutionResult {
+                receipts, senders, ..
+            } = match self.execute_block_from_state(&parent_header, block, &chain_config, &mut vm) {
+                Ok(result) => result,
+                Err(err) => {
+                    let err = self.reject_block(block.clone(), err).await;
+                    return Err((
                         err,
                         Some(BatchBlockProcessingFailure {
                             failed_block_hash: block.hash(),
                             last_valid_hash,
                         }),
-                    )
-                })?;
+                    ));
+                }
+            };
             debug!("Executed block with hash {}", block.hash());
             last_valid_hash = block.hash();
             total_gas_used += block.header.gas_used;
             transactions_count += block.body.transactions.len();
             all_receipts.push((block.hash(), receipts));
+            all_senders.push((block.hash(), senders));
 
             // Conversion is safe because EXECUTE_BATCH_SIZE=1024
             log_batch_progress(blocks_len as u32, i as u32);
@@ -615,16 +698,22 @@ impl Blockchain {
         let state_updates = account_updates_list.state_updates;
         let accounts_updates = account_updates_list.storage_updates;
         let code_updates = account_updates_list.code_updates;
+        let preimages = account_updates_list.preimages;
 
         // Check state root matches the one in block header
-        validate_state_root(&last_block.header, new_state_root).map_err(|e| (e, None))?;
+        if let Err(err) = validate_state_root(&last_block.header, new_state_root) {
+            // Only the state after the whole batch is checked, so the last block takes the blame
+            return Err((self.reject_block(last_block.clone(), err).await, None));
+        }
 
         let update_batch = UpdateBatch {
             account_updates: state_updates,
             storage_updates: accounts_updates,
             blocks,
             receipts: all_receipts,
+            senders: all_senders,
             code_updates,
+            preimages,
         };
 
         self.storage
@@ -645,6 +734,7 @@ impl Blockchain {
             // Set the latest gas used as the average gas used per block in the batch
             METRICS_BLOCKS.set_latest_gas_used(total_gas_used as f64 / blocks_len as f64);
             METRICS_BLOCKS.set_latest_gigagas(throughput);
+            self.update_code_cache_metrics();
         );
 
         if self.options.perf_logs_enabled {
@@ -919,13 +1009,32 @@ impl Blockchain {
     }
 
     pub fn new_evm(&self, vm_db: StoreVmDatabase) -> Result<Evm, EvmError> {
-        let evm = match self.options.r#type {
+        let mut evm = match self.options.r#type {
             BlockchainType::L1 => Evm::new_for_l1(vm_db),
             BlockchainType::L2(fee_config) => Evm::new_for_l2(vm_db, fee_config)?,
         };
+        evm.parallel_execution = self.options.parallel_execution;
+        self.configure_evm(&mut evm);
         Ok(evm)
     }
 
+    /// Shares the code cache, the custom hooks and the custom precompiles of the chain with an `Evm`
+    /// not created by `new_evm`
+    pub fn configure_evm(&self, evm: &mut Evm) {
+        evm.db.code_cache = Some(self.code_cache.clone());
+        evm.db.custom_hooks = self.options.hooks.clone();
+        evm.db.custom_precompiles = self.options.precompiles.clone();
+    }
+
+    #[cfg(feature = "metrics")]
+    fn update_code_cache_metrics(&self) {
+        METRICS_BLOCKS.set_code_cache_stats(
+            self.code_cache.hits(),
+            self.code_cache.misses(),
+            self.code_cache.size(),
+        );
+    }
+
     /// Get the current fork of the chain, based on the latest block's timestamp
     pub async fn current_fork(&self) -> Result<Fork, StoreError> {
         let chain_config = self.storage.get_chain_config()?;
@@ -1,4 +1,4 @@
-use prometheus::{Encoder, Gauge, IntGauge, Registry, TextEncoder};
+use prometheus::{Encoder, Gauge, IntCounter, IntGauge, Registry, TextEncoder};
 use std::sync::LazyLock;
 
 use crate::MetricsError;
@@ -14,6 +14,10 @@ pub struct MetricsBlocks {
     block_building_ms: IntGauge,
     block_building_base_fee: IntGauge,
     gas_used: Gauge,
+    code_cache_hits: IntCounter,
+    code_cache_misses: IntCounter,
+    code_cache_hit_rate: Gauge,
+    code_cache_size: IntGauge,
 }
 
 impl Default for MetricsBlocks {
@@ -60,6 +64,26 @@ impl MetricsBlocks {
                 "Keeps track of the gas used in the latest block",
             )
             .unwrap(),
+            code_cache_hits: IntCounter::new(
+                "code_cache_hits",
+                "Keeps track of the amount of bytecode analyses found in the code cache",
+            )
+            .unwrap(),
+            code_cache_misses: IntCounter::new(
+                "code_cache_misses",
+                "Keeps track of the amount of bytecode analyses not found in the code cache",
+            )
+            .unwrap(),
+            code_cache_hit_rate: Gauge::new(
+                "code_cache_hit_rate",
+                "Keeps track of the percentage of bytecode analyses found in the code cache",
+            )
+            .unwrap(),
+            code_cache_size: IntGauge::new(
+                "code_cache_size",
+                "Keeps track of the size in bytes of the bytecode in the code cache",
+            )
+            .unwrap(),
         }
     }
 
@@ -92,6 +116,22 @@ impl MetricsBlocks {
         self.gas_used.set(gas_used);
     }
 
+    /// Sets the stats of the code cache, given its total hits and misses so far
+    pub fn set_code_cache_stats(&self, hits: u64, misses: u64, size: usize) {
+        // The counters only track this cache, so they advance by what it counted since last set
+        self.code_cache_hits
+            .inc_by(hits.saturating_sub(self.code_cache_hits.get()));
+        self.code_cache_misses
+            .inc_by(misses.saturating_sub(self.code_cache_misses.get()));
+        self.code_cache_size
+            .set(size.try_into().unwrap_or(i64::MAX));
+        let lookups = hits.saturating_add(misses);
+        if lookups > 0 {
+            self.code_cache_hit_rate
+                .set(hits as f64 / lookups as f64 * 100.0);
+        }
+    }
+
     pub fn gather_metrics(&self) -> Result<String, MetricsError> {
         if self.block_number.get() <= 0 {
             return Ok(String::new());
@@ -113,6 +153,14 @@ impl MetricsBlocks {
             .map_err(|e| MetricsError::PrometheusErr(e.to_string()))?;
         r.register(Box::new(self.block_building_ms.clone()))
             .map_err(|e| MetricsError::PrometheusErr(e.to_string()))?;
+        r.register(Box::new(self.code_cache_hits.clone()))
+            .map_err(|e| MetricsError::PrometheusErr(e.to_string()))?;
+        r.register(Box::new(self.code_cache_misses.clone()))
+            .map_err(|e| MetricsError::PrometheusErr(e.to_string()))?;
+        r.register(Box::new(self.code_cache_hit_rate.clone()))
+            .map_err(|e| MetricsError::PrometheusErr(e.to_string()))?;
+        r.register(Box::new(self.code_cache_size.clone()))
+            .map_err(|e| MetricsError::PrometheusErr(e.to_string()))?;
 
         let encoder = TextEncoder::new();
         let metric_families = r.gather();
@@ -393,6 +393,7 @@ impl Blockchain {
         let base_fee = payload.header.base_fee_per_gas.unwrap_or_default();
         let mut context =
             PayloadBuildContext::new(payload, &self.storage, self.options.r#type.clone())?;
+        self.configure_evm(&mut context.vm);
 
         if let BlockchainType::L1 = self.options.r#type {
             self.apply_system_operations(&mut context)?;
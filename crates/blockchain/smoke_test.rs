@@ -1,21 +1,32 @@
 #[cfg(test)]
 mod blockchain_integration_test {
-    use std::{fs::File, io::BufReader};
+    use std::{fs::File, io::BufReader, time::Duration};
 
     use crate::{
-        Blockchain,
+        Blockchain, BlockchainOptions,
         error::{ChainError, InvalidForkChoice},
         fork_choice::apply_fork_choice,
         is_canonical, latest_canonical_block_hash,
         payload::{BuildPayloadArgs, create_payload},
+        stateless::{StatelessValidationError, validate_stateless},
+        vm::StoreVmDatabase,
     };
 
     use bytes::Bytes;
     use ethrex_common::{
-        H160, H256,
-        types::{Block, BlockHeader, DEFAULT_BUILDER_GAS_CEIL, ELASTICITY_MULTIPLIER},
+        Address, H160, H256, U256,
+        types::{
+            Block, BlockHeader, DEFAULT_BUILDER_GAS_CEIL, ELASTICITY_MULTIPLIER, Genesis,
+            GenesisAccount, LegacyTransaction, Transaction, TxKind,
+            block_execution_witness::WitnessOptions,
+        },
+        utils::keccak,
     };
+    use ethrex_rlp::{decode::RLPDecode, encode::RLPEncode, structs::Encoder};
     use ethrex_storage::{EngineType, Store};
+    use ethrex_vm::Evm;
+    use secp256k1::{Message, SECP256K1, SecretKey};
+    use sha3::{Digest, Keccak256};
 
     #[tokio::test]
     async fn test_small_to_long_reorg() {
@@ -296,6 +307,308 @@ mod blockchain_integration_test {
         assert_eq!(latest_canonical_block_hash(&store).await.unwrap(), hash_b);
     }
 
+    #[tokio::test]
+    async fn parallel_execution_matches_sequential_on_conflicting_blocks() {
+        // PUSH1 0, SLOAD, PUSH1 1, ADD, PUSH1 0, SSTORE, STOP
+        let counter_code =
+            Bytes::from_static(&[0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00]);
+        let counter = Address::from_low_u64_be(0xc0ffee);
+        let keys: Vec<SecretKey> = (1..=4)
+            .map(|byte| SecretKey::from_slice(&[byte; 32]).unwrap())
+            .collect();
+
+        let mut genesis = test_genesis();
+        genesis.alloc.insert(
+            counter,
+            GenesisAccount {
+                code: counter_code,
+                storage: Default::default(),
+                balance: U256::zero(),
+                nonce: 1,
+            },
+        );
+        for key in &keys {
+            genesis.alloc.insert(
+                address_of(key),
+                GenesisAccount {
+                    code: Bytes::new(),
+                    storage: Default::default(),
+                    balance: U256::from(10).pow(U256::from(21)),
+                    nonce: 0,
+                },
+            );
+        }
+        let sequential_store = store_with_genesis(genesis.clone()).await;
+        let parallel_store = store_with_genesis(genesis).await;
+        let sequential = Blockchain::new(sequential_store.clone(), BlockchainOptions::default());
+        let parallel = Blockchain::new(
+            parallel_store.clone(),
+            BlockchainOptions {
+                parallel_execution: true,
+                ..Default::default()
+            },
+        );
+
+        // Every transaction increments the same counter, and every sender also pays the next one,
+        // so all of them conflict with the previous ones
+        for (i, key) in keys.iter().enumerate() {
+            let next_sender = address_of(&keys[(i + 1) % keys.len()]);
+            for nonce in 0..2 {
+                let to = if nonce == 0 { counter } else { next_sender };
+                let tx = signed_legacy_tx(key, nonce, to, U256::from(1_000_000));
+                sequential.add_transaction_to_pool(tx).await.unwrap();
+            }
+        }
+        let genesis_header = sequential_store.get_block_header(0).unwrap().unwrap();
+        let block = build_block(&sequential, &sequential_store, &genesis_header).await;
+        assert_eq!(block.body.transactions.len(), 8);
+
+        // Importing validates the state, receipts and requests roots and the gas used
+        sequential.add_block(block.clone()).await.unwrap();
+        parallel.add_block(block.clone()).await.unwrap();
+
+        let hash = block.hash();
+        assert_eq!(
+            sequential_store
+                .get_receipts_for_block(&hash)
+                .await
+                .unwrap(),
+            parallel_store.get_receipts_for_block(&hash).await.unwrap()
+        );
+        assert_eq!(
+            sequential_store
+                .get_storage_at_hash(hash, counter, H256::zero())
+                .unwrap(),
+            Some(U256::from(4))
+        );
+        let coinbase = block.header.coinbase;
+        for address in keys.iter().map(address_of).chain([counter, coinbase]) {
+            assert_eq!(
+                sequential_store
+                    .get_account_info_by_hash(hash, address)
+                    .unwrap(),
+                parallel_store
+                    .get_account_info_by_hash(hash, address)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn generated_witnesses_validate_statelessly() {
+        // PUSH1 0, SLOAD, PUSH1 1, ADD, PUSH1 0, SSTORE, STOP
+        let counter_code =
+            Bytes::from_static(&[0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00]);
+        let counter = Address::from_low_u64_be(0xc0ffee);
+        let key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let mut genesis = test_genesis();
+        genesis.alloc.insert(
+            counter,
+            GenesisAccount {
+                code: counter_code.clone(),
+                storage: Default::default(),
+                balance: U256::zero(),
+                nonce: 1,
+            },
+        );
+        genesis.alloc.insert(
+            address_of(&key),
+            GenesisAccount {
+                code: Bytes::new(),
+                storage: Default::default(),
+                balance: U256::from(10).pow(U256::from(21)),
+                nonce: 0,
+            },
+        );
+        let store = store_with_genesis(genesis).await;
+        let blockchain = Blockchain::new(store.clone(), BlockchainOptions::default());
+        let recipient = Address::from_low_u64_be(0xbeef);
+        for (nonce, to) in [counter, recipient].into_iter().enumerate() {
+            let tx = signed_legacy_tx(&key, nonce as u64, to, U256::from(1_000_000));
+            blockchain.add_transaction_to_pool(tx).await.unwrap();
+        }
+        let genesis_header = store.get_block_header(0).unwrap().unwrap();
+        let block = build_block(&blockchain, &store, &genesis_header).await;
+        assert_eq!(block.body.transactions.len(), 2);
+        blockchain.add_block(block.clone()).await.unwrap();
+
+        let witness = blockchain
+            .generate_witness_for_blocks(std::slice::from_ref(&block))
+            .await
+            .unwrap();
+        validate_stateless(vec![block.clone()], witness.clone(), ELASTICITY_MULTIPLIER).unwrap();
+        let compact_witness = blockchain
+            .generate_witness_for_blocks_with_options(
+                std::slice::from_ref(&block),
+                WitnessOptions::compact(),
+            )
+            .await
+            .unwrap();
+        validate_stateless(vec![block.clone()], compact_witness, ELASTICITY_MULTIPLIER).unwrap();
+
+        // A tampered code no longer matches the code hash of the account
+        let mut tampered = witness.clone();
+        for code in &mut tampered.codes {
+            code.push(0x00);
+        }
+        let error =
+            validate_stateless(vec![block.clone()], tampered, ELASTICITY_MULTIPLIER).unwrap_err();
+        assert!(
+            matches!(error, StatelessValidationError::MissingCode(hash) if hash == keccak(&counter_code[..])),
+            "{error}"
+        );
+
+        // Tampered nodes leave the state trie incomplete
+        let mut tampered = witness.clone();
+        for node in &mut tampered.nodes {
+            if keccak(node.as_slice()) != genesis_header.state_root {
+                node.push(0x00);
+            }
+        }
+        let error =
+            validate_stateless(vec![block.clone()], tampered, ELASTICITY_MULTIPLIER).unwrap_err();
+        assert!(
+            matches!(error, StatelessValidationError::MissingAccountNodes(_)),
+            "{error}"
+        );
+
+        // A tampered parent header no longer matches the parent hash of the block
+        let mut tampered = witness;
+        for header_bytes in &mut tampered.block_headers_bytes {
+            let mut header = BlockHeader::decode(header_bytes).unwrap();
+            if header.number == 0 {
+                header.extra_data = Bytes::from_static(b"tampered");
+                *header_bytes = header.encode_to_vec();
+            }
+        }
+        let error = validate_stateless(vec![block], tampered, ELASTICITY_MULTIPLIER).unwrap_err();
+        assert!(
+            matches!(error, StatelessValidationError::HeaderMismatch(0)),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn intermediate_roots_match_applying_all_changes_from_the_parent() {
+        // PUSH1 0, SLOAD, PUSH1 1, ADD, PUSH1 0, SSTORE, STOP
+        let counter_code =
+            Bytes::from_static(&[0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00]);
+        let counter = Address::from_low_u64_be(0xc0ffee);
+        let keys: Vec<SecretKey> = (1..=3)
+            .map(|byte| SecretKey::from_slice(&[byte; 32]).unwrap())
+            .collect();
+
+        let mut genesis = test_genesis();
+        genesis.alloc.insert(
+            counter,
+            GenesisAccount {
+                code: counter_code,
+                storage: Default::default(),
+                balance: U256::zero(),
+                nonce: 1,
+            },
+        );
+        for key in &keys {
+            genesis.alloc.insert(
+                address_of(key),
+                GenesisAccount {
+                    code: Bytes::new(),
+                    storage: Default::default(),
+                    balance: U256::from(10).pow(U256::from(21)),
+                    nonce: 0,
+                },
+            );
+        }
+        let store = store_with_genesis(genesis).await;
+        let blockchain = Blockchain::default_with_store(store.clone());
+
+        // The same accounts and storage slot are changed again by later transactions
+        for (i, key) in keys.iter().enumerate() {
+            let next_sender = address_of(&keys[(i + 1) % keys.len()]);
+            for nonce in 0..2 {
+                let to = if nonce == 0 { counter } else { next_sender };
+                let tx = signed_legacy_tx(key, nonce, to, U256::from(1_000_000));
+                blockchain.add_transaction_to_pool(tx).await.unwrap();
+            }
+        }
+        let genesis_header = store.get_block_header(0).unwrap().unwrap();
+        let block = build_block(&blockchain, &store, &genesis_header).await;
+        assert_eq!(block.body.transactions.len(), 6);
+
+        let roots = blockchain
+            .intermediate_roots(block.clone(), 0, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        // Apply all the changes since the parent state after every transaction
+        let mut vm = Evm::new_for_l1(StoreVmDatabase::new(store.clone(), genesis_header.hash()));
+        vm.rerun_block(&block, Some(0)).unwrap();
+        let mut remaining_gas = block.header.gas_limit;
+        let mut expected_roots = Vec::new();
+        for tx in &block.body.transactions {
+            let sender = tx.sender().unwrap();
+            vm.execute_tx(tx, &block.header, &mut remaining_gas, sender)
+                .unwrap();
+            let account_updates = vm.clone().get_state_transitions().unwrap();
+            let account_updates_list = store
+                .apply_account_updates_batch(genesis_header.hash(), &account_updates)
+                .await
+                .unwrap()
+                .unwrap();
+            expected_roots.push(account_updates_list.state_trie_hash);
+        }
+        assert_eq!(roots, expected_roots);
+    }
+
+    fn address_of(key: &SecretKey) -> Address {
+        let public_key = key.public_key(SECP256K1).serialize_uncompressed();
+        Address::from_slice(&Keccak256::digest(&public_key[1..])[12..])
+    }
+
+    fn signed_legacy_tx(key: &SecretKey, nonce: u64, to: Address, value: U256) -> Transaction {
+        let mut tx = LegacyTransaction {
+            nonce,
+            gas_price: 10_000_000_000,
+            gas: 100_000,
+            to: TxKind::Call(to),
+            value,
+            ..Default::default()
+        };
+        let mut payload = Vec::new();
+        Encoder::new(&mut payload)
+            .encode_field(&tx.nonce)
+            .encode_field(&tx.gas_price)
+            .encode_field(&tx.gas)
+            .encode_field(&tx.to)
+            .encode_field(&tx.value)
+            .encode_field(&tx.data)
+            .finish();
+        let message = Message::from_digest(Keccak256::digest(&payload).into());
+        let (recovery_id, signature) = SECP256K1
+            .sign_ecdsa_recoverable(&message, key)
+            .serialize_compact();
+        tx.r = U256::from_big_endian(&signature[..32]);
+        tx.s = U256::from_big_endian(&signature[32..]);
+        tx.v = U256::from(i32::from(recovery_id) + 27);
+        Transaction::LegacyTransaction(tx)
+    }
+
+    async fn build_block(blockchain: &Blockchain, store: &Store, parent: &BlockHeader) -> Block {
+        let args = BuildPayloadArgs {
+            parent: parent.hash(),
+            timestamp: parent.timestamp + 12,
+            fee_recipient: H160::random(),
+            random: H256::random(),
+            withdrawals: Some(Vec::new()),
+            beacon_root: Some(H256::random()),
+            version: 1,
+            elasticity_multiplier: ELASTICITY_MULTIPLIER,
+            gas_ceil: DEFAULT_BUILDER_GAS_CEIL,
+        };
+        let block = create_payload(&args, store, Bytes::new()).unwrap();
+        blockchain.build_payload(block).await.unwrap().payload
+    }
+
     async fn new_block(store: &Store, parent: &BlockHeader) -> Block {
         let args = BuildPayloadArgs {
             parent: parent.hash(),
@@ -318,13 +631,17 @@ mod blockchain_integration_test {
     }
 
     async fn test_store() -> Store {
-        // Get genesis
+        store_with_genesis(test_genesis()).await
+    }
+
+    fn test_genesis() -> Genesis {
         let file = File::open("../../fixtures/genesis/execution-api.json")
             .expect("Failed to open genesis file");
         let reader = BufReader::new(file);
-        let genesis = serde_json::from_reader(reader).expect("Failed to deserialize genesis file");
+        serde_json::from_reader(reader).expect("Failed to deserialize genesis file")
+    }
 
-        // Build store with genesis
+    async fn store_with_genesis(genesis: Genesis) -> Store {
         let store =
             Store::new("store.db", EngineType::InMemory).expect("Failed to build DB for testing");
 
@@ -0,0 +1,245 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bytes::Bytes;
+use ethrex_common::{
+    Address, H256, U256,
+    constants::{EMPTY_KECCACK_HASH, EMPTY_TRIE_HASH},
+    types::{
+        AccountState, Block, BlockHeader, ChainConfig,
+        block_execution_witness::{
+            ExecutionWitness, GuestProgramState, GuestProgramStateError, hash_address,
+        },
+    },
+};
+use ethrex_rlp::decode::RLPDecode;
+use ethrex_vm::{Evm, EvmError, GuestProgramStateWrapper, VmDatabase};
+
+use crate::{
+    error::ChainError, validate_block, validate_gas_used, validate_receipts_root,
+    validate_requests_hash,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum StatelessValidationError {
+    #[error("No blocks to validate")]
+    NoBlocks,
+    #[error("Invalid execution witness: {0}")]
+    InvalidWitness(#[from] GuestProgramStateError),
+    #[error("Header of block {0} doesn't match the parent hash of its successor")]
+    HeaderMismatch(u64),
+    #[error("Witness lacks the state trie nodes of account {0:#x}")]
+    MissingAccountNodes(Address),
+    #[error("Witness lacks the storage trie nodes of slot {key:#x} of account {address:#x}")]
+    MissingStorageNodes { address: Address, key: H256 },
+    #[error("Witness lacks the code with hash {0:#x}")]
+    MissingCode(H256),
+    #[error(
+        "Initial state root mismatch: parent header has {expected:#x}, witness has {computed:#x}"
+    )]
+    InitialStateRootMismatch { expected: H256, computed: H256 },
+    #[error("Final state root mismatch: block header has {expected:#x}, computed {computed:#x}")]
+    FinalStateRootMismatch { expected: H256, computed: H256 },
+    #[error("Invalid block {number}: {error}")]
+    InvalidBlock { number: u64, error: ChainError },
+    #[error("EVM error: {0}")]
+    Evm(#[from] EvmError),
+}
+
+/// Executes L1 blocks using only the state in their execution witness, checking the witness
+/// against the headers and the execution against the blocks.
+/// Unlike the guest program, the data the witness lacks is reported as the cause of a failed validation.
+pub fn validate_stateless(
+    mut blocks: Vec<Block>,
+    witness: ExecutionWitness,
+    elasticity_multiplier: u64,
+) -> Result<(), StatelessValidationError> {
+    // The header hashes are computed and checked against the witness below
+    for block in &mut blocks {
+        block.header.hash = Default::default();
+    }
+    let (first_block_number, first_parent_hash) = blocks
+        .first()
+        .map(|block| (block.header.number, block.header.parent_hash))
+        .ok_or(StatelessValidationError::NoBlocks)?;
+    let chain_config = witness.chain_config;
+    let mut state: GuestProgramState = witness.try_into()?;
+    // Compact witnesses leave out the headers of the executed blocks
+    if let Some((_last_block, executed_blocks)) = blocks.split_last() {
+        for block in executed_blocks {
+            state
+                .block_headers
+                .entry(block.header.number)
+                .or_insert_with(|| block.header.clone());
+        }
+    }
+    let mut state = GuestProgramStateWrapper::new(state);
+    state.initialize_block_header_hashes(&blocks)?;
+    if let Some(number) = state.get_first_invalid_block_hash()? {
+        return Err(StatelessValidationError::HeaderMismatch(number));
+    }
+    let parent_header = state.get_block_parent_header(first_block_number)?;
+    if first_parent_hash != parent_header.hash() {
+        return Err(StatelessValidationError::HeaderMismatch(
+            parent_header.number,
+        ));
+    }
+    let initial_state_root = state.state_trie_root()?;
+    if initial_state_root != parent_header.state_root {
+        return Err(StatelessValidationError::InitialStateRootMismatch {
+            expected: parent_header.state_root,
+            computed: initial_state_root,
+        });
+    }
+
+    let db = WitnessDatabase {
+        state: state.clone(),
+        gap: Default::default(),
+    };
+    execute_blocks(
+        &blocks,
+        parent_header,
+        &chain_config,
+        elasticity_multiplier,
+        &mut state,
+        &db,
+    )
+    // Any failure is most likely caused by the data the witness lacks
+    .map_err(|error| db.take_gap().map(Into::into).unwrap_or(error))
+}
+
+fn execute_blocks(
+    blocks: &[Block],
+    mut parent_header: BlockHeader,
+    chain_config: &ChainConfig,
+    elasticity_multiplier: u64,
+    state: &mut GuestProgramStateWrapper,
+    db: &WitnessDatabase,
+) -> Result<(), StatelessValidationError> {
+    for block in blocks {
+        let number = block.header.number;
+        let invalid_block = |error| StatelessValidationError::InvalidBlock { number, error };
+        validate_block(block, &parent_header, chain_config, elasticity_multiplier)
+            .map_err(invalid_block)?;
+        let mut vm = Evm::new_for_l1(db.clone());
+        let result = vm.execute_block(block)?;
+        let account_updates = vm.get_state_transitions()?;
+        state.apply_account_updates(&account_updates)?;
+        validate_gas_used(&result.receipts, &block.header).map_err(invalid_block)?;
+        validate_receipts_root(&block.header, &result.receipts).map_err(invalid_block)?;
+        validate_requests_hash(&block.header, chain_config, &result.requests)
+            .map_err(invalid_block)?;
+        parent_header = block.header.clone();
+    }
+    let final_state_root = state.state_trie_root()?;
+    if final_state_root != parent_header.state_root {
+        return Err(StatelessValidationError::FinalStateRootMismatch {
+            expected: parent_header.state_root,
+            computed: final_state_root,
+        });
+    }
+    Ok(())
+}
+
+/// Data needed by the execution that the witness lacks
+enum WitnessGap {
+    Account(Address),
+    Storage { address: Address, key: H256 },
+    Code(H256),
+}
+
+impl From<WitnessGap> for StatelessValidationError {
+    fn from(gap: WitnessGap) -> Self {
+        match gap {
+            WitnessGap::Account(address) => Self::MissingAccountNodes(address),
+            WitnessGap::Storage { address, key } => Self::MissingStorageNodes { address, key },
+            WitnessGap::Code(code_hash) => Self::MissingCode(code_hash),
+        }
+    }
+}
+
+/// Reads the state from the witness like the guest program does, treating the data the witness
+/// lacks as absent, but keeps track of the first gap found so it can be reported if the validation fails
+#[derive(Clone)]
+struct WitnessDatabase {
+    state: GuestProgramStateWrapper,
+    gap: Arc<Mutex<Option<WitnessGap>>>,
+}
+
+impl WitnessDatabase {
+    fn record_gap(&self, gap: WitnessGap) {
+        if let Ok(mut first_gap) = self.gap.lock() {
+            first_gap.get_or_insert(gap);
+        }
+    }
+
+    fn take_gap(&self) -> Option<WitnessGap> {
+        self.gap.lock().ok()?.take()
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, GuestProgramState>, EvmError> {
+        self.state
+            .lock_mutex()
+            .map_err(|error| EvmError::DB(error.to_string()))
+    }
+}
+
+impl VmDatabase for WitnessDatabase {
+    fn get_account_state(&self, address: Address) -> Result<Option<AccountState>, EvmError> {
+        let state = self.lock_state()?;
+        let state_trie = state
+            .state_trie
+            .as_ref()
+            .ok_or(EvmError::DB("State trie wasn't built".to_string()))?;
+        match state_trie.get(&hash_address(&address)) {
+            Ok(Some(encoded_state)) => AccountState::decode(&encoded_state)
+                .map(Some)
+                .map_err(|error| EvmError::DB(error.to_string())),
+            Ok(None) => Ok(None),
+            Err(_) => {
+                self.record_gap(WitnessGap::Account(address));
+                Ok(None)
+            }
+        }
+    }
+
+    fn get_storage_slot(&self, address: Address, key: H256) -> Result<Option<U256>, EvmError> {
+        let mut state = self.lock_state()?;
+        if !state.storage_tries.contains_key(&address) {
+            let storage_root = state
+                .get_account_state(address)
+                .map_err(|error| EvmError::DB(error.to_string()))?
+                .map(|account| account.storage_root);
+            if let Some(storage_root) = storage_root
+                && storage_root != *EMPTY_TRIE_HASH
+                && !state.nodes_hashed.contains_key(&storage_root)
+            {
+                self.record_gap(WitnessGap::Storage { address, key });
+            }
+        }
+        state.get_storage_slot(address, key).map_err(|error| {
+            self.record_gap(WitnessGap::Storage { address, key });
+            EvmError::DB(error.to_string())
+        })
+    }
+
+    fn get_block_hash(&self, block_number: u64) -> Result<H256, EvmError> {
+        self.state.get_block_hash(block_number)
+    }
+
+    fn get_chain_config(&self) -> Result<ChainConfig, EvmError> {
+        self.state.get_chain_config()
+    }
+
+    fn get_account_code(&self, code_hash: H256) -> Result<Bytes, EvmError> {
+        if code_hash == *EMPTY_KECCACK_HASH {
+            return Ok(Bytes::new());
+        }
+        match self.lock_state()?.codes_hashed.get(&code_hash) {
+            Some(code) => Ok(Bytes::copy_from_slice(code)),
+            None => {
+                self.record_gap(WitnessGap::Code(code_hash));
+                Ok(Bytes::new())
+            }
+        }
+    }
+}
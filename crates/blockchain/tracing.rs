@@ -1,11 +1,13 @@
 use std::{
+    collections::HashMap,
+    io::Write,
     sync::{Arc, Mutex},
     time::Duration,
 };
 
-use ethrex_common::{H256, tracing::CallTrace, types::Block};
-use ethrex_storage::Store;
-use ethrex_vm::{Evm, EvmError};
+use ethrex_common::{Address, H256, U256, tracing::CallTrace, types::Block};
+use ethrex_storage::{Store, error::StoreError};
+use ethrex_vm::{Evm, EvmError, GasProfile};
 
 use crate::{Blockchain, error::ChainError, vm::StoreVmDatabase};
 
@@ -82,6 +84,217 @@ impl Blockchain {
         Ok(call_traces)
     }
 
+    /// Writes the EIP-3155 opcode trace of each transaction in the block, or only of the one at
+    /// `tx_index` if given, to the output created for it by `create_output` from its index and hash.
+    /// Traces are written as the transactions are executed, from oldest to newest.
+    /// May need to re-execute blocks in order to rebuild the block's prestate, up to the amount given by `reexec`
+    pub async fn trace_block_opcodes<W, F>(
+        &self,
+        block: Block,
+        tx_index: Option<usize>,
+        reexec: u32,
+        timeout: Duration,
+        create_output: F,
+    ) -> Result<(), ChainError>
+    where
+        W: Write + 'static,
+        F: Fn(usize, H256) -> std::io::Result<W> + Send + Sync + 'static,
+    {
+        let indexes = match tx_index {
+            Some(index) if index < block.body.transactions.len() => index..index + 1,
+            Some(index) => {
+                return Err(ChainError::Custom(format!(
+                    "Transaction index {index} out of range"
+                )));
+            }
+            None => 0..block.body.transactions.len(),
+        };
+        // Obtain the block's parent state
+        let mut vm = self
+            .rebuild_parent_state(block.header.parent_hash, reexec)
+            .await?;
+        // Run the block until the first transaction we want to trace
+        vm.rerun_block(&block, Some(indexes.start))?;
+        // Trace each transaction, see `trace_block_calls`
+        let vm = Arc::new(Mutex::new(vm));
+        let block = Arc::new(block);
+        let create_output = Arc::new(create_output);
+        for index in indexes {
+            let block = block.clone();
+            let vm = vm.clone();
+            let create_output = create_output.clone();
+            let tx_hash = block.as_ref().body.transactions[index].hash();
+            timeout_trace_operation(timeout, move || {
+                let output = create_output(index, tx_hash).map_err(|error| {
+                    EvmError::Custom(format!("Failed to create opcode trace output: {error}"))
+                })?;
+                vm.lock()
+                    .map_err(|_| EvmError::Custom("Unexpected Runtime Error".to_string()))?
+                    .trace_tx_opcodes(block.as_ref(), index, output)
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Outputs the gas profile of the given transaction, with its folded stacks if `with_folded` is set
+    /// May need to re-execute blocks in order to rebuild the transaction's prestate, up to the amount given by `reexec`
+    pub async fn gas_profile_transaction(
+        &self,
+        tx_hash: H256,
+        reexec: u32,
+        timeout: Duration,
+        with_folded: bool,
+    ) -> Result<GasProfile, ChainError> {
+        // Fetch the transaction's location and the block it is contained in
+        let Some((_, block_hash, tx_index)) =
+            self.storage.get_transaction_location(tx_hash).await?
+        else {
+            return Err(ChainError::Custom("Transaction not Found".to_string()));
+        };
+        let tx_index = tx_index as usize;
+        let Some(block) = self.storage.get_block_by_hash(block_hash).await? else {
+            return Err(ChainError::Custom("Block not Found".to_string()));
+        };
+        // Obtain the block's parent state
+        let mut vm = self
+            .rebuild_parent_state(block.header.parent_hash, reexec)
+            .await?;
+        // Run the block until the transaction we want to profile
+        vm.rerun_block(&block, Some(tx_index))?;
+        // Profile the transaction
+        timeout_trace_operation(timeout, move || {
+            vm.profile_gas(&block, Some(tx_index), with_folded)
+        })
+        .await
+    }
+
+    /// Outputs the gas profile of all the transactions in the block, with its folded stacks if `with_folded` is set
+    /// May need to re-execute blocks in order to rebuild the block's prestate, up to the amount given by `reexec`
+    /// The timeout applies to profiling the whole block
+    pub async fn gas_profile_block(
+        &self,
+        block: Block,
+        reexec: u32,
+        timeout: Duration,
+        with_folded: bool,
+    ) -> Result<GasProfile, ChainError> {
+        // Obtain the block's parent state
+        let mut vm = self
+            .rebuild_parent_state(block.header.parent_hash, reexec)
+            .await?;
+        // Run anything necessary before executing the block's transactions (system calls, etc)
+        vm.rerun_block(&block, Some(0))?;
+        // Profile all transactions
+        timeout_trace_operation(timeout, move || vm.profile_gas(&block, None, with_folded)).await
+    }
+
+    /// Returns the state root right after executing each transaction of the block
+    /// May need to re-execute blocks in order to rebuild the block's prestate, up to the amount given by `reexec`
+    /// The timeout applies to executing the whole block
+    pub async fn intermediate_roots(
+        &self,
+        block: Block,
+        reexec: u32,
+        timeout: Duration,
+    ) -> Result<Vec<H256>, ChainError> {
+        // Obtain the state our Evm is based on along with the changes needed to reach the block's parent state
+        let (mut vm, base_hash) = self
+            .rebuild_parent_state_from_base(block.header.parent_hash, reexec)
+            .await?;
+        let Some(base_header) = self.storage.get_block_header_by_hash(base_hash)? else {
+            return Err(ChainError::ParentNotFound);
+        };
+        // Run anything necessary before executing the block's transactions (system calls, etc)
+        vm.rerun_block(&block, Some(0))?;
+        let store = self.storage.clone();
+        timeout_trace_operation(timeout, move || {
+            let store_error = |error: StoreError| EvmError::DB(error.to_string());
+            // The tries are updated in memory with the changes of each transaction on top of those of the
+            // previous ones, starting with the changes needed to reach the block's prestate
+            let mut state_trie = store
+                .open_locked_state_trie(base_header.state_root)
+                .map_err(store_error)?;
+            let mut storage_tries = HashMap::new();
+            // Getting the state transitions clears the state cached by the Evm, so we take them from a copy
+            let account_updates = vm.clone().get_state_transitions()?;
+            store
+                .apply_account_updates_in_memory(
+                    &mut state_trie,
+                    &mut storage_tries,
+                    &account_updates,
+                )
+                .map_err(store_error)?;
+            let mut checkpoint = vm.db.current_accounts_state.clone();
+
+            let mut remaining_gas = block.header.gas_limit;
+            let mut roots = Vec::with_capacity(block.body.transactions.len());
+            for tx in &block.body.transactions {
+                let sender = tx.sender().map_err(|error| {
+                    EvmError::Transaction(format!("Couldn't recover addresses with error: {error}"))
+                })?;
+                vm.execute_tx(tx, &block.header, &mut remaining_gas, sender)?;
+                let account_updates = vm.get_state_transitions_since(&mut checkpoint)?;
+                let root = store
+                    .apply_account_updates_in_memory(
+                        &mut state_trie,
+                        &mut storage_tries,
+                        &account_updates,
+                    )
+                    .map_err(store_error)?;
+                roots.push(root);
+            }
+            Ok(roots)
+        })
+        .await
+    }
+
+    /// Returns up to `max_results` storage slots of the given account as they were right before executing the transaction at `tx_index`
+    /// Slots are keyed by their hashed key and returned in order starting from `start`, along with the hashed key of the next slot if there are more left
+    /// May need to re-execute blocks in order to rebuild the transaction's prestate, up to the amount given by `reexec`
+    pub async fn storage_range_at(
+        &self,
+        block_hash: H256,
+        tx_index: usize,
+        address: Address,
+        start: H256,
+        max_results: usize,
+        reexec: u32,
+    ) -> Result<(Vec<(H256, U256)>, Option<H256>), ChainError> {
+        let Some(block) = self.storage.get_block_by_hash(block_hash).await? else {
+            return Err(ChainError::Custom("Block not Found".to_string()));
+        };
+        if tx_index > block.body.transactions.len() {
+            return Err(ChainError::Custom(format!(
+                "Transaction index {tx_index} out of range for block {block_hash:#x}"
+            )));
+        }
+        // Obtain the state our Evm is based on along with the changes needed to reach the block's parent state
+        let (mut vm, base_hash) = self
+            .rebuild_parent_state_from_base(block.header.parent_hash, reexec)
+            .await?;
+        let Some(base_header) = self.storage.get_block_header_by_hash(base_hash)? else {
+            return Err(ChainError::ParentNotFound);
+        };
+        // Run the block until the transaction we want the state for
+        vm.rerun_block(&block, Some(tx_index))?;
+        let account_updates = vm.get_state_transitions()?;
+        // Walking the storage trie reads from the database, which must not block the runtime's worker threads
+        let storage = self.storage.clone();
+        tokio::task::spawn_blocking(move || {
+            storage.storage_range_with_updates(
+                base_header.state_root,
+                address,
+                &account_updates,
+                start,
+                max_results,
+            )
+        })
+        .await
+        .map_err(|_| ChainError::Custom("Unexpected Runtime Error".to_string()))?
+        .map_err(ChainError::from)
+    }
+
     /// Rebuild the parent state for a block given its parent hash, returning an `Evm` instance with all changes cached
     /// Will re-execute all ancestor block's which's state is not stored up to a maximum given by `reexec`
     async fn rebuild_parent_state(
@@ -89,6 +302,18 @@ impl Blockchain {
         parent_hash: H256,
         reexec: u32,
     ) -> Result<Evm, ChainError> {
+        Ok(self
+            .rebuild_parent_state_from_base(parent_hash, reexec)
+            .await?
+            .0)
+    }
+
+    /// Same as `rebuild_parent_state` but also returns the hash of the block which's stored state the `Evm` is based on
+    async fn rebuild_parent_state_from_base(
+        &self,
+        parent_hash: H256,
+        reexec: u32,
+    ) -> Result<(Evm, H256), ChainError> {
         // Check if we need to re-execute parent blocks
         let blocks_to_re_execute =
             get_missing_state_parents(parent_hash, &self.storage, reexec).await?;
@@ -112,7 +337,7 @@ impl Blockchain {
         for block in blocks_to_re_execute.iter().rev() {
             vm.rerun_block(block, None)?;
         }
-        Ok(vm)
+        Ok((vm, parent_hash))
     }
 }
 
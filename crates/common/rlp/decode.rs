@@ -8,6 +8,10 @@ use ethereum_types::{
 };
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
+/// Derives [`RLPDecode`] for a struct, decoding its fields from a list in declaration order.
+/// See the [`ethrex_rlp_derive`] crate for the attributes supported.
+pub use ethrex_rlp_derive::RLPDecode;
+
 /// Trait for decoding RLP encoded slices of data.
 /// See <https://ethereum.org/en/developers/docs/data-structures-and-encoding/rlp/#rlp-decoding> for more information.
 /// The [`decode_unfinished`](RLPDecode::decode_unfinished) method is used to decode an RLP encoded slice of data and return the decoded value along with the remaining bytes.
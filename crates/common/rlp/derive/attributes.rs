@@ -0,0 +1,218 @@
+use proc_macro2::Span;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, GenericParam, Generics, Ident, Index, LitStr,
+    Member, Path, Type, WherePredicate, ext::IdentExt, parse_quote, spanned::Spanned,
+};
+
+/// A struct deriving the traits, along with its parsed `#[rlp(..)]` attributes
+pub struct RlpStruct<'a> {
+    pub ident: &'a Ident,
+    pub generics: &'a Generics,
+    pub transparent: bool,
+    pub allow_trailing: bool,
+    pub fields: Vec<RlpField<'a>>,
+    pub style: Style,
+}
+
+/// How the struct's fields are declared, which determines how to build it
+pub enum Style {
+    Named,
+    Unnamed,
+    Unit,
+}
+
+pub struct RlpField<'a> {
+    pub member: Member,
+    /// Name used in decoding errors, the index for tuple structs
+    pub name: String,
+    pub ty: &'a Type,
+    pub kind: FieldKind,
+}
+
+pub enum FieldKind {
+    Regular,
+    Optional,
+    Flatten,
+    With(Path),
+    Skip,
+}
+
+impl<'a> RlpStruct<'a> {
+    pub fn parse(input: &'a DeriveInput) -> syn::Result<Self> {
+        let Data::Struct(data) = &input.data else {
+            return Err(Error::new(
+                input.ident.span(),
+                "RLP derives are only supported for structs",
+            ));
+        };
+
+        let mut transparent = false;
+        let mut allow_trailing = false;
+        for attr in rlp_attributes(&input.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("transparent") {
+                    transparent = true;
+                } else if meta.path.is_ident("allow_trailing") {
+                    allow_trailing = true;
+                } else {
+                    return Err(meta.error("unknown rlp container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let style = match &data.fields {
+            Fields::Named(_) => Style::Named,
+            Fields::Unnamed(_) => Style::Unnamed,
+            Fields::Unit => Style::Unit,
+        };
+        let fields = data
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let (member, name) = match &field.ident {
+                    Some(ident) => (Member::Named(ident.clone()), ident.unraw().to_string()),
+                    None => (Member::Unnamed(Index::from(index)), index.to_string()),
+                };
+                Ok(RlpField {
+                    member,
+                    name,
+                    ty: &field.ty,
+                    kind: FieldKind::parse(&field.attrs, &field.ty)?,
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        let rlp_struct = Self {
+            ident: &input.ident,
+            generics: &input.generics,
+            transparent,
+            allow_trailing,
+            fields,
+            style,
+        };
+        rlp_struct.validate()?;
+        Ok(rlp_struct)
+    }
+
+    fn validate(&self) -> syn::Result<()> {
+        if self.transparent {
+            if self.allow_trailing {
+                return Err(Error::new(
+                    self.ident.span(),
+                    "transparent structs can't allow trailing items",
+                ));
+            }
+            let encoded = self.encoded_fields().collect::<Vec<_>>();
+            let [field] = encoded.as_slice() else {
+                return Err(Error::new(
+                    self.ident.span(),
+                    "transparent structs must have exactly one field that isn't skipped",
+                ));
+            };
+            if !matches!(field.kind, FieldKind::Regular) {
+                return Err(Error::new(
+                    field.ty.span(),
+                    "the field of a transparent struct can't have rlp attributes",
+                ));
+            }
+            return Ok(());
+        }
+
+        // A missing item can only be told apart if all the ones after it are missing too
+        let mut optional_found = false;
+        for field in self.encoded_fields() {
+            match field.kind {
+                FieldKind::Optional => optional_found = true,
+                _ if optional_found => {
+                    return Err(Error::new(
+                        field.ty.span(),
+                        "only optional fields can follow an optional field",
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The fields that are part of the encoding
+    pub fn encoded_fields(&self) -> impl Iterator<Item = &RlpField<'a>> {
+        self.fields
+            .iter()
+            .filter(|field| !matches!(field.kind, FieldKind::Skip))
+    }
+
+    /// The struct's generics with `bound` required for each of its type parameters
+    pub fn bounded_generics(&self, bound: Path) -> Generics {
+        let mut generics = self.generics.clone();
+        let predicates: Vec<WherePredicate> = generics
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                GenericParam::Type(param) => {
+                    let ident = &param.ident;
+                    Some(parse_quote!(#ident: #bound))
+                }
+                _ => None,
+            })
+            .collect();
+        generics.make_where_clause().predicates.extend(predicates);
+        generics
+    }
+}
+
+impl FieldKind {
+    fn parse(attrs: &[Attribute], ty: &Type) -> syn::Result<Self> {
+        let mut kind = FieldKind::Regular;
+        for attr in rlp_attributes(attrs) {
+            attr.parse_nested_meta(|meta| {
+                let parsed = if meta.path.is_ident("optional") {
+                    if !is_option(ty) {
+                        return Err(Error::new(
+                            ty.span(),
+                            "optional fields must be of type `Option`",
+                        ));
+                    }
+                    FieldKind::Optional
+                } else if meta.path.is_ident("flatten") {
+                    FieldKind::Flatten
+                } else if meta.path.is_ident("skip") {
+                    FieldKind::Skip
+                } else if meta.path.is_ident("with") {
+                    let module: LitStr = meta.value()?.parse()?;
+                    FieldKind::With(module.parse()?)
+                } else {
+                    return Err(meta.error("unknown rlp field attribute"));
+                };
+                if !matches!(kind, FieldKind::Regular) {
+                    return Err(meta.error("a field can only have one rlp attribute"));
+                }
+                kind = parsed;
+                Ok(())
+            })?;
+        }
+        Ok(kind)
+    }
+}
+
+fn rlp_attributes(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("rlp"))
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Name of the local variable holding a decoded field
+pub fn field_variable(field: &RlpField) -> Ident {
+    Ident::new(&format!("field_{}", field.name), Span::call_site())
+}
@@ -0,0 +1,110 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, parse_quote};
+
+use crate::attributes::{FieldKind, RlpStruct, Style, field_variable};
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let rlp_struct = RlpStruct::parse(input)?;
+    let ident = rlp_struct.ident;
+    let generics = rlp_struct.bounded_generics(parse_quote!(::ethrex_rlp::decode::RLPDecode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Builds the struct out of the variables holding its decoded fields
+    let variables = rlp_struct
+        .fields
+        .iter()
+        .map(field_variable)
+        .collect::<Vec<_>>();
+    let members = rlp_struct.fields.iter().map(|field| &field.member);
+    let construct = match rlp_struct.style {
+        Style::Named => quote!(Self { #(#members: #variables),* }),
+        Style::Unnamed => quote!(Self(#(#variables),*)),
+        Style::Unit => quote!(Self),
+    };
+    let skipped = rlp_struct
+        .fields
+        .iter()
+        .filter(|field| matches!(field.kind, FieldKind::Skip))
+        .map(|field| {
+            let variable = field_variable(field);
+            quote!(let #variable = ::core::default::Default::default();)
+        })
+        .collect::<Vec<_>>();
+
+    if rlp_struct.transparent {
+        // Validated to have a single encoded field
+        let (variable, ty) = rlp_struct
+            .encoded_fields()
+            .map(|field| (field_variable(field), field.ty))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        return Ok(quote! {
+            #[automatically_derived]
+            impl #impl_generics ::ethrex_rlp::decode::RLPDecode for #ident #ty_generics #where_clause {
+                fn decode_unfinished(
+                    rlp: &[u8],
+                ) -> ::core::result::Result<(Self, &[u8]), ::ethrex_rlp::error::RLPDecodeError> {
+                    #(let (#variable, rest) = <#ty as ::ethrex_rlp::decode::RLPDecode>::decode_unfinished(rlp)?;)*
+                    #(#skipped)*
+                    ::core::result::Result::Ok((#construct, rest))
+                }
+            }
+        });
+    }
+
+    let decoded = rlp_struct.encoded_fields().map(|field| {
+        let variable = field_variable(field);
+        let name = &field.name;
+        let ty = field.ty;
+        match &field.kind {
+            FieldKind::Regular => quote! {
+                let (#variable, decoder) = decoder.decode_field::<#ty>(#name)?;
+            },
+            FieldKind::Optional => quote! {
+                let (#variable, decoder): (#ty, _) = decoder.decode_optional_field();
+            },
+            FieldKind::Flatten => quote! {
+                let (#variable, decoder) =
+                    <#ty as ::ethrex_rlp::structs::RLPDecodeFields>::decode_fields(decoder)?;
+            },
+            FieldKind::With(module) => quote! {
+                let (#variable, decoder) =
+                    decoder.decode_field_with::<#ty>(#name, #module::decode_unfinished)?;
+            },
+            FieldKind::Skip => quote!(),
+        }
+    });
+    let finish = if rlp_struct.allow_trailing {
+        quote!(decoder.finish_unchecked())
+    } else {
+        quote!(decoder.finish()?)
+    };
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::ethrex_rlp::structs::RLPDecodeFields for #ident #ty_generics #where_clause {
+            fn decode_fields<'rlp>(
+                decoder: ::ethrex_rlp::structs::Decoder<'rlp>,
+            ) -> ::core::result::Result<
+                (Self, ::ethrex_rlp::structs::Decoder<'rlp>),
+                ::ethrex_rlp::error::RLPDecodeError,
+            > {
+                #(#decoded)*
+                #(#skipped)*
+                ::core::result::Result::Ok((#construct, decoder))
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::ethrex_rlp::decode::RLPDecode for #ident #ty_generics #where_clause {
+            fn decode_unfinished(
+                rlp: &[u8],
+            ) -> ::core::result::Result<(Self, &[u8]), ::ethrex_rlp::error::RLPDecodeError> {
+                let decoder = ::ethrex_rlp::structs::Decoder::new(rlp)?;
+                let (decoded, decoder) =
+                    <Self as ::ethrex_rlp::structs::RLPDecodeFields>::decode_fields(decoder)?;
+                ::core::result::Result::Ok((decoded, #finish))
+            }
+        }
+    })
+}
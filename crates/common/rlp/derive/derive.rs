@@ -0,0 +1,59 @@
+//! Derive macros for the `RLPEncode` and `RLPDecode` traits of `ethrex-rlp`.
+//!
+//! Structs are encoded as a list of their fields in declaration order, the same way the
+//! `Encoder` and `Decoder` helpers of `ethrex_rlp::structs` do. The macros are re-exported by
+//! `ethrex_rlp::encode` and `ethrex_rlp::decode` next to the traits they derive.
+//!
+//! ```ignore
+//! use ethrex_rlp::{decode::RLPDecode, encode::RLPEncode};
+//!
+//! #[derive(RLPEncode, RLPDecode)]
+//! #[rlp(allow_trailing)]
+//! struct Ping {
+//!     version: u8,
+//!     from: Endpoint,
+//!     to: Endpoint,
+//!     expiration: u64,
+//!     #[rlp(optional)]
+//!     enr_seq: Option<u64>,
+//! }
+//! ```
+//!
+//! # Container attributes
+//!
+//! - `#[rlp(transparent)]`: for newtypes, the struct is encoded as its only field instead of as a list.
+//! - `#[rlp(allow_trailing)]`: extra items at the end of the list are ignored when decoding
+//!   instead of failing, as some p2p messages require for forward compatibility.
+//!
+//! # Field attributes
+//!
+//! - `#[rlp(optional)]`: the field is an `Option` that is omitted from the list when it's `None`.
+//!   Only trailing fields can be optional, as a missing field can't be told apart otherwise.
+//! - `#[rlp(flatten)]`: the fields of the field's type, which must derive the traits too, are
+//!   encoded in the struct's list instead of in a nested list.
+//! - `#[rlp(with = "module")]`: the field is encoded with `module::encode(&T, &mut dyn BufMut)`
+//!   and decoded with `module::decode_unfinished(&[u8]) -> Result<(T, &[u8]), RLPDecodeError>`.
+//! - `#[rlp(skip)]`: the field isn't encoded and is set to its `Default` value when decoding.
+
+mod attributes;
+mod decode;
+mod encode;
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+#[proc_macro_derive(RLPEncode, attributes(rlp))]
+pub fn derive_rlp_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    encode::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(RLPDecode, attributes(rlp))]
+pub fn derive_rlp_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    decode::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
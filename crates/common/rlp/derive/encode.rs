@@ -0,0 +1,73 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, parse_quote};
+
+use crate::attributes::{FieldKind, RlpStruct};
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let rlp_struct = RlpStruct::parse(input)?;
+    let ident = rlp_struct.ident;
+    let generics = rlp_struct.bounded_generics(parse_quote!(::ethrex_rlp::encode::RLPEncode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    if rlp_struct.transparent {
+        // Validated to have a single encoded field
+        let member = rlp_struct
+            .encoded_fields()
+            .map(|field| &field.member)
+            .collect::<Vec<_>>();
+        return Ok(quote! {
+            #[automatically_derived]
+            impl #impl_generics ::ethrex_rlp::encode::RLPEncode for #ident #ty_generics #where_clause {
+                fn encode(&self, buf: &mut dyn ::ethrex_rlp::__private::BufMut) {
+                    #(::ethrex_rlp::encode::RLPEncode::encode(&self.#member, buf))*
+                }
+
+                fn length(&self) -> usize {
+                    #(::ethrex_rlp::encode::RLPEncode::length(&self.#member))*
+                }
+            }
+        });
+    }
+
+    // Each field is chained onto the encoder of the previous ones
+    let encoder = rlp_struct
+        .encoded_fields()
+        .fold(quote!(encoder), |encoder, field| {
+            let member = &field.member;
+            match &field.kind {
+                FieldKind::Regular => quote!(#encoder.encode_field(&self.#member)),
+                FieldKind::Optional => quote!(#encoder.encode_optional_field(&self.#member)),
+                FieldKind::Flatten => quote! {
+                    ::ethrex_rlp::structs::RLPEncodeFields::encode_fields(&self.#member, #encoder)
+                },
+                FieldKind::With(module) => {
+                    quote!(#encoder.encode_field_with(&self.#member, #module::encode))
+                }
+                FieldKind::Skip => encoder,
+            }
+        });
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::ethrex_rlp::structs::RLPEncodeFields for #ident #ty_generics #where_clause {
+            fn encode_fields<'rlp>(
+                &self,
+                encoder: ::ethrex_rlp::structs::Encoder<'rlp>,
+            ) -> ::ethrex_rlp::structs::Encoder<'rlp> {
+                #encoder
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::ethrex_rlp::encode::RLPEncode for #ident #ty_generics #where_clause {
+            fn encode(&self, buf: &mut dyn ::ethrex_rlp::__private::BufMut) {
+                ::ethrex_rlp::structs::RLPEncodeFields::encode_fields(
+                    self,
+                    ::ethrex_rlp::structs::Encoder::new(buf),
+                )
+                .finish();
+            }
+        }
+    })
+}
@@ -5,6 +5,10 @@ use tinyvec::ArrayVec;
 
 use super::constants::RLP_NULL;
 
+/// Derives [`RLPEncode`] for a struct, encoding its fields as a list in declaration order.
+/// See the [`ethrex_rlp_derive`] crate for the attributes supported.
+pub use ethrex_rlp_derive::RLPEncode;
+
 /// Function for encoding a value to RLP.
 /// For encoding the value into a buffer directly, use [`RLPEncode::encode`].
 pub fn encode<T: RLPEncode>(value: T) -> Vec<u8> {
@@ -3,3 +3,10 @@ pub mod decode;
 pub mod encode;
 pub mod error;
 pub mod structs;
+
+#[doc(hidden)]
+pub mod __private {
+    // Used by the code generated by the derive macros, so that crates using them don't need to
+    // depend on `bytes`
+    pub use bytes::BufMut;
+}
@@ -66,6 +66,21 @@ impl<'a> Decoder<'a> {
         Ok((field, updated_self))
     }
 
+    /// Decodes the next field with the given function instead of its [`RLPDecode`] implementation
+    pub fn decode_field_with<T>(
+        self,
+        name: &str,
+        decode: impl FnOnce(&'a [u8]) -> Result<(T, &'a [u8]), RLPDecodeError>,
+    ) -> Result<(T, Self), RLPDecodeError> {
+        let (field, rest) =
+            decode(self.payload).map_err(|err| field_decode_error::<T>(name, err))?;
+        let updated_self = Self {
+            payload: rest,
+            ..self
+        };
+        Ok((field, updated_self))
+    }
+
     /// Returns the next field without decoding it, i.e. the payload bytes including its prefix.
     pub fn get_encoded_item(self) -> Result<(Vec<u8>, Self), RLPDecodeError> {
         match get_item_with_prefix(self.payload) {
@@ -189,6 +204,16 @@ impl<'a> Encoder<'a> {
         self
     }
 
+    /// Stores a field encoded with the given function instead of its [`RLPEncode`] implementation.
+    pub fn encode_field_with<T: ?Sized>(
+        mut self,
+        value: &T,
+        encode: impl FnOnce(&T, &mut dyn BufMut),
+    ) -> Self {
+        encode(value, &mut self.temp_buf);
+        self
+    }
+
     /// If `Some`, stores a field to be encoded, else does nothing.
     pub fn encode_optional_field<T: RLPEncode>(mut self, opt_value: &Option<T>) -> Self {
         if let Some(value) = opt_value {
@@ -228,6 +253,19 @@ impl<'a> Encoder<'a> {
     }
 }
 
+/// Encodes the fields of a struct without the list surrounding them, so they can be flattened
+/// into the list of another struct with `#[rlp(flatten)]`.
+/// Implemented by `#[derive(RLPEncode)]` for structs that aren't `#[rlp(transparent)]`.
+pub trait RLPEncodeFields {
+    fn encode_fields<'a>(&self, encoder: Encoder<'a>) -> Encoder<'a>;
+}
+
+/// Decodes the fields of a struct from the list of another struct, the counterpart of [`RLPEncodeFields`].
+/// Implemented by `#[derive(RLPDecode)]` for structs that aren't `#[rlp(transparent)]`.
+pub trait RLPDecodeFields: Sized {
+    fn decode_fields<'a>(decoder: Decoder<'a>) -> Result<(Self, Decoder<'a>), RLPDecodeError>;
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
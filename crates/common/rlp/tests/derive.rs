@@ -0,0 +1,200 @@
+use bytes::{BufMut, Bytes};
+use ethrex_rlp::{
+    decode::RLPDecode,
+    encode::RLPEncode,
+    error::RLPDecodeError,
+    structs::{Decoder, Encoder},
+};
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct Simple {
+    a: u8,
+    b: u16,
+}
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct Tuple(u64, Bytes);
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+#[rlp(transparent)]
+struct Wrapper(Vec<u64>);
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct WithOptionals {
+    a: u8,
+    #[rlp(optional)]
+    b: Option<u16>,
+    #[rlp(optional)]
+    c: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct Flattened {
+    first: u8,
+    #[rlp(flatten)]
+    inner: Simple,
+    last: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct Nested {
+    first: u8,
+    inner: Simple,
+}
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+#[rlp(allow_trailing)]
+struct Forward {
+    a: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct Skipping {
+    a: u8,
+    #[rlp(skip)]
+    cached: Option<u64>,
+    b: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct Generic<T> {
+    value: T,
+}
+
+/// Encodes a number as a string of its digits
+mod as_string {
+    use super::*;
+
+    pub fn encode(value: &u64, buf: &mut dyn BufMut) {
+        value.to_string().encode(buf);
+    }
+
+    pub fn decode_unfinished(rlp: &[u8]) -> Result<(u64, &[u8]), RLPDecodeError> {
+        let (string, rest) = String::decode_unfinished(rlp)?;
+        let value = string.parse().map_err(|_| RLPDecodeError::MalformedData)?;
+        Ok((value, rest))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, RLPEncode, RLPDecode)]
+struct CustomCodec {
+    #[rlp(with = "as_string")]
+    value: u64,
+}
+
+fn assert_roundtrip<T: RLPEncode + RLPDecode + PartialEq + std::fmt::Debug>(value: T) {
+    let encoded = value.encode_to_vec();
+    assert_eq!(encoded.len(), value.length());
+    assert_eq!(T::decode(&encoded).unwrap(), value);
+}
+
+#[test]
+fn derive_matches_manual_encoding() {
+    let value = Simple { a: 61, b: 75 };
+    let mut manual = Vec::new();
+    Encoder::new(&mut manual)
+        .encode_field(&value.a)
+        .encode_field(&value.b)
+        .finish();
+    assert_eq!(value.encode_to_vec(), manual);
+    assert_eq!(manual, vec![0xc2, 61, 75]);
+    assert_roundtrip(value);
+
+    let tuple = Tuple(1024, Bytes::from_static(b"rlp"));
+    assert_eq!(
+        tuple.encode_to_vec(),
+        (1024u64, Bytes::from_static(b"rlp")).encode_to_vec()
+    );
+    assert_roundtrip(tuple);
+}
+
+#[test]
+fn derive_transparent() {
+    let value = Wrapper(vec![1, 2, 3]);
+    assert_eq!(value.encode_to_vec(), vec![1u64, 2, 3].encode_to_vec());
+    assert_roundtrip(value);
+}
+
+#[test]
+fn derive_optional_fields() {
+    let none = WithOptionals {
+        a: 1,
+        b: None,
+        c: None,
+    };
+    assert_eq!(none.encode_to_vec(), vec![0xc1, 1]);
+    assert_roundtrip(none);
+
+    let some = WithOptionals {
+        a: 1,
+        b: Some(2),
+        c: None,
+    };
+    assert_eq!(some.encode_to_vec(), vec![0xc2, 1, 2]);
+    assert_roundtrip(some);
+
+    assert_roundtrip(WithOptionals {
+        a: 1,
+        b: Some(2),
+        c: Some(3),
+    });
+}
+
+#[test]
+fn derive_flatten_and_nested() {
+    let flattened = Flattened {
+        first: 1,
+        inner: Simple { a: 2, b: 3 },
+        last: 4,
+    };
+    assert_eq!(flattened.encode_to_vec(), vec![0xc4, 1, 2, 3, 4]);
+    assert_roundtrip(flattened);
+
+    let nested = Nested {
+        first: 1,
+        inner: Simple { a: 2, b: 3 },
+    };
+    assert_eq!(nested.encode_to_vec(), vec![0xc4, 1, 0xc2, 2, 3]);
+    assert_roundtrip(nested);
+}
+
+#[test]
+fn derive_trailing_items() {
+    let encoded = (1u8, 2u8).encode_to_vec();
+    assert_eq!(Forward::decode(&encoded).unwrap(), Forward { a: 1 });
+    assert!(Simple::decode(&(1u8, 2u16, 3u8).encode_to_vec()).is_err());
+}
+
+#[test]
+fn derive_skip_and_generics() {
+    let value = Skipping {
+        a: 1,
+        cached: Some(10),
+        b: 2,
+    };
+    let encoded = value.encode_to_vec();
+    assert_eq!(encoded, vec![0xc2, 1, 2]);
+    assert_eq!(
+        Skipping::decode(&encoded).unwrap(),
+        Skipping {
+            a: 1,
+            cached: None,
+            b: 2
+        }
+    );
+
+    assert_roundtrip(Generic { value: 7u32 });
+    assert_roundtrip(Generic {
+        value: Simple { a: 1, b: 2 },
+    });
+}
+
+#[test]
+fn derive_custom_codec() {
+    let value = CustomCodec { value: 1234 };
+    let decoder = Decoder::new(&value.encode_to_vec()).unwrap();
+    let (string, decoder): (String, _) = decoder.decode_field("value").unwrap();
+    decoder.finish().unwrap();
+    assert_eq!(string, "1234");
+    assert_roundtrip(value);
+}
@@ -44,7 +44,7 @@ pub struct CallTraceFrame {
     pub logs: Vec<CallLog>,
 }
 
-#[derive(Serialize, Debug, Default)]
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum CallType {
     #[default]
     CALL,
@@ -0,0 +1,66 @@
+//! Measures committing a state trie after a block updated many of its accounts, which is where the
+//! `parallel` feature hashes the subtries below the upper branches concurrently.
+//! Run it with and without `--features parallel` to compare against committing serially.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use ethereum_types::H256;
+use ethrex_trie::{InMemoryTrieDB, NodeHash, Trie};
+
+type TrieMap = Arc<Mutex<BTreeMap<NodeHash, Vec<u8>>>>;
+
+/// Accounts in the state before applying the block
+const STATE_ACCOUNTS: usize = 50_000;
+
+fn random_bytes() -> Vec<u8> {
+    H256::random().as_bytes().to_vec()
+}
+
+/// Builds a state trie with random accounts, returning its database and root
+fn build_state() -> (TrieMap, H256) {
+    let map = TrieMap::default();
+    let mut trie = Trie::new(Box::new(InMemoryTrieDB::new(map.clone())));
+    for _ in 0..STATE_ACCOUNTS {
+        trie.insert(random_bytes(), random_bytes()).unwrap();
+    }
+    let root = trie.hash().unwrap();
+    (map, root)
+}
+
+fn state_root_benchmark(c: &mut Criterion) {
+    let (map, root) = build_state();
+
+    let mut group = c.benchmark_group("State root");
+    group.sample_size(10);
+    for touched_accounts in [1_000, 5_000] {
+        let updates: Vec<_> = (0..touched_accounts)
+            .map(|_| (random_bytes(), random_bytes()))
+            .collect();
+        group.bench_with_input(
+            BenchmarkId::new("commit", touched_accounts),
+            &updates,
+            |b, updates| {
+                // Only committing the updated trie is measured, the updates are applied beforehand
+                b.iter_batched(
+                    || {
+                        let mut trie = Trie::open(Box::new(InMemoryTrieDB::new(map.clone())), root);
+                        for (path, value) in updates {
+                            trie.insert(path.clone(), value.clone()).unwrap();
+                        }
+                        trie
+                    },
+                    |mut trie| black_box(trie.collect_changes_since_last_hash().0),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, state_root_benchmark);
+criterion_main!(benches);
@@ -9,6 +9,11 @@ use std::{
 
 pub trait TrieDB: Send + Sync {
     fn get(&self, key: NodeHash) -> Result<Option<Vec<u8>>, TrieError>;
+    /// Retrieves a node given its hash and the nibbles of its path from the root of the trie.
+    /// Databases keying nodes by path rely on this, while the rest can ignore the path
+    fn get_at_path(&self, _path: &[u8], key: NodeHash) -> Result<Option<Vec<u8>>, TrieError> {
+        self.get(key)
+    }
     fn put_batch(&self, key_values: Vec<(NodeHash, Vec<u8>)>) -> Result<(), TrieError>;
     // TODO: replace putbatch with this function.
     fn put_batch_no_alloc(&self, key_values: &[(NodeHash, Node)]) -> Result<(), TrieError> {
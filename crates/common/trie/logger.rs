@@ -32,9 +32,9 @@ impl TrieLogger {
     }
 }
 
-impl TrieDB for TrieLogger {
-    fn get(&self, key: NodeHash) -> Result<Option<Vec<u8>>, TrieError> {
-        let result = self.inner_db.get(key)?;
+impl TrieLogger {
+    /// Adds the node read from the inner database to the witness
+    fn record(&self, result: Option<Vec<u8>>) -> Result<Option<Vec<u8>>, TrieError> {
         if let Some(result) = result.as_ref()
             && let Ok(decoded) = Node::decode(result)
         {
@@ -43,6 +43,16 @@ impl TrieDB for TrieLogger {
         }
         Ok(result)
     }
+}
+
+impl TrieDB for TrieLogger {
+    fn get(&self, key: NodeHash) -> Result<Option<Vec<u8>>, TrieError> {
+        self.record(self.inner_db.get(key)?)
+    }
+
+    fn get_at_path(&self, path: &[u8], key: NodeHash) -> Result<Option<Vec<u8>>, TrieError> {
+        self.record(self.inner_db.get_at_path(path, key)?)
+    }
 
     fn put(&self, key: NodeHash, value: Vec<u8>) -> Result<(), TrieError> {
         self.inner_db.put(key, value)
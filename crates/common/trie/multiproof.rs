@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use ethereum_types::H256;
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    EMPTY_TRIE_HASH, NodeRLP, PathRLP, Trie, TrieError, ValueRLP,
+    nibbles::Nibbles,
+    node::{Node, NodeRef},
+    node_hash::NodeHash,
+};
+
+/// Proof of the values of an arbitrary set of paths of a trie.
+/// Contains every node traversed when looking up the paths exactly once, so the nodes shared by
+/// the paths aren't repeated as they would be when joining their individual proofs.
+/// Besides reading the values, the proof allows updating them, see [`PartialTrie`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MultiProof {
+    /// Encoded nodes, starting with the root and following a depth-first traversal
+    pub nodes: Vec<NodeRLP>,
+}
+
+impl Trie {
+    /// Obtains a multiproof for the given paths, which proves the value or the absence of each of them.
+    /// Besides the nodes traversed, it includes the siblings needed to restructure the trie if the
+    /// paths were removed, so the proof can be used to compute the root after updating the paths.
+    pub fn get_multiproof(&self, paths: &[PathRLP]) -> Result<MultiProof, TrieError> {
+        let mut proof = MultiProof::default();
+        if !self.root.is_valid() {
+            return Ok(proof);
+        }
+        let root = self
+            .root
+            .get_node_at(self.db(), &[])?
+            .ok_or(TrieError::InconsistentTree)?;
+        // The root is always included, even if its encoding is shorter than a hash
+        proof.nodes.push(root.encode_raw());
+        let paths = paths.iter().map(|path| Nibbles::from_bytes(path)).collect();
+        proof.collect_nodes(self, &root, Nibbles::default(), paths)?;
+        Ok(proof)
+    }
+}
+
+impl MultiProof {
+    /// Traverses the subtrie of `node`, located at `position`, along the given paths, adding the
+    /// nodes found along the way
+    fn collect_nodes(
+        &mut self,
+        trie: &Trie,
+        node: &Node,
+        position: Nibbles,
+        paths: Vec<Nibbles>,
+    ) -> Result<(), TrieError> {
+        match node {
+            Node::Branch(branch) => {
+                let mut paths_by_choice: [Vec<Nibbles>; 16] = Default::default();
+                for mut path in paths {
+                    // Paths ending at the branch are stored in its value
+                    if let Some(choice) = path.next_choice() {
+                        paths_by_choice[choice].push(path);
+                    }
+                }
+                let mut untraversed = Vec::new();
+                for (choice, (child_ref, paths)) in
+                    branch.choices.iter().zip(paths_by_choice).enumerate()
+                {
+                    if !child_ref.is_valid() {
+                        continue;
+                    }
+                    let child_position = position.append_new(choice as u8);
+                    if paths.is_empty() {
+                        untraversed.push((child_position, child_ref));
+                        continue;
+                    }
+                    let child = self.add_node(trie, &child_position, child_ref)?;
+                    self.collect_nodes(trie, &child, child_position, paths)?;
+                }
+                // If all the traversed children were removed, the branch would be replaced by its
+                // only remaining child, which needs to be known to restructure the trie
+                if let [(sibling_position, sibling)] = untraversed.as_slice() {
+                    self.add_node(trie, sibling_position, sibling)?;
+                }
+            }
+            Node::Extension(extension) => {
+                let paths = paths
+                    .into_iter()
+                    .filter_map(|mut path| path.skip_prefix(&extension.prefix).then_some(path))
+                    .collect::<Vec<_>>();
+                if !paths.is_empty() {
+                    let child_position = position.concat(extension.prefix.clone());
+                    let child = self.add_node(trie, &child_position, &extension.child)?;
+                    self.collect_nodes(trie, &child, child_position, paths)?;
+                }
+            }
+            Node::Leaf(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Fetches the referenced node, adding it to the proof unless it's inlined in its parent
+    fn add_node(
+        &mut self,
+        trie: &Trie,
+        position: &Nibbles,
+        node_ref: &NodeRef,
+    ) -> Result<Node, TrieError> {
+        let node = node_ref
+            .get_node_at(trie.db(), position.as_ref())?
+            .ok_or(TrieError::InconsistentTree)?;
+        if let NodeHash::Hashed(_) = node_ref.compute_hash() {
+            self.nodes.push(node.encode_raw());
+        }
+        Ok(node)
+    }
+
+    /// Reconstructs the partial trie with the given root out of the proof's nodes
+    pub fn into_partial_trie(self, root: H256) -> Result<PartialTrie, TrieError> {
+        if root == *EMPTY_TRIE_HASH {
+            return Ok(PartialTrie(Trie::stateless()));
+        }
+        let nodes: BTreeMap<H256, NodeRLP> = self
+            .nodes
+            .into_iter()
+            .map(|node| (H256::from_slice(&Keccak256::digest(&node)), node))
+            .collect();
+        if !nodes.contains_key(&root) {
+            return Err(TrieError::Verify(format!(
+                "proof doesn't contain the root node {root:#x}"
+            )));
+        }
+        let mut trie = Trie::stateless();
+        trie.root = Trie::get_embedded_root(&nodes, root)?;
+        Ok(PartialTrie(trie))
+    }
+
+    /// Verifies the proof against the given root, returning the value of each path, or `None`
+    /// if the proof shows it isn't part of the trie
+    pub fn verify(
+        &self,
+        root: H256,
+        paths: &[PathRLP],
+    ) -> Result<Vec<Option<ValueRLP>>, TrieError> {
+        let trie = self.clone().into_partial_trie(root)?;
+        paths.iter().map(|path| trie.get(path)).collect()
+    }
+}
+
+/// Trie containing only the nodes of a [`MultiProof`].
+/// Paths covered by the proof can be read and updated as with a full trie, in order to compute
+/// the new root, while accessing any other path fails.
+pub struct PartialTrie(Trie);
+
+impl PartialTrie {
+    /// Retrieves the value of a path covered by the proof
+    pub fn get(&self, path: &PathRLP) -> Result<Option<ValueRLP>, TrieError> {
+        self.0.get(path).map_err(not_covered)
+    }
+
+    /// Inserts a value into a path covered by the proof
+    pub fn insert(&mut self, path: PathRLP, value: ValueRLP) -> Result<(), TrieError> {
+        self.0.insert(path, value).map_err(not_covered)
+    }
+
+    /// Removes the value of a path covered by the proof, returning it if it was part of the trie
+    pub fn remove(&mut self, path: &PathRLP) -> Result<Option<ValueRLP>, TrieError> {
+        self.0.remove(path).map_err(not_covered)
+    }
+
+    /// Returns the root of the trie, taking into account the updates made
+    pub fn hash(&self) -> H256 {
+        self.0.hash_no_commit()
+    }
+}
+
+/// Nodes missing from the proof are looked up in an empty database, which makes the trie inconsistent
+fn not_covered(err: TrieError) -> TrieError {
+    match err {
+        TrieError::InconsistentTree => {
+            TrieError::Verify("path is not covered by the proof".to_string())
+        }
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn key(index: u64) -> PathRLP {
+        Keccak256::digest(index.to_be_bytes()).to_vec()
+    }
+
+    fn trie_with_keys(count: u64) -> Trie {
+        let mut trie = Trie::default();
+        for index in 0..count {
+            trie.insert(key(index), index.to_be_bytes().to_vec())
+                .unwrap();
+        }
+        trie.hash().unwrap();
+        trie
+    }
+
+    #[test]
+    fn multiproof_proves_values_and_absence() {
+        let trie = trie_with_keys(500);
+        let root = trie.hash_no_commit();
+        // Keys from 500 onwards aren't part of the trie
+        let paths: Vec<_> = (0..20).map(|i| key(i * 2)).chain([key(1001)]).collect();
+        let proof = trie.get_multiproof(&paths).unwrap();
+
+        let values = proof.verify(root, &paths).unwrap();
+        for (path, value) in paths.iter().zip(values) {
+            assert_eq!(value, trie.get(path).unwrap());
+        }
+        // Nodes aren't repeated
+        assert_eq!(
+            proof.nodes.iter().collect::<HashSet<_>>().len(),
+            proof.nodes.len()
+        );
+
+        // The multiproof is smaller than the separate proofs of each path
+        let separate_proofs: usize = paths
+            .iter()
+            .map(|path| trie.get_proof(path).unwrap().len())
+            .sum();
+        assert!(proof.nodes.len() < separate_proofs);
+    }
+
+    #[test]
+    fn multiproof_rejects_invalid_proofs() {
+        let trie = trie_with_keys(100);
+        let root = trie.hash_no_commit();
+        let paths = vec![key(1), key(2)];
+        let proof = trie.get_multiproof(&paths).unwrap();
+
+        // Wrong root
+        assert!(proof.verify(H256::repeat_byte(1), &paths).is_err());
+        // Missing the first node traversed after the root
+        let mut incomplete = proof.clone();
+        incomplete.nodes.remove(1);
+        assert!(incomplete.verify(root, &paths).is_err());
+        // Paths outside of the proof
+        assert!(proof.verify(root, &[key(3)]).is_err());
+    }
+
+    #[test]
+    fn multiproof_updates_match_full_trie() {
+        let mut trie = trie_with_keys(300);
+        let root = trie.hash_no_commit();
+        let removed: Vec<_> = (0..30).map(key).collect();
+        let updated: Vec<_> = (30..60).map(key).collect();
+        let inserted: Vec<_> = (1000..1030).map(key).collect();
+        let paths: Vec<_> = removed
+            .iter()
+            .chain(&updated)
+            .chain(&inserted)
+            .cloned()
+            .collect();
+        let mut partial_trie = trie
+            .get_multiproof(&paths)
+            .unwrap()
+            .into_partial_trie(root)
+            .unwrap();
+
+        for path in &removed {
+            assert!(partial_trie.remove(path).unwrap().is_some());
+            trie.remove(path).unwrap();
+        }
+        for path in updated.iter().chain(&inserted) {
+            partial_trie.insert(path.clone(), vec![0xff]).unwrap();
+            trie.insert(path.clone(), vec![0xff]).unwrap();
+        }
+        assert_eq!(partial_trie.hash(), trie.hash().unwrap());
+    }
+
+    #[test]
+    fn multiproof_collapses_branches_after_removals() {
+        let mut trie = trie_with_keys(3);
+        let root = trie.hash_no_commit();
+        let paths: Vec<_> = (0..3).map(key).collect();
+        let mut partial_trie = trie
+            .get_multiproof(&paths[..2])
+            .unwrap()
+            .into_partial_trie(root)
+            .unwrap();
+
+        // Removing the proven keys collapses the trie into the remaining one
+        for path in &paths[..2] {
+            partial_trie.remove(path).unwrap();
+            trie.remove(path).unwrap();
+        }
+        assert_eq!(partial_trie.hash(), trie.hash().unwrap());
+    }
+
+    #[test]
+    fn multiproof_empty_trie() {
+        let trie = Trie::default();
+        let proof = trie.get_multiproof(&[key(0)]).unwrap();
+        assert!(proof.nodes.is_empty());
+        assert_eq!(
+            proof.verify(*EMPTY_TRIE_HASH, &[key(0)]).unwrap(),
+            vec![None]
+        );
+    }
+}
@@ -8,21 +8,47 @@ use ethrex_rlp::{
 };
 
 /// Struct representing a list of nibbles (half-bytes)
-#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Default)]
 pub struct Nibbles {
     pub(crate) data: Vec<u8>,
+    /// Amount of nibbles at the start of `data` consumed while traversing a trie, which make up
+    /// the path of the node being visited. They aren't part of the nibbles otherwise
+    already_consumed: usize,
+}
+
+impl PartialEq for Nibbles {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl Eq for Nibbles {}
+
+impl PartialOrd for Nibbles {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Nibbles {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.as_ref().cmp(other.as_ref())
+    }
 }
 
 impl std::hash::Hash for Nibbles {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.data.hash(state);
+        self.as_ref().hash(state);
     }
 }
 
 impl Nibbles {
     /// Create `Nibbles` from  hex-encoded nibbles
     pub const fn from_hex(hex: Vec<u8>) -> Self {
-        Self { data: hex }
+        Self {
+            data: hex,
+            already_consumed: 0,
+        }
     }
 
     /// Splits incoming bytes into nibbles and appends the leaf flag (a 16 nibble at the end)
@@ -40,24 +66,24 @@ impl Nibbles {
             data.push(16);
         }
 
-        Self { data }
+        Self::from_hex(data)
     }
 
     /// Returns the amount of nibbles
     pub fn len(&self) -> usize {
-        self.data.len()
+        self.data.len() - self.already_consumed
     }
 
     /// Returns true if there are no nibbles
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.len() == 0
     }
 
     /// If `prefix` is a prefix of self, move the offset after
     /// the prefix and return true, otherwise return false.
     pub fn skip_prefix(&mut self, prefix: &Nibbles) -> bool {
-        if self.len() >= prefix.len() && &self.data[..prefix.len()] == prefix.as_ref() {
-            self.data = self.data[prefix.len()..].to_vec();
+        if self.len() >= prefix.len() && &self.as_ref()[..prefix.len()] == prefix.as_ref() {
+            self.already_consumed += prefix.len();
             true
         } else {
             false
@@ -67,9 +93,9 @@ impl Nibbles {
     /// Compares self to another, comparing prefixes only in case of unequal lengths.
     pub fn compare_prefix(&self, prefix: &Nibbles) -> cmp::Ordering {
         if self.len() > prefix.len() {
-            self.data[..prefix.len()].cmp(&prefix.data)
+            self.as_ref()[..prefix.len()].cmp(prefix.as_ref())
         } else {
-            self.data[..].cmp(&prefix.data[..self.len()])
+            self.as_ref().cmp(&prefix.as_ref()[..self.len()])
         }
     }
 
@@ -85,7 +111,9 @@ impl Nibbles {
     /// Removes and returns the first nibble
     #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<u8> {
-        (!self.is_empty()).then(|| self.data.remove(0))
+        let nibble = *self.data.get(self.already_consumed)?;
+        self.already_consumed += 1;
+        Some(nibble)
     }
 
     /// Removes and returns the first nibble if it is a suitable choice index (aka < 16)
@@ -93,14 +121,24 @@ impl Nibbles {
         self.next().filter(|choice| *choice < 16).map(usize::from)
     }
 
-    /// Returns the nibbles after the given offset
+    /// Returns the nibbles consumed so far, which are the path of the node being visited when
+    /// traversing a trie from its root
+    pub fn consumed(&self) -> &[u8] {
+        &self.data[..self.already_consumed]
+    }
+
+    /// Returns the nibbles after the given offset, treating the ones before it as consumed
     pub fn offset(&self, offset: usize) -> Nibbles {
-        self.slice(offset, self.len())
+        assert!(offset <= self.len(), "offset out of range");
+        Nibbles {
+            data: self.data.clone(),
+            already_consumed: self.already_consumed + offset,
+        }
     }
 
     /// Returns the nibbles beween the start and end indexes
     pub fn slice(&self, start: usize, end: usize) -> Nibbles {
-        Nibbles::from_hex(self.data[start..end].to_vec())
+        Nibbles::from_hex(self.as_ref()[start..end].to_vec())
     }
 
     /// Extends the nibbles with another list of nibbles
@@ -110,12 +148,12 @@ impl Nibbles {
 
     /// Return the nibble at the given index, will panic if the index is out of range
     pub fn at(&self, i: usize) -> usize {
-        self.data[i] as usize
+        self.as_ref()[i] as usize
     }
 
     /// Inserts a nibble at the start
     pub fn prepend(&mut self, nibble: u8) {
-        self.data.insert(0, nibble);
+        self.data.insert(self.already_consumed, nibble);
     }
 
     /// Inserts a nibble at the end
@@ -128,10 +166,11 @@ impl Nibbles {
     pub fn encode_compact(&self) -> Vec<u8> {
         let mut compact = vec![];
         let is_leaf = self.is_leaf();
+        let data = self.as_ref();
         let mut hex = if is_leaf {
-            &self.data[0..self.data.len() - 1]
+            &data[0..data.len() - 1]
         } else {
-            &self.data[0..]
+            data
         };
         // node type    path length    |    prefix    hexchar
         // --------------------------------------------------
@@ -162,20 +201,16 @@ impl Nibbles {
 
     /// Returns true if the nibbles contain the leaf flag (16) at the end
     pub fn is_leaf(&self) -> bool {
-        if self.is_empty() {
-            false
-        } else {
-            self.data[self.data.len() - 1] == 16
-        }
+        self.as_ref().last() == Some(&16)
     }
 
     /// Combines the nibbles into bytes, trimming the leaf flag if necessary
     pub fn to_bytes(&self) -> Vec<u8> {
         // Trim leaf flag
-        let data = if !self.is_empty() && self.is_leaf() {
-            &self.data[..self.len() - 1]
+        let data = if self.is_leaf() {
+            &self.as_ref()[..self.len() - 1]
         } else {
-            &self.data[..]
+            self.as_ref()
         };
         // Combine nibbles into bytes
         data.chunks(2)
@@ -188,28 +223,26 @@ impl Nibbles {
 
     /// Concatenates self and another Nibbles returning a new Nibbles
     pub fn concat(&self, other: Nibbles) -> Nibbles {
-        Nibbles {
-            data: [self.data.clone(), other.data].concat(),
-        }
+        Nibbles::from_hex([self.as_ref(), other.as_ref()].concat())
     }
 
     /// Returns a copy of self with the nibble added at the and
     pub fn append_new(&self, nibble: u8) -> Nibbles {
-        Nibbles {
-            data: [self.data.clone(), vec![nibble]].concat(),
-        }
+        Nibbles::from_hex([self.as_ref(), &[nibble]].concat())
     }
 }
 
 impl AsRef<[u8]> for Nibbles {
     fn as_ref(&self) -> &[u8] {
-        &self.data
+        &self.data[self.already_consumed..]
     }
 }
 
 impl RLPEncode for Nibbles {
     fn encode(&self, buf: &mut dyn bytes::BufMut) {
-        Encoder::new(buf).encode_field(&self.data).finish();
+        Encoder::new(buf)
+            .encode_field(&self.as_ref().to_vec())
+            .finish();
     }
 }
 
@@ -217,7 +250,7 @@ impl RLPDecode for Nibbles {
     fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
         let decoder = Decoder::new(rlp)?;
         let (data, decoder) = decoder.decode_field("data")?;
-        Ok((Self { data }, decoder.finish()?))
+        Ok((Self::from_hex(data), decoder.finish()?))
     }
 }
 
@@ -285,6 +318,26 @@ mod test {
         assert_eq!(a.as_ref(), &[1, 2, 3, 4, 5])
     }
 
+    #[test]
+    fn consumed_tracks_traversed_nibbles() {
+        let mut a = Nibbles::from_hex(vec![1, 2, 3, 4, 5]);
+        assert!(a.consumed().is_empty());
+        assert!(a.skip_prefix(&Nibbles::from_hex(vec![1, 2])));
+        assert_eq!(a.next_choice(), Some(3));
+        assert_eq!(a.consumed(), &[1, 2, 3]);
+        assert_eq!(a, Nibbles::from_hex(vec![4, 5]));
+
+        let mut b = a.offset(1);
+        assert_eq!(b.consumed(), &[1, 2, 3, 4]);
+        b.prepend(6);
+        assert_eq!(b.consumed(), &[1, 2, 3, 4]);
+        assert_eq!(b.as_ref(), &[6, 5]);
+        assert_eq!(
+            b.encode_compact(),
+            Nibbles::from_hex(vec![6, 5]).encode_compact()
+        );
+    }
+
     #[test]
     fn count_prefix_all() {
         let a = Nibbles::from_hex(vec![1, 2, 3, 4, 5]);
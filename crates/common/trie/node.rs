@@ -17,10 +17,18 @@ use ethrex_rlp::{
 pub use extension::ExtensionNode;
 pub use leaf::LeafNode;
 
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+
 use crate::{TrieDB, error::TrieError, nibbles::Nibbles};
 
 use super::{ValueRLP, node_hash::NodeHash};
 
+/// Amount of branch levels, starting from the root, whose children are committed in parallel.
+/// Each level multiplies the amount of subtries that can be hashed concurrently by up to 16
+#[cfg(feature = "parallel")]
+const PARALLEL_COMMIT_DEPTH: usize = 2;
+
 /// A reference to a node.
 #[derive(Clone, Debug)]
 pub enum NodeRef {
@@ -44,6 +52,17 @@ impl NodeRef {
         }
     }
 
+    /// Same as [`NodeRef::get_node`] for a node located at the given path of the trie
+    pub fn get_node_at(&self, db: &dyn TrieDB, path: &[u8]) -> Result<Option<Node>, TrieError> {
+        match *self {
+            NodeRef::Hash(hash @ NodeHash::Hashed(_)) => db
+                .get_at_path(path, hash)?
+                .map(|rlp| Node::decode(&rlp).map_err(TrieError::RLPDecode))
+                .transpose(),
+            _ => self.get_node(db),
+        }
+    }
+
     pub fn is_valid(&self) -> bool {
         match self {
             NodeRef::Node(_, _) => true,
@@ -52,16 +71,17 @@ impl NodeRef {
     }
 
     pub fn commit(&mut self, acc: &mut Vec<(NodeHash, Vec<u8>)>) -> NodeHash {
+        self.commit_at_depth(acc, 0)
+    }
+
+    /// Commits the node at the given depth of the trie
+    fn commit_at_depth(&mut self, acc: &mut Vec<(NodeHash, Vec<u8>)>, depth: usize) -> NodeHash {
         match *self {
             NodeRef::Node(ref mut node, ref mut hash) => {
                 match Arc::make_mut(node) {
-                    Node::Branch(node) => {
-                        for node in &mut node.choices {
-                            node.commit(acc);
-                        }
-                    }
+                    Node::Branch(node) => commit_children(&mut node.choices, acc, depth + 1),
                     Node::Extension(node) => {
-                        node.child.commit(acc);
+                        node.child.commit_at_depth(acc, depth);
                     }
                     Node::Leaf(_) => {}
                 }
@@ -86,6 +106,40 @@ impl NodeRef {
     }
 }
 
+/// Commits the children of a branch, which are at the given depth of the trie.
+/// The children of the branches in the upper levels are committed in parallel, as each of them
+/// is the root of a large subtrie when many paths were updated
+#[cfg(feature = "parallel")]
+fn commit_children(children: &mut [NodeRef; 16], acc: &mut Vec<(NodeHash, Vec<u8>)>, depth: usize) {
+    let modified_children = children
+        .iter()
+        .filter(|child| matches!(child, NodeRef::Node(..)))
+        .count();
+    if depth <= PARALLEL_COMMIT_DEPTH && modified_children > 1 {
+        let children_nodes: Vec<_> = children
+            .par_iter_mut()
+            .map(|child| {
+                let mut child_acc = Vec::new();
+                child.commit_at_depth(&mut child_acc, depth);
+                child_acc
+            })
+            .collect();
+        acc.extend(children_nodes.into_iter().flatten());
+    } else {
+        for child in children {
+            child.commit_at_depth(acc, depth);
+        }
+    }
+}
+
+/// Commits the children of a branch, which are at the given depth of the trie
+#[cfg(not(feature = "parallel"))]
+fn commit_children(children: &mut [NodeRef; 16], acc: &mut Vec<(NodeHash, Vec<u8>)>, depth: usize) {
+    for child in children {
+        child.commit_at_depth(acc, depth);
+    }
+}
+
 impl Default for NodeRef {
     fn default() -> Self {
         Self::Hash(NodeHash::default())
@@ -28,7 +28,7 @@ impl ExtensionNode {
         if path.skip_prefix(&self.prefix) {
             let child_node = self
                 .child
-                .get_node(db)?
+                .get_node_at(db, path.consumed())?
                 .ok_or(TrieError::InconsistentTree)?;
 
             child_node.get(db, path)
@@ -57,11 +57,12 @@ impl ExtensionNode {
         let match_index = path.count_prefix(&self.prefix);
         if match_index == self.prefix.len() {
             // Insert into child node
+            let child_path = path.offset(match_index);
             let child_node = self
                 .child
-                .get_node(db)?
+                .get_node_at(db, child_path.consumed())?
                 .ok_or(TrieError::InconsistentTree)?;
-            let new_child_node = child_node.insert(db, path.offset(match_index), value)?;
+            let new_child_node = child_node.insert(db, child_path, value)?;
             self.child = new_child_node.into();
             Ok(self.into())
         } else if match_index == 0 {
@@ -72,7 +73,7 @@ impl ExtensionNode {
             };
             let mut choices = BranchNode::EMPTY_CHOICES;
             let branch_node = if self.prefix.at(0) == 16 {
-                match new_node.get_node(db)? {
+                match new_node.get_node_at(db, &[path.consumed(), self.prefix.as_ref()].concat())? {
                     Some(Node::Leaf(leaf)) => BranchNode::new_with_value(choices, leaf.value),
                     _ => return Err(TrieError::InconsistentTree),
                 }
@@ -107,7 +108,7 @@ impl ExtensionNode {
         if path.skip_prefix(&self.prefix) {
             let child_node = self
                 .child
-                .get_node(db)?
+                .get_node_at(db, path.consumed())?
                 .ok_or(TrieError::InconsistentTree)?;
             // Remove value from child subtrie
             let (child_node, old_value) = child_node.remove(db, path)?;
@@ -174,7 +175,7 @@ impl ExtensionNode {
         if path.skip_prefix(&self.prefix) {
             let child_node = self
                 .child
-                .get_node(db)?
+                .get_node_at(db, path.consumed())?
                 .ok_or(TrieError::InconsistentTree)?;
             child_node.get_path(db, path, node_path)?;
         }
@@ -1,12 +1,14 @@
 pub mod db;
 pub mod error;
 pub mod logger;
+mod multiproof;
 mod nibbles;
 pub mod node;
 mod node_hash;
 mod rlp;
 #[cfg(test)]
 mod test_utils;
+mod trie_diff;
 mod trie_iter;
 pub mod trie_sorted;
 mod verify_range;
@@ -18,7 +20,9 @@ use std::sync::{Arc, Mutex};
 
 pub use self::db::{InMemoryTrieDB, TrieDB};
 pub use self::logger::{TrieLogger, TrieWitness};
+pub use self::multiproof::{MultiProof, PartialTrie};
 pub use self::nibbles::Nibbles;
+pub use self::trie_diff::{LeafChange, TrieDiff};
 pub use self::verify_range::verify_range;
 pub use self::{
     node::{Node, NodeRef},
@@ -96,7 +100,10 @@ impl Trie {
         Ok(match self.root {
             NodeRef::Node(ref node, _) => node.get(self.db.as_ref(), Nibbles::from_bytes(path))?,
             NodeRef::Hash(hash) if hash.is_valid() => {
-                let rlp = self.db.get(hash)?.ok_or(TrieError::InconsistentTree)?;
+                let rlp = self
+                    .db
+                    .get_at_path(&[], hash)?
+                    .ok_or(TrieError::InconsistentTree)?;
                 let node = Node::decode(&rlp).map_err(TrieError::RLPDecode)?;
                 node.get(self.db.as_ref(), Nibbles::from_bytes(path))?
             }
@@ -111,7 +118,7 @@ impl Trie {
         self.root = if self.root.is_valid() {
             // If the trie is not empty, call the root node's insertion logic.
             self.root
-                .get_node(self.db.as_ref())?
+                .get_node_at(self.db.as_ref(), &[])?
                 .ok_or(TrieError::InconsistentTree)?
                 .insert(self.db.as_ref(), path, value)?
                 .into()
@@ -133,7 +140,7 @@ impl Trie {
         // If the trie is not empty, call the root node's removal logic.
         let (node, value) = self
             .root
-            .get_node(self.db.as_ref())?
+            .get_node_at(self.db.as_ref(), &[])?
             .ok_or(TrieError::InconsistentTree)?
             .remove(self.db.as_ref(), Nibbles::from_bytes(path))?;
         self.root = node.map(Into::into).unwrap_or_default();
@@ -211,7 +218,7 @@ impl Trie {
                 node_path.push(data[..len as usize].to_vec());
             }
 
-            let root = match self.root.get_node(self.db.as_ref())? {
+            let root = match self.root.get_node_at(self.db.as_ref(), &[])? {
                 Some(x) => x,
                 None => return Ok(Vec::new()),
             };
@@ -233,7 +240,7 @@ impl Trie {
         if self.root.is_valid() {
             let encoded_root = self
                 .root
-                .get_node(self.db.as_ref())?
+                .get_node_at(self.db.as_ref(), &[])?
                 .ok_or(TrieError::InconsistentTree)?
                 .encode_raw();
 
@@ -390,8 +397,9 @@ impl Trie {
                     Some(idx) => {
                         let child_ref = &branch_node.choices[idx];
                         if child_ref.is_valid() {
-                            let child_node =
-                                child_ref.get_node(db)?.ok_or(TrieError::InconsistentTree)?;
+                            let child_node = child_ref
+                                .get_node_at(db, partial_path.consumed())?
+                                .ok_or(TrieError::InconsistentTree)?;
                             get_node_inner(db, child_node, partial_path)
                         } else {
                             Ok(vec![])
@@ -405,7 +413,7 @@ impl Trie {
                     {
                         let child_node = extension_node
                             .child
-                            .get_node(db)?
+                            .get_node_at(db, partial_path.consumed())?
                             .ok_or(TrieError::InconsistentTree)?;
                         get_node_inner(db, child_node, partial_path)
                     } else {
@@ -421,7 +429,7 @@ impl Trie {
             get_node_inner(
                 self.db.as_ref(),
                 self.root
-                    .get_node(self.db.as_ref())?
+                    .get_node_at(self.db.as_ref(), &[])?
                     .ok_or(TrieError::InconsistentTree)?,
                 partial_path,
             )
@@ -434,7 +442,7 @@ impl Trie {
         if self.hash_no_commit() == *EMPTY_TRIE_HASH {
             return Ok(None);
         }
-        self.root.get_node(self.db.as_ref())
+        self.root.get_node_at(self.db.as_ref(), &[])
     }
 
     /// Creates a new Trie based on a temporary InMemory DB
@@ -0,0 +1,392 @@
+use std::collections::VecDeque;
+
+use crate::{
+    PathRLP, Trie, TrieDB, TrieError, ValueRLP,
+    nibbles::Nibbles,
+    node::{Node, NodeRef},
+};
+
+/// A leaf that differs between two tries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeafChange {
+    /// The path is only present in the new trie
+    Inserted { path: PathRLP, value: ValueRLP },
+    /// The path is only present in the old trie
+    Removed { path: PathRLP, value: ValueRLP },
+    /// The path is present in both tries with different values
+    Changed {
+        path: PathRLP,
+        old_value: ValueRLP,
+        new_value: ValueRLP,
+    },
+}
+
+impl LeafChange {
+    pub fn path(&self) -> &PathRLP {
+        match self {
+            LeafChange::Inserted { path, .. }
+            | LeafChange::Removed { path, .. }
+            | LeafChange::Changed { path, .. } => path,
+        }
+    }
+
+    /// Returns the path along with its value in the old and the new trie
+    pub fn into_parts(self) -> (PathRLP, Option<ValueRLP>, Option<ValueRLP>) {
+        match self {
+            LeafChange::Inserted { path, value } => (path, None, Some(value)),
+            LeafChange::Removed { path, value } => (path, Some(value), None),
+            LeafChange::Changed {
+                path,
+                old_value,
+                new_value,
+            } => (path, Some(old_value), Some(new_value)),
+        }
+    }
+}
+
+/// A subtrie being compared, which may be part of a node whose first nibbles were already traversed
+enum DiffNode {
+    Ref(NodeRef),
+    Extension { prefix: Nibbles, child: NodeRef },
+    Leaf { partial: Nibbles, value: ValueRLP },
+}
+
+/// Iterator over the leaves that differ between two tries, ordered by path.
+/// Both tries are walked in lockstep, skipping the subtries with the same hash at the same position,
+/// so only the nodes that changed are read from the database.
+pub struct TrieDiff {
+    old_db: Box<dyn TrieDB>,
+    new_db: Box<dyn TrieDB>,
+    // Subtries left to compare along with their position, the next one to compare is on top
+    stack: Vec<(Nibbles, Option<DiffNode>, Option<DiffNode>)>,
+    // Changes found but not yet returned, ordered by path
+    pending: VecDeque<LeafChange>,
+}
+
+impl Trie {
+    /// Returns an iterator over the leaves that differ between `self` and `new`
+    pub fn diff(self, new: Trie) -> TrieDiff {
+        let old_root = self.root.is_valid().then_some(DiffNode::Ref(self.root));
+        let new_root = new.root.is_valid().then_some(DiffNode::Ref(new.root));
+        TrieDiff {
+            old_db: self.db,
+            new_db: new.db,
+            stack: vec![(Nibbles::default(), old_root, new_root)],
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl TrieDiff {
+    /// Compares the subtries at the given position, queueing the changes found or the subtries to
+    /// compare next if they can't be told apart yet
+    fn compare(
+        &mut self,
+        position: Nibbles,
+        old: Option<DiffNode>,
+        new: Option<DiffNode>,
+    ) -> Result<(), TrieError> {
+        if let (Some(DiffNode::Ref(old)), Some(DiffNode::Ref(new))) = (&old, &new)
+            && old.compute_hash() == new.compute_hash()
+        {
+            return Ok(());
+        }
+        let old = old
+            .map(|node| resolve(self.old_db.as_ref(), &position, node))
+            .transpose()?;
+        let new = new
+            .map(|node| resolve(self.new_db.as_ref(), &position, node))
+            .transpose()?;
+
+        match (old, new) {
+            (None, None) => {}
+            (
+                Some(DiffNode::Leaf {
+                    partial: old_partial,
+                    value: old_value,
+                }),
+                Some(DiffNode::Leaf {
+                    partial: new_partial,
+                    value: new_value,
+                }),
+            ) => {
+                if old_partial == new_partial {
+                    if old_value != new_value {
+                        self.pending.push_back(LeafChange::Changed {
+                            path: position.concat(old_partial).to_bytes(),
+                            old_value,
+                            new_value,
+                        });
+                    }
+                } else {
+                    let removed = LeafChange::Removed {
+                        path: position.concat(old_partial).to_bytes(),
+                        value: old_value,
+                    };
+                    let inserted = LeafChange::Inserted {
+                        path: position.concat(new_partial).to_bytes(),
+                        value: new_value,
+                    };
+                    if removed.path() < inserted.path() {
+                        self.pending.extend([removed, inserted]);
+                    } else {
+                        self.pending.extend([inserted, removed]);
+                    }
+                }
+            }
+            (Some(DiffNode::Leaf { partial, value }), None) => {
+                self.pending.push_back(LeafChange::Removed {
+                    path: position.concat(partial).to_bytes(),
+                    value,
+                });
+            }
+            (None, Some(DiffNode::Leaf { partial, value })) => {
+                self.pending.push_back(LeafChange::Inserted {
+                    path: position.concat(partial).to_bytes(),
+                    value,
+                });
+            }
+            (
+                Some(DiffNode::Extension {
+                    prefix: old_prefix,
+                    child: old_child,
+                }),
+                Some(DiffNode::Extension {
+                    prefix: new_prefix,
+                    child: new_child,
+                }),
+            ) if old_prefix == new_prefix => {
+                self.stack.push((
+                    position.concat(old_prefix),
+                    Some(DiffNode::Ref(old_child)),
+                    Some(DiffNode::Ref(new_child)),
+                ));
+            }
+            (old, new) => {
+                // The subtries have different shapes, so compare them one nibble at a time
+                let (old_value, mut old_children) = split(old);
+                let (new_value, mut new_children) = split(new);
+                match (old_value, new_value) {
+                    (Some(old_value), Some(new_value)) if old_value != new_value => {
+                        self.pending.push_back(LeafChange::Changed {
+                            path: position.to_bytes(),
+                            old_value,
+                            new_value,
+                        });
+                    }
+                    (Some(value), None) => self.pending.push_back(LeafChange::Removed {
+                        path: position.to_bytes(),
+                        value,
+                    }),
+                    (None, Some(value)) => self.pending.push_back(LeafChange::Inserted {
+                        path: position.to_bytes(),
+                        value,
+                    }),
+                    _ => {}
+                }
+                // Pushed in reverse so the lowest choice is compared first
+                for choice in (0..16).rev() {
+                    let old = old_children[choice].take();
+                    let new = new_children[choice].take();
+                    if old.is_some() || new.is_some() {
+                        self.stack
+                            .push((position.append_new(choice as u8), old, new));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for TrieDiff {
+    type Item = Result<LeafChange, TrieError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Some(Ok(change));
+            }
+            let (position, old, new) = self.stack.pop()?;
+            if let Err(err) = self.compare(position, old, new) {
+                // The tries can't be compared any further
+                self.stack.clear();
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+/// Reads the node of a subtrie from the database if it's only referenced by hash
+fn resolve(db: &dyn TrieDB, position: &Nibbles, node: DiffNode) -> Result<DiffNode, TrieError> {
+    let DiffNode::Ref(node_ref) = node else {
+        return Ok(node);
+    };
+    Ok(
+        match node_ref
+            .get_node_at(db, position.as_ref())?
+            .ok_or(TrieError::InconsistentTree)?
+        {
+            Node::Branch(branch) => DiffNode::Ref(Node::Branch(branch).into()),
+            Node::Extension(extension) => DiffNode::Extension {
+                prefix: extension.prefix,
+                child: extension.child,
+            },
+            Node::Leaf(leaf) => DiffNode::Leaf {
+                partial: leaf.partial,
+                value: leaf.value,
+            },
+        },
+    )
+}
+
+/// Splits a resolved subtrie into the value stored at its position and its subtries by next nibble
+fn split(node: Option<DiffNode>) -> (Option<ValueRLP>, [Option<DiffNode>; 16]) {
+    let mut children: [Option<DiffNode>; 16] = Default::default();
+    let value = match node {
+        None => None,
+        Some(DiffNode::Ref(NodeRef::Node(node, _))) => match node.as_ref() {
+            Node::Branch(branch) => {
+                for (choice, child) in branch.choices.iter().enumerate() {
+                    if child.is_valid() {
+                        children[choice] = Some(DiffNode::Ref(child.clone()));
+                    }
+                }
+                (!branch.value.is_empty()).then(|| branch.value.clone())
+            }
+            // Extensions and leaves are never kept as references once resolved
+            _ => None,
+        },
+        Some(DiffNode::Ref(NodeRef::Hash(_))) => None,
+        Some(DiffNode::Extension { prefix, child }) => {
+            let choice = prefix.at(0);
+            children[choice] = Some(if prefix.len() == 1 {
+                DiffNode::Ref(child)
+            } else {
+                DiffNode::Extension {
+                    prefix: prefix.offset(1),
+                    child,
+                }
+            });
+            None
+        }
+        Some(DiffNode::Leaf { partial, value }) => {
+            if partial.is_leaf() && partial.len() == 1 {
+                // Only the leaf flag is left, the value is stored at this position
+                Some(value)
+            } else {
+                children[partial.at(0)] = Some(DiffNode::Leaf {
+                    partial: partial.offset(1),
+                    value,
+                });
+                None
+            }
+        }
+    };
+    (value, children)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn trie_from(entries: &BTreeMap<PathRLP, ValueRLP>) -> Trie {
+        let mut trie = Trie::default();
+        for (path, value) in entries {
+            trie.insert(path.clone(), value.clone()).unwrap();
+        }
+        trie.hash().unwrap();
+        trie
+    }
+
+    /// Computes the expected diff by comparing every entry of both maps
+    fn expected_diff(
+        old: &BTreeMap<PathRLP, ValueRLP>,
+        new: &BTreeMap<PathRLP, ValueRLP>,
+    ) -> Vec<LeafChange> {
+        let mut paths: Vec<_> = old.keys().chain(new.keys()).cloned().collect();
+        paths.sort();
+        paths.dedup();
+        paths
+            .into_iter()
+            .filter_map(|path| match (old.get(&path), new.get(&path)) {
+                (Some(old_value), Some(new_value)) if old_value != new_value => {
+                    Some(LeafChange::Changed {
+                        path,
+                        old_value: old_value.clone(),
+                        new_value: new_value.clone(),
+                    })
+                }
+                (Some(value), None) => Some(LeafChange::Removed {
+                    path,
+                    value: value.clone(),
+                }),
+                (None, Some(value)) => Some(LeafChange::Inserted {
+                    path,
+                    value: value.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn assert_diff(old: &BTreeMap<PathRLP, ValueRLP>, new: &BTreeMap<PathRLP, ValueRLP>) {
+        let diff = trie_from(old)
+            .diff(trie_from(new))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(diff, expected_diff(old, new));
+    }
+
+    #[test]
+    fn diff_identical_tries() {
+        let entries: BTreeMap<_, _> = (0..100u64)
+            .map(|i| (i.to_be_bytes().to_vec(), vec![1]))
+            .collect();
+        assert_diff(&entries, &entries);
+        assert_diff(&BTreeMap::new(), &BTreeMap::new());
+    }
+
+    #[test]
+    fn diff_from_and_to_empty_trie() {
+        let entries: BTreeMap<_, _> = (0..100u64)
+            .map(|i| (i.to_be_bytes().to_vec(), vec![1]))
+            .collect();
+        assert_diff(&BTreeMap::new(), &entries);
+        assert_diff(&entries, &BTreeMap::new());
+    }
+
+    #[test]
+    fn diff_with_different_shapes() {
+        // A single leaf in the old trie becomes a branch with an extension in the new one
+        let old = BTreeMap::from([(vec![0x12, 0x34], vec![1])]);
+        let new = BTreeMap::from([
+            (vec![0x12, 0x34], vec![2]),
+            (vec![0x12, 0x35], vec![3]),
+            (vec![0x56], vec![4]),
+        ]);
+        assert_diff(&old, &new);
+        assert_diff(&new, &old);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn proptest_diff_matches_entries(
+            // Few distinct nibbles, so that paths share long prefixes
+            old in proptest::collection::btree_map(
+                proptest::collection::vec(0..4u8, 3),
+                proptest::collection::vec(1..u8::MAX, 1..40),
+                0..64,
+            ),
+            new in proptest::collection::btree_map(
+                proptest::collection::vec(0..4u8, 3),
+                proptest::collection::vec(1..u8::MAX, 1..40),
+                0..64,
+            ),
+        ) {
+            assert_diff(&old, &new);
+        }
+    }
+}
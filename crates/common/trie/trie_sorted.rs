@@ -255,12 +255,12 @@ where
                     .finalize()
             }
             Node::Extension(extension_node) => {
-                extension_node.prefix.data.insert(0, index as u8);
+                extension_node.prefix.prepend(index as u8);
                 *node_hash = extension_node.compute_hash();
                 node_hash.finalize()
             }
             Node::Leaf(leaf_node) => {
-                leaf_node.partial.data.insert(0, index as u8);
+                leaf_node.partial.prepend(index as u8);
                 *node_hash = leaf_node.compute_hash();
                 node_hash.finalize()
             }
@@ -32,10 +32,23 @@ pub type BlockHash = H256;
 use once_cell::sync::OnceCell;
 
 #[derive(
-    PartialEq, Eq, Debug, Clone, Deserialize, Serialize, Default, RSerialize, RDeserialize, Archive,
+    PartialEq,
+    Eq,
+    Debug,
+    Clone,
+    Deserialize,
+    Serialize,
+    Default,
+    RSerialize,
+    RDeserialize,
+    Archive,
+    RLPEncode,
+    RLPDecode,
 )]
 pub struct Block {
     pub header: BlockHeader,
+    // Encoded as the rest of the block's list, not as a nested list
+    #[rlp(flatten)]
     pub body: BlockBody,
 }
 
@@ -49,35 +62,6 @@ impl Block {
     }
 }
 
-impl RLPEncode for Block {
-    fn encode(&self, buf: &mut dyn bytes::BufMut) {
-        Encoder::new(buf)
-            .encode_field(&self.header)
-            .encode_field(&self.body.transactions)
-            .encode_field(&self.body.ommers)
-            .encode_optional_field(&self.body.withdrawals)
-            .finish();
-    }
-}
-
-impl RLPDecode for Block {
-    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
-        let decoder = Decoder::new(rlp)?;
-        let (header, decoder) = decoder.decode_field("header")?;
-        let (transactions, decoder) = decoder.decode_field("transactions")?;
-        let (ommers, decoder) = decoder.decode_field("ommers")?;
-        let (withdrawals, decoder) = decoder.decode_optional_field();
-        let remaining = decoder.finish()?;
-        let body = BlockBody {
-            transactions,
-            ommers,
-            withdrawals,
-        };
-        let block = Block::new(header, body);
-        Ok((block, remaining))
-    }
-}
-
 /// Header part of a block on the chain.
 #[derive(
     Clone, Debug, PartialEq, Eq, Serialize, Default, Deserialize, RSerialize, RDeserialize, Archive,
@@ -231,13 +215,25 @@ impl RLPDecode for BlockHeader {
 
 // The body of a block on the chain
 #[derive(
-    Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default, RSerialize, RDeserialize, Archive,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Default,
+    RSerialize,
+    RDeserialize,
+    Archive,
+    RLPEncode,
+    RLPDecode,
 )]
 pub struct BlockBody {
     pub transactions: Vec<Transaction>,
     // TODO: ommers list is always empty, so we can remove it
     #[serde(rename = "uncles")]
     pub ommers: Vec<BlockHeader>,
+    #[rlp(optional)]
     pub withdrawals: Option<Vec<Withdrawal>>,
 }
 
@@ -289,33 +285,6 @@ pub fn compute_withdrawals_root(withdrawals: &[Withdrawal]) -> H256 {
     Trie::compute_hash_from_unsorted_iter(iter)
 }
 
-impl RLPEncode for BlockBody {
-    fn encode(&self, buf: &mut dyn bytes::BufMut) {
-        Encoder::new(buf)
-            .encode_field(&self.transactions)
-            .encode_field(&self.ommers)
-            .encode_optional_field(&self.withdrawals)
-            .finish();
-    }
-}
-
-impl RLPDecode for BlockBody {
-    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
-        let decoder = Decoder::new(rlp)?;
-        let (transactions, decoder) = decoder.decode_field("transactions")?;
-        let (ommers, decoder) = decoder.decode_field("ommers")?;
-        let (withdrawals, decoder) = decoder.decode_optional_field();
-        Ok((
-            BlockBody {
-                transactions,
-                ommers,
-                withdrawals,
-            },
-            decoder.finish()?,
-        ))
-    }
-}
-
 impl BlockHeader {
     fn compute_block_hash(&self) -> H256 {
         let mut buf = vec![];
@@ -1,18 +1,18 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::str::FromStr;
 
 use crate::types::Block;
 use crate::{
     H160,
-    constants::EMPTY_KECCACK_HASH,
+    constants::{EMPTY_KECCACK_HASH, EMPTY_TRIE_HASH},
     types::{AccountState, AccountUpdate, BlockHeader, ChainConfig},
     utils::{decode_hex, keccak},
 };
 use bytes::Bytes;
 use ethereum_types::{Address, H256, U256};
 use ethrex_rlp::{decode::RLPDecode, encode::RLPEncode};
-use ethrex_trie::{NodeRLP, Trie};
+use ethrex_trie::{Node, NodeHash, NodeRLP, NodeRef, Trie};
 use rkyv::{Archive, Deserialize as RDeserialize, Serialize as RSerialize};
 use serde::de::{SeqAccess, Visitor};
 use serde::ser::SerializeSeq;
@@ -109,6 +109,172 @@ pub enum GuestProgramStateError {
     Custom(String),
 }
 
+/// Size reductions applied when building an execution witness. They are all disabled by default
+/// so the witness matches the ones produced by other clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct WitnessOptions {
+    /// Drop the trie nodes that can't be reached from the initial state, such as the nodes of the
+    /// intermediate states of a batch, and keep a single copy of each node
+    pub prune_unreachable_nodes: bool,
+    /// Keep a single copy of the bytecodes accessed by several blocks of a batch
+    pub dedup_codes: bool,
+    /// Leave out the headers of the executed blocks, the guest program takes them from the blocks
+    pub compact_headers: bool,
+}
+
+impl WitnessOptions {
+    /// Options applying every size reduction
+    pub fn compact() -> Self {
+        Self {
+            prune_unreachable_nodes: true,
+            dedup_codes: true,
+            compact_headers: true,
+        }
+    }
+}
+
+/// Size in bytes of each part of an execution witness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionWitnessStats {
+    pub state_nodes: usize,
+    pub storage_nodes: usize,
+    /// Nodes that can't be reached from the initial state, so the guest program never uses them
+    pub unreachable_nodes: usize,
+    pub codes: usize,
+    pub headers: usize,
+    pub keys: usize,
+}
+
+impl ExecutionWitnessStats {
+    pub fn total(&self) -> usize {
+        self.state_nodes
+            + self.storage_nodes
+            + self.unreachable_nodes
+            + self.codes
+            + self.headers
+            + self.keys
+    }
+}
+
+impl ExecutionWitness {
+    /// Returns the state root of the parent of the first block, which is the root of the witness' state trie
+    pub fn initial_state_root(&self) -> Result<H256, GuestProgramStateError> {
+        let parent_number =
+            self.first_block_number
+                .checked_sub(1)
+                .ok_or(GuestProgramStateError::Custom(
+                    "First block number cannot be zero".to_string(),
+                ))?;
+        for header_bytes in &self.block_headers_bytes {
+            let header = BlockHeader::decode(header_bytes).map_err(|e| {
+                GuestProgramStateError::Custom(format!("Failed to decode block headers: {e}"))
+            })?;
+            if header.number == parent_number {
+                return Ok(header.state_root);
+            }
+        }
+        Err(GuestProgramStateError::MissingParentHeaderOf(
+            self.first_block_number,
+        ))
+    }
+
+    /// Computes how many bytes each part of the witness takes
+    pub fn stats(&self) -> Result<ExecutionWitnessStats, GuestProgramStateError> {
+        let (state_nodes, storage_nodes) = self.reachable_nodes()?;
+        let mut stats = ExecutionWitnessStats {
+            codes: self.codes.iter().map(Vec::len).sum(),
+            headers: self.block_headers_bytes.iter().map(Vec::len).sum(),
+            keys: self.keys.iter().map(Vec::len).sum(),
+            ..Default::default()
+        };
+        for node in &self.nodes {
+            let hash = keccak(node);
+            if state_nodes.contains(&hash) {
+                stats.state_nodes += node.len();
+            } else if storage_nodes.contains(&hash) {
+                stats.storage_nodes += node.len();
+            } else {
+                stats.unreachable_nodes += node.len();
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Removes the nodes that can't be reached from the initial state and the repeated ones
+    pub fn prune_unreachable_nodes(&mut self) -> Result<(), GuestProgramStateError> {
+        let (state_nodes, storage_nodes) = self.reachable_nodes()?;
+        let mut kept_nodes = BTreeSet::new();
+        self.nodes.retain(|node| {
+            let hash = keccak(node);
+            (state_nodes.contains(&hash) || storage_nodes.contains(&hash))
+                && kept_nodes.insert(hash)
+        });
+        Ok(())
+    }
+
+    /// Removes the repeated bytecodes
+    pub fn dedup_codes(&mut self) {
+        let mut kept_codes = BTreeSet::new();
+        self.codes.retain(|code| kept_codes.insert(keccak(code)));
+    }
+
+    /// Returns the hashes of the nodes reachable from the initial state trie, and the ones reachable
+    /// from the storage tries of its accounts
+    fn reachable_nodes(&self) -> Result<(BTreeSet<H256>, BTreeSet<H256>), GuestProgramStateError> {
+        let nodes: BTreeMap<H256, &[u8]> = self
+            .nodes
+            .iter()
+            .map(|node| (keccak(node), node.as_slice()))
+            .collect();
+        let mut state_nodes = BTreeSet::new();
+        let mut storage_nodes = BTreeSet::new();
+        let mut pending = vec![(self.initial_state_root()?, false)];
+        while let Some((hash, is_storage)) = pending.pop() {
+            let reachable = if is_storage {
+                &mut storage_nodes
+            } else {
+                &mut state_nodes
+            };
+            // Nodes missing from the witness aren't needed by the execution
+            let Some(node_rlp) = nodes.get(&hash) else {
+                continue;
+            };
+            if !reachable.insert(hash) {
+                continue;
+            }
+            let node = Node::decode_raw(node_rlp).map_err(|e| {
+                GuestProgramStateError::Custom(format!("Failed to decode trie node: {e}"))
+            })?;
+            let children = match node {
+                Node::Branch(branch) => Vec::from(branch.choices),
+                Node::Extension(extension) => vec![extension.child],
+                Node::Leaf(leaf) => {
+                    if !is_storage {
+                        let account = AccountState::decode(&leaf.value).map_err(|e| {
+                            GuestProgramStateError::Custom(format!(
+                                "Failed to decode account state: {e}"
+                            ))
+                        })?;
+                        if account.storage_root != *EMPTY_TRIE_HASH {
+                            pending.push((account.storage_root, true));
+                        }
+                    }
+                    vec![]
+                }
+            };
+            // Inline children are embedded in their parent's encoding
+            for child in children {
+                if let NodeRef::Hash(NodeHash::Hashed(child_hash)) = child {
+                    pending.push((child_hash, is_storage));
+                }
+            }
+        }
+        Ok((state_nodes, storage_nodes))
+    }
+}
+
 impl TryFrom<ExecutionWitness> for GuestProgramState {
     type Error = GuestProgramStateError;
 
@@ -566,7 +732,7 @@ where
     deserializer.deserialize_seq(BytesVecVisitor)
 }
 
-fn hash_address(address: &Address) -> Vec<u8> {
+pub fn hash_address(address: &Address) -> Vec<u8> {
     Keccak256::new_with_prefix(address.to_fixed_bytes())
         .finalize()
         .to_vec()
@@ -577,3 +743,187 @@ pub fn hash_key(key: &H256) -> Vec<u8> {
         .finalize()
         .to_vec()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT_A: H256 = H256::repeat_byte(0xa);
+    const SLOT_B: H256 = H256::repeat_byte(0xb);
+    const ACCOUNT: Address = Address::repeat_byte(1);
+    const REMOVED_ACCOUNT: Address = Address::repeat_byte(2);
+    const UNTOUCHED_ACCOUNT: Address = Address::repeat_byte(3);
+
+    fn storage_trie(slots: &[(H256, u64)]) -> Trie {
+        let mut trie = Trie::empty_in_memory();
+        for (key, value) in slots {
+            trie.insert(hash_key(key), U256::from(*value).encode_to_vec())
+                .unwrap();
+        }
+        trie
+    }
+
+    fn state_trie(accounts: &[(Address, AccountState)]) -> Trie {
+        let mut trie = Trie::empty_in_memory();
+        for (address, account) in accounts {
+            trie.insert(hash_address(address), account.encode_to_vec())
+                .unwrap();
+        }
+        trie
+    }
+
+    fn account_with_storage(storage_root: H256) -> AccountState {
+        AccountState {
+            storage_root,
+            ..Default::default()
+        }
+    }
+
+    fn account_with_nonce(nonce: u64) -> AccountState {
+        AccountState {
+            nonce,
+            ..Default::default()
+        }
+    }
+
+    /// Builds a witness with the proofs of every account and slot, some of them repeated,
+    /// along with nodes that don't belong to the initial state
+    fn witness_with_unreachable_nodes() -> (ExecutionWitness, Vec<Vec<u8>>) {
+        let storage = storage_trie(&[(SLOT_A, 1), (SLOT_B, 2)]);
+        let accounts = [
+            (ACCOUNT, account_with_storage(storage.hash_no_commit())),
+            (REMOVED_ACCOUNT, account_with_nonce(1)),
+            (UNTOUCHED_ACCOUNT, account_with_nonce(2)),
+        ];
+        let state = state_trie(&accounts);
+
+        let mut nodes = Vec::new();
+        for (address, _) in &accounts {
+            nodes.extend(state.get_proof(&hash_address(address)).unwrap());
+        }
+        for key in [SLOT_A, SLOT_B] {
+            nodes.extend(storage.get_proof(&hash_key(&key)).unwrap());
+        }
+        // Nodes of the state after the block, which the guest program never reads
+        let unreachable_nodes = state_trie(&[(ACCOUNT, account_with_nonce(9))])
+            .get_proof(&hash_address(&ACCOUNT))
+            .unwrap();
+        nodes.extend(unreachable_nodes.clone());
+
+        let parent_header = BlockHeader {
+            number: 0,
+            state_root: state.hash_no_commit(),
+            ..Default::default()
+        };
+        let witness = ExecutionWitness {
+            codes: vec![vec![0x60, 0x00], vec![0x60, 0x00]],
+            block_headers_bytes: vec![parent_header.encode_to_vec()],
+            first_block_number: 1,
+            chain_config: ChainConfig::default(),
+            nodes,
+            keys: vec![ACCOUNT.as_bytes().to_vec(), SLOT_A.as_bytes().to_vec()],
+        };
+        (witness, unreachable_nodes)
+    }
+
+    #[test]
+    fn pruned_witness_keeps_the_nodes_the_guest_needs() {
+        let (mut witness, unreachable_nodes) = witness_with_unreachable_nodes();
+        let reachable_nodes: BTreeSet<Vec<u8>> = witness
+            .nodes
+            .iter()
+            .filter(|node| !unreachable_nodes.contains(node))
+            .cloned()
+            .collect();
+
+        witness.prune_unreachable_nodes().unwrap();
+        assert_eq!(witness.nodes.len(), reachable_nodes.len());
+        assert!(
+            witness
+                .nodes
+                .iter()
+                .all(|node| reachable_nodes.contains(node))
+        );
+
+        // Deleting slot A collapses the storage root branch, which needs the leaf of slot B
+        let mut guest_state = GuestProgramState::try_from(witness).unwrap();
+        assert_eq!(
+            guest_state.get_storage_slot(ACCOUNT, SLOT_A).unwrap(),
+            Some(U256::one())
+        );
+        let mut storage_update = AccountUpdate::new(ACCOUNT);
+        storage_update.added_storage.insert(SLOT_A, U256::zero());
+        guest_state
+            .apply_account_updates(&[storage_update, AccountUpdate::removed(REMOVED_ACCOUNT)])
+            .unwrap();
+
+        let expected_storage = storage_trie(&[(SLOT_B, 2)]);
+        let expected_state = state_trie(&[
+            (
+                ACCOUNT,
+                account_with_storage(expected_storage.hash_no_commit()),
+            ),
+            (UNTOUCHED_ACCOUNT, account_with_nonce(2)),
+        ]);
+        assert_eq!(
+            guest_state.state_trie_root().unwrap(),
+            expected_state.hash_no_commit()
+        );
+    }
+
+    #[test]
+    fn witness_stats_add_up_to_its_size() {
+        let (mut witness, unreachable_nodes) = witness_with_unreachable_nodes();
+        let size = |items: &[Vec<u8>]| items.iter().map(Vec::len).sum::<usize>();
+
+        let stats = witness.stats().unwrap();
+        assert_eq!(stats.unreachable_nodes, size(&unreachable_nodes));
+        assert_eq!(
+            stats.state_nodes + stats.storage_nodes + stats.unreachable_nodes,
+            size(&witness.nodes)
+        );
+        assert_eq!(stats.codes, size(&witness.codes));
+        assert_eq!(stats.headers, size(&witness.block_headers_bytes));
+        assert_eq!(stats.keys, size(&witness.keys));
+        assert_eq!(stats.total(), witness_size(&witness));
+        assert!(stats.storage_nodes > 0);
+
+        witness.prune_unreachable_nodes().unwrap();
+        witness.dedup_codes();
+        let pruned_stats = witness.stats().unwrap();
+        assert_eq!(pruned_stats.unreachable_nodes, 0);
+        assert_eq!(
+            pruned_stats.state_nodes + pruned_stats.storage_nodes,
+            size(&witness.nodes)
+        );
+        assert_eq!(pruned_stats.codes, stats.codes / 2);
+        assert_eq!(pruned_stats.total(), witness_size(&witness));
+    }
+
+    #[test]
+    fn witness_options_reject_unknown_fields() {
+        let options: WitnessOptions =
+            serde_json::from_str(r#"{"pruneUnreachableNodes":true}"#).unwrap();
+        assert_eq!(
+            options,
+            WitnessOptions {
+                prune_unreachable_nodes: true,
+                ..Default::default()
+            }
+        );
+        assert!(serde_json::from_str::<WitnessOptions>(r#"{"pruneNodes":true}"#).is_err());
+    }
+
+    fn witness_size(witness: &ExecutionWitness) -> usize {
+        [
+            &witness.nodes,
+            &witness.codes,
+            &witness.block_headers_bytes,
+            &witness.keys,
+        ]
+        .into_iter()
+        .flatten()
+        .map(Vec::len)
+        .sum()
+    }
+}
@@ -280,7 +280,21 @@ lazy_static::lazy_static! {
 }
 
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq, PartialOrd, Default, Hash, Clone, Copy, Serialize, Deserialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Default,
+    Hash,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    RSerialize,
+    RDeserialize,
+    Archive,
+)]
 pub enum Fork {
     Frontier = 0,
     FrontierThawing = 1,
@@ -1,2 +1,3 @@
 pub mod batch;
 pub mod fee_config;
+pub mod precompiles;
@@ -0,0 +1,17 @@
+use ethereum_types::Address;
+use rkyv::{Archive, Deserialize as RDeserialize, Serialize as RSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::{rkyv_utils::H160Wrapper, types::Fork};
+
+/// Where and since when a custom precompile of the L2 is active. Precompiles are code, so this
+/// is what the sequencer sends to the prover, which checks it executes the same ones.
+#[derive(
+    Serialize, Deserialize, RDeserialize, RSerialize, Archive, Clone, Copy, Debug, PartialEq, Eq,
+)]
+pub struct CustomPrecompileSpec {
+    #[rkyv(with=H160Wrapper)]
+    pub address: Address,
+    /// First fork the precompile is active in
+    pub activation: Fork,
+}
@@ -1,6 +1,7 @@
 pub mod calldata;
 pub mod l1_messages;
 pub mod merkle_tree;
+pub mod precompiles;
 pub mod privileged_transactions;
 pub mod prover;
 pub mod state_diff;
@@ -0,0 +1,11 @@
+//! Custom precompiles of the L2, see `docs/l2/fundamentals/custom_precompiles.md`.
+
+use ethrex_vm::{CustomPrecompile, CustomPrecompileError, CustomPrecompiles};
+
+/// Precompiles this L2 adds to the standard ones. Both the node and the guest program of the
+/// prover take them from here, so that the prover executes the same code as the sequencer.
+const L2_PRECOMPILES: &[CustomPrecompile] = &[];
+
+pub fn l2_custom_precompiles() -> Result<CustomPrecompiles, CustomPrecompileError> {
+    CustomPrecompiles::new(L2_PRECOMPILES.iter().copied())
+}
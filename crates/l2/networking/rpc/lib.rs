@@ -1,7 +1,8 @@
 pub mod clients;
 pub mod l2;
 mod rpc;
-pub mod signer;
 pub mod utils;
 
+pub use ethrex_rpc::signer;
+
 pub use rpc::start_api;
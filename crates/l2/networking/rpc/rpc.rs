@@ -2,8 +2,7 @@ use crate::l2::batch::{BatchNumberRequest, GetBatchByBatchNumberRequest};
 use crate::l2::fee_vault::GetFeeVaultAddress;
 use crate::l2::l1_message::GetL1MessageProof;
 use crate::utils::{RpcErr, RpcNamespace, resolve_namespace};
-use axum::extract::State;
-use axum::{Json, Router, http::StatusCode, routing::post};
+use axum::{Extension, Router, routing::post};
 use bytes::Bytes;
 use ethrex_blockchain::Blockchain;
 use ethrex_common::types::Transaction;
@@ -12,10 +11,10 @@ use ethrex_p2p::sync_manager::SyncManager;
 use ethrex_p2p::types::Node;
 use ethrex_p2p::types::NodeRecord;
 use ethrex_rpc::RpcHandler as L1RpcHandler;
+use ethrex_rpc::http_config::{HttpAccess, HttpConfig};
 use ethrex_rpc::{
-    GasTipEstimator, NodeData, RpcRequestWrapper,
-    types::transaction::SendRawTransactionRequest,
-    utils::{RpcRequest, RpcRequestId},
+    CreateCheckpointRequest, GasTipEstimator, NodeData, RpcErrorMetadata, RpcService,
+    types::transaction::SendRawTransactionRequest, utils::RpcRequest,
 };
 use ethrex_storage::Store;
 use serde_json::Value;
@@ -23,15 +22,16 @@ use std::{
     collections::HashMap,
     future::IntoFuture,
     net::SocketAddr,
+    path::PathBuf,
     sync::{Arc, Mutex},
     time::Duration,
 };
 use tokio::{net::TcpListener, sync::Mutex as TokioMutex};
-use tower_http::cors::CorsLayer;
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 use tracing_subscriber::{EnvFilter, Registry, reload};
 
 use crate::l2::transaction::SponsoredTx;
+use crate::signer::Signer;
 use ethrex_common::Address;
 use ethrex_storage_rollup::StoreRollup;
 use secp256k1::SecretKey;
@@ -67,6 +67,8 @@ pub const FILTER_DURATION: Duration = {
 pub async fn start_api(
     http_addr: SocketAddr,
     authrpc_addr: SocketAddr,
+    ipc_path: Option<PathBuf>,
+    http_config: HttpConfig,
     storage: Store,
     blockchain: Arc<Blockchain>,
     jwt_secret: Bytes,
@@ -80,6 +82,9 @@ pub async fn start_api(
     rollup_store: StoreRollup,
     log_filter_handler: Option<reload::Handle<EnvFilter, Registry>>,
     gas_ceil: u64,
+    signers: Vec<Signer>,
+    checkpoint_dir: Option<PathBuf>,
+    bad_block_traces_dir: Option<PathBuf>,
 ) -> Result<(), RpcErr> {
     // TODO: Refactor how filters are handled,
     // filters are used by the filters endpoints (eth_newFilter, eth_getFilterChanges, ...etc)
@@ -101,6 +106,10 @@ pub async fn start_api(
             gas_tip_estimator: Arc::new(TokioMutex::new(GasTipEstimator::new())),
             log_filter_handler,
             gas_ceil,
+            signers: Arc::new(signers),
+            nonce_locks: Default::default(),
+            checkpoint_dir,
+            bad_block_traces_dir,
         },
         valid_delegation_addresses,
         sponsor_pk,
@@ -119,60 +128,71 @@ pub async fn start_api(
         }
     });
 
-    // All request headers allowed.
-    // All methods allowed.
-    // All origins allowed.
-    // All headers exposed.
-    let cors = CorsLayer::permissive();
+    // The IPC server is only reachable from the local host, so it serves the local-only methods
+    let ipc_config = http_config.clone();
+    let mut http_config = http_config;
+    http_config.deny_local_only_methods(http_addr);
+    let cors = http_config.cors_layer();
+    let http_access = Arc::new(HttpAccess::new(http_config));
 
     let http_router = Router::new()
-        .route("/", post(handle_http_request))
+        .route("/", post(ethrex_rpc::handle_http_request::<RpcApiContext>))
         .layer(cors)
+        .layer(Extension(http_access))
         .with_state(service_context.clone());
     let http_listener = TcpListener::bind(http_addr)
         .await
         .map_err(|error| RpcErr::Internal(error.to_string()))?;
-    let http_server = axum::serve(http_listener, http_router)
-        .with_graceful_shutdown(ethrex_rpc::shutdown_signal())
-        .into_future();
+    let http_server = axum::serve(
+        http_listener,
+        http_router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(ethrex_rpc::shutdown_signal())
+    .into_future();
     info!("Starting HTTP server at {http_addr}");
 
+    // Failing to serve IPC must not take down the HTTP server
+    let ipc_server = async move {
+        match ipc_path {
+            #[cfg(unix)]
+            Some(ipc_path) => {
+                if let Err(error) =
+                    ethrex_rpc::start_ipc_server(ipc_path.clone(), service_context, ipc_config)
+                        .await
+                {
+                    error!(
+                        "Failed to serve RPC over IPC at {}: {error}",
+                        ipc_path.display()
+                    );
+                }
+                Ok::<(), std::io::Error>(())
+            }
+            #[cfg(not(unix))]
+            Some(ipc_path) => {
+                tracing::warn!(
+                    "IPC is only supported on unix platforms, not serving RPC at {}",
+                    ipc_path.display()
+                );
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    };
+
     info!("Not starting Auth-RPC server. The address passed as argument is {authrpc_addr}");
 
-    let _ =
-        tokio::try_join!(http_server).inspect_err(|e| info!("Error shutting down servers: {e:?}"));
+    let _ = tokio::try_join!(http_server, ipc_server)
+        .inspect_err(|e| info!("Error shutting down servers: {e:?}"));
 
     Ok(())
 }
 
-async fn handle_http_request(
-    State(service_context): State<RpcApiContext>,
-    body: String,
-) -> Result<Json<Value>, StatusCode> {
-    let res = match serde_json::from_str::<RpcRequestWrapper>(&body) {
-        Ok(RpcRequestWrapper::Single(request)) => {
-            let res = map_http_requests(&request, service_context).await;
-            ethrex_rpc::rpc_response(request.id, res).map_err(|_| StatusCode::BAD_REQUEST)?
-        }
-        Ok(RpcRequestWrapper::Multiple(requests)) => {
-            let mut responses = Vec::new();
-            for req in requests {
-                let res = map_http_requests(&req, service_context.clone()).await;
-                responses.push(
-                    ethrex_rpc::rpc_response(req.id, res).map_err(|_| StatusCode::BAD_REQUEST)?,
-                );
-            }
-            serde_json::to_value(responses).map_err(|_| StatusCode::BAD_REQUEST)?
-        }
-        Err(_) => ethrex_rpc::rpc_response(
-            RpcRequestId::String("".to_string()),
-            Err(ethrex_rpc::RpcErr::BadParams(
-                "Invalid request body".to_string(),
-            )),
-        )
-        .map_err(|_| StatusCode::BAD_REQUEST)?,
-    };
-    Ok(Json(res))
+impl RpcService for RpcApiContext {
+    async fn handle_request(&self, req: &RpcRequest) -> Result<Value, RpcErrorMetadata> {
+        map_http_requests(req, self.clone())
+            .await
+            .map_err(Into::into)
+    }
 }
 
 /// Handle requests that can come from either clients or other users
@@ -182,6 +202,9 @@ pub async fn map_http_requests(req: &RpcRequest, context: RpcApiContext) -> Resu
             map_eth_requests(req, context).await
         }
         Ok(RpcNamespace::EthrexL2) => map_l2_requests(req, context).await,
+        Ok(RpcNamespace::L1RpcNamespace(ethrex_rpc::RpcNamespace::Admin)) => {
+            map_admin_requests(req, context).await
+        }
         _ => ethrex_rpc::map_http_requests(req, context.l1_ctx)
             .await
             .map_err(RpcErr::L1RpcErr),
@@ -211,6 +234,24 @@ pub async fn map_eth_requests(req: &RpcRequest, context: RpcApiContext) -> Resul
     }
 }
 
+pub async fn map_admin_requests(req: &RpcRequest, context: RpcApiContext) -> Result<Value, RpcErr> {
+    match req.method.as_str() {
+        // The checkpoint also has to include the rollup store
+        "admin_createCheckpoint" => {
+            let request = CreateCheckpointRequest::parse(&req.params)?;
+            let path = request.path(context.l1_ctx.checkpoint_dir.as_deref())?;
+            let manifest = context
+                .rollup_store
+                .create_checkpoint(&context.l1_ctx.storage, &path)
+                .await?;
+            Ok(serde_json::to_value(manifest)?)
+        }
+        _ => ethrex_rpc::map_http_requests(req, context.l1_ctx)
+            .await
+            .map_err(RpcErr::L1RpcErr),
+    }
+}
+
 pub async fn map_l2_requests(req: &RpcRequest, context: RpcApiContext) -> Result<Value, RpcErr> {
     match req.method.as_str() {
         "ethrex_sendTransaction" => SponsoredTx::call(req, context).await,
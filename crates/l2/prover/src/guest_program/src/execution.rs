@@ -8,6 +8,8 @@ use ethrex_blockchain::{
 use ethrex_common::types::AccountUpdate;
 use ethrex_common::types::block_execution_witness::ExecutionWitness;
 use ethrex_common::types::fee_config::FeeConfig;
+#[cfg(feature = "l2")]
+use ethrex_common::types::precompiles::CustomPrecompileSpec;
 use ethrex_common::types::{
     block_execution_witness::GuestProgramState, block_execution_witness::GuestProgramStateError,
 };
@@ -18,7 +20,9 @@ use ethrex_common::{
 };
 #[cfg(feature = "l2")]
 use ethrex_l2_common::l1_messages::L1Message;
-use ethrex_vm::{Evm, EvmError, GuestProgramStateWrapper, VmDatabase};
+#[cfg(feature = "l2")]
+use ethrex_l2_common::precompiles::l2_custom_precompiles;
+use ethrex_vm::{CustomPrecompiles, Evm, EvmError, GuestProgramStateWrapper, VmDatabase};
 use std::collections::{BTreeMap, HashMap};
 
 #[cfg(feature = "l2")]
@@ -68,6 +72,9 @@ pub enum StatelessExecutionError {
     #[cfg(feature = "l2")]
     #[error("FeeConfig not provided for L2 execution")]
     FeeConfigNotFound,
+    #[cfg(feature = "l2")]
+    #[error("The custom precompiles of the guest program don't match those of the sequencer")]
+    CustomPrecompilesMismatch,
     #[error("Batch has no blocks")]
     EmptyBatchError,
     #[error("Invalid database")]
@@ -102,6 +109,7 @@ pub fn execution_program(input: ProgramInput) -> Result<ProgramOutput, Stateless
         execution_witness,
         elasticity_multiplier,
         fee_config: _fee_config,
+        custom_precompiles: _custom_precompiles,
         #[cfg(feature = "l2")]
         blob_commitment,
         #[cfg(feature = "l2")]
@@ -117,6 +125,7 @@ pub fn execution_program(input: ProgramInput) -> Result<ProgramOutput, Stateless
             execution_witness,
             elasticity_multiplier,
             _fee_config,
+            &_custom_precompiles,
             blob_commitment,
             blob_proof,
             chain_id,
@@ -138,7 +147,13 @@ pub fn stateless_validation_l1(
         last_block_hash,
         non_privileged_count,
         ..
-    } = execute_stateless(blocks, execution_witness, elasticity_multiplier, None)?;
+    } = execute_stateless(
+        blocks,
+        execution_witness,
+        elasticity_multiplier,
+        None,
+        CustomPrecompiles::default(),
+    )?;
 
     Ok(ProgramOutput {
         initial_state_hash,
@@ -161,10 +176,18 @@ pub fn stateless_validation_l2(
     execution_witness: ExecutionWitness,
     elasticity_multiplier: u64,
     fee_config: Option<FeeConfig>,
+    custom_precompiles: &[CustomPrecompileSpec],
     blob_commitment: Commitment,
     blob_proof: Proof,
     chain_id: u64,
 ) -> Result<ProgramOutput, StatelessExecutionError> {
+    // The precompiles are code of the guest, so they can't come in the input. Checking them
+    // against those the sequencer used makes a mismatch fail loudly instead of diverging.
+    let precompiles = l2_custom_precompiles()
+        .map_err(|error| StatelessExecutionError::Internal(error.to_string()))?;
+    if precompiles.specs() != custom_precompiles {
+        return Err(StatelessExecutionError::CustomPrecompilesMismatch);
+    }
     let initial_db = execution_witness.clone();
 
     let StatelessResult {
@@ -178,7 +201,13 @@ pub fn stateless_validation_l2(
         nodes_hashed,
         codes_hashed,
         parent_block_header,
-    } = execute_stateless(blocks, execution_witness, elasticity_multiplier, fee_config)?;
+    } = execute_stateless(
+        blocks,
+        execution_witness,
+        elasticity_multiplier,
+        fee_config,
+        precompiles,
+    )?;
 
     let (l1messages, privileged_transactions) =
         get_batch_l1messages_and_privileged_transactions(blocks, &receipts)?;
@@ -263,11 +292,23 @@ fn execute_stateless(
     execution_witness: ExecutionWitness,
     elasticity_multiplier: u64,
     fee_config: Option<FeeConfig>,
+    precompiles: CustomPrecompiles,
 ) -> Result<StatelessResult, StatelessExecutionError> {
-    let guest_program_state: GuestProgramState = execution_witness
+    let mut guest_program_state: GuestProgramState = execution_witness
         .try_into()
         .map_err(StatelessExecutionError::GuestProgramState)?;
 
+    // Compact witnesses leave out the headers of the executed blocks, so take them from the blocks.
+    // They are validated along with the witness headers when checking the chain of block hashes.
+    if let Some((_last_block, executed_blocks)) = blocks.split_last() {
+        for block in executed_blocks {
+            guest_program_state
+                .block_headers
+                .entry(block.header.number)
+                .or_insert_with(|| block.header.clone());
+        }
+    }
+
     // Cache these L2-specific state fields for later state diff blob validation
     // to avoid expensive recomputation after the guest_program_state is moved
     // to the wrapper
@@ -331,7 +372,8 @@ fn execute_stateless(
         let mut vm = Evm::new_for_l2(
             wrapped_db.clone(),
             fee_config.ok_or_else(|| StatelessExecutionError::FeeConfigNotFound)?,
-        )?;
+        )?
+        .with_precompiles(precompiles.clone());
         #[cfg(not(feature = "l2"))]
         let mut vm = Evm::new_for_l1(wrapped_db.clone());
         let result = vm
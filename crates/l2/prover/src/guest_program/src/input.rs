@@ -1,5 +1,6 @@
 use ethrex_common::types::{
     Block, block_execution_witness::ExecutionWitness, fee_config::FeeConfig,
+    precompiles::CustomPrecompileSpec,
 };
 use rkyv::{Archive, Deserialize as RDeserialize, Serialize as RSerialize};
 use serde::{Deserialize, Serialize};
@@ -20,6 +21,8 @@ pub struct ProgramInput {
     pub elasticity_multiplier: u64,
     /// Configuration for L2 fees
     pub fee_config: Option<FeeConfig>,
+    /// Custom precompiles the L2 blocks were executed with, which must be the ones of the guest
+    pub custom_precompiles: Vec<CustomPrecompileSpec>,
     #[cfg(feature = "l2")]
     /// KZG commitment to the blob data
     #[serde_as(as = "[_; 48]")]
@@ -37,6 +40,7 @@ impl Default for ProgramInput {
             execution_witness: ExecutionWitness::default(),
             elasticity_multiplier: Default::default(),
             fee_config: None,
+            custom_precompiles: Vec::new(),
             #[cfg(feature = "l2")]
             blob_commitment: [0; 48],
             #[cfg(feature = "l2")]
@@ -131,6 +131,7 @@ impl Prover {
                 #[cfg(feature = "l2")]
                 blob_proof: input.blob_proof,
                 fee_config: Some(input.fee_config),
+                custom_precompiles: input.custom_precompiles,
             },
         }))
     }
@@ -190,6 +190,7 @@ impl BlockProducer {
         let execution_result = BlockExecutionResult {
             receipts: payload_build_result.receipts,
             requests: Vec::new(),
+            senders: Vec::new(),
         };
 
         let account_updates_list = self
@@ -46,6 +46,7 @@ pub async fn build_payload(
 
     debug!("Building payload");
     let mut context = PayloadBuildContext::new(payload, store, blockchain.options.r#type.clone())?;
+    blockchain.configure_evm(&mut context.vm);
 
     fill_transactions(
         blockchain.clone(),
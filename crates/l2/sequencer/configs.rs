@@ -67,6 +67,7 @@ pub struct ProofCoordinatorConfig {
     pub validium: bool,
     pub tdx_private_key: Option<SecretKey>,
     pub qpl_tool_path: Option<String>,
+    pub compact_witness: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -53,6 +53,8 @@ pub enum SequencerError {
     Admin(#[from] AdminError),
     #[error("Block gas limit cannot be greater than batch gas limit")]
     GasLimitError,
+    #[error("Custom hooks can't be installed in a sequencer whose batches are proven")]
+    CustomHooksNotProvable,
 }
 
 #[derive(Debug, thiserror::Error)]
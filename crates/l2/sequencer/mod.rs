@@ -73,6 +73,13 @@ pub async fn start_l2(
         return Ok(());
     };
 
+    // Hooks are code of the embedder, so they can't be sent to the prover and the guest program
+    // would execute the batches without them
+    if !needed_proof_types.is_empty() && !blockchain.options.hooks.is_empty() {
+        error!("Custom hooks can't be installed in a sequencer whose batches are proven.");
+        return Err(errors::SequencerError::CustomHooksNotProvable);
+    }
+
     if needed_proof_types.contains(&ProverType::Aligned) && !cfg.aligned.aligned_mode {
         error!(
             "Aligned mode is required. Please set the `--aligned` flag or use the `ALIGNED_MODE` environment variable to true."
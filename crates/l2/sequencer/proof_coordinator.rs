@@ -7,8 +7,9 @@ use crate::{
 use bytes::Bytes;
 use ethrex_blockchain::{Blockchain, BlockchainType};
 use ethrex_common::types::BlobsBundle;
-use ethrex_common::types::block_execution_witness::ExecutionWitness;
+use ethrex_common::types::block_execution_witness::{ExecutionWitness, WitnessOptions};
 use ethrex_common::types::fee_config::FeeConfig;
+use ethrex_common::types::precompiles::CustomPrecompileSpec;
 use ethrex_common::{
     Address,
     types::{Block, blobs_bundle},
@@ -51,6 +52,8 @@ pub struct ProverInputData {
     #[serde_as(as = "[_; 48]")]
     pub blob_proof: blobs_bundle::Proof,
     pub fee_config: FeeConfig,
+    /// Custom precompiles the blocks were executed with
+    pub custom_precompiles: Vec<CustomPrecompileSpec>,
 }
 
 /// Enum for the ProverServer <--> ProverClient Communication Protocol.
@@ -186,6 +189,7 @@ pub struct ProofCoordinator {
     #[cfg(feature = "metrics")]
     request_timestamp: Arc<Mutex<HashMap<u64, SystemTime>>>,
     qpl_tool_path: Option<String>,
+    witness_options: WitnessOptions,
 }
 
 impl ProofCoordinator {
@@ -236,6 +240,11 @@ impl ProofCoordinator {
             #[cfg(feature = "metrics")]
             request_timestamp: Arc::new(Mutex::new(HashMap::new())),
             qpl_tool_path: config.qpl_tool_path.clone(),
+            witness_options: if config.compact_witness {
+                WitnessOptions::compact()
+            } else {
+                WitnessOptions::default()
+            },
         })
     }
 
@@ -477,9 +486,15 @@ impl ProofCoordinator {
 
         let witness = self
             .blockchain
-            .generate_witness_for_blocks(&blocks)
+            .generate_witness_for_blocks_with_options(&blocks, self.witness_options)
             .await
             .map_err(ProofCoordinatorError::from)?;
+        if let Ok(stats) = witness.stats() {
+            debug!(
+                "Execution witness for batch {batch_number} takes {} bytes: {stats:?}",
+                stats.total()
+            );
+        }
 
         // Get blobs bundle cached by the L1 Committer (blob, commitment, proof)
         let (blob_commitment, blob_proof) = if self.validium {
@@ -518,6 +533,7 @@ impl ProofCoordinator {
             #[cfg(feature = "l2")]
             blob_proof,
             fee_config,
+            custom_precompiles: self.blockchain.options.precompiles.specs(),
         })
     }
 
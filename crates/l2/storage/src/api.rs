@@ -1,6 +1,6 @@
 // Storage API for L2
 
-use std::fmt::Debug;
+use std::{fmt::Debug, path::Path};
 
 use ethrex_common::{
     H256,
@@ -147,4 +147,7 @@ pub trait StoreEngineRollup: Debug + Send + Sync {
     ) -> Result<(), RollupStoreError>;
 
     async fn revert_to_batch(&self, batch_number: u64) -> Result<(), RollupStoreError>;
+
+    /// Writes a consistent copy of the store to `path`, which must not exist yet
+    async fn create_checkpoint(&self, path: &Path) -> Result<(), RollupStoreError>;
 }
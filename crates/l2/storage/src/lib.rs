@@ -4,4 +4,4 @@ mod store;
 mod store_db;
 
 pub use error::RollupStoreError;
-pub use store::{EngineType as EngineTypeRollup, Store as StoreRollup};
+pub use store::{EngineType as EngineTypeRollup, ROLLUP_STORE_FILE, Store as StoreRollup};
@@ -1,4 +1,7 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::api::StoreEngineRollup;
 use crate::error::RollupStoreError;
@@ -10,8 +13,12 @@ use ethrex_common::{
     types::{AccountUpdate, Blob, BlobsBundle, BlockNumber, batch::Batch},
 };
 use ethrex_l2_common::prover::{BatchProof, ProverType};
+use ethrex_storage::{CheckpointManifest, Store as L1Store};
 use tracing::info;
 
+/// Name of the rollup store file inside the datadir
+pub const ROLLUP_STORE_FILE: &str = "rollup_store";
+
 #[derive(Debug, Clone)]
 pub struct Store {
     engine: Arc<dyn StoreEngineRollup>,
@@ -346,6 +353,38 @@ impl Store {
         self.engine.revert_to_batch(batch_number).await
     }
 
+    /// Creates a consistent copy of both the L1 store and this one at `path`, which must not exist yet,
+    /// without stopping the node. The rollup store is placed at `rollup_store` inside it, mirroring the datadir.
+    /// It is copied first so that every block referenced by its batches is also in the copy of the L1 store
+    pub async fn create_checkpoint(
+        &self,
+        store: &L1Store,
+        path: &Path,
+    ) -> Result<CheckpointManifest, RollupStoreError> {
+        if path.exists() {
+            return Err(RollupStoreError::Custom(format!(
+                "Checkpoint path {} already exists",
+                path.display()
+            )));
+        }
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".rollup_store");
+        let temp_path = PathBuf::from(temp_path);
+        self.engine.create_checkpoint(&temp_path).await?;
+
+        let manifest = match store.create_checkpoint(path).await {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                let _ = std::fs::remove_file(&temp_path);
+                return Err(RollupStoreError::Custom(error.to_string()));
+            }
+        };
+        std::fs::rename(&temp_path, path.join(ROLLUP_STORE_FILE)).map_err(|error| {
+            RollupStoreError::Custom(format!("Failed to move rollup store checkpoint: {error}"))
+        })?;
+        Ok(manifest)
+    }
+
     pub async fn delete_proof_by_batch_and_type(
         &self,
         batch_number: u64,
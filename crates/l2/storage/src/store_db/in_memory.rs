@@ -1,6 +1,7 @@
 use std::{
     collections::HashMap,
     fmt::Debug,
+    path::Path,
     sync::{Arc, Mutex, MutexGuard},
 };
 
@@ -334,6 +335,12 @@ impl StoreEngineRollup for Store {
     async fn get_last_batch_number(&self) -> Result<Option<u64>, RollupStoreError> {
         Ok(self.inner()?.state_roots.keys().max().cloned())
     }
+
+    async fn create_checkpoint(&self, _path: &Path) -> Result<(), RollupStoreError> {
+        Err(RollupStoreError::Custom(
+            "Checkpoints are not supported by the in-memory store".to_string(),
+        ))
+    }
 }
 
 impl Debug for Store {
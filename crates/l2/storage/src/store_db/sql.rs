@@ -584,6 +584,21 @@ impl StoreEngineRollup for SQLStore {
         self.execute_in_tx(queries, None).await
     }
 
+    async fn create_checkpoint(&self, path: &Path) -> Result<(), RollupStoreError> {
+        if path.exists() {
+            return Err(RollupStoreError::Custom(format!(
+                "Checkpoint path {} already exists",
+                path.display()
+            )));
+        }
+        let path = path.to_str().ok_or_else(|| {
+            RollupStoreError::Custom("Checkpoint path is not valid unicode".to_string())
+        })?;
+        // VACUUM INTO reads the database in a single transaction, so concurrent writes are left out
+        self.read_conn.execute("VACUUM INTO ?1", [path]).await?;
+        Ok(())
+    }
+
     async fn store_proof_by_batch_and_type(
         &self,
         batch_number: u64,
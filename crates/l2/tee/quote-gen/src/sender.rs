@@ -34,6 +34,7 @@ pub async fn get_batch(commit_hash: String) -> Result<(u64, ProgramInput), Strin
                     #[cfg(feature = "l2")]
                     blob_proof: input.blob_proof,
                     fee_config: Some(input.fee_config),
+                    custom_precompiles: input.custom_precompiles,
                 },
             )),
             _ => Err("No blocks to prove.".to_owned()),
@@ -271,7 +271,8 @@ impl RLPEncode for PingMessage {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, RLPEncode, RLPDecode)]
+#[rlp(allow_trailing)]
 pub struct FindNodeMessage {
     /// The target is a 64-byte secp256k1 public key.
     pub target: H512,
@@ -287,26 +288,6 @@ impl FindNodeMessage {
     }
 }
 
-impl RLPEncode for FindNodeMessage {
-    fn encode(&self, buf: &mut dyn BufMut) {
-        structs::Encoder::new(buf)
-            .encode_field(&self.target)
-            .encode_field(&self.expiration)
-            .finish();
-    }
-}
-
-impl RLPDecode for FindNodeMessage {
-    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
-        let decoder = Decoder::new(rlp)?;
-        let (target, decoder) = decoder.decode_field("target")?;
-        let (expiration, decoder) = decoder.decode_field("expiration")?;
-        let remaining = decoder.finish_unchecked();
-        let msg = FindNodeMessage { target, expiration };
-        Ok((msg, remaining))
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct FindNodeRequest {
     /// the number of nodes sent
@@ -9,7 +9,7 @@ use std::{
 };
 
 use ethrex_common::H256;
-use prometheus::{Gauge, IntCounter, Registry};
+use prometheus::{Gauge, IntCounter, IntCounterVec, Opts, Registry};
 use tokio::sync::Mutex;
 
 use crate::rlpx::{error::PeerConnectionError, p2p::DisconnectReason};
@@ -99,6 +99,14 @@ pub struct Metrics {
     pub bytecode_download_start_time: Arc<Mutex<Option<SystemTime>>>,
     pub bytecode_download_end_time: Arc<Mutex<Option<SystemTime>>>,
 
+    /* Snap Serving */
+    /// Bytes of data and proofs served to peers, grouped by response message
+    pub snap_served_bytes: IntCounterVec,
+    /// Requests answered with an empty response because the peer exceeded its serving quota
+    pub snap_throttled_requests: IntCounterVec,
+    pub snap_proof_cache_hits: IntCounter,
+    pub snap_proof_cache_misses: IntCounter,
+
     start_time: SystemTime,
 }
 
@@ -633,6 +641,52 @@ impl Default for Metrics {
             .register(Box::new(storage_tries_state_roots_computed.clone()))
             .expect("Failed to register storage_tries_state_roots_computed counter");
 
+        let snap_served_bytes = IntCounterVec::new(
+            Opts::new(
+                "snap_served_bytes",
+                "Total bytes served to peers through snap responses",
+            ),
+            &["message"],
+        )
+        .expect("Failed to create snap_served_bytes counter");
+
+        let snap_throttled_requests = IntCounterVec::new(
+            Opts::new(
+                "snap_throttled_requests",
+                "Total snap requests not served because the peer exceeded its quota",
+            ),
+            &["message"],
+        )
+        .expect("Failed to create snap_throttled_requests counter");
+
+        let snap_proof_cache_hits = IntCounter::new(
+            "snap_proof_cache_hits",
+            "Total boundary proofs served from the snap proof cache",
+        )
+        .expect("Failed to create snap_proof_cache_hits counter");
+
+        let snap_proof_cache_misses = IntCounter::new(
+            "snap_proof_cache_misses",
+            "Total boundary proofs built from the trie when serving snap requests",
+        )
+        .expect("Failed to create snap_proof_cache_misses counter");
+
+        registry
+            .register(Box::new(snap_served_bytes.clone()))
+            .expect("Failed to register snap_served_bytes counter");
+
+        registry
+            .register(Box::new(snap_throttled_requests.clone()))
+            .expect("Failed to register snap_throttled_requests counter");
+
+        registry
+            .register(Box::new(snap_proof_cache_hits.clone()))
+            .expect("Failed to register snap_proof_cache_hits counter");
+
+        registry
+            .register(Box::new(snap_proof_cache_misses.clone()))
+            .expect("Failed to register snap_proof_cache_misses counter");
+
         Metrics {
             _registry: registry,
             enabled: Arc::new(Mutex::new(false)),
@@ -710,6 +764,12 @@ impl Default for Metrics {
             bytecode_download_start_time: Arc::new(Mutex::new(None)),
             bytecode_download_end_time: Arc::new(Mutex::new(None)),
 
+            // Snap serving
+            snap_served_bytes,
+            snap_throttled_requests,
+            snap_proof_cache_hits,
+            snap_proof_cache_misses,
+
             start_time: SystemTime::now(),
         }
     }
@@ -11,6 +11,7 @@ use crate::{
         message::Message,
         p2p::SUPPORTED_SNAP_CAPABILITIES,
     },
+    snap::SnapServeQuotas,
     tx_broadcaster::{TxBroadcaster, TxBroadcasterError},
     types::{Node, NodeRecord},
 };
@@ -46,6 +47,7 @@ pub struct P2PContext {
     pub client_version: String,
     pub based_context: Option<P2PBasedContext>,
     pub tx_broadcaster: GenServerHandle<TxBroadcaster>,
+    pub(crate) snap_quotas: SnapServeQuotas,
 }
 
 impl P2PContext {
@@ -89,6 +91,7 @@ impl P2PContext {
             client_version,
             based_context,
             tx_broadcaster,
+            snap_quotas: SnapServeQuotas::default(),
         })
     }
 }
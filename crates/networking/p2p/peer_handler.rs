@@ -146,8 +146,8 @@ impl PeerHandler {
         Self { peer_table }
     }
 
-    /// Creates a dummy PeerHandler for tests where interacting with peers is not needed
-    /// This should only be used in tests as it won't be able to interact with the node's connected peers
+    /// Creates a dummy PeerHandler for tests and read-only nodes where interacting with peers is not needed
+    /// It won't be able to interact with the node's connected peers
     pub fn dummy() -> PeerHandler {
         PeerHandler::new(PeerTable::spawn(TARGET_PEERS))
     }
@@ -145,6 +145,7 @@ pub(crate) async fn perform(
                 .map_or_else(|| L2ConnState::Unsupported, L2ConnState::Disconnected),
             tx_broadcaster: context.tx_broadcaster.clone(),
             current_requests: HashMap::new(),
+            snap_quotas: context.snap_quotas.clone(),
         },
         stream,
     ))
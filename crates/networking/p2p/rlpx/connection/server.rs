@@ -26,11 +26,12 @@ use crate::{
             self, Capability, DisconnectMessage, DisconnectReason, PingMessage, PongMessage,
             SUPPORTED_ETH_CAPABILITIES, SUPPORTED_SNAP_CAPABILITIES,
         },
+        snap::{AccountRange, ByteCodes, StorageRanges, TrieNodes},
         utils::{log_peer_debug, log_peer_error, log_peer_warn},
     },
     snap::{
-        process_account_range_request, process_byte_codes_request, process_storage_ranges_request,
-        process_trie_nodes_request,
+        SnapResponse, SnapServeQuotas, process_account_range_request, process_byte_codes_request,
+        process_storage_ranges_request, process_trie_nodes_request,
     },
     tx_broadcaster::{InMessage, TxBroadcaster, send_tx_hashes},
     types::Node,
@@ -186,6 +187,8 @@ pub struct Established {
     pub(crate) l2_state: L2ConnState,
     pub(crate) tx_broadcaster: GenServerHandle<TxBroadcaster>,
     pub(crate) current_requests: HashMap<u64, (String, oneshot::Sender<Message>)>,
+    /// Bytes each peer can still be served through snap requests, shared by all connections
+    pub(crate) snap_quotas: SnapServeQuotas,
 }
 
 impl Established {
@@ -825,6 +828,40 @@ pub(crate) async fn send(
     state.sink.send(message).await
 }
 
+/// Returns the amount of bytes a snap request from the peer can be answered with, or `None` if
+/// the peer used up its serving quota and should receive an empty response instead
+fn snap_response_budget<T: SnapResponse>(state: &Established, requested_bytes: u64) -> Option<u64> {
+    let budget = state
+        .snap_quotas
+        .response_budget(state.node.node_id(), requested_bytes);
+    if budget.is_none() {
+        METRICS
+            .snap_throttled_requests
+            .with_label_values(&[T::NAME])
+            .inc();
+        log_peer_debug(
+            &state.node,
+            &format!(
+                "Snap serving quota exceeded, replying with an empty {}",
+                T::NAME
+            ),
+        );
+    }
+    budget
+}
+
+/// Charges a snap response to the peer's serving quota
+fn charge_snap_response<T: SnapResponse>(state: &Established, response: &T) {
+    let served_bytes = response.served_bytes();
+    state
+        .snap_quotas
+        .consume(state.node.node_id(), served_bytes);
+    METRICS
+        .snap_served_bytes
+        .with_label_values(&[T::NAME])
+        .inc_by(served_bytes);
+}
+
 /// Reads from the frame until a frame is available.
 ///
 /// Returns `None` when the stream buffer is 0. This could indicate that the client has disconnected,
@@ -885,8 +922,15 @@ async fn handle_incoming_message(
                 backend::validate_status(msg_data, &state.storage, eth).await?
             };
         }
-        Message::GetAccountRange(req) => {
-            let response = process_account_range_request(req, state.storage.clone()).await?;
+        Message::GetAccountRange(mut req) => {
+            let response = match snap_response_budget::<AccountRange>(state, req.response_bytes) {
+                Some(budget) => {
+                    req.response_bytes = budget;
+                    process_account_range_request(req, state.storage.clone()).await?
+                }
+                None => AccountRange::empty(req.id),
+            };
+            charge_snap_response(state, &response);
             send(state, Message::AccountRange(response)).await?
         }
         Message::Transactions(txs) if peer_supports_eth => {
@@ -1005,24 +1049,46 @@ async fn handle_incoming_message(
                     .await?;
             }
         }
-        Message::GetStorageRanges(req) => {
-            let response = process_storage_ranges_request(req, state.storage.clone()).await?;
+        Message::GetStorageRanges(mut req) => {
+            let response = match snap_response_budget::<StorageRanges>(state, req.response_bytes) {
+                Some(budget) => {
+                    req.response_bytes = budget;
+                    process_storage_ranges_request(req, state.storage.clone()).await?
+                }
+                None => StorageRanges::empty(req.id),
+            };
+            charge_snap_response(state, &response);
             send(state, Message::StorageRanges(response)).await?
         }
-        Message::GetByteCodes(req) => {
-            let storage_clone = state.storage.clone();
-            let response =
-                tokio::task::spawn_blocking(move || process_byte_codes_request(req, storage_clone))
+        Message::GetByteCodes(mut req) => {
+            let response = match snap_response_budget::<ByteCodes>(state, req.bytes) {
+                Some(budget) => {
+                    req.bytes = budget;
+                    let storage_clone = state.storage.clone();
+                    tokio::task::spawn_blocking(move || {
+                        process_byte_codes_request(req, storage_clone)
+                    })
                     .await
                     .map_err(|_| {
                         PeerConnectionError::InternalError(
                             "Failed to execute bytecode retrieval task".to_string(),
                         )
-                    })??;
+                    })??
+                }
+                None => ByteCodes::empty(req.id),
+            };
+            charge_snap_response(state, &response);
             send(state, Message::ByteCodes(response)).await?
         }
-        Message::GetTrieNodes(req) => {
-            let response = process_trie_nodes_request(req, state.storage.clone()).await?;
+        Message::GetTrieNodes(mut req) => {
+            let response = match snap_response_budget::<TrieNodes>(state, req.bytes) {
+                Some(budget) => {
+                    req.bytes = budget;
+                    process_trie_nodes_request(req, state.storage.clone()).await?
+                }
+                None => TrieNodes::empty(req.id),
+            };
+            charge_snap_response(state, &response);
             send(state, Message::TrieNodes(response)).await?
         }
         Message::L2(req) if peer_supports_l2 => {
@@ -1,15 +1,299 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, LazyLock, Mutex},
+    time::Instant,
+};
+
 use bytes::Bytes;
+use ethrex_common::H256;
 use ethrex_rlp::encode::RLPEncode;
 use ethrex_storage::{Store, error::StoreError};
 
-use crate::rlpx::{
-    error::PeerConnectionError,
-    snap::{
-        AccountRange, AccountRangeUnit, AccountStateSlim, ByteCodes, GetAccountRange, GetByteCodes,
-        GetStorageRanges, GetTrieNodes, StorageRanges, StorageSlot, TrieNodes,
+use crate::{
+    metrics::METRICS,
+    rlpx::{
+        error::PeerConnectionError,
+        snap::{
+            AccountRange, AccountRangeUnit, AccountStateSlim, ByteCodes, GetAccountRange,
+            GetByteCodes, GetStorageRanges, GetTrieNodes, StorageRanges, StorageSlot, TrieNodes,
+        },
     },
 };
 
+// Serving limits
+
+/// Maximum amount of bytes served in a single response, regardless of the amount requested
+pub const SNAP_SOFT_RESPONSE_LIMIT: u64 = 2 * 1024 * 1024;
+/// Rate at which each peer's serving quota is refilled, in bytes per second
+pub const SNAP_PEER_QUOTA_BYTES_PER_SEC: u64 = 4 * 1024 * 1024;
+/// Maximum serving quota of a peer, which it can use in a burst of requests
+pub const SNAP_PEER_QUOTA_BURST_BYTES: u64 = 16 * 1024 * 1024;
+/// Minimum amount of bytes a peer must have left in its quota to be served a response, so an
+/// almost exhausted quota doesn't keep answering with a stream of tiny responses
+pub const SNAP_MIN_RESPONSE_BYTES: u64 = 64 * 1024;
+/// Amount of peers whose serving quota is tracked before forgetting the ones with a full quota
+const SNAP_QUOTA_TRACKED_PEERS: usize = 1024;
+/// Amount of boundary proofs kept in the proof cache
+const BOUNDARY_PROOF_CACHE_SIZE: usize = 1024;
+
+/// Token bucket limiting the bytes served to a single peer through snap requests
+#[derive(Debug)]
+pub struct SnapServeQuota {
+    available_bytes: u64,
+    last_refill: Instant,
+}
+
+impl Default for SnapServeQuota {
+    fn default() -> Self {
+        Self {
+            available_bytes: SNAP_PEER_QUOTA_BURST_BYTES,
+            last_refill: Instant::now(),
+        }
+    }
+}
+
+impl SnapServeQuota {
+    /// Returns the amount of bytes that can be served for a request asking for `requested_bytes`,
+    /// or `None` if the peer has less than [`SNAP_MIN_RESPONSE_BYTES`] left (or less than the
+    /// requested bytes, for smaller requests) and the request shouldn't be served
+    pub fn response_budget(&mut self, requested_bytes: u64) -> Option<u64> {
+        self.refill();
+        let min_budget = requested_bytes.clamp(1, SNAP_MIN_RESPONSE_BYTES);
+        (self.available_bytes >= min_budget).then_some(requested_bytes.min(self.available_bytes))
+    }
+
+    /// Charges the bytes of a served response to the quota
+    pub fn consume(&mut self, served_bytes: u64) {
+        self.available_bytes = self.available_bytes.saturating_sub(served_bytes);
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let refill = now.duration_since(self.last_refill).as_secs_f64()
+            * SNAP_PEER_QUOTA_BYTES_PER_SEC as f64;
+        self.available_bytes = self
+            .available_bytes
+            .saturating_add(refill as u64)
+            .min(SNAP_PEER_QUOTA_BURST_BYTES);
+        self.last_refill = now;
+    }
+}
+
+/// Serving quotas of every peer, keyed by node id so reconnecting doesn't reset a peer's quota.
+/// Cloning it shares the quotas.
+#[derive(Debug, Clone, Default)]
+pub struct SnapServeQuotas(Arc<Mutex<HashMap<H256, SnapServeQuota>>>);
+
+impl SnapServeQuotas {
+    /// Same as [`SnapServeQuota::response_budget`] for the quota of the given peer
+    pub fn response_budget(&self, node_id: H256, requested_bytes: u64) -> Option<u64> {
+        let mut quotas = self.0.lock().ok()?;
+        if !quotas.contains_key(&node_id) && quotas.len() >= SNAP_QUOTA_TRACKED_PEERS {
+            // A full quota is the same as an untracked one
+            quotas.retain(|_, quota| {
+                quota.refill();
+                quota.available_bytes < SNAP_PEER_QUOTA_BURST_BYTES
+            });
+        }
+        quotas
+            .entry(node_id)
+            .or_default()
+            .response_budget(requested_bytes)
+    }
+
+    /// Charges the bytes of a response served to the given peer to its quota
+    pub fn consume(&self, node_id: H256, served_bytes: u64) {
+        if let Ok(mut quotas) = self.0.lock() {
+            quotas.entry(node_id).or_default().consume(served_bytes);
+        }
+    }
+}
+
+/// Snap messages sent in response to a request
+pub trait SnapResponse {
+    /// Name of the message, used to label the metrics
+    const NAME: &'static str;
+
+    /// Response for a request that won't be served, peers treat it as if we didn't have the data
+    fn empty(id: u64) -> Self;
+
+    /// Bytes of data and proofs contained in the response
+    fn served_bytes(&self) -> u64;
+}
+
+impl SnapResponse for AccountRange {
+    const NAME: &'static str = "AccountRange";
+
+    fn empty(id: u64) -> Self {
+        AccountRange {
+            id,
+            accounts: vec![],
+            proof: vec![],
+        }
+    }
+
+    fn served_bytes(&self) -> u64 {
+        self.accounts
+            .iter()
+            .map(|unit| 32 + unit.account.length() as u64)
+            .sum::<u64>()
+            + items_len(&self.proof)
+    }
+}
+
+impl SnapResponse for StorageRanges {
+    const NAME: &'static str = "StorageRanges";
+
+    fn empty(id: u64) -> Self {
+        StorageRanges {
+            id,
+            slots: vec![],
+            proof: vec![],
+        }
+    }
+
+    fn served_bytes(&self) -> u64 {
+        self.slots
+            .iter()
+            .flatten()
+            .map(|slot| 32 + slot.data.length() as u64)
+            .sum::<u64>()
+            + items_len(&self.proof)
+    }
+}
+
+impl SnapResponse for ByteCodes {
+    const NAME: &'static str = "ByteCodes";
+
+    fn empty(id: u64) -> Self {
+        ByteCodes { id, codes: vec![] }
+    }
+
+    fn served_bytes(&self) -> u64 {
+        items_len(&self.codes)
+    }
+}
+
+impl SnapResponse for TrieNodes {
+    const NAME: &'static str = "TrieNodes";
+
+    fn empty(id: u64) -> Self {
+        TrieNodes { id, nodes: vec![] }
+    }
+
+    fn served_bytes(&self) -> u64 {
+        items_len(&self.nodes)
+    }
+}
+
+fn items_len(items: &[Bytes]) -> u64 {
+    items.iter().map(|item| item.len() as u64).sum()
+}
+
+/// Identifies the proof of a single key: the state root, the hashed address of the account if
+/// the key belongs to its storage trie, and the key
+type BoundaryProofKey = (H256, Option<H256>, H256);
+
+/// Proofs for the boundaries of recently served ranges. Syncing peers split the key space in the
+/// same chunks and retry failed requests with other servers, so the same boundaries are requested
+/// repeatedly while the state root stays the same.
+#[derive(Default)]
+struct BoundaryProofCache {
+    proofs: HashMap<BoundaryProofKey, Arc<Vec<Vec<u8>>>>,
+    insertion_order: VecDeque<BoundaryProofKey>,
+}
+
+static BOUNDARY_PROOF_CACHE: LazyLock<Mutex<BoundaryProofCache>> = LazyLock::new(Default::default);
+
+impl BoundaryProofCache {
+    fn insert(&mut self, key: BoundaryProofKey, proof: Arc<Vec<Vec<u8>>>) {
+        if self.proofs.insert(key, proof).is_none() {
+            self.insertion_order.push_back(key);
+        }
+        if self.insertion_order.len() > BOUNDARY_PROOF_CACHE_SIZE
+            && let Some(oldest) = self.insertion_order.pop_front()
+        {
+            self.proofs.remove(&oldest);
+        }
+    }
+}
+
+/// Returns the proof of a single key from the cache, building it if it's not cached
+fn boundary_proof(
+    key: BoundaryProofKey,
+    build: impl FnOnce() -> Result<Vec<Vec<u8>>, StoreError>,
+) -> Result<Arc<Vec<Vec<u8>>>, StoreError> {
+    let cached = BOUNDARY_PROOF_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.proofs.get(&key).cloned());
+    if let Some(proof) = cached {
+        METRICS.snap_proof_cache_hits.inc();
+        return Ok(proof);
+    }
+    METRICS.snap_proof_cache_misses.inc();
+    let proof = Arc::new(build()?);
+    if let Ok(mut cache) = BOUNDARY_PROOF_CACHE.lock() {
+        cache.insert(key, proof.clone());
+    }
+    Ok(proof)
+}
+
+/// Joins the proofs of the boundaries of a range, skipping the nodes they have in common
+fn range_proof(start_proof: &[Vec<u8>], end_proof: Option<&[Vec<u8>]>) -> Vec<Bytes> {
+    let mut included = HashSet::new();
+    start_proof
+        .iter()
+        .chain(end_proof.unwrap_or_default())
+        .map(Vec::as_slice)
+        .filter(|node| included.insert(*node))
+        .map(Bytes::copy_from_slice)
+        .collect()
+}
+
+/// Proof of the range between `starting_hash` and `last_hash` of the state trie
+fn account_range_proof(
+    store: &Store,
+    root_hash: H256,
+    starting_hash: H256,
+    last_hash: Option<H256>,
+) -> Result<Vec<Bytes>, StoreError> {
+    let proof = |hash: H256| {
+        boundary_proof((root_hash, None, hash), || {
+            store.get_account_range_proof(root_hash, hash, None)
+        })
+    };
+    let start_proof = proof(starting_hash)?;
+    let end_proof = last_hash.map(proof).transpose()?;
+    Ok(range_proof(
+        &start_proof,
+        end_proof.as_deref().map(Vec::as_slice),
+    ))
+}
+
+/// Proof of the range between `starting_hash` and `last_hash` of an account's storage trie
+fn storage_range_proof(
+    store: &Store,
+    root_hash: H256,
+    hashed_address: H256,
+    starting_hash: H256,
+    last_hash: Option<H256>,
+) -> Result<Vec<Bytes>, StoreError> {
+    let proof = |hash: H256| {
+        boundary_proof((root_hash, Some(hashed_address), hash), || {
+            Ok(store
+                .get_storage_range_proof(root_hash, hashed_address, hash, None)?
+                .unwrap_or_default())
+        })
+    };
+    let start_proof = proof(starting_hash)?;
+    let end_proof = last_hash.map(proof).transpose()?;
+    Ok(range_proof(
+        &start_proof,
+        end_proof.as_deref().map(Vec::as_slice),
+    ))
+}
+
 // Request Processing
 
 pub async fn process_account_range_request(
@@ -17,6 +301,7 @@ pub async fn process_account_range_request(
     store: Store,
 ) -> Result<AccountRange, StoreError> {
     tokio::task::spawn_blocking(move || {
+        let response_bytes = request.response_bytes.min(SNAP_SOFT_RESPONSE_LIMIT);
         let mut accounts = vec![];
         let mut bytes_used = 0;
         for (hash, account) in store.iter_accounts_from(request.root_hash, request.starting_hash)? {
@@ -24,15 +309,16 @@ pub async fn process_account_range_request(
             let account = AccountStateSlim::from(account);
             bytes_used += 32 + account.length() as u64;
             accounts.push(AccountRangeUnit { hash, account });
-            if hash >= request.limit_hash || bytes_used >= request.response_bytes {
+            if hash >= request.limit_hash || bytes_used >= response_bytes {
                 break;
             }
         }
-        let proof = proof_to_encodable(store.get_account_range_proof(
+        let proof = account_range_proof(
+            &store,
             request.root_hash,
             request.starting_hash,
             accounts.last().map(|acc| acc.hash),
-        )?);
+        )?;
         Ok(AccountRange {
             id: request.id,
             accounts,
@@ -48,6 +334,7 @@ pub async fn process_storage_ranges_request(
     store: Store,
 ) -> Result<StorageRanges, StoreError> {
     tokio::task::spawn_blocking(move || {
+        let response_bytes = request.response_bytes.min(SNAP_SOFT_RESPONSE_LIMIT);
         let mut slots = vec![];
         let mut proof = vec![];
         let mut bytes_used = 0;
@@ -61,10 +348,10 @@ pub async fn process_storage_ranges_request(
             {
                 for (hash, data) in storage_iter {
                     debug_assert!(hash >= request.starting_hash);
-                    bytes_used += 64_u64; // slot size
+                    bytes_used += 32 + data.length() as u64;
                     account_slots.push(StorageSlot { hash, data });
-                    if hash >= request.limit_hash || bytes_used >= request.response_bytes {
-                        if bytes_used >= request.response_bytes {
+                    if hash >= request.limit_hash || bytes_used >= response_bytes {
+                        if bytes_used >= response_bytes {
                             res_capped = true;
                         }
                         break;
@@ -75,23 +362,20 @@ pub async fn process_storage_ranges_request(
             // Generate proofs only if the response doesn't contain the full storage range for the account
             // Aka if the starting hash is not zero or if the response was capped due to byte limit
             if !request.starting_hash.is_zero() || res_capped && !account_slots.is_empty() {
-                proof.extend(proof_to_encodable(
-                    store
-                        .get_storage_range_proof(
-                            request.root_hash,
-                            hashed_address,
-                            request.starting_hash,
-                            account_slots.last().map(|acc| acc.hash),
-                        )?
-                        .unwrap_or_default(),
-                ));
+                proof.extend(storage_range_proof(
+                    &store,
+                    request.root_hash,
+                    hashed_address,
+                    request.starting_hash,
+                    account_slots.last().map(|acc| acc.hash),
+                )?);
             }
 
             if !account_slots.is_empty() {
                 slots.push(account_slots);
             }
 
-            if bytes_used >= request.response_bytes {
+            if bytes_used >= response_bytes {
                 break;
             }
         }
@@ -109,6 +393,7 @@ pub fn process_byte_codes_request(
     request: GetByteCodes,
     store: Store,
 ) -> Result<ByteCodes, StoreError> {
+    let response_bytes = request.bytes.min(SNAP_SOFT_RESPONSE_LIMIT);
     let mut codes = vec![];
     let mut bytes_used = 0;
     for code_hash in request.hashes {
@@ -116,7 +401,7 @@ pub fn process_byte_codes_request(
             bytes_used += code.len() as u64;
             codes.push(code);
         }
-        if bytes_used >= request.bytes {
+        if bytes_used >= response_bytes {
             break;
         }
     }
@@ -132,7 +417,7 @@ pub async fn process_trie_nodes_request(
 ) -> Result<TrieNodes, PeerConnectionError> {
     tokio::task::spawn_blocking(move || {
         let mut nodes = vec![];
-        let mut remaining_bytes = request.bytes;
+        let mut remaining_bytes = request.bytes.min(SNAP_SOFT_RESPONSE_LIMIT);
         for paths in request.paths {
             if paths.is_empty() {
                 return Err(PeerConnectionError::BadRequest(
@@ -161,12 +446,6 @@ pub async fn process_trie_nodes_request(
     .map_err(|e| StoreError::Custom(format!("task panicked: {e}")))?
 }
 
-// Helper method to convert proof to RLP-encodable format
-#[inline]
-pub(crate) fn proof_to_encodable(proof: Vec<Vec<u8>>) -> Vec<Bytes> {
-    proof.into_iter().map(Bytes::from).collect()
-}
-
 // Helper method to obtain proof from RLP-encodable format
 #[inline]
 pub(crate) fn encodable_to_proof(proof: &[Bytes]) -> Vec<Vec<u8>> {
@@ -175,7 +454,7 @@ pub(crate) fn encodable_to_proof(proof: &[Bytes]) -> Vec<Vec<u8>> {
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{str::FromStr, time::Duration};
 
     use ethrex_common::{BigEndianHash, H256, types::AccountState};
     use ethrex_rlp::{decode::RLPDecode, encode::RLPEncode};
@@ -459,6 +738,70 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn snap_quota_throttles_until_refilled() {
+        let mut quota = SnapServeQuota {
+            available_bytes: SNAP_MIN_RESPONSE_BYTES + 1000,
+            last_refill: Instant::now(),
+        };
+        // Keep the last refill in the future so no bytes are refilled while checking the budget
+        let budget_without_refill = |quota: &mut SnapServeQuota, requested_bytes| {
+            quota.last_refill = Instant::now() + Duration::from_secs(60);
+            quota.response_budget(requested_bytes)
+        };
+        assert_eq!(
+            budget_without_refill(&mut quota, SNAP_SOFT_RESPONSE_LIMIT),
+            Some(SNAP_MIN_RESPONSE_BYTES + 1000)
+        );
+        quota.consume(SNAP_MIN_RESPONSE_BYTES);
+        // An almost exhausted quota only serves requests asking for less than the minimum budget
+        assert_eq!(
+            budget_without_refill(&mut quota, SNAP_SOFT_RESPONSE_LIMIT),
+            None
+        );
+        assert_eq!(budget_without_refill(&mut quota, 1000), Some(1000));
+        quota.consume(1000);
+        assert_eq!(budget_without_refill(&mut quota, 1), None);
+        // After a second without requests the peer can be served again
+        quota.last_refill = Instant::now() - Duration::from_secs(1);
+        assert_eq!(
+            quota.response_budget(SNAP_SOFT_RESPONSE_LIMIT),
+            Some(SNAP_SOFT_RESPONSE_LIMIT)
+        );
+    }
+
+    #[test]
+    fn snap_quotas_are_shared_by_node_id() {
+        let quotas = SnapServeQuotas::default();
+        let node_id = H256::repeat_byte(1);
+        // Another connection of the same peer shares its quota
+        let reconnected_quotas = quotas.clone();
+        quotas.consume(node_id, SNAP_PEER_QUOTA_BURST_BYTES);
+        // Keep the last refill in the future so no bytes are refilled while checking the budget
+        if let Some(quota) = quotas.0.lock().unwrap().get_mut(&node_id) {
+            quota.last_refill = Instant::now() + Duration::from_secs(60);
+        }
+        assert_eq!(
+            reconnected_quotas.response_budget(node_id, SNAP_SOFT_RESPONSE_LIMIT),
+            None
+        );
+        assert_eq!(
+            reconnected_quotas.response_budget(H256::repeat_byte(2), SNAP_SOFT_RESPONSE_LIMIT),
+            Some(SNAP_SOFT_RESPONSE_LIMIT)
+        );
+    }
+
+    #[test]
+    fn range_proof_skips_shared_nodes() {
+        let start_proof = vec![vec![1], vec![2], vec![3]];
+        let end_proof = vec![vec![1], vec![2], vec![4]];
+        let proof = range_proof(&start_proof, Some(&end_proof));
+        assert_eq!(
+            encodable_to_proof(&proof),
+            vec![vec![1], vec![2], vec![3], vec![4]]
+        );
+    }
+
     // Initial state setup for hive snap tests
 
     fn setup_initial_state() -> Result<(Store, H256), StoreError> {
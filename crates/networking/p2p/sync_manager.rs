@@ -67,8 +67,8 @@ impl SyncManager {
         sync_manager
     }
 
-    /// Creates a dummy SyncManager for tests where syncing is not needed
-    /// This should only be used in tests as it won't be able to connect to the p2p network
+    /// Creates a dummy SyncManager for tests and read-only nodes where syncing is not needed
+    /// It won't be able to connect to the p2p network
     pub fn dummy() -> Self {
         Self {
             snap_enabled: Arc::new(AtomicBool::new(false)),
@@ -0,0 +1,71 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::Value;
+use tracing::info;
+
+use crate::{
+    rpc::{RpcApiContext, RpcHandler},
+    utils::RpcErr,
+};
+
+/// Creates a consistent copy of the database inside the node's checkpoint directory, under the
+/// given name which must not exist yet, and returns the manifest describing it.
+/// The copy can be used as the datadir of another node.
+pub struct CreateCheckpointRequest {
+    pub name: PathBuf,
+}
+
+impl CreateCheckpointRequest {
+    /// Path of the checkpoint inside the checkpoint directory, rejecting names that could escape it
+    pub fn path(&self, checkpoint_dir: Option<&Path>) -> Result<PathBuf, RpcErr> {
+        let checkpoint_dir = checkpoint_dir.ok_or(RpcErr::Internal(
+            "Checkpoints are disabled, the node has no checkpoint directory".to_owned(),
+        ))?;
+        let mut components = self.name.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => Ok(checkpoint_dir.join(name)),
+            _ => Err(RpcErr::WrongParam(
+                "Expected the name of the checkpoint, not a path".to_owned(),
+            )),
+        }
+    }
+}
+
+impl RpcHandler for CreateCheckpointRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.len() != 1 {
+            return Err(RpcErr::BadParams("Expected 1 param".to_owned()));
+        };
+        let name = params
+            .first()
+            .and_then(Value::as_str)
+            .ok_or(RpcErr::WrongParam("Expected string".to_string()))?;
+        Ok(Self { name: name.into() })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        let path = self.path(context.checkpoint_dir.as_deref())?;
+        info!("Requested database checkpoint at {}", path.display());
+        let manifest = context.storage.create_checkpoint(&path).await?;
+        serde_json::to_value(manifest).map_err(|error| RpcErr::Internal(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoints_stay_inside_the_checkpoint_dir() {
+        let dir = Path::new("/checkpoints");
+        let request = |name: &str| CreateCheckpointRequest { name: name.into() };
+        assert_eq!(request("daily").path(Some(dir)).unwrap(), dir.join("daily"));
+        assert!(request("daily").path(None).is_err());
+        for name in ["", "..", "/tmp/daily", "a/b", "../daily", "."] {
+            assert!(request(name).path(Some(dir)).is_err(), "{name}");
+        }
+    }
+}
@@ -9,7 +9,9 @@ use crate::{
     rpc::NodeData,
     utils::{RpcErr, RpcRequest},
 };
+mod checkpoint;
 mod peers;
+pub use checkpoint::CreateCheckpointRequest;
 pub use peers::peers;
 
 #[derive(Serialize, Debug)]
@@ -43,6 +43,8 @@ pub enum EthClientError {
     GetMessageProofError(#[from] GetMessageProofError),
     #[error("debug_executionWitness request error: {0}")]
     GetWitnessError(#[from] GetWitnessError),
+    #[error("admin_createCheckpoint request error: {0}")]
+    CreateCheckpointError(#[from] CreateCheckpointError),
     #[error("eth_maxPriorityFeePerGas request error: {0}")]
     GetMaxPriorityFeeError(#[from] GetMaxPriorityFeeError),
     #[error("Unreachable nonce")]
@@ -276,6 +278,14 @@ pub enum GetWitnessError {
     RPCError(String),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum CreateCheckpointError {
+    #[error("{0}")]
+    SerdeJSONError(#[from] serde_json::Error),
+    #[error("{0}")]
+    RPCError(String),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum GetMaxPriorityFeeError {
     #[error("{0}")]
@@ -1,7 +1,9 @@
 use std::{collections::BTreeMap, fmt};
 
 use crate::{
-    clients::eth::errors::{CallError, GetPeerCountError, GetWitnessError, TxPoolContentError},
+    clients::eth::errors::{
+        CallError, CreateCheckpointError, GetPeerCountError, GetWitnessError, TxPoolContentError,
+    },
     debug::execution_witness::RpcExecutionWitness,
     mempool::MempoolContent,
     types::{
@@ -24,6 +26,7 @@ use ethrex_common::{
     utils::decode_hex,
 };
 use ethrex_rlp::decode::RLPDecode;
+use ethrex_storage::CheckpointManifest;
 use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
@@ -650,6 +653,24 @@ impl EthClient {
         }
     }
 
+    /// Asks the node to create a checkpoint of its database named `name` inside its checkpoint directory
+    pub async fn create_checkpoint(
+        &self,
+        name: &str,
+    ) -> Result<CheckpointManifest, EthClientError> {
+        let params = Some(vec![json!(name)]);
+        let request = RpcRequest::new("admin_createCheckpoint", params);
+
+        match self.send_request(request).await? {
+            RpcResponse::Success(result) => serde_json::from_value(result.result)
+                .map_err(CreateCheckpointError::SerdeJSONError)
+                .map_err(EthClientError::from),
+            RpcResponse::Error(error_response) => {
+                Err(CreateCheckpointError::RPCError(error_response.error.message).into())
+            }
+        }
+    }
+
     pub async fn tx_pool_content(&self) -> Result<MempoolContent, EthClientError> {
         let request = RpcRequest::new("txpool_content", None);
 
@@ -0,0 +1,252 @@
+use ethrex_common::{
+    H256,
+    types::{Block, BlockHash},
+};
+use ethrex_rlp::encode::RLPEncode;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    rpc::{RpcApiContext, RpcHandler},
+    types::block::RpcBlock,
+    utils::RpcErr,
+};
+
+/// Returns the blocks that failed validation while being imported, along with their rlp encoding.
+/// Only the most recent ones are kept, see `ethrex_storage::MAX_BAD_BLOCKS`.
+pub struct GetBadBlocksRequest;
+
+#[derive(Serialize)]
+struct BadBlock {
+    hash: BlockHash,
+    block: RpcBlock,
+    rlp: String,
+}
+
+impl RpcHandler for GetBadBlocksRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        if params.as_ref().is_some_and(|params| !params.is_empty()) {
+            return Err(RpcErr::BadParams("No params expected".to_owned()));
+        }
+        Ok(GetBadBlocksRequest)
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!("Requested bad blocks");
+        let bad_blocks = context
+            .storage
+            .get_bad_blocks()
+            .await?
+            .into_iter()
+            .map(|block| {
+                let hash = block.hash();
+                let rlp = format!("0x{}", hex::encode(block.encode_to_vec()));
+                Ok(BadBlock {
+                    hash,
+                    block: RpcBlock::build(block.header, block.body, hash, true)?,
+                    rlp,
+                })
+            })
+            .collect::<Result<Vec<_>, RpcErr>>()?;
+        Ok(serde_json::to_value(bad_blocks)?)
+    }
+}
+
+/// Looks up a block by hash among the stored blocks and, failing that, among the bad blocks
+pub(crate) async fn get_block_or_bad_block(
+    context: &RpcApiContext,
+    block_hash: H256,
+) -> Result<Block, RpcErr> {
+    if let Some(block) = context.storage.get_block_by_hash(block_hash).await? {
+        return Ok(block);
+    }
+    context
+        .storage
+        .get_bad_block(block_hash)
+        .await?
+        .ok_or(RpcErr::Internal("Block not Found".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use ethrex_blockchain::payload::{BuildPayloadArgs, create_payload};
+    use ethrex_common::{
+        Address, U256,
+        types::{
+            DEFAULT_BUILDER_GAS_CEIL, EIP1559Transaction, ELASTICITY_MULTIPLIER, Genesis,
+            GenesisAccount, Transaction, TxKind,
+        },
+    };
+    use ethrex_storage::{EngineType, Store};
+    use secp256k1::SecretKey;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        signer::{LocalSigner, Signable, Signer},
+        tracing::StandardTraceBadBlockToFileRequest,
+        utils::test_utils::{TEST_GENESIS, default_context_with_storage},
+    };
+
+    const CONTRACT: u64 = 0xc0ffee;
+    /// PUSH1 1, PUSH1 0, SSTORE, STOP
+    const CONTRACT_CODE: [u8; 6] = [0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+
+    /// Builds a block calling the test contract on top of the genesis, and imports it with a
+    /// wrong state root so that it is rejected as a bad block
+    async fn setup() -> (RpcApiContext, Block, H256) {
+        let signer = LocalSigner::new(SecretKey::from_slice(&[0x42; 32]).unwrap());
+        let mut genesis: Genesis = serde_json::from_str(TEST_GENESIS).unwrap();
+        genesis.alloc.insert(
+            signer.address,
+            GenesisAccount {
+                code: Bytes::new(),
+                storage: Default::default(),
+                balance: U256::from(10).pow(U256::from(18)),
+                nonce: 0,
+            },
+        );
+        genesis.alloc.insert(
+            Address::from_low_u64_be(CONTRACT),
+            GenesisAccount {
+                code: Bytes::from_static(&CONTRACT_CODE),
+                storage: Default::default(),
+                balance: U256::zero(),
+                nonce: 1,
+            },
+        );
+        let store = Store::new("", EngineType::InMemory).unwrap();
+        store.add_initial_state(genesis.clone()).await.unwrap();
+        let context = default_context_with_storage(store.clone()).await;
+
+        let tx = Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id: genesis.config.chain_id,
+            max_priority_fee_per_gas: 1_000_000_000,
+            max_fee_per_gas: 10_000_000_000,
+            gas_limit: 100_000,
+            to: TxKind::Call(Address::from_low_u64_be(CONTRACT)),
+            ..Default::default()
+        })
+        .sign(&Signer::Local(signer))
+        .await
+        .unwrap();
+        let tx_hash = context
+            .blockchain
+            .add_transaction_to_pool(tx)
+            .await
+            .unwrap();
+
+        let genesis_header = store.get_block_header(0).unwrap().unwrap();
+        let args = BuildPayloadArgs {
+            parent: genesis_header.hash(),
+            timestamp: genesis_header.timestamp + 12,
+            fee_recipient: Address::zero(),
+            random: H256::zero(),
+            withdrawals: Some(Vec::new()),
+            beacon_root: Some(H256::zero()),
+            version: 1,
+            elasticity_multiplier: ELASTICITY_MULTIPLIER,
+            gas_ceil: DEFAULT_BUILDER_GAS_CEIL,
+        };
+        let payload = create_payload(&args, &store, Bytes::new()).unwrap();
+        let block = context
+            .blockchain
+            .build_payload(payload)
+            .await
+            .unwrap()
+            .payload;
+        assert_eq!(block.body.transactions.len(), 1);
+
+        let mut header = block.header.clone();
+        header.state_root = H256::repeat_byte(0xbb);
+        header.hash = Default::default();
+        let bad_block = Block::new(header, block.body);
+        assert!(
+            context
+                .blockchain
+                .add_block(bad_block.clone())
+                .await
+                .is_err()
+        );
+        (context, bad_block, tx_hash)
+    }
+
+    #[tokio::test]
+    async fn rejected_blocks_are_listed_with_their_rlp() {
+        let (context, bad_block, _) = setup().await;
+        let result = GetBadBlocksRequest::parse(&None)
+            .unwrap()
+            .handle(context)
+            .await
+            .unwrap();
+        let bad_blocks = result.as_array().unwrap();
+        assert_eq!(bad_blocks.len(), 1);
+        assert_eq!(bad_blocks[0]["hash"], json!(bad_block.hash()));
+        assert_eq!(
+            bad_blocks[0]["rlp"],
+            json!(format!("0x{}", hex::encode(bad_block.encode_to_vec())))
+        );
+        assert_eq!(bad_blocks[0]["block"]["hash"], json!(bad_block.hash()));
+    }
+
+    #[tokio::test]
+    async fn bad_block_traces_are_written_to_file() {
+        let (mut context, bad_block, tx_hash) = setup().await;
+        let dir = std::env::temp_dir().join(format!("ethrex_bad_block_traces_{}", H256::random()));
+        context.bad_block_traces_dir = Some(dir.clone());
+        let params = Some(vec![json!(bad_block.hash())]);
+        let result = StandardTraceBadBlockToFileRequest::parse(&params)
+            .unwrap()
+            .handle(context.clone())
+            .await
+            .unwrap();
+        let files = result.as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        let path = files[0].as_str().unwrap();
+        assert!(path.starts_with(&dir.display().to_string()));
+        assert!(path.contains(&format!("-0-0x{}", hex::encode(&tx_hash[..4]))));
+
+        let trace = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let lines: Vec<Value> = trace
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        // A line per opcode of the contract, then the summary
+        assert_eq!(lines.len(), 5);
+        let ops: Vec<_> = lines[..4].iter().map(|step| step["op"].clone()).collect();
+        assert_eq!(ops, [json!(0x60), json!(0x60), json!(0x55), json!(0x00)]);
+        assert_eq!(lines[0]["opName"], json!("PUSH1"));
+        assert_eq!(lines[0]["gasCost"], json!("0x3"));
+        assert_eq!(lines[0]["depth"], json!(1));
+        assert_eq!(lines[2]["stack"], json!(["0x1", "0x0"]));
+        let receipt_gas = bad_block.header.gas_used;
+        assert_eq!(lines[4]["gasUsed"], json!(format!("{receipt_gas:#x}")));
+        assert!(lines[4].get("error").is_none());
+
+        // Filtering by the transaction only traces that one
+        let params = Some(vec![json!(bad_block.hash()), json!({ "txHash": tx_hash })]);
+        let result = StandardTraceBadBlockToFileRequest::parse(&params)
+            .unwrap()
+            .handle(context.clone())
+            .await
+            .unwrap();
+        assert_eq!(result, json!([path]));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), trace);
+
+        // Filtering by a transaction that isn't in the block writes nothing
+        let params = Some(vec![
+            json!(bad_block.hash()),
+            json!({ "txHash": H256::repeat_byte(1) }),
+        ]);
+        let result = StandardTraceBadBlockToFileRequest::parse(&params)
+            .unwrap()
+            .handle(context)
+            .await
+            .unwrap();
+        assert_eq!(result, json!([]));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
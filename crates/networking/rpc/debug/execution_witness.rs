@@ -3,7 +3,7 @@ use ethrex_common::{
     serde_utils,
     types::{
         ChainConfig,
-        block_execution_witness::{ExecutionWitness, GuestProgramStateError},
+        block_execution_witness::{ExecutionWitness, GuestProgramStateError, WitnessOptions},
     },
 };
 use serde::{Deserialize, Serialize};
@@ -73,34 +73,19 @@ pub fn execution_witness_from_rpc_chain_config(
     Ok(witness)
 }
 
+/// Builds the execution witness of a block or a range of blocks. An optional last param enables the
+/// witness size reductions of [`WitnessOptions`].
 pub struct ExecutionWitnessRequest {
     pub from: BlockIdentifier,
     pub to: Option<BlockIdentifier>,
+    pub options: WitnessOptions,
 }
 
-impl RpcHandler for ExecutionWitnessRequest {
-    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
-        let params = params
-            .as_ref()
-            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
-        if params.len() > 2 {
-            return Err(RpcErr::BadParams(format!(
-                "Expected one or two params and {} were provided",
-                params.len()
-            )));
-        }
-
-        let from = BlockIdentifier::parse(params[0].clone(), 0)?;
-        let to = if let Some(param) = params.get(1) {
-            Some(BlockIdentifier::parse(param.clone(), 1)?)
-        } else {
-            None
-        };
-
-        Ok(ExecutionWitnessRequest { from, to })
-    }
+/// Same params as [`ExecutionWitnessRequest`], but returns the size of each part of the witness
+pub struct ExecutionWitnessStatsRequest(ExecutionWitnessRequest);
 
-    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+impl ExecutionWitnessRequest {
+    async fn build_witness(&self, context: &RpcApiContext) -> Result<ExecutionWitness, RpcErr> {
         let from_block_number = self
             .from
             .resolve_block_number(&context.storage)
@@ -154,11 +139,43 @@ impl RpcHandler for ExecutionWitnessRequest {
             blocks.push(block);
         }
 
-        let execution_witness = context
+        context
             .blockchain
-            .generate_witness_for_blocks(&blocks)
+            .generate_witness_for_blocks_with_options(&blocks, self.options)
             .await
-            .map_err(|e| RpcErr::Internal(format!("Failed to build execution witness {e}")))?;
+            .map_err(|e| RpcErr::Internal(format!("Failed to build execution witness {e}")))
+    }
+}
+
+impl RpcHandler for ExecutionWitnessRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.is_empty() || params.len() > 3 {
+            return Err(RpcErr::BadParams(format!(
+                "Expected one to three params and {} were provided",
+                params.len()
+            )));
+        }
+
+        let from = BlockIdentifier::parse(params[0].clone(), 0)?;
+        // The end of the range can be omitted when passing options
+        let (to, options) = match (params.get(1), params.get(2)) {
+            (Some(options @ Value::Object(_)), None) => (None, Some(options)),
+            (Some(to), options) => (Some(BlockIdentifier::parse(to.clone(), 1)?), options),
+            (None, _) => (None, None),
+        };
+        let options = options
+            .map(|options| serde_json::from_value(options.clone()))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(ExecutionWitnessRequest { from, to, options })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        let execution_witness = self.build_witness(&context).await?;
 
         let rpc_execution_witness = RpcExecutionWitness::from(execution_witness);
 
@@ -166,3 +183,22 @@ impl RpcHandler for ExecutionWitnessRequest {
             .map_err(|error| RpcErr::Internal(error.to_string()))
     }
 }
+
+impl RpcHandler for ExecutionWitnessStatsRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        Ok(ExecutionWitnessStatsRequest(
+            ExecutionWitnessRequest::parse(params)?,
+        ))
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        let stats = self
+            .0
+            .build_witness(&context)
+            .await?
+            .stats()
+            .map_err(|e| RpcErr::Internal(format!("Failed to compute witness stats {e}")))?;
+
+        serde_json::to_value(stats).map_err(|error| RpcErr::Internal(error.to_string()))
+    }
+}
@@ -1 +1,5 @@
+pub mod bad_blocks;
 pub mod execution_witness;
+pub mod modified_accounts;
+pub mod state_range;
+pub mod verify_witness;
@@ -0,0 +1,367 @@
+use ethrex_common::{
+    Address, H256,
+    types::{BlockHash, BlockHeader, BlockNumber},
+};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    rpc::{RpcApiContext, RpcHandler},
+    utils::RpcErr,
+};
+
+/// Max amount of blocks between the start and end blocks of a request
+pub const MAX_MODIFIED_ACCOUNTS_RANGE: u64 = 128;
+
+/// Returns the addresses of the accounts that changed between the states of two blocks, or within
+/// a single block if no end block is given.
+/// Accounts whose address preimage isn't stored are returned by their hashed address.
+pub struct GetModifiedAccountsByNumberRequest {
+    start: BlockNumber,
+    end: Option<BlockNumber>,
+}
+
+/// Same as [`GetModifiedAccountsByNumberRequest`] but identifying the blocks by hash
+pub struct GetModifiedAccountsByHashRequest {
+    start: BlockHash,
+    end: Option<BlockHash>,
+}
+
+/// Account that differs between two states
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum ModifiedAccount {
+    Address(Address),
+    /// The preimage of the hashed address isn't stored, as for the accounts changed before the
+    /// node started keeping preimages
+    HashedAddress(H256),
+}
+
+/// Parses the start and optional end block of the modified accounts requests
+fn parse_block_range<T: serde::de::DeserializeOwned>(
+    params: &Option<Vec<Value>>,
+) -> Result<(T, Option<T>), RpcErr> {
+    let params = params
+        .as_ref()
+        .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+    if params.len() != 1 && params.len() != 2 {
+        return Err(RpcErr::BadParams("Expected 1 or 2 params".to_owned()));
+    };
+    let end = params
+        .get(1)
+        .map(|end| serde_json::from_value(end.clone()))
+        .transpose()?;
+    Ok((serde_json::from_value(params[0].clone())?, end))
+}
+
+impl RpcHandler for GetModifiedAccountsByNumberRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let (start, end) = parse_block_range(params)?;
+        Ok(GetModifiedAccountsByNumberRequest { start, end })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!(
+            "Requested modified accounts between blocks {} and {:?}",
+            self.start, self.end
+        );
+        let header = |number: BlockNumber| {
+            context
+                .storage
+                .get_block_header(number)?
+                .ok_or(RpcErr::Internal(format!("Block {number} not found")))
+        };
+        let (start, end) = match self.end {
+            Some(end) => (header(self.start)?, header(end)?),
+            None => {
+                let end = header(self.start)?;
+                (parent_header(&context, &end)?, end)
+            }
+        };
+        modified_accounts(&context, &start, &end).await
+    }
+}
+
+impl RpcHandler for GetModifiedAccountsByHashRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let (start, end) = parse_block_range(params)?;
+        Ok(GetModifiedAccountsByHashRequest { start, end })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!(
+            "Requested modified accounts between blocks {:#x} and {:?}",
+            self.start, self.end
+        );
+        let header = |hash: BlockHash| {
+            context
+                .storage
+                .get_block_header_by_hash(hash)?
+                .ok_or(RpcErr::Internal(format!("Block {hash:#x} not found")))
+        };
+        let (start, end) = match self.end {
+            Some(end) => (header(self.start)?, header(end)?),
+            None => {
+                let end = header(self.start)?;
+                (parent_header(&context, &end)?, end)
+            }
+        };
+        modified_accounts(&context, &start, &end).await
+    }
+}
+
+fn parent_header(context: &RpcApiContext, header: &BlockHeader) -> Result<BlockHeader, RpcErr> {
+    context
+        .storage
+        .get_block_header_by_hash(header.parent_hash)?
+        .ok_or(RpcErr::Internal(format!(
+            "Parent of block {} not found",
+            header.number
+        )))
+}
+
+/// Returns the addresses of the accounts that differ between the states of both blocks
+async fn modified_accounts(
+    context: &RpcApiContext,
+    start: &BlockHeader,
+    end: &BlockHeader,
+) -> Result<Value, RpcErr> {
+    if start.number >= end.number {
+        return Err(RpcErr::BadParams(format!(
+            "Start block ({}) must be lower than end block ({})",
+            start.number, end.number
+        )));
+    }
+    if end.number - start.number > MAX_MODIFIED_ACCOUNTS_RANGE {
+        return Err(RpcErr::BadParams(format!(
+            "Range of blocks ({}) exceeds the max of {MAX_MODIFIED_ACCOUNTS_RANGE}",
+            end.number - start.number
+        )));
+    }
+    for header in [start, end] {
+        if !context.storage.contains_state_node(header.state_root)? {
+            return Err(RpcErr::Internal(format!(
+                "State for block {} is not available",
+                header.number
+            )));
+        }
+    }
+    // Diffing the tries reads every node that changed between both states
+    let storage = context.storage.clone();
+    let (start_root, end_root) = (start.state_root, end.state_root);
+    let accounts = tokio::task::spawn_blocking(move || -> Result<Vec<_>, RpcErr> {
+        let mut accounts = Vec::new();
+        for change in storage.iter_account_changes(start_root, end_root)? {
+            let (hashed_address, _, _) = change?;
+            let account = match storage.get_address_preimage(hashed_address)? {
+                Some(address) => ModifiedAccount::Address(address),
+                None => ModifiedAccount::HashedAddress(hashed_address),
+            };
+            accounts.push(account);
+        }
+        Ok(accounts)
+    })
+    .await
+    .map_err(|error| RpcErr::Internal(error.to_string()))??;
+    Ok(serde_json::to_value(accounts)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use bytes::Bytes;
+    use ethrex_common::{
+        H256, U256,
+        types::{AccountInfo, AccountState, AccountUpdate, Block, BlockBody, Genesis, code_hash},
+    };
+    use ethrex_rlp::encode::RLPEncode;
+    use ethrex_storage::{EngineType, Store, StoreOptions, UpdateBatch};
+    use serde_json::json;
+
+    use super::*;
+    use crate::utils::test_utils::{TEST_GENESIS, default_context_with_storage};
+
+    /// Adds a canonical block on top of the latest one, applying `updates` to its state
+    async fn add_block(store: &Store, updates: Vec<AccountUpdate>) -> BlockHash {
+        let parent = latest_header(store).await;
+        let updates = store
+            .apply_account_updates_batch(parent.hash(), &updates)
+            .await
+            .unwrap()
+            .unwrap();
+        let header = BlockHeader {
+            number: parent.number + 1,
+            parent_hash: parent.hash(),
+            state_root: updates.state_trie_hash,
+            ..Default::default()
+        };
+        let block = Block::new(header, BlockBody::default());
+        let (number, hash) = (block.header.number, block.hash());
+        store
+            .store_block_updates(UpdateBatch {
+                account_updates: updates.state_updates,
+                storage_updates: updates.storage_updates,
+                blocks: vec![block],
+                receipts: Vec::new(),
+                senders: Vec::new(),
+                code_updates: updates.code_updates,
+                preimages: updates.preimages,
+            })
+            .await
+            .unwrap();
+        store
+            .forkchoice_update(None, number, hash, None, None)
+            .await
+            .unwrap();
+        hash
+    }
+
+    async fn latest_header(store: &Store) -> BlockHeader {
+        let latest = store.get_latest_block_number().await.unwrap();
+        store.get_block_header(latest).unwrap().unwrap()
+    }
+
+    fn funded(address: u64, balance: u64) -> AccountUpdate {
+        let mut update = AccountUpdate::new(Address::from_low_u64_be(address));
+        update.info = Some(AccountInfo {
+            code_hash: code_hash(&Bytes::new()),
+            balance: U256::from(balance),
+            nonce: 0,
+        });
+        update
+    }
+
+    /// Funds accounts 1 and 2 in block 1, and account 3 in block 2
+    async fn setup() -> (RpcApiContext, BlockHash, BlockHash) {
+        let options = StoreOptions {
+            store_preimages: true,
+            ..Default::default()
+        };
+        let store = Store::new_with_options("", EngineType::InMemory, options).unwrap();
+        let genesis: Genesis = serde_json::from_str(TEST_GENESIS).unwrap();
+        store.add_initial_state(genesis).await.unwrap();
+        let first = add_block(&store, vec![funded(1, 10), funded(2, 20)]).await;
+        let second = add_block(&store, vec![funded(3, 30)]).await;
+        (default_context_with_storage(store).await, first, second)
+    }
+
+    fn addresses(result: Value) -> BTreeSet<Address> {
+        serde_json::from_value(result).unwrap()
+    }
+
+    fn expected(addresses: &[u64]) -> BTreeSet<Address> {
+        addresses
+            .iter()
+            .map(|address| Address::from_low_u64_be(*address))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn modified_accounts_by_number() {
+        let (context, _, _) = setup().await;
+        let request = |params: Vec<Value>| {
+            let request = GetModifiedAccountsByNumberRequest::parse(&Some(params)).unwrap();
+            let context = context.clone();
+            async move { request.handle(context).await }
+        };
+        assert_eq!(
+            addresses(request(vec![json!(1)]).await.unwrap()),
+            expected(&[1, 2])
+        );
+        assert_eq!(
+            addresses(request(vec![json!(2)]).await.unwrap()),
+            expected(&[3])
+        );
+        assert_eq!(
+            addresses(request(vec![json!(0), json!(2)]).await.unwrap()),
+            expected(&[1, 2, 3])
+        );
+        assert!(request(vec![json!(2), json!(1)]).await.is_err());
+        assert!(request(vec![json!(3)]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn modified_accounts_by_hash() {
+        let (context, first, second) = setup().await;
+        let request = |params: Vec<Value>| {
+            let request = GetModifiedAccountsByHashRequest::parse(&Some(params)).unwrap();
+            let context = context.clone();
+            async move { request.handle(context).await }
+        };
+        assert_eq!(
+            addresses(request(vec![json!(second)]).await.unwrap()),
+            expected(&[3])
+        );
+        assert_eq!(
+            addresses(request(vec![json!(first), json!(second)]).await.unwrap()),
+            expected(&[3])
+        );
+    }
+
+    #[tokio::test]
+    async fn modified_accounts_range_is_capped() {
+        let (context, first, _) = setup().await;
+        let far_header = BlockHeader {
+            number: 1 + MAX_MODIFIED_ACCOUNTS_RANGE + 1,
+            ..Default::default()
+        };
+        let far = far_header.hash();
+        context
+            .storage
+            .add_block_header(far, far_header)
+            .await
+            .unwrap();
+        let result = GetModifiedAccountsByHashRequest::parse(&Some(vec![json!(first), json!(far)]))
+            .unwrap()
+            .handle(context)
+            .await;
+        assert!(matches!(result, Err(RpcErr::BadParams(_))));
+    }
+
+    #[tokio::test]
+    async fn modified_accounts_without_preimages_are_hashed() {
+        let (context, _, _) = setup().await;
+        // Updating the state through the trie directly leaves no preimage behind
+        let parent = latest_header(&context.storage).await;
+        let mut trie = context.storage.open_state_trie(parent.state_root).unwrap();
+        trie.insert(
+            H256::repeat_byte(0xaa).as_bytes().to_vec(),
+            AccountState::default().encode_to_vec(),
+        )
+        .unwrap();
+        let (state_root, nodes) = trie.collect_changes_since_last_hash();
+        context
+            .storage
+            .store_block_updates(UpdateBatch {
+                account_updates: nodes,
+                storage_updates: Vec::new(),
+                blocks: Vec::new(),
+                receipts: Vec::new(),
+                senders: Vec::new(),
+                code_updates: Vec::new(),
+                preimages: Vec::new(),
+            })
+            .await
+            .unwrap();
+        let header = BlockHeader {
+            number: parent.number + 1,
+            parent_hash: parent.hash(),
+            state_root,
+            ..Default::default()
+        };
+        let hash = header.hash();
+        context
+            .storage
+            .add_block_header(hash, header)
+            .await
+            .unwrap();
+        let result = GetModifiedAccountsByHashRequest::parse(&Some(vec![json!(hash)]))
+            .unwrap()
+            .handle(context)
+            .await
+            .unwrap();
+        assert_eq!(result, json!([H256::repeat_byte(0xaa)]));
+    }
+}
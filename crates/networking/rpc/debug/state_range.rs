@@ -0,0 +1,562 @@
+use std::collections::BTreeMap;
+
+use ethrex_common::{Address, BigEndianHash, H256, U256, serde_utils, types::BlockHash};
+use ethrex_storage::Store;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    rpc::{RpcApiContext, RpcHandler},
+    tracing::DEFAULT_TIMEOUT,
+    types::block_identifier::BlockIdentifierOrHash,
+    utils::RpcErr,
+};
+
+/// Default max amount of blocks to re-execute in order to rebuild the state of a transaction
+const DEFAULT_REEXEC: u32 = 128;
+/// Max amount of accounts returned by a single `debug_accountRange` request, matches geth's limit
+const ACCOUNT_RANGE_MAX_RESULTS: usize = 256;
+/// Max amount of storage slots returned by a single `debug_storageRangeAt` request, and for each
+/// account of a `debug_accountRange` request
+const STORAGE_RANGE_MAX_RESULTS: usize = 1024;
+
+/// Returns a page of an account's storage as it was right before executing the given transaction.
+/// Slots are keyed by their hashed key, their `key` field is only filled if the node stores preimages.
+pub struct StorageRangeAtRequest {
+    block_hash: BlockHash,
+    tx_index: usize,
+    address: Address,
+    start: H256,
+    max_results: usize,
+}
+
+/// Returns a page of the accounts in the state trie at the end of the given block.
+/// Accounts are keyed by their hashed address, their `address` field is only present if the node
+/// stores preimages.
+pub struct AccountRangeRequest {
+    block: BlockIdentifierOrHash,
+    start: H256,
+    max_results: usize,
+    no_code: bool,
+    no_storage: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StorageRangeResult {
+    storage: BTreeMap<H256, StorageEntry>,
+    next_key: Option<H256>,
+}
+
+#[derive(Serialize)]
+struct StorageEntry {
+    // Preimage of the hashed key, only known if the node stores preimages
+    key: Option<H256>,
+    value: H256,
+}
+
+#[derive(Serialize)]
+struct AccountRangeResult {
+    root: H256,
+    accounts: BTreeMap<H256, DumpAccount>,
+    next: Option<H256>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DumpAccount {
+    #[serde(with = "serde_utils::u256::dec_str")]
+    balance: U256,
+    nonce: u64,
+    root: H256,
+    code_hash: H256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage: Option<BTreeMap<H256, H256>>,
+    // Hashed key of the first slot left out when the storage has more slots than the ones returned,
+    // the rest can be fetched with `debug_storageRangeAt`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_storage_key: Option<H256>,
+    // Preimage of the hashed address, only known if the node stores preimages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<Address>,
+    key: H256,
+}
+
+/// Parses a hex encoded trie key of up to 32 bytes, right padding it with zeros.
+/// Padding keeps the ordering of the keys, so a partial key can be used to start iterating from a prefix.
+fn parse_start_key(value: &Value, arg_index: u64) -> Result<H256, RpcErr> {
+    let hex_str: String = serde_json::from_value(value.clone())?;
+    let Some(hex_str) = hex_str.strip_prefix("0x") else {
+        return Err(RpcErr::BadHexFormat(arg_index));
+    };
+    let bytes = hex::decode(hex_str).map_err(|_| RpcErr::BadHexFormat(arg_index))?;
+    if bytes.len() > 32 {
+        return Err(RpcErr::BadParams(format!(
+            "Start key is longer than 32 bytes: {} bytes",
+            bytes.len()
+        )));
+    }
+    let mut key = H256::zero();
+    key.0[..bytes.len()].copy_from_slice(&bytes);
+    Ok(key)
+}
+
+impl RpcHandler for StorageRangeAtRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.len() != 5 {
+            return Err(RpcErr::BadParams("Expected 5 params".to_owned()));
+        };
+        let max_results: usize = serde_json::from_value(params[4].clone())?;
+        Ok(StorageRangeAtRequest {
+            block_hash: serde_json::from_value(params[0].clone())?,
+            tx_index: serde_json::from_value(params[1].clone())?,
+            address: serde_json::from_value(params[2].clone())?,
+            start: parse_start_key(&params[3], 3)?,
+            max_results: max_results.min(STORAGE_RANGE_MAX_RESULTS),
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!(
+            "Requested storage range of account {} at block {:#x} tx {}",
+            self.address, self.block_hash, self.tx_index
+        );
+        let (slots, next_key) = context
+            .blockchain
+            .storage_range_at(
+                self.block_hash,
+                self.tx_index,
+                self.address,
+                self.start,
+                self.max_results,
+                DEFAULT_REEXEC,
+            )
+            .await
+            .map_err(|err| RpcErr::Internal(err.to_string()))?;
+        let mut storage = BTreeMap::new();
+        for (hashed_key, value) in slots {
+            storage.insert(
+                hashed_key,
+                StorageEntry {
+                    key: context.storage.get_storage_key_preimage(hashed_key)?,
+                    value: H256::from_uint(&value),
+                },
+            );
+        }
+        Ok(serde_json::to_value(StorageRangeResult {
+            storage,
+            next_key,
+        })?)
+    }
+}
+
+impl RpcHandler for AccountRangeRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.len() < 3 || params.len() > 6 {
+            return Err(RpcErr::BadParams("Expected 3 to 6 params".to_owned()));
+        };
+        let max_results: usize = serde_json::from_value(params[2].clone())?;
+        let flag = |index: usize| -> Result<bool, RpcErr> {
+            Ok(params
+                .get(index)
+                .map(|value| serde_json::from_value(value.clone()))
+                .transpose()?
+                .unwrap_or_default())
+        };
+        // The sixth param (`incompletes`) is accepted for compatibility but ignored,
+        // accounts lacking their address preimage are always returned
+        Ok(AccountRangeRequest {
+            block: BlockIdentifierOrHash::parse(params[0].clone(), 0)?,
+            start: parse_start_key(&params[1], 1)?,
+            max_results: max_results.min(ACCOUNT_RANGE_MAX_RESULTS),
+            no_code: flag(3)?,
+            no_storage: flag(4)?,
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!("Requested account range at block {}", self.block);
+        let Some(block_number) = self.block.resolve_block_number(&context.storage).await? else {
+            return Err(RpcErr::Internal(
+                "Could not resolve block number".to_owned(),
+            ));
+        };
+        let Some(header) = context.storage.get_block_header(block_number)? else {
+            return Err(RpcErr::Internal("Could not get block header".to_owned()));
+        };
+        if !context.storage.contains_state_node(header.state_root)? {
+            return Err(RpcErr::Internal(format!(
+                "State for block {block_number} is not available"
+            )));
+        }
+        // Walking the tries reads from the database, which must not block the runtime's worker threads
+        let store = context.storage.clone();
+        let state_root = header.state_root;
+        let (start, max_results) = (self.start, self.max_results);
+        let (no_code, no_storage) = (self.no_code, self.no_storage);
+        let result = tokio::time::timeout(
+            DEFAULT_TIMEOUT,
+            tokio::task::spawn_blocking(move || {
+                account_range(&store, state_root, start, max_results, no_code, no_storage)
+            }),
+        )
+        .await
+        .map_err(|_| RpcErr::Internal("Account range timed out".to_string()))?
+        .map_err(|error| RpcErr::Internal(error.to_string()))??;
+        Ok(serde_json::to_value(result)?)
+    }
+}
+
+/// Returns up to `max_results` accounts of the state trie starting from `start`, along with up to
+/// `STORAGE_RANGE_MAX_RESULTS` slots of each one's storage
+fn account_range(
+    store: &Store,
+    state_root: H256,
+    start: H256,
+    max_results: usize,
+    no_code: bool,
+    no_storage: bool,
+) -> Result<AccountRangeResult, RpcErr> {
+    let mut accounts_iter = store.iter_accounts_from(state_root, start)?;
+    let page: Vec<_> = accounts_iter.by_ref().take(max_results).collect();
+    let next = accounts_iter
+        .next()
+        .map(|(hashed_address, _)| hashed_address);
+
+    let mut accounts = BTreeMap::new();
+    for (hashed_address, account_state) in page {
+        let code = if no_code {
+            None
+        } else {
+            store
+                .get_account_code(account_state.code_hash)?
+                .map(|code| format!("0x{}", hex::encode(code)))
+        };
+        let (storage, next_storage_key) = if no_storage {
+            (None, None)
+        } else {
+            match store.iter_storage(state_root, hashed_address)? {
+                Some(mut slots) => {
+                    let storage = slots
+                        .by_ref()
+                        .take(STORAGE_RANGE_MAX_RESULTS)
+                        .map(|(hashed_key, value)| (hashed_key, H256::from_uint(&value)))
+                        .collect();
+                    let next_storage_key = slots.next().map(|(hashed_key, _)| hashed_key);
+                    (Some(storage), next_storage_key)
+                }
+                None => (None, None),
+            }
+        };
+        accounts.insert(
+            hashed_address,
+            DumpAccount {
+                balance: account_state.balance,
+                nonce: account_state.nonce,
+                root: account_state.storage_root,
+                code_hash: account_state.code_hash,
+                code,
+                storage,
+                next_storage_key,
+                address: store.get_address_preimage(hashed_address)?,
+                key: hashed_address,
+            },
+        );
+    }
+    Ok(AccountRangeResult {
+        root: state_root,
+        accounts,
+        next,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use bytes::Bytes;
+    use ethrex_blockchain::{
+        Blockchain,
+        payload::{BuildPayloadArgs, create_payload},
+    };
+    use ethrex_common::types::{
+        DEFAULT_BUILDER_GAS_CEIL, ELASTICITY_MULTIPLIER, Genesis, GenesisAccount,
+    };
+    use ethrex_storage::{EngineType, Store, StoreOptions, hash_address, hash_key};
+    use serde_json::json;
+
+    use super::*;
+    use crate::utils::test_utils::{TEST_GENESIS, default_context_with_storage};
+
+    const CONTRACT: u64 = 0xc0ffee;
+    const SLOTS: u64 = 7;
+
+    /// Builds a store from the test genesis plus a contract with `SLOTS` storage slots,
+    /// and adds an empty block on top of it
+    async fn setup() -> (RpcApiContext, BlockHash, Genesis) {
+        setup_with_options(StoreOptions::default(), SLOTS).await
+    }
+
+    /// Like `setup`, but with the given store options and amount of storage slots in the contract
+    async fn setup_with_options(
+        options: StoreOptions,
+        slots: u64,
+    ) -> (RpcApiContext, BlockHash, Genesis) {
+        let mut genesis: Genesis = serde_json::from_str(TEST_GENESIS).unwrap();
+        genesis.alloc.insert(
+            Address::from_low_u64_be(CONTRACT),
+            GenesisAccount {
+                code: Bytes::from_static(&[0x00]),
+                storage: (1..=slots)
+                    .map(|i| (U256::from(i), U256::from(i * 10)))
+                    .collect(),
+                balance: U256::zero(),
+                nonce: 1,
+            },
+        );
+        let store = Store::new_with_options("", EngineType::InMemory, options).unwrap();
+        store.add_initial_state(genesis.clone()).await.unwrap();
+        let genesis_header = store.get_block_header(0).unwrap().unwrap();
+        let args = BuildPayloadArgs {
+            parent: genesis_header.hash(),
+            timestamp: genesis_header.timestamp + 12,
+            fee_recipient: Address::zero(),
+            random: H256::zero(),
+            withdrawals: Some(Vec::new()),
+            beacon_root: Some(H256::zero()),
+            version: 1,
+            elasticity_multiplier: ELASTICITY_MULTIPLIER,
+            gas_ceil: DEFAULT_BUILDER_GAS_CEIL,
+        };
+        let blockchain = Blockchain::default_with_store(store.clone());
+        let payload = create_payload(&args, &store, Bytes::new()).unwrap();
+        let block = blockchain.build_payload(payload).await.unwrap().payload;
+        let block_hash = block.hash();
+        blockchain.add_block(block).await.unwrap();
+        (
+            default_context_with_storage(store).await,
+            block_hash,
+            genesis,
+        )
+    }
+
+    #[tokio::test]
+    async fn storage_range_at_pages_through_storage() {
+        let (context, block_hash, _) = setup().await;
+        let expected: BTreeSet<(H256, H256)> = (1..=SLOTS)
+            .map(|i| {
+                let key = H256::from_uint(&U256::from(i));
+                (
+                    H256::from_slice(&hash_key(&key)),
+                    H256::from_uint(&U256::from(i * 10)),
+                )
+            })
+            .collect();
+
+        let mut start = json!("0x00");
+        let mut slots = BTreeSet::new();
+        loop {
+            let params = Some(vec![
+                json!(block_hash),
+                json!(0),
+                json!(Address::from_low_u64_be(CONTRACT)),
+                start,
+                json!(3),
+            ]);
+            let result = StorageRangeAtRequest::parse(&params)
+                .unwrap()
+                .handle(context.clone())
+                .await
+                .unwrap();
+            let storage = result["storage"].as_object().unwrap();
+            assert!(storage.len() <= 3);
+            for (hashed_key, entry) in storage {
+                assert!(entry["key"].is_null());
+                slots.insert((
+                    serde_json::from_value(json!(hashed_key)).unwrap(),
+                    serde_json::from_value(entry["value"].clone()).unwrap(),
+                ));
+            }
+            if result["nextKey"].is_null() {
+                break;
+            }
+            start = result["nextKey"].clone();
+        }
+        assert_eq!(slots, expected);
+    }
+
+    #[tokio::test]
+    async fn storage_range_at_rejects_out_of_range_tx_index() {
+        let (context, block_hash, _) = setup().await;
+        let params = Some(vec![
+            json!(block_hash),
+            json!(1),
+            json!(Address::from_low_u64_be(CONTRACT)),
+            json!("0x00"),
+            json!(10),
+        ]);
+        let result = StorageRangeAtRequest::parse(&params)
+            .unwrap()
+            .handle(context)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn account_range_pages_through_state() {
+        let (context, _, genesis) = setup().await;
+        let mut start = json!("0x");
+        let mut accounts = BTreeMap::new();
+        loop {
+            let params = Some(vec![
+                json!("0x0"),
+                start,
+                json!(5),
+                json!(true),
+                json!(false),
+            ]);
+            let result = AccountRangeRequest::parse(&params)
+                .unwrap()
+                .handle(context.clone())
+                .await
+                .unwrap();
+            let page = result["accounts"].as_object().unwrap();
+            assert!(page.len() <= 5);
+            for (hashed_address, account) in page {
+                assert!(account.get("code").is_none());
+                accounts.insert(hashed_address.clone(), account.clone());
+            }
+            if result["next"].is_null() {
+                break;
+            }
+            start = result["next"].clone();
+        }
+        assert_eq!(accounts.len(), genesis.alloc.len());
+        let contract_key = format!(
+            "{:#x}",
+            H256::from_slice(&hash_address(&Address::from_low_u64_be(CONTRACT)))
+        );
+        let contract = &accounts[&contract_key];
+        assert_eq!(contract["nonce"], json!(1));
+        assert_eq!(
+            contract["storage"].as_object().unwrap().len(),
+            SLOTS as usize
+        );
+        assert!(contract.get("nextStorageKey").is_none());
+    }
+
+    #[tokio::test]
+    async fn account_range_caps_storage_per_account() {
+        let slots = STORAGE_RANGE_MAX_RESULTS as u64 + 10;
+        let (context, block_hash, _) = setup_with_options(StoreOptions::default(), slots).await;
+        let contract = Address::from_low_u64_be(CONTRACT);
+        let contract_key = H256::from_slice(&hash_address(&contract));
+        let params = Some(vec![
+            json!("0x1"),
+            json!(contract_key),
+            json!(1),
+            json!(true),
+        ]);
+        let result = AccountRangeRequest::parse(&params)
+            .unwrap()
+            .handle(context.clone())
+            .await
+            .unwrap();
+        let account = &result["accounts"][format!("{contract_key:#x}")];
+        let storage = account["storage"].as_object().unwrap();
+        assert_eq!(storage.len(), STORAGE_RANGE_MAX_RESULTS);
+
+        // The slots left out are the ones returned by `debug_storageRangeAt` from the reported key
+        let next_storage_key = account["nextStorageKey"].clone();
+        assert!(!storage.contains_key(next_storage_key.as_str().unwrap()));
+        let params = Some(vec![
+            json!(block_hash),
+            json!(0),
+            json!(contract),
+            next_storage_key,
+            json!(STORAGE_RANGE_MAX_RESULTS),
+        ]);
+        let result = StorageRangeAtRequest::parse(&params)
+            .unwrap()
+            .handle(context)
+            .await
+            .unwrap();
+        assert_eq!(
+            result["storage"].as_object().unwrap().len(),
+            (slots as usize) - STORAGE_RANGE_MAX_RESULTS
+        );
+        assert!(result["nextKey"].is_null());
+    }
+
+    #[tokio::test]
+    async fn state_ranges_include_stored_preimages() {
+        let options = StoreOptions {
+            store_preimages: true,
+            ..Default::default()
+        };
+        let (context, block_hash, _) = setup_with_options(options, SLOTS).await;
+        let contract = Address::from_low_u64_be(CONTRACT);
+
+        let params = Some(vec![
+            json!(block_hash),
+            json!(0),
+            json!(contract),
+            json!("0x00"),
+            json!(SLOTS),
+        ]);
+        let result = StorageRangeAtRequest::parse(&params)
+            .unwrap()
+            .handle(context.clone())
+            .await
+            .unwrap();
+        let keys: BTreeSet<H256> = result["storage"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|entry| serde_json::from_value(entry["key"].clone()).unwrap())
+            .collect();
+        let expected: BTreeSet<H256> = (1..=SLOTS)
+            .map(|i| H256::from_uint(&U256::from(i)))
+            .collect();
+        assert_eq!(keys, expected);
+
+        let contract_key = H256::from_slice(&hash_address(&contract));
+        let params = Some(vec![json!("0x0"), json!(contract_key), json!(1)]);
+        let result = AccountRangeRequest::parse(&params)
+            .unwrap()
+            .handle(context)
+            .await
+            .unwrap();
+        let account = &result["accounts"][format!("{contract_key:#x}")];
+        assert_eq!(account["address"], json!(contract));
+    }
+
+    #[test]
+    fn account_range_caps_max_results() {
+        let params = Some(vec![json!("latest"), json!("0x"), json!(100_000)]);
+        let request = AccountRangeRequest::parse(&params).unwrap();
+        assert_eq!(request.max_results, ACCOUNT_RANGE_MAX_RESULTS);
+    }
+
+    #[test]
+    fn storage_range_at_caps_max_results() {
+        let params = Some(vec![
+            json!(H256::zero()),
+            json!(0),
+            json!(Address::from_low_u64_be(CONTRACT)),
+            json!("0x00"),
+            json!(100_000),
+        ]);
+        let request = StorageRangeAtRequest::parse(&params).unwrap();
+        assert_eq!(request.max_results, STORAGE_RANGE_MAX_RESULTS);
+    }
+}
@@ -0,0 +1,109 @@
+use ethrex_blockchain::stateless::validate_stateless;
+use ethrex_common::{
+    H256,
+    types::{
+        Block, BlockNumber, ChainConfig, ELASTICITY_MULTIPLIER,
+        block_execution_witness::ExecutionWitnessStats,
+    },
+};
+use ethrex_rlp::decode::RLPDecode;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    debug::execution_witness::{RpcExecutionWitness, execution_witness_from_rpc_chain_config},
+    rpc::{RpcApiContext, RpcHandler},
+    tracing::DEFAULT_TIMEOUT,
+    utils::RpcErr,
+};
+
+/// Validates a block statelessly against an execution witness, as returned by `debug_executionWitness`.
+/// The block is given RLP-encoded, as returned by `debug_getRawBlock`, so witnesses produced by other
+/// clients can be checked against this node's execution.
+pub struct VerifyExecutionWitnessRequest {
+    block: Block,
+    witness: RpcExecutionWitness,
+}
+
+/// Outcome of a stateless block validation, `error` describes the first mismatch found
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WitnessVerification {
+    pub block_number: BlockNumber,
+    pub block_hash: H256,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Size of each part of the witness, if it could be decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<ExecutionWitnessStats>,
+}
+
+/// Executes the block using only the state contained in the witness and checks the resulting
+/// state root, receipts root, gas used and requests hash against the block header
+pub fn verify_execution_witness(
+    block: Block,
+    witness: RpcExecutionWitness,
+    chain_config: ChainConfig,
+) -> WitnessVerification {
+    let block_number = block.header.number;
+    let block_hash = block.hash();
+    let mut stats = None;
+    let result = execution_witness_from_rpc_chain_config(witness, chain_config, block_number)
+        .map_err(|e| format!("Invalid execution witness: {e}"))
+        .and_then(|witness| {
+            stats = witness.stats().ok();
+            validate_stateless(vec![block], witness, ELASTICITY_MULTIPLIER)
+                .map_err(|e| e.to_string())
+        });
+    WitnessVerification {
+        block_number,
+        block_hash,
+        valid: result.is_ok(),
+        error: result.err(),
+        stats,
+    }
+}
+
+/// Decodes an RLP-encoded block given as a hex string
+pub fn decode_raw_block(raw_block: &str) -> Result<Block, RpcErr> {
+    let bytes = hex::decode(raw_block.trim().trim_start_matches("0x"))
+        .map_err(|e| RpcErr::BadParams(format!("Invalid block hex: {e}")))?;
+    Block::decode(&bytes).map_err(|e| RpcErr::BadParams(format!("Invalid block RLP: {e}")))
+}
+
+impl RpcHandler for VerifyExecutionWitnessRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.len() != 2 {
+            return Err(RpcErr::BadParams("Expected 2 params".to_owned()));
+        };
+        let raw_block: String = serde_json::from_value(params[0].clone())?;
+        Ok(VerifyExecutionWitnessRequest {
+            block: decode_raw_block(&raw_block)?,
+            witness: serde_json::from_value(params[1].clone())?,
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!(
+            "Requested execution witness verification for block {}",
+            self.block.header.number
+        );
+        let chain_config = context.storage.get_chain_config()?;
+        let (block, witness) = (self.block.clone(), self.witness.clone());
+        let verification = tokio::time::timeout(
+            DEFAULT_TIMEOUT,
+            tokio::task::spawn_blocking(move || {
+                verify_execution_witness(block, witness, chain_config)
+            }),
+        )
+        .await
+        .map_err(|_| RpcErr::Internal("Witness verification timed out".to_string()))?
+        .map_err(|error| RpcErr::Internal(error.to_string()))?;
+        serde_json::to_value(verification).map_err(|error| RpcErr::Internal(error.to_string()))
+    }
+}
@@ -0,0 +1,588 @@
+use bytes::Bytes;
+use ethrex_common::{
+    Address, H256,
+    types::{
+        EIP1559Transaction, EIP2930Transaction, GenericTransaction, LegacyTransaction, Transaction,
+    },
+};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    eth::transaction::EstimateGasRequest,
+    rpc::{RpcApiContext, RpcHandler},
+    signer::{Signable, Signer, sign_legacy_transaction_eip155},
+    types::{transaction::RpcTransaction, typed_data::TypedData},
+    utils::{RpcErr, parse_json_hex},
+};
+
+/// Lists the addresses of the accounts managed by the node
+pub struct AccountsRequest;
+
+/// Signs an EIP-191 personal message with a managed account
+pub struct SignRequest {
+    address: Address,
+    message: Bytes,
+}
+
+/// Signs EIP-712 structured data with a managed account
+pub struct SignTypedDataRequest {
+    address: Address,
+    typed_data: TypedData,
+}
+
+/// Fills in the missing fields of a transaction from a managed account and signs it without sending it
+pub struct SignTransactionRequest {
+    transaction: GenericTransaction,
+}
+
+/// Fills in the missing fields of a transaction from a managed account, signs it and adds it to the mempool
+pub struct SendTransactionRequest {
+    transaction: GenericTransaction,
+}
+
+#[derive(Serialize)]
+struct SignTransactionResult {
+    #[serde(with = "ethrex_common::serde_utils::bytes")]
+    raw: Bytes,
+    tx: RpcTransaction,
+}
+
+fn find_signer(context: &RpcApiContext, address: Address) -> Result<&Signer, RpcErr> {
+    context
+        .signers
+        .iter()
+        .find(|signer| signer.address() == address)
+        .ok_or(RpcErr::BadParams(format!("Unknown account {address:#x}")))
+}
+
+/// Signs the given data with the account, returning the signature with a `v` of 27 or 28
+async fn sign_data(signer: &Signer, data: Vec<u8>) -> Result<Value, RpcErr> {
+    let mut signature = signer
+        .sign(data.into())
+        .await
+        .map_err(|err| RpcErr::Internal(err.to_string()))?;
+    if signature[64] < 27 {
+        signature[64] += 27;
+    }
+    Ok(Value::String(format!("0x{}", hex::encode(signature))))
+}
+
+fn parse_transaction(params: &Option<Vec<Value>>) -> Result<GenericTransaction, RpcErr> {
+    let params = params
+        .as_ref()
+        .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+    if params.len() != 1 {
+        return Err(RpcErr::BadParams(format!(
+            "Expected one param and {} were provided",
+            params.len()
+        )));
+    };
+    Ok(serde_json::from_value(params[0].clone())?)
+}
+
+/// Builds a signed transaction out of the request, filling in the nonce, gas limit, fees and chain id if missing.
+/// Transactions with a gas price and no EIP-1559 fields are built as legacy (or EIP-2930 if they have an access list).
+async fn fill_and_sign_transaction(
+    request: &GenericTransaction,
+    context: &RpcApiContext,
+) -> Result<Transaction, RpcErr> {
+    let signer = find_signer(context, request.from)?;
+    if request.authorization_list.is_some()
+        || !request.blobs.is_empty()
+        || !request.blob_versioned_hashes.is_empty()
+    {
+        return Err(RpcErr::BadParams(
+            "Blob and set code transactions can't be signed by the node".to_owned(),
+        ));
+    }
+
+    let chain_id = context.storage.get_chain_config()?.chain_id;
+    if request.chain_id.is_some_and(|id| id != chain_id) {
+        return Err(RpcErr::BadParams(format!(
+            "Chain id mismatch, the node is running chain {chain_id}"
+        )));
+    }
+
+    let latest_block_number = context.storage.get_latest_block_number().await?;
+    let nonce = match request.nonce {
+        Some(nonce) => nonce,
+        None => match context.blockchain.mempool.get_nonce(&request.from)? {
+            Some(nonce) => nonce,
+            None => context
+                .storage
+                .get_nonce_by_account_address(latest_block_number, request.from)
+                .await?
+                .unwrap_or_default(),
+        },
+    };
+
+    let gas_limit = match request.gas {
+        Some(gas) => gas,
+        None => {
+            let mut transaction = request.clone();
+            transaction.nonce = Some(nonce);
+            let estimation = EstimateGasRequest {
+                transaction,
+                block: None,
+            }
+            .handle(context.clone())
+            .await?;
+            parse_json_hex(&estimation).map_err(RpcErr::Internal)?
+        }
+    };
+
+    let access_list = request
+        .access_list
+        .iter()
+        .map(|entry| (entry.address, entry.storage_keys.clone()))
+        .collect::<Vec<_>>();
+    let is_legacy = request.gas_price != 0
+        && request.max_fee_per_gas.is_none()
+        && request.max_priority_fee_per_gas.is_none();
+
+    if is_legacy && access_list.is_empty() {
+        // Legacy transactions are replay protected by including the chain id in the signature
+        let mut transaction = LegacyTransaction {
+            nonce,
+            gas_price: request.gas_price,
+            gas: gas_limit,
+            to: request.to.clone(),
+            value: request.value,
+            data: request.input.clone(),
+            ..Default::default()
+        };
+        sign_legacy_transaction_eip155(&mut transaction, signer, chain_id)
+            .await
+            .map_err(|err| RpcErr::Internal(err.to_string()))?;
+        return Ok(Transaction::LegacyTransaction(transaction));
+    }
+
+    let transaction = if is_legacy {
+        Transaction::EIP2930Transaction(EIP2930Transaction {
+            chain_id,
+            nonce,
+            gas_price: request.gas_price,
+            gas_limit,
+            to: request.to.clone(),
+            value: request.value,
+            data: request.input.clone(),
+            access_list,
+            ..Default::default()
+        })
+    } else {
+        let max_priority_fee_per_gas = match request.max_priority_fee_per_gas {
+            Some(tip) => tip,
+            None => {
+                context
+                    .gas_tip_estimator
+                    .lock()
+                    .await
+                    .estimate_gas_tip(&context.storage)
+                    .await?
+            }
+        };
+        let max_fee_per_gas = match request.max_fee_per_gas {
+            Some(max_fee) => max_fee,
+            None => {
+                let base_fee = context
+                    .storage
+                    .get_block_header(latest_block_number)?
+                    .and_then(|header| header.base_fee_per_gas)
+                    .ok_or(RpcErr::Internal(
+                        "Missing base fee on latest block".to_owned(),
+                    ))?;
+                // Leave room for the base fee to keep rising for a few blocks
+                base_fee
+                    .checked_mul(2)
+                    .and_then(|fee| fee.checked_add(max_priority_fee_per_gas))
+                    .ok_or(RpcErr::BadParams(
+                        "maxPriorityFeePerGas is too large to derive maxFeePerGas".to_owned(),
+                    ))?
+            }
+        };
+        if max_fee_per_gas < max_priority_fee_per_gas {
+            return Err(RpcErr::BadParams(
+                "maxFeePerGas is lower than maxPriorityFeePerGas".to_owned(),
+            ));
+        }
+        Transaction::EIP1559Transaction(EIP1559Transaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit,
+            to: request.to.clone(),
+            value: request.value,
+            data: request.input.clone(),
+            access_list,
+            ..Default::default()
+        })
+    };
+
+    transaction
+        .sign(signer)
+        .await
+        .map_err(|err| RpcErr::Internal(err.to_string()))
+}
+
+impl RpcHandler for AccountsRequest {
+    fn parse(_params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        Ok(Self)
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        let accounts: Vec<Address> = context.signers.iter().map(Signer::address).collect();
+        Ok(serde_json::to_value(accounts)?)
+    }
+}
+
+impl RpcHandler for SignRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.len() != 2 {
+            return Err(RpcErr::BadParams("Expected 2 params".to_owned()));
+        };
+        let message: String = serde_json::from_value(params[1].clone())?;
+        let message = message
+            .strip_prefix("0x")
+            .and_then(|message| hex::decode(message).ok())
+            .ok_or(RpcErr::BadHexFormat(1))?;
+        Ok(SignRequest {
+            address: serde_json::from_value(params[0].clone())?,
+            message: message.into(),
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!("Requested message signature from {:#x}", self.address);
+        let signer = find_signer(&context, self.address)?;
+        let mut data = format!("\x19Ethereum Signed Message:\n{}", self.message.len()).into_bytes();
+        data.extend_from_slice(&self.message);
+        sign_data(signer, data).await
+    }
+}
+
+impl RpcHandler for SignTypedDataRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.len() != 2 {
+            return Err(RpcErr::BadParams("Expected 2 params".to_owned()));
+        };
+        // Typed data may be sent either as an object or as a JSON encoded string
+        let typed_data = match &params[1] {
+            Value::String(typed_data) => serde_json::from_str(typed_data)?,
+            typed_data => serde_json::from_value(typed_data.clone())?,
+        };
+        Ok(SignTypedDataRequest {
+            address: serde_json::from_value(params[0].clone())?,
+            typed_data,
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!("Requested typed data signature from {:#x}", self.address);
+        let signer = find_signer(&context, self.address)?;
+        sign_data(signer, self.typed_data.encode_for_signing()?).await
+    }
+}
+
+impl RpcHandler for SignTransactionRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        Ok(SignTransactionRequest {
+            transaction: parse_transaction(params)?,
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!(
+            "Requested transaction signature from {:#x}",
+            self.transaction.from
+        );
+        let transaction = fill_and_sign_transaction(&self.transaction, &context).await?;
+        let raw = transaction.encode_canonical_to_vec().into();
+        Ok(serde_json::to_value(SignTransactionResult {
+            raw,
+            tx: RpcTransaction::build(transaction, None, None, None)?,
+        })?)
+    }
+}
+
+impl RpcHandler for SendTransactionRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        Ok(SendTransactionRequest {
+            transaction: parse_transaction(params)?,
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!(
+            "Requested transaction submission from {:#x}",
+            self.transaction.from
+        );
+        // Only managed accounts get a lock, so that unknown ones can't grow the locks
+        find_signer(&context, self.transaction.from)?;
+        let _nonce_guard = context.nonce_locks.lock(self.transaction.from).await;
+        let transaction = fill_and_sign_transaction(&self.transaction, &context).await?;
+        let hash: H256 = context
+            .blockchain
+            .add_transaction_to_pool(transaction)
+            .await?;
+        Ok(Value::String(format!("{hash:#x}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use ethrex_common::{
+        U256,
+        types::{Genesis, GenesisAccount, TxType},
+    };
+    use ethrex_storage::{EngineType, Store};
+    use secp256k1::{SECP256K1, SecretKey};
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        eth::test_utils::setup_store,
+        signer::{LocalSigner, RemoteSigner},
+        utils::test_utils::default_context_with_storage,
+    };
+
+    fn test_key() -> SecretKey {
+        SecretKey::from_slice(&[0x42; 32]).unwrap()
+    }
+
+    async fn context_with_signer(signer: Signer) -> RpcApiContext {
+        let mut context = default_context_with_storage(setup_store().await).await;
+        context.signers = Arc::new(vec![signer]);
+        context
+    }
+
+    async fn sign_request(context: &RpcApiContext, request: Value) -> Transaction {
+        let request = parse_transaction(&Some(vec![request])).unwrap();
+        fill_and_sign_transaction(&request, context).await.unwrap()
+    }
+
+    /// Checks that the transaction is signed by the managed account and bound to the node's chain
+    fn assert_signed_for_chain(tx: &Transaction, context: &RpcApiContext, address: Address) {
+        let chain_id = context.storage.get_chain_config().unwrap().chain_id;
+        assert_eq!(tx.sender().unwrap(), address);
+        assert_eq!(tx.chain_id(), Some(chain_id));
+    }
+
+    #[tokio::test]
+    async fn signs_legacy_transactions_with_replay_protection() {
+        let signer = LocalSigner::new(test_key());
+        let address = signer.address;
+        let context = context_with_signer(signer.into()).await;
+        let tx = sign_request(
+            &context,
+            json!({
+                "from": address,
+                "to": Address::from_low_u64_be(1),
+                "gas": "0x5208",
+                "gasPrice": "0x3b9aca00",
+                "value": "0x1"
+            }),
+        )
+        .await;
+        assert_eq!(tx.tx_type(), TxType::Legacy);
+        assert_signed_for_chain(&tx, &context, address);
+        let Transaction::LegacyTransaction(legacy) = &tx else {
+            unreachable!()
+        };
+        let chain_id = context.storage.get_chain_config().unwrap().chain_id;
+        assert!(legacy.v >= U256::from(35 + 2 * chain_id));
+    }
+
+    #[tokio::test]
+    async fn signs_access_list_transactions() {
+        let signer = LocalSigner::new(test_key());
+        let address = signer.address;
+        let context = context_with_signer(signer.into()).await;
+        let tx = sign_request(
+            &context,
+            json!({
+                "from": address,
+                "to": Address::from_low_u64_be(1),
+                "gas": "0x10000",
+                "gasPrice": "0x3b9aca00",
+                "accessList": [{
+                    "address": Address::from_low_u64_be(1),
+                    "storageKeys": [H256::zero()]
+                }]
+            }),
+        )
+        .await;
+        assert_eq!(tx.tx_type(), TxType::EIP2930);
+        assert_signed_for_chain(&tx, &context, address);
+    }
+
+    #[tokio::test]
+    async fn signs_dynamic_fee_transactions() {
+        let signer = LocalSigner::new(test_key());
+        let address = signer.address;
+        let context = context_with_signer(signer.into()).await;
+        let tx = sign_request(
+            &context,
+            json!({
+                "from": address,
+                "to": Address::from_low_u64_be(1),
+                "gas": "0x5208",
+                "maxFeePerGas": "0x77359400",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "nonce": "0x7"
+            }),
+        )
+        .await;
+        assert_eq!(tx.tx_type(), TxType::EIP1559);
+        assert_eq!(tx.nonce(), 7);
+        assert_signed_for_chain(&tx, &context, address);
+    }
+
+    #[tokio::test]
+    async fn rejects_blob_transactions_and_unknown_accounts() {
+        let signer = LocalSigner::new(test_key());
+        let address = signer.address;
+        let context = context_with_signer(signer.into()).await;
+        let unknown = parse_transaction(&Some(vec![json!({
+            "from": Address::from_low_u64_be(2),
+            "to": Address::from_low_u64_be(1),
+            "gas": "0x5208",
+        })]))
+        .unwrap();
+        assert!(fill_and_sign_transaction(&unknown, &context).await.is_err());
+        let blob = parse_transaction(&Some(vec![json!({
+            "from": address,
+            "to": Address::from_low_u64_be(1),
+            "gas": "0x5208",
+            "blobVersionedHashes": [H256::zero()],
+        })]))
+        .unwrap();
+        assert!(fill_and_sign_transaction(&blob, &context).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_tips_overflowing_the_derived_max_fee() {
+        let signer = LocalSigner::new(test_key());
+        let address = signer.address;
+        let context = context_with_signer(signer.into()).await;
+        let request = parse_transaction(&Some(vec![json!({
+            "from": address,
+            "to": Address::from_low_u64_be(1),
+            "gas": "0x5208",
+            "maxPriorityFeePerGas": format!("{:#x}", u64::MAX),
+        })]))
+        .unwrap();
+        let result = fill_and_sign_transaction(&request, &context).await;
+        assert!(matches!(result, Err(RpcErr::BadParams(_))));
+    }
+
+    #[tokio::test]
+    async fn concurrent_sends_get_consecutive_nonces() {
+        let signer = LocalSigner::new(test_key());
+        let address = signer.address;
+        let mut genesis: Genesis =
+            serde_json::from_str(include_str!("../../../../fixtures/genesis/l1.json")).unwrap();
+        genesis.alloc.insert(
+            address,
+            GenesisAccount {
+                code: Bytes::new(),
+                storage: Default::default(),
+                balance: U256::from(10).pow(U256::from(21)),
+                nonce: 0,
+            },
+        );
+        let store = Store::new("", EngineType::InMemory).unwrap();
+        store.add_initial_state(genesis).await.unwrap();
+        let mut context = default_context_with_storage(store).await;
+        context.signers = Arc::new(vec![signer.into()]);
+
+        let send = |context: RpcApiContext| async move {
+            let params = Some(vec![json!({
+                "from": address,
+                "to": Address::from_low_u64_be(1),
+                "gas": "0x5208",
+                "maxFeePerGas": "0x77359400",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "value": "0x1"
+            })]);
+            SendTransactionRequest::parse(&params)
+                .unwrap()
+                .handle(context)
+                .await
+        };
+        let (first, second) = tokio::join!(send(context.clone()), send(context.clone()));
+        assert_ne!(first.unwrap(), second.unwrap());
+        assert_eq!(
+            context.blockchain.mempool.get_nonce(&address).unwrap(),
+            Some(2)
+        );
+    }
+
+    /// Starts a server mimicking web3signer, which returns signatures with a `v` of 27 or 28
+    async fn start_remote_signer(key: SecretKey) -> RemoteSigner {
+        let local = LocalSigner::new(key);
+        let app = Router::new().fallback(move |body: String| {
+            let local = local.clone();
+            async move {
+                let body: Value = serde_json::from_str(&body).unwrap();
+                let data =
+                    hex::decode(body["data"].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                let mut signature = local.sign(data.into());
+                signature[64] += 27;
+                format!("0x{}", hex::encode(signature))
+            }
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        RemoteSigner::new(url.parse().unwrap(), key.public_key(SECP256K1))
+    }
+
+    #[tokio::test]
+    async fn normalizes_remote_signer_recovery_id() {
+        let remote = start_remote_signer(test_key()).await;
+        let address = remote.address;
+        let context = context_with_signer(remote.into()).await;
+        for request in [
+            json!({
+                "from": address,
+                "to": Address::from_low_u64_be(1),
+                "gas": "0x5208",
+                "gasPrice": "0x3b9aca00",
+            }),
+            json!({
+                "from": address,
+                "to": Address::from_low_u64_be(1),
+                "gas": "0x5208",
+                "maxFeePerGas": "0x77359400",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+            }),
+        ] {
+            let tx = sign_request(&context, request).await;
+            assert_signed_for_chain(&tx, &context, address);
+        }
+
+        // Message signatures are returned with a `v` of 27 or 28
+        let signature = SignRequest {
+            address,
+            message: Bytes::from_static(b"hello"),
+        }
+        .handle(context)
+        .await
+        .unwrap();
+        let signature = hex::decode(signature.as_str().unwrap().trim_start_matches("0x")).unwrap();
+        assert!(matches!(signature[64], 27 | 28));
+    }
+}
@@ -1,4 +1,5 @@
 pub(crate) mod account;
+pub(crate) mod accounts;
 pub(crate) mod block;
 pub(crate) mod client;
 pub(crate) mod fee_market;
@@ -0,0 +1,121 @@
+use ethrex_common::{
+    Address, H256, serde_utils,
+    types::{BlockHash, BlockNumber, Index},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    rpc::{RpcApiContext, RpcHandler},
+    utils::RpcErr,
+};
+
+/// Default amount of transactions returned by `ethrex_getTransactionsByAddress`
+const DEFAULT_TRANSACTIONS_BY_ADDRESS_LIMIT: usize = 100;
+/// Max amount of transactions returned by a single `ethrex_getTransactionsByAddress` request
+const MAX_TRANSACTIONS_BY_ADDRESS_LIMIT: usize = 1000;
+
+/// Returns a page of the canonical transactions sent or received by an address, newest first.
+/// Requires the node to be running with the address transaction index enabled.
+pub struct GetTransactionsByAddressRequest {
+    address: Address,
+    cursor: Option<TransactionsCursor>,
+    limit: usize,
+}
+
+/// Position in an address' transaction history, pages start right before it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionsCursor {
+    #[serde(with = "serde_utils::u64::hex_str")]
+    block_number: BlockNumber,
+    #[serde(with = "serde_utils::u64::hex_str")]
+    transaction_index: Index,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionsByAddressResult {
+    transactions: Vec<AddressTransactionEntry>,
+    next_cursor: Option<TransactionsCursor>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AddressTransactionEntry {
+    hash: H256,
+    block_hash: BlockHash,
+    #[serde(with = "serde_utils::u64::hex_str")]
+    block_number: BlockNumber,
+    #[serde(with = "serde_utils::u64::hex_str")]
+    transaction_index: Index,
+}
+
+impl RpcHandler for GetTransactionsByAddressRequest {
+    fn parse(params: &Option<Vec<Value>>) -> Result<Self, RpcErr> {
+        let params = params
+            .as_ref()
+            .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+        if params.is_empty() || params.len() > 3 {
+            return Err(RpcErr::BadParams("Expected 1 to 3 params".to_owned()));
+        };
+        let cursor = match params.get(1) {
+            Some(value) => serde_json::from_value(value.clone())?,
+            None => None,
+        };
+        let limit = match params.get(2) {
+            Some(value) => serde_json::from_value::<Option<usize>>(value.clone())?
+                .unwrap_or(DEFAULT_TRANSACTIONS_BY_ADDRESS_LIMIT),
+            None => DEFAULT_TRANSACTIONS_BY_ADDRESS_LIMIT,
+        };
+        if limit == 0 {
+            return Err(RpcErr::BadParams("Limit must be greater than 0".to_owned()));
+        }
+        Ok(GetTransactionsByAddressRequest {
+            address: serde_json::from_value(params[0].clone())?,
+            cursor,
+            limit: limit.min(MAX_TRANSACTIONS_BY_ADDRESS_LIMIT),
+        })
+    }
+
+    async fn handle(&self, context: RpcApiContext) -> Result<Value, RpcErr> {
+        debug!("Requested transactions of address {:#x}", self.address);
+        if !context.storage.indexes_transactions_by_address() {
+            return Err(RpcErr::Internal(
+                "Address transaction index is disabled, restart the node with --tx-index.by-address to enable it"
+                    .to_owned(),
+            ));
+        }
+        let transactions = context
+            .storage
+            .get_transactions_by_address(
+                self.address,
+                self.cursor
+                    .map(|cursor| (cursor.block_number, cursor.transaction_index)),
+                self.limit,
+            )
+            .await?;
+        // A full page means there may be more transactions left
+        let next_cursor = transactions
+            .last()
+            .filter(|_| transactions.len() == self.limit)
+            .map(|last| TransactionsCursor {
+                block_number: last.block_number,
+                transaction_index: last.index,
+            });
+        let transactions = transactions
+            .into_iter()
+            .map(|entry| AddressTransactionEntry {
+                hash: entry.transaction_hash,
+                block_hash: entry.block_hash,
+                block_number: entry.block_number,
+                transaction_index: entry.index,
+            })
+            .collect();
+        Ok(serde_json::to_value(TransactionsByAddressResult {
+            transactions,
+            next_cursor,
+        })?)
+    }
+}
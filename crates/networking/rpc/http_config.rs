@@ -0,0 +1,407 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::http::{HeaderMap, HeaderValue, header::HOST};
+use serde_json::Value;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::utils::{
+    RpcErr, RpcErrorMetadata, RpcErrorResponse, RpcNamespace, RpcRequest, RpcRequestId,
+    RpcSuccessResponse,
+};
+
+/// Window over which the per-IP request rate limit is enforced
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+/// Amount of tracked clients above which stale rate limit entries are pruned
+const RATE_LIMIT_PRUNE_THRESHOLD: usize = 10_000;
+/// Methods writing to the node's filesystem, only served over HTTP to the local host
+const LOCAL_ONLY_METHODS: [&str; 2] = [
+    "admin_createCheckpoint",
+    "debug_standardTraceBadBlockToFile",
+];
+
+/// Access rules enforced on the public HTTP RPC server before requests are dispatched.
+/// The IPC server applies the same method and namespace filters, except for the local-only
+/// methods, while the auth RPC server is not affected by them.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Namespaces served, all of them if `None`
+    pub namespaces: Option<Vec<RpcNamespace>>,
+    /// Methods rejected even if their namespace is enabled
+    pub denied_methods: HashSet<String>,
+    /// Max amount of requests per second accepted from a single IP
+    pub rate_limit: Option<u32>,
+    /// Max amount of requests in a single batch, unlimited if `None`
+    pub max_batch_size: Option<usize>,
+    /// Max size in bytes of a response, batches included, unlimited if `None`
+    pub max_response_size: Option<usize>,
+    /// Origins allowed by CORS, `*` allows any
+    pub cors_origins: Vec<String>,
+    /// Values accepted in the `Host` header, `*` accepts any
+    pub vhosts: Vec<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            namespaces: None,
+            denied_methods: HashSet::new(),
+            rate_limit: None,
+            max_batch_size: None,
+            max_response_size: None,
+            cors_origins: vec!["*".to_string()],
+            vhosts: vec!["*".to_string()],
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Rejects requests for disabled namespaces or denied methods as if the method didn't exist
+    pub fn check_method(&self, req: &RpcRequest) -> Result<(), RpcErr> {
+        if self.denied_methods.contains(&req.method) {
+            return Err(RpcErr::MethodNotFound(req.method.clone()));
+        }
+        let namespace_enabled = match &self.namespaces {
+            Some(namespaces) => namespaces.contains(&req.namespace()?),
+            None => true,
+        };
+        if !namespace_enabled {
+            return Err(RpcErr::MethodNotFound(req.method.clone()));
+        }
+        Ok(())
+    }
+
+    /// Denies the methods that must not be reachable from other hosts if the server doesn't listen on a loopback address
+    pub fn deny_local_only_methods(&mut self, http_addr: SocketAddr) {
+        if !http_addr.ip().is_loopback() {
+            self.denied_methods
+                .extend(LOCAL_ONLY_METHODS.map(String::from));
+        }
+    }
+
+    /// Checks the `Host` header against the allowed virtual hosts.
+    /// Requests without one or addressed to an IP are always accepted, as they can't be DNS rebinding attacks.
+    pub fn is_host_allowed(&self, headers: &HeaderMap) -> bool {
+        if self.vhosts.iter().any(|vhost| vhost == "*") {
+            return true;
+        }
+        let Some(host) = headers.get(HOST).and_then(|host| host.to_str().ok()) else {
+            return true;
+        };
+        let hostname = strip_port(host);
+        if hostname.parse::<IpAddr>().is_ok() {
+            return true;
+        }
+        self.vhosts
+            .iter()
+            .any(|vhost| vhost.eq_ignore_ascii_case(hostname))
+    }
+
+    pub fn cors_layer(&self) -> CorsLayer {
+        if self.cors_origins.iter().any(|origin| origin == "*") {
+            // All request headers allowed.
+            // All methods allowed.
+            // All origins allowed.
+            // All headers exposed.
+            return CorsLayer::permissive();
+        }
+        let origins: Vec<HeaderValue> = self
+            .cors_origins
+            .iter()
+            .filter_map(|origin| HeaderValue::from_str(origin).ok())
+            .collect();
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods(Any)
+            .allow_headers(Any)
+    }
+}
+
+/// Access rules of the HTTP server along with the state needed to enforce them
+#[derive(Debug)]
+pub struct HttpAccess {
+    pub config: HttpConfig,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl HttpAccess {
+    pub fn new(config: HttpConfig) -> Self {
+        Self {
+            rate_limiter: config.rate_limit.map(RateLimiter::new),
+            config,
+        }
+    }
+
+    /// Returns whether the client can still send `requests` requests in the current window
+    pub fn check_rate_limit(&self, ip: IpAddr, requests: usize) -> bool {
+        self.rate_limiter.as_ref().is_none_or(|rate_limiter| {
+            rate_limiter.check(ip, u32::try_from(requests).unwrap_or(u32::MAX))
+        })
+    }
+}
+
+/// Body of a response of the HTTP server, holding at most the configured max response size.
+/// Responses are serialized as soon as they are computed, so a batch never keeps more than one
+/// unserialized response around, and serialization stops as soon as the limit is reached.
+#[derive(Debug)]
+pub(crate) struct ResponseBody {
+    body: Vec<u8>,
+    max_size: Option<usize>,
+    batch: bool,
+    empty: bool,
+    full: bool,
+}
+
+impl ResponseBody {
+    pub fn single(max_size: Option<usize>) -> Self {
+        Self {
+            body: Vec::new(),
+            max_size,
+            batch: false,
+            empty: true,
+            full: false,
+        }
+    }
+
+    pub fn batch(max_size: Option<usize>) -> Self {
+        Self {
+            body: vec![b'['],
+            batch: true,
+            ..Self::single(max_size)
+        }
+    }
+
+    /// Returns whether the limit was reached, in which case further requests shouldn't be served
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// Appends the response to a request, which is replaced by a `ResponseTooLarge` error
+    /// if it doesn't fit in the remaining size
+    pub fn push(&mut self, id: RpcRequestId, res: Result<Value, RpcErrorMetadata>) {
+        if !self.empty {
+            self.body.push(b',');
+        }
+        self.empty = false;
+        let start = self.body.len();
+        if !self.full {
+            let mut writer = LimitedWriter {
+                remaining: self.max_size.map(|max_size| max_size.saturating_sub(start)),
+                inner: &mut self.body,
+            };
+            if write_response(&mut writer, id.clone(), res).is_ok() {
+                return;
+            }
+            self.body.truncate(start);
+            self.full = true;
+        }
+        // Error responses are tiny, so they are written even over the limit
+        let _ = write_response(&mut self.body, id, Err(RpcErr::ResponseTooLarge.into()));
+    }
+
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        if self.batch {
+            self.body.push(b']');
+        }
+        self.body
+    }
+}
+
+fn write_response(
+    writer: impl Write,
+    id: RpcRequestId,
+    res: Result<Value, RpcErrorMetadata>,
+) -> serde_json::Result<()> {
+    let jsonrpc = "2.0".to_string();
+    match res {
+        Ok(result) => serde_json::to_writer(
+            writer,
+            &RpcSuccessResponse {
+                id,
+                jsonrpc,
+                result,
+            },
+        ),
+        Err(error) => serde_json::to_writer(writer, &RpcErrorResponse { id, jsonrpc, error }),
+    }
+}
+
+/// Fails writes going over `remaining` bytes
+struct LimitedWriter<'a> {
+    inner: &'a mut Vec<u8>,
+    remaining: Option<usize>,
+}
+
+impl Write for LimitedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining = remaining
+                .checked_sub(buf.len())
+                .ok_or_else(|| std::io::Error::other("response too large"))?;
+        }
+        self.inner.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Removes the port from a `Host` header value, handling bracketed IPv6 addresses
+fn strip_port(host: &str) -> &str {
+    if let Some(ipv6) = host.strip_prefix('[') {
+        return ipv6.split(']').next().unwrap_or(ipv6);
+    }
+    match host.rsplit_once(':') {
+        // A bare IPv6 address has more than one colon
+        Some((hostname, _port)) if !hostname.contains(':') => hostname,
+        _ => host,
+    }
+}
+
+/// Fixed window request counter per client IP
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: u32,
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Accounts for `requests` new requests from `ip`, returning whether they are within the limit
+    pub fn check(&self, ip: IpAddr, requests: u32) -> bool {
+        let now = Instant::now();
+        let Ok(mut clients) = self.clients.lock() else {
+            return true;
+        };
+        if clients.len() > RATE_LIMIT_PRUNE_THRESHOLD {
+            clients.retain(|_, (window_start, _)| now - *window_start < RATE_LIMIT_WINDOW);
+        }
+        let (window_start, count) = clients.entry(ip).or_insert((now, 0));
+        if now - *window_start >= RATE_LIMIT_WINDOW {
+            *window_start = now;
+            *count = 0;
+        }
+        *count = count.saturating_add(requests);
+        *count <= self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_with_host(host: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, HeaderValue::from_str(host).unwrap());
+        headers
+    }
+
+    #[test]
+    fn vhosts_are_enforced() {
+        let config = HttpConfig {
+            vhosts: vec!["localhost".to_string(), "node.example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(config.is_host_allowed(&headers_with_host("localhost:8545")));
+        assert!(config.is_host_allowed(&headers_with_host("Node.Example.com")));
+        assert!(config.is_host_allowed(&headers_with_host("127.0.0.1:8545")));
+        assert!(config.is_host_allowed(&headers_with_host("[::1]:8545")));
+        assert!(config.is_host_allowed(&HeaderMap::new()));
+        assert!(!config.is_host_allowed(&headers_with_host("evil.com:8545")));
+    }
+
+    #[test]
+    fn disabled_namespaces_and_denied_methods_are_rejected() {
+        let config = HttpConfig {
+            namespaces: Some(vec![RpcNamespace::Eth, RpcNamespace::Net]),
+            denied_methods: HashSet::from(["eth_sendRawTransaction".to_string()]),
+            ..Default::default()
+        };
+        assert!(
+            config
+                .check_method(&RpcRequest::new("eth_chainId", None))
+                .is_ok()
+        );
+        assert!(
+            config
+                .check_method(&RpcRequest::new("net_version", None))
+                .is_ok()
+        );
+        assert!(
+            config
+                .check_method(&RpcRequest::new("eth_sendRawTransaction", None))
+                .is_err()
+        );
+        assert!(
+            config
+                .check_method(&RpcRequest::new("admin_nodeInfo", None))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn local_only_methods_are_denied_on_public_addresses() {
+        for method in [
+            "admin_createCheckpoint",
+            "debug_standardTraceBadBlockToFile",
+        ] {
+            let request = RpcRequest::new(method, None);
+            let mut config = HttpConfig::default();
+            config.deny_local_only_methods("127.0.0.1:8545".parse().unwrap());
+            assert!(config.check_method(&request).is_ok());
+            config.deny_local_only_methods("0.0.0.0:8545".parse().unwrap());
+            assert!(config.check_method(&request).is_err());
+        }
+    }
+
+    #[test]
+    fn responses_over_the_max_size_are_replaced_by_errors() {
+        let result = Value::String("a".repeat(100));
+        let mut body = ResponseBody::batch(Some(150));
+        body.push(RpcRequestId::Number(1), Ok(result.clone()));
+        assert!(!body.is_full());
+        body.push(RpcRequestId::Number(2), Ok(result.clone()));
+        assert!(body.is_full());
+        body.push(RpcRequestId::Number(3), Ok(Value::Bool(true)));
+        let responses: Vec<Value> = serde_json::from_slice(&body.into_bytes()).unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["result"], result);
+        assert_eq!(responses[1]["error"]["code"], -32003);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[2]["error"]["code"], -32003);
+    }
+
+    #[test]
+    fn responses_are_unbounded_by_default() {
+        let result = Value::String("a".repeat(10_000));
+        let mut body = ResponseBody::single(None);
+        body.push(RpcRequestId::Number(1), Ok(result.clone()));
+        let response: Value = serde_json::from_slice(&body.into_bytes()).unwrap();
+        assert_eq!(response["result"], result);
+    }
+
+    #[test]
+    fn rate_limit_resets_every_window() {
+        let limiter = RateLimiter::new(2);
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        let other_ip: IpAddr = "127.0.0.2".parse().unwrap();
+        assert!(limiter.check(ip, 2));
+        assert!(!limiter.check(ip, 1));
+        assert!(limiter.check(other_ip, 1));
+        std::thread::sleep(RATE_LIMIT_WINDOW);
+        assert!(limiter.check(ip, 1));
+    }
+}
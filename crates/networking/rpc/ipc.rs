@@ -0,0 +1,374 @@
+//! JSON-RPC over a Unix domain socket.
+//!
+//! Requests are read as a stream of JSON values, so both newline-delimited and back-to-back
+//! requests are accepted, and every response is written followed by a newline, matching geth's IPC.
+//! The methods served are filtered by the same rules as the public HTTP server.
+
+use std::{
+    io::ErrorKind,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bytes::BytesMut;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream, unix::OwnedWriteHalf},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    http_config::HttpConfig,
+    rpc::{RpcRequestWrapper, RpcService, map_filtered_request, rpc_response, shutdown_signal},
+    utils::{RpcErr, RpcRequestId},
+};
+
+/// Max size of a single request, connections exceeding it are dropped
+const MAX_REQUEST_SIZE: usize = 5 * 1024 * 1024;
+/// Amount of bytes read from a connection at once
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Serves `service` over a Unix domain socket at `path` until the node shuts down.
+/// Requests for the methods rejected by `config` are answered as if the method didn't exist.
+pub async fn start_ipc_server<S: RpcService>(
+    path: PathBuf,
+    service: S,
+    config: HttpConfig,
+) -> std::io::Result<()> {
+    // A socket left behind by a previous run would make the bind fail
+    remove_stale_socket(&path)?;
+    let listener = bind_private(&path)?;
+    info!("Starting IPC server at {}", path.display());
+
+    let config = Arc::new(config);
+    let accept_loop = async {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, service.clone(), config.clone()));
+                }
+                Err(error) => warn!("Failed to accept IPC connection: {error}"),
+            }
+        }
+    };
+    tokio::select! {
+        _ = accept_loop => {}
+        _ = shutdown_signal() => {}
+    }
+    if let Err(error) = remove_stale_socket(&path) {
+        warn!("Failed to remove IPC socket {}: {error}", path.display());
+    }
+    Ok(())
+}
+
+/// Binds a socket at `path` that only the user running the node can connect to.
+/// The socket is created inside a directory only accessible by that user and moved to `path` once
+/// its permissions are restricted, so no one else can connect in between.
+fn bind_private(path: &Path) -> std::io::Result<UnixListener> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is not a valid socket path", path.display()),
+        )
+    })?;
+    // Created with mode 0700, and removed with its contents when dropped
+    let private_dir = tempfile::Builder::new()
+        .prefix(".ethrex-ipc-")
+        .tempdir_in(parent)?;
+    let private_path = private_dir.path().join(file_name);
+    let listener = UnixListener::bind(&private_path)?;
+    std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))?;
+    std::fs::rename(&private_path, path)?;
+    Ok(listener)
+}
+
+/// Removes the socket at the given path, failing if something other than a socket is there
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        Ok(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        )),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Serves the requests sent through the connection in order.
+/// Stops at the end of the stream, after the first malformed request or once a request exceeds `MAX_REQUEST_SIZE`.
+async fn handle_connection<S: RpcService>(stream: UnixStream, service: S, config: Arc<HttpConfig>) {
+    let (mut reader, mut writer) = stream.into_split();
+    let mut buffer = BytesMut::with_capacity(READ_BUFFER_SIZE);
+    let mut decoder = RequestDecoder::default();
+    loop {
+        let request = match decoder.decode(&mut buffer) {
+            Ok(Some(request)) => request,
+            Ok(None) => {
+                buffer.reserve(READ_BUFFER_SIZE);
+                match reader.read_buf(&mut buffer).await {
+                    // Incomplete requests left at the end of the stream are dropped
+                    Ok(0) => return,
+                    Ok(_) => continue,
+                    Err(error) => {
+                        debug!("Closing IPC connection: {error}");
+                        return;
+                    }
+                }
+            }
+            // The stream can't be resynchronized after malformed input, so the connection is closed
+            Err(error) if error.kind() == ErrorKind::InvalidData => {
+                let _ = write_response(&mut writer, &invalid_request_response(error)).await;
+                return;
+            }
+            Err(error) => {
+                debug!("Closing IPC connection: {error}");
+                return;
+            }
+        };
+        let response = handle_request(request, &service, &config).await;
+        if let Err(error) = write_response(&mut writer, &response).await {
+            debug!("Failed to write to IPC connection: {error}");
+            return;
+        }
+    }
+}
+
+async fn write_response(writer: &mut OwnedWriteHalf, response: &Value) -> std::io::Result<()> {
+    let mut response = serde_json::to_vec(response)?;
+    response.push(b'\n');
+    writer.write_all(&response).await
+}
+
+/// Splits the bytes read from a connection into requests, which are JSON objects or arrays
+/// optionally separated by whitespace.
+/// The end of a request is found by tracking the nesting of the bytes scanned so far, so bytes
+/// are only scanned once however many reads a request spans, and it's only parsed once complete.
+#[derive(Debug, Default)]
+struct RequestDecoder {
+    /// Amount of bytes at the start of the buffer already scanned without completing a request
+    scanned: usize,
+    /// Objects and arrays open at the scanned position
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl RequestDecoder {
+    /// Takes the first request out of the buffer, returning `None` if it isn't complete yet.
+    /// Input which isn't a request fails with [`ErrorKind::InvalidData`].
+    fn decode(&mut self, buffer: &mut BytesMut) -> std::io::Result<Option<Value>> {
+        for position in self.scanned..buffer.len() {
+            let byte = buffer[position];
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' if self.depth > 0 => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' if self.depth > 0 => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        *self = Self::default();
+                        let request = buffer.split_to(position + 1);
+                        if request.len() > MAX_REQUEST_SIZE {
+                            return Err(request_too_large());
+                        }
+                        return serde_json::from_slice(&request)
+                            .map(Some)
+                            .map_err(|error| std::io::Error::new(ErrorKind::InvalidData, error));
+                    }
+                }
+                _ if self.depth > 0 || byte.is_ascii_whitespace() => {}
+                _ => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unexpected byte {byte:#04x} between requests"),
+                    ));
+                }
+            }
+        }
+        self.scanned = buffer.len();
+        if self.scanned > MAX_REQUEST_SIZE {
+            return Err(request_too_large());
+        }
+        Ok(None)
+    }
+}
+
+fn request_too_large() -> std::io::Error {
+    std::io::Error::other(format!("request exceeds {MAX_REQUEST_SIZE} bytes"))
+}
+
+async fn handle_request<S: RpcService>(request: Value, service: &S, config: &HttpConfig) -> Value {
+    let response = match serde_json::from_value::<RpcRequestWrapper>(request) {
+        Ok(RpcRequestWrapper::Single(request)) => {
+            let res = map_filtered_request(&request, config, service).await;
+            rpc_response(request.id, res)
+        }
+        Ok(RpcRequestWrapper::Multiple(requests)) => {
+            let mut responses = Vec::new();
+            for request in requests {
+                let res = map_filtered_request(&request, config, service).await;
+                match rpc_response(request.id, res) {
+                    Ok(response) => responses.push(response),
+                    Err(error) => return invalid_request_response(error),
+                }
+            }
+            Ok(Value::Array(responses))
+        }
+        Err(error) => Err(error.into()),
+    };
+    response.unwrap_or_else(invalid_request_response)
+}
+
+fn invalid_request_response(error: impl std::fmt::Display) -> Value {
+    debug!("Invalid IPC request: {error}");
+    rpc_response(
+        RpcRequestId::String("".to_string()),
+        Err(RpcErr::BadParams("Invalid request body".to_string())),
+    )
+    .unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::default_context_with_storage;
+    use ethrex_storage::{EngineType, Store};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    /// Decodes the requests in `input`, fed to the decoder in chunks of `chunk_size` bytes,
+    /// up to the first error
+    fn decode_all(input: &[u8], chunk_size: usize) -> Vec<std::io::Result<Value>> {
+        let mut decoder = RequestDecoder::default();
+        let mut buffer = BytesMut::new();
+        let mut requests = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            buffer.extend_from_slice(chunk);
+            loop {
+                match decoder.decode(&mut buffer) {
+                    Ok(Some(request)) => requests.push(Ok(request)),
+                    Ok(None) => break,
+                    Err(error) => {
+                        requests.push(Err(error));
+                        return requests;
+                    }
+                }
+            }
+        }
+        requests
+    }
+
+    #[test]
+    fn decode_stops_at_incomplete_tail() {
+        let input = br#"{"id":1}
+{"id":2,"params":["}\"{"]}{"id":"#;
+        for chunk_size in [1, 5, input.len()] {
+            let requests = decode_all(input, chunk_size);
+            assert_eq!(requests.len(), 2);
+            assert_eq!(requests[1].as_ref().unwrap()["id"], 2);
+            assert_eq!(requests[1].as_ref().unwrap()["params"][0], "}\"{");
+        }
+    }
+
+    #[test]
+    fn decode_stops_after_malformed_request() {
+        for input in [
+            &br#"{"id":1} ]{"id":2}"#[..],
+            br#"{"id":1} {"id":]{"id":2}"#,
+        ] {
+            let requests = decode_all(input, input.len());
+            assert_eq!(requests.len(), 2);
+            assert!(requests[0].is_ok());
+            assert_eq!(
+                requests[1].as_ref().unwrap_err().kind(),
+                ErrorKind::InvalidData
+            );
+        }
+    }
+
+    #[test]
+    fn decode_drops_oversized_requests() {
+        let mut input = br#"{"id":1}"#.to_vec();
+        input.extend_from_slice(br#"{"data":""#);
+        input.resize(input.len() + MAX_REQUEST_SIZE, b'a');
+        let complete = [input.as_slice(), br#""}"#].concat();
+        for input in [input, complete] {
+            let requests = decode_all(&input, READ_BUFFER_SIZE);
+            assert_eq!(requests.len(), 2);
+            assert!(requests[0].is_ok());
+            assert_eq!(requests[1].as_ref().unwrap_err().kind(), ErrorKind::Other);
+        }
+    }
+
+    #[test]
+    fn only_stale_sockets_are_removed() {
+        let path = std::env::temp_dir().join(format!("ethrex-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "not a socket").unwrap();
+        assert!(remove_stale_socket(&path).is_err());
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+        assert!(remove_stale_socket(&path).is_ok());
+    }
+
+    #[tokio::test]
+    async fn serve_requests_over_ipc() {
+        let storage = Store::new("", EngineType::InMemory).expect("Failed to create test DB");
+        let context = default_context_with_storage(storage).await;
+        let path = std::env::temp_dir().join(format!("ethrex-test-{}.ipc", std::process::id()));
+        let config = HttpConfig {
+            denied_methods: ["eth_sendRawTransaction".to_string()].into(),
+            ..Default::default()
+        };
+        let server = tokio::spawn(start_ipc_server(path.clone(), context, config));
+
+        let mut stream = loop {
+            match UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        // Only the user running the node can connect to the socket
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // A newline-delimited request followed by others without a trailing newline
+        stream
+            .write_all(
+                br#"{"jsonrpc":"2.0","id":1,"method":"web3_clientVersion","params":[]}
+{"jsonrpc":"2.0","id":2,"method":"eth_unknownMethod","params":[]}{"jsonrpc":"2.0","id":3,"method":"eth_sendRawTransaction","params":["0x00"]}"#,
+            )
+            .await
+            .unwrap();
+
+        let mut lines = BufReader::new(stream).lines();
+        let first: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(first["id"], 1);
+        assert!(first["result"].is_string());
+        let second: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(second["id"], 2);
+        assert!(second["error"].is_object());
+        // Methods denied by the config aren't served
+        let third: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(third["id"], 3);
+        assert_eq!(third["error"]["code"], -32601);
+
+        server.abort();
+        let _ = std::fs::remove_file(path);
+    }
+}
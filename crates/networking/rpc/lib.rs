@@ -6,9 +6,14 @@ mod authentication;
 pub mod debug;
 mod engine;
 mod eth;
+mod ethrex;
+pub mod http_config;
+#[cfg(unix)]
+mod ipc;
 mod mempool;
 mod net;
 mod rpc;
+pub mod signer;
 mod tracing;
 
 pub mod clients;
@@ -20,14 +25,17 @@ pub use rpc::start_api;
 
 // TODO: These exports are needed by ethrex-l2-rpc, but we do not want to
 // export them in the public API of this crate.
+pub use admin::CreateCheckpointRequest;
 pub use eth::{
     filter::{ActiveFilters, clean_outdated_filters},
     gas_price::GasPrice,
     gas_tip_estimator::GasTipEstimator,
     transaction::EstimateGasRequest,
 };
+#[cfg(unix)]
+pub use ipc::start_ipc_server;
 pub use rpc::{
-    NodeData, RpcApiContext, RpcHandler, RpcRequestWrapper, map_debug_requests, map_eth_requests,
-    map_http_requests, rpc_response, shutdown_signal,
+    NodeData, RpcApiContext, RpcHandler, RpcRequestWrapper, RpcService, handle_http_request,
+    map_debug_requests, map_eth_requests, map_http_requests, rpc_response, shutdown_signal,
 };
 pub use utils::{RpcErr, RpcErrorMetadata, RpcNamespace};
@@ -1,5 +1,11 @@
 use crate::authentication::authenticate;
-use crate::debug::execution_witness::ExecutionWitnessRequest;
+use crate::debug::bad_blocks::GetBadBlocksRequest;
+use crate::debug::execution_witness::{ExecutionWitnessRequest, ExecutionWitnessStatsRequest};
+use crate::debug::modified_accounts::{
+    GetModifiedAccountsByHashRequest, GetModifiedAccountsByNumberRequest,
+};
+use crate::debug::state_range::{AccountRangeRequest, StorageRangeAtRequest};
+use crate::debug::verify_witness::VerifyExecutionWitnessRequest;
 use crate::engine::blobs::BlobsV2Request;
 use crate::engine::payload::GetPayloadV5Request;
 use crate::engine::{
@@ -19,6 +25,10 @@ use crate::eth::{
         GetBalanceRequest, GetCodeRequest, GetProofRequest, GetStorageAtRequest,
         GetTransactionCountRequest,
     },
+    accounts::{
+        AccountsRequest, SendTransactionRequest, SignRequest, SignTransactionRequest,
+        SignTypedDataRequest,
+    },
     block::{
         BlockNumberRequest, GetBlobBaseFee, GetBlockByHashRequest, GetBlockByNumberRequest,
         GetBlockReceiptsRequest, GetBlockTransactionCountRequest, GetRawBlockRequest,
@@ -36,7 +46,13 @@ use crate::eth::{
         GetTransactionByHashRequest, GetTransactionReceiptRequest,
     },
 };
-use crate::tracing::{TraceBlockByNumberRequest, TraceTransactionRequest};
+use crate::ethrex::GetTransactionsByAddressRequest;
+use crate::http_config::{HttpAccess, HttpConfig, ResponseBody};
+use crate::signer::{NonceLocks, Signer};
+use crate::tracing::{
+    GasProfileBlockRequest, GasProfileTransactionRequest, IntermediateRootsRequest,
+    StandardTraceBadBlockToFileRequest, TraceBlockByNumberRequest, TraceTransactionRequest,
+};
 use crate::types::transaction::SendRawTransactionRequest;
 use crate::utils::{
     RpcErr, RpcErrorMetadata, RpcErrorResponse, RpcNamespace, RpcRequest, RpcRequestId,
@@ -44,8 +60,10 @@ use crate::utils::{
 };
 use crate::{admin, net};
 use crate::{eth, mempool};
-use axum::extract::{DefaultBodyLimit, State};
-use axum::{Json, Router, http::StatusCode, routing::post};
+use axum::extract::{ConnectInfo, DefaultBodyLimit, State};
+use axum::http::{HeaderMap, header};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json, Router, http::StatusCode, routing::post};
 use axum_extra::{
     TypedHeader,
     headers::{Authorization, authorization::Bearer},
@@ -64,16 +82,14 @@ use std::{
     collections::HashMap,
     future::IntoFuture,
     net::SocketAddr,
+    path::PathBuf,
     sync::{Arc, Mutex},
     time::Duration,
 };
 use tokio::{net::TcpListener, sync::Mutex as TokioMutex};
-use tower_http::cors::CorsLayer;
 use tracing::{error, info};
 use tracing_subscriber::{EnvFilter, Registry, reload};
 
-#[cfg(all(feature = "jemalloc_profiling", target_os = "linux"))]
-use axum::response::IntoResponse;
 // only works on linux
 #[cfg(all(feature = "jemalloc_profiling", target_os = "linux"))]
 pub async fn handle_get_heap() -> Result<impl IntoResponse, (StatusCode, String)> {
@@ -164,6 +180,15 @@ pub struct RpcApiContext {
     pub gas_tip_estimator: Arc<TokioMutex<GasTipEstimator>>,
     pub log_filter_handler: Option<reload::Handle<EnvFilter, Registry>>,
     pub gas_ceil: u64,
+    /// Accounts managed by the node, used by the signing endpoints
+    pub signers: Arc<Vec<Signer>>,
+    /// Serializes the transactions sent from each managed account, see [NonceLocks]
+    pub nonce_locks: NonceLocks,
+    /// Directory where `admin_createCheckpoint` creates checkpoints, the method is disabled if `None`
+    pub checkpoint_dir: Option<PathBuf>,
+    /// Directory where `debug_standardTraceBadBlockToFile` writes traces, a directory within the
+    /// system's temporary directory if `None`
+    pub bad_block_traces_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -198,7 +223,9 @@ pub const FILTER_DURATION: Duration = {
 #[allow(clippy::too_many_arguments)]
 pub async fn start_api(
     http_addr: SocketAddr,
-    authrpc_addr: SocketAddr,
+    authrpc_addr: Option<SocketAddr>,
+    ipc_path: Option<PathBuf>,
+    http_config: HttpConfig,
     storage: Store,
     blockchain: Arc<Blockchain>,
     jwt_secret: Bytes,
@@ -210,6 +237,9 @@ pub async fn start_api(
     log_filter_handler: Option<reload::Handle<EnvFilter, Registry>>,
     gas_ceil: Option<u64>,
     extra_data: String,
+    signers: Vec<Signer>,
+    checkpoint_dir: Option<PathBuf>,
+    bad_block_traces_dir: Option<PathBuf>,
 ) -> Result<(), RpcErr> {
     // TODO: Refactor how filters are handled,
     // filters are used by the filters endpoints (eth_newFilter, eth_getFilterChanges, ...etc)
@@ -230,6 +260,10 @@ pub async fn start_api(
         gas_tip_estimator: Arc::new(TokioMutex::new(GasTipEstimator::new())),
         log_filter_handler,
         gas_ceil: gas_ceil.unwrap_or(DEFAULT_BUILDER_GAS_CEIL),
+        signers: Arc::new(signers),
+        nonce_locks: Default::default(),
+        checkpoint_dir,
+        bad_block_traces_dir,
     };
 
     // Periodically clean up the active filters for the filters endpoints.
@@ -244,11 +278,12 @@ pub async fn start_api(
         }
     });
 
-    // All request headers allowed.
-    // All methods allowed.
-    // All origins allowed.
-    // All headers exposed.
-    let cors = CorsLayer::permissive();
+    // The IPC server is only reachable from the local host, so it serves the local-only methods
+    let ipc_config = http_config.clone();
+    let mut http_config = http_config;
+    http_config.deny_local_only_methods(http_addr);
+    let cors = http_config.cors_layer();
+    let http_access = Arc::new(HttpAccess::new(http_config));
 
     let http_router = Router::new()
         .route("/debug/pprof/allocs", axum::routing::get(handle_get_heap))
@@ -256,34 +291,81 @@ pub async fn start_api(
             "/debug/pprof/allocs/flamegraph",
             axum::routing::get(handle_get_heap_flamegraph),
         )
-        .route("/", post(handle_http_request))
+        .route("/", post(handle_http_request::<RpcApiContext>))
         .layer(cors)
+        .layer(Extension(http_access))
         .with_state(service_context.clone());
     let http_listener = TcpListener::bind(http_addr)
         .await
         .map_err(|error| RpcErr::Internal(error.to_string()))?;
-    let http_server = axum::serve(http_listener, http_router)
-        .with_graceful_shutdown(shutdown_signal())
-        .into_future();
+    let http_server = axum::serve(
+        http_listener,
+        http_router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .into_future();
     info!("Starting HTTP server at {http_addr}");
 
-    let authrpc_handler = |ctx, auth, body| async { handle_authrpc_request(ctx, auth, body).await };
-    let authrpc_router = Router::new()
-        .route("/", post(authrpc_handler))
-        .with_state(service_context)
-        // Bump the body limit for the engine API to 256MB
-        // This is needed to receive payloads bigger than the default limit of 2MB
-        .layer(DefaultBodyLimit::max(256 * 1024 * 1024));
+    let ipc_context = service_context.clone();
+    // Failing to serve IPC must not take down the HTTP and engine servers
+    let ipc_server = async move {
+        match ipc_path {
+            #[cfg(unix)]
+            Some(ipc_path) => {
+                if let Err(error) =
+                    crate::ipc::start_ipc_server(ipc_path.clone(), ipc_context, ipc_config).await
+                {
+                    error!(
+                        "Failed to serve RPC over IPC at {}: {error}",
+                        ipc_path.display()
+                    );
+                }
+                Ok::<(), std::io::Error>(())
+            }
+            #[cfg(not(unix))]
+            Some(ipc_path) => {
+                tracing::warn!(
+                    "IPC is only supported on unix platforms, not serving RPC at {}",
+                    ipc_path.display()
+                );
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    };
 
-    let authrpc_listener = TcpListener::bind(authrpc_addr)
-        .await
-        .map_err(|error| RpcErr::Internal(error.to_string()))?;
-    let authrpc_server = axum::serve(authrpc_listener, authrpc_router)
-        .with_graceful_shutdown(shutdown_signal())
-        .into_future();
-    info!("Starting Auth-RPC server at {authrpc_addr}");
+    // Nodes that don't follow a consensus client, such as read-only ones, don't serve the engine API
+    let authrpc_server = match authrpc_addr {
+        Some(authrpc_addr) => {
+            let authrpc_handler =
+                |ctx, auth, body| async { handle_authrpc_request(ctx, auth, body).await };
+            let authrpc_router = Router::new()
+                .route("/", post(authrpc_handler))
+                .with_state(service_context)
+                // Bump the body limit for the engine API to 256MB
+                // This is needed to receive payloads bigger than the default limit of 2MB
+                .layer(DefaultBodyLimit::max(256 * 1024 * 1024));
+
+            let authrpc_listener = TcpListener::bind(authrpc_addr)
+                .await
+                .map_err(|error| RpcErr::Internal(error.to_string()))?;
+            info!("Starting Auth-RPC server at {authrpc_addr}");
+            Some(
+                axum::serve(authrpc_listener, authrpc_router)
+                    .with_graceful_shutdown(shutdown_signal())
+                    .into_future(),
+            )
+        }
+        None => None,
+    };
+    let authrpc_server = async move {
+        match authrpc_server {
+            Some(server) => server.await,
+            None => Ok(()),
+        }
+    };
 
-    let _ = tokio::try_join!(authrpc_server, http_server)
+    let _ = tokio::try_join!(authrpc_server, http_server, ipc_server)
         .inspect_err(|e| error!("Error shutting down servers: {e:?}"));
 
     Ok(())
@@ -295,30 +377,81 @@ pub async fn shutdown_signal() {
         .expect("failed to install Ctrl+C handler");
 }
 
-async fn handle_http_request(
-    State(service_context): State<RpcApiContext>,
+/// Handles a request received by a public HTTP server, enforcing its access rules.
+/// Shared by the L1 and L2 servers, which differ in the service answering the requests.
+pub async fn handle_http_request<S: RpcService>(
+    State(service): State<S>,
+    Extension(http_access): Extension<Arc<HttpAccess>>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     body: String,
-) -> Result<Json<Value>, StatusCode> {
-    let res = match serde_json::from_str::<RpcRequestWrapper>(&body) {
+) -> Result<Response, StatusCode> {
+    if !http_access.config.is_host_allowed(&headers) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let max_response_size = http_access.config.max_response_size;
+    let response = match serde_json::from_str::<RpcRequestWrapper>(&body) {
         Ok(RpcRequestWrapper::Single(request)) => {
-            let res = map_http_requests(&request, service_context).await;
-            rpc_response(request.id, res).map_err(|_| StatusCode::BAD_REQUEST)?
+            if !http_access.check_rate_limit(client_addr.ip(), 1) {
+                return Err(StatusCode::TOO_MANY_REQUESTS);
+            }
+            let mut response = ResponseBody::single(max_response_size);
+            let res = map_filtered_request(&request, &http_access.config, &service).await;
+            response.push(request.id, res);
+            response
         }
         Ok(RpcRequestWrapper::Multiple(requests)) => {
-            let mut responses = Vec::new();
-            for req in requests {
-                let res = map_http_requests(&req, service_context.clone()).await;
-                responses.push(rpc_response(req.id, res).map_err(|_| StatusCode::BAD_REQUEST)?);
+            match http_access.config.max_batch_size {
+                Some(max_batch_size) if requests.len() > max_batch_size => {
+                    let mut response = ResponseBody::single(None);
+                    response.push(
+                        RpcRequestId::String("".to_string()),
+                        Err(RpcErr::BatchTooLarge(max_batch_size).into()),
+                    );
+                    response
+                }
+                _ => {
+                    if !http_access.check_rate_limit(client_addr.ip(), requests.len()) {
+                        return Err(StatusCode::TOO_MANY_REQUESTS);
+                    }
+                    let mut response = ResponseBody::batch(max_response_size);
+                    for req in requests {
+                        // Once over the max response size the remaining requests aren't served
+                        let res = if response.is_full() {
+                            Err(RpcErr::ResponseTooLarge.into())
+                        } else {
+                            map_filtered_request(&req, &http_access.config, &service).await
+                        };
+                        response.push(req.id, res);
+                    }
+                    response
+                }
             }
-            serde_json::to_value(responses).map_err(|_| StatusCode::BAD_REQUEST)?
         }
-        Err(_) => rpc_response(
-            RpcRequestId::String("".to_string()),
-            Err(RpcErr::BadParams("Invalid request body".to_string())),
-        )
-        .map_err(|_| StatusCode::BAD_REQUEST)?,
+        Err(_) => {
+            let mut response = ResponseBody::single(None);
+            response.push(
+                RpcRequestId::String("".to_string()),
+                Err(RpcErr::BadParams("Invalid request body".to_string()).into()),
+            );
+            response
+        }
     };
-    Ok(Json(res))
+    Ok((
+        [(header::CONTENT_TYPE, "application/json")],
+        response.into_bytes(),
+    )
+        .into_response())
+}
+
+/// Handles a request received by a public HTTP or IPC server, rejecting the methods it doesn't expose
+pub(crate) async fn map_filtered_request<S: RpcService>(
+    req: &RpcRequest,
+    config: &HttpConfig,
+    service: &S,
+) -> Result<Value, RpcErrorMetadata> {
+    config.check_method(req)?;
+    service.handle_request(req).await
 }
 
 pub async fn handle_authrpc_request(
@@ -352,6 +485,23 @@ pub async fn handle_authrpc_request(
     }
 }
 
+/// Answers the requests received by the public HTTP and IPC servers.
+/// Implemented by the L1 context and by the L2 one, which serves additional methods.
+pub trait RpcService: Clone + Send + Sync + 'static {
+    fn handle_request(
+        &self,
+        req: &RpcRequest,
+    ) -> impl Future<Output = Result<Value, RpcErrorMetadata>> + Send;
+}
+
+impl RpcService for RpcApiContext {
+    async fn handle_request(&self, req: &RpcRequest) -> Result<Value, RpcErrorMetadata> {
+        map_http_requests(req, self.clone())
+            .await
+            .map_err(Into::into)
+    }
+}
+
 /// Handle requests that can come from either clients or other users
 pub async fn map_http_requests(req: &RpcRequest, context: RpcApiContext) -> Result<Value, RpcErr> {
     match req.namespace() {
@@ -361,6 +511,7 @@ pub async fn map_http_requests(req: &RpcRequest, context: RpcApiContext) -> Resu
         Ok(RpcNamespace::Web3) => map_web3_requests(req, context),
         Ok(RpcNamespace::Net) => map_net_requests(req, context).await,
         Ok(RpcNamespace::Mempool) => map_mempool_requests(req, context).await,
+        Ok(RpcNamespace::Ethrex) => map_ethrex_requests(req, context).await,
         Ok(RpcNamespace::Engine) => Err(RpcErr::Internal(
             "Engine namespace not allowed in map_http_requests".to_owned(),
         )),
@@ -422,6 +573,11 @@ pub async fn map_eth_requests(req: &RpcRequest, context: RpcApiContext) -> Resul
             FilterChangesRequest::stateful_call(req, context.storage, context.active_filters).await
         }
         "eth_sendRawTransaction" => SendRawTransactionRequest::call(req, context).await,
+        "eth_accounts" => AccountsRequest::call(req, context).await,
+        "eth_sendTransaction" => SendTransactionRequest::call(req, context).await,
+        "eth_signTransaction" => SignTransactionRequest::call(req, context).await,
+        "eth_sign" => SignRequest::call(req, context).await,
+        "eth_signTypedData_v4" => SignTypedDataRequest::call(req, context).await,
         "eth_getProof" => GetProofRequest::call(req, context).await,
         "eth_gasPrice" => GasPrice::call(req, context).await,
         "eth_maxPriorityFeePerGas" => {
@@ -439,8 +595,25 @@ pub async fn map_debug_requests(req: &RpcRequest, context: RpcApiContext) -> Res
         "debug_getRawTransaction" => GetRawTransaction::call(req, context).await,
         "debug_getRawReceipts" => GetRawReceipts::call(req, context).await,
         "debug_executionWitness" => ExecutionWitnessRequest::call(req, context).await,
+        "debug_executionWitnessStats" => ExecutionWitnessStatsRequest::call(req, context).await,
+        "debug_verifyExecutionWitness" => VerifyExecutionWitnessRequest::call(req, context).await,
         "debug_traceTransaction" => TraceTransactionRequest::call(req, context).await,
         "debug_traceBlockByNumber" => TraceBlockByNumberRequest::call(req, context).await,
+        "debug_gasProfileTransaction" => GasProfileTransactionRequest::call(req, context).await,
+        "debug_gasProfileBlock" => GasProfileBlockRequest::call(req, context).await,
+        "debug_getBadBlocks" => GetBadBlocksRequest::call(req, context).await,
+        "debug_intermediateRoots" => IntermediateRootsRequest::call(req, context).await,
+        "debug_standardTraceBadBlockToFile" => {
+            StandardTraceBadBlockToFileRequest::call(req, context).await
+        }
+        "debug_getModifiedAccountsByNumber" => {
+            GetModifiedAccountsByNumberRequest::call(req, context).await
+        }
+        "debug_getModifiedAccountsByHash" => {
+            GetModifiedAccountsByHashRequest::call(req, context).await
+        }
+        "debug_storageRangeAt" => StorageRangeAtRequest::call(req, context).await,
+        "debug_accountRange" => AccountRangeRequest::call(req, context).await,
         unknown_debug_method => Err(RpcErr::MethodNotFound(unknown_debug_method.to_owned())),
     }
 }
@@ -486,6 +659,7 @@ pub async fn map_admin_requests(
         "admin_nodeInfo" => admin::node_info(context.storage, &context.node_data),
         "admin_peers" => admin::peers(&mut context).await,
         "admin_setLogLevel" => admin::set_log_level(req, &context.log_filter_handler).await,
+        "admin_createCheckpoint" => admin::CreateCheckpointRequest::call(req, context).await,
         unknown_admin_method => Err(RpcErr::MethodNotFound(unknown_admin_method.to_owned())),
     }
 }
@@ -517,6 +691,18 @@ pub async fn map_mempool_requests(
     }
 }
 
+pub async fn map_ethrex_requests(
+    req: &RpcRequest,
+    context: RpcApiContext,
+) -> Result<Value, RpcErr> {
+    match req.method.as_str() {
+        "ethrex_getTransactionsByAddress" => {
+            GetTransactionsByAddressRequest::call(req, context).await
+        }
+        unknown_ethrex_method => Err(RpcErr::MethodNotFound(unknown_ethrex_method.to_owned())),
+    }
+}
+
 pub fn rpc_response<E>(id: RpcRequestId, res: Result<Value, E>) -> Result<Value, RpcErr>
 where
     E: Into<RpcErrorMetadata>,
@@ -8,11 +8,17 @@ use ethrex_common::{
         LegacyTransaction, Transaction, TxType,
     },
 };
-use ethrex_rlp::encode::PayloadRLPEncode;
+use ethrex_rlp::{encode::PayloadRLPEncode, structs::Encoder};
 use reqwest::{Client, StatusCode, Url};
 use rustc_hex::FromHexError;
 use secp256k1::{Message, PublicKey, SECP256K1, SecretKey};
 use serde::Serialize;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+};
+use tokio::sync::{Mutex as TokioMutex, OwnedMutexGuard};
 use url::ParseError;
 
 #[derive(Clone, Debug)]
@@ -66,6 +72,25 @@ impl Signer {
     }
 }
 
+/// Locks of the managed accounts, held while sending a transaction from picking its nonce until it's
+/// in the mempool so that concurrent requests from the same account get consecutive nonces
+#[derive(Clone, Debug, Default)]
+pub struct NonceLocks(Arc<Mutex<HashMap<Address, Arc<TokioMutex<()>>>>>);
+
+impl NonceLocks {
+    /// Waits for the lock of the account, which is released when the returned guard is dropped
+    pub async fn lock(&self, address: Address) -> OwnedMutexGuard<()> {
+        let lock = self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(address)
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
+}
+
 impl From<LocalSigner> for Signer {
     fn from(value: LocalSigner) -> Self {
         Self::Local(value)
@@ -95,6 +120,18 @@ impl LocalSigner {
         }
     }
 
+    /// Decrypts the private key of a Web3 Secret Storage (keystore V3) JSON file
+    pub fn from_keystore(
+        path: impl AsRef<Path>,
+        password: impl AsRef<[u8]>,
+    ) -> Result<Self, SignerError> {
+        let private_key = eth_keystore::decrypt_key(path, password)
+            .map_err(|e| SignerError::KeystoreError(e.to_string()))?;
+        let private_key = SecretKey::from_slice(&private_key)
+            .map_err(|e| SignerError::KeystoreError(e.to_string()))?;
+        Ok(Self::new(private_key))
+    }
+
     pub fn sign(&self, data: Bytes) -> Signature {
         let hash = keccak(data);
         let msg = Message::from_digest(hash.0);
@@ -199,12 +236,22 @@ pub enum SignerError {
     PrivilegedL2TxUnsupported,
     #[error("Web3signer error: {0}")]
     Web3SignerError(String),
+    #[error("Failed to decrypt keystore: {0}")]
+    KeystoreError(String),
+}
+
+/// Returns the recovery id of a signature, remote signers return it as 27 or 28 instead of 0 or 1
+fn recovery_id(signature: &Signature) -> u8 {
+    match signature[64] {
+        v @ 27.. => v - 27,
+        v => v,
+    }
 }
 
 fn parse_signature(signature: Signature) -> (U256, U256, bool) {
     let r = U256::from_big_endian(&signature[..32]);
     let s = U256::from_big_endian(&signature[32..64]);
-    let y_parity = signature[64] != 0 && signature[64] != 27;
+    let y_parity = recovery_id(&signature) != 0;
 
     (r, s, y_parity)
 }
@@ -243,18 +290,45 @@ impl Signable for Transaction {
     }
 }
 
+/// Signs without replay protection, the signature is valid on every chain.
+/// Use [`sign_legacy_transaction_eip155`] to bind it to a chain.
 impl Signable for LegacyTransaction {
     async fn sign_inplace(&mut self, signer: &Signer) -> Result<(), SignerError> {
         let signature = signer.sign(self.encode_payload_to_vec().into()).await?;
 
-        let recovery_id = U256::from(signature[64]);
-        self.v = recovery_id + 27;
+        self.v = U256::from(recovery_id(&signature)) + 27;
         (self.r, self.s, _) = parse_signature(signature);
 
         Ok(())
     }
 }
 
+/// Signs a legacy transaction with EIP-155 replay protection, so that it's only valid on the given chain
+pub async fn sign_legacy_transaction_eip155(
+    tx: &mut LegacyTransaction,
+    signer: &Signer,
+    chain_id: u64,
+) -> Result<(), SignerError> {
+    let mut payload = vec![];
+    Encoder::new(&mut payload)
+        .encode_field(&tx.nonce)
+        .encode_field(&tx.gas_price)
+        .encode_field(&tx.gas)
+        .encode_field(&tx.to)
+        .encode_field(&tx.value)
+        .encode_field(&tx.data)
+        .encode_field(&chain_id)
+        .encode_field(&0u8)
+        .encode_field(&0u8)
+        .finish();
+    let signature = signer.sign(payload.into()).await?;
+
+    tx.v = U256::from(recovery_id(&signature)) + 35 + U256::from(chain_id) * 2;
+    (tx.r, tx.s, _) = parse_signature(signature);
+
+    Ok(())
+}
+
 impl Signable for EIP1559Transaction {
     async fn sign_inplace(&mut self, signer: &Signer) -> Result<(), SignerError> {
         let mut payload = vec![TxType::EIP1559 as u8];
@@ -1,16 +1,26 @@
-use std::time::Duration;
+use std::{fs::File, io::BufWriter, time::Duration};
 
 use ethrex_common::H256;
-use ethrex_common::{serde_utils, tracing::CallTrace, types::BlockNumber};
-use serde::{Deserialize, Serialize};
+use ethrex_common::{
+    serde_utils,
+    tracing::CallTrace,
+    types::{BlockHash, BlockNumber},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::Value;
 
-use crate::{rpc::RpcHandler, utils::RpcErr};
+use crate::{
+    debug::bad_blocks::get_block_or_bad_block, rpc::RpcHandler,
+    types::block_identifier::BlockIdentifierOrHash, utils::RpcErr,
+};
 
 /// Default max amount of blocks to re-excute if it is not given
 const DEFAULT_REEXEC: u32 = 128;
 /// Default max amount of time to spend tracing a transaction (doesn't take into account state rebuild time)
-const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Directory within the system's temporary directory where bad block traces are written to, unless
+/// another directory is configured
+const BAD_BLOCK_TRACES_DIR: &str = "bad_block_traces";
 
 pub struct TraceTransactionRequest {
     tx_hash: H256,
@@ -22,6 +32,64 @@ pub struct TraceBlockByNumberRequest {
     trace_config: TraceConfig,
 }
 
+/// Returns where the gas of a transaction goes, by opcode, contract and function selector
+pub struct GasProfileTransactionRequest {
+    tx_hash: H256,
+    config: GasProfileConfig,
+}
+
+/// Returns where the gas of all the transactions of a block goes, by opcode, contract and function selector
+pub struct GasProfileBlockRequest {
+    block: BlockIdentifierOrHash,
+    config: GasProfileConfig,
+}
+
+/// Returns the state root after executing each transaction of the given block, which may be a bad block
+pub struct IntermediateRootsRequest {
+    block_hash: BlockHash,
+    config: IntermediateRootsConfig,
+}
+
+/// Re-executes a bad block tracing the opcodes of its transactions, and writes the EIP-3155 trace
+/// of each one to its own file, returning the paths of the files written
+pub struct StandardTraceBadBlockToFileRequest {
+    block_hash: BlockHash,
+    config: StandardTraceConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct IntermediateRootsConfig {
+    #[serde(default, with = "serde_utils::duration::opt")]
+    timeout: Option<Duration>,
+    #[serde(default)]
+    reexec: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct StandardTraceConfig {
+    /// Only trace the transaction with this hash instead of the whole block
+    #[serde(default)]
+    tx_hash: Option<H256>,
+    #[serde(default, with = "serde_utils::duration::opt")]
+    timeout: Option<Duration>,
+    #[serde(default)]
+    reexec: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct GasProfileConfig {
+    /// Also output the profile as folded stacks, the input format of flamegraph tools
+    #[serde(default)]
+    folded: bool,
+    #[serde(default, with = "serde_utils::duration::opt")]
+    timeout: Option<Duration>,
+    #[serde(default)]
+    reexec: Option<u32>,
+}
+
 #[derive(Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 struct TraceConfig {
@@ -181,3 +249,189 @@ impl RpcHandler for TraceBlockByNumberRequest {
         }
     }
 }
+
+/// Parses the params of the gas profile requests: the transaction or block to profile and an
+/// optional config
+fn parse_gas_profile_params(
+    params: &Option<Vec<serde_json::Value>>,
+) -> Result<(&Value, GasProfileConfig), RpcErr> {
+    let params = params
+        .as_ref()
+        .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+    if params.len() != 1 && params.len() != 2 {
+        return Err(RpcErr::BadParams("Expected 1 or 2 params".to_owned()));
+    };
+    let config = if params.len() == 2 {
+        serde_json::from_value(params[1].clone())?
+    } else {
+        GasProfileConfig::default()
+    };
+    Ok((&params[0], config))
+}
+
+impl RpcHandler for GasProfileTransactionRequest {
+    fn parse(params: &Option<Vec<serde_json::Value>>) -> Result<Self, RpcErr> {
+        let (tx_hash, config) = parse_gas_profile_params(params)?;
+        Ok(GasProfileTransactionRequest {
+            tx_hash: serde_json::from_value(tx_hash.clone())?,
+            config,
+        })
+    }
+
+    async fn handle(
+        &self,
+        context: crate::rpc::RpcApiContext,
+    ) -> Result<serde_json::Value, crate::utils::RpcErr> {
+        let profile = context
+            .blockchain
+            .gas_profile_transaction(
+                self.tx_hash,
+                self.config.reexec.unwrap_or(DEFAULT_REEXEC),
+                self.config.timeout.unwrap_or(DEFAULT_TIMEOUT),
+                self.config.folded,
+            )
+            .await
+            .map_err(|err| RpcErr::Internal(err.to_string()))?;
+        Ok(serde_json::to_value(profile)?)
+    }
+}
+
+impl RpcHandler for GasProfileBlockRequest {
+    fn parse(params: &Option<Vec<serde_json::Value>>) -> Result<Self, RpcErr> {
+        let (block, config) = parse_gas_profile_params(params)?;
+        Ok(GasProfileBlockRequest {
+            block: BlockIdentifierOrHash::parse(block.clone(), 0)?,
+            config,
+        })
+    }
+
+    async fn handle(
+        &self,
+        context: crate::rpc::RpcApiContext,
+    ) -> Result<serde_json::Value, crate::utils::RpcErr> {
+        let Some(number) = self.block.resolve_block_number(&context.storage).await? else {
+            return Err(RpcErr::Internal("Block not Found".to_string()));
+        };
+        let block = context
+            .storage
+            .get_block_by_number(number)
+            .await?
+            .ok_or(RpcErr::Internal("Block not Found".to_string()))?;
+        let profile = context
+            .blockchain
+            .gas_profile_block(
+                block,
+                self.config.reexec.unwrap_or(DEFAULT_REEXEC),
+                self.config.timeout.unwrap_or(DEFAULT_TIMEOUT),
+                self.config.folded,
+            )
+            .await
+            .map_err(|err| RpcErr::Internal(err.to_string()))?;
+        Ok(serde_json::to_value(profile)?)
+    }
+}
+
+/// Parses the params of the bad block requests: the hash of the block and an optional config
+fn parse_block_hash_params<Config: DeserializeOwned + Default>(
+    params: &Option<Vec<serde_json::Value>>,
+) -> Result<(BlockHash, Config), RpcErr> {
+    let params = params
+        .as_ref()
+        .ok_or(RpcErr::BadParams("No params provided".to_owned()))?;
+    if params.len() != 1 && params.len() != 2 {
+        return Err(RpcErr::BadParams("Expected 1 or 2 params".to_owned()));
+    };
+    let config = if params.len() == 2 {
+        serde_json::from_value(params[1].clone())?
+    } else {
+        Config::default()
+    };
+    Ok((serde_json::from_value(params[0].clone())?, config))
+}
+
+impl RpcHandler for IntermediateRootsRequest {
+    fn parse(params: &Option<Vec<serde_json::Value>>) -> Result<Self, RpcErr> {
+        let (block_hash, config) = parse_block_hash_params(params)?;
+        Ok(IntermediateRootsRequest { block_hash, config })
+    }
+
+    async fn handle(
+        &self,
+        context: crate::rpc::RpcApiContext,
+    ) -> Result<serde_json::Value, crate::utils::RpcErr> {
+        let block = get_block_or_bad_block(&context, self.block_hash).await?;
+        let roots = context
+            .blockchain
+            .intermediate_roots(
+                block,
+                self.config.reexec.unwrap_or(DEFAULT_REEXEC),
+                self.config.timeout.unwrap_or(DEFAULT_TIMEOUT),
+            )
+            .await
+            .map_err(|err| RpcErr::Internal(err.to_string()))?;
+        Ok(serde_json::to_value(roots)?)
+    }
+}
+
+impl RpcHandler for StandardTraceBadBlockToFileRequest {
+    fn parse(params: &Option<Vec<serde_json::Value>>) -> Result<Self, RpcErr> {
+        let (block_hash, config) = parse_block_hash_params(params)?;
+        Ok(StandardTraceBadBlockToFileRequest { block_hash, config })
+    }
+
+    async fn handle(
+        &self,
+        context: crate::rpc::RpcApiContext,
+    ) -> Result<serde_json::Value, crate::utils::RpcErr> {
+        let block = context
+            .storage
+            .get_bad_block(self.block_hash)
+            .await?
+            .ok_or(RpcErr::Internal("Bad block not Found".to_string()))?;
+        let txs: Vec<(usize, H256)> = block
+            .body
+            .transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .enumerate()
+            .filter(|(_, tx_hash)| self.config.tx_hash.is_none_or(|hash| hash == *tx_hash))
+            .collect();
+        // Only the requested transaction is executed, there's nothing to trace if it's not in the block
+        let tx_index = match (self.config.tx_hash, txs.first()) {
+            (Some(_), Some((index, _))) => Some(*index),
+            (Some(_), None) => return Ok(serde_json::to_value(Vec::<String>::new())?),
+            (None, _) => None,
+        };
+        let dir = context
+            .bad_block_traces_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join(BAD_BLOCK_TRACES_DIR));
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|err| RpcErr::Internal(err.to_string()))?;
+        let block_hash = self.block_hash;
+        let trace_path = move |index: usize, tx_hash: H256| {
+            dir.join(format!(
+                "block_0x{}-{index}-0x{}.json",
+                hex::encode(&block_hash[..4]),
+                hex::encode(&tx_hash[..4])
+            ))
+        };
+        let files: Vec<String> = txs
+            .into_iter()
+            .map(|(index, tx_hash)| trace_path(index, tx_hash).display().to_string())
+            .collect();
+        let reexec = self.config.reexec.unwrap_or(DEFAULT_REEXEC);
+        let timeout = self.config.timeout.unwrap_or(DEFAULT_TIMEOUT);
+        // Each trace is written to its file as its transaction is executed, a JSON object per
+        // line following EIP-3155
+        context
+            .blockchain
+            .trace_block_opcodes(block, tx_index, reexec, timeout, move |index, tx_hash| {
+                File::create(trace_path(index, tx_hash)).map(BufWriter::new)
+            })
+            .await
+            .map_err(|err| RpcErr::Internal(err.to_string()))?;
+        Ok(serde_json::to_value(files)?)
+    }
+}
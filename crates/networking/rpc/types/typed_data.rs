@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ethrex_common::{Address, H256, U256, utils::keccak};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::utils::RpcErr;
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+/// Structured data as defined by EIP-712, as received by `eth_signTypedData_v4`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    pub types: BTreeMap<String, Vec<TypedDataField>>,
+    pub primary_type: String,
+    pub domain: Value,
+    pub message: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TypedDataField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+}
+
+impl TypedData {
+    /// Returns the bytes whose keccak hash is signed: `0x19 ‖ 0x01 ‖ domainSeparator ‖ hashStruct(message)`
+    pub fn encode_for_signing(&self) -> Result<Vec<u8>, RpcErr> {
+        let domain_separator = self.hash_struct(DOMAIN_TYPE, &self.domain)?;
+        let message_hash = self.hash_struct(&self.primary_type, &self.message)?;
+        let mut encoded = Vec::with_capacity(66);
+        encoded.extend_from_slice(&[0x19, 0x01]);
+        encoded.extend_from_slice(domain_separator.as_bytes());
+        encoded.extend_from_slice(message_hash.as_bytes());
+        Ok(encoded)
+    }
+
+    /// Returns the EIP-712 signing hash of the typed data
+    pub fn hash(&self) -> Result<H256, RpcErr> {
+        Ok(keccak(self.encode_for_signing()?))
+    }
+
+    fn fields(&self, type_name: &str) -> Result<&Vec<TypedDataField>, RpcErr> {
+        self.types
+            .get(type_name)
+            .ok_or_else(|| RpcErr::BadParams(format!("Unknown typed data type {type_name}")))
+    }
+
+    /// Encodes the type as `Name(type1 name1,...)` followed by its referenced struct types sorted by name
+    fn encode_type(&self, type_name: &str) -> Result<String, RpcErr> {
+        let mut dependencies = BTreeSet::new();
+        self.collect_dependencies(type_name, &mut dependencies)?;
+        dependencies.remove(type_name);
+
+        let mut encoded = String::new();
+        for name in std::iter::once(type_name).chain(dependencies.iter().map(String::as_str)) {
+            let fields = self
+                .fields(name)?
+                .iter()
+                .map(|field| format!("{} {}", field.r#type, field.name))
+                .collect::<Vec<_>>()
+                .join(",");
+            encoded.push_str(&format!("{name}({fields})"));
+        }
+        Ok(encoded)
+    }
+
+    fn collect_dependencies(
+        &self,
+        type_name: &str,
+        dependencies: &mut BTreeSet<String>,
+    ) -> Result<(), RpcErr> {
+        let base_type = type_name.split('[').next().unwrap_or(type_name);
+        if !self.types.contains_key(base_type) || !dependencies.insert(base_type.to_string()) {
+            return Ok(());
+        }
+        for field in self.fields(base_type)? {
+            self.collect_dependencies(&field.r#type, dependencies)?;
+        }
+        Ok(())
+    }
+
+    fn hash_struct(&self, type_name: &str, data: &Value) -> Result<H256, RpcErr> {
+        let Value::Object(values) = data else {
+            return Err(RpcErr::BadParams(format!(
+                "Expected an object for typed data type {type_name}"
+            )));
+        };
+        let mut encoded = keccak(self.encode_type(type_name)?).as_bytes().to_vec();
+        for field in self.fields(type_name)? {
+            let value = values.get(&field.name).ok_or_else(|| {
+                RpcErr::BadParams(format!("Missing typed data field {}", field.name))
+            })?;
+            encoded.extend_from_slice(self.encode_value(&field.r#type, value)?.as_bytes());
+        }
+        Ok(keccak(encoded))
+    }
+
+    fn encode_value(&self, type_name: &str, value: &Value) -> Result<H256, RpcErr> {
+        let bad_value = || {
+            RpcErr::BadParams(format!(
+                "Invalid value {value} for typed data type {type_name}"
+            ))
+        };
+
+        if let Some(item_type) = type_name
+            .strip_suffix(']')
+            .and_then(|type_name| type_name.rsplit_once('['))
+            .map(|(item_type, _length)| item_type)
+        {
+            let Value::Array(items) = value else {
+                return Err(bad_value());
+            };
+            let mut encoded = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                encoded.extend_from_slice(self.encode_value(item_type, item)?.as_bytes());
+            }
+            return Ok(keccak(encoded));
+        }
+        if self.types.contains_key(type_name) {
+            return self.hash_struct(type_name, value);
+        }
+
+        match type_name {
+            "string" => Ok(keccak(value.as_str().ok_or_else(bad_value)?)),
+            "bytes" => Ok(keccak(decode_hex(value).ok_or_else(bad_value)?)),
+            "bool" => {
+                let flag = match value {
+                    Value::Bool(flag) => *flag,
+                    Value::String(flag) => flag.parse().map_err(|_| bad_value())?,
+                    _ => return Err(bad_value()),
+                };
+                Ok(H256::from_low_u64_be(flag as u64))
+            }
+            "address" => {
+                let address: Address =
+                    serde_json::from_value(value.clone()).map_err(|_| bad_value())?;
+                Ok(H256::from(address))
+            }
+            _ if type_name.starts_with("bytes") => {
+                let size: usize = type_name["bytes".len()..]
+                    .parse()
+                    .map_err(|_| bad_value())?;
+                let bytes = decode_hex(value).ok_or_else(bad_value)?;
+                if size == 0 || size > 32 || bytes.len() > size {
+                    return Err(bad_value());
+                }
+                let mut word = H256::zero();
+                word.0[..bytes.len()].copy_from_slice(&bytes);
+                Ok(word)
+            }
+            _ if type_name.starts_with("uint") => {
+                let (negative, number) = parse_integer(value).ok_or_else(bad_value)?;
+                if negative {
+                    return Err(bad_value());
+                }
+                Ok(H256(number.to_big_endian()))
+            }
+            _ if type_name.starts_with("int") => {
+                let (negative, number) = parse_integer(value).ok_or_else(bad_value)?;
+                // Negative numbers are encoded in two's complement
+                let number = if negative {
+                    (!number).overflowing_add(U256::one()).0
+                } else {
+                    number
+                };
+                Ok(H256(number.to_big_endian()))
+            }
+            _ => Err(RpcErr::BadParams(format!(
+                "Unknown typed data type {type_name}"
+            ))),
+        }
+    }
+}
+
+fn decode_hex(value: &Value) -> Option<Vec<u8>> {
+    hex::decode(value.as_str()?.strip_prefix("0x")?).ok()
+}
+
+/// Parses an integer given either as a JSON number, a decimal string or a hex string.
+/// Returns whether it's negative along with its absolute value.
+fn parse_integer(value: &Value) -> Option<(bool, U256)> {
+    match value {
+        Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+            (Some(number), _) => Some((false, U256::from(number))),
+            (None, Some(number)) => Some((true, U256::from(number.unsigned_abs()))),
+            _ => None,
+        },
+        Value::String(number) => {
+            let (negative, number) = match number.strip_prefix('-') {
+                Some(number) => (true, number),
+                None => (false, number.as_str()),
+            };
+            let number = match number.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16).ok()?,
+                None => U256::from_dec_str(number).ok()?,
+            };
+            Some((negative, number))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // Example from EIP-712
+    const MAIL_TYPED_DATA: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"}
+            ],
+            "Person": [
+                {"name": "name", "type": "string"},
+                {"name": "wallet", "type": "address"}
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person"},
+                {"name": "contents", "type": "string"}
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {"name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+            "to": {"name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    #[test]
+    fn encode_mail_type() {
+        let typed_data: TypedData = serde_json::from_str(MAIL_TYPED_DATA).unwrap();
+        assert_eq!(
+            typed_data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+    }
+
+    #[test]
+    fn hash_mail_typed_data() {
+        let typed_data: TypedData = serde_json::from_str(MAIL_TYPED_DATA).unwrap();
+        assert_eq!(
+            typed_data
+                .hash_struct(DOMAIN_TYPE, &typed_data.domain)
+                .unwrap(),
+            H256::from_str("0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+                .unwrap()
+        );
+        assert_eq!(
+            typed_data.hash().unwrap(),
+            H256::from_str("0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+                .unwrap()
+        );
+    }
+}
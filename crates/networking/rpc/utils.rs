@@ -39,6 +39,10 @@ pub enum RpcErr {
     InvalidPayloadAttributes(String),
     #[error("Unknown payload: {0}")]
     UnknownPayload(String),
+    #[error("Batch too large: {0} requests")]
+    BatchTooLarge(usize),
+    #[error("Response too large")]
+    ResponseTooLarge,
 }
 
 impl From<RpcErr> for RpcErrorMetadata {
@@ -140,6 +144,17 @@ impl From<RpcErr> for RpcErrorMetadata {
                 data: None,
                 message: format!("Unknown payload: {context}"),
             },
+            RpcErr::BatchTooLarge(limit) => RpcErrorMetadata {
+                code: -32600,
+                data: None,
+                message: format!("batch too large, the limit is {limit} requests"),
+            },
+            RpcErr::ResponseTooLarge => RpcErrorMetadata {
+                // Same code geth uses for this error
+                code: -32003,
+                data: None,
+                message: "response too large".to_string(),
+            },
         }
     }
 }
@@ -167,6 +182,7 @@ impl From<secp256k1::Error> for RpcErr {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RpcNamespace {
     Engine,
     Eth,
@@ -175,9 +191,10 @@ pub enum RpcNamespace {
     Web3,
     Net,
     Mempool,
+    Ethrex,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum RpcRequestId {
     Number(u64),
@@ -221,6 +238,7 @@ pub fn resolve_namespace(maybe_namespace: &str, method: String) -> Result<RpcNam
         "net" => Ok(RpcNamespace::Net),
         // TODO: The namespace is set to match geth's namespace for compatibility, consider changing it in the future
         "txpool" => Ok(RpcNamespace::Mempool),
+        "ethrex" => Ok(RpcNamespace::Ethrex),
         _ => Err(RpcErr::MethodNotFound(method)),
     }
 }
@@ -385,7 +403,9 @@ pub mod test_utils {
         let local_node_record = example_local_node_record();
         start_api(
             http_addr,
-            authrpc_addr,
+            Some(authrpc_addr),
+            None,
+            Default::default(),
             storage,
             blockchain,
             jwt_secret,
@@ -397,6 +417,9 @@ pub mod test_utils {
             None,
             None,
             String::new(),
+            Vec::new(),
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -421,6 +444,10 @@ pub mod test_utils {
             gas_tip_estimator: Arc::new(TokioMutex::new(GasTipEstimator::new())),
             log_filter_handler: None,
             gas_ceil: DEFAULT_BUILDER_GAS_CEIL,
+            signers: Default::default(),
+            nonce_locks: Default::default(),
+            checkpoint_dir: None,
+            bad_block_traces_dir: None,
         }
     }
 }
@@ -1,11 +1,11 @@
 use bytes::Bytes;
-use ethereum_types::H256;
+use ethereum_types::{Address, H256};
 use ethrex_common::types::{
     Block, BlockBody, BlockHash, BlockHeader, BlockNumber, ChainConfig, Index, Receipt, Transaction,
 };
-use std::{fmt::Debug, panic::RefUnwindSafe};
+use std::{fmt::Debug, panic::RefUnwindSafe, path::Path};
 
-use crate::UpdateBatch;
+use crate::{AddressTransaction, AddressTransactionsUpdate, TrieScheme, UpdateBatch};
 use crate::{error::StoreError, store::STATE_TRIE_SEGMENTS};
 use ethrex_trie::{Nibbles, NodeHash, Trie};
 
@@ -98,6 +98,27 @@ pub trait StoreEngine: Debug + Send + Sync + RefUnwindSafe {
         transaction_hash: H256,
     ) -> Result<Option<(BlockNumber, BlockHash, Index)>, StoreError>;
 
+    /// Obtain the senders of the transactions of a block, if they were kept when storing it
+    async fn get_block_senders(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Option<Vec<Address>>, StoreError>;
+
+    /// Remove entries from the address-indexed transaction history
+    async fn remove_address_transactions(
+        &self,
+        entries: Vec<AddressTransaction>,
+    ) -> Result<(), StoreError>;
+
+    /// Obtain up to `limit` canonical entries of the address-indexed transaction history for the given address,
+    /// newest first, starting right before the position (block number and index) given by `cursor`
+    async fn get_transactions_by_address(
+        &self,
+        address: Address,
+        cursor: Option<(BlockNumber, Index)>,
+        limit: usize,
+    ) -> Result<Vec<AddressTransaction>, StoreError>;
+
     /// Add receipt
     async fn add_receipt(
         &self,
@@ -245,6 +266,39 @@ pub trait StoreEngine: Debug + Send + Sync + RefUnwindSafe {
         self.open_storage_trie(hashed_address, storage_root)
     }
 
+    /// Returns how trie nodes are keyed in the database
+    fn trie_scheme(&self) -> TrieScheme {
+        TrieScheme::Hash
+    }
+
+    /// Reads the writes made by the primary instance since the last call.
+    /// Only does something for secondary instances of engines that support them
+    async fn catch_up_with_primary(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    /// Creates a consistent copy of the whole database at `path`, which must not exist yet.
+    /// Returns the header of the head block of the copy
+    async fn create_checkpoint(&self, _path: &Path) -> Result<BlockHeader, StoreError> {
+        Err(StoreError::Custom(
+            "Checkpoints are not supported by this engine".to_string(),
+        ))
+    }
+
+    /// Writes trie nodes at the given paths of the state trie, or of the storage trie of the
+    /// given account, as part of the state of the given block, which must be made canonical.
+    /// Only supported by engines using the path scheme
+    async fn write_trie_nodes_at_paths(
+        &self,
+        _hashed_address: Option<H256>,
+        _nodes: Vec<(Nibbles, NodeHash, Vec<u8>)>,
+        _block: (BlockNumber, BlockHash),
+    ) -> Result<(), StoreError> {
+        Err(StoreError::Custom(
+            "Writing trie nodes by path is not supported by this engine".to_string(),
+        ))
+    }
+
     async fn forkchoice_update(
         &self,
         new_canonical_blocks: Option<Vec<(BlockNumber, BlockHash)>>,
@@ -252,6 +306,7 @@ pub trait StoreEngine: Debug + Send + Sync + RefUnwindSafe {
         head_hash: BlockHash,
         safe: Option<BlockNumber>,
         finalized: Option<BlockNumber>,
+        address_transactions: AddressTransactionsUpdate,
     ) -> Result<(), StoreError>;
 
     async fn get_receipts_for_block(
@@ -318,6 +373,19 @@ pub trait StoreEngine: Debug + Send + Sync + RefUnwindSafe {
         latest_valid: BlockHash,
     ) -> Result<(), StoreError>;
 
+    /// Stores a block that failed validation, for debugging purposes, along with the position it
+    /// was added in.
+    async fn add_bad_block(&self, position: u64, block: Block) -> Result<(), StoreError>;
+
+    /// Returns every bad block stored along with the position it was added in, in no particular order.
+    async fn get_bad_blocks(&self) -> Result<Vec<(u64, Block)>, StoreError>;
+
+    /// Returns the bad block with the given hash, if it's stored.
+    async fn get_bad_block(&self, block_hash: BlockHash) -> Result<Option<Block>, StoreError>;
+
+    /// Removes a bad block previously stored.
+    async fn remove_bad_block(&self, block_hash: BlockHash) -> Result<(), StoreError>;
+
     /// Returns the latest valid ancestor hash for a given invalid block hash.
     /// Used to provide `latest_valid_hash` in the Engine API when processing invalid payloads.
     async fn get_latest_valid_ancestor(
@@ -346,4 +414,10 @@ pub trait StoreEngine: Debug + Send + Sync + RefUnwindSafe {
         &self,
         account_codes: Vec<(H256, Bytes)>,
     ) -> Result<(), StoreError>;
+
+    /// Stores the preimages of hashed addresses and storage keys, keyed by their hash
+    async fn add_preimages(&self, preimages: Vec<(H256, Bytes)>) -> Result<(), StoreError>;
+
+    /// Obtains the preimage of a hashed address or storage key, if it was stored
+    fn get_preimage(&self, hash: H256) -> Result<Option<Bytes>, StoreError>;
 }
@@ -0,0 +1,56 @@
+use std::{fs, path::Path};
+
+use ethereum_types::H256;
+use ethrex_common::types::BlockNumber;
+use serde::{Deserialize, Serialize};
+
+use crate::error::StoreError;
+
+/// Name of the file describing a checkpoint, written next to the database files
+pub const CHECKPOINT_MANIFEST_FILE: &str = "checkpoint.json";
+/// Name the manifest is moved to once the restored checkpoint has been validated
+pub const RESTORED_CHECKPOINT_MANIFEST_FILE: &str = "checkpoint.restored.json";
+
+/// Describes the state of the chain in a database checkpoint, so that it can be validated when restoring it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointManifest {
+    pub chain_id: u64,
+    pub head_block_number: BlockNumber,
+    pub head_block_hash: H256,
+    pub state_root: H256,
+}
+
+impl CheckpointManifest {
+    /// Writes the manifest into the checkpoint directory
+    pub fn write(&self, checkpoint_dir: &Path) -> Result<(), StoreError> {
+        let manifest = serde_json::to_string_pretty(self).map_err(|e| {
+            StoreError::Custom(format!("Failed to encode checkpoint manifest: {e}"))
+        })?;
+        fs::write(checkpoint_dir.join(CHECKPOINT_MANIFEST_FILE), manifest)
+            .map_err(|e| StoreError::Custom(format!("Failed to write checkpoint manifest: {e}")))
+    }
+
+    /// Reads the manifest of a checkpoint directory, `None` if the directory isn't a restored checkpoint
+    pub fn read(checkpoint_dir: &Path) -> Result<Option<Self>, StoreError> {
+        let manifest_path = checkpoint_dir.join(CHECKPOINT_MANIFEST_FILE);
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+        let manifest = fs::read_to_string(manifest_path)
+            .map_err(|e| StoreError::Custom(format!("Failed to read checkpoint manifest: {e}")))?;
+        serde_json::from_str(&manifest)
+            .map(Some)
+            .map_err(|e| StoreError::Custom(format!("Failed to decode checkpoint manifest: {e}")))
+    }
+
+    /// Moves the manifest aside so that the restored checkpoint isn't validated again, as the chain
+    /// can move past its head afterwards
+    pub fn mark_restored(checkpoint_dir: &Path) -> Result<(), StoreError> {
+        fs::rename(
+            checkpoint_dir.join(CHECKPOINT_MANIFEST_FILE),
+            checkpoint_dir.join(RESTORED_CHECKPOINT_MANIFEST_FILE),
+        )
+        .map_err(|e| StoreError::Custom(format!("Failed to move checkpoint manifest: {e}")))
+    }
+}
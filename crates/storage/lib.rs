@@ -1,4 +1,5 @@
 mod api;
+mod checkpoint;
 
 #[cfg(feature = "rocksdb")]
 mod rlp;
@@ -9,7 +10,11 @@ mod trie_db;
 mod utils;
 
 pub mod error;
+pub use checkpoint::{
+    CHECKPOINT_MANIFEST_FILE, CheckpointManifest, RESTORED_CHECKPOINT_MANIFEST_FILE,
+};
 pub use store::{
-    AccountUpdatesList, EngineType, MAX_SNAPSHOT_READS, STATE_TRIE_SEGMENTS, Store, UpdateBatch,
-    hash_address, hash_key,
+    AccountChange, AccountUpdatesList, AddressTransaction, AddressTransactionsUpdate, EngineType,
+    MAX_BAD_BLOCKS, MAX_SNAPSHOT_READS, STATE_TRIE_SEGMENTS, StorageChange, Store, StoreOptions,
+    TrieScheme, UpdateBatch, hash_address, hash_key,
 };
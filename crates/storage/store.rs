@@ -1,4 +1,5 @@
 use crate::api::StoreEngine;
+use crate::checkpoint::CheckpointManifest;
 use crate::error::StoreError;
 use crate::store_db::in_memory::Store as InMemoryStore;
 #[cfg(feature = "rocksdb")]
@@ -11,12 +12,14 @@ use ethrex_common::{
     types::{
         AccountInfo, AccountState, AccountUpdate, Block, BlockBody, BlockHash, BlockHeader,
         BlockNumber, ChainConfig, ForkId, Genesis, GenesisAccount, Index, Receipt, Transaction,
-        code_hash,
+        TxKind, code_hash,
     },
 };
 use ethrex_rlp::decode::RLPDecode;
 use ethrex_rlp::encode::RLPEncode;
 use ethrex_trie::{Nibbles, NodeHash, Trie, TrieLogger, TrieNode, TrieWitness};
+#[cfg(feature = "parallel")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use sha3::{Digest as _, Keccak256};
 use std::sync::Arc;
 use std::{
@@ -30,15 +33,70 @@ pub const STATE_TRIE_SEGMENTS: usize = 2;
 /// Maximum amount of reads from the snapshot in a single transaction to avoid performance hits due to long-living reads
 /// This will always be the amount yielded by snapshot reads unless there are less elements left
 pub const MAX_SNAPSHOT_READS: usize = 100;
+/// Maximum amount of blocks that failed validation kept for debugging purposes, the oldest ones
+/// added are dropped first
+pub const MAX_BAD_BLOCKS: usize = 10;
 
 #[derive(Debug, Clone)]
 pub struct Store {
     pub engine: Arc<dyn StoreEngine>,
     pub chain_config: Arc<RwLock<ChainConfig>>,
     pub latest_block_header: Arc<RwLock<BlockHeader>>,
+    /// Whether transactions are indexed by their sender and recipient addresses
+    index_transactions_by_address: bool,
+    /// Whether the preimages of the hashed addresses and storage keys of stored blocks are kept
+    store_preimages: bool,
+}
+
+/// Optional features of the store which are off by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StoreOptions {
+    /// Keep an index of every canonical transaction by its sender and recipient addresses
+    pub index_transactions_by_address: bool,
+    /// Keep the preimages of the hashed addresses and storage keys updated by stored blocks, so
+    /// tries can be diffed by address and key instead of by their hashes
+    pub store_preimages: bool,
+    /// How trie nodes are keyed in the database. Only taken into account when creating a new
+    /// database, existing ones keep the scheme they were created with
+    pub trie_scheme: Option<TrieScheme>,
+}
+
+/// How trie nodes are stored in the database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrieScheme {
+    /// Nodes are keyed by their hash, every version of every node is kept
+    #[default]
+    Hash,
+    /// Nodes are keyed by their path in the trie, only the latest version of each path is kept
+    /// along with the nodes replaced in the last few blocks
+    Path,
+}
+
+/// Entry of the address-indexed transaction history.
+/// Each transaction is indexed once for its sender and once for its recipient (if it's a call to a different address)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressTransaction {
+    pub address: Address,
+    pub block_number: BlockNumber,
+    pub block_hash: BlockHash,
+    pub index: Index,
+    pub transaction_hash: H256,
+}
+
+/// Changes to the address-indexed transaction history caused by a forkchoice update
+#[derive(Debug, Clone, Default)]
+pub struct AddressTransactionsUpdate {
+    /// Entries of the blocks that stop being canonical
+    pub removed: Vec<AddressTransaction>,
+    /// Entries of the blocks that become canonical
+    pub added: Vec<AddressTransaction>,
 }
 
 pub type StorageTrieNodes = Vec<(H256, Vec<(NodeHash, Vec<u8>)>)>;
+/// Hashed address of an account that differs between two states, along with its state in each of them
+pub type AccountChange = (H256, Option<AccountState>, Option<AccountState>);
+/// Hashed key of a storage slot that differs between two states, along with its value in each of them
+pub type StorageChange = (H256, Option<U256>, Option<U256>);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EngineType {
@@ -50,50 +108,130 @@ pub enum EngineType {
 pub struct UpdateBatch {
     /// Nodes to be added to the state trie
     pub account_updates: Vec<TrieNode>,
-    /// Storage tries updated and their new nodes
+    /// Storage tries updated and their new nodes, none if the trie was emptied or its account removed
     pub storage_updates: Vec<(H256, Vec<TrieNode>)>,
     /// Blocks to be added
     pub blocks: Vec<Block>,
     /// Receipts added per block
     pub receipts: Vec<(H256, Vec<Receipt>)>,
+    /// Senders of the transactions of each block as recovered during execution, only written if
+    /// the store indexes transactions by address
+    pub senders: Vec<(H256, Vec<Address>)>,
     /// Code updates
     pub code_updates: Vec<(H256, Bytes)>,
+    /// Preimages of the hashed addresses and storage keys updated by the blocks, only written if
+    /// the store keeps preimages
+    pub preimages: Vec<(H256, Bytes)>,
 }
 
 type StorageUpdates = Vec<(H256, Vec<(NodeHash, Vec<u8>)>)>;
 
+/// Changes to an account gathered from the updates of a batch before writing them to the tries
+struct PendingAccount {
+    hashed_address: H256,
+    /// New state of the account, `None` if the account was removed or didn't exist
+    state: Option<AccountState>,
+    /// Storage writes by hashed key, in the order they were made
+    storage: Vec<(Vec<u8>, U256)>,
+    /// Whether the account was removed, dropping the storage it had before
+    storage_cleared: bool,
+}
+
 pub struct AccountUpdatesList {
     pub state_trie_hash: H256,
     pub state_updates: Vec<(NodeHash, Vec<u8>)>,
     pub storage_updates: StorageUpdates,
     pub code_updates: Vec<(H256, Bytes)>,
+    /// Preimages of the hashed addresses and storage keys updated, empty unless the store keeps preimages
+    pub preimages: Vec<(H256, Bytes)>,
 }
 
 impl Store {
-    pub async fn store_block_updates(&self, update_batch: UpdateBatch) -> Result<(), StoreError> {
+    pub async fn store_block_updates(
+        &self,
+        mut update_batch: UpdateBatch,
+    ) -> Result<(), StoreError> {
+        if !self.store_preimages || update_batch.blocks.is_empty() {
+            update_batch.preimages.clear();
+        }
+        if !self.index_transactions_by_address {
+            update_batch.senders.clear();
+        }
         self.engine.apply_updates(update_batch).await
     }
 
     pub fn new(path: impl AsRef<Path>, engine_type: EngineType) -> Result<Self, StoreError> {
+        Self::new_with_options(path, engine_type, StoreOptions::default())
+    }
+
+    pub fn new_with_options(
+        path: impl AsRef<Path>,
+        engine_type: EngineType,
+        options: StoreOptions,
+    ) -> Result<Self, StoreError> {
         let path = path.as_ref();
-        info!(engine = ?engine_type, ?path, "Opening storage engine");
+        info!(engine = ?engine_type, ?path, ?options, "Opening storage engine");
         let store = match engine_type {
             #[cfg(feature = "rocksdb")]
             EngineType::RocksDB => Self {
-                engine: Arc::new(RocksDBStore::new(path)?),
+                engine: Arc::new(RocksDBStore::new(path, options.trie_scheme)?),
                 chain_config: Default::default(),
                 latest_block_header: Arc::new(RwLock::new(BlockHeader::default())),
+                index_transactions_by_address: options.index_transactions_by_address,
+                store_preimages: options.store_preimages,
             },
             EngineType::InMemory => Self {
                 engine: Arc::new(InMemoryStore::new()),
                 chain_config: Default::default(),
                 latest_block_header: Arc::new(RwLock::new(BlockHeader::default())),
+                index_transactions_by_address: options.index_transactions_by_address,
+                store_preimages: options.store_preimages,
             },
         };
 
         Ok(store)
     }
 
+    /// Opens the RocksDB database of another running node as a read-only secondary instance.
+    /// `secondary_path` holds the secondary's own logs and must be unique to this instance.
+    /// Writes made by the primary only become visible after calling [`Self::catch_up_with_primary`]
+    #[cfg(feature = "rocksdb")]
+    pub fn new_secondary(
+        path: impl AsRef<Path>,
+        secondary_path: impl AsRef<Path>,
+    ) -> Result<Self, StoreError> {
+        let path = path.as_ref();
+        let secondary_path = secondary_path.as_ref();
+        info!(
+            ?path,
+            ?secondary_path,
+            "Opening storage engine as secondary"
+        );
+        Ok(Self {
+            engine: Arc::new(RocksDBStore::new_secondary(path, secondary_path)?),
+            chain_config: Default::default(),
+            latest_block_header: Arc::new(RwLock::new(BlockHeader::default())),
+            index_transactions_by_address: false,
+            store_preimages: false,
+        })
+    }
+
+    /// Opens an existing RocksDB database without allowing writes to it, for tools inspecting the
+    /// database of a stopped node. The chain config isn't read back from the database, it has to
+    /// be loaded with [`Self::load_secondary_state`]
+    #[cfg(feature = "rocksdb")]
+    pub fn new_read_only(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let path = path.as_ref();
+        info!(?path, "Opening storage engine as read-only");
+        Ok(Self {
+            engine: Arc::new(RocksDBStore::new_read_only(path)?),
+            chain_config: Default::default(),
+            latest_block_header: Arc::new(RwLock::new(BlockHeader::default())),
+            index_transactions_by_address: false,
+            store_preimages: false,
+        })
+    }
+
     pub async fn new_from_genesis(
         store_path: &Path,
         engine_type: EngineType,
@@ -237,6 +375,13 @@ impl Store {
     }
 
     pub async fn remove_block(&self, block_number: BlockNumber) -> Result<(), StoreError> {
+        // Only canonical blocks are indexed by address, so drop the entries of the block being removed
+        if self.index_transactions_by_address
+            && let Some(block_hash) = self.engine.get_canonical_block_hash(block_number).await?
+        {
+            let entries = self.address_transactions_for_block(block_hash).await?;
+            self.engine.remove_address_transactions(entries).await?;
+        }
         self.engine.remove_block(block_number).await
     }
 
@@ -312,6 +457,30 @@ impl Store {
         self.engine.get_transaction_location(transaction_hash).await
     }
 
+    /// Returns whether the address-indexed transaction history is being kept
+    pub fn indexes_transactions_by_address(&self) -> bool {
+        self.index_transactions_by_address
+    }
+
+    /// Obtain up to `limit` canonical transactions sent or received by the given address, newest first.
+    /// If a `cursor` (block number and index) is given, only transactions strictly older than it are returned.
+    /// Fails if the store was not opened with the address index enabled.
+    pub async fn get_transactions_by_address(
+        &self,
+        address: Address,
+        cursor: Option<(BlockNumber, Index)>,
+        limit: usize,
+    ) -> Result<Vec<AddressTransaction>, StoreError> {
+        if !self.index_transactions_by_address {
+            return Err(StoreError::Custom(
+                "Transactions are not indexed by address".to_string(),
+            ));
+        }
+        self.engine
+            .get_transactions_by_address(address, cursor, limit)
+            .await
+    }
+
     pub async fn add_account_code(&self, code_hash: H256, code: Bytes) -> Result<(), StoreError> {
         self.engine.add_account_code(code_hash, code).await
     }
@@ -378,24 +547,53 @@ impl Store {
 
     pub async fn apply_account_updates_from_trie_batch(
         &self,
-        mut state_trie: Trie,
+        state_trie: Trie,
         account_updates: impl IntoIterator<Item = &AccountUpdate>,
     ) -> Result<AccountUpdatesList, StoreError> {
-        let mut ret_storage_updates = Vec::new();
         let mut code_updates = Vec::new();
+        // Preimages of the hashed addresses and storage keys, to identify what changed later on
+        let mut preimages: HashMap<H256, Bytes> = HashMap::new();
+        let mut add_preimage = |hash: H256, preimage: &[u8]| {
+            if self.store_preimages {
+                preimages
+                    .entry(hash)
+                    .or_insert_with(|| Bytes::copy_from_slice(preimage));
+            }
+        };
+        // Updates are merged by account, so that the storage tries of different accounts are
+        // independent of each other and can be updated concurrently
+        let mut accounts: Vec<PendingAccount> = Vec::new();
+        let mut account_indexes: HashMap<H256, usize> = HashMap::new();
         for update in account_updates {
-            let hashed_address = hash_address(&update.address);
+            let hashed_address = hash_address_fixed(&update.address);
+            add_preimage(hashed_address, update.address.as_bytes());
+            let index = match account_indexes.get(&hashed_address) {
+                Some(index) => *index,
+                None => {
+                    let state = state_trie
+                        .get(&hashed_address.as_bytes().to_vec())?
+                        .map(|encoded_state| AccountState::decode(&encoded_state))
+                        .transpose()?;
+                    accounts.push(PendingAccount {
+                        hashed_address,
+                        state,
+                        storage: Vec::new(),
+                        storage_cleared: false,
+                    });
+                    account_indexes.insert(hashed_address, accounts.len() - 1);
+                    accounts.len() - 1
+                }
+            };
+            let account = &mut accounts[index];
             if update.removed {
-                // Remove account from trie
-                state_trie.remove(&hashed_address)?;
+                // Storage written before the removal is dropped along with the account
+                account.state = None;
+                account.storage.clear();
+                account.storage_cleared = true;
                 continue;
             }
-            // Add or update AccountState in the trie
             // Fetch current state or create a new state to be inserted
-            let mut account_state = match state_trie.get(&hashed_address)? {
-                Some(encoded_state) => AccountState::decode(&encoded_state)?,
-                None => AccountState::default(),
-            };
+            let account_state = account.state.get_or_insert_with(AccountState::default);
             if let Some(info) = &update.info {
                 account_state.nonce = info.nonce;
                 account_state.balance = info.balance;
@@ -405,37 +603,107 @@ impl Store {
                     code_updates.push((info.code_hash, code.clone()));
                 }
             }
-            // Store the added storage in the account's storage trie and compute its new root
-            if !update.added_storage.is_empty() {
-                let mut storage_trie = self.engine.open_storage_trie(
-                    H256::from_slice(&hashed_address),
-                    account_state.storage_root,
-                )?;
-                for (storage_key, storage_value) in &update.added_storage {
-                    let hashed_key = hash_key(storage_key);
-                    if storage_value.is_zero() {
-                        storage_trie.remove(&hashed_key)?;
-                    } else {
-                        storage_trie.insert(hashed_key, storage_value.encode_to_vec())?;
-                    }
-                }
-                let (storage_hash, storage_updates) =
-                    storage_trie.collect_changes_since_last_hash();
-                account_state.storage_root = storage_hash;
-                ret_storage_updates.push((H256::from_slice(&hashed_address), storage_updates));
+            for (storage_key, storage_value) in &update.added_storage {
+                let hashed_key = hash_key(storage_key);
+                add_preimage(H256::from_slice(&hashed_key), storage_key.as_bytes());
+                account.storage.push((hashed_key, *storage_value));
             }
-            state_trie.insert(hashed_address, account_state.encode_to_vec())?;
         }
-        let (state_trie_hash, state_updates) = state_trie.collect_changes_since_last_hash();
+        // Hashing the tries takes a while, which must not block the runtime's worker threads
+        let store = self.clone();
+        let (state_trie_hash, state_updates, storage_updates) =
+            tokio::task::spawn_blocking(move || {
+                store.commit_pending_accounts(state_trie, accounts, &account_indexes)
+            })
+            .await
+            .map_err(|e| StoreError::Custom(format!("Task panicked: {e}")))??;
 
         Ok(AccountUpdatesList {
             state_trie_hash,
             state_updates,
-            storage_updates: ret_storage_updates,
+            storage_updates,
             code_updates,
+            preimages: preimages.into_iter().collect(),
         })
     }
 
+    /// Writes the pending accounts to the state trie, updating their storage tries first (in
+    /// parallel with the `parallel` feature), and returns the new state root along with the nodes
+    /// of every trie that need to be stored
+    fn commit_pending_accounts(
+        &self,
+        mut state_trie: Trie,
+        mut accounts: Vec<PendingAccount>,
+        account_indexes: &HashMap<H256, usize>,
+    ) -> Result<(H256, Vec<TrieNode>, StorageUpdates), StoreError> {
+        // Store the added storage in each account's storage trie and compute its new root
+        #[cfg(feature = "parallel")]
+        let accounts_iter = accounts.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let accounts_iter = accounts.iter();
+        let storage_roots = accounts_iter
+            .filter(|account| account.state.is_some() && !account.storage.is_empty())
+            .map(|account| self.apply_storage_updates(account))
+            .collect::<Result<Vec<_>, StoreError>>()?;
+        let mut storage_updates = Vec::with_capacity(storage_roots.len());
+        for (hashed_address, storage_hash, account_storage_updates) in storage_roots {
+            if let Some(state) = &mut accounts[account_indexes[&hashed_address]].state {
+                state.storage_root = storage_hash;
+            }
+            // A commit without nodes only tells the storage trie was emptied if its root is empty,
+            // otherwise nothing changed
+            if !account_storage_updates.is_empty() || storage_hash == *EMPTY_TRIE_HASH {
+                storage_updates.push((hashed_address, account_storage_updates));
+            }
+        }
+        // Storage tries of removed accounts that weren't written again are dropped
+        storage_updates.extend(
+            accounts
+                .iter()
+                .filter(|account| {
+                    account.storage_cleared
+                        && (account.state.is_none() || account.storage.is_empty())
+                })
+                .map(|account| (account.hashed_address, Vec::new())),
+        );
+
+        for account in accounts {
+            let hashed_address = account.hashed_address.as_bytes().to_vec();
+            match account.state {
+                Some(state) => state_trie.insert(hashed_address, state.encode_to_vec())?,
+                None => {
+                    state_trie.remove(&hashed_address)?;
+                }
+            }
+        }
+        let (state_trie_hash, state_updates) = state_trie.collect_changes_since_last_hash();
+        Ok((state_trie_hash, state_updates, storage_updates))
+    }
+
+    /// Applies the storage writes of an account to its storage trie, returning its new root and
+    /// the nodes that need to be stored
+    fn apply_storage_updates(
+        &self,
+        account: &PendingAccount,
+    ) -> Result<(H256, H256, Vec<TrieNode>), StoreError> {
+        let storage_root = account
+            .state
+            .as_ref()
+            .map_or(*EMPTY_TRIE_HASH, |state| state.storage_root);
+        let mut storage_trie = self
+            .engine
+            .open_storage_trie(account.hashed_address, storage_root)?;
+        for (hashed_key, storage_value) in &account.storage {
+            if storage_value.is_zero() {
+                storage_trie.remove(hashed_key)?;
+            } else {
+                storage_trie.insert(hashed_key.clone(), storage_value.encode_to_vec())?;
+            }
+        }
+        let (storage_hash, storage_updates) = storage_trie.collect_changes_since_last_hash();
+        Ok((account.hashed_address, storage_hash, storage_updates))
+    }
+
     /// Performs the same actions as apply_account_updates_from_trie
     ///  but also returns the used storage tries with witness recorded
     pub async fn apply_account_updates_from_trie_with_witness(
@@ -501,8 +769,13 @@ impl Store {
         genesis_accounts: BTreeMap<Address, GenesisAccount>,
     ) -> Result<H256, StoreError> {
         let mut genesis_state_trie = self.engine.open_state_trie(*EMPTY_TRIE_HASH)?;
+        let mut preimages = Vec::new();
         for (address, account) in genesis_accounts {
             let hashed_address = hash_address(&address);
+            preimages.push((
+                H256::from_slice(&hashed_address),
+                Bytes::copy_from_slice(address.as_bytes()),
+            ));
             // Store account code (as this won't be stored in the trie)
             let code_hash = code_hash(&account.code);
             self.add_account_code(code_hash, account.code).await?;
@@ -512,7 +785,12 @@ impl Store {
                 .open_storage_trie(H256::from_slice(&hashed_address), *EMPTY_TRIE_HASH)?;
             for (storage_key, storage_value) in account.storage {
                 if !storage_value.is_zero() {
-                    let hashed_key = hash_key(&H256(storage_key.to_big_endian()));
+                    let storage_key = H256(storage_key.to_big_endian());
+                    let hashed_key = hash_key(&storage_key);
+                    preimages.push((
+                        H256::from_slice(&hashed_key),
+                        Bytes::copy_from_slice(storage_key.as_bytes()),
+                    ));
                     storage_trie.insert(hashed_key, storage_value.encode_to_vec())?;
                 }
             }
@@ -526,6 +804,9 @@ impl Store {
             };
             genesis_state_trie.insert(hashed_address, account_state.encode_to_vec())?;
         }
+        if self.store_preimages {
+            self.engine.add_preimages(preimages).await?;
+        }
         genesis_state_trie.hash().map_err(StoreError::Trie)
     }
 
@@ -624,6 +905,10 @@ impl Store {
 
     pub async fn load_initial_state(&self) -> Result<(), StoreError> {
         info!("Loading initial state from DB");
+        self.reload_latest_block_header().await
+    }
+
+    async fn reload_latest_block_header(&self) -> Result<(), StoreError> {
         let Some(number) = self.engine.get_latest_block_number().await? else {
             return Err(StoreError::MissingLatestBlockNumber);
         };
@@ -638,6 +923,99 @@ impl Store {
         Ok(())
     }
 
+    /// Loads the initial state of a store that can't be written to, such as a secondary instance,
+    /// checking that its genesis block matches the given one
+    pub async fn load_secondary_state(&self, genesis: Genesis) -> Result<(), StoreError> {
+        let genesis_block = genesis.get_block();
+        match self.engine.get_block_header(genesis_block.header.number)? {
+            Some(header) if header.hash() == genesis_block.hash() => {}
+            Some(_) => return Err(StoreError::IncompatibleChainConfig),
+            None => {
+                return Err(StoreError::Custom(
+                    "Genesis block not found in the database".to_string(),
+                ));
+            }
+        }
+        *self
+            .chain_config
+            .write()
+            .map_err(|_| StoreError::LockError)? = genesis.config;
+        self.load_initial_state().await
+    }
+
+    /// Makes the writes of the primary instance visible to a secondary one, updating the latest block
+    pub async fn catch_up_with_primary(&self) -> Result<(), StoreError> {
+        self.engine.catch_up_with_primary().await?;
+        self.reload_latest_block_header().await
+    }
+
+    /// Creates a consistent copy of the database at `path`, which must not exist yet, without stopping the node.
+    /// A manifest describing the head of the copy is written alongside it, and checked by
+    /// [`Self::validate_checkpoint_manifest`] when the copy is restored
+    pub async fn create_checkpoint(&self, path: &Path) -> Result<CheckpointManifest, StoreError> {
+        info!(?path, "Creating database checkpoint");
+        let head = self.engine.create_checkpoint(path).await?;
+        let manifest = CheckpointManifest {
+            chain_id: self.get_chain_config()?.chain_id,
+            head_block_number: head.number,
+            head_block_hash: head.hash(),
+            state_root: head.state_root,
+        };
+        manifest.write(path)?;
+        info!(
+            number = manifest.head_block_number,
+            hash = %manifest.head_block_hash,
+            "Created database checkpoint"
+        );
+        Ok(manifest)
+    }
+
+    /// Checks that a database restored from a checkpoint matches the manifest written alongside it,
+    /// which is then moved aside so that it's only checked on the first start.
+    /// Databases that weren't created from a checkpoint, or were already validated, have no manifest and are always valid
+    pub fn validate_checkpoint_manifest(&self, datadir: &Path) -> Result<(), StoreError> {
+        let Some(manifest) = CheckpointManifest::read(datadir)? else {
+            return Ok(());
+        };
+        let chain_id = self.get_chain_config()?.chain_id;
+        if manifest.chain_id != chain_id {
+            return Err(StoreError::Custom(format!(
+                "Checkpoint belongs to chain {} but the node is configured for chain {chain_id}",
+                manifest.chain_id
+            )));
+        }
+        let head_matches = self
+            .get_block_header(manifest.head_block_number)?
+            .is_some_and(|header| {
+                header.hash() == manifest.head_block_hash
+                    && header.state_root == manifest.state_root
+            });
+        if !head_matches {
+            return Err(StoreError::Custom(format!(
+                "Checkpoint head block {} ({:#x}) is not canonical in the database",
+                manifest.head_block_number, manifest.head_block_hash
+            )));
+        }
+        if manifest.state_root != *EMPTY_TRIE_HASH
+            && self
+                .open_state_trie(manifest.state_root)?
+                .root_node()?
+                .is_none()
+        {
+            return Err(StoreError::Custom(format!(
+                "State of checkpoint head block {} is missing from the database",
+                manifest.head_block_number
+            )));
+        }
+        CheckpointManifest::mark_restored(datadir)?;
+        info!(
+            number = manifest.head_block_number,
+            hash = %manifest.head_block_hash,
+            "Validated database checkpoint manifest"
+        );
+        Ok(())
+    }
+
     pub async fn get_transaction_by_hash(
         &self,
         transaction_hash: H256,
@@ -777,7 +1155,8 @@ impl Store {
 
     /// Updates the canonical chain.
     /// Inserts new canonical blocks, removes blocks beyond the new head,
-    /// and updates the head, safe, and finalized block pointers.
+    /// and updates the head, safe, and finalized block pointers, along with the address-indexed
+    /// transaction history if it's kept.
     /// All operations are performed in a single database transaction.
     pub async fn forkchoice_update(
         &self,
@@ -787,6 +1166,16 @@ impl Store {
         safe: Option<BlockNumber>,
         finalized: Option<BlockNumber>,
     ) -> Result<(), StoreError> {
+        let address_transactions = if self.index_transactions_by_address {
+            self.address_transactions_canonical_changes(
+                new_canonical_blocks.as_deref().unwrap_or_default(),
+                head_number,
+                head_hash,
+            )
+            .await?
+        } else {
+            AddressTransactionsUpdate::default()
+        };
         // Updates first the latest_block_header
         // to avoid nonce inconsistencies #3927.
         *self
@@ -803,10 +1192,68 @@ impl Store {
                 head_hash,
                 safe,
                 finalized,
+                address_transactions,
             )
-            .await?;
+            .await
+    }
 
-        Ok(())
+    /// Computes the changes to the address-indexed transaction history caused by a forkchoice update.
+    /// Returns the entries of the blocks that stop being canonical, which are rolled back,
+    /// and the entries of the blocks that replace them, which are (re)inserted.
+    async fn address_transactions_canonical_changes(
+        &self,
+        new_canonical_blocks: &[(BlockNumber, BlockHash)],
+        head_number: BlockNumber,
+        head_hash: BlockHash,
+    ) -> Result<AddressTransactionsUpdate, StoreError> {
+        let mut orphaned = Vec::new();
+        let mut newly_canonical = Vec::new();
+        for &(number, hash) in new_canonical_blocks
+            .iter()
+            .chain(std::iter::once(&(head_number, head_hash)))
+        {
+            let previous = self.engine.get_canonical_block_hash(number).await?;
+            if previous == Some(hash) {
+                continue;
+            }
+            orphaned.extend(previous);
+            newly_canonical.push(hash);
+        }
+        let latest = self.engine.get_latest_block_number().await?.unwrap_or(0);
+        for number in (head_number + 1)..=latest {
+            orphaned.extend(self.engine.get_canonical_block_hash(number).await?);
+        }
+
+        let mut update = AddressTransactionsUpdate::default();
+        for block_hash in orphaned {
+            update
+                .removed
+                .extend(self.address_transactions_for_block(block_hash).await?);
+        }
+        for block_hash in newly_canonical {
+            update
+                .added
+                .extend(self.address_transactions_for_block(block_hash).await?);
+        }
+        Ok(update)
+    }
+
+    /// Builds the address-indexed transaction history entries for the given block, if it's stored.
+    /// The senders recovered when executing the block are used if they were kept, otherwise
+    /// they are recovered again.
+    async fn address_transactions_for_block(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Vec<AddressTransaction>, StoreError> {
+        let Some(block) = self.get_block_by_hash(block_hash).await? else {
+            return Ok(Vec::new());
+        };
+        let senders = self
+            .engine
+            .get_block_senders(block_hash)
+            .await?
+            .filter(|senders| senders.len() == block.body.transactions.len());
+        Ok(address_transactions_for_block(&block, senders))
     }
 
     /// Obtain the storage trie for the given block
@@ -960,6 +1407,172 @@ impl Store {
         self.iter_storage_from(state_root, hashed_address, H256::zero())
     }
 
+    /// Returns an iterator over the accounts that differ between two state tries, ordered by hashed address.
+    /// Each account is returned along with its state in the old and the new trie, `None` if it isn't part of it.
+    /// Does not check that the state roots are valid
+    pub fn iter_account_changes(
+        &self,
+        old_state_root: H256,
+        new_state_root: H256,
+    ) -> Result<impl Iterator<Item = Result<AccountChange, StoreError>>, StoreError> {
+        let old_trie = self.engine.open_locked_state_trie(old_state_root)?;
+        let new_trie = self.engine.open_locked_state_trie(new_state_root)?;
+        Ok(old_trie.diff(new_trie).map(|change| {
+            let (path, old, new) = change?.into_parts();
+            let decode =
+                |value: Option<Vec<u8>>| value.map(|v| AccountState::decode(&v)).transpose();
+            Ok((H256::from_slice(&path), decode(old)?, decode(new)?))
+        }))
+    }
+
+    /// Returns an iterator over the storage slots that differ between two storage tries of an account,
+    /// ordered by hashed key. Each slot is returned along with its value in the old and the new trie.
+    /// Does not check that the storage roots are valid
+    pub fn iter_storage_changes(
+        &self,
+        hashed_address: H256,
+        old_storage_root: H256,
+        new_storage_root: H256,
+    ) -> Result<impl Iterator<Item = Result<StorageChange, StoreError>>, StoreError> {
+        let old_trie = self
+            .engine
+            .open_locked_storage_trie(hashed_address, old_storage_root)?;
+        let new_trie = self
+            .engine
+            .open_locked_storage_trie(hashed_address, new_storage_root)?;
+        Ok(old_trie.diff(new_trie).map(|change| {
+            let (path, old, new) = change?.into_parts();
+            let decode = |value: Option<Vec<u8>>| value.map(|v| U256::decode(&v)).transpose();
+            Ok((H256::from_slice(&path), decode(old)?, decode(new)?))
+        }))
+    }
+
+    /// Returns the address whose hash is `hashed_address`, if the account was updated by a block
+    /// stored with [`StoreOptions::store_preimages`] set
+    pub fn get_address_preimage(
+        &self,
+        hashed_address: H256,
+    ) -> Result<Option<Address>, StoreError> {
+        Ok(self
+            .engine
+            .get_preimage(hashed_address)?
+            .filter(|preimage| preimage.len() == Address::len_bytes())
+            .map(|preimage| Address::from_slice(&preimage)))
+    }
+
+    /// Returns the storage key whose hash is `hashed_key`, if the slot was written by a block stored
+    /// with [`StoreOptions::store_preimages`] set
+    pub fn get_storage_key_preimage(&self, hashed_key: H256) -> Result<Option<H256>, StoreError> {
+        Ok(self
+            .engine
+            .get_preimage(hashed_key)?
+            .filter(|preimage| preimage.len() == H256::len_bytes())
+            .map(|preimage| H256::from_slice(&preimage)))
+    }
+
+    /// Returns up to `max_results` storage slots of the given account, ordered by hashed key and starting from `start`,
+    /// as they would be after applying `account_updates` on top of the state given by `state_root`.
+    /// Also returns the hashed key of the next slot if there are more slots left after the returned ones.
+    /// The updates are only applied to an in-memory view of the storage trie, nothing is written to the database
+    /// Does not check that the state_root is valid
+    pub fn storage_range_with_updates(
+        &self,
+        state_root: H256,
+        address: Address,
+        account_updates: &[AccountUpdate],
+        start: H256,
+        max_results: usize,
+    ) -> Result<(Vec<(H256, U256)>, Option<H256>), StoreError> {
+        let hashed_address = hash_address_fixed(&address);
+        let state_trie = self.engine.open_locked_state_trie(state_root)?;
+        let storage_root = match state_trie.get(&hashed_address.as_bytes().to_vec())? {
+            Some(account_rlp) => AccountState::decode(&account_rlp)?.storage_root,
+            None => *EMPTY_TRIE_HASH,
+        };
+        let mut storage_trie = self
+            .engine
+            .open_locked_storage_trie(hashed_address, storage_root)?;
+        // Apply the updates in order, an account may be removed and re-created with new storage
+        for update in account_updates
+            .iter()
+            .filter(|update| update.address == address)
+        {
+            if update.removed {
+                storage_trie = self
+                    .engine
+                    .open_locked_storage_trie(hashed_address, *EMPTY_TRIE_HASH)?;
+                continue;
+            }
+            for (storage_key, storage_value) in &update.added_storage {
+                let hashed_key = hash_key(storage_key);
+                if storage_value.is_zero() {
+                    storage_trie.remove(&hashed_key)?;
+                } else {
+                    storage_trie.insert(hashed_key, storage_value.encode_to_vec())?;
+                }
+            }
+        }
+        let mut iter = storage_trie.into_iter();
+        iter.advance(start.0.to_vec())?;
+        let mut slots = iter.content().map(|(path, value)| {
+            Ok::<_, StoreError>((H256::from_slice(&path), U256::decode(&value)?))
+        });
+        let range = slots.by_ref().take(max_results).collect::<Result<_, _>>()?;
+        let next_key = slots.next().transpose()?.map(|(hashed_key, _)| hashed_key);
+        Ok((range, next_key))
+    }
+
+    /// Applies the account updates to `state_trie` and to the storage tries of the accounts in `storage_tries`,
+    /// opening the ones missing from the database, and returns the new state root.
+    /// Every change is kept in memory, nothing is written to the database, so the same tries can keep being
+    /// updated by later calls.
+    pub fn apply_account_updates_in_memory(
+        &self,
+        state_trie: &mut Trie,
+        storage_tries: &mut HashMap<H256, Trie>,
+        account_updates: &[AccountUpdate],
+    ) -> Result<H256, StoreError> {
+        for update in account_updates {
+            let hashed_address = hash_address_fixed(&update.address);
+            let path = hashed_address.as_bytes().to_vec();
+            if update.removed {
+                // The storage is dropped along with the account, a re-created account starts with an empty one
+                state_trie.remove(&path)?;
+                storage_tries.remove(&hashed_address);
+                continue;
+            }
+            let mut account_state = match state_trie.get(&path)? {
+                Some(encoded_state) => AccountState::decode(&encoded_state)?,
+                None => AccountState::default(),
+            };
+            if let Some(info) = &update.info {
+                account_state.nonce = info.nonce;
+                account_state.balance = info.balance;
+                account_state.code_hash = info.code_hash;
+            }
+            if !update.added_storage.is_empty() {
+                let storage_trie = match storage_tries.entry(hashed_address) {
+                    std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                    std::collections::hash_map::Entry::Vacant(entry) => entry.insert(
+                        self.engine
+                            .open_locked_storage_trie(hashed_address, account_state.storage_root)?,
+                    ),
+                };
+                for (storage_key, storage_value) in &update.added_storage {
+                    let hashed_key = hash_key(storage_key);
+                    if storage_value.is_zero() {
+                        storage_trie.remove(&hashed_key)?;
+                    } else {
+                        storage_trie.insert(hashed_key, storage_value.encode_to_vec())?;
+                    }
+                }
+                account_state.storage_root = storage_trie.hash_no_commit();
+            }
+            state_trie.insert(path, account_state.encode_to_vec())?;
+        }
+        Ok(state_trie.hash_no_commit())
+    }
+
     pub fn get_account_range_proof(
         &self,
         state_root: H256,
@@ -1054,6 +1667,25 @@ impl Store {
         self.engine.get_receipts_for_block(block_hash).await
     }
 
+    /// Returns how trie nodes are keyed in the database
+    pub fn trie_scheme(&self) -> TrieScheme {
+        self.engine.trie_scheme()
+    }
+
+    /// Writes trie nodes at the given paths of the state trie, or of the storage trie of the
+    /// given account, as part of the state of the given block, which must be made canonical
+    /// next. Used to migrate databases to the path scheme
+    pub async fn write_trie_nodes_at_paths(
+        &self,
+        hashed_address: Option<H256>,
+        nodes: Vec<(Nibbles, NodeHash, Vec<u8>)>,
+        block: (BlockNumber, BlockHash),
+    ) -> Result<(), StoreError> {
+        self.engine
+            .write_trie_nodes_at_paths(hashed_address, nodes, block)
+            .await
+    }
+
     /// Creates a new state trie with an empty state root, for testing purposes only
     pub fn new_state_trie_for_test(&self) -> Result<Trie, StoreError> {
         self.engine.open_state_trie(*EMPTY_TRIE_HASH)
@@ -1216,6 +1848,52 @@ impl Store {
             .await
     }
 
+    /// Stores a block that failed validation so it can be inspected later on.
+    /// Only the last `MAX_BAD_BLOCKS` added are kept, the oldest one is dropped to make room.
+    pub async fn add_bad_block(&self, block: Block) -> Result<(), StoreError> {
+        let block_hash = block.hash();
+        let bad_blocks = self.bad_blocks_by_position().await?;
+        if bad_blocks
+            .iter()
+            .any(|(_, bad_block)| bad_block.hash() == block_hash)
+        {
+            return Ok(());
+        }
+        info!("Storing bad block: {block_hash}");
+        let position = bad_blocks
+            .last()
+            .map_or(0, |(position, _)| position.saturating_add(1));
+        self.engine.add_bad_block(position, block).await?;
+        let evicted = (bad_blocks.len() + 1).saturating_sub(MAX_BAD_BLOCKS);
+        for (_, evicted) in bad_blocks.iter().take(evicted) {
+            self.engine.remove_bad_block(evicted.hash()).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the bad blocks stored, from the last one added to the first one
+    pub async fn get_bad_blocks(&self) -> Result<Vec<Block>, StoreError> {
+        Ok(self
+            .bad_blocks_by_position()
+            .await?
+            .into_iter()
+            .rev()
+            .map(|(_, block)| block)
+            .collect())
+    }
+
+    /// Returns the bad block with the given hash, if it's stored
+    pub async fn get_bad_block(&self, block_hash: BlockHash) -> Result<Option<Block>, StoreError> {
+        self.engine.get_bad_block(block_hash).await
+    }
+
+    /// Bad blocks along with the position they were added in, from the first one added
+    async fn bad_blocks_by_position(&self) -> Result<Vec<(u64, Block)>, StoreError> {
+        let mut bad_blocks = self.engine.get_bad_blocks().await?;
+        bad_blocks.sort_by_key(|(position, _)| *position);
+        Ok(bad_blocks)
+    }
+
     /// Takes a block hash and returns an iterator to its ancestors. Block headers are returned
     /// in reverse order, starting from the given block and going up to the genesis block.
     pub fn ancestors(&self, block_hash: BlockHash) -> AncestorIterator {
@@ -1311,6 +1989,37 @@ pub fn hash_key(key: &H256) -> Vec<u8> {
         .to_vec()
 }
 
+/// Builds the address-indexed transaction history entries for the given block, recovering the
+/// senders of its transactions unless they are given.
+/// Transactions whose sender can't be recovered are only indexed by their recipient.
+fn address_transactions_for_block(
+    block: &Block,
+    senders: Option<Vec<Address>>,
+) -> Vec<AddressTransaction> {
+    let block_hash = block.hash();
+    let mut entries = Vec::new();
+    for (index, tx) in block.body.transactions.iter().enumerate() {
+        let sender = match &senders {
+            Some(senders) => senders.get(index).copied(),
+            None => tx.sender().ok(),
+        };
+        let recipient = match tx.to() {
+            TxKind::Call(to) if Some(to) != sender => Some(to),
+            _ => None,
+        };
+        for address in sender.into_iter().chain(recipient) {
+            entries.push(AddressTransaction {
+                address,
+                block_number: block.header.number,
+                block_hash,
+                index: index as Index,
+                transaction_hash: tx.hash(),
+            });
+        }
+    }
+    entries
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::Bytes;
@@ -1336,6 +2045,332 @@ mod tests {
         test_store_suite(EngineType::RocksDB).await;
     }
 
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_rocksdb_secondary_store() {
+        const GENESIS_KURTOSIS: &str = include_str!("../../fixtures/genesis/kurtosis.json");
+        const GENESIS_HIVE: &str = include_str!("../../fixtures/genesis/hive.json");
+        let genesis: Genesis = serde_json::from_str(GENESIS_KURTOSIS).unwrap();
+        let primary_dir = tempfile::tempdir().unwrap();
+        let secondary_dir = tempfile::tempdir().unwrap();
+
+        let primary = Store::new(primary_dir.path(), EngineType::RocksDB).unwrap();
+        primary.add_initial_state(genesis.clone()).await.unwrap();
+
+        let secondary = Store::new_secondary(primary_dir.path(), secondary_dir.path()).unwrap();
+        let result = secondary
+            .load_secondary_state(serde_json::from_str(GENESIS_HIVE).unwrap())
+            .await;
+        assert!(matches!(result, Err(StoreError::IncompatibleChainConfig)));
+        secondary.load_secondary_state(genesis).await.unwrap();
+        assert_eq!(secondary.get_latest_block_number().await.unwrap(), 0);
+
+        // Writes of the primary are only seen after catching up
+        let (mut header, body) = create_block_for_testing();
+        header.number = 1;
+        let hash = header.hash();
+        primary.add_block(Block::new(header, body)).await.unwrap();
+        primary
+            .forkchoice_update(Some(vec![(1, hash)]), 1, hash, None, None)
+            .await
+            .unwrap();
+        assert_eq!(secondary.get_block_header_by_hash(hash).unwrap(), None);
+
+        secondary.catch_up_with_primary().await.unwrap();
+        assert_eq!(secondary.get_latest_block_number().await.unwrap(), 1);
+        assert_eq!(
+            secondary.get_latest_canonical_block_hash().await.unwrap(),
+            Some(hash)
+        );
+        assert!(secondary.get_block_header_by_hash(hash).unwrap().is_some());
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_rocksdb_checkpoint() {
+        const GENESIS_KURTOSIS: &str = include_str!("../../fixtures/genesis/kurtosis.json");
+        let genesis: Genesis = serde_json::from_str(GENESIS_KURTOSIS).unwrap();
+        let genesis_hash = genesis.get_block().hash();
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_path = dir.path().join("checkpoint");
+
+        let store = Store::new(dir.path().join("db"), EngineType::RocksDB).unwrap();
+        store.add_initial_state(genesis.clone()).await.unwrap();
+        let manifest = store.create_checkpoint(&checkpoint_path).await.unwrap();
+        assert_eq!(manifest.chain_id, genesis.config.chain_id);
+        assert_eq!(manifest.head_block_number, 0);
+        assert_eq!(manifest.head_block_hash, genesis_hash);
+        // The checkpoint path must not exist
+        assert!(store.create_checkpoint(&checkpoint_path).await.is_err());
+
+        let restored = Store::new(&checkpoint_path, EngineType::RocksDB).unwrap();
+        restored.add_initial_state(genesis).await.unwrap();
+        restored
+            .validate_checkpoint_manifest(&checkpoint_path)
+            .unwrap();
+
+        CheckpointManifest {
+            chain_id: manifest.chain_id + 1,
+            ..manifest.clone()
+        }
+        .write(&checkpoint_path)
+        .unwrap();
+        assert!(
+            restored
+                .validate_checkpoint_manifest(&checkpoint_path)
+                .is_err()
+        );
+
+        CheckpointManifest {
+            head_block_number: 1,
+            ..manifest
+        }
+        .write(&checkpoint_path)
+        .unwrap();
+        assert!(
+            restored
+                .validate_checkpoint_manifest(&checkpoint_path)
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_rocksdb_checkpoint_restore() {
+        use crate::checkpoint::{CHECKPOINT_MANIFEST_FILE, RESTORED_CHECKPOINT_MANIFEST_FILE};
+
+        const GENESIS_KURTOSIS: &str = include_str!("../../fixtures/genesis/kurtosis.json");
+        let genesis: Genesis = serde_json::from_str(GENESIS_KURTOSIS).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_path = dir.path().join("checkpoint");
+
+        let store = Store::new(dir.path().join("db"), EngineType::RocksDB).unwrap();
+        store.add_initial_state(genesis.clone()).await.unwrap();
+        let genesis_header = store.get_block_header(0).unwrap().unwrap();
+        let block_1 = add_child_block(&store, &genesis_header, &[], 1).await;
+        store
+            .forkchoice_update(
+                Some(vec![(1, block_1.hash())]),
+                1,
+                block_1.hash(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let manifest = store.create_checkpoint(&checkpoint_path).await.unwrap();
+        assert_eq!(manifest.head_block_hash, block_1.hash());
+
+        let restored = Store::new(&checkpoint_path, EngineType::RocksDB).unwrap();
+        restored.add_initial_state(genesis.clone()).await.unwrap();
+        restored
+            .validate_checkpoint_manifest(&checkpoint_path)
+            .unwrap();
+        // The manifest is only validated on the first start
+        assert!(!checkpoint_path.join(CHECKPOINT_MANIFEST_FILE).exists());
+        assert!(
+            checkpoint_path
+                .join(RESTORED_CHECKPOINT_MANIFEST_FILE)
+                .exists()
+        );
+
+        // The chain moves on, reorging the checkpoint head out
+        let side_block_1 = add_child_block(&restored, &genesis_header, &[], 2).await;
+        let side_block_2 = add_child_block(&restored, &side_block_1, &[], 2).await;
+        restored
+            .forkchoice_update(
+                Some(vec![(1, side_block_1.hash()), (2, side_block_2.hash())]),
+                2,
+                side_block_2.hash(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        drop(restored);
+
+        let restored = Store::new(&checkpoint_path, EngineType::RocksDB).unwrap();
+        restored.add_initial_state(genesis).await.unwrap();
+        restored
+            .validate_checkpoint_manifest(&checkpoint_path)
+            .unwrap();
+        assert_eq!(
+            restored.get_latest_canonical_block_hash().await.unwrap(),
+            Some(side_block_2.hash())
+        );
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_rocksdb_path_scheme_reorgs() {
+        const GENESIS_KURTOSIS: &str = include_str!("../../fixtures/genesis/kurtosis.json");
+        let genesis: Genesis = serde_json::from_str(GENESIS_KURTOSIS).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new_with_options(
+            dir.path(),
+            EngineType::RocksDB,
+            StoreOptions {
+                trie_scheme: Some(TrieScheme::Path),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        store.add_initial_state(genesis).await.unwrap();
+        let genesis_header = store.get_block_header(0).unwrap().unwrap();
+
+        let address = Address::from_low_u64_be(0xaa);
+        let removed_address = Address::from_low_u64_be(0xbb);
+        let update = |address: Address, balance: u64, slot: u64| AccountUpdate {
+            address,
+            removed: false,
+            info: Some(AccountInfo {
+                code_hash: *EMPTY_KECCACK_HASH,
+                balance: U256::from(balance),
+                nonce: 0,
+            }),
+            code: None,
+            added_storage: [(H256::from_low_u64_be(1), U256::from(slot))].into(),
+        };
+        let removal = AccountUpdate {
+            address: removed_address,
+            removed: true,
+            ..Default::default()
+        };
+
+        let block_1 = add_child_block(
+            &store,
+            &genesis_header,
+            &[update(address, 1, 10), update(removed_address, 1, 1)],
+            1,
+        )
+        .await;
+        store
+            .forkchoice_update(
+                Some(vec![(1, block_1.hash())]),
+                1,
+                block_1.hash(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let block_2 =
+            add_child_block(&store, &block_1, &[update(address, 2, 20), removal], 1).await;
+        store
+            .forkchoice_update(
+                Some(vec![(2, block_2.hash())]),
+                2,
+                block_2.hash(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        // A side chain forking from block 1 doesn't disturb the canonical state
+        let side_block_2 = add_child_block(&store, &block_1, &[update(address, 3, 30)], 2).await;
+        let side_block_3 =
+            add_child_block(&store, &side_block_2, &[update(address, 4, 40)], 2).await;
+
+        let check = |block: &BlockHeader, balance: u64, slot: u64, removed_exists: bool| {
+            let info = store
+                .get_account_info_by_hash(block.hash(), address)
+                .unwrap()
+                .unwrap();
+            assert_eq!(info.balance, U256::from(balance));
+            assert_eq!(
+                store
+                    .get_storage_at_hash(block.hash(), address, H256::from_low_u64_be(1))
+                    .unwrap(),
+                Some(U256::from(slot))
+            );
+            assert_eq!(
+                store
+                    .get_account_info_by_hash(block.hash(), removed_address)
+                    .unwrap()
+                    .is_some(),
+                removed_exists
+            );
+        };
+        let check_all = || {
+            check(&block_1, 1, 10, true);
+            check(&block_2, 2, 20, false);
+            check(&side_block_2, 3, 30, true);
+            check(&side_block_3, 4, 40, true);
+        };
+        check_all();
+
+        // Reorg to the side chain and back
+        store
+            .forkchoice_update(
+                Some(vec![(2, side_block_2.hash()), (3, side_block_3.hash())]),
+                3,
+                side_block_3.hash(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        check_all();
+        store
+            .forkchoice_update(
+                Some(vec![(2, block_2.hash())]),
+                2,
+                block_2.hash(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        check_all();
+
+        // The state written at the paths survives reopening the store
+        drop(store);
+        let store = Store::new(dir.path(), EngineType::RocksDB).unwrap();
+        assert_eq!(store.trie_scheme(), TrieScheme::Path);
+        let info = store
+            .get_account_info_by_hash(block_2.hash(), address)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.balance, U256::from(2));
+    }
+
+    // Adds a child of `parent` applying the given updates to its state, without making it canonical
+    #[cfg(feature = "rocksdb")]
+    async fn add_child_block(
+        store: &Store,
+        parent: &BlockHeader,
+        updates: &[AccountUpdate],
+        fork: u8,
+    ) -> BlockHeader {
+        let account_updates = store
+            .apply_account_updates_batch(parent.hash(), updates)
+            .await
+            .unwrap()
+            .unwrap();
+        let (header, body) = create_block_for_testing();
+        let header = BlockHeader {
+            parent_hash: parent.hash(),
+            number: parent.number + 1,
+            state_root: account_updates.state_trie_hash,
+            extra_data: Bytes::from(vec![fork]),
+            hash: Default::default(),
+            ..header
+        };
+        store
+            .store_block_updates(UpdateBatch {
+                account_updates: account_updates.state_updates,
+                storage_updates: account_updates.storage_updates,
+                blocks: vec![Block::new(header.clone(), body)],
+                receipts: vec![],
+                senders: vec![],
+                code_updates: account_updates.code_updates,
+                preimages: account_updates.preimages,
+            })
+            .await
+            .unwrap();
+        header
+    }
+
     // Creates an empty store, runs the test and then removes the store (if needed)
     async fn run_test<F, Fut>(test_func: F, engine_type: EngineType)
     where
@@ -1366,6 +2401,12 @@ mod tests {
         run_test(test_genesis_block, engine_type).await;
         run_test(test_iter_accounts, engine_type).await;
         run_test(test_iter_storage, engine_type).await;
+        run_test(test_storage_range_with_updates, engine_type).await;
+        run_test(test_storage_range_with_invalid_slot, engine_type).await;
+        run_test(test_apply_account_updates_batch, engine_type).await;
+        run_test(test_transactions_by_address, engine_type).await;
+        run_test(test_bad_blocks, engine_type).await;
+        run_test(test_preimages, engine_type).await;
     }
 
     async fn test_iter_accounts(store: Store) {
@@ -1437,6 +2478,197 @@ mod tests {
         }
     }
 
+    async fn test_apply_account_updates_batch(store: Store) {
+        let update = |i: u64, slots: &[(u64, u64)]| AccountUpdate {
+            address: Address::from_low_u64_be(i),
+            removed: false,
+            info: Some(AccountInfo {
+                code_hash: *EMPTY_KECCACK_HASH,
+                balance: U256::from(i),
+                nonce: i,
+            }),
+            code: None,
+            added_storage: slots
+                .iter()
+                .map(|(key, value)| (H256::from_low_u64_be(*key), U256::from(*value)))
+                .collect(),
+        };
+        let mut updates: Vec<_> = (0..200).map(|i| update(i, &[(i, 1), (i + 1, 2)])).collect();
+        // Updates of the same account are applied in order
+        updates.push(update(0, &[(0, 3), (1, 0), (2, 4)]));
+        // Storage written before removing an account is dropped
+        updates.push(AccountUpdate::removed(Address::from_low_u64_be(1)));
+        updates.push(update(1, &[(5, 5)]));
+        updates.push(AccountUpdate::removed(Address::from_low_u64_be(2)));
+
+        let final_slots = |i: u64| match i {
+            0 => vec![(0, 3), (2, 4)],
+            1 => vec![(5, 5)],
+            _ => vec![(i, 1), (i + 1, 2)],
+        };
+        let mut expected_trie = store.open_state_trie(*EMPTY_TRIE_HASH).unwrap();
+        for i in (0..200).filter(|i| *i != 2) {
+            let address = Address::from_low_u64_be(i);
+            let mut storage_trie = store
+                .open_storage_trie(hash_address_fixed(&address), *EMPTY_TRIE_HASH)
+                .unwrap();
+            for (key, value) in final_slots(i) {
+                storage_trie
+                    .insert(
+                        hash_key(&H256::from_low_u64_be(key)),
+                        U256::from(value).encode_to_vec(),
+                    )
+                    .unwrap();
+            }
+            let state = AccountState {
+                nonce: i,
+                balance: U256::from(i),
+                storage_root: storage_trie.hash_no_commit(),
+                code_hash: *EMPTY_KECCACK_HASH,
+            };
+            expected_trie
+                .insert(hash_address(&address), state.encode_to_vec())
+                .unwrap();
+        }
+
+        let state_trie = store.open_state_trie(*EMPTY_TRIE_HASH).unwrap();
+        let result = store
+            .apply_account_updates_from_trie_batch(state_trie, &updates)
+            .await
+            .unwrap();
+        assert_eq!(result.state_trie_hash, expected_trie.hash_no_commit());
+        // Account 2 was removed so its storage trie is dropped instead of written
+        assert_eq!(result.storage_updates.len(), 200);
+        let removed = hash_address_fixed(&Address::from_low_u64_be(2));
+        assert!(
+            result
+                .storage_updates
+                .iter()
+                .any(|(hashed_address, nodes)| *hashed_address == removed && nodes.is_empty())
+        );
+    }
+
+    async fn test_storage_range_with_updates(store: Store) {
+        let address = Address::from_low_u64_be(12345);
+        let hashed_address = hash_address_fixed(&address);
+        let mut slots: BTreeMap<H256, (H256, U256)> = (0u64..100)
+            .map(|i| {
+                let key = H256::from_low_u64_be(i);
+                (H256::from_slice(&hash_key(&key)), (key, U256::from(i + 1)))
+            })
+            .collect();
+        let mut trie = store
+            .open_storage_trie(hashed_address, *EMPTY_TRIE_HASH)
+            .unwrap();
+        for (hashed_key, (_, value)) in &slots {
+            trie.insert(hashed_key.0.to_vec(), value.encode_to_vec())
+                .unwrap();
+        }
+        let storage_root = trie.hash().unwrap();
+        let mut trie = store.open_state_trie(*EMPTY_TRIE_HASH).unwrap();
+        trie.insert(
+            hashed_address.0.to_vec(),
+            AccountState {
+                nonce: 1,
+                balance: U256::zero(),
+                storage_root,
+                code_hash: *EMPTY_KECCACK_HASH,
+            }
+            .encode_to_vec(),
+        )
+        .unwrap();
+        let state_root = trie.hash().unwrap();
+        // Clear one slot, modify another one and add a new one
+        let mut update = AccountUpdate::new(address);
+        update
+            .added_storage
+            .insert(H256::from_low_u64_be(0), U256::zero());
+        update
+            .added_storage
+            .insert(H256::from_low_u64_be(1), U256::from(1000));
+        update
+            .added_storage
+            .insert(H256::from_low_u64_be(500), U256::from(500));
+        for (key, value) in &update.added_storage {
+            let hashed_key = H256::from_slice(&hash_key(key));
+            if value.is_zero() {
+                slots.remove(&hashed_key);
+            } else {
+                slots.insert(hashed_key, (*key, *value));
+            }
+        }
+        let expected: Vec<_> = slots
+            .iter()
+            .map(|(hashed_key, (_, value))| (*hashed_key, *value))
+            .collect();
+        // Page through the whole storage
+        let mut start = H256::zero();
+        let mut actual = Vec::new();
+        loop {
+            let (range, next_key) = store
+                .storage_range_with_updates(
+                    state_root,
+                    address,
+                    std::slice::from_ref(&update),
+                    start,
+                    30,
+                )
+                .unwrap();
+            assert!(range.len() <= 30);
+            actual.extend(range);
+            match next_key {
+                Some(next_key) => start = next_key,
+                None => break,
+            }
+        }
+        assert_eq!(actual, expected);
+        // Removing the account clears its storage
+        let (range, next_key) = store
+            .storage_range_with_updates(
+                state_root,
+                address,
+                &[AccountUpdate::removed(address)],
+                H256::zero(),
+                30,
+            )
+            .unwrap();
+        assert!(range.is_empty());
+        assert!(next_key.is_none());
+    }
+
+    async fn test_storage_range_with_invalid_slot(store: Store) {
+        let address = Address::from_low_u64_be(12345);
+        let hashed_address = hash_address_fixed(&address);
+        let mut trie = store
+            .open_storage_trie(hashed_address, *EMPTY_TRIE_HASH)
+            .unwrap();
+        trie.insert(
+            hash_key(&H256::from_low_u64_be(1)),
+            U256::one().encode_to_vec(),
+        )
+        .unwrap();
+        // Not a valid RLP-encoded U256
+        trie.insert(hash_key(&H256::from_low_u64_be(2)), vec![0xff; 40])
+            .unwrap();
+        let storage_root = trie.hash().unwrap();
+        let mut trie = store.open_state_trie(*EMPTY_TRIE_HASH).unwrap();
+        trie.insert(
+            hashed_address.0.to_vec(),
+            AccountState {
+                nonce: 1,
+                balance: U256::zero(),
+                storage_root,
+                code_hash: *EMPTY_KECCACK_HASH,
+            }
+            .encode_to_vec(),
+        )
+        .unwrap();
+        let state_root = trie.hash().unwrap();
+        // The page must not be silently truncated at the invalid slot
+        let result = store.storage_range_with_updates(state_root, address, &[], H256::zero(), 10);
+        assert!(result.is_err());
+    }
+
     async fn test_genesis_block(store: Store) {
         const GENESIS_KURTOSIS: &str = include_str!("../../fixtures/genesis/kurtosis.json");
         const GENESIS_HIVE: &str = include_str!("../../fixtures/genesis/hive.json");
@@ -1493,6 +2725,131 @@ mod tests {
         assert_eq!(stored_body, block_body);
     }
 
+    async fn test_preimages(store: Store) {
+        let address = Address::from_low_u64_be(1);
+        let key = H256::from_low_u64_be(2);
+        let mut update = AccountUpdate::new(address);
+        update.added_storage.insert(key, U256::one());
+        let hashed_address = hash_address_fixed(&address);
+        let hashed_key = H256::from_slice(&hash_key(&key));
+        let apply_update = |store: &Store| {
+            let state_trie = store.open_state_trie(*EMPTY_TRIE_HASH).unwrap();
+            let store = store.clone();
+            let update = update.clone();
+            async move {
+                store
+                    .apply_account_updates_from_trie_batch(state_trie, [&update])
+                    .await
+                    .unwrap()
+            }
+        };
+        async fn store_updates(
+            store: &Store,
+            updates: AccountUpdatesList,
+            blocks: Vec<Block>,
+        ) -> Result<(), StoreError> {
+            store
+                .store_block_updates(UpdateBatch {
+                    account_updates: updates.state_updates,
+                    storage_updates: updates.storage_updates,
+                    blocks,
+                    receipts: vec![],
+                    senders: vec![],
+                    code_updates: updates.code_updates,
+                    preimages: updates.preimages,
+                })
+                .await
+        }
+
+        // Preimages are off by default
+        let updates = apply_update(&store).await;
+        assert!(updates.preimages.is_empty());
+
+        // Computing the state root doesn't write them, only storing a block does
+        let store = Store {
+            store_preimages: true,
+            ..store
+        };
+        let updates = apply_update(&store).await;
+        assert_eq!(updates.preimages.len(), 2);
+        assert_eq!(store.get_address_preimage(hashed_address).unwrap(), None);
+        store_updates(&store, updates, vec![]).await.unwrap();
+        assert_eq!(store.get_address_preimage(hashed_address).unwrap(), None);
+
+        let (header, body) = create_block_for_testing();
+        let updates = apply_update(&store).await;
+        store_updates(&store, updates, vec![Block::new(header, body)])
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_address_preimage(hashed_address).unwrap(),
+            Some(address)
+        );
+        assert_eq!(
+            store.get_storage_key_preimage(hashed_key).unwrap(),
+            Some(key)
+        );
+    }
+
+    async fn test_bad_blocks(store: Store) {
+        let (header, body) = create_block_for_testing();
+        // Numbers decrease as they are added, so that eviction can't follow them
+        let blocks: Vec<Block> = (0..MAX_BAD_BLOCKS as u64 + 2)
+            .map(|index| {
+                let header = BlockHeader {
+                    number: 100 - index,
+                    hash: Default::default(),
+                    ..header.clone()
+                };
+                Block::new(header, body.clone())
+            })
+            .collect();
+
+        for block in &blocks {
+            store.add_bad_block(block.clone()).await.unwrap();
+        }
+        // Adding one already stored changes nothing
+        store.add_bad_block(blocks[5].clone()).await.unwrap();
+
+        // The two added first were dropped, the rest come from the last one added
+        let hashes = |blocks: &[Block]| blocks.iter().map(Block::hash).collect::<Vec<_>>();
+        let stored = store.get_bad_blocks().await.unwrap();
+        let expected: Vec<Block> = blocks[2..].iter().rev().cloned().collect();
+        assert_eq!(hashes(&stored), hashes(&expected));
+
+        assert!(
+            store
+                .get_bad_block(blocks[0].hash())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let last = blocks.last().unwrap();
+        let found = store.get_bad_block(last.hash()).await.unwrap().unwrap();
+        assert_eq!(found.hash(), last.hash());
+
+        // The next one drops the oldest one left
+        let newest = Block::new(
+            BlockHeader {
+                number: 1,
+                hash: Default::default(),
+                ..header
+            },
+            body,
+        );
+        store.add_bad_block(newest.clone()).await.unwrap();
+        let stored = store.get_bad_blocks().await.unwrap();
+        assert_eq!(stored.len(), MAX_BAD_BLOCKS);
+        assert_eq!(stored[0].hash(), newest.hash());
+        assert!(
+            store
+                .get_bad_block(blocks[2].hash())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
     fn create_block_for_testing() -> (BlockHeader, BlockBody) {
         let block_header = BlockHeader {
             parent_hash: H256::from_str(
@@ -1547,6 +2904,147 @@ mod tests {
         (block_header, block_body)
     }
 
+    async fn test_transactions_by_address(store: Store) {
+        let recipient = Address::from_str("0x6177843db3138ae69679a54b95cf345ed759450d").unwrap();
+        assert!(
+            store
+                .get_transactions_by_address(recipient, None, 10)
+                .await
+                .is_err()
+        );
+        let store = Store {
+            index_transactions_by_address: true,
+            ..store
+        };
+
+        let (block_header, block_body) = create_block_for_testing();
+        let block = Block::new(block_header.clone(), block_body.clone());
+        let block_hash = block.hash();
+        let number = block.header.number;
+        store.add_block(block).await.unwrap();
+        store
+            .forkchoice_update(None, number, block_hash, None, None)
+            .await
+            .unwrap();
+
+        let transactions = store
+            .get_transactions_by_address(recipient, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|tx| (tx.block_hash, tx.index))
+                .collect::<Vec<_>>(),
+            vec![(block_hash, 1), (block_hash, 0)]
+        );
+        assert_eq!(
+            transactions[0].transaction_hash,
+            block_body.transactions[1].hash()
+        );
+
+        // Paginate using the position of the last returned transaction as cursor
+        let page = store
+            .get_transactions_by_address(recipient, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(page, transactions[..1]);
+        let page = store
+            .get_transactions_by_address(recipient, Some((number, page[0].index)), 1)
+            .await
+            .unwrap();
+        assert_eq!(page, transactions[1..]);
+
+        // Reorg to a sibling block with the same transactions
+        let sibling_header = BlockHeader {
+            timestamp: block_header.timestamp + 1,
+            ..block_header.clone()
+        };
+        let sibling = Block::new(sibling_header, block_body.clone());
+        let sibling_hash = sibling.hash();
+        store.add_block(sibling).await.unwrap();
+        store
+            .forkchoice_update(None, number, sibling_hash, None, None)
+            .await
+            .unwrap();
+        let transactions = store
+            .get_transactions_by_address(recipient, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions.iter().all(|tx| tx.block_hash == sibling_hash));
+
+        // Reorg back to the original block
+        store
+            .forkchoice_update(None, number, block_hash, None, None)
+            .await
+            .unwrap();
+        let transactions = store
+            .get_transactions_by_address(recipient, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions.iter().all(|tx| tx.block_hash == block_hash));
+
+        // Side blocks are not indexed until they become canonical
+        let side_header = BlockHeader {
+            timestamp: block_header.timestamp + 2,
+            ..block_header.clone()
+        };
+        store
+            .add_block(Block::new(side_header, block_body.clone()))
+            .await
+            .unwrap();
+        let transactions = store
+            .get_transactions_by_address(recipient, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions.iter().all(|tx| tx.block_hash == block_hash));
+
+        // Removing the canonical block drops its entries
+        store.remove_block(number).await.unwrap();
+        assert!(
+            store
+                .get_transactions_by_address(recipient, None, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        // The senders kept when storing an executed block are used instead of recovering them
+        let sender = Address::repeat_byte(0x42);
+        let executed = Block::new(
+            BlockHeader {
+                timestamp: block_header.timestamp + 3,
+                ..block_header
+            },
+            block_body,
+        );
+        let executed_hash = executed.hash();
+        store
+            .store_block_updates(UpdateBatch {
+                account_updates: vec![],
+                storage_updates: vec![],
+                blocks: vec![executed],
+                receipts: vec![],
+                senders: vec![(executed_hash, vec![sender; 2])],
+                code_updates: vec![],
+                preimages: vec![],
+            })
+            .await
+            .unwrap();
+        store
+            .forkchoice_update(None, number, executed_hash, None, None)
+            .await
+            .unwrap();
+        let transactions = store
+            .get_transactions_by_address(sender, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions.iter().all(|tx| tx.block_hash == executed_hash));
+    }
+
     async fn test_store_block_number(store: Store) {
         let block_hash = H256::random();
         let block_number = 6;
@@ -1,6 +1,9 @@
-use crate::{UpdateBatch, api::StoreEngine, error::StoreError, store::STATE_TRIE_SEGMENTS};
+use crate::{
+    AddressTransaction, AddressTransactionsUpdate, UpdateBatch, api::StoreEngine,
+    error::StoreError, store::STATE_TRIE_SEGMENTS,
+};
 use bytes::Bytes;
-use ethereum_types::H256;
+use ethereum_types::{Address, H256};
 use ethrex_common::types::{
     Block, BlockBody, BlockHash, BlockHeader, BlockNumber, ChainConfig, Index, Receipt,
 };
@@ -26,6 +29,10 @@ pub struct StoreInner {
     account_codes: HashMap<H256, Bytes>,
     // Maps transaction hashes to their blocks (height+hash) and index within the blocks.
     transaction_locations: HashMap<H256, Vec<(BlockNumber, BlockHash, Index)>>,
+    // Maps addresses to the transactions they sent or received, ordered by their location
+    address_transactions: HashMap<Address, BTreeMap<(BlockNumber, Index, BlockHash), H256>>,
+    // Maps block hashes to the senders of their transactions, only kept for the address index
+    block_senders: HashMap<BlockHash, Vec<Address>>,
     receipts: HashMap<BlockHash, HashMap<Index, Receipt>>,
     pub state_trie_nodes: NodeMap,
     // A storage trie for each hashed account address
@@ -33,6 +40,10 @@ pub struct StoreInner {
     pending_blocks: HashMap<BlockHash, Block>,
     // Stores invalid blocks and their latest valid ancestor
     invalid_ancestors: HashMap<BlockHash, BlockHash>,
+    // Stores the latest blocks that failed validation, along with the position they were added in
+    bad_blocks: HashMap<BlockHash, (u64, Block)>,
+    // Maps hashed addresses and storage keys to their preimages
+    preimages: HashMap<H256, Bytes>,
     // Stores current Snap Sate
     snap_state: SnapState,
 }
@@ -71,6 +82,16 @@ impl Store {
     }
 }
 
+impl StoreInner {
+    fn remove_address_transactions(&mut self, entries: Vec<AddressTransaction>) {
+        for entry in entries {
+            if let Some(transactions) = self.address_transactions.get_mut(&entry.address) {
+                transactions.remove(&(entry.block_number, entry.index, entry.block_hash));
+            }
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl StoreEngine for Store {
     async fn apply_updates(&self, update_batch: UpdateBatch) -> Result<(), StoreError> {
@@ -120,6 +141,8 @@ impl StoreEngine for Store {
             store.block_numbers.insert(hash, number);
         }
 
+        store.block_senders.extend(update_batch.senders);
+
         for (block_hash, receipts) in update_batch.receipts {
             for (index, receipt) in receipts.into_iter().enumerate() {
                 store
@@ -130,6 +153,8 @@ impl StoreEngine for Store {
             }
         }
 
+        store.preimages.extend(update_batch.preimages);
+
         Ok(())
     }
 
@@ -160,6 +185,7 @@ impl StoreEngine for Store {
         store.block_numbers.remove(&hash);
         store.headers.remove(&hash);
         store.bodies.remove(&hash);
+        store.block_senders.remove(&hash);
         Ok(())
     }
 
@@ -302,6 +328,49 @@ impl StoreEngine for Store {
             }))
     }
 
+    async fn get_block_senders(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Option<Vec<Address>>, StoreError> {
+        Ok(self.inner()?.block_senders.get(&block_hash).cloned())
+    }
+
+    async fn remove_address_transactions(
+        &self,
+        entries: Vec<AddressTransaction>,
+    ) -> Result<(), StoreError> {
+        self.inner()?.remove_address_transactions(entries);
+        Ok(())
+    }
+
+    async fn get_transactions_by_address(
+        &self,
+        address: Address,
+        cursor: Option<(BlockNumber, Index)>,
+        limit: usize,
+    ) -> Result<Vec<AddressTransaction>, StoreError> {
+        let store = self.inner()?;
+        let Some(transactions) = store.address_transactions.get(&address) else {
+            return Ok(Vec::new());
+        };
+        let end = cursor.unwrap_or((BlockNumber::MAX, Index::MAX));
+        Ok(transactions
+            .range(..(end.0, end.1, BlockHash::zero()))
+            .rev()
+            .filter(|((number, _, hash), _)| store.canonical_hashes.get(number) == Some(hash))
+            .take(limit)
+            .map(
+                |(&(block_number, index, block_hash), &transaction_hash)| AddressTransaction {
+                    address,
+                    block_number,
+                    block_hash,
+                    index,
+                    transaction_hash,
+                },
+            )
+            .collect())
+    }
+
     async fn add_receipt(
         &self,
         block_hash: BlockHash,
@@ -436,6 +505,7 @@ impl StoreEngine for Store {
         head_hash: BlockHash,
         safe: Option<BlockNumber>,
         finalized: Option<BlockNumber>,
+        address_transactions: AddressTransactionsUpdate,
     ) -> Result<(), StoreError> {
         let mut store = self.inner()?;
 
@@ -465,6 +535,18 @@ impl StoreEngine for Store {
 
         store.chain_data.latest_block_number.replace(head_number);
 
+        store.remove_address_transactions(address_transactions.removed);
+        for entry in address_transactions.added {
+            store
+                .address_transactions
+                .entry(entry.address)
+                .or_default()
+                .insert(
+                    (entry.block_number, entry.index, entry.block_hash),
+                    entry.transaction_hash,
+                );
+        }
+
         Ok(())
     }
 
@@ -568,6 +650,30 @@ impl StoreEngine for Store {
             .clone())
     }
 
+    async fn add_bad_block(&self, position: u64, block: Block) -> Result<(), StoreError> {
+        self.inner()?
+            .bad_blocks
+            .insert(block.hash(), (position, block));
+        Ok(())
+    }
+
+    async fn get_bad_blocks(&self) -> Result<Vec<(u64, Block)>, StoreError> {
+        Ok(self.inner()?.bad_blocks.values().cloned().collect())
+    }
+
+    async fn get_bad_block(&self, block_hash: BlockHash) -> Result<Option<Block>, StoreError> {
+        Ok(self
+            .inner()?
+            .bad_blocks
+            .get(&block_hash)
+            .map(|(_, block)| block.clone()))
+    }
+
+    async fn remove_bad_block(&self, block_hash: BlockHash) -> Result<(), StoreError> {
+        self.inner()?.bad_blocks.remove(&block_hash);
+        Ok(())
+    }
+
     async fn get_latest_valid_ancestor(
         &self,
         block: BlockHash,
@@ -619,6 +725,15 @@ impl StoreEngine for Store {
 
         Ok(())
     }
+
+    async fn add_preimages(&self, preimages: Vec<(H256, Bytes)>) -> Result<(), StoreError> {
+        self.inner()?.preimages.extend(preimages);
+        Ok(())
+    }
+
+    fn get_preimage(&self, hash: H256) -> Result<Option<Bytes>, StoreError> {
+        Ok(self.inner()?.preimages.get(&hash).cloned())
+    }
 }
 
 impl Debug for Store {
@@ -1,7 +1,14 @@
-use crate::{rlp::AccountCodeHashRLP, trie_db::rocksdb_locked::RocksDBLockedTrieDB};
+use crate::{
+    TrieScheme,
+    rlp::AccountCodeHashRLP,
+    trie_db::{
+        rocksdb_locked::RocksDBLockedTrieDB,
+        rocksdb_path::{self, RocksDBPathTrieDB, TrieHeadLock},
+    },
+};
 use bytes::Bytes;
 use ethrex_common::{
-    H256,
+    Address, H256,
     types::{
         Block, BlockBody, BlockHash, BlockHeader, BlockNumber, ChainConfig, Index, Receipt,
         Transaction,
@@ -9,14 +16,14 @@ use ethrex_common::{
 };
 use ethrex_trie::{Nibbles, NodeHash, Trie};
 use rocksdb::{
-    BlockBasedOptions, BoundColumnFamily, Cache, ColumnFamilyDescriptor, MultiThreaded,
-    OptimisticTransactionDB, Options, WriteBatchWithTransaction,
+    BlockBasedOptions, BoundColumnFamily, Cache, ColumnFamilyDescriptor, DBWithThreadMode,
+    MultiThreaded, Options, WriteBatch, checkpoint::Checkpoint,
 };
 use std::{collections::HashSet, path::Path, sync::Arc};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
-    STATE_TRIE_SEGMENTS, UpdateBatch,
+    AddressTransaction, AddressTransactionsUpdate, STATE_TRIE_SEGMENTS, UpdateBatch,
     api::StoreEngine,
     error::StoreError,
     rlp::{AccountCodeRLP, BlockBodyRLP, BlockHashRLP, BlockHeaderRLP, BlockRLP},
@@ -66,6 +73,23 @@ const CF_RECEIPTS: &str = "receipts";
 /// - [`Vec<u8>`] = `(block_number, block_hash, index).encode_to_vec()`
 const CF_TRANSACTION_LOCATIONS: &str = "transaction_locations";
 
+/// Address transactions column family: [`Vec<u8>`] => [`Vec<u8>`]
+/// - [`Vec<u8>`] = Composite key
+///    ```rust,no_run
+///     // let mut composite_key = Vec::with_capacity(68);
+///     // composite_key.extend_from_slice(address.as_bytes());
+///     // composite_key.extend_from_slice(&block_number.to_be_bytes());
+///     // composite_key.extend_from_slice(&index.to_be_bytes());
+///     // composite_key.extend_from_slice(block_hash.as_bytes());
+///    ```
+/// - [`Vec<u8>`] = `transaction_hash.as_bytes().to_vec()`
+const CF_ADDRESS_TRANSACTIONS: &str = "address_transactions";
+
+/// Block senders column family: [`Vec<u8>`] => [`Vec<u8>`]
+/// - [`Vec<u8>`] = `block_hash.as_bytes().to_vec()`
+/// - [`Vec<u8>`] = `senders.encode_to_vec()`, the senders of the block's transactions in order
+const CF_BLOCK_SENDERS: &str = "block_senders";
+
 /// Chain data column family: [`Vec<u8>`] => [`Vec<u8>`]
 /// - [`Vec<u8>`] = `Self::chain_data_key(ChainDataIndex::ChainConfig)`
 /// - [`Vec<u8>`] = `serde_json::to_string(chain_config)`
@@ -79,6 +103,10 @@ const CF_SNAP_STATE: &str = "snap_state";
 /// State trie nodes column family: [`NodeHash`] => [`Vec<u8>`]
 /// - [`NodeHash`] = `node_hash.as_ref()`
 /// - [`Vec<u8>`] = `node_data`
+///
+/// With the path scheme: [`Vec<u8>`] => [`Vec<u8>`]
+/// - [`Vec<u8>`] = `rocksdb_path::node_key(None, &path)`
+/// - [`Vec<u8>`] = `[node_hash.as_ref(), &node_data].concat()`
 const CF_STATE_TRIE_NODES: &str = "state_trie_nodes";
 
 /// Storage tries nodes column family: [`Vec<u8>`] => [`Vec<u8>`]
@@ -89,8 +117,39 @@ const CF_STATE_TRIE_NODES: &str = "state_trie_nodes";
 ///     // key.extend_from_slice(node_hash.as_ref());
 ///   ```
 /// - [`Vec<u8>`] = `node_data`
+///
+/// With the path scheme: [`Vec<u8>`] => [`Vec<u8>`]
+/// - [`Vec<u8>`] = `rocksdb_path::node_key(Some(address_hash), &path)`
+/// - [`Vec<u8>`] = `[node_hash.as_ref(), &node_data].concat()`
 const CF_STORAGE_TRIES_NODES: &str = "storage_tries_nodes";
 
+/// Trie nodes history column family, only used by the path scheme: [`Vec<u8>`] => [`Vec<u8>`]
+/// Holds the nodes replaced in recent blocks and those of the blocks that aren't canonical
+/// - [`Vec<u8>`] = Composite key
+///   ```rust,no_run
+///     // let mut key = vec![address_hash.is_some() as u8];
+///     // key.extend(rocksdb_path::node_key(address_hash, &path));
+///     // key.extend_from_slice(node_hash.as_ref());
+///     // key.extend_from_slice(&block_number.to_be_bytes());
+///   ```
+/// - [`Vec<u8>`] = `node_data`
+const CF_TRIE_NODES_HISTORY: &str = "trie_nodes_history";
+
+/// Trie history journal column family, only used by the path scheme: [`Vec<u8>`] => [`Vec<u8>`]
+/// - [`Vec<u8>`] = `rocksdb_path::journal_key(block_number, block_hash)`
+/// - [`Vec<u8>`] = `rocksdb_path::BlockJournal::encode_to_vec()`
+///
+/// The empty key holds the `journal_key` of the block whose state is stored at the path keys
+const CF_TRIE_HISTORY_JOURNAL: &str = "trie_history_journal";
+
+/// Column families of the path scheme
+const PATH_SCHEME_COLUMNS: rocksdb_path::PathSchemeColumns = rocksdb_path::PathSchemeColumns {
+    state: CF_STATE_TRIE_NODES,
+    storage: CF_STORAGE_TRIES_NODES,
+    history: CF_TRIE_NODES_HISTORY,
+    journal: CF_TRIE_HISTORY_JOURNAL,
+};
+
 /// Pending blocks column family: [`Vec<u8>`] => [`Vec<u8>`]
 /// - [`Vec<u8>`] = `BlockHashRLP::from(block.hash()).bytes().clone()`
 /// - [`Vec<u8>`] = `BlockRLP::from(block).bytes().clone()`
@@ -101,53 +160,31 @@ const CF_PENDING_BLOCKS: &str = "pending_blocks";
 /// - [`Vec<u8>`] = `BlockHashRLP::from(latest_valid).bytes().clone()`
 const CF_INVALID_ANCESTORS: &str = "invalid_ancestors";
 
+/// Bad blocks column family: [`Vec<u8>`] => [`Vec<u8>`]
+/// - [`Vec<u8>`] = `BlockHashRLP::from(block.hash()).bytes().clone()`
+/// - [`Vec<u8>`] = `(position, block).encode_to_vec()`, position being the order it was added in
+const CF_BAD_BLOCKS: &str = "bad_blocks";
+
+/// Preimages column family: [`Vec<u8>`] => [`Vec<u8>`]
+/// - [`Vec<u8>`] = `hash.as_bytes().to_vec()`, the keccak hash of an address or storage key
+/// - [`Vec<u8>`] = the address or storage key itself
+const CF_PREIMAGES: &str = "preimages";
+
 #[derive(Debug)]
 pub struct Store {
-    db: Arc<OptimisticTransactionDB<MultiThreaded>>,
+    db: Arc<DBWithThreadMode<MultiThreaded>>,
+    trie_scheme: TrieScheme,
+    trie_head_lock: TrieHeadLock,
 }
 
 impl Store {
-    pub fn new(path: &Path) -> Result<Self, StoreError> {
-        let mut db_options = Options::default();
+    /// Opens the database at the given path, creating it if it doesn't exist.
+    /// The trie scheme is only used when creating the database, existing ones keep theirs
+    pub fn new(path: &Path, trie_scheme: Option<TrieScheme>) -> Result<Self, StoreError> {
+        let mut db_options = Self::db_options();
         db_options.create_if_missing(true);
         db_options.create_missing_column_families(true);
 
-        let cache = Cache::new_lru_cache(4 * 1024 * 1024 * 1024); // 4GB cache
-
-        db_options.set_max_open_files(-1);
-        db_options.set_max_file_opening_threads(16);
-
-        db_options.set_max_background_jobs(8);
-
-        db_options.set_level_zero_file_num_compaction_trigger(2);
-        db_options.set_level_zero_slowdown_writes_trigger(10);
-        db_options.set_level_zero_stop_writes_trigger(16);
-        db_options.set_target_file_size_base(512 * 1024 * 1024); // 512MB
-        db_options.set_max_bytes_for_level_base(2 * 1024 * 1024 * 1024); // 2GB L1
-        db_options.set_max_bytes_for_level_multiplier(10.0);
-        db_options.set_level_compaction_dynamic_level_bytes(true);
-
-        db_options.set_db_write_buffer_size(1024 * 1024 * 1024); // 1GB
-        db_options.set_write_buffer_size(128 * 1024 * 1024); // 128MB
-        db_options.set_max_write_buffer_number(4);
-        db_options.set_min_write_buffer_number_to_merge(2);
-
-        db_options.set_wal_recovery_mode(rocksdb::DBRecoveryMode::PointInTime);
-        db_options.set_max_total_wal_size(2 * 1024 * 1024 * 1024); // 2GB
-        db_options.set_wal_ttl_seconds(3600);
-        db_options.set_wal_bytes_per_sync(32 * 1024 * 1024); // 32MB
-        db_options.set_bytes_per_sync(32 * 1024 * 1024); // 32MB
-        db_options.set_use_fsync(false); // fdatasync
-
-        db_options.set_enable_pipelined_write(true);
-        db_options.set_allow_concurrent_memtable_write(true);
-        db_options.set_enable_write_thread_adaptive_yield(true);
-        db_options.set_compaction_readahead_size(4 * 1024 * 1024); // 4MB
-        db_options.set_advise_random_on_open(false);
-
-        // db_options.enable_statistics();
-        // db_options.set_stats_dump_period_sec(600);
-
         // Current column families that the code expects
         let expected_column_families = vec![
             CF_CANONICAL_BLOCK_HASHES,
@@ -157,25 +194,31 @@ impl Store {
             CF_ACCOUNT_CODES,
             CF_RECEIPTS,
             CF_TRANSACTION_LOCATIONS,
+            CF_ADDRESS_TRANSACTIONS,
+            CF_BLOCK_SENDERS,
             CF_CHAIN_DATA,
             CF_SNAP_STATE,
             CF_STATE_TRIE_NODES,
             CF_STORAGE_TRIES_NODES,
             CF_PENDING_BLOCKS,
             CF_INVALID_ANCESTORS,
+            CF_BAD_BLOCKS,
+            CF_PREIMAGES,
+            CF_TRIE_NODES_HISTORY,
+            CF_TRIE_HISTORY_JOURNAL,
         ];
 
         // Get existing column families to know which ones to drop later
-        let existing_cfs =
-            match OptimisticTransactionDB::<MultiThreaded>::list_cf(&db_options, path) {
+        let (existing_cfs, is_new_db) =
+            match DBWithThreadMode::<MultiThreaded>::list_cf(&db_options, path) {
                 Ok(cfs) => {
                     info!("Found existing column families: {:?}", cfs);
-                    cfs
+                    (cfs, false)
                 }
                 Err(_) => {
                     // Database doesn't exist yet
                     info!("Database doesn't exist, will create with expected column families");
-                    vec!["default".to_string()]
+                    (vec!["default".to_string()], true)
                 }
             };
 
@@ -195,86 +238,14 @@ impl Store {
             }
         }
 
+        let cache = Cache::new_lru_cache(4 * 1024 * 1024 * 1024); // 4GB cache
         let mut cf_descriptors = Vec::new();
         for cf_name in &all_cfs_to_open {
-            let mut cf_opts = Options::default();
-
-            cf_opts.set_level_zero_file_num_compaction_trigger(4);
-            cf_opts.set_level_zero_slowdown_writes_trigger(20);
-            cf_opts.set_level_zero_stop_writes_trigger(36);
-
-            match cf_name.as_str() {
-                CF_HEADERS | CF_BODIES => {
-                    cf_opts.set_compression_type(rocksdb::DBCompressionType::Zstd);
-                    cf_opts.set_write_buffer_size(128 * 1024 * 1024); // 128MB
-                    cf_opts.set_max_write_buffer_number(4);
-                    cf_opts.set_target_file_size_base(256 * 1024 * 1024); // 256MB
-
-                    let mut block_opts = BlockBasedOptions::default();
-                    block_opts.set_block_cache(&cache);
-                    block_opts.set_block_size(32 * 1024); // 32KB blocks
-                    block_opts.set_cache_index_and_filter_blocks(true);
-                    cf_opts.set_block_based_table_factory(&block_opts);
-                }
-                CF_CANONICAL_BLOCK_HASHES | CF_BLOCK_NUMBERS => {
-                    cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
-                    cf_opts.set_write_buffer_size(64 * 1024 * 1024); // 64MB
-                    cf_opts.set_max_write_buffer_number(3);
-                    cf_opts.set_target_file_size_base(128 * 1024 * 1024); // 128MB
-
-                    let mut block_opts = BlockBasedOptions::default();
-                    block_opts.set_block_cache(&cache);
-                    block_opts.set_block_size(16 * 1024); // 16KB
-                    block_opts.set_bloom_filter(10.0, false);
-                    block_opts.set_cache_index_and_filter_blocks(true);
-                    cf_opts.set_block_based_table_factory(&block_opts);
-                }
-                CF_STATE_TRIE_NODES | CF_STORAGE_TRIES_NODES => {
-                    cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
-                    cf_opts.set_write_buffer_size(512 * 1024 * 1024); // 512MB
-                    cf_opts.set_max_write_buffer_number(6);
-                    cf_opts.set_min_write_buffer_number_to_merge(2);
-                    cf_opts.set_target_file_size_base(256 * 1024 * 1024); // 256MB
-                    cf_opts.set_memtable_prefix_bloom_ratio(0.2); // Bloom filter
-
-                    let mut block_opts = BlockBasedOptions::default();
-                    block_opts.set_block_size(16 * 1024); // 16KB
-                    block_opts.set_block_cache(&cache);
-                    block_opts.set_bloom_filter(10.0, false); // 10 bits per key
-                    block_opts.set_cache_index_and_filter_blocks(true);
-                    block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
-                    cf_opts.set_block_based_table_factory(&block_opts);
-                }
-                CF_RECEIPTS | CF_ACCOUNT_CODES => {
-                    cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
-                    cf_opts.set_write_buffer_size(128 * 1024 * 1024); // 128MB
-                    cf_opts.set_max_write_buffer_number(3);
-                    cf_opts.set_target_file_size_base(256 * 1024 * 1024); // 256MB
-
-                    let mut block_opts = BlockBasedOptions::default();
-                    block_opts.set_block_cache(&cache);
-                    block_opts.set_block_size(32 * 1024); // 32KB
-                    block_opts.set_block_cache(&cache);
-                    cf_opts.set_block_based_table_factory(&block_opts);
-                }
-                _ => {
-                    // Default for other CFs
-                    cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
-                    cf_opts.set_write_buffer_size(64 * 1024 * 1024); // 64MB
-                    cf_opts.set_max_write_buffer_number(3);
-                    cf_opts.set_target_file_size_base(128 * 1024 * 1024); // 128MB
-
-                    let mut block_opts = BlockBasedOptions::default();
-                    block_opts.set_block_size(16 * 1024);
-                    block_opts.set_block_cache(&cache);
-                    cf_opts.set_block_based_table_factory(&block_opts);
-                }
-            }
-
+            let cf_opts = Self::cf_options(cf_name, &cache);
             cf_descriptors.push(ColumnFamilyDescriptor::new(cf_name, cf_opts));
         }
 
-        let db = OptimisticTransactionDB::<MultiThreaded>::open_cf_descriptors(
+        let db = DBWithThreadMode::<MultiThreaded>::open_cf_descriptors(
             &db_options,
             path,
             cf_descriptors,
@@ -299,7 +270,300 @@ impl Store {
             }
         }
 
-        Ok(Self { db: Arc::new(db) })
+        let trie_scheme = Self::init_trie_scheme(&db, is_new_db, trie_scheme)?;
+
+        Ok(Self {
+            db: Arc::new(db),
+            trie_scheme,
+            trie_head_lock: TrieHeadLock::default(),
+        })
+    }
+
+    /// Opens the database of another running node as a secondary instance, which can only read.
+    /// The secondary instance keeps its own logs at `secondary_path`, which can't be shared with other
+    /// instances, and only sees the writes made by the primary after calling
+    /// [`StoreEngine::catch_up_with_primary`]
+    pub fn new_secondary(path: &Path, secondary_path: &Path) -> Result<Self, StoreError> {
+        let db_options = Self::db_options();
+        let cf_descriptors = Self::existing_cf_descriptors(&db_options, path)?;
+
+        let db = DBWithThreadMode::<MultiThreaded>::open_cf_descriptors_as_secondary(
+            &db_options,
+            path,
+            secondary_path,
+            cf_descriptors,
+        )
+        .map_err(|e| StoreError::Custom(format!("Failed to open RocksDB as secondary: {}", e)))?;
+
+        let trie_scheme = Self::read_trie_scheme(&db)?.unwrap_or_default();
+        info!(
+            "Opened database as secondary, using {:?} trie scheme",
+            trie_scheme
+        );
+
+        Ok(Self {
+            db: Arc::new(db),
+            trie_scheme,
+            trie_head_lock: TrieHeadLock::default(),
+        })
+    }
+
+    /// Opens an existing database, such as a checkpoint, without allowing writes to it
+    pub fn new_read_only(path: &Path) -> Result<Self, StoreError> {
+        let db_options = Self::db_options();
+        let cf_descriptors = Self::existing_cf_descriptors(&db_options, path)?;
+
+        let db = DBWithThreadMode::<MultiThreaded>::open_cf_descriptors_read_only(
+            &db_options,
+            path,
+            cf_descriptors,
+            false,
+        )
+        .map_err(|e| StoreError::Custom(format!("Failed to open RocksDB as read-only: {}", e)))?;
+
+        let trie_scheme = Self::read_trie_scheme(&db)?.unwrap_or_default();
+
+        Ok(Self {
+            db: Arc::new(db),
+            trie_scheme,
+            trie_head_lock: TrieHeadLock::default(),
+        })
+    }
+
+    /// Descriptors of the column families of an existing database, to open it without creating any
+    fn existing_cf_descriptors(
+        db_options: &Options,
+        path: &Path,
+    ) -> Result<Vec<ColumnFamilyDescriptor>, StoreError> {
+        let existing_cfs = DBWithThreadMode::<MultiThreaded>::list_cf(db_options, path)
+            .map_err(|e| StoreError::Custom(format!("Failed to open RocksDB: {}", e)))?;
+
+        let cache = Cache::new_lru_cache(4 * 1024 * 1024 * 1024); // 4GB cache
+        Ok(existing_cfs
+            .iter()
+            .filter(|cf_name| *cf_name != "default")
+            .map(|cf_name| ColumnFamilyDescriptor::new(cf_name, Self::cf_options(cf_name, &cache)))
+            .collect())
+    }
+
+    /// Options shared by primary and secondary instances
+    fn db_options() -> Options {
+        let mut db_options = Options::default();
+
+        db_options.set_max_open_files(-1);
+        db_options.set_max_file_opening_threads(16);
+
+        db_options.set_max_background_jobs(8);
+
+        db_options.set_level_zero_file_num_compaction_trigger(2);
+        db_options.set_level_zero_slowdown_writes_trigger(10);
+        db_options.set_level_zero_stop_writes_trigger(16);
+        db_options.set_target_file_size_base(512 * 1024 * 1024); // 512MB
+        db_options.set_max_bytes_for_level_base(2 * 1024 * 1024 * 1024); // 2GB L1
+        db_options.set_max_bytes_for_level_multiplier(10.0);
+        db_options.set_level_compaction_dynamic_level_bytes(true);
+
+        db_options.set_db_write_buffer_size(1024 * 1024 * 1024); // 1GB
+        db_options.set_write_buffer_size(128 * 1024 * 1024); // 128MB
+        db_options.set_max_write_buffer_number(4);
+        db_options.set_min_write_buffer_number_to_merge(2);
+
+        db_options.set_wal_recovery_mode(rocksdb::DBRecoveryMode::PointInTime);
+        db_options.set_max_total_wal_size(2 * 1024 * 1024 * 1024); // 2GB
+        db_options.set_wal_ttl_seconds(3600);
+        db_options.set_wal_bytes_per_sync(32 * 1024 * 1024); // 32MB
+        db_options.set_bytes_per_sync(32 * 1024 * 1024); // 32MB
+        db_options.set_use_fsync(false); // fdatasync
+
+        db_options.set_enable_pipelined_write(true);
+        db_options.set_allow_concurrent_memtable_write(true);
+        db_options.set_enable_write_thread_adaptive_yield(true);
+        db_options.set_compaction_readahead_size(4 * 1024 * 1024); // 4MB
+        db_options.set_advise_random_on_open(false);
+
+        // db_options.enable_statistics();
+        // db_options.set_stats_dump_period_sec(600);
+
+        db_options
+    }
+
+    /// Options of each column family, tuned to the data it holds
+    fn cf_options(cf_name: &str, cache: &Cache) -> Options {
+        let mut cf_opts = Options::default();
+
+        cf_opts.set_level_zero_file_num_compaction_trigger(4);
+        cf_opts.set_level_zero_slowdown_writes_trigger(20);
+        cf_opts.set_level_zero_stop_writes_trigger(36);
+
+        match cf_name {
+            CF_HEADERS | CF_BODIES => {
+                cf_opts.set_compression_type(rocksdb::DBCompressionType::Zstd);
+                cf_opts.set_write_buffer_size(128 * 1024 * 1024); // 128MB
+                cf_opts.set_max_write_buffer_number(4);
+                cf_opts.set_target_file_size_base(256 * 1024 * 1024); // 256MB
+
+                let mut block_opts = BlockBasedOptions::default();
+                block_opts.set_block_cache(cache);
+                block_opts.set_block_size(32 * 1024); // 32KB blocks
+                block_opts.set_cache_index_and_filter_blocks(true);
+                cf_opts.set_block_based_table_factory(&block_opts);
+            }
+            CF_CANONICAL_BLOCK_HASHES | CF_BLOCK_NUMBERS => {
+                cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+                cf_opts.set_write_buffer_size(64 * 1024 * 1024); // 64MB
+                cf_opts.set_max_write_buffer_number(3);
+                cf_opts.set_target_file_size_base(128 * 1024 * 1024); // 128MB
+
+                let mut block_opts = BlockBasedOptions::default();
+                block_opts.set_block_cache(cache);
+                block_opts.set_block_size(16 * 1024); // 16KB
+                block_opts.set_bloom_filter(10.0, false);
+                block_opts.set_cache_index_and_filter_blocks(true);
+                cf_opts.set_block_based_table_factory(&block_opts);
+            }
+            CF_STATE_TRIE_NODES | CF_STORAGE_TRIES_NODES => {
+                cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+                cf_opts.set_write_buffer_size(512 * 1024 * 1024); // 512MB
+                cf_opts.set_max_write_buffer_number(6);
+                cf_opts.set_min_write_buffer_number_to_merge(2);
+                cf_opts.set_target_file_size_base(256 * 1024 * 1024); // 256MB
+                cf_opts.set_memtable_prefix_bloom_ratio(0.2); // Bloom filter
+
+                let mut block_opts = BlockBasedOptions::default();
+                block_opts.set_block_size(16 * 1024); // 16KB
+                block_opts.set_block_cache(cache);
+                block_opts.set_bloom_filter(10.0, false); // 10 bits per key
+                block_opts.set_cache_index_and_filter_blocks(true);
+                block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
+                cf_opts.set_block_based_table_factory(&block_opts);
+            }
+            CF_RECEIPTS | CF_ACCOUNT_CODES => {
+                cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+                cf_opts.set_write_buffer_size(128 * 1024 * 1024); // 128MB
+                cf_opts.set_max_write_buffer_number(3);
+                cf_opts.set_target_file_size_base(256 * 1024 * 1024); // 256MB
+
+                let mut block_opts = BlockBasedOptions::default();
+                block_opts.set_block_cache(cache);
+                block_opts.set_block_size(32 * 1024); // 32KB
+                block_opts.set_block_cache(cache);
+                cf_opts.set_block_based_table_factory(&block_opts);
+            }
+            _ => {
+                // Default for other CFs
+                cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+                cf_opts.set_write_buffer_size(64 * 1024 * 1024); // 64MB
+                cf_opts.set_max_write_buffer_number(3);
+                cf_opts.set_target_file_size_base(128 * 1024 * 1024); // 128MB
+
+                let mut block_opts = BlockBasedOptions::default();
+                block_opts.set_block_size(16 * 1024);
+                block_opts.set_block_cache(cache);
+                cf_opts.set_block_based_table_factory(&block_opts);
+            }
+        }
+
+        cf_opts
+    }
+
+    /// Returns the trie scheme of the database, persisting the requested one if it's new.
+    /// Databases created before the scheme was persisted always use the hash scheme
+    fn init_trie_scheme(
+        db: &DBWithThreadMode<MultiThreaded>,
+        is_new_db: bool,
+        requested: Option<TrieScheme>,
+    ) -> Result<TrieScheme, StoreError> {
+        let stored = Self::read_trie_scheme(db)?;
+        let trie_scheme = match stored {
+            Some(scheme) => scheme,
+            None if is_new_db => requested.unwrap_or_default(),
+            None => TrieScheme::Hash,
+        };
+        if stored.is_none() {
+            let value = match trie_scheme {
+                TrieScheme::Hash => [0],
+                TrieScheme::Path => [1],
+            };
+            let cf = db
+                .cf_handle(CF_CHAIN_DATA)
+                .ok_or_else(|| StoreError::Custom("Column family not found".to_string()))?;
+            db.put_cf(&cf, Self::chain_data_key(ChainDataIndex::TrieScheme), value)?;
+        }
+        if requested.is_some_and(|requested| requested != trie_scheme) {
+            warn!(
+                "Ignoring requested trie scheme {:?}, the database was created with the {:?} scheme",
+                requested, trie_scheme
+            );
+        }
+        info!("Using {:?} trie scheme", trie_scheme);
+        Ok(trie_scheme)
+    }
+
+    /// Returns the trie scheme persisted in the database, if any
+    fn read_trie_scheme(
+        db: &DBWithThreadMode<MultiThreaded>,
+    ) -> Result<Option<TrieScheme>, StoreError> {
+        let cf = db
+            .cf_handle(CF_CHAIN_DATA)
+            .ok_or_else(|| StoreError::Custom("Column family not found".to_string()))?;
+        let key = Self::chain_data_key(ChainDataIndex::TrieScheme);
+        Ok(match db.get_cf(&cf, key)?.as_deref() {
+            Some([1]) => Some(TrieScheme::Path),
+            Some(_) => Some(TrieScheme::Hash),
+            None => None,
+        })
+    }
+
+    /// Adds to the batch the write of the state of the given block at the path keys, reverting
+    /// the blocks of the trie head that aren't its ancestors.
+    /// The trie head lock must be held until the batch is written
+    fn move_trie_head(
+        db: &Arc<DBWithThreadMode<MultiThreaded>>,
+        batch: &mut WriteBatch,
+        head: (BlockNumber, BlockHash),
+    ) -> Result<(), StoreError> {
+        let Some(trie_head) = rocksdb_path::read_trie_head(db, PATH_SCHEME_COLUMNS)? else {
+            // The state written before the first block is made canonical is the one of that
+            // block, such as the genesis state
+            return rocksdb_path::set_trie_head(db, PATH_SCHEME_COLUMNS, batch, head);
+        };
+        if trie_head == head {
+            return Ok(());
+        }
+
+        let [cf_headers] = open_cfs(db, [CF_HEADERS])?;
+        let parent = |(number, hash): (BlockNumber, BlockHash)| {
+            let header = db
+                .get_cf(&cf_headers, BlockHashRLP::from(hash).bytes())?
+                .map(|bytes| BlockHeaderRLP::from_bytes(bytes).to())
+                .transpose()?
+                .ok_or_else(|| StoreError::Custom(format!("Missing header of block {hash:#x}")))?;
+            let number = number.checked_sub(1).ok_or_else(|| {
+                StoreError::Custom(
+                    "The trie head and the new head have no common ancestor".to_string(),
+                )
+            })?;
+            Ok::<_, StoreError>((number, header.parent_hash))
+        };
+        // Blocks from the trie head and the new head down to their latest common ancestor
+        let (mut old, mut new) = (trie_head, head);
+        let (mut reverted, mut applied) = (Vec::new(), Vec::new());
+        while new.0 > old.0 {
+            applied.push(new);
+            new = parent(new)?;
+        }
+        while old.0 > new.0 {
+            reverted.push(old);
+            old = parent(old)?;
+        }
+        while old.1 != new.1 {
+            reverted.push(old);
+            old = parent(old)?;
+            applied.push(new);
+            new = parent(new)?;
+        }
+        applied.reverse();
+        rocksdb_path::move_trie_head(db, PATH_SCHEME_COLUMNS, batch, &reverted, old, &applied)
     }
 
     // Helper method to get column family handle
@@ -370,7 +634,7 @@ impl Store {
         let db = self.db.clone();
 
         tokio::task::spawn_blocking(move || {
-            let mut batch = WriteBatchWithTransaction::default();
+            let mut batch = WriteBatch::default();
 
             for (cf_name, key, value) in batch_ops {
                 let cf = db.cf_handle(&cf_name).ok_or_else(|| {
@@ -386,6 +650,16 @@ impl Store {
         .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
     }
 
+    // Helper method to encode an address transactions composite key
+    fn address_transaction_key(entry: &AddressTransaction) -> Vec<u8> {
+        let mut key = Vec::with_capacity(68);
+        key.extend_from_slice(entry.address.as_bytes());
+        key.extend_from_slice(&entry.block_number.to_be_bytes());
+        key.extend_from_slice(&entry.index.to_be_bytes());
+        key.extend_from_slice(entry.block_hash.as_bytes());
+        key
+    }
+
     // Helper method to encode ChainDataIndex as key
     fn chain_data_key(index: ChainDataIndex) -> Vec<u8> {
         (index as u8).encode_to_vec()
@@ -441,6 +715,8 @@ impl Store {
 impl StoreEngine for Store {
     async fn apply_updates(&self, update_batch: UpdateBatch) -> Result<(), StoreError> {
         let db = self.db.clone();
+        let trie_scheme = self.trie_scheme;
+        let trie_head_lock = self.trie_head_lock.clone();
 
         tokio::task::spawn_blocking(move || {
             let [
@@ -452,6 +728,8 @@ impl StoreEngine for Store {
                 cf_tx_locations,
                 cf_headers,
                 cf_bodies,
+                cf_preimages,
+                cf_block_senders,
             ] = open_cfs(
                 &db,
                 [
@@ -463,23 +741,71 @@ impl StoreEngine for Store {
                     CF_TRANSACTION_LOCATIONS,
                     CF_HEADERS,
                     CF_BODIES,
+                    CF_PREIMAGES,
+                    CF_BLOCK_SENDERS,
                 ],
             )?;
 
             let _span = tracing::trace_span!("Block DB update").entered();
-            let mut batch = WriteBatchWithTransaction::default();
-
-            for (node_hash, node_data) in update_batch.account_updates {
-                batch.put_cf(&cf_state, node_hash.as_ref(), node_data);
-            }
+            // Writes at the trie head read its journal, which must not change until they're done
+            let _trie_head_guard = (trie_scheme == TrieScheme::Path)
+                .then(|| trie_head_lock.lock())
+                .transpose()
+                .map_err(|_| StoreError::LockError)?;
+            let mut batch = WriteBatch::default();
+
+            match trie_scheme {
+                TrieScheme::Hash => {
+                    for (node_hash, node_data) in update_batch.account_updates {
+                        batch.put_cf(&cf_state, node_hash.as_ref(), node_data);
+                    }
 
-            for (address_hash, storage_updates) in update_batch.storage_updates {
-                for (node_hash, node_data) in storage_updates {
-                    // Key: address_hash + node_hash
-                    let mut key = Vec::with_capacity(64);
-                    key.extend_from_slice(address_hash.as_bytes());
-                    key.extend_from_slice(node_hash.as_ref());
-                    batch.put_cf(&cf_storage, key, node_data);
+                    for (address_hash, storage_updates) in update_batch.storage_updates {
+                        for (node_hash, node_data) in storage_updates {
+                            // Key: address_hash + node_hash
+                            let mut key = Vec::with_capacity(64);
+                            key.extend_from_slice(address_hash.as_bytes());
+                            key.extend_from_slice(node_hash.as_ref());
+                            batch.put_cf(&cf_storage, key, node_data);
+                        }
+                    }
+                }
+                TrieScheme::Path => {
+                    // Nodes are only written at their paths once their block is canonical
+                    let blocks: Vec<_> = update_batch
+                        .blocks
+                        .iter()
+                        .map(|block| (block.header.number, block.hash()))
+                        .collect();
+                    let mut tries = vec![(None, update_batch.account_updates)];
+                    tries.extend(
+                        update_batch
+                            .storage_updates
+                            .into_iter()
+                            .map(|(address_hash, nodes)| (Some(address_hash), nodes)),
+                    );
+                    if blocks.is_empty() {
+                        let tries = tries
+                            .into_iter()
+                            .map(|(address_hash, nodes)| {
+                                Ok((address_hash, rocksdb_path::nodes_with_paths(nodes)?))
+                            })
+                            .collect::<Result<_, StoreError>>()?;
+                        rocksdb_path::write_at_trie_head(
+                            &db,
+                            PATH_SCHEME_COLUMNS,
+                            &mut batch,
+                            tries,
+                        )?;
+                    } else {
+                        rocksdb_path::store_block_nodes(
+                            &db,
+                            PATH_SCHEME_COLUMNS,
+                            &mut batch,
+                            &blocks,
+                            tries,
+                        )?;
+                    }
                 }
             }
 
@@ -523,6 +849,18 @@ impl StoreEngine for Store {
                 batch.put_cf(&cf_codes, code_key, code_value);
             }
 
+            for (hash, preimage) in update_batch.preimages {
+                batch.put_cf(&cf_preimages, hash.as_bytes(), preimage);
+            }
+
+            for (block_hash, senders) in update_batch.senders {
+                batch.put_cf(
+                    &cf_block_senders,
+                    block_hash.as_bytes(),
+                    senders.encode_to_vec(),
+                );
+            }
+
             // Single write operation
             db.write(batch)
                 .map_err(|e| StoreError::Custom(format!("RocksDB batch write error: {}", e)))
@@ -537,7 +875,7 @@ impl StoreEngine for Store {
         let db = self.db.clone();
 
         tokio::task::spawn_blocking(move || {
-            let mut batch = WriteBatchWithTransaction::default();
+            let mut batch = WriteBatch::default();
 
             let [cf_headers, cf_bodies, cf_block_numbers, cf_tx_locations] = open_cfs(
                 &db,
@@ -646,19 +984,26 @@ impl StoreEngine for Store {
     }
 
     async fn remove_block(&self, block_number: BlockNumber) -> Result<(), StoreError> {
-        let mut batch = WriteBatchWithTransaction::default();
+        let mut batch = WriteBatch::default();
 
         let Some(hash) = self.get_canonical_block_hash_sync(block_number)? else {
             return Ok(());
         };
 
-        let [cf_canonical, cf_bodies, cf_headers, cf_block_numbers] = open_cfs(
+        let [
+            cf_canonical,
+            cf_bodies,
+            cf_headers,
+            cf_block_numbers,
+            cf_block_senders,
+        ] = open_cfs(
             &self.db,
             [
                 CF_CANONICAL_BLOCK_HASHES,
                 CF_BODIES,
                 CF_HEADERS,
                 CF_BLOCK_NUMBERS,
+                CF_BLOCK_SENDERS,
             ],
         )?;
 
@@ -666,6 +1011,7 @@ impl StoreEngine for Store {
         batch.delete_cf(&cf_bodies, hash.as_bytes());
         batch.delete_cf(&cf_headers, hash.as_bytes());
         batch.delete_cf(&cf_block_numbers, hash.as_bytes());
+        batch.delete_cf(&cf_block_senders, hash.as_bytes());
 
         self.db
             .write(batch)
@@ -839,6 +1185,105 @@ impl StoreEngine for Store {
         .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
     }
 
+    async fn get_block_senders(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Option<Vec<Address>>, StoreError> {
+        self.read_async(CF_BLOCK_SENDERS, block_hash.as_bytes().to_vec())
+            .await?
+            .map(|bytes| Vec::<Address>::decode(&bytes))
+            .transpose()
+            .map_err(StoreError::from)
+    }
+
+    async fn remove_address_transactions(
+        &self,
+        entries: Vec<AddressTransaction>,
+    ) -> Result<(), StoreError> {
+        let db = self.db.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let [cf] = open_cfs(&db, [CF_ADDRESS_TRANSACTIONS])?;
+            let mut batch = WriteBatch::default();
+            for entry in &entries {
+                batch.delete_cf(&cf, Self::address_transaction_key(entry));
+            }
+            db.write(batch)
+                .map_err(|e| StoreError::Custom(format!("RocksDB batch write error: {}", e)))
+        })
+        .await
+        .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
+    }
+
+    async fn get_transactions_by_address(
+        &self,
+        address: Address,
+        cursor: Option<(BlockNumber, Index)>,
+        limit: usize,
+    ) -> Result<Vec<AddressTransaction>, StoreError> {
+        let db = self.db.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let [cf_address_transactions, cf_canonical] =
+                open_cfs(&db, [CF_ADDRESS_TRANSACTIONS, CF_CANONICAL_BLOCK_HASHES])?;
+
+            // Keys of entries at the cursor position are longer than the seek key so they are skipped
+            let (end_number, end_index) = cursor.unwrap_or((BlockNumber::MAX, Index::MAX));
+            let mut seek_key = Vec::with_capacity(68);
+            seek_key.extend_from_slice(address.as_bytes());
+            seek_key.extend_from_slice(&end_number.to_be_bytes());
+            seek_key.extend_from_slice(&end_index.to_be_bytes());
+            if cursor.is_none() {
+                seek_key.extend_from_slice(&[u8::MAX; 32]);
+            }
+
+            let iter = db.iterator_cf(
+                &cf_address_transactions,
+                rocksdb::IteratorMode::From(&seek_key, rocksdb::Direction::Reverse),
+            );
+            let mut transactions = Vec::new();
+            for item in iter {
+                let (key, value) = item?;
+                if transactions.len() >= limit || !key.starts_with(address.as_bytes()) {
+                    break;
+                }
+                if key.len() != 68 || value.len() != 32 {
+                    return Err(StoreError::Custom(
+                        "Invalid address transaction entry".to_string(),
+                    ));
+                }
+                let block_number =
+                    BlockNumber::from_be_bytes(key[20..28].try_into().map_err(|_| {
+                        StoreError::Custom("Invalid BlockNumber bytes".to_string())
+                    })?);
+                let index = Index::from_be_bytes(
+                    key[28..36]
+                        .try_into()
+                        .map_err(|_| StoreError::Custom("Invalid Index bytes".to_string()))?,
+                );
+                let block_hash = BlockHash::from_slice(&key[36..68]);
+
+                let canonical_hash = db
+                    .get_cf(&cf_canonical, block_number.to_le_bytes())?
+                    .and_then(|bytes| BlockHashRLP::from_bytes(bytes).to().ok());
+                if canonical_hash != Some(block_hash) {
+                    continue;
+                }
+
+                transactions.push(AddressTransaction {
+                    address,
+                    block_number,
+                    block_hash,
+                    index,
+                    transaction_hash: H256::from_slice(&value),
+                });
+            }
+            Ok(transactions)
+        })
+        .await
+        .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
+    }
+
     async fn add_receipt(
         &self,
         block_hash: BlockHash,
@@ -896,7 +1341,7 @@ impl StoreEngine for Store {
                 .ok_or_else(|| StoreError::Custom("Column family not found".to_string()))?;
 
             let mut iter = db.iterator_cf(&cf, rocksdb::IteratorMode::Start);
-            let mut batch = WriteBatchWithTransaction::default();
+            let mut batch = WriteBatch::default();
 
             while let Some(Ok((key, _))) = iter.next() {
                 batch.delete_cf(&cf, key);
@@ -1072,6 +1517,15 @@ impl StoreEngine for Store {
         hashed_address: H256,
         storage_root: H256,
     ) -> Result<Trie, StoreError> {
+        if self.trie_scheme == TrieScheme::Path {
+            let db = RocksDBPathTrieDB::new(
+                self.db.clone(),
+                PATH_SCHEME_COLUMNS,
+                Some(hashed_address),
+                self.trie_head_lock.clone(),
+            )?;
+            return Ok(Trie::open(Box::new(db), storage_root));
+        }
         let db = Box::new(RocksDBTrieDB::new(
             self.db.clone(),
             CF_STORAGE_TRIES_NODES,
@@ -1081,6 +1535,15 @@ impl StoreEngine for Store {
     }
 
     fn open_state_trie(&self, state_root: H256) -> Result<Trie, StoreError> {
+        if self.trie_scheme == TrieScheme::Path {
+            let db = RocksDBPathTrieDB::new(
+                self.db.clone(),
+                PATH_SCHEME_COLUMNS,
+                None,
+                self.trie_head_lock.clone(),
+            )?;
+            return Ok(Trie::open(Box::new(db), state_root));
+        }
         let db = Box::new(RocksDBTrieDB::new(
             self.db.clone(),
             CF_STATE_TRIE_NODES,
@@ -1090,6 +1553,10 @@ impl StoreEngine for Store {
     }
 
     fn open_locked_state_trie(&self, state_root: H256) -> Result<Trie, StoreError> {
+        // Path scheme reads check the hash of every node, so they don't need a snapshot
+        if self.trie_scheme == TrieScheme::Path {
+            return self.open_state_trie(state_root);
+        }
         let db = RocksDBLockedTrieDB::new(self.db.clone(), CF_STATE_TRIE_NODES, None)?;
         Ok(Trie::open(Box::new(db), state_root))
     }
@@ -1099,6 +1566,9 @@ impl StoreEngine for Store {
         hashed_address: H256,
         storage_root: H256,
     ) -> Result<Trie, StoreError> {
+        if self.trie_scheme == TrieScheme::Path {
+            return self.open_storage_trie(hashed_address, storage_root);
+        }
         let db = RocksDBLockedTrieDB::new(
             self.db.clone(),
             CF_STORAGE_TRIES_NODES,
@@ -1107,6 +1577,75 @@ impl StoreEngine for Store {
         Ok(Trie::open(Box::new(db), storage_root))
     }
 
+    fn trie_scheme(&self) -> TrieScheme {
+        self.trie_scheme
+    }
+
+    async fn catch_up_with_primary(&self) -> Result<(), StoreError> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            db.try_catch_up_with_primary()
+                .map_err(|e| StoreError::Custom(format!("Failed to catch up with primary: {}", e)))
+        })
+        .await
+        .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
+    }
+
+    async fn create_checkpoint(&self, path: &Path) -> Result<BlockHeader, StoreError> {
+        let db = self.db.clone();
+        let checkpoint_path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            Checkpoint::new(&db)?.create_checkpoint(&checkpoint_path)
+        })
+        .await
+        .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))??;
+
+        // Blocks could have been added while the checkpoint was being created,
+        // so its head is read from the checkpoint itself
+        let checkpoint = Self::new_read_only(path)?;
+        let number = checkpoint
+            .get_latest_block_number()
+            .await?
+            .ok_or(StoreError::MissingLatestBlockNumber)?;
+        checkpoint.get_block_header(number)?.ok_or_else(|| {
+            StoreError::Custom("Checkpoint head block header is missing".to_string())
+        })
+    }
+
+    async fn write_trie_nodes_at_paths(
+        &self,
+        hashed_address: Option<H256>,
+        nodes: Vec<(Nibbles, NodeHash, Vec<u8>)>,
+        block: (BlockNumber, BlockHash),
+    ) -> Result<(), StoreError> {
+        if self.trie_scheme != TrieScheme::Path {
+            return Err(StoreError::Custom(
+                "Trie nodes can only be written by path with the path scheme".to_string(),
+            ));
+        }
+        let db = self.db.clone();
+        let trie_head_lock = self.trie_head_lock.clone();
+        tokio::task::spawn_blocking(move || {
+            let cf = db
+                .cf_handle(PATH_SCHEME_COLUMNS.nodes(hashed_address.is_some()))
+                .ok_or_else(|| StoreError::Custom("Column family not found".to_string()))?;
+            let _trie_head_guard = trie_head_lock.lock().map_err(|_| StoreError::LockError)?;
+            let mut batch = WriteBatch::default();
+            for (path, node_hash, node_data) in nodes {
+                batch.put_cf(
+                    &cf,
+                    rocksdb_path::node_key(hashed_address, path.as_ref()),
+                    [node_hash.as_ref(), &node_data].concat(),
+                );
+            }
+            rocksdb_path::set_trie_head(&db, PATH_SCHEME_COLUMNS, &mut batch, block)?;
+            db.write(batch)
+                .map_err(|e| StoreError::Custom(format!("RocksDB batch write error: {}", e)))
+        })
+        .await
+        .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
+    }
+
     async fn forkchoice_update(
         &self,
         new_canonical_blocks: Option<Vec<(BlockNumber, BlockHash)>>,
@@ -1114,16 +1653,35 @@ impl StoreEngine for Store {
         head_hash: BlockHash,
         safe: Option<BlockNumber>,
         finalized: Option<BlockNumber>,
+        address_transactions: AddressTransactionsUpdate,
     ) -> Result<(), StoreError> {
         // Get current latest block number to know what to clean up
         let latest = self.get_latest_block_number().await?.unwrap_or(0);
         let db = self.db.clone();
+        let trie_scheme = self.trie_scheme;
+        let trie_head_lock = self.trie_head_lock.clone();
 
         tokio::task::spawn_blocking(move || {
-            let mut batch = WriteBatchWithTransaction::default();
+            let mut batch = WriteBatch::default();
+
+            // The trie head is moved in the same batch that makes its block canonical
+            let _trie_head_guard = match trie_scheme {
+                TrieScheme::Path => {
+                    let guard = trie_head_lock.lock().map_err(|_| StoreError::LockError)?;
+                    Self::move_trie_head(&db, &mut batch, (head_number, head_hash))?;
+                    Some(guard)
+                }
+                TrieScheme::Hash => None,
+            };
 
-            let [cf_canonical, cf_chain_data] =
-                open_cfs(&db, [CF_CANONICAL_BLOCK_HASHES, CF_CHAIN_DATA])?;
+            let [cf_canonical, cf_chain_data, cf_address_transactions] = open_cfs(
+                &db,
+                [
+                    CF_CANONICAL_BLOCK_HASHES,
+                    CF_CHAIN_DATA,
+                    CF_ADDRESS_TRANSACTIONS,
+                ],
+            )?;
 
             // Update canonical block hashes
             if let Some(canonical_blocks) = new_canonical_blocks {
@@ -1163,6 +1721,21 @@ impl StoreEngine for Store {
                 );
             }
 
+            // Update the address-indexed transaction history
+            for entry in &address_transactions.removed {
+                batch.delete_cf(
+                    &cf_address_transactions,
+                    Self::address_transaction_key(entry),
+                );
+            }
+            for entry in &address_transactions.added {
+                batch.put_cf(
+                    &cf_address_transactions,
+                    Self::address_transaction_key(entry),
+                    entry.transaction_hash.as_bytes(),
+                );
+            }
+
             db.write(batch)
                 .map_err(|e| StoreError::Custom(format!("RocksDB batch write error: {}", e)))
         })
@@ -1318,6 +1891,57 @@ impl StoreEngine for Store {
         self.write_async(CF_INVALID_ANCESTORS, key, value).await
     }
 
+    async fn add_bad_block(&self, position: u64, block: Block) -> Result<(), StoreError> {
+        let hash_key = BlockHashRLP::from(block.hash()).bytes().clone();
+        let value = (position, block).encode_to_vec();
+        self.write_async(CF_BAD_BLOCKS, hash_key, value).await
+    }
+
+    async fn get_bad_block(&self, block_hash: BlockHash) -> Result<Option<Block>, StoreError> {
+        let hash_key = BlockHashRLP::from(block_hash).bytes().clone();
+
+        self.read_async(CF_BAD_BLOCKS, hash_key)
+            .await?
+            .map(|bytes| <(u64, Block)>::decode(bytes.as_slice()).map(|(_, block)| block))
+            .transpose()
+            .map_err(StoreError::from)
+    }
+
+    async fn get_bad_blocks(&self) -> Result<Vec<(u64, Block)>, StoreError> {
+        let db = self.db.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let cf = db
+                .cf_handle(CF_BAD_BLOCKS)
+                .ok_or_else(|| StoreError::Custom("Column family not found".to_string()))?;
+
+            db.iterator_cf(&cf, rocksdb::IteratorMode::Start)
+                .map(|entry| {
+                    let (_, value) = entry
+                        .map_err(|e| StoreError::Custom(format!("RocksDB read error: {}", e)))?;
+                    <(u64, Block)>::decode(&value).map_err(StoreError::from)
+                })
+                .collect()
+        })
+        .await
+        .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
+    }
+
+    async fn remove_bad_block(&self, block_hash: BlockHash) -> Result<(), StoreError> {
+        let db = self.db.clone();
+        let hash_key = BlockHashRLP::from(block_hash).bytes().clone();
+
+        tokio::task::spawn_blocking(move || {
+            let cf = db
+                .cf_handle(CF_BAD_BLOCKS)
+                .ok_or_else(|| StoreError::Custom("Column family not found".to_string()))?;
+            db.delete_cf(&cf, hash_key)
+                .map_err(|e| StoreError::Custom(format!("RocksDB delete error: {}", e)))
+        })
+        .await
+        .map_err(|e| StoreError::Custom(format!("Task panicked: {}", e)))?
+    }
+
     async fn get_latest_valid_ancestor(
         &self,
         block: BlockHash,
@@ -1392,11 +2016,31 @@ impl StoreEngine for Store {
 
         self.write_batch_async(batch_ops).await
     }
+
+    async fn add_preimages(&self, preimages: Vec<(H256, Bytes)>) -> Result<(), StoreError> {
+        let batch_ops = preimages
+            .into_iter()
+            .map(|(hash, preimage)| {
+                (
+                    CF_PREIMAGES.to_string(),
+                    hash.as_bytes().to_vec(),
+                    preimage.to_vec(),
+                )
+            })
+            .collect();
+        self.write_batch_async(batch_ops).await
+    }
+
+    fn get_preimage(&self, hash: H256) -> Result<Option<Bytes>, StoreError> {
+        Ok(self
+            .read_sync(CF_PREIMAGES, hash.as_bytes())?
+            .map(Bytes::from))
+    }
 }
 
 /// Open column families
 fn open_cfs<'a, const N: usize>(
-    db: &'a Arc<OptimisticTransactionDB<MultiThreaded>>,
+    db: &'a Arc<DBWithThreadMode<MultiThreaded>>,
     names: [&str; N],
 ) -> Result<[Arc<BoundColumnFamily<'a>>; N], StoreError> {
     let mut handles = Vec::with_capacity(N);
@@ -2,3 +2,5 @@
 pub mod rocksdb;
 #[cfg(feature = "rocksdb")]
 pub mod rocksdb_locked;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb_path;
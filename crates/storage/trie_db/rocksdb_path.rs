@@ -0,0 +1,1233 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use ethrex_common::{
+    H256,
+    types::{BlockHash, BlockNumber},
+};
+use ethrex_rlp::{
+    decode::RLPDecode,
+    encode::RLPEncode,
+    error::RLPDecodeError,
+    structs::{Decoder, Encoder},
+};
+use ethrex_trie::{Nibbles, Node, NodeHash, NodeRef, TrieDB, TrieNode, error::TrieError};
+use rocksdb::{
+    BoundColumnFamily, DBWithThreadMode, Direction, IteratorMode, MultiThreaded, WriteBatch,
+};
+
+use crate::error::StoreError;
+
+/// Amount of blocks for which the nodes replaced in the tries are kept, allowing to read their
+/// states and to revert them on reorgs
+pub const TRIE_HISTORY_BLOCKS: u64 = 128;
+
+/// Trie node along with its path from the root of its trie
+pub type PathTrieNode = (Nibbles, NodeHash, Vec<u8>);
+
+/// Lock held from reading the trie head and its journal until the batch updating them is written,
+/// so concurrent writes at the trie head don't drop each other's changes
+pub type TrieHeadLock = Arc<Mutex<()>>;
+
+/// Key of the journal column family holding the block whose state is stored at the path keys.
+/// It sorts before the journals of every block
+const TRIE_HEAD_KEY: &[u8] = &[];
+
+/// Column families used by the path scheme
+#[derive(Debug, Clone, Copy)]
+pub struct PathSchemeColumns {
+    /// Node of each path of the state trie in the state of the trie head
+    pub state: &'static str,
+    /// Node of each path of the storage tries in the state of the trie head
+    pub storage: &'static str,
+    /// Nodes of recent blocks that aren't at their path, either because they were replaced or
+    /// because their block isn't canonical
+    pub history: &'static str,
+    /// Journal of the changes made on behalf of each recent block, along with the trie head
+    pub journal: &'static str,
+}
+
+impl PathSchemeColumns {
+    /// Column family of the nodes of the state trie or of the storage tries
+    pub fn nodes(&self, is_storage: bool) -> &'static str {
+        if is_storage { self.storage } else { self.state }
+    }
+}
+
+/// Changes to the trie nodes made on behalf of a block, which allow reverting them if the block
+/// stops being canonical and pruning the history they added once the block gets old
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BlockJournal {
+    /// Whether the state of the block was stored, which isn't the case for the blocks of a
+    /// batch other than the last one
+    pub has_state: bool,
+    /// History keys of the nodes committed by the block, written at their paths once it's canonical
+    pub nodes: Vec<Bytes>,
+    /// Tagged node keys written while the block was canonical along with their previous values,
+    /// empty if they had none
+    pub undo: Vec<(Bytes, Bytes)>,
+    /// History keys of the nodes replaced or removed while the block was canonical
+    pub replaced: Vec<Bytes>,
+    /// Hashed addresses of the accounts whose storage tries were emptied or removed by the block,
+    /// whose nodes are removed once it's canonical
+    pub cleared: Vec<H256>,
+}
+
+impl RLPEncode for BlockJournal {
+    fn encode(&self, buf: &mut dyn bytes::BufMut) {
+        Encoder::new(buf)
+            .encode_field(&self.has_state)
+            .encode_field(&self.nodes)
+            .encode_field(&self.undo)
+            .encode_field(&self.replaced)
+            .encode_field(&self.cleared)
+            .finish();
+    }
+}
+
+impl RLPDecode for BlockJournal {
+    fn decode_unfinished(rlp: &[u8]) -> Result<(Self, &[u8]), RLPDecodeError> {
+        let decoder = Decoder::new(rlp)?;
+        let (has_state, decoder) = decoder.decode_field("has_state")?;
+        let (nodes, decoder) = decoder.decode_field("nodes")?;
+        let (undo, decoder) = decoder.decode_field("undo")?;
+        let (replaced, decoder) = decoder.decode_field("replaced")?;
+        let (cleared, decoder) = decoder.decode_field("cleared")?;
+        let journal = BlockJournal {
+            has_state,
+            nodes,
+            undo,
+            replaced,
+            cleared,
+        };
+        Ok((journal, decoder.finish()?))
+    }
+}
+
+/// RocksDB implementation for the TrieDB trait storing nodes by their path instead of their hash.
+/// Each path holds the node of the trie head, the latest canonical block whose state was written,
+/// prefixed by the node's hash. The nodes replaced by newer blocks and those of the blocks that
+/// aren't canonical are kept in a history column family for a few blocks, so their states can
+/// still be read.
+pub struct RocksDBPathTrieDB {
+    /// RocksDB database
+    db: Arc<DBWithThreadMode<MultiThreaded>>,
+    columns: PathSchemeColumns,
+    /// Storage trie address prefix
+    address_prefix: Option<H256>,
+    trie_head_lock: TrieHeadLock,
+}
+
+impl RocksDBPathTrieDB {
+    pub fn new(
+        db: Arc<DBWithThreadMode<MultiThreaded>>,
+        columns: PathSchemeColumns,
+        address_prefix: Option<H256>,
+        trie_head_lock: TrieHeadLock,
+    ) -> Result<Self, TrieError> {
+        // Verify column families exist
+        for cf_name in [
+            columns.state,
+            columns.storage,
+            columns.history,
+            columns.journal,
+        ] {
+            if db.cf_handle(cf_name).is_none() {
+                return Err(TrieError::DbError(anyhow::anyhow!(
+                    "Column family not found: {}",
+                    cf_name
+                )));
+            }
+        }
+
+        Ok(Self {
+            db,
+            columns,
+            address_prefix,
+            trie_head_lock,
+        })
+    }
+}
+
+impl TrieDB for RocksDBPathTrieDB {
+    /// Nodes can only be looked up by hash alone if they are the root of the trie
+    fn get(&self, key: NodeHash) -> Result<Option<Vec<u8>>, TrieError> {
+        self.get_at_path(&[], key)
+    }
+
+    fn get_at_path(&self, path: &[u8], key: NodeHash) -> Result<Option<Vec<u8>>, TrieError> {
+        read_node(&self.db, self.columns, self.address_prefix, path, key)
+            .map_err(|e| TrieError::DbError(anyhow::anyhow!("RocksDB get error: {}", e)))
+    }
+
+    /// Writes the nodes of a trie commit as part of the state of the trie head, which must
+    /// contain every node changed since the last one
+    fn put_batch(&self, key_values: Vec<(NodeHash, Vec<u8>)>) -> Result<(), TrieError> {
+        let write = || -> Result<(), StoreError> {
+            let nodes = nodes_with_paths(key_values)?;
+            let _trie_head_guard = self
+                .trie_head_lock
+                .lock()
+                .map_err(|_| StoreError::LockError)?;
+            let mut batch = WriteBatch::default();
+            write_at_trie_head(
+                &self.db,
+                self.columns,
+                &mut batch,
+                vec![(self.address_prefix, nodes)],
+            )?;
+            self.db.write(batch)?;
+            Ok(())
+        };
+        write().map_err(|e| TrieError::DbError(anyhow::anyhow!("RocksDB batch write error: {}", e)))
+    }
+}
+
+fn cf<'a>(
+    db: &'a DBWithThreadMode<MultiThreaded>,
+    name: &str,
+) -> Result<Arc<BoundColumnFamily<'a>>, StoreError> {
+    db.cf_handle(name)
+        .ok_or_else(|| StoreError::Custom(format!("Column family '{}' not found", name)))
+}
+
+/// Key of the node at the given path of a trie: the hashed address of the account for storage
+/// tries, the nibbles of the path packed into bytes and the amount of nibbles in it.
+/// The nodes of each subtrie end up next to each other, with their root first.
+pub fn node_key(address_prefix: Option<H256>, path: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(66);
+    if let Some(address) = address_prefix {
+        key.extend_from_slice(address.as_bytes());
+    }
+    key.extend(path.chunks(2).map(|chunk| match chunk {
+        [high, low] => (high << 4) | low,
+        [high] => high << 4,
+        _ => 0,
+    }));
+    key.push(path.len() as u8);
+    key
+}
+
+/// Node key prefixed by whether it belongs to a storage trie, which identifies its column family
+fn tagged_key(address_prefix: Option<H256>, path: &[u8]) -> Vec<u8> {
+    let mut key = vec![address_prefix.is_some() as u8];
+    key.extend(node_key(address_prefix, path));
+    key
+}
+
+/// Splits a tagged key into the address of its storage trie, if any, and the path of the node
+fn parse_tagged_key(key: &[u8]) -> Option<(Option<H256>, Vec<u8>)> {
+    let (tag, node_key) = key.split_first()?;
+    let (address_prefix, packed) = match *tag {
+        0 => (None, node_key),
+        _ => (
+            Some(H256::from_slice(node_key.get(..32)?)),
+            node_key.get(32..)?,
+        ),
+    };
+    let (len, packed) = packed.split_last()?;
+    let mut path: Vec<u8> = packed
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0F])
+        .collect();
+    path.truncate(*len as usize);
+    Some((address_prefix, path))
+}
+
+/// Prefix of the keys of the historical versions of a node: its tagged key followed by its hash
+fn history_prefix(address_prefix: Option<H256>, path: &[u8], hash: NodeHash) -> Vec<u8> {
+    let mut key = tagged_key(address_prefix, path);
+    key.extend_from_slice(hash.as_ref());
+    key
+}
+
+/// Key of a historical version of a node, kept on behalf of the given block
+fn history_key(
+    address_prefix: Option<H256>,
+    path: &[u8],
+    hash: NodeHash,
+    block_number: BlockNumber,
+) -> Vec<u8> {
+    let mut key = history_prefix(address_prefix, path, hash);
+    key.extend_from_slice(&block_number.to_be_bytes());
+    key
+}
+
+/// Splits a history key into the address of its storage trie, the path and the hash of the node
+fn parse_history_key(key: &[u8]) -> Option<(Option<H256>, Vec<u8>, NodeHash)> {
+    let tagged_len = key.len().checked_sub(40)?;
+    let (address_prefix, path) = parse_tagged_key(&key[..tagged_len])?;
+    let hash = H256::from_slice(&key[tagged_len..tagged_len + 32]);
+    Some((address_prefix, path, NodeHash::Hashed(hash)))
+}
+
+/// Reads the node with the given hash at the given path, looking into the recent history if it
+/// isn't part of the state of the trie head
+fn read_node(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    address_prefix: Option<H256>,
+    path: &[u8],
+    hash: NodeHash,
+) -> Result<Option<Vec<u8>>, StoreError> {
+    let hash = if path.is_empty() {
+        // Roots are always stored by their hash, even if their encoding is shorter
+        NodeHash::Hashed(hash.finalize())
+    } else {
+        hash
+    };
+    let node_cf = cf(db, columns.nodes(address_prefix.is_some()))?;
+    if let Some(value) = db.get_cf(&node_cf, node_key(address_prefix, path))?
+        && value.starts_with(hash.as_ref())
+    {
+        return Ok(Some(value[hash.as_ref().len()..].to_vec()));
+    }
+    let history_cf = cf(db, columns.history)?;
+    let prefix = history_prefix(address_prefix, path, hash);
+    let mut history = db.iterator_cf(&history_cf, IteratorMode::From(&prefix, Direction::Forward));
+    match history.next().transpose()? {
+        Some((key, value)) if key.starts_with(&prefix) => Ok(Some(value.to_vec())),
+        _ => Ok(None),
+    }
+}
+
+/// Hashed children of a node, along with their path from the root of the trie.
+/// Embedded children aren't stored on their own, so they have no path key.
+fn hashed_children(path: &[u8], node: &Node) -> Vec<(Vec<u8>, NodeHash)> {
+    match node {
+        Node::Branch(branch) => branch
+            .choices
+            .iter()
+            .enumerate()
+            .filter_map(|(choice, child)| match child {
+                NodeRef::Hash(hash @ NodeHash::Hashed(_)) => {
+                    Some(([path, &[choice as u8]].concat(), *hash))
+                }
+                _ => None,
+            })
+            .collect(),
+        Node::Extension(extension) => match &extension.child {
+            NodeRef::Hash(hash @ NodeHash::Hashed(_)) => {
+                vec![([path, extension.prefix.as_ref()].concat(), *hash)]
+            }
+            _ => Vec::new(),
+        },
+        Node::Leaf(_) => Vec::new(),
+    }
+}
+
+/// Writes the nodes of trie commits at their paths, removing the nodes at the paths that are no
+/// longer part of the tries. The nodes replaced or removed are kept in the history on behalf of
+/// the given block, if any, and recorded so the writes can be reverted.
+/// The nodes written are tracked so that the commits of several blocks can be added to the same
+/// batch, each one reading the paths as left by the previous ones.
+struct CanonicalWrite<'a> {
+    db: &'a DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    batch: &'a mut WriteBatch,
+    /// Values added to the batch by tagged node key, `None` for the deleted ones
+    written: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// Block on behalf of which the changes are made, `None` when there is no previous state
+    block_number: Option<BlockNumber>,
+    undo: Vec<(Bytes, Bytes)>,
+    replaced: Vec<Bytes>,
+}
+
+impl<'a> CanonicalWrite<'a> {
+    fn new(
+        db: &'a DBWithThreadMode<MultiThreaded>,
+        columns: PathSchemeColumns,
+        batch: &'a mut WriteBatch,
+        block_number: Option<BlockNumber>,
+    ) -> Self {
+        Self {
+            db,
+            columns,
+            batch,
+            written: BTreeMap::new(),
+            block_number,
+            undo: Vec::new(),
+            replaced: Vec::new(),
+        }
+    }
+
+    /// Reads the value at the given path, including the writes added to the batch
+    fn read(
+        &self,
+        address_prefix: Option<H256>,
+        path: &[u8],
+    ) -> Result<Option<Vec<u8>>, StoreError> {
+        let key = tagged_key(address_prefix, path);
+        if let Some(value) = self.written.get(&key) {
+            return Ok(value.clone());
+        }
+        let node_cf = cf(self.db, self.columns.nodes(address_prefix.is_some()))?;
+        Ok(self.db.get_cf(&node_cf, &key[1..])?)
+    }
+
+    /// Adds to the batch the write of a value at a tagged node key, or its removal if `None`
+    fn write(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) -> Result<(), StoreError> {
+        let is_storage = key.first().is_some_and(|tag| *tag != 0);
+        let node_cf = cf(self.db, self.columns.nodes(is_storage))?;
+        match &value {
+            Some(value) => self.batch.put_cf(&node_cf, &key[1..], value),
+            None => self.batch.delete_cf(&node_cf, &key[1..]),
+        }
+        self.written.insert(key, value);
+        Ok(())
+    }
+
+    /// Moves the node at the given path out of the trie, writing `new_value` in its place if any
+    fn replace(
+        &mut self,
+        address_prefix: Option<H256>,
+        path: &[u8],
+        old_value: Option<&[u8]>,
+        new_value: Option<Vec<u8>>,
+    ) -> Result<(), StoreError> {
+        if let Some(block_number) = self.block_number {
+            if let Some(old_value) = old_value
+                && old_value.len() > 32
+            {
+                let history_cf = cf(self.db, self.columns.history)?;
+                let (old_hash, old_node) = old_value.split_at(32);
+                let old_hash = NodeHash::Hashed(H256::from_slice(old_hash));
+                let history_key = history_key(address_prefix, path, old_hash, block_number);
+                self.batch.put_cf(&history_cf, &history_key, old_node);
+                self.replaced.push(history_key.into());
+            }
+            self.undo.push((
+                tagged_key(address_prefix, path).into(),
+                Bytes::copy_from_slice(old_value.unwrap_or_default()),
+            ));
+        }
+        self.write(tagged_key(address_prefix, path), new_value)
+    }
+
+    /// Removes every node of the storage trie of an account
+    fn clear_storage_trie(&mut self, address: H256) -> Result<(), StoreError> {
+        let storage_cf = cf(self.db, self.columns.storage)?;
+        let mut nodes = BTreeMap::new();
+        for entry in self.db.iterator_cf(
+            &storage_cf,
+            IteratorMode::From(address.as_bytes(), Direction::Forward),
+        ) {
+            let (key, value) = entry?;
+            if !key.starts_with(address.as_bytes()) {
+                break;
+            }
+            nodes.insert([&[1u8][..], &key].concat(), Some(value.to_vec()));
+        }
+        let prefix = [&[1u8][..], address.as_bytes()].concat();
+        nodes.extend(
+            self.written
+                .range(prefix.clone()..)
+                .take_while(|(key, _)| key.starts_with(&prefix))
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        for (key, value) in nodes {
+            let Some(value) = value else {
+                continue;
+            };
+            let (address_prefix, path) = parse_tagged_key(&key).ok_or(StoreError::DecodeError)?;
+            self.replace(address_prefix, &path, Some(&value), None)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the nodes of a commit of a trie. An empty commit of a storage trie means that it was
+    /// emptied or that its account was removed, so every node left of it is removed
+    fn write_trie(
+        &mut self,
+        address_prefix: Option<H256>,
+        nodes: Vec<PathTrieNode>,
+    ) -> Result<(), StoreError> {
+        if nodes.is_empty() {
+            return match address_prefix {
+                Some(address) => self.clear_storage_trie(address),
+                None => Ok(()),
+            };
+        }
+        // Positions of the new trie: those of the nodes of the commit, and those of the children
+        // they kept from the previous state
+        let mut new_nodes = HashMap::with_capacity(nodes.len());
+        let mut kept_children = HashMap::new();
+        let mut old_values = HashMap::with_capacity(nodes.len());
+        for (path, hash, encoded) in nodes {
+            let path = path.as_ref().to_vec();
+            let node = Node::decode(&encoded)?;
+            kept_children.extend(hashed_children(&path, &node));
+            let old_value = self.read(address_prefix, &path)?;
+            if !old_value
+                .as_ref()
+                .is_some_and(|old_value| old_value.starts_with(hash.as_ref()))
+            {
+                let new_value = [hash.as_ref(), &encoded].concat();
+                self.replace(address_prefix, &path, old_value.as_deref(), Some(new_value))?;
+            }
+            new_nodes.insert(path.clone(), hash);
+            old_values.insert(path, old_value);
+        }
+
+        // Nodes of the previous state at positions that aren't part of the new trie are removed.
+        // Those positions are below the nodes of the commit, as the rest of the trie didn't change
+        let mut pending = vec![Vec::new()];
+        while let Some(path) = pending.pop() {
+            let old_value = match old_values.remove(&path) {
+                Some(old_value) => old_value,
+                None => self.read(address_prefix, &path)?,
+            };
+            let Some(old_value) = old_value.filter(|old_value| old_value.len() > 32) else {
+                continue;
+            };
+            let old_hash = NodeHash::Hashed(H256::from_slice(&old_value[..32]));
+            match new_nodes.get(&path) {
+                // Same subtrie as before
+                Some(hash) if *hash == old_hash => continue,
+                // Replaced by a node of the commit
+                Some(_) => {}
+                // Kept from the previous state
+                None if kept_children.contains_key(&path) => continue,
+                None => self.replace(address_prefix, &path, Some(&old_value), None)?,
+            }
+            if let Ok(old_node) = Node::decode(&old_value[32..]) {
+                pending.extend(
+                    hashed_children(&path, &old_node)
+                        .into_iter()
+                        .map(|(child_path, _)| child_path),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Key of the journal of a block, sorted by block number
+pub fn journal_key(block_number: BlockNumber, block_hash: BlockHash) -> Vec<u8> {
+    [&block_number.to_be_bytes()[..], block_hash.as_bytes()].concat()
+}
+
+fn parse_journal_key(key: &[u8]) -> Option<(BlockNumber, BlockHash)> {
+    let number = BlockNumber::from_be_bytes(key.get(..8)?.try_into().ok()?);
+    let hash = BlockHash::from_slice(key.get(8..40)?);
+    Some((number, hash))
+}
+
+fn read_journal(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    block: (BlockNumber, BlockHash),
+) -> Result<Option<BlockJournal>, StoreError> {
+    let journal_cf = cf(db, columns.journal)?;
+    db.get_cf(&journal_cf, journal_key(block.0, block.1))?
+        .map(|journal| BlockJournal::decode(&journal))
+        .transpose()
+        .map_err(StoreError::from)
+}
+
+/// Returns the block whose state is stored at the path keys, `None` if no block was made
+/// canonical yet
+pub fn read_trie_head(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+) -> Result<Option<(BlockNumber, BlockHash)>, StoreError> {
+    let journal_cf = cf(db, columns.journal)?;
+    match db.get_cf(&journal_cf, TRIE_HEAD_KEY)? {
+        Some(head) => parse_journal_key(&head)
+            .map(Some)
+            .ok_or(StoreError::DecodeError),
+        None => Ok(None),
+    }
+}
+
+/// Adds to the batch the update of the block whose state is stored at the path keys
+pub fn set_trie_head(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    batch: &mut WriteBatch,
+    head: (BlockNumber, BlockHash),
+) -> Result<(), StoreError> {
+    let journal_cf = cf(db, columns.journal)?;
+    batch.put_cf(&journal_cf, TRIE_HEAD_KEY, journal_key(head.0, head.1));
+    Ok(())
+}
+
+/// Adds to the batch the nodes of a batch of blocks, keeping them in the history until the blocks
+/// become canonical. Only the state of the last block is stored.
+pub fn store_block_nodes(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    batch: &mut WriteBatch,
+    blocks: &[(BlockNumber, BlockHash)],
+    tries: Vec<(Option<H256>, Vec<TrieNode>)>,
+) -> Result<(), StoreError> {
+    let Some((last_number, _)) = blocks.last() else {
+        return Ok(());
+    };
+    let history_cf = cf(db, columns.history)?;
+    let journal_cf = cf(db, columns.journal)?;
+    let mut node_keys = Vec::new();
+    let mut cleared = Vec::new();
+    for (address_prefix, nodes) in tries {
+        if nodes.is_empty()
+            && let Some(address) = address_prefix
+        {
+            cleared.push(address);
+        }
+        for (path, hash, node) in nodes_with_paths(nodes)? {
+            let key = history_key(address_prefix, path.as_ref(), hash, *last_number);
+            batch.put_cf(&history_cf, &key, node);
+            node_keys.push(Bytes::from(key));
+        }
+    }
+    for (index, (number, hash)) in blocks.iter().enumerate() {
+        // Blocks that were already stored keep their journal, which may be needed to revert them
+        let key = journal_key(*number, *hash);
+        if db.get_cf(&journal_cf, &key)?.is_some() {
+            continue;
+        }
+        let journal = if index == blocks.len() - 1 {
+            BlockJournal {
+                has_state: true,
+                nodes: std::mem::take(&mut node_keys),
+                cleared: std::mem::take(&mut cleared),
+                ..Default::default()
+            }
+        } else {
+            BlockJournal::default()
+        };
+        batch.put_cf(&journal_cf, key, journal.encode_to_vec());
+    }
+    Ok(())
+}
+
+/// Adds to the batch the nodes of trie commits as part of the state of the trie head.
+/// The [`TrieHeadLock`] must be held until the batch is written
+pub fn write_at_trie_head(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    batch: &mut WriteBatch,
+    tries: Vec<(Option<H256>, Vec<PathTrieNode>)>,
+) -> Result<(), StoreError> {
+    let head = read_trie_head(db, columns)?;
+    let mut journal = match head {
+        Some(head) => Some(
+            read_journal(db, columns, head)?.unwrap_or_else(|| BlockJournal {
+                has_state: true,
+                ..Default::default()
+            }),
+        ),
+        None => None,
+    };
+    let mut write = CanonicalWrite::new(db, columns, batch, head.map(|(number, _)| number));
+    for (address_prefix, nodes) in tries {
+        write.write_trie(address_prefix, nodes)?;
+    }
+    let (undo, replaced) = (write.undo, write.replaced);
+    if let (Some((number, hash)), Some(journal)) = (head, journal.as_mut()) {
+        journal.undo.extend(undo);
+        journal.replaced.extend(replaced);
+        let journal_cf = cf(db, columns.journal)?;
+        batch.put_cf(
+            &journal_cf,
+            journal_key(number, hash),
+            journal.encode_to_vec(),
+        );
+    }
+    Ok(())
+}
+
+/// Adds to the batch the move of the trie head from its current block to another one: the blocks
+/// in `reverted`, newest first, are reverted down to `fork_point`, and then the nodes of the blocks
+/// in `applied`, oldest first, are written at their paths.
+/// The [`TrieHeadLock`] must be held until the batch is written
+pub fn move_trie_head(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    batch: &mut WriteBatch,
+    reverted: &[(BlockNumber, BlockHash)],
+    fork_point: (BlockNumber, BlockHash),
+    applied: &[(BlockNumber, BlockHash)],
+) -> Result<(), StoreError> {
+    let journal = |block: (BlockNumber, BlockHash), action: &str| {
+        read_journal(db, columns, block)?.ok_or_else(|| {
+            StoreError::Custom(format!(
+                "Can't {action} the trie nodes of block {} ({:#x}), they are not stored or were pruned",
+                block.0, block.1
+            ))
+        })
+    };
+    // Journals are read upfront so that the trie is left untouched if any is missing
+    let reverted_journals = reverted
+        .iter()
+        .map(|block| journal(*block, "revert"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let applied_journals = applied
+        .iter()
+        .map(|block| journal(*block, "apply"))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !reverted.is_empty()
+        && read_journal(db, columns, fork_point)?.is_some_and(|journal| !journal.has_state)
+    {
+        return Err(StoreError::Custom(format!(
+            "Can't revert the trie to block {} ({:#x}), its state is not stored",
+            fork_point.0, fork_point.1
+        )));
+    }
+    let journal_cf = cf(db, columns.journal)?;
+    let history_cf = cf(db, columns.history)?;
+    let mut write = CanonicalWrite::new(db, columns, batch, None);
+
+    for (block, mut journal) in reverted.iter().zip(reverted_journals) {
+        for (key, old_value) in journal.undo.drain(..).rev() {
+            let old_value = (!old_value.is_empty()).then(|| old_value.to_vec());
+            write.write(key.to_vec(), old_value)?;
+        }
+        write.batch.put_cf(
+            &journal_cf,
+            journal_key(block.0, block.1),
+            journal.encode_to_vec(),
+        );
+    }
+
+    for (block, mut journal) in applied.iter().zip(applied_journals) {
+        let mut tries: HashMap<Option<H256>, Vec<PathTrieNode>> = journal
+            .cleared
+            .iter()
+            .map(|address| (Some(*address), Vec::new()))
+            .collect();
+        for key in &journal.nodes {
+            let (address_prefix, path, hash) =
+                parse_history_key(key).ok_or(StoreError::DecodeError)?;
+            let node = db.get_cf(&history_cf, key)?.ok_or_else(|| {
+                StoreError::Custom(format!(
+                    "Missing trie node of block {} ({:#x})",
+                    block.0, block.1
+                ))
+            })?;
+            tries
+                .entry(address_prefix)
+                .or_default()
+                .push((Nibbles::from_hex(path), hash, node));
+        }
+        write.block_number = Some(block.0);
+        for (address_prefix, nodes) in tries {
+            write.write_trie(address_prefix, nodes)?;
+        }
+        journal.undo = std::mem::take(&mut write.undo);
+        journal.replaced.append(&mut write.replaced);
+        write.batch.put_cf(
+            &journal_cf,
+            journal_key(block.0, block.1),
+            journal.encode_to_vec(),
+        );
+    }
+
+    let head = applied.last().copied().unwrap_or(fork_point);
+    set_trie_head(db, columns, batch, head)?;
+    if !applied.is_empty() {
+        prune_history(db, columns, batch, head.0)?;
+    }
+    Ok(())
+}
+
+/// Adds to the batch the removal of the journals of the blocks more than [`TRIE_HISTORY_BLOCKS`]
+/// blocks older than the given one, along with the history entries they added
+fn prune_history(
+    db: &DBWithThreadMode<MultiThreaded>,
+    columns: PathSchemeColumns,
+    batch: &mut WriteBatch,
+    block_number: BlockNumber,
+) -> Result<(), StoreError> {
+    let Some(oldest_kept) = block_number.checked_sub(TRIE_HISTORY_BLOCKS) else {
+        return Ok(());
+    };
+    let history_cf = cf(db, columns.history)?;
+    let journal_cf = cf(db, columns.journal)?;
+    // Block journals start after the trie head
+    let first_key = 0u64.to_be_bytes();
+    for entry in db.iterator_cf(
+        &journal_cf,
+        IteratorMode::From(&first_key, Direction::Forward),
+    ) {
+        let (key, value) = entry?;
+        match parse_journal_key(&key) {
+            Some((number, _)) if number < oldest_kept => {}
+            _ => break,
+        }
+        let journal = BlockJournal::decode(&value)?;
+        for history_key in journal.nodes.into_iter().chain(journal.replaced) {
+            batch.delete_cf(&history_cf, history_key);
+        }
+        batch.delete_cf(&journal_cf, key);
+    }
+    Ok(())
+}
+
+/// Obtains the path of each node of a trie commit by traversing them from the commit's root.
+/// Nodes embedded in their parents aren't returned, as they are never looked up on their own.
+pub fn nodes_with_paths(nodes: Vec<TrieNode>) -> Result<Vec<PathTrieNode>, TrieError> {
+    let nodes: HashMap<NodeHash, (Node, Vec<u8>)> = nodes
+        .into_iter()
+        .map(|(hash, encoded)| {
+            let node = Node::decode(&encoded).map_err(TrieError::RLPDecode)?;
+            Ok((hash, (node, encoded)))
+        })
+        .collect::<Result<_, TrieError>>()?;
+    // Children of each node that are part of the commit, along with their path from their parent
+    let children = |node: &Node| -> Vec<(Nibbles, NodeHash)> {
+        match node {
+            Node::Branch(branch) => branch
+                .choices
+                .iter()
+                .enumerate()
+                .filter_map(|(choice, child)| match child {
+                    NodeRef::Hash(hash) if nodes.contains_key(hash) => {
+                        Some((Nibbles::from_hex(vec![choice as u8]), *hash))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            Node::Extension(extension) => match &extension.child {
+                NodeRef::Hash(hash) if nodes.contains_key(hash) => {
+                    vec![(extension.prefix.clone(), *hash)]
+                }
+                _ => Vec::new(),
+            },
+            Node::Leaf(_) => Vec::new(),
+        }
+    };
+    let referenced: std::collections::HashSet<NodeHash> = nodes
+        .values()
+        .flat_map(|(node, _)| children(node))
+        .map(|(_, hash)| hash)
+        .collect();
+
+    let mut nodes_with_paths = Vec::with_capacity(nodes.len());
+    let mut pending: Vec<(Nibbles, NodeHash)> = nodes
+        .keys()
+        .filter(|hash| !referenced.contains(hash))
+        .map(|hash| (Nibbles::default(), *hash))
+        .collect();
+    while let Some((path, hash)) = pending.pop() {
+        let (node, encoded) = &nodes[&hash];
+        pending.extend(
+            children(node)
+                .into_iter()
+                .map(|(child_path, child_hash)| (path.concat(child_path), child_hash)),
+        );
+        if path.is_empty() {
+            nodes_with_paths.push((path, NodeHash::Hashed(hash.finalize()), encoded.clone()));
+        } else if let NodeHash::Hashed(_) = hash {
+            nodes_with_paths.push((path, hash, encoded.clone()));
+        }
+    }
+    Ok(nodes_with_paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethrex_trie::Trie;
+    use rocksdb::{ColumnFamilyDescriptor, Options};
+    use tempfile::TempDir;
+
+    const COLUMNS: PathSchemeColumns = PathSchemeColumns {
+        state: "state",
+        storage: "storage",
+        history: "history",
+        journal: "journal",
+    };
+
+    fn open_db(temp_dir: &TempDir) -> Arc<DBWithThreadMode<MultiThreaded>> {
+        let mut db_options = Options::default();
+        db_options.create_if_missing(true);
+        db_options.create_missing_column_families(true);
+        let cf_descriptors = ["state", "storage", "history", "journal"]
+            .map(|name| ColumnFamilyDescriptor::new(name, Options::default()));
+        Arc::new(
+            DBWithThreadMode::<MultiThreaded>::open_cf_descriptors(
+                &db_options,
+                temp_dir.path().join("test_db"),
+                cf_descriptors,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn key(index: u64) -> Vec<u8> {
+        H256::from_low_u64_be(index).as_bytes().to_vec()
+    }
+
+    fn block(number: BlockNumber, fork: u64) -> (BlockNumber, BlockHash) {
+        (number, H256::from_low_u64_be((fork << 32) | number))
+    }
+
+    /// Stores the changes of the trie as the state of the given block, without making it canonical
+    fn store_block(
+        db: &Arc<DBWithThreadMode<MultiThreaded>>,
+        trie: &mut Trie,
+        block: (BlockNumber, BlockHash),
+    ) -> H256 {
+        let (root, nodes) = trie.collect_changes_since_last_hash();
+        let mut batch = WriteBatch::default();
+        store_block_nodes(db, COLUMNS, &mut batch, &[block], vec![(None, nodes)]).unwrap();
+        db.write(batch).unwrap();
+        root
+    }
+
+    /// Moves the trie head in a single batch
+    fn move_head(
+        db: &Arc<DBWithThreadMode<MultiThreaded>>,
+        reverted: &[(BlockNumber, BlockHash)],
+        fork_point: (BlockNumber, BlockHash),
+        applied: &[(BlockNumber, BlockHash)],
+    ) -> Result<(), StoreError> {
+        let mut batch = WriteBatch::default();
+        move_trie_head(db, COLUMNS, &mut batch, reverted, fork_point, applied)?;
+        db.write(batch)?;
+        Ok(())
+    }
+
+    /// Stores the changes of the trie as the state of the given block, which extends the trie head
+    fn commit_block(
+        db: &Arc<DBWithThreadMode<MultiThreaded>>,
+        trie: &mut Trie,
+        block: (BlockNumber, BlockHash),
+    ) -> H256 {
+        let root = store_block(db, trie, block);
+        let head = read_trie_head(db, COLUMNS).unwrap().unwrap();
+        move_head(db, &[], head, &[block]).unwrap();
+        root
+    }
+
+    fn open_trie(db: &Arc<DBWithThreadMode<MultiThreaded>>, root: H256) -> Trie {
+        let trie_db =
+            RocksDBPathTrieDB::new(db.clone(), COLUMNS, None, TrieHeadLock::default()).unwrap();
+        Trie::open(Box::new(trie_db), root)
+    }
+
+    /// Amount of nodes stored at their path
+    fn path_nodes(db: &Arc<DBWithThreadMode<MultiThreaded>>) -> usize {
+        let state_cf = db.cf_handle("state").unwrap();
+        db.iterator_cf(&state_cf, IteratorMode::Start).count()
+    }
+
+    /// Amount of storage trie nodes stored at their path
+    fn storage_path_nodes(db: &Arc<DBWithThreadMode<MultiThreaded>>) -> usize {
+        let storage_cf = db.cf_handle("storage").unwrap();
+        db.iterator_cf(&storage_cf, IteratorMode::Start).count()
+    }
+
+    /// Opens a database whose trie head is the genesis block, with an empty state
+    fn open_genesis_db(temp_dir: &TempDir) -> Arc<DBWithThreadMode<MultiThreaded>> {
+        let db = open_db(temp_dir);
+        let mut batch = WriteBatch::default();
+        set_trie_head(&db, COLUMNS, &mut batch, block(0, 0)).unwrap();
+        db.write(batch).unwrap();
+        db
+    }
+
+    #[test]
+    fn path_scheme_reads_latest_and_recent_states() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = open_genesis_db(&temp_dir);
+
+        let mut trie = open_trie(&db, *ethrex_trie::EMPTY_TRIE_HASH);
+        for i in 0..1000 {
+            trie.insert(key(i), vec![1]).unwrap();
+        }
+        let first_root = commit_block(&db, &mut trie, block(1, 0));
+
+        let mut trie = open_trie(&db, first_root);
+        for i in 0..500 {
+            trie.insert(key(i), vec![2]).unwrap();
+        }
+        for i in 500..600 {
+            trie.remove(&key(i)).unwrap();
+        }
+        let second_root = commit_block(&db, &mut trie, block(2, 0));
+
+        let latest = open_trie(&db, second_root);
+        let previous = open_trie(&db, first_root);
+        for i in 0..1000 {
+            let expected = match i {
+                0..500 => Some(vec![2]),
+                500..600 => None,
+                _ => Some(vec![1]),
+            };
+            assert_eq!(latest.get(&key(i)).unwrap(), expected);
+            assert_eq!(previous.get(&key(i)).unwrap(), Some(vec![1]));
+        }
+        assert_eq!(latest.into_iter().content().count(), 900);
+    }
+
+    #[test]
+    fn path_scheme_prunes_old_states() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = open_genesis_db(&temp_dir);
+
+        let mut trie = open_trie(&db, *ethrex_trie::EMPTY_TRIE_HASH);
+        trie.insert(key(0), vec![0]).unwrap();
+        trie.insert(key(1), vec![0]).unwrap();
+        let mut roots = vec![commit_block(&db, &mut trie, block(1, 0))];
+        for block_number in 2..=TRIE_HISTORY_BLOCKS + 3 {
+            let mut trie = open_trie(&db, *roots.last().unwrap());
+            trie.insert(key(0), block_number.to_be_bytes().to_vec())
+                .unwrap();
+            roots.push(commit_block(&db, &mut trie, block(block_number, 0)));
+        }
+
+        // The first state was replaced by block 2, whose history is pruned once the chain is
+        // more than TRIE_HISTORY_BLOCKS blocks ahead of it
+        assert!(open_trie(&db, roots[0]).get(&key(0)).is_err());
+        for (index, root) in roots.iter().enumerate().skip(1) {
+            assert_eq!(
+                open_trie(&db, *root).get(&key(0)).unwrap(),
+                Some((index as u64 + 1).to_be_bytes().to_vec())
+            );
+        }
+        // Blocks older than the history can't be reverted
+        let head = block(TRIE_HISTORY_BLOCKS + 3, 0);
+        assert!(move_head(&db, &[head, block(2, 0)], block(1, 0), &[]).is_err());
+    }
+
+    #[test]
+    fn side_chains_are_kept_out_of_the_paths_until_canonical() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = open_genesis_db(&temp_dir);
+
+        let mut trie = open_trie(&db, *ethrex_trie::EMPTY_TRIE_HASH);
+        for i in 0..100 {
+            trie.insert(key(i), vec![1]).unwrap();
+        }
+        let base_root = commit_block(&db, &mut trie, block(1, 0));
+
+        // Two children of the same block, changing the same paths
+        let mut canonical = open_trie(&db, base_root);
+        for i in 0..50 {
+            canonical.insert(key(i), vec![2]).unwrap();
+        }
+        let canonical_root = commit_block(&db, &mut canonical, block(2, 0));
+        let mut side = open_trie(&db, base_root);
+        for i in 25..100 {
+            side.remove(&key(i)).unwrap();
+        }
+        let side_root = store_block(&db, &mut side, block(2, 1));
+        let nodes_before_reorg = path_nodes(&db);
+
+        let expected = |root: H256, values: &dyn Fn(u64) -> Option<Vec<u8>>| {
+            let trie = open_trie(&db, root);
+            for i in 0..100 {
+                assert_eq!(trie.get(&key(i)).unwrap(), values(i), "key {i}");
+            }
+        };
+        let canonical_values = |i: u64| Some(if i < 50 { vec![2] } else { vec![1] });
+        let side_values = |i: u64| (i < 25).then(|| vec![1]);
+        expected(canonical_root, &canonical_values);
+        expected(side_root, &side_values);
+        expected(base_root, &|_| Some(vec![1]));
+
+        // Reorg to the side chain, whose removed paths are deleted
+        move_head(&db, &[block(2, 0)], block(1, 0), &[block(2, 1)]).unwrap();
+        assert_eq!(read_trie_head(&db, COLUMNS).unwrap(), Some(block(2, 1)));
+        assert!(path_nodes(&db) < nodes_before_reorg);
+        expected(canonical_root, &canonical_values);
+        expected(side_root, &side_values);
+        expected(base_root, &|_| Some(vec![1]));
+
+        // And back to the original chain, which restores its nodes
+        move_head(&db, &[block(2, 1)], block(1, 0), &[block(2, 0)]).unwrap();
+        assert_eq!(path_nodes(&db), nodes_before_reorg);
+        expected(canonical_root, &canonical_values);
+        expected(side_root, &side_values);
+    }
+
+    #[test]
+    fn removed_paths_are_deleted() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = open_genesis_db(&temp_dir);
+
+        let mut trie = open_trie(&db, *ethrex_trie::EMPTY_TRIE_HASH);
+        for i in 0..1000 {
+            trie.insert(key(i), vec![1]).unwrap();
+        }
+        let root = commit_block(&db, &mut trie, block(1, 0));
+
+        let mut trie = open_trie(&db, root);
+        for i in 1..1000 {
+            trie.remove(&key(i)).unwrap();
+        }
+        commit_block(&db, &mut trie, block(2, 0));
+        // Only the root, a leaf, is left
+        assert_eq!(path_nodes(&db), 1);
+
+        move_head(&db, &[block(2, 0)], block(1, 0), &[]).unwrap();
+        assert_eq!(open_trie(&db, root).into_iter().content().count(), 1000);
+    }
+
+    #[test]
+    fn several_blocks_are_moved_in_one_batch() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = open_genesis_db(&temp_dir);
+        let step_temp_dir = TempDir::new().unwrap();
+        let step_db = open_genesis_db(&step_temp_dir);
+
+        // The same blocks are made canonical one by one in a second database
+        let mut roots = vec![*ethrex_trie::EMPTY_TRIE_HASH];
+        for number in 1..=3 {
+            let mut trie = open_trie(&db, *roots.last().unwrap());
+            let mut step_trie = open_trie(&step_db, *roots.last().unwrap());
+            for i in 0..300 {
+                let value = (i % (number * 100) != 0).then(|| vec![number as u8]);
+                for trie in [&mut trie, &mut step_trie] {
+                    match &value {
+                        Some(value) => trie.insert(key(i), value.clone()).unwrap(),
+                        None => {
+                            trie.remove(&key(i)).unwrap();
+                        }
+                    }
+                }
+            }
+            roots.push(store_block(&db, &mut trie, block(number, 0)));
+            commit_block(&step_db, &mut step_trie, block(number, 0));
+        }
+
+        let blocks = [block(1, 0), block(2, 0), block(3, 0)];
+        move_head(&db, &[], block(0, 0), &blocks).unwrap();
+        assert_eq!(read_trie_head(&db, COLUMNS).unwrap(), Some(block(3, 0)));
+        let nodes = |db: &Arc<DBWithThreadMode<MultiThreaded>>| {
+            let state_cf = db.cf_handle("state").unwrap();
+            db.iterator_cf(&state_cf, IteratorMode::Start)
+                .map(|entry| entry.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(nodes(&db), nodes(&step_db));
+        for root in &roots[1..] {
+            assert_eq!(
+                open_trie(&db, *root)
+                    .into_iter()
+                    .content()
+                    .collect::<Vec<_>>(),
+                open_trie(&step_db, *root)
+                    .into_iter()
+                    .content()
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        let reverted = [block(3, 0), block(2, 0), block(1, 0)];
+        move_head(&db, &reverted, block(0, 0), &[]).unwrap();
+        assert_eq!(path_nodes(&db), 0);
+    }
+
+    #[test]
+    fn emptied_storage_tries_are_removed() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = open_genesis_db(&temp_dir);
+        let address = H256::repeat_byte(0xaa);
+        let open_storage_trie = |root: H256| {
+            let trie_db =
+                RocksDBPathTrieDB::new(db.clone(), COLUMNS, Some(address), TrieHeadLock::default())
+                    .unwrap();
+            Trie::open(Box::new(trie_db), root)
+        };
+        let store_storage_block = |block, nodes| {
+            let mut batch = WriteBatch::default();
+            store_block_nodes(
+                &db,
+                COLUMNS,
+                &mut batch,
+                &[block],
+                vec![(Some(address), nodes)],
+            )
+            .unwrap();
+            db.write(batch).unwrap();
+        };
+
+        let mut trie = open_storage_trie(*ethrex_trie::EMPTY_TRIE_HASH);
+        for i in 0..100 {
+            trie.insert(key(i), vec![1]).unwrap();
+        }
+        let (root, nodes) = trie.collect_changes_since_last_hash();
+        store_storage_block(block(1, 0), nodes);
+        move_head(&db, &[], block(0, 0), &[block(1, 0)]).unwrap();
+        let stored_nodes = storage_path_nodes(&db);
+        assert!(stored_nodes > 0);
+
+        // The account is removed, which commits no nodes of its storage trie
+        store_storage_block(block(2, 0), Vec::new());
+        move_head(&db, &[], block(1, 0), &[block(2, 0)]).unwrap();
+        assert_eq!(storage_path_nodes(&db), 0);
+        assert_eq!(open_storage_trie(root).into_iter().content().count(), 100);
+
+        move_head(&db, &[block(2, 0)], block(1, 0), &[]).unwrap();
+        assert_eq!(storage_path_nodes(&db), stored_nodes);
+    }
+
+    #[test]
+    fn put_batch_writes_at_the_trie_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = open_db(&temp_dir);
+
+        // Without a trie head the nodes are just written
+        let mut trie = open_trie(&db, *ethrex_trie::EMPTY_TRIE_HASH);
+        for i in 0..100 {
+            trie.insert(key(i), vec![1]).unwrap();
+        }
+        let first_root = trie.hash().unwrap();
+        assert_eq!(
+            db.iterator_cf(&db.cf_handle("history").unwrap(), IteratorMode::Start)
+                .count(),
+            0
+        );
+
+        // With one, the nodes they replace and remove are kept and the writes can be reverted
+        let mut batch = WriteBatch::default();
+        set_trie_head(&db, COLUMNS, &mut batch, block(1, 0)).unwrap();
+        db.write(batch).unwrap();
+        let mut trie = open_trie(&db, first_root);
+        for i in 50..100 {
+            trie.remove(&key(i)).unwrap();
+        }
+        let second_root = trie.hash().unwrap();
+        assert_eq!(
+            open_trie(&db, first_root).into_iter().content().count(),
+            100
+        );
+        assert_eq!(
+            open_trie(&db, second_root).into_iter().content().count(),
+            50
+        );
+
+        let journal = read_journal(&db, COLUMNS, block(1, 0)).unwrap().unwrap();
+        assert!(!journal.undo.is_empty());
+        assert!(!journal.replaced.is_empty());
+    }
+
+    #[test]
+    fn node_keys_keep_subtries_together() {
+        let path = |nibbles: &[u8]| node_key(None, nibbles);
+        assert!(path(&[]) < path(&[1]));
+        assert!(path(&[1]) < path(&[1, 0]));
+        assert!(path(&[1, 0]) < path(&[1, 0, 5]));
+        assert!(path(&[1, 0, 5]) < path(&[1, 1]));
+        assert!(path(&[1, 15, 15]) < path(&[2]));
+    }
+
+    #[test]
+    fn history_keys_can_be_parsed() {
+        let address = H256::repeat_byte(0xab);
+        let hash = NodeHash::Hashed(H256::repeat_byte(0xcd));
+        for (address_prefix, path) in [
+            (None, vec![]),
+            (None, vec![1, 2, 3]),
+            (Some(address), vec![]),
+            (Some(address), vec![4, 5, 6, 7]),
+        ] {
+            let key = history_key(address_prefix, &path, hash, 7);
+            assert_eq!(parse_history_key(&key), Some((address_prefix, path, hash)));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod db;
+mod parallel;
 mod tracing;
 
 use super::BlockExecutionResult;
@@ -20,7 +21,6 @@ use ethrex_levm::EVMConfig;
 use ethrex_levm::constants::{SYS_CALL_GAS_LIMIT, TX_BASE_COST};
 use ethrex_levm::db::gen_db::GeneralizedDatabase;
 use ethrex_levm::errors::{InternalError, TxValidationError};
-use ethrex_levm::tracing::LevmCallTracer;
 use ethrex_levm::vm::VMType;
 use ethrex_levm::{
     Environment,
@@ -31,6 +31,7 @@ use std::cmp::min;
 
 /// The struct implements the following functions:
 /// [LEVM::execute_block]
+/// [LEVM::execute_block_parallel]
 /// [LEVM::execute_tx]
 /// [LEVM::get_state_transitions]
 /// [LEVM::process_withdrawals]
@@ -46,6 +47,7 @@ impl LEVM {
         Self::prepare_block(block, db, vm_type)?;
 
         let mut receipts = Vec::new();
+        let mut senders = Vec::new();
         let mut cumulative_gas_used = 0;
 
         for (tx, tx_sender) in block.body.get_transactions_with_sender().map_err(|error| {
@@ -54,16 +56,34 @@ impl LEVM {
             let report = Self::execute_tx(tx, tx_sender, &block.header, db, vm_type)?;
 
             cumulative_gas_used += report.gas_used;
-            let receipt = Receipt::new(
-                tx.tx_type(),
-                matches!(report.result.clone(), TxResult::Success),
-                cumulative_gas_used,
-                report.logs.clone(),
-            );
-
-            receipts.push(receipt);
+            receipts.push(Self::build_receipt(tx, &report, cumulative_gas_used));
+            senders.push(tx_sender);
         }
 
+        Self::finalize_block(block, db, receipts, senders, vm_type)
+    }
+
+    fn build_receipt(
+        tx: &Transaction,
+        report: &ExecutionReport,
+        cumulative_gas_used: u64,
+    ) -> Receipt {
+        Receipt::new(
+            tx.tx_type(),
+            matches!(report.result, TxResult::Success),
+            cumulative_gas_used,
+            report.logs.clone(),
+        )
+    }
+
+    /// Processes the withdrawals and requests of the block once its transactions were executed
+    fn finalize_block(
+        block: &Block,
+        db: &mut GeneralizedDatabase,
+        receipts: Vec<Receipt>,
+        senders: Vec<Address>,
+        vm_type: VMType,
+    ) -> Result<BlockExecutionResult, EvmError> {
         if let Some(withdrawals) = &block.body.withdrawals {
             Self::process_withdrawals(db, withdrawals)?;
         }
@@ -76,10 +96,15 @@ impl LEVM {
             VMType::L2(_) => Default::default(),
         };
 
-        Ok(BlockExecutionResult { receipts, requests })
+        Ok(BlockExecutionResult {
+            receipts,
+            requests,
+            senders,
+        })
     }
 
-    fn setup_env(
+    /// Builds the environment a transaction is executed in, for running it on a [`VM`] directly.
+    pub fn setup_env(
         tx: &Transaction,
         tx_sender: Address,
         block_header: &BlockHeader,
@@ -131,7 +156,7 @@ impl LEVM {
         vm_type: VMType,
     ) -> Result<ExecutionReport, EvmError> {
         let env = Self::setup_env(tx, tx_sender, block_header, db)?;
-        let mut vm = VM::new(env, db, tx, LevmCallTracer::disabled(), vm_type)?;
+        let mut vm = VM::new(env, db, tx, vm_type)?;
 
         vm.execute().map_err(VMError::into)
     }
@@ -402,8 +427,7 @@ pub fn generic_system_contract_levm(
         data: calldata,
         ..Default::default()
     });
-    let mut vm =
-        VM::new(env, db, tx, LevmCallTracer::disabled(), vm_type).map_err(EvmError::from)?;
+    let mut vm = VM::new(env, db, tx, vm_type).map_err(EvmError::from)?;
 
     let report = vm.execute().map_err(EvmError::from)?;
 
@@ -564,5 +588,5 @@ fn vm_from_generic<'a>(
             ..Default::default()
         }),
     };
-    VM::new(env, db, &tx, LevmCallTracer::disabled(), vm_type)
+    VM::new(env, db, &tx, vm_type)
 }
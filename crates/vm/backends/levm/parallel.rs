@@ -0,0 +1,355 @@
+//! Optimistic parallel execution of the transactions of a block.
+//!
+//! Every transaction is first executed speculatively and in parallel on top of the state left by
+//! the block's system calls, recording the accounts and storage slots it reads. The results are
+//! then committed in block order: a transaction that didn't read anything written by the previous
+//! ones would have behaved the same if executed sequentially, so its changes are applied as they
+//! are. Otherwise it's executed again on top of the committed state. Either way the resulting state,
+//! receipts and account updates are the same as with sequential execution.
+//!
+//! The priority fee is paid to the coinbase when committing each transaction instead of during its
+//! execution, otherwise every transaction would conflict with the previous one through the coinbase balance.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use ethrex_common::{
+    Address, H256, U256,
+    constants::EMPTY_TRIE_HASH,
+    types::{AccountState, Block, BlockHeader, ChainConfig, Transaction},
+};
+use ethrex_levm::{
+    account::AccountStatus,
+    code_cache::CodeCache,
+    custom_precompiles::CustomPrecompiles,
+    db::{
+        Database,
+        gen_db::{CacheDB, GeneralizedDatabase},
+    },
+    errors::{DatabaseError, ExecutionReport, InternalError},
+    hooks::hook::CustomHooks,
+    vm::{VM, VMType},
+};
+use rayon::prelude::*;
+
+use super::LEVM;
+use crate::{BlockExecutionResult, EvmError};
+
+/// Storage root given to cached accounts that have storage, LEVM only checks whether it's empty
+const NON_EMPTY_STORAGE_ROOT: H256 = H256([0xff; 32]);
+
+impl LEVM {
+    /// Same as [LEVM::execute_block], but executing the transactions optimistically in parallel.
+    /// L2 blocks are always executed sequentially.
+    pub fn execute_block_parallel(
+        block: &Block,
+        db: &mut GeneralizedDatabase,
+        vm_type: VMType,
+    ) -> Result<BlockExecutionResult, EvmError> {
+        if let VMType::L2(_) = vm_type {
+            return Self::execute_block(block, db, vm_type);
+        }
+        Self::prepare_block(block, db, vm_type)?;
+
+        let transactions = block.body.get_transactions_with_sender().map_err(|error| {
+            EvmError::Transaction(format!("Couldn't recover addresses with error: {error}"))
+        })?;
+
+        let snapshot = StateSnapshot::take(db);
+        let speculative_outcomes: Vec<_> = transactions
+            .par_iter()
+            .map(|(tx, tx_sender)| {
+                execute_tx_on_snapshot(tx, *tx_sender, &block.header, &snapshot, vm_type)
+            })
+            .collect();
+        snapshot.restore(db)?;
+
+        let mut receipts = Vec::new();
+        let mut cumulative_gas_used = 0;
+        let mut written = StateKeys::default();
+
+        for ((tx, tx_sender), outcome) in transactions.iter().zip(speculative_outcomes) {
+            let outcome = match outcome {
+                Ok(outcome) if !outcome.reads.intersects(&written) => outcome,
+                // The transaction saw stale state or failed, so it's executed again on top of the previous ones
+                _ => {
+                    let snapshot = StateSnapshot::take(db);
+                    let outcome =
+                        execute_tx_on_snapshot(tx, *tx_sender, &block.header, &snapshot, vm_type);
+                    snapshot.restore(db)?;
+                    outcome?
+                }
+            };
+
+            cumulative_gas_used += outcome.report.gas_used;
+            receipts.push(Self::build_receipt(
+                tx,
+                &outcome.report,
+                cumulative_gas_used,
+            ));
+            outcome.commit(db, block.header.coinbase, &mut written)?;
+        }
+
+        let senders = transactions
+            .into_iter()
+            .map(|(_tx, tx_sender)| tx_sender)
+            .collect();
+        Self::finalize_block(block, db, receipts, senders, vm_type)
+    }
+}
+
+/// Cached state of a block being executed, shared by the transactions executed on top of it
+struct StateSnapshot {
+    store: Arc<dyn Database>,
+    accounts: CacheDB,
+    codes: BTreeMap<H256, Bytes>,
+    code_cache: Option<Arc<CodeCache>>,
+    custom_hooks: CustomHooks,
+    custom_precompiles: CustomPrecompiles,
+}
+
+impl StateSnapshot {
+    /// Moves the cached state out of `db`. It must be given back with [`StateSnapshot::restore`]
+    /// once the transactions executed on top of it are done.
+    fn take(db: &mut GeneralizedDatabase) -> Arc<Self> {
+        Arc::new(Self {
+            store: db.store.clone(),
+            accounts: std::mem::take(&mut db.current_accounts_state),
+            codes: std::mem::take(&mut db.codes),
+            code_cache: db.code_cache.clone(),
+            custom_hooks: db.custom_hooks.clone(),
+            custom_precompiles: db.custom_precompiles.clone(),
+        })
+    }
+
+    fn restore(self: Arc<Self>, db: &mut GeneralizedDatabase) -> Result<(), EvmError> {
+        let snapshot = Arc::try_unwrap(self)
+            .map_err(|_| EvmError::Custom("Block state snapshot is still in use".to_string()))?;
+        db.current_accounts_state = snapshot.accounts;
+        db.codes = snapshot.codes;
+        Ok(())
+    }
+}
+
+/// Accounts and storage slots read or written by transactions
+#[derive(Default)]
+struct StateKeys {
+    accounts: HashSet<Address>,
+    storage: HashSet<(Address, H256)>,
+}
+
+impl StateKeys {
+    fn intersects(&self, other: &StateKeys) -> bool {
+        self.accounts
+            .iter()
+            .any(|address| other.accounts.contains(address))
+            || self.storage.iter().any(|slot| other.storage.contains(slot))
+    }
+}
+
+/// Database of a single transaction executed on top of a [`StateSnapshot`], recording what it reads
+struct TxStateView {
+    snapshot: Arc<StateSnapshot>,
+    reads: Mutex<StateKeys>,
+}
+
+impl TxStateView {
+    fn new(snapshot: Arc<StateSnapshot>) -> Self {
+        Self {
+            snapshot,
+            reads: Mutex::new(StateKeys::default()),
+        }
+    }
+
+    fn record(&self, f: impl FnOnce(&mut StateKeys)) -> Result<(), DatabaseError> {
+        let mut reads = self
+            .reads
+            .lock()
+            .map_err(|_| DatabaseError::Custom("Poisoned transaction read set".to_string()))?;
+        f(&mut reads);
+        Ok(())
+    }
+
+    fn take_reads(&self) -> Result<StateKeys, DatabaseError> {
+        let mut reads = StateKeys::default();
+        self.record(|recorded| std::mem::swap(recorded, &mut reads))?;
+        Ok(reads)
+    }
+}
+
+impl Database for TxStateView {
+    fn get_account_state(&self, address: Address) -> Result<AccountState, DatabaseError> {
+        self.record(|reads| {
+            reads.accounts.insert(address);
+        })?;
+        match self.snapshot.accounts.get(&address) {
+            Some(account) => Ok(AccountState {
+                nonce: account.info.nonce,
+                balance: account.info.balance,
+                storage_root: if account.has_storage {
+                    NON_EMPTY_STORAGE_ROOT
+                } else {
+                    *EMPTY_TRIE_HASH
+                },
+                code_hash: account.info.code_hash,
+            }),
+            None => self.snapshot.store.get_account_state(address),
+        }
+    }
+
+    fn get_storage_value(&self, address: Address, key: H256) -> Result<U256, DatabaseError> {
+        self.record(|reads| {
+            reads.storage.insert((address, key));
+        })?;
+        if let Some(account) = self.snapshot.accounts.get(&address) {
+            if let Some(value) = account.storage.get(&key) {
+                return Ok(*value);
+            }
+            // Same as in LEVM, the storage of destroyed and recreated accounts isn't in the database
+            if account.status == AccountStatus::DestroyedModified {
+                return Ok(U256::zero());
+            }
+        }
+        self.snapshot.store.get_storage_value(address, key)
+    }
+
+    fn get_block_hash(&self, block_number: u64) -> Result<H256, DatabaseError> {
+        self.snapshot.store.get_block_hash(block_number)
+    }
+
+    fn get_chain_config(&self) -> Result<ChainConfig, DatabaseError> {
+        self.snapshot.store.get_chain_config()
+    }
+
+    fn get_account_code(&self, code_hash: H256) -> Result<Bytes, DatabaseError> {
+        match self.snapshot.codes.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.snapshot.store.get_account_code(code_hash),
+        }
+    }
+}
+
+/// Result of executing a transaction on top of a [`StateSnapshot`]
+struct TxOutcome {
+    report: ExecutionReport,
+    /// Priority fee owed to the coinbase
+    coinbase_fee: U256,
+    reads: StateKeys,
+    /// State of the accounts accessed by the transaction, before and after it
+    initial_accounts: CacheDB,
+    accounts: CacheDB,
+    codes: BTreeMap<H256, Bytes>,
+}
+
+fn execute_tx_on_snapshot(
+    tx: &Transaction,
+    tx_sender: Address,
+    block_header: &BlockHeader,
+    snapshot: &Arc<StateSnapshot>,
+    vm_type: VMType,
+) -> Result<TxOutcome, EvmError> {
+    let view = Arc::new(TxStateView::new(snapshot.clone()));
+    let mut db = GeneralizedDatabase::new(view.clone());
+    db.code_cache = snapshot.code_cache.clone();
+    db.custom_hooks = snapshot.custom_hooks.clone();
+    db.custom_precompiles = snapshot.custom_precompiles.clone();
+
+    let env = LEVM::setup_env(tx, tx_sender, block_header, &mut db)?;
+    let priority_fee_per_gas = env
+        .gas_price
+        .checked_sub(env.base_fee_per_gas)
+        .ok_or(InternalError::Underflow)?;
+    let report = {
+        let mut vm = VM::new(env, &mut db, tx, vm_type)?;
+        vm.defer_coinbase_payment = true;
+        vm.execute()?
+    };
+
+    let coinbase_fee = U256::from(report.gas_used)
+        .checked_mul(priority_fee_per_gas)
+        .ok_or(InternalError::Overflow)?;
+
+    Ok(TxOutcome {
+        report,
+        coinbase_fee,
+        reads: view.take_reads()?,
+        initial_accounts: db.initial_accounts_state,
+        accounts: db.current_accounts_state,
+        codes: db.codes,
+    })
+}
+
+impl TxOutcome {
+    /// Applies the changes of the transaction to `db` and pays the coinbase, as if the transaction had been
+    /// executed on it. The state the transaction modified is added to `written`.
+    fn commit(
+        self,
+        db: &mut GeneralizedDatabase,
+        coinbase: Address,
+        written: &mut StateKeys,
+    ) -> Result<(), EvmError> {
+        // The fee is paid before destroying the accounts that self destructed, so it's lost if the coinbase is one of them
+        let coinbase_destroyed = self
+            .accounts
+            .get(&coinbase)
+            .is_some_and(|account| account.status == AccountStatus::Destroyed);
+
+        for (address, account) in self.accounts {
+            if account.is_unmodified() {
+                continue;
+            }
+            let initial_account = self.initial_accounts.get(&address).ok_or_else(|| {
+                EvmError::Custom(format!("Missing initial state of account {address}"))
+            })?;
+            let destroyed = matches!(
+                account.status,
+                AccountStatus::Destroyed | AccountStatus::DestroyedModified
+            );
+
+            if destroyed || account.info != initial_account.info {
+                written.accounts.insert(address);
+            }
+            for (key, value) in &account.storage {
+                if initial_account.storage.get(key) != Some(value) {
+                    written.storage.insert((address, *key));
+                }
+            }
+
+            // Storage read from the database must be in the initial state for computing the account updates
+            db.get_account(address)?;
+            if let Some(block_initial_account) = db.initial_accounts_state.get_mut(&address) {
+                for (key, value) in &initial_account.storage {
+                    block_initial_account.storage.entry(*key).or_insert(*value);
+                }
+            }
+
+            let block_account = db.get_account_mut(address)?;
+            if destroyed {
+                *block_account = account;
+            } else {
+                block_account.info = account.info;
+                block_account.has_storage = account.has_storage;
+                block_account.storage.extend(account.storage);
+            }
+        }
+
+        for (code_hash, code) in self.codes {
+            db.codes.entry(code_hash).or_insert(code);
+        }
+
+        if !coinbase_destroyed {
+            let coinbase_account = db.get_account_mut(coinbase)?;
+            coinbase_account.info.balance = coinbase_account
+                .info
+                .balance
+                .checked_add(self.coinbase_fee)
+                .ok_or(InternalError::Overflow)?;
+            if !self.coinbase_fee.is_zero() {
+                written.accounts.insert(coinbase);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
 use ethrex_common::types::{Block, Transaction};
 use ethrex_common::{tracing::CallTrace, types::BlockHeader};
+use ethrex_levm::eip3155::Eip3155Tracer;
+use ethrex_levm::gas_profiler::GasProfiler;
 use ethrex_levm::vm::VMType;
 use ethrex_levm::{db::gen_db::GeneralizedDatabase, tracing::LevmCallTracer, vm::VM};
 
@@ -58,19 +62,74 @@ impl LEVM {
             block_header,
             db,
         )?;
-        let mut vm = VM::new(
-            env,
-            db,
-            tx,
-            LevmCallTracer::new(only_top_call, with_log),
-            vm_type,
-        )?;
+        let tracer = Rc::new(RefCell::new(LevmCallTracer::new(only_top_call, with_log)));
+        let mut vm = VM::new(env, db, tx, vm_type)?;
+        vm.set_inspector(tracer.clone());
 
         vm.execute()?;
 
-        let callframe = vm.get_trace_result()?;
+        let callframe = tracer.borrow_mut().take_trace_result()?;
 
         // We only return the top call because a transaction only has one call with subcalls
         Ok(vec![callframe])
     }
+
+    /// Run transaction with the EIP-3155 opcode tracer activated, writing its trace to `output`.
+    pub fn trace_tx_opcodes(
+        db: &mut GeneralizedDatabase,
+        block_header: &BlockHeader,
+        tx: &Transaction,
+        vm_type: VMType,
+        output: impl Write + 'static,
+    ) -> Result<(), EvmError> {
+        let env = Self::setup_env(
+            tx,
+            tx.sender().map_err(|error| {
+                EvmError::Transaction(format!("Couldn't recover addresses with error: {error}"))
+            })?,
+            block_header,
+            db,
+        )?;
+        let tracer = Rc::new(RefCell::new(Eip3155Tracer::new(output)));
+        let mut vm = VM::new(env, db, tx, vm_type)?;
+        vm.set_inspector(tracer.clone());
+
+        let report = vm.execute()?;
+
+        let gas_remaining = u64::try_from(vm.current_call_frame.gas_remaining).unwrap_or_default();
+        let mut tracer = tracer.borrow_mut();
+        tracer.end_transaction(gas_remaining, &report);
+        tracer
+            .finish()
+            .map_err(|error| EvmError::Custom(format!("Failed to write opcode trace: {error}")))
+    }
+
+    /// Run transaction with the gas profiler installed, adding its stats to the profiler.
+    pub fn profile_tx_gas(
+        db: &mut GeneralizedDatabase,
+        block_header: &BlockHeader,
+        tx: &Transaction,
+        vm_type: VMType,
+        profiler: &Rc<RefCell<GasProfiler>>,
+    ) -> Result<(), EvmError> {
+        let env = Self::setup_env(
+            tx,
+            tx.sender().map_err(|error| {
+                EvmError::Transaction(format!("Couldn't recover addresses with error: {error}"))
+            })?,
+            block_header,
+            db,
+        )?;
+        let mut vm = VM::new(env, db, tx, vm_type)?;
+        vm.set_inspector(profiler.clone());
+
+        let report = vm.execute()?;
+
+        let gas_remaining = u64::try_from(vm.current_call_frame.gas_remaining).unwrap_or_default();
+        profiler
+            .borrow_mut()
+            .end_transaction(gas_remaining, &report);
+
+        Ok(())
+    }
 }
@@ -11,8 +11,10 @@ use ethrex_common::types::{
 };
 use ethrex_common::{Address, types::fee_config::FeeConfig};
 pub use ethrex_levm::call_frame::CallFrameBackup;
+use ethrex_levm::custom_precompiles::CustomPrecompiles;
 use ethrex_levm::db::Database as LevmDatabase;
-use ethrex_levm::db::gen_db::GeneralizedDatabase;
+use ethrex_levm::db::gen_db::{CacheDB, GeneralizedDatabase};
+use ethrex_levm::hooks::hook::CustomHooks;
 use ethrex_levm::vm::VMType;
 use std::sync::Arc;
 use tracing::instrument;
@@ -21,6 +23,8 @@ use tracing::instrument;
 pub struct Evm {
     pub db: GeneralizedDatabase,
     pub vm_type: VMType,
+    /// Whether the transactions of a block are executed optimistically in parallel, see [LEVM::execute_block_parallel]
+    pub parallel_execution: bool,
 }
 
 impl core::fmt::Debug for Evm {
@@ -36,6 +40,7 @@ impl Evm {
         Evm {
             db: GeneralizedDatabase::new(Arc::new(wrapped_db)),
             vm_type: VMType::L1,
+            parallel_execution: false,
         }
     }
 
@@ -48,6 +53,7 @@ impl Evm {
         let evm = Evm {
             db: GeneralizedDatabase::new(Arc::new(wrapped_db)),
             vm_type: VMType::L2(fee_config),
+            parallel_execution: false,
         };
 
         Ok(evm)
@@ -68,12 +74,33 @@ impl Evm {
         Evm {
             db: GeneralizedDatabase::new(store),
             vm_type,
+            parallel_execution: false,
         }
     }
 
+    /// Sets the custom hooks run in every transaction executed by this EVM, after the default ones.
+    /// Every program executing the same blocks (e.g. the node and the prover) must use the same
+    /// hooks, as they may change the outcome of the transactions.
+    pub fn with_hooks(mut self, hooks: CustomHooks) -> Self {
+        self.db.custom_hooks = hooks;
+        self
+    }
+
+    /// Sets the custom precompiles available to the transactions executed by this EVM, if it's an
+    /// L2 one. Every program executing the same blocks (e.g. the node and the prover) must use the
+    /// same precompiles.
+    pub fn with_precompiles(mut self, precompiles: CustomPrecompiles) -> Self {
+        self.db.custom_precompiles = precompiles;
+        self
+    }
+
     #[instrument(level = "trace", name = "Block execution", skip_all)]
     pub fn execute_block(&mut self, block: &Block) -> Result<BlockExecutionResult, EvmError> {
-        LEVM::execute_block(block, &mut self.db, self.vm_type)
+        if self.parallel_execution {
+            LEVM::execute_block_parallel(block, &mut self.db, self.vm_type)
+        } else {
+            LEVM::execute_block(block, &mut self.db, self.vm_type)
+        }
     }
 
     /// Wraps [LEVM::execute_tx].
@@ -128,6 +155,15 @@ impl Evm {
         LEVM::get_state_transitions(&mut self.db)
     }
 
+    /// Returns the changes made to the state since `checkpoint`, an earlier copy of the cached state (`db.current_accounts_state`),
+    /// and brings the checkpoint up to date. Unlike [Evm::get_state_transitions] the cached state is kept.
+    pub fn get_state_transitions_since(
+        &self,
+        checkpoint: &mut CacheDB,
+    ) -> Result<Vec<AccountUpdate>, EvmError> {
+        Ok(self.db.get_state_transitions_since(checkpoint)?)
+    }
+
     /// Wraps [LEVM::process_withdrawals].
     /// Applies the withdrawals to the state or the block_chache if using [LEVM].
     pub fn process_withdrawals(&mut self, withdrawals: &[Withdrawal]) -> Result<(), EvmError> {
@@ -189,4 +225,7 @@ impl Evm {
 pub struct BlockExecutionResult {
     pub receipts: Vec<Receipt>,
     pub requests: Vec<Requests>,
+    /// Senders of the block's transactions, in order, as recovered for executing them.
+    /// Empty if they weren't recovered
+    pub senders: Vec<Address>,
 }
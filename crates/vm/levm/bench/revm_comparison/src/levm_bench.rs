@@ -10,7 +10,6 @@ use ethrex_levm::{
     Environment,
     db::gen_db::GeneralizedDatabase,
     errors::TxResult,
-    tracing::LevmCallTracer,
     vm::{VM, VMType},
 };
 use ethrex_storage::Store;
@@ -83,5 +82,5 @@ fn init_vm(db: &mut GeneralizedDatabase, nonce: u64, calldata: Bytes) -> Result<
         data: calldata,
         ..Default::default()
     });
-    VM::new(env, db, &tx, LevmCallTracer::disabled(), VMType::L1)
+    VM::new(env, db, &tx, VMType::L1)
 }
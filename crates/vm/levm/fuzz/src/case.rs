@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bytes::Bytes;
+use ethrex_common::{
+    Address, H160, H256, U256,
+    types::{BlockHeader, ChainConfig, GenesisAccount, LegacyTransaction, Transaction, TxKind},
+};
+use proptest::prelude::*;
+
+/// Sender of the transactions, the address of [`SENDER_SECRET_KEY`]
+pub const SENDER: Address = H160([
+    0xa9, 0x4f, 0x53, 0x74, 0xfc, 0xe5, 0xed, 0xbc, 0x8e, 0x2a, 0x86, 0x97, 0xc1, 0x53, 0x31, 0x67,
+    0x7e, 0x6e, 0xbf, 0x0b,
+]);
+/// Key the state tests sign the transactions with, the same one used by the ethereum/tests fillers
+pub const SENDER_SECRET_KEY: H256 = H256([
+    0x45, 0xa9, 0x15, 0xe4, 0xd0, 0x60, 0x14, 0x9e, 0xb4, 0x36, 0x59, 0x60, 0xe6, 0xa7, 0xa4, 0x5f,
+    0x33, 0x43, 0x93, 0x09, 0x30, 0x61, 0x11, 0x6b, 0x19, 0x7e, 0x32, 0x40, 0x06, 0x5f, 0xf2, 0xd8,
+]);
+pub const SENDER_BALANCE: u64 = 1_000_000_000_000_000_000;
+
+pub const COINBASE: Address = H160([
+    0x2a, 0xdc, 0x25, 0x66, 0x50, 0x18, 0xaa, 0x1f, 0xe0, 0xe6, 0xbc, 0x66, 0x6d, 0xac, 0x8f, 0xc2,
+    0x69, 0x7f, 0xf9, 0xba,
+]);
+pub const BLOCK_NUMBER: u64 = 1;
+pub const TIMESTAMP: u64 = 1000;
+pub const BLOCK_GAS_LIMIT: u64 = 30_000_000;
+pub const BASE_FEE: u64 = 7;
+pub const PREV_RANDAO: H256 = H256([0x01; 32]);
+
+/// Amount of contracts in the pre state, the first one is called by the transaction
+pub const CONTRACT_COUNT: u64 = 3;
+const FIRST_CONTRACT: u64 = 0x1000;
+
+/// Opcodes generated on their own, pushes are generated along with their data
+#[rustfmt::skip]
+const OPCODES: &[u8] = &[
+    // Arithmetic and comparison
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b,
+    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x20,
+    // Environment and block
+    0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f,
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a,
+    // Stack, memory, storage and flow
+    0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f,
+    // Dups and swaps
+    0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x8b, 0x8c, 0x8d, 0x8e, 0x8f,
+    0x90, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0x9b, 0x9c, 0x9d, 0x9e, 0x9f,
+    // Logs
+    0xa0, 0xa1, 0xa2, 0xa3, 0xa4,
+    // Calls, creations and halts
+    0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xfa, 0xfd, 0xfe, 0xff,
+];
+
+/// Transaction calling a contract on top of a random pre state
+#[derive(Debug, Clone)]
+pub struct FuzzCase {
+    pub contracts: Vec<FuzzContract>,
+    pub calldata: Vec<u8>,
+    pub value: u64,
+    pub gas_limit: u64,
+    pub priority_fee: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzContract {
+    pub code: Vec<u8>,
+    pub balance: u64,
+    pub storage: BTreeMap<u8, u64>,
+}
+
+pub fn contract_address(index: u64) -> Address {
+    Address::from_low_u64_be(FIRST_CONTRACT + index)
+}
+
+impl FuzzCase {
+    pub fn target(&self) -> Address {
+        contract_address(0)
+    }
+
+    pub fn gas_price(&self) -> u64 {
+        BASE_FEE + self.priority_fee
+    }
+
+    pub fn pre_state(&self) -> BTreeMap<Address, GenesisAccount> {
+        let mut pre_state: BTreeMap<Address, GenesisAccount> = self
+            .contracts
+            .iter()
+            .enumerate()
+            .map(|(index, contract)| {
+                let account = GenesisAccount {
+                    code: Bytes::from(contract.code.clone()),
+                    storage: contract
+                        .storage
+                        .iter()
+                        .filter(|(_, value)| **value != 0)
+                        .map(|(key, value)| (U256::from(*key), U256::from(*value)))
+                        .collect(),
+                    balance: U256::from(contract.balance),
+                    nonce: 1,
+                };
+                (contract_address(index as u64), account)
+            })
+            .collect();
+        pre_state.insert(
+            SENDER,
+            GenesisAccount {
+                code: Bytes::new(),
+                storage: HashMap::new(),
+                balance: U256::from(SENDER_BALANCE),
+                nonce: 0,
+            },
+        );
+        pre_state
+    }
+
+    /// Unsigned transaction of the case, the sender is always [`SENDER`]
+    pub fn transaction(&self) -> Transaction {
+        Transaction::LegacyTransaction(LegacyTransaction {
+            nonce: 0,
+            gas_price: self.gas_price(),
+            gas: self.gas_limit,
+            to: TxKind::Call(self.target()),
+            value: U256::from(self.value),
+            data: Bytes::from(self.calldata.clone()),
+            ..Default::default()
+        })
+    }
+}
+
+/// Header of the block the transaction is executed in
+pub fn block_header() -> BlockHeader {
+    BlockHeader {
+        coinbase: COINBASE,
+        number: BLOCK_NUMBER,
+        gas_limit: BLOCK_GAS_LIMIT,
+        timestamp: TIMESTAMP,
+        prev_randao: PREV_RANDAO,
+        base_fee_per_gas: Some(BASE_FEE),
+        blob_gas_used: Some(0),
+        excess_blob_gas: Some(0),
+        ..Default::default()
+    }
+}
+
+/// Chain config with every fork up to Cancun active since genesis
+pub fn chain_config() -> ChainConfig {
+    ChainConfig {
+        chain_id: 1,
+        homestead_block: Some(0),
+        eip150_block: Some(0),
+        eip155_block: Some(0),
+        eip158_block: Some(0),
+        byzantium_block: Some(0),
+        constantinople_block: Some(0),
+        petersburg_block: Some(0),
+        istanbul_block: Some(0),
+        berlin_block: Some(0),
+        london_block: Some(0),
+        merge_netsplit_block: Some(0),
+        terminal_total_difficulty: Some(0),
+        terminal_total_difficulty_passed: true,
+        shanghai_time: Some(0),
+        cancun_time: Some(0),
+        ..Default::default()
+    }
+}
+
+pub fn fuzz_case() -> impl Strategy<Value = FuzzCase> {
+    (
+        prop::collection::vec(contract(), CONTRACT_COUNT as usize),
+        prop::collection::vec(any::<u8>(), 0..68),
+        prop_oneof![Just(0u64), 0..1_000u64],
+        50_000..2_000_000u64,
+        0..10u64,
+    )
+        .prop_map(
+            |(contracts, calldata, value, gas_limit, priority_fee)| FuzzCase {
+                contracts,
+                calldata,
+                value,
+                gas_limit,
+                priority_fee,
+            },
+        )
+}
+
+fn contract() -> impl Strategy<Value = FuzzContract> {
+    (
+        bytecode(),
+        prop_oneof![Just(0u64), any::<u64>()],
+        prop::collection::btree_map(0..8u8, any::<u64>(), 0..4),
+    )
+        .prop_map(|(code, balance, storage)| FuzzContract {
+            code,
+            balance,
+            storage,
+        })
+}
+
+fn bytecode() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(instruction(), 0..64).prop_map(|instructions| instructions.concat())
+}
+
+/// Instructions are biased towards small pushes and known addresses, so that memory offsets,
+/// storage keys and call targets are meaningful most of the time
+fn instruction() -> BoxedStrategy<Vec<u8>> {
+    prop_oneof![
+        6 => prop::sample::select(OPCODES).prop_map(|opcode| vec![opcode]),
+        4 => (0..64u8).prop_map(|value| vec![0x60, value]),
+        2 => (0..CONTRACT_COUNT).prop_map(|index| {
+            [vec![0x73], contract_address(index).as_bytes().to_vec()].concat()
+        }),
+        1 => (1..=32u8).prop_flat_map(|size| {
+            prop::collection::vec(any::<u8>(), size as usize)
+                .prop_map(move |data| [vec![0x5f + size], data].concat())
+        }),
+        1 => any::<u8>().prop_map(|byte| vec![byte]),
+    ]
+    .boxed()
+}
@@ -0,0 +1,133 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bytes::Bytes;
+use ethrex_common::{
+    Address, H256, U256,
+    types::{AccountState, AccountUpdate, ChainConfig, GenesisAccount},
+    utils::keccak,
+};
+use ethrex_levm::{
+    db::{Database, gen_db::GeneralizedDatabase},
+    errors::DatabaseError,
+    vm::VMType,
+};
+use ethrex_vm::{EvmError, backends::levm::LEVM};
+
+use crate::{
+    Alloc, Outcome,
+    case::{FuzzCase, SENDER, block_header, chain_config},
+};
+
+pub fn run_levm(case: &FuzzCase) -> Outcome {
+    let pre_state = case.pre_state();
+    let mut db = GeneralizedDatabase::new(Arc::new(FuzzDatabase::new(pre_state.clone())));
+
+    match LEVM::execute_tx(
+        &case.transaction(),
+        SENDER,
+        &block_header(),
+        &mut db,
+        VMType::L1,
+    ) {
+        Ok(report) => {
+            let updates = db
+                .get_state_transitions()
+                .unwrap_or_else(|error| panic!("LEVM failed to get state transitions: {error}"));
+            Outcome::Executed {
+                success: report.is_success(),
+                gas_used: report.gas_used,
+                output: report.output,
+                logs: report.logs,
+                post_state: apply_account_updates(pre_state, updates),
+            }
+        }
+        Err(EvmError::Transaction(_)) => Outcome::Invalid,
+        // Internal errors are bugs, panicking lets the runner minimize the case like any other divergence
+        Err(error) => panic!("LEVM failed: {error}"),
+    }
+}
+
+fn apply_account_updates(mut state: Alloc, updates: Vec<AccountUpdate>) -> Alloc {
+    for update in updates {
+        if update.removed {
+            state.remove(&update.address);
+            continue;
+        }
+        let account = state
+            .entry(update.address)
+            .or_insert_with(|| GenesisAccount {
+                code: Bytes::new(),
+                storage: HashMap::new(),
+                balance: U256::zero(),
+                nonce: 0,
+            });
+        if let Some(info) = update.info {
+            account.balance = info.balance;
+            account.nonce = info.nonce;
+        }
+        if let Some(code) = update.code {
+            account.code = code;
+        }
+        for (key, value) in update.added_storage {
+            let key = U256::from_big_endian(key.as_bytes());
+            if value.is_zero() {
+                account.storage.remove(&key);
+            } else {
+                account.storage.insert(key, value);
+            }
+        }
+    }
+    state
+}
+
+/// Pre state of a case as seen by LEVM
+struct FuzzDatabase {
+    accounts: Alloc,
+    codes: HashMap<H256, Bytes>,
+    chain_config: ChainConfig,
+}
+
+impl FuzzDatabase {
+    fn new(accounts: Alloc) -> Self {
+        Self {
+            codes: accounts
+                .values()
+                .map(|account| (keccak(&account.code), account.code.clone()))
+                .collect(),
+            accounts,
+            chain_config: chain_config(),
+        }
+    }
+}
+
+impl Database for FuzzDatabase {
+    fn get_account_state(&self, address: Address) -> Result<AccountState, DatabaseError> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .map(AccountState::from)
+            .unwrap_or_default())
+    }
+
+    fn get_storage_value(&self, address: Address, key: H256) -> Result<U256, DatabaseError> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&U256::from_big_endian(key.as_bytes())))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    /// Same hashes as revm's `EmptyDB`, so that BLOCKHASH returns the same in both
+    fn get_block_hash(&self, block_number: u64) -> Result<H256, DatabaseError> {
+        Ok(keccak(block_number.to_string()))
+    }
+
+    fn get_chain_config(&self) -> Result<ChainConfig, DatabaseError> {
+        Ok(self.chain_config)
+    }
+
+    fn get_account_code(&self, code_hash: H256) -> Result<Bytes, DatabaseError> {
+        Ok(self.codes.get(&code_hash).cloned().unwrap_or_default())
+    }
+}
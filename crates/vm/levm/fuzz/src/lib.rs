@@ -0,0 +1,102 @@
+//! Differential fuzzing of LEVM against revm.
+//!
+//! Random pre states, bytecode and calldata are generated with proptest and the resulting
+//! transaction is executed by both VMs, comparing whether it's valid, its success, gas used,
+//! return data, logs and the post state. Divergent cases are minimized by proptest and can be
+//! saved as state tests, see [`state_test`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bytes::Bytes;
+use ethrex_common::{
+    Address,
+    types::{GenesisAccount, Log},
+};
+
+pub mod case;
+pub mod levm_runner;
+pub mod revm_runner;
+pub mod state_test;
+
+pub use case::{FuzzCase, fuzz_case};
+pub use levm_runner::run_levm;
+pub use revm_runner::run_revm;
+
+/// State of every existing account, keyed by address
+pub type Alloc = BTreeMap<Address, GenesisAccount>;
+
+/// Result of executing a [`FuzzCase`] in one of the VMs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The transaction was rejected before executing it
+    Invalid,
+    Executed {
+        success: bool,
+        gas_used: u64,
+        output: Bytes,
+        logs: Vec<Log>,
+        post_state: Alloc,
+    },
+}
+
+/// Runs the case in both VMs, returning the differences found in LEVM's outcome with respect to revm's
+pub fn compare(case: &FuzzCase) -> Vec<String> {
+    divergences(&run_levm(case), &run_revm(case))
+}
+
+fn divergences(levm: &Outcome, revm: &Outcome) -> Vec<String> {
+    let (
+        Outcome::Executed {
+            success: levm_success,
+            gas_used: levm_gas_used,
+            output: levm_output,
+            logs: levm_logs,
+            post_state: levm_post_state,
+        },
+        Outcome::Executed {
+            success: revm_success,
+            gas_used: revm_gas_used,
+            output: revm_output,
+            logs: revm_logs,
+            post_state: revm_post_state,
+        },
+    ) = (levm, revm)
+    else {
+        return if levm == revm {
+            Vec::new()
+        } else {
+            vec![format!("validity: levm {levm:?}, revm {revm:?}")]
+        };
+    };
+
+    let mut divergences = Vec::new();
+    if levm_success != revm_success {
+        divergences.push(format!("success: levm {levm_success}, revm {revm_success}"));
+    }
+    if levm_gas_used != revm_gas_used {
+        divergences.push(format!(
+            "gas used: levm {levm_gas_used}, revm {revm_gas_used}"
+        ));
+    }
+    if levm_output != revm_output {
+        divergences.push(format!(
+            "output: levm 0x{}, revm 0x{}",
+            hex::encode(levm_output),
+            hex::encode(revm_output)
+        ));
+    }
+    if levm_logs != revm_logs {
+        divergences.push(format!("logs: levm {levm_logs:?}, revm {revm_logs:?}"));
+    }
+    let addresses = levm_post_state.keys().chain(revm_post_state.keys());
+    for address in addresses.collect::<BTreeSet<_>>() {
+        let levm_account = levm_post_state.get(address);
+        let revm_account = revm_post_state.get(address);
+        if levm_account != revm_account {
+            divergences.push(format!(
+                "account {address:#x}: levm {levm_account:?}, revm {revm_account:?}"
+            ));
+        }
+    }
+    divergences
+}
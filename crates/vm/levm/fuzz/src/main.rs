@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use levm_differential_fuzz::{compare, fuzz_case, run_revm, state_test::save_state_test};
+use proptest::test_runner::{Config, RngAlgorithm, TestCaseError, TestError, TestRng, TestRunner};
+
+#[derive(Parser)]
+#[command(
+    name = "differential-fuzz",
+    about = "Runs random transactions through LEVM and revm and reports where they diverge"
+)]
+struct Options {
+    #[arg(long, default_value_t = 10_000)]
+    /// Amount of random cases to run
+    cases: u32,
+    #[arg(long)]
+    /// Seed of the case generator, a random one is used if not given
+    seed: Option<u64>,
+    #[arg(long, default_value = "divergences")]
+    /// Directory the minimized divergent case is saved to, as a state test
+    output: PathBuf,
+}
+
+fn main() {
+    let options = Options::parse();
+
+    let config = Config {
+        cases: options.cases,
+        // Divergences are saved as state tests instead
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let mut runner = match options.seed {
+        Some(seed) => {
+            let mut seed_bytes = [0; 32];
+            seed_bytes[..8].copy_from_slice(&seed.to_le_bytes());
+            TestRunner::new_with_rng(
+                config,
+                TestRng::from_seed(RngAlgorithm::ChaCha, &seed_bytes),
+            )
+        }
+        None => TestRunner::new(config),
+    };
+
+    let result = runner.run(&fuzz_case(), |case| {
+        let divergences = compare(&case);
+        if divergences.is_empty() {
+            Ok(())
+        } else {
+            Err(TestCaseError::fail(divergences.join("\n")))
+        }
+    });
+
+    match result {
+        Ok(()) => println!("No divergences found in {} cases", options.cases),
+        Err(TestError::Fail(reason, case)) => {
+            eprintln!(
+                "LEVM and revm diverged:\n{}\n\nMinimized case: {case:#?}",
+                reason.message()
+            );
+            // Only revm is run again, the failure could have been a panic in LEVM
+            let expected = run_revm(&case);
+            let divergences = [reason.message().to_string()];
+            match save_state_test(&options.output, &case, &expected, &divergences) {
+                Ok(path) => eprintln!("Saved state test to {}", path.display()),
+                Err(error) => eprintln!("Failed to save state test: {error}"),
+            }
+            std::process::exit(1);
+        }
+        Err(TestError::Abort(reason)) => {
+            eprintln!("Fuzzing aborted: {reason}");
+            std::process::exit(2);
+        }
+    }
+}
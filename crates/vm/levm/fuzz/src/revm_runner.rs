@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use ethrex_common::{Address, H256, U256, types::GenesisAccount, types::Log};
+use revm::{
+    Evm,
+    db::{CacheDB, EmptyDB},
+    primitives::{
+        AccountInfo, B256, BlobExcessGasAndPrice, Bytecode, EVMError, SpecId, TransactTo,
+    },
+};
+
+use crate::{
+    Outcome,
+    case::{
+        BASE_FEE, BLOCK_GAS_LIMIT, BLOCK_NUMBER, COINBASE, FuzzCase, PREV_RANDAO, SENDER, TIMESTAMP,
+    },
+};
+
+pub fn run_revm(case: &FuzzCase) -> Outcome {
+    let pre_state = case.pre_state();
+    let mut db = CacheDB::new(EmptyDB::default());
+    for (address, account) in &pre_state {
+        let code = Bytecode::new_raw(account.code.to_vec().into());
+        db.insert_account_info(
+            to_revm_address(*address),
+            AccountInfo {
+                balance: to_revm_u256(account.balance),
+                nonce: account.nonce,
+                code_hash: code.hash_slow(),
+                code: Some(code),
+            },
+        );
+        for (key, value) in &account.storage {
+            db.insert_account_storage(
+                to_revm_address(*address),
+                to_revm_u256(*key),
+                to_revm_u256(*value),
+            )
+            .unwrap_or_else(|error| panic!("Failed to insert revm storage: {error:?}"));
+        }
+    }
+
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_spec_id(SpecId::CANCUN)
+        .modify_cfg_env(|cfg| cfg.chain_id = 1)
+        .modify_block_env(|block| {
+            block.number = revm::primitives::U256::from(BLOCK_NUMBER);
+            block.coinbase = to_revm_address(COINBASE);
+            block.timestamp = revm::primitives::U256::from(TIMESTAMP);
+            block.gas_limit = revm::primitives::U256::from(BLOCK_GAS_LIMIT);
+            block.basefee = revm::primitives::U256::from(BASE_FEE);
+            block.difficulty = revm::primitives::U256::ZERO;
+            block.prevrandao = Some(B256::from(PREV_RANDAO.0));
+            block.blob_excess_gas_and_price = Some(BlobExcessGasAndPrice::new(0));
+        })
+        .modify_tx_env(|tx| {
+            tx.caller = to_revm_address(SENDER);
+            tx.transact_to = TransactTo::Call(to_revm_address(case.target()));
+            tx.data = case.calldata.clone().into();
+            tx.value = revm::primitives::U256::from(case.value);
+            tx.gas_limit = case.gas_limit;
+            tx.gas_price = revm::primitives::U256::from(case.gas_price());
+            tx.gas_priority_fee = None;
+            tx.nonce = Some(0);
+            tx.chain_id = None;
+        })
+        .build();
+
+    let result_and_state = match evm.transact() {
+        Ok(result_and_state) => result_and_state,
+        Err(EVMError::Transaction(_)) => return Outcome::Invalid,
+        Err(error) => panic!("revm failed: {error:?}"),
+    };
+    let result = result_and_state.result;
+
+    let mut post_state = pre_state;
+    for (address, account) in result_and_state.state {
+        if !account.is_touched() {
+            continue;
+        }
+        let address = from_revm_address(address);
+        // Touched empty accounts are removed since Spurious Dragon
+        if account.is_selfdestructed() || account.is_empty() {
+            post_state.remove(&address);
+            continue;
+        }
+        let post_account = post_state.entry(address).or_insert_with(|| GenesisAccount {
+            code: Bytes::new(),
+            storage: HashMap::new(),
+            balance: U256::zero(),
+            nonce: 0,
+        });
+        post_account.balance = from_revm_u256(account.info.balance);
+        post_account.nonce = account.info.nonce;
+        if let Some(code) = &account.info.code {
+            post_account.code = Bytes::copy_from_slice(&code.original_bytes());
+        }
+        for (key, slot) in account.storage {
+            let key = from_revm_u256(key);
+            let value = from_revm_u256(slot.present_value);
+            if value.is_zero() {
+                post_account.storage.remove(&key);
+            } else {
+                post_account.storage.insert(key, value);
+            }
+        }
+    }
+
+    Outcome::Executed {
+        success: result.is_success(),
+        gas_used: result.gas_used(),
+        output: result
+            .output()
+            .map(|output| Bytes::copy_from_slice(output))
+            .unwrap_or_default(),
+        logs: result
+            .logs()
+            .iter()
+            .map(|log| Log {
+                address: from_revm_address(log.address),
+                topics: log
+                    .data
+                    .topics()
+                    .iter()
+                    .map(|topic| H256(topic.0))
+                    .collect(),
+                data: Bytes::copy_from_slice(&log.data.data),
+            })
+            .collect(),
+        post_state,
+    }
+}
+
+fn to_revm_address(address: Address) -> revm::primitives::Address {
+    revm::primitives::Address::from_slice(address.as_bytes())
+}
+
+fn from_revm_address(address: revm::primitives::Address) -> Address {
+    Address::from_slice(address.as_slice())
+}
+
+fn to_revm_u256(value: U256) -> revm::primitives::U256 {
+    revm::primitives::U256::from_be_bytes(value.to_big_endian())
+}
+
+fn from_revm_u256(value: revm::primitives::U256) -> U256 {
+    U256::from_big_endian(&value.to_be_bytes::<32>())
+}
@@ -0,0 +1,125 @@
+//! Export of fuzz cases as state tests, in the format read by `tooling/ef_tests/state_v2`.
+//!
+//! The expected post state is the one computed by revm, so a saved divergence fails on LEVM
+//! until it's fixed and works as a regression test afterwards.
+
+use std::path::{Path, PathBuf};
+
+use ethrex_common::{
+    H256,
+    types::{AccountState, Log},
+    utils::keccak,
+};
+use ethrex_rlp::encode::RLPEncode;
+use ethrex_trie::Trie;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    Alloc, Outcome,
+    case::{
+        BASE_FEE, BLOCK_GAS_LIMIT, BLOCK_NUMBER, COINBASE, FuzzCase, PREV_RANDAO, SENDER,
+        SENDER_SECRET_KEY, TIMESTAMP,
+    },
+};
+
+/// Builds a state test named `name` for `case`, expecting the outcome of revm
+pub fn state_test(name: &str, comment: &str, case: &FuzzCase, expected: &Outcome) -> Value {
+    let pre_state = case.pre_state();
+    let (post_state, logs) = match expected {
+        Outcome::Executed {
+            post_state, logs, ..
+        } => (post_state, logs.as_slice()),
+        Outcome::Invalid => (&pre_state, [].as_slice()),
+    };
+
+    json!({
+        name: {
+            "_info": { "comment": comment },
+            "env": {
+                "currentCoinbase": COINBASE,
+                "currentDifficulty": "0x0",
+                "currentExcessBlobGas": "0x0",
+                "currentGasLimit": hex_number(BLOCK_GAS_LIMIT),
+                "currentNumber": hex_number(BLOCK_NUMBER),
+                "currentTimestamp": hex_number(TIMESTAMP),
+                "currentBaseFee": hex_number(BASE_FEE),
+                "currentRandom": PREV_RANDAO,
+            },
+            "pre": alloc_json(&pre_state),
+            "transaction": {
+                "data": [format!("0x{}", hex::encode(&case.calldata))],
+                "gasLimit": [hex_number(case.gas_limit)],
+                "gasPrice": hex_number(case.gas_price()),
+                "nonce": "0x0",
+                "secretKey": SENDER_SECRET_KEY,
+                "sender": SENDER,
+                "to": case.target(),
+                "value": [hex_number(case.value)],
+            },
+            "post": {
+                "Cancun": [{
+                    "hash": state_root(post_state),
+                    "logs": logs_hash(logs),
+                    "indexes": { "data": 0, "gas": 0, "value": 0 },
+                }],
+            },
+        }
+    })
+}
+
+/// Writes the state test of a divergent case to `directory`, returning its path
+pub fn save_state_test(
+    directory: &Path,
+    case: &FuzzCase,
+    expected: &Outcome,
+    divergences: &[String],
+) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(directory)?;
+    let name = format!(
+        "levm_revm_divergence_{}",
+        hex::encode(&keccak(format!("{case:?}")).as_bytes()[..4])
+    );
+    let test = state_test(&name, &divergences.join("\n"), case, expected);
+    let path = directory.join(format!("{name}.json"));
+    std::fs::write(&path, serde_json::to_string_pretty(&test)?)?;
+    Ok(path)
+}
+
+fn hex_number(number: u64) -> String {
+    format!("{number:#x}")
+}
+
+fn alloc_json(alloc: &Alloc) -> Value {
+    let accounts: Map<String, Value> = alloc
+        .iter()
+        .map(|(address, account)| {
+            let storage: Map<String, Value> = account
+                .storage
+                .iter()
+                .map(|(key, value)| (format!("{key:#x}"), json!(format!("{value:#x}"))))
+                .collect();
+            let account = json!({
+                "balance": format!("{:#x}", account.balance),
+                "code": format!("0x{}", hex::encode(&account.code)),
+                "nonce": hex_number(account.nonce),
+                "storage": storage,
+            });
+            (format!("{address:#x}"), account)
+        })
+        .collect();
+    Value::Object(accounts)
+}
+
+fn state_root(alloc: &Alloc) -> H256 {
+    let iter = alloc.iter().map(|(address, account)| {
+        (
+            keccak(address).as_bytes().to_vec(),
+            AccountState::from(account).encode_to_vec(),
+        )
+    });
+    Trie::compute_hash_from_unsorted_iter(iter)
+}
+
+fn logs_hash(logs: &[Log]) -> H256 {
+    keccak(logs.to_vec().encode_to_vec())
+}
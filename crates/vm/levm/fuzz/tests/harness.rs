@@ -0,0 +1,150 @@
+//! Checks the harness itself: cases go through both VMs, and the state tests saved for them are
+//! read and passed by the `state_v2` runner.
+
+use ef_tests_statev2::modules::{
+    parser::parse_file,
+    result_check::check_test_case_results,
+    runner::{get_tx_from_test_case, get_vm_env_for_test},
+    utils::load_initial_state,
+};
+use ethrex_common::{
+    U256,
+    types::{Fork, TxKind},
+};
+use ethrex_levm::vm::{VM, VMType};
+use levm_differential_fuzz::{
+    Outcome, compare,
+    case::{CONTRACT_COUNT, FuzzCase, FuzzContract, SENDER},
+    run_levm, run_revm,
+    state_test::save_state_test,
+};
+use proptest::{
+    prelude::*,
+    strategy::ValueTree,
+    test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+};
+
+/// Enough cases to go through every part of the harness, finding divergences is left to the binary
+const CASES: u32 = 32;
+
+/// Runner with a fixed seed, so the same cases are run every time
+fn runner() -> TestRunner {
+    let config = Config {
+        cases: CASES,
+        failure_persistence: None,
+        ..Config::default()
+    };
+    TestRunner::new_with_rng(config, TestRng::from_seed(RngAlgorithm::ChaCha, &[0x42; 32]))
+}
+
+/// Contract writing a few storage slots, then logging and returning a word.
+/// Unlike the programs of [`levm_differential_fuzz::fuzz_case`], both VMs are known to agree on these.
+fn storing_contract() -> impl Strategy<Value = FuzzContract> {
+    (
+        prop::collection::btree_map(0..8u8, any::<u8>(), 0..4),
+        any::<u8>(),
+        prop::collection::btree_map(0..8u8, any::<u64>(), 0..4),
+    )
+        .prop_map(|(writes, word, storage)| {
+            let mut code = Vec::new();
+            for (key, value) in writes {
+                // PUSH1 value, PUSH1 key, SSTORE
+                code.extend([0x60, value, 0x60, key, 0x55]);
+            }
+            // PUSH1 word, PUSH1 0, MSTORE
+            code.extend([0x60, word, 0x60, 0x00, 0x52]);
+            // PUSH1 32, PUSH1 0, LOG0
+            code.extend([0x60, 0x20, 0x60, 0x00, 0xa0]);
+            // PUSH1 32, PUSH1 0, RETURN
+            code.extend([0x60, 0x20, 0x60, 0x00, 0xf3]);
+            FuzzContract {
+                code,
+                balance: 0,
+                storage,
+            }
+        })
+}
+
+fn agreeing_case() -> impl Strategy<Value = FuzzCase> {
+    (
+        prop::collection::vec(storing_contract(), CONTRACT_COUNT as usize),
+        prop::collection::vec(any::<u8>(), 0..68),
+        0..1_000u64,
+        200_000..1_000_000u64,
+        0..10u64,
+    )
+        .prop_map(
+            |(contracts, calldata, value, gas_limit, priority_fee)| FuzzCase {
+                contracts,
+                calldata,
+                value,
+                gas_limit,
+                priority_fee,
+            },
+        )
+}
+
+#[test]
+fn levm_and_revm_agree_on_simple_programs() {
+    runner()
+        .run(&agreeing_case(), |case| {
+            // A rejected transaction would not execute the programs at all
+            prop_assert!(matches!(
+                run_levm(&case),
+                Outcome::Executed { success: true, .. }
+            ));
+            let divergences = compare(&case);
+            prop_assert!(divergences.is_empty(), "{}", divergences.join("\n"));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[tokio::test]
+async fn saved_state_tests_pass_on_the_state_tests_runner() {
+    let case = agreeing_case()
+        .new_tree(&mut runner())
+        .unwrap()
+        .current();
+    let expected = run_revm(&case);
+    let directory = tempfile::tempdir().unwrap();
+    let path = save_state_test(
+        directory.path(),
+        &case,
+        &expected,
+        &["no divergence".to_string()],
+    )
+    .unwrap();
+
+    let tests = parse_file(&path, false).unwrap();
+    assert_eq!(tests.len(), 1);
+    let test = &tests[0];
+    assert_eq!(test.pre.len(), case.pre_state().len());
+    assert_eq!(test.test_cases.len(), 1);
+    let test_case = &test.test_cases[0];
+    assert_eq!(test_case.fork, Fork::Cancun);
+    assert_eq!(test_case.sender, SENDER);
+    assert_eq!(test_case.to, TxKind::Call(case.target()));
+    assert_eq!(test_case.data.as_ref(), case.calldata.as_slice());
+    assert_eq!(test_case.gas, case.gas_limit);
+    assert_eq!(test_case.value, U256::from(case.value));
+
+    // Runs the test the same way the state tests runner does, which checks the post state root
+    let (mut db, initial_block_hash, store, genesis) =
+        load_initial_state(test, &test_case.fork).await;
+    let env = get_vm_env_for_test(test.env, test_case).unwrap();
+    let tx = get_tx_from_test_case(test_case).await.unwrap();
+    let mut vm = VM::new(env, &mut db, &tx, VMType::L1).unwrap();
+    let execution_result = vm.execute();
+    let checks = check_test_case_results(
+        &mut vm,
+        initial_block_hash,
+        store,
+        test_case,
+        execution_result,
+        genesis,
+    )
+    .await
+    .unwrap();
+    assert!(checks.passed);
+}
@@ -0,0 +1,538 @@
+//! Interactive step debugger, installed in the VM as an [`Inspector`].
+//!
+//! Execution pauses before the first opcode and then whenever the chosen stepping mode, a
+//! breakpoint or a watchpoint says so. While paused, commands are read from stdin to inspect the
+//! state of the VM or to resume execution. See [`HELP`] for the list of commands.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use ethrex_common::{Address, H256, U256};
+use ethrex_levm::{call_frame::CallFrame, inspector::Inspector, opcodes::Opcode, vm::VM};
+
+pub const HELP: &str = "\
+Commands:
+  s, step                     Execute the next opcode, entering calls
+  n, next                     Execute the next opcode, stepping over calls
+  c, continue                 Run until a breakpoint or watchpoint is hit
+  b, break pc <pc> [address]  Break when reaching pc, in any contract unless an address is given
+  b, break op <opcode>        Break before executing an opcode, e.g. `break op SSTORE`
+  w, watch <slot> [address]   Break after a storage slot is written, in any contract unless an address is given
+  l, list                     List breakpoints and watchpoints
+  d, delete <index>           Delete a breakpoint or watchpoint
+  stack                       Print the stack, top first
+  memory [offset] [size]      Print the memory of the current call frame
+  storage [address]           Print the storage slots accessed so far, of the current contract by default
+  tstorage                    Print the transient storage
+  frames, bt                  Print the call frame stack
+  q, quit                     Run to the end without stopping
+  h, help                     Print this message";
+
+/// Bytes of memory printed per line
+const MEMORY_ROW_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// Pause before the next opcode
+    Step,
+    /// Pause before the next opcode executed at this depth or in a parent call frame
+    Next(usize),
+    /// Pause only on breakpoints and watchpoints
+    Continue,
+    /// Don't pause anymore
+    Detached,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Stop {
+    Pc { pc: usize, address: Option<Address> },
+    Opcode(Opcode),
+    Slot { key: H256, address: Option<Address> },
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Pc { pc, address } => {
+                write!(f, "break at pc {pc}")?;
+                if let Some(address) = address {
+                    write!(f, " of {address:#x}")?;
+                }
+                Ok(())
+            }
+            Stop::Opcode(opcode) => write!(f, "break on {opcode:?}"),
+            Stop::Slot { key, address } => {
+                write!(f, "watch slot {key:#x}")?;
+                if let Some(address) = address {
+                    write!(f, " of {address:#x}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct Debugger {
+    mode: Mode,
+    /// Breakpoints and watchpoints, in the order they were added
+    stops: Vec<Stop>,
+    /// Description of the last watched storage write, execution pauses on the next opcode
+    watch_hit: Option<String>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Step,
+            stops: Vec::new(),
+            watch_hit: None,
+        }
+    }
+
+    fn should_pause(&mut self, opcode: Opcode, call_frame: &CallFrame) -> bool {
+        if let Some(hit) = self.watch_hit.take() {
+            println!("{hit}");
+            return true;
+        }
+        let mode_pause = match self.mode {
+            Mode::Step => true,
+            Mode::Next(depth) => call_frame.depth <= depth,
+            Mode::Continue | Mode::Detached => false,
+        };
+        mode_pause
+            || self.stops.iter().enumerate().any(|(index, stop)| {
+                let hit = match stop {
+                    Stop::Pc { pc, address } => {
+                        *pc == call_frame.pc
+                            && address.is_none_or(|address| address == call_frame.code_address)
+                    }
+                    Stop::Opcode(stop_opcode) => *stop_opcode == opcode,
+                    Stop::Slot { .. } => false,
+                };
+                if hit {
+                    println!("Hit #{index}: {stop}");
+                }
+                hit
+            })
+    }
+
+    /// Reads and runs commands until one of them resumes the execution
+    fn prompt(&mut self, vm: &VM<'_>) {
+        let mut line = String::new();
+        loop {
+            print!("(levm) ");
+            let _ = io::stdout().flush();
+            line.clear();
+            match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    // Nothing else to read, let the transaction finish
+                    self.mode = Mode::Detached;
+                    return;
+                }
+                Ok(_) => {}
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some((command, args)) = words.split_first() else {
+                continue;
+            };
+            match self.run_command(command, args, vm) {
+                Ok(Some(mode)) => {
+                    self.mode = mode;
+                    return;
+                }
+                Ok(None) => {}
+                Err(error) => println!("{error}"),
+            }
+        }
+    }
+
+    /// Runs a command, returning the mode to resume the execution with if it does so
+    fn run_command(
+        &mut self,
+        command: &str,
+        args: &[&str],
+        vm: &VM<'_>,
+    ) -> Result<Option<Mode>, String> {
+        let call_frame = &vm.current_call_frame;
+        match command {
+            "s" | "step" => return Ok(Some(Mode::Step)),
+            "n" | "next" => return Ok(Some(Mode::Next(call_frame.depth))),
+            "c" | "continue" => return Ok(Some(Mode::Continue)),
+            "q" | "quit" => return Ok(Some(Mode::Detached)),
+            "b" | "break" => {
+                let stop = match args {
+                    ["pc", pc] => Stop::Pc {
+                        pc: parse_number(pc)?,
+                        address: None,
+                    },
+                    ["pc", pc, address] => Stop::Pc {
+                        pc: parse_number(pc)?,
+                        address: Some(parse_address(address)?),
+                    },
+                    ["op", opcode] => Stop::Opcode(
+                        opcode
+                            .to_uppercase()
+                            .parse()
+                            .map_err(|_| format!("Unknown opcode {opcode}"))?,
+                    ),
+                    _ => return Err("Usage: break pc <pc> [address] | break op <opcode>".into()),
+                };
+                self.add_stop(stop);
+            }
+            "w" | "watch" => {
+                let (key, address) = match args {
+                    [key] => (key, None),
+                    [key, address] => (key, Some(parse_address(address)?)),
+                    _ => return Err("Usage: watch <slot> [address]".into()),
+                };
+                let key = H256::from(parse_u256(key)?.to_big_endian());
+                self.add_stop(Stop::Slot { key, address });
+            }
+            "l" | "list" => {
+                if self.stops.is_empty() {
+                    println!("No breakpoints or watchpoints");
+                }
+                for (index, stop) in self.stops.iter().enumerate() {
+                    println!("#{index}: {stop}");
+                }
+            }
+            "d" | "delete" => {
+                let [index] = args else {
+                    return Err("Usage: delete <index>".into());
+                };
+                let index: usize = parse_number(index)?;
+                if index >= self.stops.len() {
+                    return Err(format!("No breakpoint or watchpoint #{index}"));
+                }
+                let stop = self.stops.remove(index);
+                println!("Deleted #{index}: {stop}");
+            }
+            "stack" => print_stack(call_frame),
+            "memory" => {
+                let offset = args.first().map(|arg| parse_number(arg)).transpose()?;
+                let size = args.get(1).map(|arg| parse_number(arg)).transpose()?;
+                print_memory(call_frame, offset.unwrap_or(0), size);
+            }
+            "storage" => {
+                let address = match args.first() {
+                    Some(address) => parse_address(address)?,
+                    None => call_frame.to,
+                };
+                print_storage(vm, address);
+            }
+            "tstorage" => print_transient_storage(vm),
+            "frames" | "bt" => print_call_frames(vm),
+            "h" | "help" => println!("{HELP}"),
+            _ => {
+                return Err(format!(
+                    "Unknown command {command}, run `help` to list them"
+                ));
+            }
+        }
+        Ok(None)
+    }
+
+    fn add_stop(&mut self, stop: Stop) {
+        println!("#{}: {stop}", self.stops.len());
+        self.stops.push(stop);
+    }
+}
+
+impl Inspector for Debugger {
+    fn step_vm(&mut self, opcode: Opcode, vm: &VM<'_>) {
+        if self.mode == Mode::Detached || !self.should_pause(opcode, &vm.current_call_frame) {
+            return;
+        }
+        print_location(opcode, &vm.current_call_frame);
+        self.prompt(vm);
+    }
+
+    fn storage_write(&mut self, address: Address, key: H256, previous: U256, new: U256) {
+        if self.mode == Mode::Detached {
+            return;
+        }
+        let watched = self.stops.iter().enumerate().find(|(_, stop)| {
+            matches!(stop, Stop::Slot { key: watched_key, address: watched_address }
+                if *watched_key == key && watched_address.is_none_or(|watched| watched == address))
+        });
+        if let Some((index, stop)) = watched {
+            self.watch_hit = Some(format!(
+                "Hit #{index}: {stop}, {address:#x} slot {key:#x} changed {previous:#x} -> {new:#x}"
+            ));
+        }
+    }
+}
+
+fn print_location(opcode: Opcode, call_frame: &CallFrame) {
+    let mut location = format!(
+        "[depth {}] {:#x} pc {} {opcode:?}",
+        call_frame.depth, call_frame.code_address, call_frame.pc
+    );
+    if (Opcode::PUSH1..=Opcode::PUSH32).contains(&opcode) {
+        let size = usize::from(u8::from(opcode) - u8::from(Opcode::PUSH1) + 1);
+        let start = call_frame.pc + 1;
+        // Data past the end of the code is read as zeros
+        let data = call_frame
+            .bytecode
+            .get(start..(start + size).min(call_frame.bytecode.len()))
+            .unwrap_or_default();
+        location.push_str(&format!(" 0x{}", hex::encode(data)));
+    }
+    println!("{location} (gas remaining {})", call_frame.gas_remaining);
+}
+
+fn print_stack(call_frame: &CallFrame) {
+    let stack = &call_frame.stack;
+    if stack.is_empty() {
+        println!("Empty stack");
+    }
+    for (index, value) in stack.values[stack.offset..].iter().enumerate() {
+        println!("{index:>4}: {value:#x}");
+    }
+}
+
+fn print_memory(call_frame: &CallFrame, offset: usize, size: Option<usize>) {
+    let memory = call_frame.memory.to_vec();
+    let end = size.map_or(memory.len(), |size| offset.saturating_add(size));
+    let Some(memory) = memory.get(offset..end.min(memory.len())) else {
+        println!("Memory size is {} bytes", memory.len());
+        return;
+    };
+    if memory.is_empty() {
+        println!("Empty memory");
+    }
+    for (row, chunk) in memory.chunks(MEMORY_ROW_SIZE).enumerate() {
+        println!(
+            "{:#06x}: {}",
+            offset + row * MEMORY_ROW_SIZE,
+            hex::encode(chunk)
+        );
+    }
+}
+
+fn print_storage(vm: &VM<'_>, address: Address) {
+    let Some(account) = vm.db.current_accounts_state.get(&address) else {
+        println!("Account {address:#x} wasn't accessed");
+        return;
+    };
+    if account.storage.is_empty() {
+        println!("No storage slots of {address:#x} were accessed");
+    }
+    for (key, value) in &account.storage {
+        println!("{key:#x}: {value:#x}");
+    }
+}
+
+fn print_transient_storage(vm: &VM<'_>) {
+    let entries: BTreeMap<_, _> = vm.substate.transient_entries().into_iter().collect();
+    if entries.is_empty() {
+        println!("Empty transient storage");
+    }
+    for ((address, key), value) in entries {
+        println!("{address:#x} {key:#x}: {value:#x}");
+    }
+}
+
+/// Prints the call frames from the transaction down to the current one
+fn print_call_frames(vm: &VM<'_>) {
+    for call_frame in vm.call_frames.iter().chain([&vm.current_call_frame]) {
+        let kind = if call_frame.is_create {
+            "CREATE"
+        } else if call_frame.is_static {
+            "STATICCALL"
+        } else {
+            "CALL"
+        };
+        let mut frame = format!(
+            "#{} {kind} from {:#x} to {:#x}",
+            call_frame.depth, call_frame.msg_sender, call_frame.to
+        );
+        if call_frame.code_address != call_frame.to {
+            frame.push_str(&format!(" with code of {:#x}", call_frame.code_address));
+        }
+        println!(
+            "{frame}, value {:#x}, pc {}, gas remaining {}",
+            call_frame.msg_value, call_frame.pc, call_frame.gas_remaining
+        );
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hex number
+fn parse_u256(value: &str) -> Result<U256, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    };
+    parsed.ok_or_else(|| format!("Invalid number {value}"))
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    parse_u256(value)?
+        .try_into()
+        .map_err(|_| format!("Number {value} is too big"))
+}
+
+fn parse_address(value: &str) -> Result<Address, String> {
+    Address::from_str(value).map_err(|_| format!("Invalid address {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ethrex_blockchain::vm::StoreVmDatabase;
+    use ethrex_common::types::{Fork, LegacyTransaction, Transaction, TxKind};
+    use ethrex_levm::{EVMConfig, Environment, db::gen_db::GeneralizedDatabase, vm::VMType};
+    use ethrex_storage::{EngineType, Store};
+    use ethrex_vm::DynVmDatabase;
+
+    use super::*;
+
+    const CONTRACT: Address = Address::repeat_byte(0x42);
+
+    fn test_db() -> GeneralizedDatabase {
+        let store = Store::new("", EngineType::InMemory).unwrap();
+        let store: DynVmDatabase = Box::new(StoreVmDatabase::new(store, H256::zero()));
+        GeneralizedDatabase::new(Arc::new(store))
+    }
+
+    fn test_vm(db: &mut GeneralizedDatabase) -> VM<'_> {
+        let env = Environment {
+            gas_limit: 100_000,
+            block_gas_limit: 100_000,
+            config: EVMConfig::new(Fork::Prague, EVMConfig::canonical_values(Fork::Prague)),
+            ..Default::default()
+        };
+        let tx = Transaction::LegacyTransaction(LegacyTransaction {
+            gas: 100_000,
+            to: TxKind::Call(CONTRACT),
+            ..Default::default()
+        });
+        VM::new(env, db, &tx, VMType::L1).unwrap()
+    }
+
+    fn run(debugger: &mut Debugger, line: &str, vm: &VM<'_>) -> Result<Option<Mode>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        debugger.run_command(words[0], &words[1..], vm)
+    }
+
+    #[test]
+    fn resuming_commands_set_the_mode() {
+        let mut db = test_db();
+        let vm = test_vm(&mut db);
+        let mut debugger = Debugger::new();
+
+        assert_eq!(run(&mut debugger, "step", &vm), Ok(Some(Mode::Step)));
+        assert_eq!(run(&mut debugger, "n", &vm), Ok(Some(Mode::Next(0))));
+        assert_eq!(run(&mut debugger, "c", &vm), Ok(Some(Mode::Continue)));
+        assert_eq!(run(&mut debugger, "quit", &vm), Ok(Some(Mode::Detached)));
+        assert_eq!(run(&mut debugger, "stack", &vm), Ok(None));
+        assert!(run(&mut debugger, "jump", &vm).is_err());
+    }
+
+    #[test]
+    fn breakpoints_are_added_and_deleted() {
+        let mut db = test_db();
+        let vm = test_vm(&mut db);
+        let mut debugger = Debugger::new();
+
+        run(&mut debugger, "break pc 0x10", &vm).unwrap();
+        run(&mut debugger, &format!("b pc 3 {CONTRACT:#x}"), &vm).unwrap();
+        run(&mut debugger, "break op sstore", &vm).unwrap();
+        run(&mut debugger, "watch 1", &vm).unwrap();
+        assert!(run(&mut debugger, "break op NOTANOPCODE", &vm).is_err());
+        assert!(run(&mut debugger, "break pc", &vm).is_err());
+        assert!(run(&mut debugger, "watch 1 not-an-address", &vm).is_err());
+        assert_eq!(debugger.stops.len(), 4);
+        assert!(matches!(
+            debugger.stops[1],
+            Stop::Pc {
+                pc: 3,
+                address: Some(CONTRACT)
+            }
+        ));
+        assert!(matches!(debugger.stops[2], Stop::Opcode(Opcode::SSTORE)));
+
+        run(&mut debugger, "delete 0", &vm).unwrap();
+        assert!(run(&mut debugger, "delete 3", &vm).is_err());
+        assert_eq!(debugger.stops.len(), 3);
+        assert!(matches!(debugger.stops[0], Stop::Pc { pc: 3, .. }));
+    }
+
+    #[test]
+    fn pauses_follow_the_mode_and_the_breakpoints() {
+        let mut db = test_db();
+        let vm = test_vm(&mut db);
+        let call_frame = &vm.current_call_frame;
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.should_pause(Opcode::PUSH1, call_frame));
+
+        debugger.mode = Mode::Continue;
+        assert!(!debugger.should_pause(Opcode::PUSH1, call_frame));
+
+        debugger.mode = Mode::Next(call_frame.depth + 1);
+        assert!(debugger.should_pause(Opcode::PUSH1, call_frame));
+
+        debugger.mode = Mode::Continue;
+        debugger.stops.push(Stop::Opcode(Opcode::SSTORE));
+        debugger.stops.push(Stop::Pc {
+            pc: call_frame.pc,
+            address: Some(Address::repeat_byte(0x01)),
+        });
+        assert!(!debugger.should_pause(Opcode::PUSH1, call_frame));
+        assert!(debugger.should_pause(Opcode::SSTORE, call_frame));
+
+        debugger.stops.push(Stop::Pc {
+            pc: call_frame.pc,
+            address: Some(CONTRACT),
+        });
+        assert!(debugger.should_pause(Opcode::PUSH1, call_frame));
+    }
+
+    #[test]
+    fn watched_storage_writes_pause_on_the_next_opcode() {
+        let mut db = test_db();
+        let vm = test_vm(&mut db);
+        let call_frame = &vm.current_call_frame;
+        let mut debugger = Debugger::new();
+        debugger.mode = Mode::Continue;
+        let key = H256::from_low_u64_be(1);
+        debugger.stops.push(Stop::Slot {
+            key,
+            address: Some(CONTRACT),
+        });
+
+        debugger.storage_write(Address::repeat_byte(0x01), key, U256::zero(), U256::one());
+        debugger.storage_write(
+            CONTRACT,
+            H256::from_low_u64_be(2),
+            U256::zero(),
+            U256::one(),
+        );
+        assert!(!debugger.should_pause(Opcode::PUSH1, call_frame));
+
+        debugger.storage_write(CONTRACT, key, U256::zero(), U256::one());
+        assert!(debugger.should_pause(Opcode::PUSH1, call_frame));
+        // The hit is only reported once
+        assert!(!debugger.should_pause(Opcode::PUSH1, call_frame));
+    }
+
+    #[test]
+    fn numbers_are_parsed_as_decimal_or_hex() {
+        assert_eq!(parse_u256("10"), Ok(U256::from(10)));
+        assert_eq!(parse_u256("0x10"), Ok(U256::from(16)));
+        assert!(parse_u256("0xzz").is_err());
+        assert!(parse_number(&format!("{}", U256::MAX)).is_err());
+    }
+}
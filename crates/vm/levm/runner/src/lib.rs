@@ -1 +1,3 @@
+pub mod debugger;
 pub mod input;
+pub mod replay;
@@ -4,14 +4,14 @@ use env_logger::Env;
 use ethrex_blockchain::vm::StoreVmDatabase;
 use ethrex_common::{
     Address, H160, H256, U256,
-    types::{Account, LegacyTransaction, Transaction},
+    types::{Account, Genesis, LegacyTransaction, Transaction},
 };
 use ethrex_levm::{
     EVMConfig, Environment,
     account::LevmAccount,
     db::gen_db::GeneralizedDatabase,
+    inspector::Inspector,
     opcodes::Opcode,
-    tracing::LevmCallTracer,
     vm::{VM, VMType},
 };
 use ethrex_storage::Store;
@@ -19,8 +19,11 @@ use ethrex_vm::DynVmDatabase;
 use log::{debug, error, info};
 use num_bigint::BigUint;
 use num_traits::Num;
-use runner::input::{InputAccount, InputTransaction, RunnerInput};
-use std::{collections::BTreeMap, io::Write};
+use runner::{
+    debugger::Debugger,
+    input::{InputAccount, InputTransaction, RunnerInput},
+};
+use std::{cell::RefCell, collections::BTreeMap, io::Write, rc::Rc};
 use std::{
     fs::{self, File},
     io::BufReader,
@@ -46,6 +49,36 @@ struct Cli {
         help = "Converts mnemonics file into a bytecode file"
     )]
     emit_bytes: Option<String>,
+
+    #[arg(
+        long,
+        short,
+        action = clap::ArgAction::SetTrue,
+        help = "Execute the transaction step by step in an interactive debugger"
+    )]
+    debug: bool,
+
+    #[arg(
+        long,
+        value_name = "TX_HASH",
+        requires_all = ["datadir", "network"],
+        help = "Replay a transaction from the store of a node instead of running the input"
+    )]
+    replay: Option<H256>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Data directory of the node to replay the transaction from"
+    )]
+    datadir: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "GENESIS_FILE_PATH",
+        help = "Genesis file of the node to replay the transaction from"
+    )]
+    network: Option<String>,
 }
 
 fn main() {
@@ -80,6 +113,17 @@ fn main() {
         return;
     }
 
+    if let Some(tx_hash) = cli.replay {
+        let datadir = cli
+            .datadir
+            .expect("A datadir is needed to replay a transaction");
+        let network = cli
+            .network
+            .expect("The genesis file is needed to replay a transaction");
+        replay_transaction(&datadir, &network, tx_hash, cli.debug);
+        return;
+    }
+
     // Parse input
     // Input is mutable just to assign bytecode to the transaction recipient if provided
     let mut runner_input: RunnerInput = if let Some(input_file_path) = cli.input {
@@ -149,7 +193,6 @@ fn main() {
         env,
         &mut db,
         &Transaction::LegacyTransaction(LegacyTransaction::from(runner_input.transaction.clone())),
-        LevmCallTracer::disabled(),
         VMType::L1,
     )
     .expect("Failed to initialize VM");
@@ -169,6 +212,10 @@ fn main() {
         .memory
         .store_data(0, &runner_input.initial_memory);
 
+    if cli.debug {
+        start_debugger(&mut vm);
+    }
+
     // Execute Transaction
     let result = vm.execute();
 
@@ -200,6 +247,39 @@ fn main() {
     );
 }
 
+fn start_debugger(vm: &mut VM<'_>) {
+    info!("Starting debugger, run `help` to list the commands");
+    vm.set_inspector(Rc::new(RefCell::new(Debugger::new())));
+}
+
+/// Replays a transaction from the database of a node, which is opened read-only
+fn replay_transaction(datadir: &str, genesis_path: &str, tx_hash: H256, debug: bool) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start the async runtime");
+    let genesis_file = File::open(genesis_path).expect("Failed to open the genesis file");
+    let genesis: Genesis = serde_json::from_reader(BufReader::new(genesis_file))
+        .expect("Failed to parse the genesis file");
+    let store = Store::new_read_only(datadir).expect("Failed to open the store");
+    runtime
+        .block_on(store.load_secondary_state(genesis))
+        .expect("Failed to load the store, is the genesis the one of the node?");
+
+    let inspector: Option<Rc<RefCell<dyn Inspector>>> = debug.then(|| {
+        info!("Starting debugger, run `help` to list the commands");
+        Rc::new(RefCell::new(Debugger::new())) as Rc<RefCell<dyn Inspector>>
+    });
+    let result = runtime.block_on(runner::replay::replay_transaction(
+        store, tx_hash, inspector,
+    ));
+    info!("\n\nResult:");
+    match result {
+        Ok(report) => info!(" {:?}\n", report),
+        Err(e) => error!(" Error: {}\n", e),
+    }
+}
+
 /// Prints on screen difference between initial state and current one.
 fn compare_initial_and_current_accounts(
     initial_accounts: BTreeMap<Address, LevmAccount>,
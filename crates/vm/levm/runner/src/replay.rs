@@ -0,0 +1,254 @@
+//! Replay of transactions already stored by a node, on top of the state of their parent block.
+
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use ethrex_blockchain::vm::StoreVmDatabase;
+use ethrex_common::{H256, types::BlockHash};
+use ethrex_levm::{
+    db::gen_db::GeneralizedDatabase,
+    errors::{ExecutionReport, VMError},
+    inspector::Inspector,
+    vm::{VM, VMType},
+};
+use ethrex_storage::{Store, error::StoreError};
+use ethrex_vm::{DynVmDatabase, EvmError, backends::levm::LEVM};
+use log::info;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Transaction {0:#x} not found in the store")]
+    TransactionNotFound(H256),
+    #[error("Block {0:#x} not found in the store")]
+    BlockNotFound(BlockHash),
+    #[error("State of block {0:#x} not found in the store, the node may have pruned it")]
+    MissingState(BlockHash),
+    #[error("Failed to execute the block: {0}")]
+    Evm(#[from] EvmError),
+    #[error("Failed to execute the transaction: {0}")]
+    Vm(#[from] VMError),
+}
+
+/// ## Replays a transaction of the store
+/// - Executes the transactions that precede it in its block, on top of the state of the parent block
+/// - Executes it with the given inspector, if any, and returns its report
+pub async fn replay_transaction(
+    store: Store,
+    tx_hash: H256,
+    inspector: Option<Rc<RefCell<dyn Inspector>>>,
+) -> Result<ExecutionReport, ReplayError> {
+    let (block_number, block_hash, index) = store
+        .get_transaction_location(tx_hash)
+        .await?
+        .ok_or(ReplayError::TransactionNotFound(tx_hash))?;
+    let block = store
+        .get_block_by_hash(block_hash)
+        .await?
+        .ok_or(ReplayError::BlockNotFound(block_hash))?;
+    let parent_hash = block.header.parent_hash;
+    let parent = store
+        .get_block_header_by_hash(parent_hash)?
+        .ok_or(ReplayError::BlockNotFound(parent_hash))?;
+    if !store.contains_state_node(parent.state_root)? {
+        return Err(ReplayError::MissingState(parent_hash));
+    }
+    info!("Replaying transaction {tx_hash:#x}, number {index} of block {block_number}");
+
+    let vm_db: DynVmDatabase = Box::new(StoreVmDatabase::new(store, parent_hash));
+    let mut db = GeneralizedDatabase::new(Arc::new(vm_db));
+    LEVM::prepare_block(&block, &mut db, VMType::L1)?;
+
+    let transactions = block
+        .body
+        .get_transactions_with_sender()
+        .map_err(|error| EvmError::Transaction(error.to_string()))?;
+    let index = usize::try_from(index).unwrap_or(usize::MAX);
+    let (tx, sender) = transactions
+        .get(index)
+        .ok_or(ReplayError::TransactionNotFound(tx_hash))?;
+    for (previous_tx, previous_sender) in &transactions[..index] {
+        LEVM::execute_tx(
+            previous_tx,
+            *previous_sender,
+            &block.header,
+            &mut db,
+            VMType::L1,
+        )?;
+    }
+
+    let env = LEVM::setup_env(tx, *sender, &block.header, &mut db)?;
+    let mut vm = VM::new(env, &mut db, tx, VMType::L1)?;
+    if let Some(inspector) = inspector {
+        vm.set_inspector(inspector);
+    }
+    Ok(vm.execute()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use ethrex_common::{
+        Address, U256,
+        types::{
+            Block, BlockBody, BlockHeader, Genesis, GenesisAccount, LegacyTransaction, Transaction,
+            TxKind,
+        },
+    };
+    use ethrex_levm::errors::TxResult;
+    use ethrex_rlp::structs::Encoder;
+    use ethrex_storage::{EngineType, UpdateBatch};
+    use secp256k1::{Message, SECP256K1, SecretKey};
+    use sha3::{Digest, Keccak256};
+
+    use super::*;
+
+    const RECIPIENT: Address = Address::repeat_byte(0x42);
+
+    fn sender_key() -> SecretKey {
+        SecretKey::from_slice(&[0x11; 32]).unwrap()
+    }
+
+    fn address_of(key: &SecretKey) -> Address {
+        let public_key = key.public_key(SECP256K1).serialize_uncompressed();
+        Address::from_slice(&Keccak256::digest(&public_key[1..])[12..])
+    }
+
+    fn transfer(nonce: u64) -> Transaction {
+        let mut tx = LegacyTransaction {
+            nonce,
+            gas_price: 10_000_000_000,
+            gas: 21_000,
+            to: TxKind::Call(RECIPIENT),
+            value: U256::one(),
+            ..Default::default()
+        };
+        let mut payload = Vec::new();
+        Encoder::new(&mut payload)
+            .encode_field(&tx.nonce)
+            .encode_field(&tx.gas_price)
+            .encode_field(&tx.gas)
+            .encode_field(&tx.to)
+            .encode_field(&tx.value)
+            .encode_field(&tx.data)
+            .finish();
+        let message = Message::from_digest(Keccak256::digest(&payload).into());
+        let (recovery_id, signature) = SECP256K1
+            .sign_ecdsa_recoverable(&message, &sender_key())
+            .serialize_compact();
+        tx.r = U256::from_big_endian(&signature[..32]);
+        tx.s = U256::from_big_endian(&signature[32..]);
+        tx.v = U256::from(i32::from(recovery_id) + 27);
+        Transaction::LegacyTransaction(tx)
+    }
+
+    async fn store_with_genesis() -> (Store, BlockHeader) {
+        let file = File::open("../../../../fixtures/genesis/execution-api.json")
+            .expect("Failed to open genesis file");
+        let mut genesis: Genesis =
+            serde_json::from_reader(BufReader::new(file)).expect("Failed to parse genesis file");
+        genesis.alloc.insert(
+            address_of(&sender_key()),
+            GenesisAccount {
+                code: Default::default(),
+                storage: Default::default(),
+                balance: U256::from(10).pow(18.into()),
+                nonce: 0,
+            },
+        );
+        let genesis_header = genesis.get_block().header;
+        let store = Store::new("", EngineType::InMemory).unwrap();
+        store.add_initial_state(genesis).await.unwrap();
+        (store, genesis_header)
+    }
+
+    fn child_header(parent: &BlockHeader) -> BlockHeader {
+        BlockHeader {
+            hash: Default::default(),
+            parent_hash: parent.hash(),
+            number: parent.number + 1,
+            timestamp: parent.timestamp + 12,
+            ..parent.clone()
+        }
+    }
+
+    fn block(header: BlockHeader, transactions: Vec<Transaction>) -> Block {
+        Block::new(
+            header,
+            BlockBody {
+                transactions,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Stores the blocks as canonical, without executing them
+    async fn add_canonical_blocks(store: &Store, blocks: Vec<Block>) {
+        let canonical = blocks
+            .iter()
+            .map(|block| (block.header.number, block.hash()))
+            .collect::<Vec<_>>();
+        let (head_number, head_hash) = *canonical.last().unwrap();
+        store
+            .store_block_updates(UpdateBatch {
+                account_updates: vec![],
+                storage_updates: vec![],
+                blocks,
+                receipts: vec![],
+                senders: vec![],
+                code_updates: vec![],
+                preimages: vec![],
+            })
+            .await
+            .unwrap();
+        store
+            .forkchoice_update(Some(canonical), head_number, head_hash, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn replays_a_transaction_after_the_previous_ones_of_its_block() {
+        let (store, genesis_header) = store_with_genesis().await;
+        let block = block(
+            child_header(&genesis_header),
+            vec![transfer(0), transfer(1)],
+        );
+        add_canonical_blocks(&store, vec![block]).await;
+
+        // The second transfer is only valid once the first one bumped the nonce
+        let report = replay_transaction(store, transfer(1).hash(), None)
+            .await
+            .unwrap();
+        assert!(matches!(report.result, TxResult::Success));
+        assert_eq!(report.gas_used, 21_000);
+    }
+
+    #[tokio::test]
+    async fn unknown_transactions_are_reported() {
+        let (store, _) = store_with_genesis().await;
+        let tx_hash = transfer(0).hash();
+
+        let error = replay_transaction(store, tx_hash, None).await.unwrap_err();
+        assert!(matches!(error, ReplayError::TransactionNotFound(hash) if hash == tx_hash));
+    }
+
+    #[tokio::test]
+    async fn missing_parent_state_is_reported_instead_of_panicking() {
+        let (store, genesis_header) = store_with_genesis().await;
+        // A parent whose state was never stored, like one pruned by the node
+        let parent = BlockHeader {
+            state_root: H256::repeat_byte(0xaa),
+            ..child_header(&genesis_header)
+        };
+        let parent_hash = parent.hash();
+        let child = block(child_header(&parent), vec![transfer(0)]);
+        add_canonical_blocks(&store, vec![block(parent, vec![]), child]).await;
+
+        let error = replay_transaction(store, transfer(0).hash(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ReplayError::MissingState(hash) if hash == parent_hash));
+    }
+}
@@ -0,0 +1,237 @@
+//! Cache of analyzed bytecode shared across transactions and blocks.
+//!
+//! Every call frame needs to know which offsets of its bytecode are valid jump destinations, which
+//! requires walking the bytecode skipping the data of push instructions, and decodes the value of
+//! every push it executes. Contracts are usually called many times per block, so both are computed
+//! once and cached by code hash. As the bytecode of a code hash never changes, entries never need
+//! to be invalidated, only evicted to bound the memory used.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use bytes::Bytes;
+use ethrex_common::{H256, U256};
+
+use crate::opcodes::Opcode;
+
+/// Default bound of the total size of the analyses cached, 64 MiB
+pub const DEFAULT_CODE_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Bytecode along with its valid jump destinations and the values of its pushes
+#[derive(Debug)]
+pub struct AnalyzedCode {
+    pub bytecode: Bytes,
+    /// Bitmap of the offsets holding a `JUMPDEST` that isn't part of the data of a push
+    jump_targets: Vec<u64>,
+    /// Value of each `PUSH1`..`PUSH32`, by the offset of its data, sorted by offset. Pushes
+    /// truncated by the end of the bytecode are left out.
+    push_values: Vec<(usize, U256)>,
+}
+
+impl AnalyzedCode {
+    #[expect(
+        clippy::arithmetic_side_effects,
+        reason = "offsets are bounded by the bytecode length and opcode is at least PUSH1"
+    )]
+    pub fn new(bytecode: Bytes) -> Self {
+        let mut jump_targets = vec![0; bytecode.len().div_ceil(64)];
+        let mut push_values = Vec::new();
+        let push1 = u8::from(Opcode::PUSH1);
+        let push32 = u8::from(Opcode::PUSH32);
+        let jumpdest = u8::from(Opcode::JUMPDEST);
+
+        let mut offset = 0;
+        while let Some(&opcode) = bytecode.get(offset) {
+            if opcode == jumpdest {
+                if let Some(word) = jump_targets.get_mut(offset / 64) {
+                    *word |= 1 << (offset % 64);
+                }
+            } else if (push1..=push32).contains(&opcode) {
+                // Skip the data of the push, which is between 1 and 32 bytes long
+                let data_offset = offset + 1;
+                let data_len = usize::from(opcode - push1 + 1);
+                if let Some(data) = bytecode.get(data_offset..data_offset + data_len) {
+                    push_values.push((data_offset, U256::from_big_endian(data)));
+                }
+                offset += data_len;
+            }
+            offset += 1;
+        }
+
+        Self {
+            bytecode,
+            jump_targets,
+            push_values,
+        }
+    }
+
+    /// Whether `offset` holds a `JUMPDEST` opcode, not a byte of push data
+    #[expect(clippy::arithmetic_side_effects, reason = "the shift is less than 64")]
+    pub fn is_jump_target(&self, offset: usize) -> bool {
+        self.jump_targets
+            .get(offset / 64)
+            .is_some_and(|word| word & (1 << (offset % 64)) != 0)
+    }
+
+    /// Value pushed by the push whose data starts at `offset`, `None` if there's no such push
+    pub fn push_value(&self, offset: usize) -> Option<U256> {
+        self.push_values
+            .binary_search_by_key(&offset, |(data_offset, _)| *data_offset)
+            .ok()
+            .and_then(|index| self.push_values.get(index))
+            .map(|(_, value)| *value)
+    }
+
+    /// Approximate amount of memory used by the analysis, bytecode included
+    #[expect(
+        clippy::arithmetic_side_effects,
+        reason = "bounded by the memory allocated by the analysis"
+    )]
+    pub fn size(&self) -> usize {
+        self.bytecode.len()
+            + self.jump_targets.len() * size_of::<u64>()
+            + self.push_values.len() * size_of::<(usize, U256)>()
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Analysis of each code hash, along with the tick it was last used at
+    codes: HashMap<H256, (Arc<AnalyzedCode>, u64)>,
+    /// Code hashes by the tick they were last used at, the least recently used ones are evicted
+    /// first
+    by_last_use: BTreeMap<u64, H256>,
+    /// Incremented on every use of an entry
+    tick: u64,
+    /// Total size of the analyses cached
+    size: usize,
+}
+
+impl Entries {
+    /// Marks the entry of `code_hash` as the most recently used, returning its analysis
+    fn touch(&mut self, code_hash: H256) -> Option<Arc<AnalyzedCode>> {
+        let (code, last_use) = self.codes.get_mut(&code_hash)?;
+        self.by_last_use.remove(last_use);
+        self.tick = self.tick.wrapping_add(1);
+        *last_use = self.tick;
+        self.by_last_use.insert(self.tick, code_hash);
+        Some(code.clone())
+    }
+}
+
+impl std::fmt::Debug for Entries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entries")
+            .field("len", &self.codes.len())
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// Bounded cache of [`AnalyzedCode`] by code hash, safe to share between threads.
+/// Once the size of the analyses cached exceeds `max_size`, the least recently used entries are
+/// evicted.
+#[derive(Debug)]
+pub struct CodeCache {
+    entries: Mutex<Entries>,
+    max_size: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for CodeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CODE_CACHE_SIZE)
+    }
+}
+
+impl CodeCache {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            max_size,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the analysis of the bytecode with the given hash, analyzing and caching it if it
+    /// isn't cached yet. Counts as a hit or a miss of the cache.
+    pub fn analyze(&self, code_hash: H256, bytecode: &Bytes) -> Arc<AnalyzedCode> {
+        if let Some(code) = self.lock().touch(code_hash) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return code;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // Analyzed without holding the lock, other threads may be analyzing the same code
+        let code = Arc::new(AnalyzedCode::new(bytecode.clone()));
+        let size = code.size();
+        let mut entries = self.lock();
+        if entries.codes.contains_key(&code_hash) || size > self.max_size {
+            return code;
+        }
+        entries.size = entries.size.saturating_add(size);
+        while entries.size > self.max_size {
+            let Some((_, evicted)) = entries.by_last_use.pop_first() else {
+                break;
+            };
+            if let Some((evicted, _)) = entries.codes.remove(&evicted) {
+                entries.size = entries.size.saturating_sub(evicted.size());
+            }
+        }
+        entries.tick = entries.tick.wrapping_add(1);
+        let tick = entries.tick;
+        entries.codes.insert(code_hash, (code.clone(), tick));
+        entries.by_last_use.insert(tick, code_hash);
+        code
+    }
+
+    /// Returns the bytecode with the given hash if it's cached, without counting as a hit or a miss.
+    pub fn get_bytecode(&self, code_hash: &H256) -> Option<Bytes> {
+        self.lock()
+            .codes
+            .get(code_hash)
+            .map(|(code, _)| code.bytecode.clone())
+    }
+
+    /// Whether the analysis of the bytecode with the given hash is cached
+    pub fn contains(&self, code_hash: &H256) -> bool {
+        self.lock().codes.contains_key(code_hash)
+    }
+
+    /// Amount of analyses found in the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Amount of analyses not found in the cache
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Amount of codes cached
+    pub fn len(&self) -> usize {
+        self.lock().codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total size of the analyses cached
+    pub fn size(&self) -> usize {
+        self.lock().size
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
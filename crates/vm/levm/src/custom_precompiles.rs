@@ -0,0 +1,134 @@
+//! Custom precompiles for L2 chains.
+//!
+//! Rollups can add their own precompiles (e.g. a reader of L1 block info or a Poseidon hash) on
+//! top of the standard ones. They are given to the VM through the database, like the custom
+//! hooks, and only honoured when it runs with [`VMType::L2`](crate::vm::VMType), so the L1
+//! execution is never affected.
+//!
+//! Every program executing the L2 blocks has to use the same precompiles, that is the node (block
+//! producer and RPC) and the guest program of the prover. Otherwise their executions diverge.
+//!
+//! ```ignore
+//! let precompiles = CustomPrecompiles::new([CustomPrecompile {
+//!     address: Address::from_low_u64_be(0xfff0),
+//!     name: "POSEIDON",
+//!     activation: Fork::Prague,
+//!     gas_cost: |calldata| 60 + 6 * calldata.len().div_ceil(32) as u64,
+//!     execute: poseidon_hash,
+//! }])?;
+//! ```
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use ethrex_common::{
+    Address,
+    types::{Fork, precompiles::CustomPrecompileSpec},
+};
+
+use crate::{
+    errors::VMError,
+    precompiles::{PRECOMPILES, increase_precompile_consumed_gas},
+};
+
+/// Gas consumed by a call to a custom precompile with the given calldata
+pub type CustomPrecompileGasFn = fn(calldata: &Bytes) -> u64;
+
+/// Implementation of a custom precompile, returning its output. The gas is already consumed when
+/// it's called. Errors make the call fail, consuming all the gas sent to it.
+pub type CustomPrecompileFn = fn(calldata: &Bytes, fork: Fork) -> Result<Bytes, VMError>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CustomPrecompile {
+    pub address: Address,
+    pub name: &'static str,
+    /// First fork the precompile is active in. Before it, the address is a regular account.
+    pub activation: Fork,
+    pub gas_cost: CustomPrecompileGasFn,
+    pub execute: CustomPrecompileFn,
+}
+
+impl CustomPrecompile {
+    /// Consumes the gas of the call from `gas_remaining` and executes it.
+    pub fn call(
+        &self,
+        calldata: &Bytes,
+        gas_remaining: &mut u64,
+        fork: Fork,
+    ) -> Result<Bytes, VMError> {
+        increase_precompile_consumed_gas((self.gas_cost)(calldata), gas_remaining)?;
+        (self.execute)(calldata, fork)
+    }
+
+    pub fn spec(&self) -> CustomPrecompileSpec {
+        CustomPrecompileSpec {
+            address: self.address,
+            activation: self.activation,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum CustomPrecompileError {
+    #[error("Address {0:#x} is reserved for a standard precompile")]
+    ReservedAddress(Address),
+    #[error("More than one precompile at {0:#x}")]
+    DuplicatedAddress(Address),
+}
+
+/// Custom precompiles of a chain, by address. Cheap to clone, so that it can be shared by every
+/// VM executing the chain.
+#[derive(Debug, Clone, Default)]
+pub struct CustomPrecompiles {
+    precompiles: Arc<BTreeMap<Address, CustomPrecompile>>,
+}
+
+impl CustomPrecompiles {
+    /// Addresses of standard precompiles, of any fork, can't be used.
+    pub fn new(
+        precompiles: impl IntoIterator<Item = CustomPrecompile>,
+    ) -> Result<Self, CustomPrecompileError> {
+        let mut by_address = BTreeMap::new();
+        for precompile in precompiles {
+            let address = precompile.address;
+            if PRECOMPILES
+                .iter()
+                .any(|standard| standard.address == address)
+            {
+                return Err(CustomPrecompileError::ReservedAddress(address));
+            }
+            if by_address.insert(address, precompile).is_some() {
+                return Err(CustomPrecompileError::DuplicatedAddress(address));
+            }
+        }
+        Ok(Self {
+            precompiles: Arc::new(by_address),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.precompiles.is_empty()
+    }
+
+    /// Precompile at `address`, if there's one active in `fork`.
+    pub fn get(&self, address: &Address, fork: Fork) -> Option<&CustomPrecompile> {
+        self.precompiles
+            .get(address)
+            .filter(|precompile| fork >= precompile.activation)
+    }
+
+    /// Precompiles active in `fork`, sorted by address.
+    pub fn active(&self, fork: Fork) -> impl Iterator<Item = &CustomPrecompile> {
+        self.precompiles
+            .values()
+            .filter(move |precompile| fork >= precompile.activation)
+    }
+
+    /// Address and activation of every precompile, sorted by address.
+    pub fn specs(&self) -> Vec<CustomPrecompileSpec> {
+        self.precompiles
+            .values()
+            .map(CustomPrecompile::spec)
+            .collect()
+    }
+}
@@ -12,8 +12,12 @@ use super::Database;
 use crate::account::AccountStatus;
 use crate::account::LevmAccount;
 use crate::call_frame::CallFrameBackup;
+use crate::code_cache::CodeCache;
+use crate::custom_precompiles::CustomPrecompiles;
 use crate::errors::InternalError;
 use crate::errors::VMError;
+use crate::hooks::hook::CustomHooks;
+use crate::utils::JumpTargetFilter;
 use crate::utils::account_to_levm_account;
 use crate::utils::restore_cache_state;
 use crate::vm::VM;
@@ -29,6 +33,12 @@ pub struct GeneralizedDatabase {
     pub initial_accounts_state: CacheDB,
     pub codes: BTreeMap<H256, Bytes>,
     pub tx_backup: Option<CallFrameBackup>,
+    /// Cache of analyzed bytecode shared with other databases, usually those of previous blocks
+    pub code_cache: Option<Arc<CodeCache>>,
+    /// Hooks added to those of the VM type in every VM executed on this database
+    pub custom_hooks: CustomHooks,
+    /// Precompiles added to the standard ones in every L2 VM executed on this database
+    pub custom_precompiles: CustomPrecompiles,
 }
 
 impl GeneralizedDatabase {
@@ -39,6 +49,9 @@ impl GeneralizedDatabase {
             initial_accounts_state: CacheDB::new(),
             tx_backup: None,
             codes: BTreeMap::new(),
+            code_cache: None,
+            custom_hooks: CustomHooks::default(),
+            custom_precompiles: CustomPrecompiles::default(),
         }
     }
 
@@ -62,6 +75,9 @@ impl GeneralizedDatabase {
             initial_accounts_state: levm_accounts,
             tx_backup: None,
             codes,
+            code_cache: None,
+            custom_hooks: CustomHooks::default(),
+            custom_precompiles: CustomPrecompiles::default(),
         }
     }
 
@@ -100,12 +116,38 @@ impl GeneralizedDatabase {
         match self.codes.entry(code_hash) {
             Entry::Occupied(entry) => Ok(entry.into_mut()),
             Entry::Vacant(entry) => {
-                let code = self.store.get_account_code(code_hash)?;
+                let cached = self
+                    .code_cache
+                    .as_ref()
+                    .and_then(|cache| cache.get_bytecode(&code_hash));
+                let code = match cached {
+                    Some(code) => code,
+                    None => self.store.get_account_code(code_hash)?,
+                };
                 Ok(entry.insert(code))
             }
         }
     }
 
+    /// Filter of the jump targets of `bytecode`, the code of the account at `code_address`.
+    /// The analysis of the code is taken from the code cache, if any.
+    pub fn jump_target_filter(
+        &mut self,
+        code_address: Address,
+        bytecode: &Bytes,
+    ) -> Result<JumpTargetFilter, InternalError> {
+        let Some(cache) = self.code_cache.clone() else {
+            return Ok(JumpTargetFilter::new(bytecode.clone()));
+        };
+        if bytecode.is_empty() {
+            return Ok(JumpTargetFilter::new(bytecode.clone()));
+        }
+        let code_hash = self.get_account(code_address)?.info.code_hash;
+        Ok(JumpTargetFilter::from_analysis(
+            cache.analyze(code_hash, bytecode),
+        ))
+    }
+
     /// Shortcut for getting the code when we only have the address of an account and we don't need anything else.
     pub fn get_account_code(&mut self, address: Address) -> Result<&Bytes, InternalError> {
         let code_hash = self.get_account(address)?.info.code_hash;
@@ -258,6 +300,98 @@ impl GeneralizedDatabase {
         self.codes.clear();
         Ok(account_updates)
     }
+
+    /// Returns the changes made to the cached state since `checkpoint`, an earlier copy of `current_accounts_state`,
+    /// and brings the checkpoint up to date with them.
+    /// Accounts missing from the checkpoint were loaded after it was taken, so their state back then is the initial one.
+    /// Unlike `get_state_transitions` the cache is kept, so execution can go on after getting the changes.
+    pub fn get_state_transitions_since(
+        &self,
+        checkpoint: &mut CacheDB,
+    ) -> Result<Vec<AccountUpdate>, VMError> {
+        let mut account_updates: Vec<AccountUpdate> = vec![];
+        for (address, new_state_account) in self.current_accounts_state.iter() {
+            if new_state_account.is_unmodified() {
+                continue;
+            }
+            let initial_state_account =
+                self.initial_accounts_state
+                    .get(address)
+                    .ok_or(VMError::Internal(InternalError::Custom(format!(
+                        "Failed to get account {address} from immutable cache",
+                    ))))?;
+            let old_state_account = checkpoint.get(address).unwrap_or(initial_state_account);
+            if old_state_account == new_state_account {
+                continue;
+            }
+            let old_destroyed_modified =
+                old_state_account.status == AccountStatus::DestroyedModified;
+
+            // The account was destroyed and created again since the checkpoint, its storage is replaced as a whole
+            if new_state_account.status == AccountStatus::DestroyedModified
+                && !old_destroyed_modified
+            {
+                account_updates.push(AccountUpdate::removed(*address));
+                account_updates.push(AccountUpdate {
+                    address: *address,
+                    removed: false,
+                    info: Some(new_state_account.info.clone()),
+                    code: Some(self.get_cached_code(address, new_state_account)?),
+                    added_storage: new_state_account.storage.clone(),
+                });
+                checkpoint.insert(*address, new_state_account.clone());
+                continue;
+            }
+
+            let info_updated = old_state_account.info != new_state_account.info;
+            let code = if old_state_account.info.code_hash != new_state_account.info.code_hash {
+                Some(self.get_cached_code(address, new_state_account)?)
+            } else {
+                None
+            };
+
+            let mut added_storage = BTreeMap::new();
+            for (key, new_value) in &new_state_account.storage {
+                // Slots missing from the checkpoint weren't changed before it, unless the storage
+                // of the account had been cleared by destroying it
+                let old_value = match old_state_account.storage.get(key) {
+                    Some(value) => *value,
+                    None if old_destroyed_modified => U256::zero(),
+                    None => *initial_state_account.storage.get(key).ok_or_else(|| {
+                        VMError::Internal(InternalError::Custom(format!(
+                            "Failed to get old value from account's initial storage for address: {address}"
+                        )))
+                    })?,
+                };
+                if *new_value != old_value {
+                    added_storage.insert(*key, *new_value);
+                }
+            }
+
+            let removed = new_state_account.is_empty() && !old_state_account.is_empty();
+            if removed || info_updated || !added_storage.is_empty() {
+                account_updates.push(AccountUpdate {
+                    address: *address,
+                    removed,
+                    info: info_updated.then(|| new_state_account.info.clone()),
+                    code,
+                    added_storage,
+                });
+            }
+            checkpoint.insert(*address, new_state_account.clone());
+        }
+        Ok(account_updates)
+    }
+
+    /// Code of an account of the cache, which is always loaded along with it
+    fn get_cached_code(&self, address: &Address, account: &LevmAccount) -> Result<Bytes, VMError> {
+        self.codes
+            .get(&account.info.code_hash)
+            .cloned()
+            .ok_or(VMError::Internal(InternalError::Custom(format!(
+                "Failed to get code for account {address}"
+            ))))
+    }
 }
 
 impl<'a> VM<'a> {
@@ -0,0 +1,240 @@
+//! Opcode tracer following [EIP-3155](https://eips.ethereum.org/EIPS/eip-3155), the trace format
+//! shared by the clients and the tools that compare their executions.
+//!
+//! Traces have a line per opcode executed, followed by a summary of the transaction. Optional
+//! fields (memory, return data and storage) aren't output.
+
+use std::io::{self, Write};
+
+use bytes::Bytes;
+use ethrex_common::{U256, serde_utils};
+use serde::Serialize;
+
+use crate::{
+    errors::{ExecutionReport, TxResult},
+    inspector::{CallInputs, CallOutcome, Inspector},
+    opcodes::Opcode,
+    vm::VM,
+};
+
+/// Opcode about to be executed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip3155Step {
+    pub pc: u64,
+    pub op: u8,
+    /// Gas remaining before executing the opcode
+    #[serde(with = "serde_utils::u64::hex_str")]
+    pub gas: u64,
+    /// Gas consumed by the opcode. For calls and creations, the gas used by the new call frame
+    /// isn't included.
+    #[serde(with = "serde_utils::u64::hex_str")]
+    pub gas_cost: u64,
+    /// Size of the memory in bytes
+    pub mem_size: u64,
+    /// Stack values, with the top of the stack last
+    pub stack: Vec<U256>,
+    /// Depth of the call frame, 1 for the transaction itself
+    pub depth: u64,
+    /// Gas refunded so far by the transaction
+    pub refund: u64,
+    pub op_name: String,
+}
+
+/// Outcome of the transaction, the last line of its trace
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip3155Summary {
+    #[serde(with = "serde_utils::bytes")]
+    pub output: Bytes,
+    #[serde(with = "serde_utils::u64::hex_str")]
+    pub gas_used: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Call frame being executed
+struct Frame {
+    gas_limit: u64,
+    /// Index of the last step of the frame, whose gas cost is known once the next one starts
+    pending: Option<usize>,
+    /// Gas used by the call frames created by the pending step
+    calls_gas: u64,
+}
+
+/// It is an [`Inspector`], install it with [`VM::set_inspector`], end the transaction once it's
+/// executed with [`Eip3155Tracer::end_transaction`] and check the trace was written with
+/// [`Eip3155Tracer::finish`].
+///
+/// Steps are written to the output as JSON lines as soon as their gas cost is known, only the
+/// steps of the calls and creations still running are kept in memory.
+pub struct Eip3155Tracer {
+    /// Steps not written yet, starting from the one at index `written_steps`
+    steps: Vec<Eip3155Step>,
+    written_steps: usize,
+    frames: Vec<Frame>,
+    output: Box<dyn Write>,
+    /// First error writing to the output, nothing else is written after it
+    error: Option<io::Error>,
+}
+
+impl Eip3155Tracer {
+    pub fn new(output: impl Write + 'static) -> Self {
+        Self {
+            steps: Vec::new(),
+            written_steps: 0,
+            frames: Vec::new(),
+            output: Box::new(output),
+            error: None,
+        }
+    }
+
+    /// Ends the trace of the transaction executed, which left `gas_remaining` to its call frame,
+    /// writing its remaining steps and its summary.
+    pub fn end_transaction(&mut self, gas_remaining: u64, report: &ExecutionReport) {
+        if let Some(mut frame) = self.frames.pop() {
+            self.settle(&mut frame, gas_remaining);
+        }
+        self.frames.clear();
+        self.write_settled_steps();
+        let summary = Eip3155Summary {
+            output: report.output.clone(),
+            gas_used: report.gas_used,
+            error: match &report.result {
+                TxResult::Success => None,
+                TxResult::Revert(error) => Some(error.to_string()),
+            },
+        };
+        self.write_line(&summary);
+    }
+
+    /// Flushes the output, returning the first error found while writing the trace
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.output.flush(),
+        }
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) {
+        if self.error.is_none()
+            && let Err(error) = write_json_line(&mut self.output, value)
+        {
+            self.error = Some(error);
+        }
+    }
+
+    /// Writes the steps whose gas cost is known, which are all the ones before the oldest pending
+    /// step: the pending steps of outer frames are calls and creations waiting for the inner ones.
+    fn write_settled_steps(&mut self) {
+        let settled = self
+            .frames
+            .iter()
+            .find_map(|frame| frame.pending)
+            .map_or(self.steps.len(), |index| {
+                index.saturating_sub(self.written_steps)
+            })
+            .min(self.steps.len());
+        for step in self.steps.drain(..settled) {
+            if self.error.is_none()
+                && let Err(error) = write_json_line(&mut self.output, &step)
+            {
+                self.error = Some(error);
+            }
+        }
+        self.written_steps += settled;
+    }
+
+    /// Sets the gas cost of the pending step of `frame`, which left `gas_remaining`.
+    fn settle(&mut self, frame: &mut Frame, gas_remaining: u64) {
+        let Some(step) = frame
+            .pending
+            .take()
+            .and_then(|index| index.checked_sub(self.written_steps))
+            .and_then(|index| self.steps.get_mut(index))
+        else {
+            return;
+        };
+        step.gas_cost = step
+            .gas
+            .saturating_sub(gas_remaining)
+            .saturating_sub(frame.calls_gas);
+        frame.calls_gas = 0;
+    }
+
+    fn enter_frame(&mut self, call: &CallInputs<'_>) {
+        if call.depth == 0 {
+            // Leftovers of a transaction that wasn't ended
+            self.written_steps += self.steps.len();
+            self.steps.clear();
+            self.frames.clear();
+        }
+        self.frames.push(Frame {
+            gas_limit: call.gas_limit,
+            pending: None,
+            calls_gas: 0,
+        });
+    }
+}
+
+impl Inspector for Eip3155Tracer {
+    fn step_vm(&mut self, opcode: Opcode, vm: &VM<'_>) {
+        let call_frame = &vm.current_call_frame;
+        let gas = u64::try_from(call_frame.gas_remaining).unwrap_or_default();
+        if let Some(mut frame) = self.frames.pop() {
+            self.settle(&mut frame, gas);
+            frame.pending = Some(self.written_steps + self.steps.len());
+            self.frames.push(frame);
+        }
+
+        let stack = &call_frame.stack;
+        self.steps.push(Eip3155Step {
+            pc: u64::try_from(call_frame.pc).unwrap_or(u64::MAX),
+            op: u8::from(opcode),
+            gas,
+            gas_cost: 0,
+            mem_size: u64::try_from(call_frame.memory.len()).unwrap_or(u64::MAX),
+            stack: stack
+                .values
+                .get(stack.offset..)
+                .unwrap_or_default()
+                .iter()
+                .rev()
+                .copied()
+                .collect(),
+            depth: u64::try_from(call_frame.depth)
+                .unwrap_or(u64::MAX)
+                .saturating_add(1),
+            refund: vm.substate.refunded_gas,
+            op_name: format!("{opcode:?}"),
+        });
+        self.write_settled_steps();
+    }
+
+    fn call_enter(&mut self, call: &CallInputs<'_>) {
+        self.enter_frame(call);
+    }
+
+    fn create(&mut self, call: &CallInputs<'_>) {
+        self.enter_frame(call);
+    }
+
+    fn call_exit(&mut self, outcome: &CallOutcome<'_>) {
+        // The transaction is ended by `end_transaction`, as its gas used includes the refunds
+        if self.frames.len() > 1
+            && let Some(mut frame) = self.frames.pop()
+        {
+            let gas_remaining = frame.gas_limit.saturating_sub(outcome.gas_used);
+            self.settle(&mut frame, gas_remaining);
+            if let Some(parent) = self.frames.last_mut() {
+                parent.calls_gas = parent.calls_gas.saturating_add(outcome.gas_used);
+            }
+            self.write_settled_steps();
+        }
+    }
+}
+
+fn write_json_line(output: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *output, value)?;
+    output.write_all(b"\n")
+}
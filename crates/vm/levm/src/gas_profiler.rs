@@ -0,0 +1,320 @@
+//! Gas profiler, an [`Inspector`] that aggregates where the gas of transactions goes.
+//!
+//! Gas and execution time are aggregated per opcode, per contract and per function selector of
+//! each contract. The gas of each opcode is its own cost: for calls and creations the gas used by
+//! the new call frame is accounted to the callee instead. Profiles can also be output as folded
+//! stacks, the input format of flamegraph tools.
+//!
+//! The same profiler can be installed in the VMs of several transactions to profile a whole block,
+//! calling [`GasProfiler::end_transaction`] after executing each of them.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use ethrex_common::Address;
+use serde::Serialize;
+
+use crate::{
+    call_frame::CallFrame,
+    errors::ExecutionReport,
+    inspector::{CallInputs, CallOutcome, Inspector},
+    opcodes::Opcode,
+};
+
+/// Gas and time spent by the transactions profiled
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasProfile {
+    pub transactions: u64,
+    /// Gas used by the transactions, after refunds
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    /// Gas charged before execution, for the transaction data, access lists and authorizations
+    pub intrinsic_gas: u64,
+    /// Stats of each opcode, by name
+    pub opcodes: BTreeMap<String, OpcodeStats>,
+    /// Stats of each contract whose code was executed, precompiles included
+    pub contracts: BTreeMap<Address, ContractStats>,
+    /// Gas of each stack of calls and opcode, in the folded format of flamegraph tools
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folded: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpcodeStats {
+    pub count: u64,
+    pub gas: u64,
+    pub time_ns: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallStats {
+    pub calls: u64,
+    /// Gas used by the calls, including their subcalls
+    pub gas: u64,
+    /// Gas used by the calls, excluding their subcalls
+    pub self_gas: u64,
+    /// Time spent executing the calls, excluding their subcalls
+    pub self_time_ns: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractStats {
+    #[serde(flatten)]
+    pub stats: CallStats,
+    /// Stats of each function called, by selector. Calls with less than 4 bytes of calldata are
+    /// profiled as `fallback` and contract creations as `constructor`.
+    pub functions: BTreeMap<String, CallStats>,
+}
+
+/// Opcode being executed in a call frame
+struct PendingOpcode {
+    opcode: Opcode,
+    gas_remaining: u64,
+    started: Instant,
+    /// Gas used and time spent by the calls made by the opcode
+    calls_gas: u64,
+    calls_time: Duration,
+}
+
+struct Frame {
+    address: Address,
+    selector: String,
+    gas_limit: u64,
+    /// Gas remaining when the first opcode was executed
+    initial_gas: Option<u64>,
+    started: Instant,
+    /// Index of the stack of calls leading to this frame, see [`GasProfiler::stacks`]
+    stack: usize,
+    pending: Option<PendingOpcode>,
+    /// Gas used and time spent by the calls made by this frame
+    calls_gas: u64,
+    calls_time: Duration,
+}
+
+#[derive(Default)]
+pub struct GasProfiler {
+    profile: GasProfile,
+    /// Stats of each opcode, added to the profile when it's returned
+    opcodes: HashMap<u8, OpcodeStats>,
+    frames: Vec<Frame>,
+    /// Stacks of calls found, each one as the labels of its call frames separated by `;`
+    stacks: Vec<String>,
+    stack_indexes: HashMap<String, usize>,
+    /// Gas of each stack of calls, by opcode. Gas spent by call frames that don't execute any
+    /// opcode, as precompiles, is keyed by `None`.
+    folded: HashMap<(usize, Option<u8>), u64>,
+}
+
+impl GasProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Finishes profiling the transaction executed, which left `gas_remaining` to its call frame.
+    pub fn end_transaction(&mut self, gas_remaining: u64, report: &ExecutionReport) {
+        self.exit_frame(gas_remaining);
+        self.frames.clear();
+        self.profile.transactions = self.profile.transactions.saturating_add(1);
+        self.profile.gas_used = self.profile.gas_used.saturating_add(report.gas_used);
+        self.profile.gas_refunded = self
+            .profile
+            .gas_refunded
+            .saturating_add(report.gas_refunded);
+    }
+
+    /// Returns the profile of the transactions executed so far, along with its folded stacks if
+    /// `with_folded` is set.
+    pub fn profile(&self, with_folded: bool) -> GasProfile {
+        let mut profile = self.profile.clone();
+        for (opcode, stats) in &self.opcodes {
+            let total = profile
+                .opcodes
+                .entry(format!("{:?}", Opcode::from(*opcode)))
+                .or_default();
+            total.count = total.count.saturating_add(stats.count);
+            total.gas = total.gas.saturating_add(stats.gas);
+            total.time_ns = total.time_ns.saturating_add(stats.time_ns);
+        }
+        if with_folded {
+            let lines: BTreeMap<String, u64> = self
+                .folded
+                .iter()
+                .filter(|(_, gas)| **gas > 0)
+                .filter_map(|((stack, opcode), gas)| {
+                    let stack = self.stacks.get(*stack)?;
+                    let line = match opcode {
+                        Some(opcode) => format!("{stack};{:?}", Opcode::from(*opcode)),
+                        None => stack.clone(),
+                    };
+                    Some((line, *gas))
+                })
+                .collect();
+            let folded = lines
+                .into_iter()
+                .map(|(line, gas)| format!("{line} {gas}\n"))
+                .collect();
+            profile.folded = Some(folded);
+        }
+        profile
+    }
+
+    fn stack_index(&mut self, stack: String) -> usize {
+        if let Some(index) = self.stack_indexes.get(&stack) {
+            return *index;
+        }
+        let index = self.stacks.len();
+        self.stacks.push(stack.clone());
+        self.stack_indexes.insert(stack, index);
+        index
+    }
+
+    /// Accounts the opcode pending in the current frame, which left `gas_remaining`.
+    fn end_opcode(&mut self, gas_remaining: u64) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        let Some(pending) = frame.pending.take() else {
+            return;
+        };
+        let gas = pending
+            .gas_remaining
+            .saturating_sub(gas_remaining)
+            .saturating_sub(pending.calls_gas);
+        let time = pending.started.elapsed().saturating_sub(pending.calls_time);
+
+        let opcode = u8::from(pending.opcode);
+        let stats = self.opcodes.entry(opcode).or_default();
+        stats.count = stats.count.saturating_add(1);
+        stats.gas = stats.gas.saturating_add(gas);
+        stats.time_ns = stats.time_ns.saturating_add(nanos(time));
+
+        let folded = self.folded.entry((frame.stack, Some(opcode))).or_default();
+        *folded = folded.saturating_add(gas);
+    }
+
+    /// Accounts the current frame, which ended with `gas_remaining`.
+    fn exit_frame(&mut self, gas_remaining: u64) {
+        self.end_opcode(gas_remaining);
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        let initial_gas = frame.initial_gas.unwrap_or(frame.gas_limit);
+        let gas = initial_gas.saturating_sub(gas_remaining);
+        let self_gas = gas.saturating_sub(frame.calls_gas);
+        let time = frame.started.elapsed();
+        let self_time = nanos(time.saturating_sub(frame.calls_time));
+
+        if frame.initial_gas.is_none() {
+            // No opcodes were executed, e.g. a precompile, so its gas is accounted to the call
+            let folded = self.folded.entry((frame.stack, None)).or_default();
+            *folded = folded.saturating_add(self_gas);
+        }
+
+        let contract = self.profile.contracts.entry(frame.address).or_default();
+        let function = contract.functions.entry(frame.selector).or_default();
+        for stats in [&mut contract.stats, function] {
+            stats.calls = stats.calls.saturating_add(1);
+            stats.gas = stats.gas.saturating_add(gas);
+            stats.self_gas = stats.self_gas.saturating_add(self_gas);
+            stats.self_time_ns = stats.self_time_ns.saturating_add(self_time);
+        }
+
+        if let Some(parent) = self.frames.last_mut() {
+            parent.calls_gas = parent.calls_gas.saturating_add(gas);
+            parent.calls_time = parent.calls_time.saturating_add(time);
+            if let Some(pending) = parent.pending.as_mut() {
+                pending.calls_gas = pending.calls_gas.saturating_add(gas);
+                pending.calls_time = pending.calls_time.saturating_add(time);
+            }
+        }
+    }
+
+    fn enter_frame(&mut self, call: &CallInputs<'_>, is_create: bool) {
+        if call.depth == 0 {
+            // Leftovers of a transaction that wasn't ended
+            self.frames.clear();
+            // Recorded up front, as transactions that don't execute any code (e.g. transfers)
+            // have no steps
+            self.profile.intrinsic_gas = self
+                .profile
+                .intrinsic_gas
+                .saturating_add(call.intrinsic_gas);
+        }
+        let selector = match call.input.first_chunk::<4>() {
+            _ if is_create => "constructor".to_string(),
+            Some(selector) => format!("{:#010x}", u32::from_be_bytes(*selector)),
+            None => "fallback".to_string(),
+        };
+        let label = format!("{:#x}:{selector}", call.to);
+        let stack = match self.frames.last() {
+            Some(parent) => {
+                let parent_stack = self.stacks.get(parent.stack).cloned().unwrap_or_default();
+                format!("{parent_stack};{label}")
+            }
+            None => label,
+        };
+        let stack = self.stack_index(stack);
+        self.frames.push(Frame {
+            address: call.to,
+            selector,
+            // The gas of the transaction itself doesn't include its intrinsic gas
+            gas_limit: call.gas_limit.saturating_sub(call.intrinsic_gas),
+            initial_gas: None,
+            started: Instant::now(),
+            stack,
+            pending: None,
+            calls_gas: 0,
+            calls_time: Duration::ZERO,
+        });
+    }
+}
+
+impl Inspector for GasProfiler {
+    fn step(&mut self, opcode: Opcode, call_frame: &CallFrame) {
+        let gas_remaining = u64::try_from(call_frame.gas_remaining).unwrap_or_default();
+        self.end_opcode(gas_remaining);
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        if frame.initial_gas.is_none() {
+            frame.initial_gas = Some(gas_remaining);
+        }
+        frame.pending = Some(PendingOpcode {
+            opcode,
+            gas_remaining,
+            started: Instant::now(),
+            calls_gas: 0,
+            calls_time: Duration::ZERO,
+        });
+    }
+
+    fn call_enter(&mut self, call: &CallInputs<'_>) {
+        self.enter_frame(call, false);
+    }
+
+    fn create(&mut self, call: &CallInputs<'_>) {
+        self.enter_frame(call, true);
+    }
+
+    fn call_exit(&mut self, outcome: &CallOutcome<'_>) {
+        // The transaction is ended by `end_transaction`, as its gas used includes the refunds
+        if self.frames.len() > 1 {
+            let gas_limit = self
+                .frames
+                .last()
+                .map(|frame| frame.gas_limit)
+                .unwrap_or_default();
+            self.exit_frame(gas_limit.saturating_sub(outcome.gas_used));
+        }
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
@@ -122,7 +122,7 @@ impl Hook for DefaultHook {
     /// ## Changes post execution
     /// 1. Undo value transfer if the transaction was reverted
     /// 2. Return unused gas + gas refunds to the sender.
-    /// 3. Pay coinbase fee
+    /// 3. Pay coinbase fee, unless deferred to the caller
     /// 4. Destruct addresses in selfdestruct set.
     fn finalize_execution(
         &mut self,
@@ -137,7 +137,9 @@ impl Hook for DefaultHook {
         let actual_gas_used = compute_actual_gas_used(vm, gas_refunded, ctx_result.gas_used)?;
         refund_sender(vm, ctx_result, gas_refunded, actual_gas_used)?;
 
-        pay_coinbase(vm, actual_gas_used)?;
+        if !vm.defer_coinbase_payment {
+            pay_coinbase(vm, actual_gas_used)?;
+        }
 
         delete_self_destruct_accounts(vm)?;
 
@@ -489,22 +491,26 @@ pub fn transfer_value(vm: &mut VM<'_>) -> Result<(), VMError> {
 /// Sets bytecode and code_address to CallFrame
 pub fn set_bytecode_and_code_address(vm: &mut VM<'_>) -> Result<(), VMError> {
     // Get bytecode and code_address for assigning those values to the callframe.
-    let (bytecode, code_address) = if vm.is_create()? {
+    let (bytecode, code_address, jump_target_filter) = if vm.is_create()? {
         // Here bytecode is the calldata and the code_address is just the created contract address.
         let calldata = std::mem::take(&mut vm.current_call_frame.calldata);
-        (calldata, vm.current_call_frame.to)
+        (calldata, vm.current_call_frame.to, None)
     } else {
         // Here bytecode and code_address could be either from the account or from the delegated account.
         let to = vm.current_call_frame.to;
         let (_is_delegation, _eip7702_gas_consumed, code_address, bytecode) =
             eip7702_get_code(vm.db, &mut vm.substate, to)?;
+        let jump_target_filter = vm.db.jump_target_filter(code_address, &bytecode)?;
 
-        (bytecode, code_address)
+        (bytecode, code_address, Some(jump_target_filter))
     };
 
     // Assign code and code_address to callframe
     vm.current_call_frame.code_address = code_address;
     vm.current_call_frame.set_code(bytecode)?;
+    if let Some(jump_target_filter) = jump_target_filter {
+        vm.current_call_frame.jump_target_filter = jump_target_filter;
+    }
 
     Ok(())
 }
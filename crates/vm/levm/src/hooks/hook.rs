@@ -4,7 +4,7 @@ use crate::{
     vm::{VM, VMType},
 };
 use ethrex_common::types::fee_config::FeeConfig;
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fmt, rc::Rc, sync::Arc};
 
 pub trait Hook {
     fn prepare_execution(&mut self, vm: &mut VM<'_>) -> Result<(), VMError>;
@@ -16,20 +16,75 @@ pub trait Hook {
     ) -> Result<(), VMError>;
 }
 
-pub fn get_hooks(vm_type: &VMType) -> Vec<Rc<RefCell<dyn Hook + 'static>>> {
+type HookBuilder = Arc<dyn Fn() -> Rc<RefCell<dyn Hook>> + Send + Sync>;
+
+/// Hooks supplied by embedders of the VM, run after the hook of the VM type (`DefaultHook` or
+/// `L2Hook`) both before and after executing each transaction.
+///
+/// Hooks keep state across the execution of a transaction, so a new instance of each hook is
+/// built for every transaction from the function given when adding it.
+#[derive(Clone, Default)]
+pub struct CustomHooks {
+    builders: Vec<HookBuilder>,
+}
+
+impl CustomHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a hook, built with `build` for every transaction.
+    pub fn with<H, F>(mut self, build: F) -> Self
+    where
+        H: Hook + 'static,
+        F: Fn() -> H + Send + Sync + 'static,
+    {
+        self.builders
+            .push(Arc::new(move || Rc::new(RefCell::new(build()))));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.builders.is_empty()
+    }
+
+    /// Builds a new instance of each hook.
+    pub fn build(&self) -> impl Iterator<Item = Rc<RefCell<dyn Hook>>> + '_ {
+        self.builders.iter().map(|build| build())
+    }
+}
+
+impl fmt::Debug for CustomHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomHooks")
+            .field("len", &self.builders.len())
+            .finish()
+    }
+}
+
+pub fn get_hooks(
+    vm_type: &VMType,
+    custom_hooks: &CustomHooks,
+) -> Vec<Rc<RefCell<dyn Hook + 'static>>> {
     match vm_type {
-        VMType::L1 => l1_hooks(),
-        VMType::L2(fee_config) => l2_hooks(*fee_config),
+        VMType::L1 => l1_hooks(custom_hooks),
+        VMType::L2(fee_config) => l2_hooks(*fee_config, custom_hooks),
     }
 }
 
-pub fn l1_hooks() -> Vec<Rc<RefCell<dyn Hook + 'static>>> {
-    vec![Rc::new(RefCell::new(DefaultHook))]
+pub fn l1_hooks(custom_hooks: &CustomHooks) -> Vec<Rc<RefCell<dyn Hook + 'static>>> {
+    let mut hooks: Vec<Rc<RefCell<dyn Hook>>> = vec![Rc::new(RefCell::new(DefaultHook))];
+    hooks.extend(custom_hooks.build());
+    hooks
 }
 
-pub fn l2_hooks(fee_config: FeeConfig) -> Vec<Rc<RefCell<dyn Hook + 'static>>> {
-    vec![
-        Rc::new(RefCell::new(L2Hook { fee_config })),
-        Rc::new(RefCell::new(BackupHook::default())),
-    ]
+pub fn l2_hooks(
+    fee_config: FeeConfig,
+    custom_hooks: &CustomHooks,
+) -> Vec<Rc<RefCell<dyn Hook + 'static>>> {
+    let mut hooks: Vec<Rc<RefCell<dyn Hook>>> = vec![Rc::new(RefCell::new(L2Hook { fee_config }))];
+    hooks.extend(custom_hooks.build());
+    // Goes last so that the changes of the other hooks can be undone
+    hooks.push(Rc::new(RefCell::new(BackupHook::default())));
+    hooks
 }
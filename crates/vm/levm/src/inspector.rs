@@ -0,0 +1,142 @@
+use crate::{
+    call_frame::CallFrame,
+    errors::{ContextResult, TxResult},
+    opcodes::Opcode,
+    vm::VM,
+};
+use bytes::Bytes;
+use ethrex_common::{Address, H256, U256, tracing::CallType, types::Log};
+
+/// Observes the execution of a transaction, for building custom analyzers without modifying the VM.
+/// Every hook does nothing by default, so implementors only override the ones they need.
+///
+/// Inspectors are installed with [`VM::set_inspector`]. When none is installed the opcode loop
+/// doesn't look for one, and the rest of the hooks only check an empty `Option`.
+pub trait Inspector {
+    /// Called before executing each opcode, with the program counter of `call_frame` pointing at it.
+    fn step(&mut self, _opcode: Opcode, _call_frame: &CallFrame) {}
+
+    /// Same as [`Inspector::step`], with read access to the whole VM for inspectors that need more
+    /// than the current call frame, e.g. the parent call frames, the substate or the cached state.
+    /// By default it calls [`Inspector::step`].
+    fn step_vm(&mut self, opcode: Opcode, vm: &VM<'_>) {
+        self.step(opcode, &vm.current_call_frame)
+    }
+
+    /// Called when a message call (CALL, CALLCODE, DELEGATECALL, STATICCALL or the transaction
+    /// itself) starts. Calls to precompiles are included.
+    fn call_enter(&mut self, _call: &CallInputs) {}
+
+    /// Called when a contract creation (CREATE, CREATE2 or a create transaction) starts.
+    /// `call.to` is the address of the new contract.
+    fn create(&mut self, _call: &CallInputs) {}
+
+    /// Called when a message call or contract creation ends, including those that fail before
+    /// starting to execute (e.g. for lacking funds or going over the max depth).
+    fn call_exit(&mut self, _outcome: &CallOutcome) {}
+
+    /// Called when a LOG opcode is executed. Logs of calls that later revert are reported too.
+    fn log(&mut self, _log: &Log) {}
+
+    /// Called when SLOAD reads a storage slot.
+    fn storage_read(&mut self, _address: Address, _key: H256, _value: U256) {}
+
+    /// Called when SSTORE writes a storage slot, before the write is applied.
+    fn storage_write(&mut self, _address: Address, _key: H256, _previous: U256, _new: U256) {}
+
+    /// Called when SELFDESTRUCT is executed.
+    fn selfdestruct(&mut self, _address: Address, _beneficiary: Address, _balance: U256) {}
+}
+
+/// Message call or contract creation about to start
+#[derive(Debug)]
+pub struct CallInputs<'a> {
+    pub call_type: CallType,
+    pub from: Address,
+    /// Callee, code address for CALLCODE and DELEGATECALL, or new contract for creations
+    pub to: Address,
+    pub value: U256,
+    pub gas_limit: u64,
+    /// Calldata, or init code for creations
+    pub input: &'a Bytes,
+    /// Depth of the new call frame, 0 for the transaction itself
+    pub depth: usize,
+    /// Gas charged to the transaction before executing it, for its data, access list and
+    /// authorizations. Always 0 for calls other than the transaction itself.
+    pub intrinsic_gas: u64,
+}
+
+/// Result of a message call or contract creation
+#[derive(Debug)]
+pub struct CallOutcome<'a> {
+    pub gas_used: u64,
+    pub output: &'a Bytes,
+    /// Reason of the failure, `None` if the call succeeded
+    pub error: Option<String>,
+}
+
+impl<'a> VM<'a> {
+    /// Runs `f` on the installed inspector, if any.
+    #[inline(always)]
+    pub(crate) fn inspect(&self, f: impl FnOnce(&mut dyn Inspector)) {
+        if let Some(inspector) = &self.inspector {
+            f(&mut *inspector.borrow_mut());
+        }
+    }
+
+    /// Notifies the start of a message call or contract creation from the current call frame.
+    #[inline(always)]
+    pub(crate) fn inspect_call_enter(
+        &self,
+        call_type: CallType,
+        from: Address,
+        to: Address,
+        value: U256,
+        gas_limit: u64,
+        input: &Bytes,
+    ) {
+        self.inspect(|inspector| {
+            let call = CallInputs {
+                call_type,
+                from,
+                to,
+                value,
+                gas_limit,
+                input,
+                depth: self.current_call_frame.depth.saturating_add(1),
+                intrinsic_gas: 0,
+            };
+            match call_type {
+                CallType::CREATE | CallType::CREATE2 => inspector.create(&call),
+                _ => inspector.call_enter(&call),
+            }
+        });
+    }
+
+    /// Notifies the end of a message call or contract creation that was executed.
+    #[inline(always)]
+    pub(crate) fn inspect_call_exit(&self, ctx_result: &ContextResult) {
+        self.inspect(|inspector| {
+            inspector.call_exit(&CallOutcome {
+                gas_used: ctx_result.gas_used,
+                output: &ctx_result.output,
+                error: match &ctx_result.result {
+                    TxResult::Success => None,
+                    TxResult::Revert(error) => Some(error.to_string()),
+                },
+            })
+        });
+    }
+
+    /// Notifies the end of a message call or contract creation that failed before executing.
+    #[inline(always)]
+    pub(crate) fn inspect_early_exit(&self, gas_used: u64, error: &str) {
+        self.inspect(|inspector| {
+            inspector.call_exit(&CallOutcome {
+                gas_used,
+                output: &Bytes::new(),
+                error: Some(error.to_string()),
+            })
+        });
+    }
+}
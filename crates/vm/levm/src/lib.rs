@@ -1,12 +1,17 @@
 pub mod call_frame;
+pub mod code_cache;
 pub mod constants;
+pub mod custom_precompiles;
 pub mod db;
 pub mod debug;
+pub mod eip3155;
 pub mod environment;
 pub mod errors;
 pub mod execution_handlers;
 pub mod gas_cost;
+pub mod gas_profiler;
 pub mod hooks;
+pub mod inspector;
 pub mod memory;
 pub mod opcode_handlers;
 pub mod opcodes;
@@ -19,7 +19,10 @@ impl<'a> VM<'a> {
             Err(InternalError::Overflow)?;
         }
 
-        let value = if let Some(slice) = call_frame
+        let value = if let Some(value) = call_frame.jump_target_filter.push_value(call_frame.pc) {
+            // Precomputed by the code cache
+            value
+        } else if let Some(slice) = call_frame
             .bytecode
             .get(call_frame.pc..call_frame.pc.wrapping_add(N))
         {
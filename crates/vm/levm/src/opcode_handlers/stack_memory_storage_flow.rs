@@ -138,6 +138,7 @@ impl<'a> VM<'a> {
         let storage_slot_key = u256_to_h256(storage_slot_key);
 
         let (value, storage_slot_was_cold) = self.access_storage_slot(address, storage_slot_key)?;
+        self.inspect(|inspector| inspector.storage_read(address, storage_slot_key, value));
 
         let current_call_frame = &mut self.current_call_frame;
 
@@ -221,6 +222,10 @@ impl<'a> VM<'a> {
                 storage_slot_was_cold,
             )?)?;
 
+        self.inspect(|inspector| {
+            inspector.storage_write(to, key, current_value, new_storage_slot_value)
+        });
+
         if new_storage_slot_value != current_value {
             self.update_account_storage(to, key, new_storage_slot_value, current_value)?;
         }
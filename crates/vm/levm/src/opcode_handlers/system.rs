@@ -9,9 +9,7 @@ use crate::{
     vm::VM,
 };
 use bytes::Bytes;
-use ethrex_common::tracing::CallType::{
-    self, CALL, CALLCODE, DELEGATECALL, SELFDESTRUCT, STATICCALL,
-};
+use ethrex_common::tracing::CallType::{self, CALL, CALLCODE, DELEGATECALL, STATICCALL};
 use ethrex_common::{Address, U256, evm::calculate_create_address, types::Fork};
 
 // System Operations (10)
@@ -103,7 +101,7 @@ impl<'a> VM<'a> {
         let is_static = callframe.is_static;
         let data = self.get_calldata(args_offset, args_size)?;
 
-        self.tracer.enter(CALL, from, to, value, gas_limit, &data);
+        self.inspect_call_enter(CALL, from, to, value, gas_limit, &data);
 
         self.generic_call(
             gas_limit,
@@ -200,8 +198,7 @@ impl<'a> VM<'a> {
         let is_static = callframe.is_static;
         let data = self.get_calldata(args_offset, args_size)?;
 
-        self.tracer
-            .enter(CALLCODE, from, code_address, value, gas_limit, &data);
+        self.inspect_call_enter(CALLCODE, from, code_address, value, gas_limit, &data);
 
         self.generic_call(
             gas_limit,
@@ -318,8 +315,7 @@ impl<'a> VM<'a> {
         let data = self.get_calldata(args_offset, args_size)?;
 
         // In this trace the `from` is the current contract, we don't want the `from` to be, for example, the EOA that sent the transaction
-        self.tracer
-            .enter(DELEGATECALL, to, code_address, value, gas_limit, &data);
+        self.inspect_call_enter(DELEGATECALL, to, code_address, value, gas_limit, &data);
 
         self.generic_call(
             gas_limit,
@@ -413,8 +409,7 @@ impl<'a> VM<'a> {
         let to = address; // In this case address and the sub-context account are the same. Unlike CALLCODE or DELEGATECODE.
         let data = self.get_calldata(args_offset, args_size)?;
 
-        self.tracer
-            .enter(STATICCALL, from, to, value, gas_limit, &data);
+        self.inspect_call_enter(STATICCALL, from, to, value, gas_limit, &data);
 
         self.generic_call(
             gas_limit,
@@ -573,10 +568,7 @@ impl<'a> VM<'a> {
             self.substate.add_selfdestruct(to);
         }
 
-        self.tracer
-            .enter(SELFDESTRUCT, to, beneficiary, balance, 0, &Bytes::new());
-
-        self.tracer.exit_early(0, None)?;
+        self.inspect(|inspector| inspector.selfdestruct(to, beneficiary, balance));
 
         Ok(OpcodeResult::Halt)
     }
@@ -630,13 +622,12 @@ impl<'a> VM<'a> {
         // Add new contract to accessed addresses
         self.substate.add_accessed_address(new_address);
 
-        // Log CREATE in tracer
+        // Notify the CREATE to the inspector
         let call_type = match salt {
             Some(_) => CallType::CREATE2,
             None => CallType::CREATE,
         };
-        self.tracer
-            .enter(call_type, deployer, new_address, value, gas_limit, &code);
+        self.inspect_call_enter(call_type, deployer, new_address, value, gas_limit, &code);
 
         let new_depth = self
             .current_call_frame
@@ -667,8 +658,7 @@ impl<'a> VM<'a> {
         let new_account = self.get_account_mut(new_address)?;
         if new_account.create_would_collide() {
             self.current_call_frame.stack.push1(FAIL)?;
-            self.tracer
-                .exit_early(gas_limit, Some("CreateAccExists".to_string()))?;
+            self.inspect_early_exit(gas_limit, "CreateAccExists");
             return Ok(OpcodeResult::Continue);
         }
 
@@ -752,7 +742,9 @@ impl<'a> VM<'a> {
             return Ok(OpcodeResult::Continue);
         }
 
-        if precompiles::is_precompile(&code_address, self.env.config.fork, self.vm_type)
+        let custom_precompile = self.custom_precompile(&code_address);
+        if (custom_precompile.is_some()
+            || precompiles::is_precompile(&code_address, self.env.config.fork, self.vm_type))
             && !is_delegation_7702
         {
             let mut gas_remaining = gas_limit;
@@ -762,6 +754,7 @@ impl<'a> VM<'a> {
                 gas_limit,
                 &mut gas_remaining,
                 self.env.config.fork,
+                custom_precompile.as_ref(),
             )?;
 
             let call_frame = &mut self.current_call_frame;
@@ -804,14 +797,15 @@ impl<'a> VM<'a> {
                 self.transfer(msg_sender, to, value)?;
             }
 
-            self.tracer.exit_context(&ctx_result, false)?;
+            self.inspect_call_exit(&ctx_result);
         } else {
             let mut stack = self.stack_pool.pop().unwrap_or_default();
             stack.clear();
 
             let next_memory = self.current_call_frame.memory.next_memory();
+            let jump_target_filter = self.db.jump_target_filter(code_address, &bytecode)?;
 
-            let new_call_frame = CallFrame::new(
+            let mut new_call_frame = CallFrame::new(
                 msg_sender,
                 to,
                 code_address,
@@ -828,6 +822,7 @@ impl<'a> VM<'a> {
                 stack,
                 next_memory,
             );
+            new_call_frame.jump_target_filter = jump_target_filter;
             self.add_callframe(new_call_frame);
 
             // Transfer value from caller to callee.
@@ -923,7 +918,7 @@ impl<'a> VM<'a> {
             }
         };
 
-        self.tracer.exit_context(ctx_result, false)?;
+        self.inspect_call_exit(ctx_result);
 
         let mut stack = executed_call_frame.stack;
         stack.clear();
@@ -975,7 +970,7 @@ impl<'a> VM<'a> {
             }
         };
 
-        self.tracer.exit_context(ctx_result, false)?;
+        self.inspect_call_exit(ctx_result);
 
         let mut stack = executed_call_frame.stack;
         stack.clear();
@@ -1034,7 +1029,7 @@ impl<'a> VM<'a> {
             .ok_or(InternalError::Overflow)?;
         callframe.stack.push1(FAIL)?; // It's the same as revert for CREATE
 
-        self.tracer.exit_early(0, Some(reason))?;
+        self.inspect_early_exit(0, &reason);
         Ok(())
     }
 }
@@ -1,6 +1,6 @@
 use crate::{
-    errors::{ContextResult, InternalError, TxResult, VMError},
-    vm::VM,
+    errors::{InternalError, VMError},
+    inspector::{CallInputs, CallOutcome, Inspector},
 };
 use bytes::Bytes;
 use ethrex_common::{
@@ -10,7 +10,8 @@ use ethrex_common::{
 };
 
 /// Geth's callTracer (https://geth.ethereum.org/docs/developers/evm-tracing/built-in-tracers)
-/// Use `LevmCallTracer::disabled()` when tracing is not wanted.
+/// It is an [`Inspector`], install it with [`VM::set_inspector`](crate::vm::VM::set_inspector)
+/// and take the trace with [`LevmCallTracer::take_trace_result`] once the transaction is executed.
 #[derive(Debug, Default)]
 pub struct LevmCallTracer {
     /// Stack for tracer callframes, at the end of execution there will be only one element.
@@ -19,43 +20,34 @@ pub struct LevmCallTracer {
     pub only_top_call: bool,
     /// If true, trace logs
     pub with_log: bool,
-    /// If active is set to false it won't trace.
-    pub active: bool,
+    /// Amount of calls being executed, 1 while only the top call is.
+    depth: usize,
 }
 
 impl LevmCallTracer {
     pub fn new(only_top_call: bool, with_log: bool) -> Self {
         LevmCallTracer {
-            callframes: vec![],
             only_top_call,
             with_log,
-            active: true,
+            ..Default::default()
         }
     }
 
-    /// This is to keep LEVM's code clean, like `self.tracer.enter(...)`,
-    /// instead of something more complex or uglier when we don't want to trace.
-    /// (For now that we only implement one tracer it may be the most convenient solution)
-    pub fn disabled() -> Self {
-        LevmCallTracer {
-            active: false,
-            ..Default::default()
-        }
+    /// Takes the trace of the executed transaction.
+    pub fn take_trace_result(&mut self) -> Result<CallTraceFrame, VMError> {
+        self.callframes.pop().ok_or(InternalError::CallFrame.into())
     }
 
     /// Starts trace call.
-    pub fn enter(
+    fn enter(
         &mut self,
         call_type: CallType,
         from: Address,
         to: Address,
         value: U256,
         gas: u64,
-        input: &Bytes, // For avoiding cloning when calling (cleaner code)
+        input: &Bytes,
     ) {
-        if !self.active {
-            return;
-        }
         if self.only_top_call && !self.callframes.is_empty() {
             // Only create callframe if it's the first one to be created.
             return;
@@ -75,15 +67,16 @@ impl LevmCallTracer {
     }
 
     /// Exits trace call.
-    /// Has no validations because it's a private method.
     fn exit(
         &mut self,
         gas_used: u64,
         output: Bytes,
         error: Option<String>,
         revert_reason: Option<String>,
-    ) -> Result<(), InternalError> {
-        let mut callframe = self.callframes.pop().ok_or(InternalError::CallFrame)?;
+    ) {
+        let Some(mut callframe) = self.callframes.pop() else {
+            return;
+        };
 
         process_output(&mut callframe, gas_used, output, error, revert_reason);
 
@@ -93,79 +86,90 @@ impl LevmCallTracer {
         } else {
             self.callframes.push(callframe);
         };
-        Ok(())
     }
+}
 
-    /// Exits trace call using the ContextResult.
-    pub fn exit_context(
-        &mut self,
-        ctx_result: &ContextResult,
-        is_top_call: bool,
-    ) -> Result<(), InternalError> {
-        if !self.active {
-            return Ok(());
-        }
+impl Inspector for LevmCallTracer {
+    fn call_enter(&mut self, call: &CallInputs) {
+        self.depth = self.depth.saturating_add(1);
+        self.enter(
+            call.call_type,
+            call.from,
+            call.to,
+            call.value,
+            call.gas_limit,
+            call.input,
+        );
+    }
+
+    fn create(&mut self, call: &CallInputs) {
+        self.call_enter(call);
+    }
+
+    fn call_exit(&mut self, outcome: &CallOutcome) {
+        self.depth = self.depth.saturating_sub(1);
+        let is_top_call = self.depth == 0;
         if self.only_top_call && !is_top_call {
             // We just want to register top call
-            return Ok(());
+            return;
         }
-        if is_top_call {
+        if is_top_call && let Some(callframe) = self.callframes.last_mut() {
             // After finishing transaction execution clear all logs of callframes that reverted.
-            clear_reverted_logs(self.current_callframe_mut()?);
+            clear_reverted_logs(callframe);
         }
-        let (gas_used, output) = (ctx_result.gas_used, ctx_result.output.clone());
-
-        let (error, revert_reason) = match ctx_result.result {
-            TxResult::Revert(ref err) => {
-                let reason = String::from_utf8(ctx_result.output.to_vec()).ok();
-                (Some(err.to_string()), reason)
+        // Calls failing before executing have no output to take a reason from
+        let revert_reason = match outcome.error {
+            Some(_) if !outcome.output.is_empty() => {
+                String::from_utf8(outcome.output.to_vec()).ok()
             }
-            _ => (None, None),
+            _ => None,
         };
-
-        self.exit(gas_used, output, error, revert_reason)
-    }
-
-    /// Exits trace call when CALL or CREATE opcodes return early or in case SELFDESTRUCT is called.
-    pub fn exit_early(
-        &mut self,
-        gas_used: u64,
-        error: Option<String>,
-    ) -> Result<(), InternalError> {
-        if !self.active || self.only_top_call {
-            return Ok(());
-        }
-        self.exit(gas_used, Bytes::new(), error, None)
+        self.exit(
+            outcome.gas_used,
+            outcome.output.clone(),
+            outcome.error.clone(),
+            revert_reason,
+        );
     }
 
     /// Registers log when opcode log is executed.
     /// Note: Logs of callframes that reverted will be removed at end of execution.
-    pub fn log(&mut self, log: &Log) -> Result<(), InternalError> {
-        if !self.active || !self.with_log {
-            return Ok(());
+    fn log(&mut self, log: &Log) {
+        if !self.with_log {
+            return;
         }
-        if self.only_top_call && self.callframes.len() > 1 {
+        if self.only_top_call && self.depth > 1 {
             // Register logs for top call only.
-            return Ok(());
+            return;
         }
-        let callframe = self.current_callframe_mut()?;
+        let Some(callframe) = self.callframes.last_mut() else {
+            return;
+        };
+        let Ok(position) = callframe.calls.len().try_into() else {
+            return;
+        };
 
-        let log = CallLog {
+        callframe.logs.push(CallLog {
             address: log.address,
             topics: log.topics.clone(),
             data: log.data.clone(),
-            position: match callframe.calls.len().try_into() {
-                Ok(pos) => pos,
-                Err(_) => return Err(InternalError::TypeConversion),
-            },
-        };
-
-        callframe.logs.push(log);
-        Ok(())
+            position,
+        });
     }
 
-    fn current_callframe_mut(&mut self) -> Result<&mut CallTraceFrame, InternalError> {
-        self.callframes.last_mut().ok_or(InternalError::CallFrame)
+    /// SELFDESTRUCT is traced as a call that ends right away.
+    fn selfdestruct(&mut self, address: Address, beneficiary: Address, balance: U256) {
+        self.enter(
+            CallType::SELFDESTRUCT,
+            address,
+            beneficiary,
+            balance,
+            0,
+            &Bytes::new(),
+        );
+        if !self.only_top_call {
+            self.exit(0, Bytes::new(), None, None);
+        }
     }
 }
 
@@ -191,13 +195,3 @@ fn clear_reverted_logs(callframe: &mut CallTraceFrame) {
         clear_reverted_logs(subcall);
     }
 }
-
-impl<'a> VM<'a> {
-    /// This method is intended to be accessed after transaction execution
-    pub fn get_trace_result(&mut self) -> Result<CallTraceFrame, VMError> {
-        self.tracer
-            .callframes
-            .pop()
-            .ok_or(InternalError::CallFrame.into())
-    }
-}
@@ -2,6 +2,7 @@ use crate::{
     EVMConfig, Environment,
     account::{AccountStatus, LevmAccount},
     call_frame::CallFrameBackup,
+    code_cache::AnalyzedCode,
     constants::*,
     db::gen_db::GeneralizedDatabase,
     errors::{ExceptionalHalt, InternalError, TxValidationError, VMError},
@@ -29,7 +30,7 @@ use secp256k1::{
     ecdsa::{RecoverableSignature, RecoveryId},
 };
 use sha3::{Digest, Keccak256};
-use std::{collections::HashMap, iter::Enumerate};
+use std::{collections::HashMap, iter::Enumerate, sync::Arc};
 pub type Storage = HashMap<U256, H256>;
 
 // ================== Address related functions ======================
@@ -76,9 +77,12 @@ pub fn calculate_create2_address(
 /// # Filter for jump target offsets.
 ///
 /// Used to filter which program offsets are not valid jump targets. Implemented as a sorted list of
-/// offsets of bytes `0x5B` (`JUMPDEST`) within push constants.
+/// offsets of bytes `0x5B` (`JUMPDEST`) within push constants, unless an analysis of the whole
+/// bytecode is given, see [`CodeCache`](crate::code_cache::CodeCache).
 #[derive(Debug)]
 pub struct JumpTargetFilter {
+    /// Analysis of the bytecode, used instead of the lazy filter when present.
+    analysis: Option<Arc<AnalyzedCode>>,
     /// The list of invalid jump target offsets.
     filter: Vec<usize>,
     /// The last processed offset, plus one.
@@ -94,6 +98,7 @@ impl JumpTargetFilter {
     /// Create an empty `JumpTargetFilter`.
     pub fn new(bytecode: Bytes) -> Self {
         Self {
+            analysis: None,
             filter: Vec::new(),
             offset: 0,
 
@@ -102,10 +107,32 @@ impl JumpTargetFilter {
         }
     }
 
+    /// Create a `JumpTargetFilter` from an analysis of the bytecode.
+    pub fn from_analysis(analysis: Arc<AnalyzedCode>) -> Self {
+        Self {
+            analysis: Some(analysis),
+            filter: Vec::new(),
+            offset: 0,
+
+            iter: Bytes::new().into_iter().enumerate(),
+            partial: 0,
+        }
+    }
+
+    /// Value of the push whose data starts at `offset`, if it was precomputed by the analysis of
+    /// the bytecode.
+    #[inline(always)]
+    pub fn push_value(&self, offset: usize) -> Option<U256> {
+        self.analysis.as_ref()?.push_value(offset)
+    }
+
     /// Check whether a target jump address is blacklisted or not.
     ///
     /// This method may potentially grow the filter if the requested address is out of range.
     pub fn is_blacklisted(&mut self, address: usize) -> bool {
+        if let Some(analysis) = &self.analysis {
+            return !analysis.is_jump_target(address);
+        }
         if let Some(delta) = address.checked_sub(self.offset) {
             // It is not realistic to expect a bytecode offset to overflow an `usize`.
             #[expect(clippy::arithmetic_side_effects)]
@@ -1,6 +1,7 @@
 use crate::{
     TransientStorage,
     call_frame::{CallFrame, Stack},
+    custom_precompiles::CustomPrecompile,
     db::gen_db::GeneralizedDatabase,
     debug::DebugMode,
     environment::Environment,
@@ -9,12 +10,12 @@ use crate::{
         backup_hook::BackupHook,
         hook::{Hook, get_hooks},
     },
+    inspector::{CallInputs, Inspector},
     memory::Memory,
-    opcodes::OpCodeFn,
+    opcodes::{OpCodeFn, Opcode},
     precompiles::{
         self, SIZE_PRECOMPILES_CANCUN, SIZE_PRECOMPILES_PRAGUE, SIZE_PRECOMPILES_PRE_CANCUN,
     },
-    tracing::LevmCallTracer,
 };
 use bytes::Bytes;
 use ethrex_common::{
@@ -276,6 +277,17 @@ impl Substate {
             })
     }
 
+    /// Return every transient storage entry with its current value.
+    pub fn transient_entries(&self) -> TransientStorage {
+        let mut entries = self
+            .parent
+            .as_ref()
+            .map(|parent| parent.transient_entries())
+            .unwrap_or_default();
+        entries.extend(&self.transient_storage);
+        entries
+    }
+
     /// Return the data associated with a transient storage entry, or zero if not present.
     pub fn set_transient(&mut self, to: &Address, key: &U256, value: U256) {
         self.transient_storage.insert((*to, *key), value);
@@ -316,8 +328,11 @@ pub struct VM<'a> {
     pub substate_backups: Vec<Substate>,
     /// Original storage values before the transaction. Used for gas calculations in SSTORE.
     pub storage_original_values: BTreeMap<(Address, H256), U256>,
-    /// When enabled, it "logs" relevant information during execution
-    pub tracer: LevmCallTracer,
+    /// Custom observer of the execution, see [`Inspector`]
+    pub inspector: Option<Rc<RefCell<dyn Inspector>>>,
+    /// When set the priority fee isn't paid to the coinbase and the caller is expected to pay it.
+    /// Used when executing transactions in parallel, so that they don't all touch the coinbase.
+    pub defer_coinbase_payment: bool,
     /// Mode for printing some useful stuff, only used in development!
     pub debug_mode: DebugMode,
     /// A pool of stacks to avoid reallocating too much when creating new call frames.
@@ -333,26 +348,33 @@ impl<'a> VM<'a> {
         env: Environment,
         db: &'a mut GeneralizedDatabase,
         tx: &Transaction,
-        tracer: LevmCallTracer,
         vm_type: VMType,
     ) -> Result<Self, VMError> {
         db.tx_backup = None; // If BackupHook is enabled, it will contain backup at the end of tx execution.
 
         let mut substate = Substate::initialize(&env, tx)?;
+        // Custom precompiles of the L2 are warm, as the standard ones
+        if let VMType::L2(_) = vm_type {
+            for precompile in db.custom_precompiles.active(env.config.fork) {
+                substate.add_accessed_address(precompile.address);
+            }
+        }
 
         let (callee, is_create) = Self::get_tx_callee(tx, db, &env, &mut substate)?;
 
         let fork = env.config.fork;
+        let hooks = get_hooks(&vm_type, &db.custom_hooks);
 
-        let mut vm = Self {
+        let vm = Self {
             call_frames: Vec::new(),
             substate,
             db,
             tx: tx.clone(),
-            hooks: get_hooks(&vm_type),
+            hooks,
             substate_backups: Vec::new(),
             storage_original_values: BTreeMap::new(),
-            tracer,
+            inspector: None,
+            defer_coinbase_payment: false,
             debug_mode: DebugMode::disabled(),
             stack_pool: Vec::new(),
             vm_type,
@@ -377,25 +399,13 @@ impl<'a> VM<'a> {
             opcode_table: VM::build_opcode_table(fork),
         };
 
-        let call_type = if is_create {
-            CallType::CREATE
-        } else {
-            CallType::CALL
-        };
-        vm.tracer.enter(
-            call_type,
-            vm.env.origin,
-            callee,
-            vm.tx.value(),
-            vm.env.gas_limit,
-            vm.tx.data(),
-        );
-
         #[cfg(feature = "debug")]
-        {
+        let vm = {
+            let mut vm = vm;
             // Enable debug mode for printing in Solidity contracts.
             vm.debug_mode.enabled = true;
-        }
+            vm
+        };
 
         Ok(vm)
     }
@@ -404,6 +414,12 @@ impl<'a> VM<'a> {
         self.hooks.push(Rc::new(RefCell::new(hook)));
     }
 
+    /// Installs an inspector to observe the execution.
+    /// The caller keeps a handle to it to read whatever it collected once the transaction is executed.
+    pub fn set_inspector(&mut self, inspector: Rc<RefCell<dyn Inspector>>) {
+        self.inspector = Some(inspector);
+    }
+
     /// Executes a whole external transaction. Performing validations at the beginning.
     pub fn execute(&mut self) -> Result<ExecutionReport, VMError> {
         if let Err(e) = self.prepare_execution() {
@@ -416,7 +432,32 @@ impl<'a> VM<'a> {
         // We want to apply these changes even if the Tx reverts. E.g. Incrementing sender nonce
         self.current_call_frame.call_frame_backup.clear();
 
-        if self.is_create()? {
+        let is_create = self.is_create()?;
+        self.inspect(|inspector| {
+            let call = CallInputs {
+                call_type: if is_create {
+                    CallType::CREATE
+                } else {
+                    CallType::CALL
+                },
+                from: self.env.origin,
+                to: self.current_call_frame.to,
+                value: self.tx.value(),
+                gas_limit: self.env.gas_limit,
+                input: self.tx.data(),
+                depth: 0,
+                intrinsic_gas: self.env.gas_limit.saturating_sub(
+                    u64::try_from(self.current_call_frame.gas_remaining).unwrap_or_default(),
+                ),
+            };
+            if is_create {
+                inspector.create(&call);
+            } else {
+                inspector.call_enter(&call);
+            }
+        });
+
+        if is_create {
             // Create contract, reverting the Tx if address is already occupied.
             if let Some(context_result) = self.handle_create_transaction()? {
                 let report = self.finalize_execution(context_result)?;
@@ -434,12 +475,15 @@ impl<'a> VM<'a> {
 
     /// Main execution loop.
     pub fn run_execution(&mut self) -> Result<ContextResult, VMError> {
+        let custom_precompile = self.custom_precompile(&self.current_call_frame.to);
         #[expect(clippy::as_conversions, reason = "remaining gas conversion")]
-        if precompiles::is_precompile(
-            &self.current_call_frame.to,
-            self.env.config.fork,
-            self.vm_type,
-        ) {
+        if custom_precompile.is_some()
+            || precompiles::is_precompile(
+                &self.current_call_frame.to,
+                self.env.config.fork,
+                self.vm_type,
+            )
+        {
             let call_frame = &mut self.current_call_frame;
 
             let mut gas_remaining = call_frame.gas_remaining as u64;
@@ -449,6 +493,7 @@ impl<'a> VM<'a> {
                 call_frame.gas_limit,
                 &mut gas_remaining,
                 self.env.config.fork,
+                custom_precompile.as_ref(),
             );
 
             call_frame.gas_remaining = gas_remaining as i64;
@@ -456,8 +501,23 @@ impl<'a> VM<'a> {
             return result;
         }
 
+        // The loop is monomorphized so that executions without an inspector don't check for one
+        // on every opcode
+        if self.inspector.is_some() {
+            self.execution_loop::<true>()
+        } else {
+            self.execution_loop::<false>()
+        }
+    }
+
+    /// Executes opcodes until the initial call frame ends, notifying each step to the inspector
+    /// when `INSPECT` is set.
+    fn execution_loop<const INSPECT: bool>(&mut self) -> Result<ContextResult, VMError> {
         loop {
             let opcode = self.current_call_frame.next_opcode();
+            if INSPECT {
+                self.inspect(|inspector| inspector.step_vm(Opcode::from(opcode), self));
+            }
             self.advance_pc(1)?;
 
             // Call the opcode, using the opcode function lookup table.
@@ -483,20 +543,33 @@ impl<'a> VM<'a> {
     }
 
     /// Executes precompile and handles the output that it returns, generating a report.
+    /// `custom_precompile` is the one of the L2 at `code_address`, if any.
     pub fn execute_precompile(
         code_address: H160,
         calldata: &Bytes,
         gas_limit: u64,
         gas_remaining: &mut u64,
         fork: Fork,
+        custom_precompile: Option<&CustomPrecompile>,
     ) -> Result<ContextResult, VMError> {
-        let execute_precompile = precompiles::execute_precompile;
+        let result = match custom_precompile {
+            Some(precompile) => precompile.call(calldata, gas_remaining, fork),
+            None => precompiles::execute_precompile(code_address, calldata, gas_remaining, fork),
+        };
 
-        Self::handle_precompile_result(
-            execute_precompile(code_address, calldata, gas_remaining, fork),
-            gas_limit,
-            *gas_remaining,
-        )
+        Self::handle_precompile_result(result, gas_limit, *gas_remaining)
+    }
+
+    /// Custom precompile of the chain active at `address`, only when running an L2.
+    pub fn custom_precompile(&self, address: &Address) -> Option<CustomPrecompile> {
+        match self.vm_type {
+            VMType::L2(_) => self
+                .db
+                .custom_precompiles
+                .get(address, self.env.config.fork)
+                .copied(),
+            VMType::L1 => None,
+        }
     }
 
     /// True if external transaction is a contract creation
@@ -531,7 +604,7 @@ impl<'a> VM<'a> {
                 .finalize_execution(self, &mut ctx_result)?;
         }
 
-        self.tracer.exit_context(&ctx_result, true)?;
+        self.inspect_call_exit(&ctx_result);
 
         let report = ExecutionReport {
             result: ctx_result.result.clone(),
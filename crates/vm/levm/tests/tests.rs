@@ -1,9 +1,157 @@
 #![allow(clippy::indexing_slicing)]
 #![allow(clippy::unwrap_used)]
 
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
 use bytes::Bytes;
-use ethrex_common::types::Fork;
-use ethrex_levm::precompiles::bls12_pairing_check;
+use ethrex_common::{
+    Address, H256, U256,
+    tracing::CallType,
+    types::{
+        Account, AccountState, ChainConfig, Fork, LegacyTransaction, Log, Transaction, TxKind,
+        fee_config::FeeConfig,
+    },
+};
+use ethrex_levm::{
+    EVMConfig, Environment,
+    code_cache::{AnalyzedCode, CodeCache},
+    custom_precompiles::{CustomPrecompile, CustomPrecompileError, CustomPrecompiles},
+    db::{Database, gen_db::GeneralizedDatabase},
+    errors::{ContextResult, DatabaseError, ExecutionReport, VMError},
+    gas_profiler::{GasProfile, GasProfiler},
+    hooks::hook::{CustomHooks, Hook},
+    inspector::{CallInputs, CallOutcome, Inspector},
+    opcodes::Opcode,
+    precompiles::bls12_pairing_check,
+    tracing::LevmCallTracer,
+    vm::{VM, VMType},
+};
+
+const SENDER: u64 = 0x1000;
+const CALLER_CONTRACT: u64 = 0xaa;
+const CALLEE_CONTRACT: u64 = 0xbb;
+
+/// Backing store without any account, every account used is preloaded in the cache
+struct EmptyDatabase;
+
+impl Database for EmptyDatabase {
+    fn get_account_state(&self, _address: Address) -> Result<AccountState, DatabaseError> {
+        Ok(AccountState::default())
+    }
+
+    fn get_storage_value(&self, _address: Address, _key: H256) -> Result<U256, DatabaseError> {
+        Ok(U256::zero())
+    }
+
+    fn get_block_hash(&self, _block_number: u64) -> Result<H256, DatabaseError> {
+        Ok(H256::zero())
+    }
+
+    fn get_chain_config(&self) -> Result<ChainConfig, DatabaseError> {
+        Ok(ChainConfig::default())
+    }
+
+    fn get_account_code(&self, _code_hash: H256) -> Result<Bytes, DatabaseError> {
+        Ok(Bytes::new())
+    }
+}
+
+/// Database with a funded sender and the given contracts
+fn test_db(contracts: &[(u64, &'static [u8])]) -> GeneralizedDatabase {
+    let mut accounts = BTreeMap::from([(
+        Address::from_low_u64_be(SENDER),
+        Account::new(
+            U256::from(10).pow(18.into()),
+            Bytes::new(),
+            0,
+            BTreeMap::new(),
+        ),
+    )]);
+    for (address, code) in contracts {
+        accounts.insert(
+            Address::from_low_u64_be(*address),
+            Account::new(U256::zero(), Bytes::from_static(code), 1, BTreeMap::new()),
+        );
+    }
+    GeneralizedDatabase::new_with_account_state(Arc::new(EmptyDatabase), accounts)
+}
+
+fn test_env(gas_limit: u64) -> Environment {
+    Environment {
+        origin: Address::from_low_u64_be(SENDER),
+        gas_limit,
+        config: EVMConfig::new(Fork::Prague, EVMConfig::canonical_values(Fork::Prague)),
+        block_gas_limit: 30_000_000,
+        ..Default::default()
+    }
+}
+
+fn call_tx(to: u64, value: U256, gas: u64) -> Transaction {
+    Transaction::LegacyTransaction(LegacyTransaction {
+        gas,
+        to: TxKind::Call(Address::from_low_u64_be(to)),
+        value,
+        ..Default::default()
+    })
+}
+
+/// Executes the transaction with the inspector installed
+fn execute_with_inspector(
+    db: &mut GeneralizedDatabase,
+    tx: &Transaction,
+    inspector: Rc<RefCell<dyn Inspector>>,
+) -> ExecutionReport {
+    let mut vm = VM::new(test_env(tx.gas_limit()), db, tx, VMType::L1).unwrap();
+    vm.set_inspector(inspector);
+    vm.execute().unwrap()
+}
+
+// PUSH1 0x2A, PUSH1 0, SSTORE, PUSH1 0, PUSH1 0, LOG0, STOP
+const CALLEE_CODE: &[u8] = &[
+    0x60, 0x2A, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xA0, 0x00,
+];
+// CALL(GAS, CALLEE_CONTRACT, 0, 0, 0, 0, 0), STOP
+const CALLER_CODE: &[u8] = &[
+    0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0xBB, 0x5A, 0xF1, 0x00,
+];
+
+/// Records the hooks called, in order
+#[derive(Default)]
+struct RecordingInspector {
+    events: Vec<String>,
+    steps: Vec<Opcode>,
+}
+
+impl Inspector for RecordingInspector {
+    fn step(&mut self, opcode: Opcode, _call_frame: &ethrex_levm::call_frame::CallFrame) {
+        self.steps.push(opcode);
+    }
+
+    fn call_enter(&mut self, call: &CallInputs) {
+        self.events.push(format!(
+            "call {:?} {:#x} {}",
+            call.call_type, call.to, call.depth
+        ));
+    }
+
+    fn call_exit(&mut self, outcome: &CallOutcome) {
+        self.events.push(format!("exit {:?}", outcome.error));
+    }
+
+    fn log(&mut self, log: &Log) {
+        self.events.push(format!("log {:#x}", log.address));
+    }
+
+    fn storage_write(&mut self, address: Address, key: H256, previous: U256, new: U256) {
+        self.events
+            .push(format!("sstore {address:#x} {key:#x} {previous} {new}"));
+    }
+}
 
 #[test]
 fn pairing_infinity() {
@@ -35,3 +183,410 @@ fn pairing_infinity() {
 
     assert_eq!(result.unwrap(), zero);
 }
+
+fn echo(calldata: &Bytes, _fork: Fork) -> Result<Bytes, VMError> {
+    Ok(calldata.clone())
+}
+
+const ECHO_ADDRESS: u64 = 0xfff0;
+
+fn echo_precompile(activation: Fork) -> CustomPrecompile {
+    CustomPrecompile {
+        address: Address::from_low_u64_be(ECHO_ADDRESS),
+        name: "ECHO",
+        activation,
+        gas_cost: |calldata| 1_000 + u64::try_from(calldata.len()).unwrap_or(u64::MAX),
+        execute: echo,
+    }
+}
+
+#[test]
+fn custom_precompiles_set() {
+    let precompile = echo_precompile(Fork::Prague);
+    assert_eq!(
+        CustomPrecompiles::new([precompile, precompile]).unwrap_err(),
+        CustomPrecompileError::DuplicatedAddress(precompile.address)
+    );
+    // Standard precompiles can't be replaced
+    let ecrecover = CustomPrecompile {
+        address: Address::from_low_u64_be(1),
+        ..precompile
+    };
+    assert_eq!(
+        CustomPrecompiles::new([ecrecover]).unwrap_err(),
+        CustomPrecompileError::ReservedAddress(ecrecover.address)
+    );
+
+    let precompiles = CustomPrecompiles::new([precompile]).unwrap();
+    // Only active from its activation fork
+    assert!(precompiles.get(&precompile.address, Fork::Cancun).is_none());
+    assert_eq!(precompiles.active(Fork::Cancun).count(), 0);
+    assert_eq!(precompiles.specs(), vec![precompile.spec()]);
+
+    let active = precompiles.get(&precompile.address, Fork::Prague).unwrap();
+    let calldata = Bytes::from_static(b"ethrex");
+    let mut gas_remaining = 2_000;
+    let output = active
+        .call(&calldata, &mut gas_remaining, Fork::Prague)
+        .unwrap();
+    assert_eq!(output, calldata);
+    assert_eq!(gas_remaining, 994);
+
+    // Not enough gas for the call
+    let mut gas_remaining = 500;
+    assert!(
+        active
+            .call(&calldata, &mut gas_remaining, Fork::Prague)
+            .is_err()
+    );
+}
+
+/// Calls the echo precompile from a transaction, with the given precompiles installed
+fn call_echo(precompiles: CustomPrecompiles, vm_type: VMType) -> ExecutionReport {
+    let mut db = test_db(&[]);
+    db.custom_precompiles = precompiles;
+    let tx = Transaction::LegacyTransaction(LegacyTransaction {
+        gas: 100_000,
+        to: TxKind::Call(Address::from_low_u64_be(ECHO_ADDRESS)),
+        data: Bytes::from_static(b"ethrex"),
+        ..Default::default()
+    });
+    let mut vm = VM::new(test_env(tx.gas_limit()), &mut db, &tx, vm_type).unwrap();
+    vm.execute().unwrap()
+}
+
+#[test]
+fn custom_precompile_called_through_the_vm() {
+    let l2 = VMType::L2(FeeConfig::default());
+    let calldata_gas = 6 * 16;
+
+    let precompiles = CustomPrecompiles::new([echo_precompile(Fork::Prague)]).unwrap();
+    let report = call_echo(precompiles.clone(), l2);
+    assert!(report.is_success());
+    assert_eq!(report.output, Bytes::from_static(b"ethrex"));
+    assert_eq!(report.gas_used, 21_000 + calldata_gas + 1_006);
+
+    // The L1 ignores them, the address is an empty account. The gas used is the EIP-7623 floor.
+    let report = call_echo(precompiles, VMType::L1);
+    assert!(report.is_success());
+    assert!(report.output.is_empty());
+    assert_eq!(report.gas_used, 21_000 + 10 * 6 * 4);
+
+    // Not active yet in the fork of the block
+    let report = call_echo(
+        CustomPrecompiles::new([echo_precompile(Fork::Osaka)]).unwrap(),
+        l2,
+    );
+    assert!(report.output.is_empty());
+    assert_eq!(report.gas_used, 21_000 + 10 * 6 * 4);
+}
+
+#[test]
+fn code_cache_analysis() {
+    // JUMPDEST, PUSH2 0x5B5B, JUMPDEST, PUSH32 with a truncated 0x5B
+    let bytecode = Bytes::from_static(&[0x5B, 0x61, 0x5B, 0x5B, 0x5B, 0x7F, 0x5B]);
+    let analyzed = AnalyzedCode::new(bytecode.clone());
+    assert!(analyzed.is_jump_target(0));
+    assert!(!analyzed.is_jump_target(1));
+    assert!(!analyzed.is_jump_target(2));
+    assert!(!analyzed.is_jump_target(3));
+    assert!(analyzed.is_jump_target(4));
+    assert!(!analyzed.is_jump_target(6));
+    assert!(!analyzed.is_jump_target(100));
+
+    // Pushes are keyed by the offset of their data, truncated ones aren't precomputed
+    assert_eq!(analyzed.push_value(2), Some(U256::from(0x5B5B)));
+    assert_eq!(analyzed.push_value(1), None);
+    assert_eq!(analyzed.push_value(6), None);
+
+    // Only fits one of the codes
+    let cache = CodeCache::new(analyzed.size());
+    let first = H256::from_low_u64_be(1);
+    let second = H256::from_low_u64_be(2);
+    cache.analyze(first, &bytecode);
+    cache.analyze(first, &bytecode);
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+    assert_eq!(cache.get_bytecode(&first), Some(bytecode.clone()));
+
+    cache.analyze(second, &bytecode);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.size(), analyzed.size());
+    assert!(cache.get_bytecode(&first).is_none());
+    assert!(cache.get_bytecode(&second).is_some());
+}
+
+#[test]
+fn code_cache_evicts_the_least_recently_used() {
+    let bytecode = Bytes::from_static(&[0x60, 0x01, 0x00]);
+    let code_size = AnalyzedCode::new(bytecode.clone()).size();
+    // Fits two of the codes
+    let cache = CodeCache::new(2 * code_size);
+    let [first, second, third] = [1, 2, 3].map(H256::from_low_u64_be);
+
+    cache.analyze(first, &bytecode);
+    cache.analyze(second, &bytecode);
+    // The first one is used again, so the second one is the least recently used
+    cache.analyze(first, &bytecode);
+    cache.analyze(third, &bytecode);
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains(&first));
+    assert!(!cache.contains(&second));
+    assert!(cache.contains(&third));
+    assert_eq!((cache.hits(), cache.misses()), (1, 3));
+}
+
+const LOOP_CONTRACT: u64 = 0xcc;
+const INVALID_JUMP_CONTRACT: u64 = 0xdd;
+// Counts down from 5 in a loop, then returns a PUSH32 with a 0x5B in its data
+const LOOP_CODE: &[u8] = &[
+    0x60, 0x05, 0x5B, 0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x02, 0x57, 0x50, 0x7F, 0x01, 0x02, 0x03,
+    0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x5B, 0x12, 0x13,
+    0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F, 0x20, 0x60, 0x00, 0x52,
+    0x60, 0x20, 0x60, 0x00, 0xF3,
+];
+// PUSH1 4, JUMP into the data of PUSH2 0x5B00
+const INVALID_JUMP_CODE: &[u8] = &[0x60, 0x04, 0x56, 0x61, 0x5B, 0x00];
+
+/// Executes a call to `to`, sharing `code_cache` with other executions if given
+fn execute_with_code_cache(to: u64, code_cache: Option<Arc<CodeCache>>) -> ExecutionReport {
+    let mut db = test_db(&[
+        (LOOP_CONTRACT, LOOP_CODE),
+        (INVALID_JUMP_CONTRACT, INVALID_JUMP_CODE),
+    ]);
+    db.code_cache = code_cache;
+    let tx = call_tx(to, U256::zero(), 100_000);
+    let mut vm = VM::new(test_env(tx.gas_limit()), &mut db, &tx, VMType::L1).unwrap();
+    vm.execute().unwrap()
+}
+
+#[test]
+fn execution_with_code_cache_matches_execution_without_it() {
+    let cache = Arc::new(CodeCache::default());
+    for to in [LOOP_CONTRACT, INVALID_JUMP_CONTRACT] {
+        let without_cache = execute_with_code_cache(to, None);
+        // The first execution analyzes the code, the second one takes it from the cache
+        let analyzed = execute_with_code_cache(to, Some(cache.clone()));
+        let cached = execute_with_code_cache(to, Some(cache.clone()));
+        assert_eq!(analyzed, without_cache);
+        assert_eq!(cached, without_cache);
+    }
+    assert_eq!((cache.hits(), cache.misses()), (2, 2));
+
+    let report = execute_with_code_cache(LOOP_CONTRACT, Some(cache));
+    assert!(report.is_success());
+    assert_eq!(report.output, Bytes::from_static(&LOOP_CODE[13..45]));
+    assert!(!execute_with_code_cache(INVALID_JUMP_CONTRACT, None).is_success());
+}
+
+#[test]
+fn inspector_observes_nested_calls() {
+    let mut db = test_db(&[
+        (CALLER_CONTRACT, CALLER_CODE),
+        (CALLEE_CONTRACT, CALLEE_CODE),
+    ]);
+    let inspector = Rc::new(RefCell::new(RecordingInspector::default()));
+    let report = execute_with_inspector(
+        &mut db,
+        &call_tx(CALLER_CONTRACT, U256::zero(), 100_000),
+        inspector.clone(),
+    );
+    assert!(report.is_success());
+
+    let inspector = inspector.borrow();
+    let caller = format!("{:#x}", Address::from_low_u64_be(CALLER_CONTRACT));
+    let callee = format!("{:#x}", Address::from_low_u64_be(CALLEE_CONTRACT));
+    assert_eq!(
+        inspector.events,
+        vec![
+            format!("call CALL {caller} 0"),
+            format!("call CALL {callee} 1"),
+            format!("sstore {callee} {:#x} 0 42", H256::zero()),
+            format!("log {callee}"),
+            "exit None".to_string(),
+            "exit None".to_string(),
+        ]
+    );
+    // 9 opcodes of the caller and 7 of the callee
+    assert_eq!(inspector.steps.len(), 16);
+    assert_eq!(inspector.steps[7], Opcode::CALL);
+    assert_eq!(inspector.steps[8], Opcode::PUSH1);
+    assert_eq!(inspector.steps.last(), Some(&Opcode::STOP));
+}
+
+#[test]
+fn call_tracer_builds_nested_trace() {
+    let mut db = test_db(&[
+        (CALLER_CONTRACT, CALLER_CODE),
+        (CALLEE_CONTRACT, CALLEE_CODE),
+    ]);
+    let tracer = Rc::new(RefCell::new(LevmCallTracer::new(false, true)));
+    execute_with_inspector(
+        &mut db,
+        &call_tx(CALLER_CONTRACT, U256::zero(), 100_000),
+        tracer.clone(),
+    );
+
+    let trace = tracer.borrow_mut().take_trace_result().unwrap();
+    assert_eq!(trace.call_type, CallType::CALL);
+    assert_eq!(trace.to, Address::from_low_u64_be(CALLER_CONTRACT));
+    assert_eq!(trace.gas, 100_000);
+    assert!(trace.error.is_none());
+    assert!(trace.logs.is_empty());
+    assert_eq!(trace.calls.len(), 1);
+    let subcall = &trace.calls[0];
+    assert_eq!(subcall.from, Address::from_low_u64_be(CALLER_CONTRACT));
+    assert_eq!(subcall.to, Address::from_low_u64_be(CALLEE_CONTRACT));
+    assert_eq!(subcall.logs.len(), 1);
+    assert!(subcall.gas_used > 0);
+
+    // Only the top call, without its subcalls
+    let mut db = test_db(&[
+        (CALLER_CONTRACT, CALLER_CODE),
+        (CALLEE_CONTRACT, CALLEE_CODE),
+    ]);
+    let tracer = Rc::new(RefCell::new(LevmCallTracer::new(true, true)));
+    execute_with_inspector(
+        &mut db,
+        &call_tx(CALLER_CONTRACT, U256::zero(), 100_000),
+        tracer.clone(),
+    );
+    let trace = tracer.borrow_mut().take_trace_result().unwrap();
+    assert!(trace.calls.is_empty());
+    assert!(trace.logs.is_empty());
+    assert!(tracer.borrow_mut().take_trace_result().is_err());
+}
+
+/// Profiles the transaction as a single transaction block
+fn profile_gas(db: &mut GeneralizedDatabase, tx: &Transaction) -> (ExecutionReport, GasProfile) {
+    let profiler = Rc::new(RefCell::new(GasProfiler::new()));
+    let mut vm = VM::new(test_env(tx.gas_limit()), db, tx, VMType::L1).unwrap();
+    vm.set_inspector(profiler.clone());
+    let report = vm.execute().unwrap();
+    let gas_remaining = u64::try_from(vm.current_call_frame.gas_remaining).unwrap();
+    profiler
+        .borrow_mut()
+        .end_transaction(gas_remaining, &report);
+    let profile = profiler.borrow().profile(true);
+    (report, profile)
+}
+
+#[test]
+fn gas_profile_of_a_transfer() {
+    let recipient = 0x2000;
+    let mut db = test_db(&[]);
+    let (report, profile) = profile_gas(&mut db, &call_tx(recipient, U256::one(), 21_000));
+
+    assert_eq!(report.gas_used, 21_000);
+    assert_eq!(profile.transactions, 1);
+    assert_eq!(profile.gas_used, 21_000);
+    // Recorded even though no opcode is executed
+    assert_eq!(profile.intrinsic_gas, 21_000);
+    assert!(profile.opcodes.is_empty());
+    let recipient = &profile.contracts[&Address::from_low_u64_be(recipient)];
+    assert_eq!(recipient.stats.calls, 1);
+    assert_eq!(recipient.stats.gas, 0);
+    assert_eq!(recipient.functions["fallback"].calls, 1);
+    assert_eq!(profile.folded.as_deref(), Some(""));
+}
+
+#[test]
+fn gas_profile_of_a_nested_call() {
+    let mut db = test_db(&[
+        (CALLER_CONTRACT, CALLER_CODE),
+        (CALLEE_CONTRACT, CALLEE_CODE),
+    ]);
+    let (report, profile) = profile_gas(&mut db, &call_tx(CALLER_CONTRACT, U256::zero(), 100_000));
+    assert!(report.is_success());
+    assert_eq!(profile.intrinsic_gas, 21_000);
+
+    let caller = &profile.contracts[&Address::from_low_u64_be(CALLER_CONTRACT)].stats;
+    let callee = &profile.contracts[&Address::from_low_u64_be(CALLEE_CONTRACT)].stats;
+    assert_eq!(caller.calls, 1);
+    assert_eq!(callee.calls, 1);
+    // The gas of the callee is accounted to it, not to the caller
+    assert_eq!(callee.self_gas, callee.gas);
+    assert_eq!(caller.gas, caller.self_gas + callee.gas);
+    assert_eq!(profile.intrinsic_gas + caller.gas, report.gas_used);
+
+    // Storing in a cold slot, and calling a cold account without the gas sent to it
+    assert_eq!(profile.opcodes["SSTORE"].count, 1);
+    assert_eq!(profile.opcodes["SSTORE"].gas, 22_100);
+    assert_eq!(profile.opcodes["CALL"].count, 1);
+    assert_eq!(profile.opcodes["CALL"].gas, 2_600);
+    let opcodes_gas: u64 = profile.opcodes.values().map(|stats| stats.gas).sum();
+    assert_eq!(opcodes_gas, caller.gas);
+
+    let folded = profile.folded.unwrap();
+    let sstore = format!(
+        "{:#x}:fallback;{:#x}:fallback;SSTORE 22100\n",
+        Address::from_low_u64_be(CALLER_CONTRACT),
+        Address::from_low_u64_be(CALLEE_CONTRACT)
+    );
+    assert!(folded.contains(&sstore));
+}
+
+/// Hook recording its calls in a log shared with the other hooks
+struct RecordingHook {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Hook for RecordingHook {
+    fn prepare_execution(&mut self, vm: &mut VM<'_>) -> Result<(), VMError> {
+        // Runs after the hook of the VM type, which already bumped the nonce of the sender
+        let nonce = vm.db.get_account(vm.env.origin)?.info.nonce;
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{} prepare {nonce}", self.name));
+        Ok(())
+    }
+
+    fn finalize_execution(
+        &mut self,
+        _vm: &mut VM<'_>,
+        report: &mut ContextResult,
+    ) -> Result<(), VMError> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{} finalize {}", self.name, report.is_success()));
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_hooks_run_in_order_on_l1_and_l2() {
+    for vm_type in [VMType::L1, VMType::L2(FeeConfig::default())] {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let hooks = ["first", "second"]
+            .into_iter()
+            .fold(CustomHooks::new(), |hooks, name| {
+                let log = log.clone();
+                hooks.with(move || RecordingHook {
+                    name,
+                    log: log.clone(),
+                })
+            });
+        let mut db = test_db(&[]);
+        db.custom_hooks = hooks;
+
+        let tx = call_tx(0x2000, U256::one(), 21_000);
+        let report = VM::new(test_env(tx.gas_limit()), &mut db, &tx, vm_type)
+            .unwrap()
+            .execute()
+            .unwrap();
+        assert!(report.is_success());
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "first prepare 1",
+                "second prepare 1",
+                "first finalize true",
+                "second finalize true"
+            ],
+            "{vm_type:?}"
+        );
+    }
+}
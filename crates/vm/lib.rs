@@ -9,6 +9,12 @@ pub mod backends;
 pub use backends::{BlockExecutionResult, Evm};
 pub use db::{DynVmDatabase, VmDatabase};
 pub use errors::EvmError;
+pub use ethrex_levm::code_cache::CodeCache;
+pub use ethrex_levm::custom_precompiles::{
+    CustomPrecompile, CustomPrecompileError, CustomPrecompiles,
+};
+pub use ethrex_levm::gas_profiler::GasProfile;
+pub use ethrex_levm::hooks::hook::{CustomHooks, Hook};
 pub use ethrex_levm::precompiles::precompiles_for_fork;
 pub use execution_result::ExecutionResult;
 pub use witness_db::GuestProgramStateWrapper;
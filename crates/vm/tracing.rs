@@ -1,6 +1,9 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
 use crate::backends::levm::LEVM;
 use ethrex_common::tracing::CallTrace;
 use ethrex_common::types::Block;
+use ethrex_levm::gas_profiler::{GasProfile, GasProfiler};
 
 use crate::{Evm, EvmError};
 
@@ -34,6 +37,55 @@ impl Evm {
         )
     }
 
+    /// Runs a single tx with the EIP-3155 opcode tracer, writing its trace to `output` as it's executed.
+    /// Assumes that the received state already contains changes from previous blocks and other
+    /// transactions within its block.
+    pub fn trace_tx_opcodes(
+        &mut self,
+        block: &Block,
+        tx_index: usize,
+        output: impl Write + 'static,
+    ) -> Result<(), EvmError> {
+        let tx = block
+            .body
+            .transactions
+            .get(tx_index)
+            .ok_or(EvmError::Custom(
+                "Missing Transaction for Trace".to_string(),
+            ))?;
+
+        LEVM::trace_tx_opcodes(&mut self.db, &block.header, tx, self.vm_type, output)
+    }
+
+    /// Runs the transaction at `tx_index`, or every transaction of the block if it's not set, with
+    /// the gas profiler and outputs their profile.
+    /// Assumes that the received state already contains changes from previous blocks and, when
+    /// profiling a single transaction, from the previous transactions within its block.
+    pub fn profile_gas(
+        &mut self,
+        block: &Block,
+        tx_index: Option<usize>,
+        with_folded: bool,
+    ) -> Result<GasProfile, EvmError> {
+        let txs = match tx_index {
+            Some(index) => block
+                .body
+                .transactions
+                .get(index..=index)
+                .ok_or(EvmError::Custom(
+                    "Missing Transaction for Gas Profile".to_string(),
+                ))?,
+            None => block.body.transactions.as_slice(),
+        };
+
+        let profiler = Rc::new(RefCell::new(GasProfiler::new()));
+        for tx in txs {
+            LEVM::profile_tx_gas(&mut self.db, &block.header, tx, self.vm_type, &profiler)?;
+        }
+
+        Ok(profiler.borrow().profile(with_folded))
+    }
+
     /// Reruns the given block, saving the changes on the state, doesn't output any results or receipts.
     /// If the optional argument `stop_index` is set, the run will stop just before executing the transaction at that index
     /// and won't process the withdrawals afterwards.
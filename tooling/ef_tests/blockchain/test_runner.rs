@@ -8,18 +8,19 @@ use ethrex_blockchain::{
     Blockchain, BlockchainOptions,
     error::{ChainError, InvalidBlockError},
     fork_choice::apply_fork_choice,
+    vm::StoreVmDatabase,
 };
 use ethrex_common::{
     constants::EMPTY_KECCACK_HASH,
     types::{
         Account as CoreAccount, Block as CoreBlock, BlockHeader as CoreBlockHeader,
-        InvalidBlockHeaderError,
+        InvalidBlockHeaderError, block_execution_witness::WitnessOptions,
     },
 };
 use ethrex_prover_lib::backend::Backend;
 use ethrex_rlp::decode::RLPDecode;
 use ethrex_storage::{EngineType, Store};
-use ethrex_vm::EvmError;
+use ethrex_vm::{Evm, EvmError};
 use guest_program::input::ProgramInput;
 use regex::Regex;
 
@@ -148,6 +149,86 @@ async fn run(
     Ok(())
 }
 
+/// Executes every block of the tests both sequentially and in parallel, checking that the receipts,
+/// requests and account updates of both modes are the same
+pub fn parse_and_compare_execution_modes(
+    path: &Path,
+    skipped_tests: Option<&[&str]>,
+) -> datatest_stable::Result<()> {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let tests = parse_tests(path);
+
+    let mut failures = Vec::new();
+
+    for (test_key, test) in tests {
+        let should_skip_test = test.network < Fork::Merge
+            || skipped_tests
+                .map(|skipped| skipped.iter().any(|s| test_key.contains(s)))
+                .unwrap_or(false);
+
+        if should_skip_test {
+            continue;
+        }
+
+        if let Err(e) = rt.block_on(compare_execution_modes(&test_key, &test)) {
+            eprintln!("Test {test_key} failed: {e}");
+            failures.push(format!("{test_key}: {e}"));
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("     -------     ").into())
+    }
+}
+
+async fn compare_execution_modes(test_key: &str, test: &TestUnit) -> Result<(), String> {
+    let store = build_store_for_test(test).await;
+    let blockchain = Blockchain::new(
+        store.clone(),
+        BlockchainOptions {
+            parallel_execution: false,
+            ..Default::default()
+        },
+    );
+
+    for block_fixture in test.blocks.iter() {
+        let Ok(block) = CoreBlock::decode(block_fixture.rlp.as_ref()) else {
+            break;
+        };
+
+        let [sequential, parallel] = [false, true].map(|parallel_execution| {
+            let vm_db = StoreVmDatabase::new(store.clone(), block.header.parent_hash);
+            let mut vm = Evm::new_for_l1(vm_db);
+            vm.parallel_execution = parallel_execution;
+            vm.execute_block(&block)
+                .and_then(|result| Ok((result, vm.get_state_transitions()?)))
+                .map(|(result, account_updates)| {
+                    (
+                        result.receipts,
+                        format!("{:?}", result.requests),
+                        account_updates,
+                    )
+                })
+                .map_err(|error| error.to_string())
+        });
+        if sequential != parallel {
+            return Err(format!(
+                "Sequential and parallel execution differ on block {} of test {test_key}.\nSequential: {sequential:?}\nParallel: {parallel:?}",
+                block.header.number
+            ));
+        }
+
+        let hash = block.hash();
+        if blockchain.add_block(block).await.is_err() {
+            break;
+        }
+        apply_fork_choice(&store, hash, hash, hash).await.unwrap();
+    }
+    Ok(())
+}
+
 fn exception_is_expected(
     expected_exceptions: Vec<BlockChainExpectedException>,
     returned_error: &ChainError,
@@ -390,31 +471,38 @@ async fn re_run_stateless(
 
     let test_should_fail = test.blocks.iter().any(|t| t.expect_exception.is_some());
 
-    let witness = blockchain.generate_witness_for_blocks(&blocks).await;
-    if test_should_fail && witness.is_err() {
-        // We can't generate witness for a test that should fail.
-        return Ok(());
-    } else if !test_should_fail && witness.is_err() {
-        return Err("Failed to create witness for a test that should not fail".into());
-    }
-    // At this point witness is guaranteed to be Ok
-    let execution_witness = witness.unwrap();
-
-    let program_input = ProgramInput {
-        blocks,
-        execution_witness,
-        elasticity_multiplier: ethrex_common::types::ELASTICITY_MULTIPLIER,
-        ..Default::default()
-    };
-
-    if let Err(e) = ethrex_prover_lib::execute(backend, program_input) {
-        if !test_should_fail {
+    // Compact witnesses must be enough to execute the blocks as well
+    for witness_options in [WitnessOptions::default(), WitnessOptions::compact()] {
+        let witness = blockchain
+            .generate_witness_for_blocks_with_options(&blocks, witness_options)
+            .await;
+        if test_should_fail && witness.is_err() {
+            // We can't generate witness for a test that should fail.
+            return Ok(());
+        } else if !test_should_fail && witness.is_err() {
+            return Err("Failed to create witness for a test that should not fail".into());
+        }
+        // At this point witness is guaranteed to be Ok
+        let execution_witness = witness.unwrap();
+
+        let program_input = ProgramInput {
+            blocks: blocks.clone(),
+            execution_witness,
+            elasticity_multiplier: ethrex_common::types::ELASTICITY_MULTIPLIER,
+            ..Default::default()
+        };
+
+        if let Err(e) = ethrex_prover_lib::execute(backend, program_input) {
+            if !test_should_fail {
+                return Err(format!(
+                    "Expected test: {test_key} to succeed but failed with {e} ({witness_options:?})"
+                ));
+            }
+        } else if test_should_fail {
             return Err(format!(
-                "Expected test: {test_key} to succeed but failed with {e}"
+                "Expected test: {test_key} to fail but succeeded ({witness_options:?})"
             ));
         }
-    } else if test_should_fail {
-        return Err(format!("Expected test: {test_key} to fail but succeeded"));
     }
     Ok(())
 }
@@ -0,0 +1,24 @@
+use ef_tests_blockchain::test_runner::parse_and_compare_execution_modes;
+use std::path::Path;
+
+const TEST_FOLDER: &str = "vectors/";
+
+const SKIPPED: &[&str] = &[
+    // Skip because they take too long to run
+    "static_Call50000_sha256",
+    "CALLBlake2f_MaxRounds",
+    "loopMul",
+    // Gas price higher than u64::MAX; impractical scenario. Fix is on its way on https://github.com/lambdaclass/ethrex/pull/4823
+    "HighGasPriceParis",
+    // Skip because it tries to deserialize number > U256::MAX
+    "ValueOverflowParis",
+    // Skip because it's a "Create" Blob Transaction, which can't be parsed as an actual Transaction.
+    "createBlobhashTx",
+];
+
+// Diffs sequential and parallel block execution over the same fixtures used by the `all` test
+fn execution_modes_runner(path: &Path) -> datatest_stable::Result<()> {
+    parse_and_compare_execution_modes(path, Some(SKIPPED))
+}
+
+datatest_stable::harness!(execution_modes_runner, TEST_FOLDER, r".*");
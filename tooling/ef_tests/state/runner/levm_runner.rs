@@ -16,7 +16,6 @@ use ethrex_levm::{
     EVMConfig, Environment,
     db::gen_db::GeneralizedDatabase,
     errors::{ExecutionReport, TxValidationError, VMError},
-    tracing::LevmCallTracer,
     vm::{VM, VMType},
 };
 use ethrex_rlp::encode::RLPEncode;
@@ -215,7 +214,6 @@ pub fn prepare_vm_for_tx<'a>(
         },
         db,
         &tx,
-        LevmCallTracer::disabled(),
         VMType::L1, // TODO: Should we run the EF tests with L2?
     )
     .map_err(|e| EFTestRunnerError::FailedToEnsurePreState(format!("Failed to initialize VM: {e}")))
@@ -7,10 +7,7 @@ use ethrex_common::types::{
     compute_transactions_root,
 };
 use ethrex_common::{H256, U256};
-use ethrex_levm::{
-    tracing::LevmCallTracer,
-    vm::{VM, VMType},
-};
+use ethrex_levm::vm::{VM, VMType};
 use std::str::FromStr;
 
 use crate::modules::types::TestCase;
@@ -39,12 +36,10 @@ pub async fn run_test(test: &Test, test_case: &TestCase) -> Result<(), RunnerErr
     // 1. We need to do a pre-execution with LEVM because we need to know gas used and generate receipts for the block header.
     let env = get_vm_env_for_test(test.env, test_case)?;
     let tx = get_tx_from_test_case(test_case).await?;
-    let tracer = LevmCallTracer::disabled();
 
     let (mut db, initial_block_hash, store, _genesis) =
         load_initial_state(test, &test_case.fork).await;
-    let mut vm =
-        VM::new(env.clone(), &mut db, &tx, tracer, VMType::L1).map_err(RunnerError::VMError)?;
+    let mut vm = VM::new(env.clone(), &mut db, &tx, VMType::L1).map_err(RunnerError::VMError)?;
     let execution_result = vm.execute();
 
     let (receipts, gas_used) = match execution_result {
@@ -14,7 +14,7 @@ use ethrex_common::{
         LegacyTransaction, Transaction, TxKind,
     },
 };
-use ethrex_levm::{EVMConfig, Environment, tracing::LevmCallTracer, vm::VM, vm::VMType};
+use ethrex_levm::{EVMConfig, Environment, vm::VM, vm::VMType};
 
 use crate::modules::{
     error::RunnerError,
@@ -81,9 +81,7 @@ pub async fn run_test(
             load_initial_state(test, &test_case.fork).await;
         let env = get_vm_env_for_test(test.env, test_case)?;
         let tx = get_tx_from_test_case(test_case).await?;
-        let tracer = LevmCallTracer::disabled();
-        let mut vm =
-            VM::new(env, &mut db, &tx, tracer, VMType::L1).map_err(RunnerError::VMError)?;
+        let mut vm = VM::new(env, &mut db, &tx, VMType::L1).map_err(RunnerError::VMError)?;
 
         // Execute transaction with VM.
         let execution_result = vm.execute();
@@ -2,7 +2,14 @@ use std::path::{Path, PathBuf};
 
 use clap::{Parser as ClapParser, Subcommand as ClapSubcommand};
 use ethrex_blockchain::{Blockchain, BlockchainOptions, BlockchainType};
-use ethrex_common::types::{Block, fee_config::FeeConfig};
+use ethrex_common::{
+    H256,
+    constants::{EMPTY_KECCACK_HASH, EMPTY_TRIE_HASH},
+    types::{AccountState, Block, BlockHash, BlockNumber, Genesis, fee_config::FeeConfig},
+};
+use ethrex_rlp::{decode::RLPDecode, encode::RLPEncode};
+use ethrex_storage::{EngineType, Store, StoreOptions, TrieScheme};
+use ethrex_trie::{Nibbles, Node, NodeHash, Trie};
 
 use crate::utils::{migrate_block_body, migrate_block_header};
 
@@ -36,6 +43,22 @@ pub enum Subcommand {
         /// Path for the new RocksDB database
         new_storage_path: PathBuf,
     },
+    #[command(
+        name = "hash2path",
+        visible_alias = "h2p",
+        about = "Migrate a rocksdb database storing trie nodes by hash to the path scheme"
+    )]
+    Hash2Path {
+        #[arg(long = "genesis")]
+        /// Path to the genesis file for the old database
+        genesis_path: PathBuf,
+        #[arg(long = "store.old")]
+        /// Path to the target hash scheme database to migrate
+        old_storage_path: PathBuf,
+        #[arg(long = "store.new")]
+        /// Path for the new path scheme database
+        new_storage_path: PathBuf,
+    },
 }
 
 impl Subcommand {
@@ -46,6 +69,11 @@ impl Subcommand {
                 old_storage_path,
                 new_storage_path,
             } => migrate_libmdbx_to_rocksdb(genesis_path, old_storage_path, new_storage_path).await,
+            Self::Hash2Path {
+                genesis_path,
+                old_storage_path,
+                new_storage_path,
+            } => migrate_hash_to_path(genesis_path, old_storage_path, new_storage_path).await,
         }
     }
 }
@@ -143,3 +171,215 @@ async fn migrate_libmdbx_to_rocksdb(
         .await
         .expect("Cannot apply forkchoice update");
 }
+
+/// Amount of trie nodes written to the new store at once
+const NODES_PER_WRITE: usize = 10_000;
+
+async fn migrate_hash_to_path(
+    genesis_path: &Path,
+    old_storage_path: &Path,
+    new_storage_path: &Path,
+) {
+    let genesis_path = genesis_path
+        .to_str()
+        .expect("Cannot convert genesis path to str");
+    let old_store = Store::new_from_genesis(old_storage_path, EngineType::RocksDB, genesis_path)
+        .await
+        .expect("Cannot open old rocksdb store");
+    if old_store.trie_scheme() == TrieScheme::Path {
+        println!("Old store already uses the path scheme");
+        return;
+    }
+
+    let new_store = Store::new_with_options(
+        new_storage_path,
+        EngineType::RocksDB,
+        StoreOptions {
+            trie_scheme: Some(TrieScheme::Path),
+            ..Default::default()
+        },
+    )
+    .expect("Cannot create new rocksdb store");
+    assert_eq!(
+        new_store.trie_scheme(),
+        TrieScheme::Path,
+        "New store already exists and uses the hash scheme"
+    );
+    let genesis_file = std::fs::File::open(genesis_path).expect("Cannot open genesis file");
+    let genesis: Genesis = serde_json::from_reader(std::io::BufReader::new(genesis_file))
+        .expect("Cannot deserialize genesis file");
+    new_store
+        .add_initial_state(genesis)
+        .await
+        .expect("Cannot store genesis in new store");
+
+    let earliest_block_number = old_store
+        .get_earliest_block_number()
+        .await
+        .expect("Cannot get earliest block from old store");
+    let last_block_number = old_store
+        .get_latest_block_number()
+        .await
+        .expect("Cannot get latest block from old store");
+    let last_block = old_store
+        .get_block_header(last_block_number)
+        .ok()
+        .flatten()
+        .expect("Cannot get last block from old store");
+
+    println!("Migrating blocks {earliest_block_number} to {last_block_number}");
+    let mut canonical_blocks = Vec::new();
+    for block_number in earliest_block_number..=last_block_number {
+        let block = old_store
+            .get_block_by_number(block_number)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| panic!("Cannot get block {block_number} from old store"));
+        let block_hash = block.hash();
+        let receipts = old_store
+            .get_receipts_for_block(&block_hash)
+            .await
+            .unwrap_or_else(|e| panic!("Cannot get receipts of block {block_number}: {e}"));
+        new_store
+            .add_block(block)
+            .await
+            .unwrap_or_else(|e| panic!("Cannot add block {block_number} to new store: {e}"));
+        new_store
+            .add_receipts(block_hash, receipts)
+            .await
+            .unwrap_or_else(|e| panic!("Cannot add receipts of block {block_number}: {e}"));
+        canonical_blocks.push((block_number, block_hash));
+    }
+
+    // Only the state of the last block is migrated, older ones are not kept by the path scheme
+    println!("Migrating state of block {last_block_number}");
+    let head = (last_block.number, last_block.hash());
+    migrate_state(&old_store, &new_store, last_block.state_root, head).await;
+
+    new_store
+        .update_earliest_block_number(earliest_block_number)
+        .await
+        .expect("Cannot update earliest block number");
+    let safe = old_store
+        .get_safe_block_number()
+        .await
+        .expect("Cannot get safe block from old store");
+    let finalized = old_store
+        .get_finalized_block_number()
+        .await
+        .expect("Cannot get finalized block from old store");
+    new_store
+        .forkchoice_update(
+            Some(canonical_blocks),
+            last_block.number,
+            last_block.hash(),
+            safe,
+            finalized,
+        )
+        .await
+        .expect("Cannot apply forkchoice update");
+}
+
+/// Copies the state trie with the given root, along with the storage tries and codes of its
+/// accounts, as the state of the given block
+async fn migrate_state(
+    old_store: &Store,
+    new_store: &Store,
+    state_root: H256,
+    block: (BlockNumber, BlockHash),
+) {
+    let state_trie = old_store
+        .open_state_trie(state_root)
+        .expect("Cannot open state trie");
+    let mut nodes = Vec::new();
+    let mut accounts = 0;
+    for (path, node) in state_trie.into_iter() {
+        if let Node::Leaf(leaf) = &node {
+            let hashed_address = H256::from_slice(&path.to_bytes());
+            let account = AccountState::decode(&leaf.value).expect("Invalid account in state trie");
+            if account.storage_root != *EMPTY_TRIE_HASH {
+                let storage_trie = old_store
+                    .open_storage_trie(hashed_address, account.storage_root)
+                    .expect("Cannot open storage trie");
+                migrate_trie(new_store, Some(hashed_address), storage_trie, block).await;
+            }
+            if account.code_hash != *EMPTY_KECCACK_HASH {
+                let code = old_store
+                    .get_account_code(account.code_hash)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_else(|| panic!("Cannot get code {:#x}", account.code_hash));
+                new_store
+                    .add_account_code(account.code_hash, code)
+                    .await
+                    .expect("Cannot add code to new store");
+            }
+            accounts += 1;
+            if accounts % 100_000 == 0 {
+                println!("Migrated {accounts} accounts");
+            }
+        }
+        nodes.extend(path_node(path, node));
+        if nodes.len() >= NODES_PER_WRITE {
+            write_nodes(new_store, None, std::mem::take(&mut nodes), block).await;
+        }
+    }
+    write_nodes(new_store, None, nodes, block).await;
+    println!("Migrated {accounts} accounts");
+
+    assert!(
+        new_store
+            .contains_state_node(state_root)
+            .expect("Cannot read new store"),
+        "State root not found after migrating the state"
+    );
+}
+
+/// Copies every node of a trie to the new store
+async fn migrate_trie(
+    new_store: &Store,
+    hashed_address: Option<H256>,
+    trie: Trie,
+    block: (BlockNumber, BlockHash),
+) {
+    let mut nodes = Vec::new();
+    for (path, node) in trie.into_iter() {
+        nodes.extend(path_node(path, node));
+        if nodes.len() >= NODES_PER_WRITE {
+            write_nodes(new_store, hashed_address, std::mem::take(&mut nodes), block).await;
+        }
+    }
+    write_nodes(new_store, hashed_address, nodes, block).await;
+}
+
+/// Turns a node yielded by the trie iterator, along with the path up to its end, into the node
+/// and its position in the trie. Nodes embedded in their parents are skipped
+fn path_node(path: Nibbles, node: Node) -> Option<(Nibbles, NodeHash, Vec<u8>)> {
+    let suffix_len = match &node {
+        Node::Branch(_) => 0,
+        Node::Extension(extension) => extension.prefix.len(),
+        Node::Leaf(leaf) => leaf.partial.len(),
+    };
+    let position = path.slice(0, path.len() - suffix_len);
+    let hash = match node.compute_hash() {
+        NodeHash::Inline(_) if !position.is_empty() => return None,
+        hash => NodeHash::Hashed(hash.finalize()),
+    };
+    Some((position, hash, node.encode_to_vec()))
+}
+
+async fn write_nodes(
+    new_store: &Store,
+    hashed_address: Option<H256>,
+    nodes: Vec<(Nibbles, NodeHash, Vec<u8>)>,
+    block: (BlockNumber, BlockHash),
+) {
+    if nodes.is_empty() {
+        return;
+    }
+    new_store
+        .write_trie_nodes_at_paths(hashed_address, nodes, block)
+        .await
+        .expect("Cannot write trie nodes to new store");
+}
@@ -0,0 +1,36 @@
+use ethrex_rlp::error::RLPDecodeError;
+use ethrex_vm::EvmError;
+
+/// Errors aborting the transition. Each maps to the exit code used by geth's `evm t8n`, so that
+/// test fillers can tell them apart.
+#[derive(Debug, thiserror::Error)]
+pub enum T8nError {
+    #[error("EVM error: {0}")]
+    Evm(#[from] EvmError),
+    #[error("Config error: {0}")]
+    Config(String),
+    /// Transaction that can't be executed, reported as rejected instead of aborting the transition
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+    #[error("Missing hash of block {0}")]
+    MissingBlockHash(u64),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("RLP error: {0}")]
+    Rlp(#[from] RLPDecodeError),
+}
+
+impl T8nError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            T8nError::Evm(_) => 2,
+            T8nError::Config(_) | T8nError::InvalidTransaction(_) => 3,
+            T8nError::MissingBlockHash(_) => 4,
+            T8nError::Json(_) => 10,
+            T8nError::Io(_) => 11,
+            T8nError::Rlp(_) => 12,
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::{collections::BTreeMap, io::Read, path::PathBuf};
+
+use clap::Parser;
+use ethrex_common::{
+    Address, U256,
+    types::{Fork, Transaction},
+};
+use ethrex_rlp::{decode::RLPDecode, encode::RLPEncode};
+use ethrex_rpc::signer::{LocalSigner, Signable, Signer, sign_legacy_transaction_eip155};
+use secp256k1::SecretKey;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{
+    error::T8nError,
+    transition::{InputTx, TransitionOutput, transition},
+    types::{Alloc, Env, InputTransaction, OutputAccount, StdinInput},
+};
+
+mod error;
+mod transition;
+mod types;
+
+/// Value of the input flags that reads them from stdin
+const STDIN: &str = "stdin";
+/// Value of the output flags that writes them to stdout
+const STDOUT: &str = "stdout";
+
+#[derive(Parser)]
+#[command(
+    name = "ethrex-t8n",
+    author = "Lambdaclass",
+    about = "State transition tool compatible with geth's `evm t8n`, built on LEVM"
+)]
+struct Options {
+    #[arg(long = "input.alloc", default_value = "alloc.json")]
+    /// Pre state, or `stdin` to read it from the combined input
+    input_alloc: String,
+    #[arg(long = "input.env", default_value = "env.json")]
+    /// Block environment, or `stdin` to read it from the combined input
+    input_env: String,
+    #[arg(long = "input.txs", default_value = "txs.json")]
+    /// Transactions as JSON, or as a hex encoded RLP list if the file ends in `.rlp`. `stdin` reads them from the combined input
+    input_txs: String,
+    #[arg(long = "output.basedir", default_value = "")]
+    /// Directory the output files are written to
+    output_basedir: PathBuf,
+    #[arg(long = "output.result", default_value = "result.json")]
+    /// File the execution result is written to, or `stdout`
+    output_result: String,
+    #[arg(long = "output.alloc", default_value = "alloc.json")]
+    /// File the post state is written to, or `stdout`
+    output_alloc: String,
+    #[arg(long = "output.body")]
+    /// File the RLP encoded list of included transactions is written to, or `stdout`
+    output_body: Option<String>,
+    #[arg(long = "state.fork", default_value = "Prague")]
+    /// Fork the transactions are executed in, from Paris to Osaka
+    fork: String,
+    #[arg(long = "state.chainid", default_value_t = 1)]
+    /// Chain id used to sign and validate transactions
+    chain_id: u64,
+    #[arg(long = "state.reward", default_value_t = 0, allow_hyphen_values = true)]
+    /// Reward credited to the coinbase, a negative value disables it
+    reward: i64,
+}
+
+#[tokio::main]
+async fn main() {
+    let options = Options::parse();
+    if let Err(error) = run(options).await {
+        eprintln!("{error}");
+        std::process::exit(error.exit_code());
+    }
+}
+
+async fn run(options: Options) -> Result<(), T8nError> {
+    let fork: Fork = serde_json::from_value(Value::String(options.fork.clone()))
+        .map_err(|_| T8nError::Config(format!("Unknown fork {}", options.fork)))?;
+
+    let mut stdin_input = if [&options.input_alloc, &options.input_env, &options.input_txs]
+        .iter()
+        .any(|input| *input == STDIN)
+    {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Some(serde_json::from_str::<StdinInput>(&input)?)
+    } else {
+        None
+    };
+
+    let alloc: Alloc = match stdin_input.as_mut() {
+        Some(stdin_input) if options.input_alloc == STDIN => stdin_input
+            .alloc
+            .take()
+            .ok_or_else(|| T8nError::Config("Missing alloc in stdin input".to_string()))?,
+        _ => read_json(&options.input_alloc)?,
+    };
+    let env: Env = match stdin_input.as_mut() {
+        Some(stdin_input) if options.input_env == STDIN => stdin_input
+            .env
+            .take()
+            .ok_or_else(|| T8nError::Config("Missing env in stdin input".to_string()))?,
+        _ => read_json(&options.input_env)?,
+    };
+    let txs = match stdin_input {
+        Some(stdin_input) if options.input_txs == STDIN => match stdin_input {
+            StdinInput {
+                txs_rlp: Some(txs_rlp),
+                ..
+            } => decode_transactions(&txs_rlp)?,
+            StdinInput { txs: Some(txs), .. } => build_transactions(txs, options.chain_id).await?,
+            _ => Vec::new(),
+        },
+        _ if options.input_txs.ends_with(".rlp") => {
+            decode_transactions(&read_json::<String>(&options.input_txs)?)?
+        }
+        _ => build_transactions(read_json(&options.input_txs)?, options.chain_id).await?,
+    };
+
+    let reward = (options.reward > 0).then(|| U256::from(options.reward.unsigned_abs()));
+    let output = transition(alloc, &env, txs, fork, options.chain_id, reward)?;
+    write_output(&options, output)
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, T8nError> {
+    let file = std::fs::File::open(path)?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+fn decode_transactions(txs_rlp: &str) -> Result<Vec<InputTx>, T8nError> {
+    let bytes = hex::decode(txs_rlp.trim_start_matches("0x"))
+        .map_err(|error| T8nError::Config(format!("Invalid transactions RLP hex: {error}")))?;
+    Ok(Vec::<Transaction>::decode(&bytes)?
+        .into_iter()
+        .map(Ok)
+        .collect())
+}
+
+/// Builds the input transactions, signing with their secret key those that come without a signature.
+/// Transactions that can't be built are kept as errors so that they're reported as rejected.
+async fn build_transactions(
+    txs: Vec<InputTransaction>,
+    chain_id: u64,
+) -> Result<Vec<InputTx>, T8nError> {
+    let mut built = Vec::with_capacity(txs.len());
+    for input in txs {
+        let mut tx = match input.to_transaction(chain_id) {
+            Ok(tx) => tx,
+            Err(T8nError::InvalidTransaction(error)) => {
+                built.push(Err(error));
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        if let Some(secret_key) = input.secret_key.filter(|_| !input.is_signed()) {
+            let secret_key = SecretKey::from_slice(secret_key.as_bytes())
+                .map_err(|error| T8nError::Config(format!("Invalid secret key: {error}")))?;
+            let signer = Signer::Local(LocalSigner::new(secret_key));
+            let signed = match &mut tx {
+                Transaction::LegacyTransaction(legacy) if input.protected => {
+                    let chain_id = input.chain_id.unwrap_or(chain_id);
+                    sign_legacy_transaction_eip155(legacy, &signer, chain_id).await
+                }
+                tx => tx.sign_inplace(&signer).await,
+            };
+            signed.map_err(|error| {
+                T8nError::Config(format!("Failed to sign transaction: {error}"))
+            })?;
+        }
+        built.push(Ok(tx));
+    }
+    Ok(built)
+}
+
+/// Writes each output to its file, except those sent to stdout, which are printed together as a single object
+fn write_output(options: &Options, output: TransitionOutput) -> Result<(), T8nError> {
+    let alloc: BTreeMap<Address, OutputAccount> = output
+        .alloc
+        .iter()
+        .map(|(address, account)| (*address, OutputAccount::from(account)))
+        .collect();
+    let body = format!("0x{}", hex::encode(output.body.encode_to_vec()));
+
+    let mut outputs = vec![
+        (
+            "result",
+            options.output_result.as_str(),
+            serde_json::to_value(&output.result)?,
+        ),
+        (
+            "alloc",
+            options.output_alloc.as_str(),
+            serde_json::to_value(&alloc)?,
+        ),
+    ];
+    if let Some(output_body) = &options.output_body {
+        outputs.push(("body", output_body.as_str(), Value::String(body)));
+    }
+
+    let mut stdout_output = Map::new();
+    for (name, destination, value) in outputs {
+        if destination == STDOUT {
+            stdout_output.insert(name.to_string(), value);
+        } else {
+            let file = std::fs::File::create(options.output_basedir.join(destination))?;
+            serde_json::to_writer_pretty(file, &value)?;
+        }
+    }
+    if !stdout_output.is_empty() {
+        println!("{}", serde_json::to_string_pretty(&stdout_output)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethrex_common::types::Transaction;
+    use ethrex_rpc::signer::LocalSigner;
+    use secp256k1::SecretKey;
+
+    use super::build_transactions;
+    use crate::types::InputTransaction;
+
+    const SECRET_KEY: &str = "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8";
+
+    fn unsigned_legacy_tx(extra_fields: &str) -> InputTransaction {
+        serde_json::from_str(&format!(
+            r#"{{
+                "nonce": "0x0",
+                "gas": "0x5208",
+                "gasPrice": "0xa",
+                "to": "0x0000000000000000000000000000000000001000",
+                "value": "0x1",
+                "input": "0x",
+                "secretKey": "{SECRET_KEY}"{extra_fields}
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    fn signer_address() -> ethrex_common::Address {
+        let key = SecretKey::from_slice(&hex::decode(&SECRET_KEY[2..]).unwrap()).unwrap();
+        LocalSigner::new(key).address
+    }
+
+    #[tokio::test]
+    async fn unsigned_legacy_transactions_are_signed_with_eip155_by_default() {
+        let chain_id = 1337;
+        let mut txs = build_transactions(vec![unsigned_legacy_tx("")], chain_id)
+            .await
+            .unwrap();
+        let tx = txs.remove(0).unwrap();
+
+        let Transaction::LegacyTransaction(legacy) = &tx else {
+            panic!("expected a legacy transaction, got {tx:?}");
+        };
+        let recovery_id = legacy.v.as_u64() - (35 + 2 * chain_id);
+        assert!(recovery_id <= 1, "v {} isn't EIP-155", legacy.v);
+        assert_eq!(tx.chain_id(), Some(chain_id));
+        assert_eq!(tx.sender().unwrap(), signer_address());
+    }
+
+    #[tokio::test]
+    async fn unprotected_legacy_transactions_are_signed_without_chain_id() {
+        let mut txs = build_transactions(vec![unsigned_legacy_tx(r#", "protected": false"#)], 1)
+            .await
+            .unwrap();
+        let tx = txs.remove(0).unwrap();
+
+        let Transaction::LegacyTransaction(legacy) = &tx else {
+            panic!("expected a legacy transaction, got {tx:?}");
+        };
+        assert!(legacy.v.as_u64() == 27 || legacy.v.as_u64() == 28);
+        assert_eq!(tx.chain_id(), None);
+        assert_eq!(tx.sender().unwrap(), signer_address());
+    }
+
+    #[tokio::test]
+    async fn fees_above_64_bits_are_rejected_instead_of_aborting() {
+        let over_u64 = unsigned_legacy_tx("");
+        let over_u64 = InputTransaction {
+            gas_price: Some(ethrex_common::U256::from(u64::MAX) + 1),
+            ..over_u64
+        };
+        let txs = build_transactions(vec![over_u64, unsigned_legacy_tx("")], 1)
+            .await
+            .unwrap();
+
+        assert_eq!(txs.len(), 2);
+        let error = txs[0].as_ref().unwrap_err();
+        assert!(error.contains("gas price"), "unexpected error {error}");
+        assert!(txs[1].is_ok());
+    }
+
+    #[test]
+    fn fees_are_parsed_as_256_bit_values() {
+        let tx: InputTransaction = serde_json::from_str(
+            r#"{
+                "nonce": "0x0",
+                "gas": "0x5208",
+                "maxFeePerGas": "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "maxPriorityFeePerGas": "0x1",
+                "to": null,
+                "value": "0x0",
+                "input": "0x"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(tx.max_fee_per_gas, Some(ethrex_common::U256::MAX));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(1.into()));
+    }
+}
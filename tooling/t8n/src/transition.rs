@@ -0,0 +1,651 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use ethrex_common::{
+    Address, H160, H256, U256,
+    constants::GAS_PER_BLOB,
+    evm::calculate_create_address,
+    types::{
+        AccountState, AccountUpdate, Block, BlockBody, BlockHeader, ChainConfig,
+        ELASTICITY_MULTIPLIER, Fork, GenesisAccount, Receipt, Transaction, TxKind, bloom_from_logs,
+        calc_excess_blob_gas, calculate_base_fee_per_gas, compute_receipts_root,
+        compute_transactions_root, compute_withdrawals_root,
+        requests::{EncodedRequests, Requests, compute_requests_hash},
+    },
+    utils::keccak,
+};
+use ethrex_levm::{
+    db::{Database, gen_db::GeneralizedDatabase},
+    errors::{DatabaseError, TxResult},
+    vm::VMType,
+};
+use ethrex_rlp::encode::RLPEncode;
+use ethrex_trie::Trie;
+use ethrex_vm::{
+    EvmError,
+    backends::levm::{LEVM, extract_all_requests_levm},
+};
+
+use crate::{
+    error::T8nError,
+    types::{Alloc, Env, ExecutionResult, LogResult, ReceiptResult, RejectedTransaction},
+};
+
+/// Mainnet deposit contract, the one used by the execution spec tests
+const DEPOSIT_CONTRACT_ADDRESS: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x21, 0x9a, 0xb5, 0x40, 0x35, 0x6c, 0xbb, 0x83, 0x9c, 0xbe, 0x05, 0x30,
+    0x3d, 0x77, 0x05, 0xfa,
+]);
+
+/// Transaction to apply, or the reason it was rejected before reaching the EVM
+pub type InputTx = Result<Transaction, String>;
+
+/// Outcome of applying the transactions on top of the pre state
+pub struct TransitionOutput {
+    pub result: ExecutionResult,
+    pub alloc: Alloc,
+    /// Transactions included in the block, rejected ones left out
+    pub body: Vec<Transaction>,
+}
+
+/// Executes `txs` in a block built from `env` on top of `alloc`, the way geth's `evm t8n` does.
+/// Invalid transactions are left out of the block and reported as rejected instead of failing the transition.
+pub fn transition(
+    alloc: Alloc,
+    env: &Env,
+    txs: Vec<InputTx>,
+    fork: Fork,
+    chain_id: u64,
+    reward: Option<U256>,
+) -> Result<TransitionOutput, T8nError> {
+    if fork < Fork::Paris || fork > Fork::Osaka {
+        return Err(T8nError::Config(format!(
+            "Unsupported fork {fork:?}, only forks from Paris to Osaka are supported"
+        )));
+    }
+    let chain_config = chain_config_for_fork(fork, chain_id);
+    let block_hashes = env.block_hashes()?;
+    let header = block_header(env, &chain_config, fork, &block_hashes)?;
+
+    let store = Arc::new(AllocDatabase::new(&alloc, block_hashes, chain_config));
+    let mut db = GeneralizedDatabase::new(store.clone());
+
+    apply_block(alloc, env, txs, &header, fork, reward, &mut db).map_err(|error| {
+        match store.missing_block_hash() {
+            Some(block_number) => T8nError::MissingBlockHash(block_number),
+            None => error,
+        }
+    })
+}
+
+fn apply_block(
+    mut alloc: Alloc,
+    env: &Env,
+    txs: Vec<InputTx>,
+    header: &BlockHeader,
+    fork: Fork,
+    reward: Option<U256>,
+    db: &mut GeneralizedDatabase,
+) -> Result<TransitionOutput, T8nError> {
+    let chain_config = db.store.get_chain_config().map_err(EvmError::from)?;
+    // Only the header is needed to run the system calls
+    LEVM::prepare_block(
+        &Block::new(header.clone(), BlockBody::default()),
+        db,
+        VMType::L1,
+    )?;
+
+    let max_blob_gas = chain_config
+        .get_fork_blob_schedule(header.timestamp)
+        .map(|schedule| u64::from(schedule.max) * u64::from(GAS_PER_BLOB))
+        .unwrap_or_default();
+
+    let mut included = Vec::new();
+    let mut receipts = Vec::new();
+    let mut receipt_results = Vec::new();
+    let mut rejected = Vec::new();
+    let mut logs = Vec::new();
+    let mut cumulative_gas_used = 0;
+    let mut blob_gas_used = 0;
+
+    for (index, tx) in txs.into_iter().enumerate() {
+        let mut reject = |error: String| rejected.push(RejectedTransaction { index, error });
+
+        let tx = match tx {
+            Ok(tx) => tx,
+            Err(error) => {
+                reject(error);
+                continue;
+            }
+        };
+        let sender = match tx.sender() {
+            Ok(sender) => sender,
+            Err(error) => {
+                reject(format!("invalid transaction signature: {error}"));
+                continue;
+            }
+        };
+        if cumulative_gas_used
+            .checked_add(tx.gas_limit())
+            .is_none_or(|gas| gas > header.gas_limit)
+        {
+            reject(format!(
+                "gas limit reached: {} gas left, transaction needs {}",
+                header.gas_limit.saturating_sub(cumulative_gas_used),
+                tx.gas_limit()
+            ));
+            continue;
+        }
+        let tx_blob_gas =
+            (tx.blob_versioned_hashes().len() as u64).saturating_mul(u64::from(GAS_PER_BLOB));
+        if blob_gas_used
+            .checked_add(tx_blob_gas)
+            .is_none_or(|blob_gas| blob_gas > max_blob_gas)
+        {
+            reject(format!(
+                "blob gas limit reached: {} blob gas left, transaction needs {tx_blob_gas}",
+                max_blob_gas.saturating_sub(blob_gas_used)
+            ));
+            continue;
+        }
+
+        // Transactions failing validation must leave no trace in the state
+        let backup = db.clone();
+        let report = match LEVM::execute_tx(&tx, sender, header, db, VMType::L1) {
+            Ok(report) => report,
+            Err(EvmError::Transaction(error)) => {
+                *db = backup;
+                reject(error);
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        // Both fit in the limits checked above, as a transaction uses at most its gas limit
+        cumulative_gas_used += report.gas_used;
+        blob_gas_used += tx_blob_gas;
+        let succeeded = matches!(report.result, TxResult::Success);
+        let receipt = Receipt::new(
+            tx.tx_type(),
+            succeeded,
+            cumulative_gas_used,
+            report.logs.clone(),
+        );
+
+        let tx_hash = tx.hash();
+        let tx_index = included.len() as u64;
+        receipt_results.push(ReceiptResult {
+            tx_type: tx.tx_type() as u64,
+            root: Bytes::new(),
+            status: succeeded.into(),
+            cumulative_gas_used,
+            logs_bloom: bloom_from_logs(&receipt.logs),
+            logs: receipt
+                .logs
+                .iter()
+                .enumerate()
+                .map(|(position, log)| LogResult {
+                    address: log.address,
+                    topics: log.topics.clone(),
+                    data: log.data.clone(),
+                    block_number: header.number,
+                    transaction_hash: tx_hash,
+                    transaction_index: tx_index,
+                    block_hash: H256::zero(),
+                    log_index: (logs.len() + position) as u64,
+                    removed: false,
+                })
+                .collect(),
+            transaction_hash: tx_hash,
+            contract_address: matches!(tx.to(), TxKind::Create)
+                .then(|| calculate_create_address(sender, tx.nonce())),
+            gas_used: report.gas_used,
+            effective_gas_price: tx
+                .effective_gas_price(header.base_fee_per_gas)
+                .unwrap_or_default(),
+            block_hash: H256::zero(),
+            transaction_index: tx_index,
+        });
+        logs.extend(receipt.logs.iter().cloned());
+        receipts.push(receipt);
+        included.push(tx);
+    }
+
+    if let Some(withdrawals) = &env.withdrawals {
+        LEVM::process_withdrawals(db, withdrawals)?;
+    }
+    let requests = extract_all_requests_levm(&receipts, db, header, VMType::L1)?;
+    if let Some(reward) = reward {
+        let coinbase = db
+            .get_account_mut(header.coinbase)
+            .map_err(EvmError::from)?;
+        coinbase.info.balance = coinbase.info.balance.checked_add(reward).ok_or_else(|| {
+            T8nError::Config("Block reward overflows the coinbase balance".to_string())
+        })?;
+    }
+
+    apply_account_updates(&mut alloc, LEVM::get_state_transitions(db)?);
+
+    let (requests_hash, requests) = if fork >= Fork::Prague {
+        let encoded: Vec<EncodedRequests> = requests.iter().map(Requests::encode).collect();
+        let requests_hash = compute_requests_hash(&encoded);
+        let non_empty = encoded
+            .into_iter()
+            .filter(|request| !request.is_empty())
+            .collect();
+        (Some(requests_hash), Some(non_empty))
+    } else {
+        (None, None)
+    };
+
+    let result = ExecutionResult {
+        state_root: compute_state_root(&alloc),
+        tx_root: compute_transactions_root(&included),
+        receipts_root: compute_receipts_root(&receipts),
+        logs_hash: keccak(logs.encode_to_vec()),
+        logs_bloom: bloom_from_logs(&logs),
+        receipts: receipt_results,
+        rejected,
+        gas_used: cumulative_gas_used,
+        current_base_fee: header.base_fee_per_gas.unwrap_or_default(),
+        withdrawals_root: header.withdrawals_root,
+        current_excess_blob_gas: header.excess_blob_gas,
+        blob_gas_used: (fork >= Fork::Cancun).then_some(blob_gas_used),
+        requests_hash,
+        requests,
+    };
+
+    Ok(TransitionOutput {
+        result,
+        alloc,
+        body: included,
+    })
+}
+
+/// Chain config with every fork up to `fork` active since genesis
+fn chain_config_for_fork(fork: Fork, chain_id: u64) -> ChainConfig {
+    let activation_time = |activation_fork: Fork| (fork >= activation_fork).then_some(0);
+    ChainConfig {
+        chain_id,
+        homestead_block: Some(0),
+        dao_fork_block: Some(0),
+        dao_fork_support: true,
+        eip150_block: Some(0),
+        eip155_block: Some(0),
+        eip158_block: Some(0),
+        byzantium_block: Some(0),
+        constantinople_block: Some(0),
+        petersburg_block: Some(0),
+        istanbul_block: Some(0),
+        muir_glacier_block: Some(0),
+        berlin_block: Some(0),
+        london_block: Some(0),
+        arrow_glacier_block: Some(0),
+        gray_glacier_block: Some(0),
+        merge_netsplit_block: Some(0),
+        terminal_total_difficulty: Some(0),
+        terminal_total_difficulty_passed: true,
+        shanghai_time: activation_time(Fork::Shanghai),
+        cancun_time: activation_time(Fork::Cancun),
+        prague_time: activation_time(Fork::Prague),
+        osaka_time: activation_time(Fork::Osaka),
+        deposit_contract_address: DEPOSIT_CONTRACT_ADDRESS,
+        ..Default::default()
+    }
+}
+
+fn block_header(
+    env: &Env,
+    chain_config: &ChainConfig,
+    fork: Fork,
+    block_hashes: &HashMap<u64, H256>,
+) -> Result<BlockHeader, T8nError> {
+    let base_fee_per_gas = match (
+        env.current_base_fee,
+        env.parent_base_fee,
+        env.parent_gas_used,
+        env.parent_gas_limit,
+    ) {
+        (Some(base_fee), ..) => base_fee,
+        (None, Some(parent_base_fee), Some(parent_gas_used), Some(parent_gas_limit)) => {
+            calculate_base_fee_per_gas(
+                env.current_gas_limit,
+                parent_gas_limit,
+                parent_gas_used,
+                parent_base_fee,
+                ELASTICITY_MULTIPLIER,
+            )
+            .ok_or_else(|| {
+                T8nError::Config("Invalid gas limit change from the parent block".to_string())
+            })?
+        }
+        _ => {
+            return Err(T8nError::Config(
+                "Either currentBaseFee or the parent base fee, gas used and gas limit are required"
+                    .to_string(),
+            ));
+        }
+    };
+
+    let excess_blob_gas = (fork >= Fork::Cancun).then(|| {
+        env.current_excess_blob_gas.unwrap_or_else(|| {
+            let parent = BlockHeader {
+                base_fee_per_gas: env.parent_base_fee,
+                blob_gas_used: env.parent_blob_gas_used,
+                excess_blob_gas: env.parent_excess_blob_gas,
+                ..Default::default()
+            };
+            chain_config
+                .get_fork_blob_schedule(env.current_timestamp)
+                .map(|schedule| calc_excess_blob_gas(&parent, schedule, fork))
+                .unwrap_or_default()
+        })
+    });
+
+    Ok(BlockHeader {
+        parent_hash: env
+            .current_number
+            .checked_sub(1)
+            .and_then(|parent_number| block_hashes.get(&parent_number))
+            .copied()
+            .unwrap_or_default(),
+        coinbase: env.current_coinbase,
+        difficulty: env.current_difficulty.unwrap_or_default(),
+        number: env.current_number,
+        gas_limit: env.current_gas_limit,
+        timestamp: env.current_timestamp,
+        prev_randao: env.current_random.unwrap_or_default(),
+        base_fee_per_gas: Some(base_fee_per_gas),
+        withdrawals_root: (fork >= Fork::Shanghai)
+            .then(|| compute_withdrawals_root(env.withdrawals.as_deref().unwrap_or_default())),
+        excess_blob_gas,
+        parent_beacon_block_root: env.parent_beacon_block_root,
+        ..Default::default()
+    })
+}
+
+fn apply_account_updates(alloc: &mut Alloc, updates: Vec<AccountUpdate>) {
+    for update in updates {
+        if update.removed {
+            alloc.remove(&update.address);
+            continue;
+        }
+        let account = alloc
+            .entry(update.address)
+            .or_insert_with(|| GenesisAccount {
+                code: Bytes::new(),
+                storage: HashMap::new(),
+                balance: U256::zero(),
+                nonce: 0,
+            });
+        if let Some(info) = update.info {
+            account.balance = info.balance;
+            account.nonce = info.nonce;
+        }
+        if let Some(code) = update.code {
+            account.code = code;
+        }
+        for (key, value) in update.added_storage {
+            let key = U256::from_big_endian(key.as_bytes());
+            if value.is_zero() {
+                account.storage.remove(&key);
+            } else {
+                account.storage.insert(key, value);
+            }
+        }
+    }
+}
+
+fn compute_state_root(alloc: &Alloc) -> H256 {
+    let iter = alloc.iter().map(|(address, account)| {
+        (
+            keccak(address).as_bytes().to_vec(),
+            AccountState::from(account).encode_to_vec(),
+        )
+    });
+    Trie::compute_hash_from_unsorted_iter(iter)
+}
+
+/// Pre state of the transition as seen by LEVM
+struct AllocDatabase {
+    accounts: Alloc,
+    codes: HashMap<H256, Bytes>,
+    block_hashes: HashMap<u64, H256>,
+    chain_config: ChainConfig,
+    /// Block whose hash was requested but not given in the env, if any
+    missing_block_hash: Mutex<Option<u64>>,
+}
+
+impl AllocDatabase {
+    fn new(alloc: &Alloc, block_hashes: HashMap<u64, H256>, chain_config: ChainConfig) -> Self {
+        Self {
+            accounts: alloc.clone(),
+            codes: alloc
+                .values()
+                .map(|account| (keccak(&account.code), account.code.clone()))
+                .collect(),
+            block_hashes,
+            chain_config,
+            missing_block_hash: Mutex::new(None),
+        }
+    }
+
+    fn missing_block_hash(&self) -> Option<u64> {
+        self.missing_block_hash
+            .lock()
+            .ok()
+            .and_then(|block_number| *block_number)
+    }
+}
+
+impl Database for AllocDatabase {
+    fn get_account_state(&self, address: Address) -> Result<AccountState, DatabaseError> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .map(AccountState::from)
+            .unwrap_or_default())
+    }
+
+    fn get_storage_value(&self, address: Address, key: H256) -> Result<U256, DatabaseError> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&U256::from_big_endian(key.as_bytes())))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn get_block_hash(&self, block_number: u64) -> Result<H256, DatabaseError> {
+        match self.block_hashes.get(&block_number) {
+            Some(hash) => Ok(*hash),
+            None => {
+                if let Ok(mut missing) = self.missing_block_hash.lock() {
+                    *missing = Some(block_number);
+                }
+                Err(DatabaseError::Custom(format!(
+                    "Missing hash of block {block_number}"
+                )))
+            }
+        }
+    }
+
+    fn get_chain_config(&self) -> Result<ChainConfig, DatabaseError> {
+        Ok(self.chain_config)
+    }
+
+    fn get_account_code(&self, code_hash: H256) -> Result<Bytes, DatabaseError> {
+        // Accounts without code have the hash of the empty code, which isn't necessarily in the alloc
+        Ok(self.codes.get(&code_hash).cloned().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs::File, io::Read};
+
+    use bytes::Bytes;
+    use ethrex_common::{
+        H256, U256,
+        types::{Block, Fork, Genesis, GenesisAccount, Transaction},
+    };
+    use ethrex_rlp::decode::RLPDecode;
+
+    use super::transition;
+    use crate::{error::T8nError, types::Env};
+
+    /// Genesis and blocks of the hive execution-apis test chain, which were produced by geth
+    fn geth_chain() -> (Genesis, Vec<Block>) {
+        let genesis_file = File::open("../../fixtures/genesis/execution-api.json")
+            .expect("Failed to open genesis file");
+        let genesis: Genesis =
+            serde_json::from_reader(genesis_file).expect("Failed to deserialize genesis file");
+
+        let mut chain = vec![];
+        File::open("../../fixtures/blockchain/chain.rlp")
+            .expect("Failed to open chain file")
+            .read_to_end(&mut chain)
+            .expect("Failed to read chain file");
+        let mut chain = chain.as_slice();
+        let mut blocks = vec![];
+        while !chain.is_empty() {
+            let (block, rest) = Block::decode_unfinished(chain).expect("Failed to decode block");
+            blocks.push(block);
+            chain = rest;
+        }
+        (genesis, blocks)
+    }
+
+    fn env_for_block(block: &Block, block_hashes: HashMap<String, H256>) -> Env {
+        let header = &block.header;
+        Env {
+            current_coinbase: header.coinbase,
+            current_gas_limit: header.gas_limit,
+            current_number: header.number,
+            current_timestamp: header.timestamp,
+            current_difficulty: Some(header.difficulty),
+            current_random: Some(header.prev_randao),
+            current_base_fee: header.base_fee_per_gas,
+            parent_base_fee: None,
+            parent_gas_used: None,
+            parent_gas_limit: None,
+            current_excess_blob_gas: header.excess_blob_gas,
+            parent_excess_blob_gas: None,
+            parent_blob_gas_used: None,
+            parent_beacon_block_root: header.parent_beacon_block_root,
+            block_hashes,
+            withdrawals: block.body.withdrawals.clone(),
+        }
+    }
+
+    fn tx_hashes(txs: &[Transaction]) -> Vec<H256> {
+        txs.iter().map(Transaction::hash).collect()
+    }
+
+    #[test]
+    fn transition_matches_blocks_produced_by_geth() {
+        let (genesis, blocks) = geth_chain();
+        let genesis_hash = genesis.get_block().hash();
+        assert_eq!(blocks[0].header.parent_hash, genesis_hash);
+
+        let mut alloc = genesis.alloc.clone();
+        let mut block_hashes = HashMap::from([("0".to_string(), genesis_hash)]);
+        for block in blocks {
+            let header = &block.header;
+            let number = header.number;
+            let env = env_for_block(&block, block_hashes.clone());
+            let txs = block.body.transactions.iter().cloned().map(Ok).collect();
+
+            let output = transition(
+                alloc,
+                &env,
+                txs,
+                Fork::Prague,
+                genesis.config.chain_id,
+                None,
+            )
+            .unwrap_or_else(|error| panic!("Transition of block {number} failed: {error}"));
+            let result = &output.result;
+
+            assert!(
+                result.rejected.is_empty(),
+                "Block {number} rejected {:?}",
+                result.rejected
+            );
+            assert_eq!(result.state_root, header.state_root, "Block {number}");
+            assert_eq!(result.tx_root, header.transactions_root, "Block {number}");
+            assert_eq!(result.receipts_root, header.receipts_root, "Block {number}");
+            assert_eq!(result.logs_bloom, header.logs_bloom, "Block {number}");
+            assert_eq!(result.gas_used, header.gas_used, "Block {number}");
+            assert_eq!(
+                result.withdrawals_root, header.withdrawals_root,
+                "Block {number}"
+            );
+            assert_eq!(result.blob_gas_used, header.blob_gas_used, "Block {number}");
+            assert_eq!(result.requests_hash, header.requests_hash, "Block {number}");
+            assert_eq!(
+                tx_hashes(&output.body),
+                tx_hashes(&block.body.transactions),
+                "Block {number}"
+            );
+
+            block_hashes.insert(number.to_string(), block.hash());
+            alloc = output.alloc;
+        }
+    }
+
+    #[test]
+    fn transactions_rejected_before_execution_keep_their_index() {
+        let (genesis, blocks) = geth_chain();
+        let block = &blocks[0];
+        let block_hashes = HashMap::from([("0".to_string(), genesis.get_block().hash())]);
+        let env = env_for_block(block, block_hashes);
+        let mut txs: Vec<_> = block.body.transactions.iter().cloned().map(Ok).collect();
+        txs.insert(0, Err("gas price higher than 2^64-1".to_string()));
+
+        let output = transition(
+            genesis.alloc.clone(),
+            &env,
+            txs,
+            Fork::Prague,
+            genesis.config.chain_id,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(output.result.rejected.len(), 1);
+        assert_eq!(output.result.rejected[0].index, 0);
+        assert_eq!(tx_hashes(&output.body), tx_hashes(&block.body.transactions));
+        assert_eq!(output.result.state_root, block.header.state_root);
+    }
+
+    #[test]
+    fn reward_overflowing_the_coinbase_balance_fails() {
+        let (genesis, blocks) = geth_chain();
+        let block_hashes = HashMap::from([("0".to_string(), genesis.get_block().hash())]);
+        let env = env_for_block(&blocks[0], block_hashes);
+        let mut alloc = genesis.alloc.clone();
+        alloc.insert(
+            env.current_coinbase,
+            GenesisAccount {
+                code: Bytes::new(),
+                storage: HashMap::new(),
+                balance: U256::MAX,
+                nonce: 0,
+            },
+        );
+
+        let result = transition(
+            alloc,
+            &env,
+            Vec::new(),
+            Fork::Prague,
+            genesis.config.chain_id,
+            Some(U256::one()),
+        );
+        assert!(matches!(result, Err(T8nError::Config(_))));
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ::bytes::Bytes;
+use ethrex_common::{
+    Address, Bloom, H256, U256,
+    serde_utils::{bytes, u64, u256},
+    types::{
+        AuthorizationTuple, EIP1559Transaction, EIP2930Transaction, EIP4844Transaction,
+        EIP7702Transaction, GenesisAccount, LegacyTransaction, Transaction, TxKind, Withdrawal,
+        requests::EncodedRequests,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::T8nError;
+
+/// Pre state of the transition, keyed by address
+pub type Alloc = BTreeMap<Address, GenesisAccount>;
+
+/// Inputs given together through stdin when any of the input flags is `stdin`
+#[derive(Debug, Deserialize)]
+pub struct StdinInput {
+    pub alloc: Option<Alloc>,
+    pub env: Option<Env>,
+    pub txs: Option<Vec<InputTransaction>>,
+    /// Hex encoded RLP list of signed transactions, used instead of `txs`
+    #[serde(rename = "txsRlp")]
+    pub txs_rlp: Option<String>,
+}
+
+/// Block environment the transactions are executed in
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Env {
+    pub current_coinbase: Address,
+    #[serde(deserialize_with = "u64::deser_hex_or_dec_str")]
+    pub current_gas_limit: u64,
+    #[serde(deserialize_with = "u64::deser_hex_or_dec_str")]
+    pub current_number: u64,
+    #[serde(deserialize_with = "u64::deser_hex_or_dec_str")]
+    pub current_timestamp: u64,
+    #[serde(default, deserialize_with = "u256::deser_hex_str_opt")]
+    pub current_difficulty: Option<U256>,
+    pub current_random: Option<H256>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub current_base_fee: Option<u64>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub parent_base_fee: Option<u64>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub parent_gas_used: Option<u64>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub parent_gas_limit: Option<u64>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub current_excess_blob_gas: Option<u64>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub parent_excess_blob_gas: Option<u64>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub parent_blob_gas_used: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
+    /// Hashes of previous blocks, keyed by their number in decimal or hex
+    #[serde(default)]
+    pub block_hashes: HashMap<String, H256>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl Env {
+    pub fn block_hashes(&self) -> Result<HashMap<u64, H256>, T8nError> {
+        self.block_hashes
+            .iter()
+            .map(|(number, hash)| {
+                let parsed = match number.strip_prefix("0x") {
+                    Some(hex) => std::primitive::u64::from_str_radix(hex, 16),
+                    None => number.parse(),
+                };
+                parsed
+                    .map(|number| (number, *hash))
+                    .map_err(|_| T8nError::Config(format!("Invalid block number {number}")))
+            })
+            .collect()
+    }
+}
+
+/// Transaction as given in the `txs` input, which may be unsigned if `secretKey` is set
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputTransaction {
+    #[serde(rename = "type", default, with = "u64::hex_str_opt")]
+    pub tx_type: Option<u64>,
+    #[serde(default, with = "u64::hex_str_opt")]
+    pub chain_id: Option<u64>,
+    #[serde(deserialize_with = "u64::deser_hex_or_dec_str")]
+    pub nonce: u64,
+    #[serde(alias = "gasLimit", deserialize_with = "u64::deser_hex_or_dec_str")]
+    pub gas: u64,
+    #[serde(default, deserialize_with = "u256::deser_hex_str_opt")]
+    pub gas_price: Option<U256>,
+    #[serde(default, deserialize_with = "u256::deser_hex_str_opt")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    #[serde(default, deserialize_with = "u256::deser_hex_str_opt")]
+    pub max_fee_per_gas: Option<U256>,
+    #[serde(default, deserialize_with = "u256::deser_hex_str_opt")]
+    pub max_fee_per_blob_gas: Option<U256>,
+    /// `None` for contract creations
+    pub to: Option<Address>,
+    #[serde(deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub value: U256,
+    #[serde(alias = "data", with = "bytes")]
+    pub input: Bytes,
+    #[serde(default)]
+    pub access_list: Vec<AccessListEntry>,
+    #[serde(default)]
+    pub blob_versioned_hashes: Vec<H256>,
+    pub authorization_list: Option<Vec<AuthorizationEntry>>,
+    #[serde(default, deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub v: U256,
+    #[serde(default, deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub r: U256,
+    #[serde(default, deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub s: U256,
+    /// Key used to sign the transaction when it doesn't come with a signature
+    pub secret_key: Option<H256>,
+    /// Whether a legacy transaction signed with `secretKey` is bound to the chain id (EIP-155),
+    /// which geth's `evm t8n` does unless told otherwise
+    #[serde(default = "default_protected")]
+    pub protected: bool,
+}
+
+fn default_protected() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListEntry {
+    pub address: Address,
+    #[serde(default)]
+    pub storage_keys: Vec<H256>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationEntry {
+    #[serde(deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub chain_id: U256,
+    pub address: Address,
+    #[serde(deserialize_with = "u64::deser_hex_or_dec_str")]
+    pub nonce: u64,
+    #[serde(alias = "v", deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub y_parity: U256,
+    #[serde(deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub r: U256,
+    #[serde(deserialize_with = "u256::deser_hex_or_dec_str")]
+    pub s: U256,
+}
+
+impl InputTransaction {
+    pub fn is_signed(&self) -> bool {
+        !self.r.is_zero() || !self.s.is_zero()
+    }
+
+    /// Builds the transaction of the type given, or of the one implied by the fields present
+    pub fn to_transaction(&self, default_chain_id: u64) -> Result<Transaction, T8nError> {
+        let chain_id = self.chain_id.unwrap_or(default_chain_id);
+        let to = match self.to {
+            Some(address) => TxKind::Call(address),
+            None => TxKind::Create,
+        };
+        let access_list = self
+            .access_list
+            .iter()
+            .map(|entry| (entry.address, entry.storage_keys.clone()))
+            .collect();
+        let gas_price = fee(self.gas_price, "gas price")?;
+        let max_priority_fee_per_gas =
+            fee(self.max_priority_fee_per_gas, "max priority fee per gas")?;
+        let max_fee_per_gas = fee(self.max_fee_per_gas, "max fee per gas")?;
+        let signature_y_parity = !self.v.is_zero() && self.v != U256::from(27);
+        let call_address = || {
+            self.to.ok_or_else(|| {
+                T8nError::Config("Blob and set code transactions can't create contracts".into())
+            })
+        };
+
+        let tx_type = match self.tx_type {
+            Some(tx_type) => tx_type,
+            None if self.authorization_list.is_some() => 4,
+            None if self.max_fee_per_blob_gas.is_some() => 3,
+            None if self.max_fee_per_gas.is_some() => 2,
+            None if !self.access_list.is_empty() => 1,
+            None => 0,
+        };
+        let tx = match tx_type {
+            0 => Transaction::LegacyTransaction(LegacyTransaction {
+                nonce: self.nonce,
+                gas_price,
+                gas: self.gas,
+                to,
+                value: self.value,
+                data: self.input.clone(),
+                v: self.v,
+                r: self.r,
+                s: self.s,
+                ..Default::default()
+            }),
+            1 => Transaction::EIP2930Transaction(EIP2930Transaction {
+                chain_id,
+                nonce: self.nonce,
+                gas_price,
+                gas_limit: self.gas,
+                to,
+                value: self.value,
+                data: self.input.clone(),
+                access_list,
+                signature_y_parity,
+                signature_r: self.r,
+                signature_s: self.s,
+                ..Default::default()
+            }),
+            2 => Transaction::EIP1559Transaction(EIP1559Transaction {
+                chain_id,
+                nonce: self.nonce,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+                gas_limit: self.gas,
+                to,
+                value: self.value,
+                data: self.input.clone(),
+                access_list,
+                signature_y_parity,
+                signature_r: self.r,
+                signature_s: self.s,
+                ..Default::default()
+            }),
+            3 => Transaction::EIP4844Transaction(EIP4844Transaction {
+                chain_id,
+                nonce: self.nonce,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+                gas: self.gas,
+                to: call_address()?,
+                value: self.value,
+                data: self.input.clone(),
+                access_list,
+                max_fee_per_blob_gas: self.max_fee_per_blob_gas.unwrap_or_default(),
+                blob_versioned_hashes: self.blob_versioned_hashes.clone(),
+                signature_y_parity,
+                signature_r: self.r,
+                signature_s: self.s,
+                ..Default::default()
+            }),
+            4 => Transaction::EIP7702Transaction(EIP7702Transaction {
+                chain_id,
+                nonce: self.nonce,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+                gas_limit: self.gas,
+                to: call_address()?,
+                value: self.value,
+                data: self.input.clone(),
+                access_list,
+                authorization_list: self
+                    .authorization_list
+                    .iter()
+                    .flatten()
+                    .map(|entry| AuthorizationTuple {
+                        chain_id: entry.chain_id,
+                        address: entry.address,
+                        nonce: entry.nonce,
+                        y_parity: entry.y_parity,
+                        r_signature: entry.r,
+                        s_signature: entry.s,
+                    })
+                    .collect(),
+                signature_y_parity,
+                signature_r: self.r,
+                signature_s: self.s,
+                ..Default::default()
+            }),
+            other => {
+                return Err(T8nError::Config(format!(
+                    "Unsupported transaction type {other}"
+                )));
+            }
+        };
+        Ok(tx)
+    }
+}
+
+/// Fees are 256 bit values in the input, but executing them requires them to fit in 64 bits
+fn fee(value: Option<U256>, name: &str) -> Result<u64, T8nError> {
+    let value = value.unwrap_or_default();
+    if value > U256::from(std::primitive::u64::MAX) {
+        return Err(T8nError::InvalidTransaction(format!(
+            "{name} higher than 2^64-1: {value}"
+        )));
+    }
+    Ok(value.as_u64())
+}
+
+/// Result of the transition, written to `--output.result`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionResult {
+    pub state_root: H256,
+    pub tx_root: H256,
+    pub receipts_root: H256,
+    pub logs_hash: H256,
+    pub logs_bloom: Bloom,
+    pub receipts: Vec<ReceiptResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejected: Vec<RejectedTransaction>,
+    #[serde(with = "u64::hex_str")]
+    pub gas_used: u64,
+    #[serde(with = "u64::hex_str")]
+    pub current_base_fee: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withdrawals_root: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "u64::hex_str_opt")]
+    pub current_excess_blob_gas: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "u64::hex_str_opt")]
+    pub blob_gas_used: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests: Option<Vec<EncodedRequests>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptResult {
+    #[serde(rename = "type", with = "u64::hex_str")]
+    pub tx_type: u64,
+    #[serde(with = "bytes")]
+    pub root: Bytes,
+    #[serde(with = "u64::hex_str")]
+    pub status: u64,
+    #[serde(with = "u64::hex_str")]
+    pub cumulative_gas_used: u64,
+    pub logs_bloom: Bloom,
+    pub logs: Vec<LogResult>,
+    pub transaction_hash: H256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_address: Option<Address>,
+    #[serde(with = "u64::hex_str")]
+    pub gas_used: u64,
+    #[serde(with = "u64::hex_str")]
+    pub effective_gas_price: u64,
+    pub block_hash: H256,
+    #[serde(with = "u64::hex_str")]
+    pub transaction_index: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogResult {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    #[serde(with = "bytes")]
+    pub data: Bytes,
+    #[serde(with = "u64::hex_str")]
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    #[serde(with = "u64::hex_str")]
+    pub transaction_index: u64,
+    pub block_hash: H256,
+    #[serde(with = "u64::hex_str")]
+    pub log_index: u64,
+    pub removed: bool,
+}
+
+/// Transaction left out of the block, identified by its position in the input
+#[derive(Debug, Serialize)]
+pub struct RejectedTransaction {
+    pub index: usize,
+    pub error: String,
+}
+
+/// Account of the post state, written to `--output.alloc`
+#[derive(Debug, Serialize)]
+pub struct OutputAccount {
+    #[serde(skip_serializing_if = "Bytes::is_empty", with = "bytes")]
+    pub code: Bytes,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<H256, H256>,
+    pub balance: U256,
+    #[serde(with = "u64::hex_str")]
+    pub nonce: u64,
+}
+
+impl From<&GenesisAccount> for OutputAccount {
+    fn from(account: &GenesisAccount) -> Self {
+        Self {
+            code: account.code.clone(),
+            storage: account
+                .storage
+                .iter()
+                .map(|(key, value)| (H256(key.to_big_endian()), H256(value.to_big_endian())))
+                .collect(),
+            balance: account.balance,
+            nonce: account.nonce,
+        }
+    }
+}